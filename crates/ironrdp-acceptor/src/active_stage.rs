@@ -0,0 +1,166 @@
+use std::borrow::Cow;
+
+use ironrdp_connector::legacy::{decode_share_control, SendDataIndicationCtx};
+use ironrdp_pdu::fast_path::{EncryptionFlags, FastPathHeader, FastPathUpdate, FastPathUpdatePdu, Fragmentation};
+use ironrdp_pdu::input::fast_path::{FastPathInput, FastPathInputEvent};
+use ironrdp_pdu::input::InputEvent;
+use ironrdp_pdu::rdp::headers::{ShareControlPdu, ShareDataPdu};
+use ironrdp_pdu::{mcs, Action, PduBufferParsing, PduParsing};
+
+use crate::{AcceptorResult, Error, Result};
+
+/// Largest amount of update data carried by a single fast-path fragment
+const MAX_FRAGMENT_SIZE: usize = 0x3F80;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ActiveStageOutput {
+    FastPathInput(Vec<FastPathInputEvent>),
+    SlowPathInput(Vec<InputEvent>),
+    /// Virtual channel data received on a static channel, including the Channel PDU Header
+    ChannelData {
+        channel_id: u16,
+        data: Vec<u8>,
+    },
+    /// The client disconnected
+    Terminate,
+}
+
+/// Server side of the active session
+///
+/// Decodes the client input and encodes the graphics updates sent to the client.
+#[derive(Debug, Clone)]
+pub struct ActiveStage {
+    io_channel_id: u16,
+    user_channel_id: u16,
+    share_id: u32,
+}
+
+impl ActiveStage {
+    pub fn new(result: &AcceptorResult) -> Self {
+        Self {
+            io_channel_id: result.io_channel_id,
+            user_channel_id: result.user_channel_id,
+            share_id: result.share_id,
+        }
+    }
+
+    pub fn process(&mut self, action: Action, frame: &[u8]) -> Result<Vec<ActiveStageOutput>> {
+        match action {
+            Action::FastPath => {
+                let input = FastPathInput::from_buffer(frame)
+                    .map_err(|e| Error::new("invalid fast-path input").with_custom(e))?;
+
+                Ok(vec![ActiveStageOutput::FastPathInput(input.0)])
+            }
+            Action::X224 => self.process_x224(frame),
+        }
+    }
+
+    /// Encodes a fast-path update, fragmenting it as needed.
+    ///
+    /// The resulting frames are written one after the other at the start of `output`.
+    pub fn encode_update(&self, update: &FastPathUpdate<'_>, output: &mut Vec<u8>) -> Result<usize> {
+        let mut data = vec![0; update.buffer_length()];
+        update
+            .to_buffer_consume(&mut data.as_mut_slice())
+            .map_err(|e| Error::new("failed to encode fast-path update").with_custom(e))?;
+
+        let update_code = update.into();
+        let chunk_count = data.chunks(MAX_FRAGMENT_SIZE).count();
+        let mut frames = Vec::with_capacity(data.len() + chunk_count * 8);
+
+        // Updates without payload (e.g.: hidden pointer) are still sent as a single empty fragment
+        let chunks = if data.is_empty() {
+            vec![data.as_slice()]
+        } else {
+            data.chunks(MAX_FRAGMENT_SIZE).collect()
+        };
+
+        for (index, chunk) in chunks.iter().enumerate() {
+            let fragmentation = match (index, chunks.len()) {
+                (_, 1) => Fragmentation::Single,
+                (0, _) => Fragmentation::First,
+                (index, count) if index == count - 1 => Fragmentation::Last,
+                _ => Fragmentation::Next,
+            };
+
+            let pdu = FastPathUpdatePdu {
+                fragmentation,
+                update_code,
+                compression_flags: None,
+                compression_type: None,
+                data: chunk,
+            };
+
+            let header = FastPathHeader::new(EncryptionFlags::empty(), pdu.buffer_length());
+
+            let start = frames.len();
+            frames.resize(start + header.buffer_length() + pdu.buffer_length(), 0);
+
+            let mut buffer = &mut frames[start..];
+            header
+                .to_buffer(&mut buffer)
+                .map_err(|e| Error::new("failed to encode fast-path header").with_custom(e))?;
+            pdu.to_buffer_consume(&mut buffer)
+                .map_err(|e| Error::new("failed to encode fast-path update").with_custom(e))?;
+        }
+
+        if output.len() < frames.len() {
+            output.resize(frames.len(), 0);
+        }
+
+        output[..frames.len()].copy_from_slice(&frames);
+
+        Ok(frames.len())
+    }
+
+    fn process_x224(&mut self, frame: &[u8]) -> Result<Vec<ActiveStageOutput>> {
+        let request = match ironrdp_pdu::decode::<mcs::McsMessage<'_>>(frame)? {
+            mcs::McsMessage::SendDataRequest(request) => request,
+            mcs::McsMessage::DisconnectProviderUltimatum(ultimatum) => {
+                info!(reason = ?ultimatum.reason, "Client disconnected");
+                return Ok(vec![ActiveStageOutput::Terminate]);
+            }
+            unexpected => {
+                return Err(Error::new("unexpected MCS message").with_reason(ironrdp_pdu::name(&unexpected)));
+            }
+        };
+
+        if request.initiator_id != self.user_channel_id {
+            warn!(request.initiator_id, "Unexpected initiator ID");
+        }
+
+        if request.channel_id != self.io_channel_id {
+            return Ok(vec![ActiveStageOutput::ChannelData {
+                channel_id: request.channel_id,
+                data: request.user_data.into_owned(),
+            }]);
+        }
+
+        let Cow::Borrowed(user_data) = request.user_data else {
+            unreachable!()
+        };
+
+        let ctx = decode_share_control(SendDataIndicationCtx {
+            initiator_id: request.initiator_id,
+            channel_id: request.channel_id,
+            user_data,
+        })?;
+
+        if ctx.share_id != self.share_id {
+            warn!(ctx.share_id, "Unexpected share ID");
+        }
+
+        let ShareControlPdu::Data(share_data_header) = ctx.pdu else {
+            return Err(Error::new("unexpected Share Control PDU").with_reason(ctx.pdu.as_short_name()));
+        };
+
+        match share_data_header.share_data_pdu {
+            ShareDataPdu::Input(input) => Ok(vec![ActiveStageOutput::SlowPathInput(input.0)]),
+            unexpected => {
+                debug!(pdu = unexpected.as_short_name(), "Ignored Share Data PDU");
+                Ok(Vec::new())
+            }
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use core::mem;
+
+use ironrdp_pdu::rdp::capability_sets;
+
+use crate::Config;
+
+/// Largest fast-path update the client is asked to reassemble
+const MAX_MULTIFRAGMENT_REQUEST_SIZE: u32 = 8 * 1024 * 1024;
+
+pub fn create_server_demand_active(config: &Config) -> capability_sets::ServerDemandActive {
+    use ironrdp_pdu::rdp::capability_sets::*;
+
+    let mut capability_sets = vec![
+        CapabilitySet::General(General {
+            major_platform_type: MajorPlatformType::Unspecified,
+            minor_platform_type: MinorPlatformType::Unspecified,
+            extra_flags: GeneralExtraFlags::FASTPATH_OUTPUT_SUPPORTED | GeneralExtraFlags::NO_BITMAP_COMPRESSION_HDR,
+            refresh_rect_support: false,
+            suppress_output_support: false,
+        }),
+        CapabilitySet::Bitmap(Bitmap {
+            pref_bits_per_pix: 32,
+            desktop_width: config.desktop_size.width,
+            desktop_height: config.desktop_size.height,
+            desktop_resize_flag: false,
+            drawing_flags: BitmapDrawingFlags::ALLOW_SKIP_ALPHA,
+        }),
+        CapabilitySet::Order(Order::new(
+            OrderFlags::NEGOTIATE_ORDER_SUPPORT | OrderFlags::ZERO_BOUNDS_DELTAS_SUPPORT,
+            OrderSupportExFlags::empty(),
+            0,
+            0,
+        )),
+        CapabilitySet::Pointer(Pointer {
+            color_pointer_cache_size: 32,
+            pointer_cache_size: 32,
+        }),
+        CapabilitySet::Input(Input {
+            input_flags: InputFlags::SCANCODES
+                | InputFlags::MOUSEX
+                | InputFlags::FASTPATH_INPUT
+                | InputFlags::UNICODE
+                | InputFlags::FASTPATH_INPUT_2,
+            keyboard_layout: 0,
+            keyboard_type: None,
+            keyboard_subtype: 0,
+            keyboard_function_key: 0,
+            keyboard_ime_filename: String::new(),
+        }),
+        CapabilitySet::VirtualChannel(VirtualChannel {
+            flags: VirtualChannelFlags::NO_COMPRESSION,
+            chunk_size: None,
+        }),
+        CapabilitySet::MultiFragmentUpdate(MultifragmentUpdate {
+            max_request_size: MAX_MULTIFRAGMENT_REQUEST_SIZE,
+        }),
+    ];
+
+    for capability_set in &config.capabilities {
+        match capability_sets
+            .iter_mut()
+            .find(|default| mem::discriminant(*default) == mem::discriminant(capability_set))
+        {
+            Some(default) => *default = capability_set.clone(),
+            None => capability_sets.push(capability_set.clone()),
+        }
+    }
+
+    ServerDemandActive {
+        pdu: DemandActive {
+            source_descriptor: "RDP".to_owned(),
+            capability_sets,
+        },
+    }
+}
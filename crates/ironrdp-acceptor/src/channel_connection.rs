@@ -0,0 +1,173 @@
+use std::mem;
+
+use ironrdp_pdu::{mcs, PduHint};
+
+use crate::{Error, Result, Sequence, State, Written};
+
+#[derive(Default, Debug)]
+#[non_exhaustive]
+pub enum ChannelConnectionState {
+    #[default]
+    Consumed,
+
+    WaitErectDomainRequest,
+    WaitAttachUserRequest,
+    SendAttachUserConfirm,
+    WaitChannelJoinRequest {
+        /// Channels the client did not join yet
+        remaining: Vec<u16>,
+    },
+    AllJoined,
+}
+
+impl State for ChannelConnectionState {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Consumed => "Consumed",
+            Self::WaitErectDomainRequest => "WaitErectDomainRequest",
+            Self::WaitAttachUserRequest => "WaitAttachUserRequest",
+            Self::SendAttachUserConfirm => "SendAttachUserConfirm",
+            Self::WaitChannelJoinRequest { .. } => "WaitChannelJoinRequest",
+            Self::AllJoined => "AllJoined",
+        }
+    }
+
+    fn is_terminal(&self) -> bool {
+        matches!(self, Self::AllJoined)
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct ChannelConnectionSequence {
+    pub state: ChannelConnectionState,
+    pub user_channel_id: u16,
+    pub channel_ids: Vec<u16>,
+}
+
+impl ChannelConnectionSequence {
+    pub fn new(user_channel_id: u16, io_channel_id: u16, mut channel_ids: Vec<u16>) -> Self {
+        // The client joins the user channel and the I/O channel as well
+        channel_ids.push(user_channel_id);
+        channel_ids.push(io_channel_id);
+
+        Self {
+            state: ChannelConnectionState::WaitErectDomainRequest,
+            user_channel_id,
+            channel_ids,
+        }
+    }
+}
+
+impl Sequence for ChannelConnectionSequence {
+    fn next_pdu_hint(&self) -> Option<&dyn PduHint> {
+        match self.state {
+            ChannelConnectionState::Consumed => None,
+            ChannelConnectionState::WaitErectDomainRequest => Some(&ironrdp_pdu::X224_HINT),
+            ChannelConnectionState::WaitAttachUserRequest => Some(&ironrdp_pdu::X224_HINT),
+            ChannelConnectionState::SendAttachUserConfirm => None,
+            ChannelConnectionState::WaitChannelJoinRequest { .. } => Some(&ironrdp_pdu::X224_HINT),
+            ChannelConnectionState::AllJoined => None,
+        }
+    }
+
+    fn step(&mut self, input: &[u8], output: &mut Vec<u8>) -> Result<Written> {
+        let (written, next_state) = match mem::take(&mut self.state) {
+            ChannelConnectionState::Consumed => {
+                return Err(Error::new(
+                    "channel connection sequence state is consumed (this is a bug)",
+                ))
+            }
+
+            ChannelConnectionState::WaitErectDomainRequest => {
+                let erect_domain_request = ironrdp_pdu::decode::<mcs::ErectDomainPdu>(input)?;
+
+                debug!(message = ?erect_domain_request, "Received");
+
+                (Written::Nothing, ChannelConnectionState::WaitAttachUserRequest)
+            }
+
+            ChannelConnectionState::WaitAttachUserRequest => {
+                let attach_user_request = ironrdp_pdu::decode::<mcs::AttachUserRequest>(input)?;
+
+                debug!(message = ?attach_user_request, "Received");
+
+                (Written::Nothing, ChannelConnectionState::SendAttachUserConfirm)
+            }
+
+            ChannelConnectionState::SendAttachUserConfirm => {
+                let attach_user_confirm = mcs::AttachUserConfirm {
+                    result: 0,
+                    initiator_id: self.user_channel_id,
+                };
+
+                debug!(message = ?attach_user_confirm, "Send");
+
+                let written = ironrdp_pdu::encode_buf(&attach_user_confirm, output)?;
+
+                (
+                    Written::from_size(written)?,
+                    ChannelConnectionState::WaitChannelJoinRequest {
+                        remaining: self.channel_ids.clone(),
+                    },
+                )
+            }
+
+            // Depending on their version, clients either wait for each Channel Join Confirm before sending
+            // the next request, or send all the requests in a single batch. Both are handled the same way
+            // since each request is answered as soon as it is received.
+            ChannelConnectionState::WaitChannelJoinRequest { mut remaining } => {
+                let channel_join_request = ironrdp_pdu::decode::<mcs::ChannelJoinRequest>(input)?;
+
+                debug!(message = ?channel_join_request, "Received");
+
+                if channel_join_request.initiator_id != self.user_channel_id {
+                    return Err(Error::new("received bad MCS Channel Join Request")
+                        .with_reason(format!("unexpected initiator ID {}", channel_join_request.initiator_id)));
+                }
+
+                let index = remaining
+                    .iter()
+                    .position(|channel_id| *channel_id == channel_join_request.channel_id)
+                    .ok_or_else(|| {
+                        Error::new("received bad MCS Channel Join Request")
+                            .with_reason(format!("unexpected channel ID {}", channel_join_request.channel_id))
+                    })?;
+
+                remaining.swap_remove(index);
+
+                let channel_join_confirm = mcs::ChannelJoinConfirm {
+                    result: 0,
+                    initiator_id: self.user_channel_id,
+                    requested_channel_id: channel_join_request.channel_id,
+                    channel_id: channel_join_request.channel_id,
+                };
+
+                debug!(message = ?channel_join_confirm, "Send");
+
+                let written = ironrdp_pdu::encode_buf(&channel_join_confirm, output)?;
+
+                let next_state = if remaining.is_empty() {
+                    ChannelConnectionState::AllJoined
+                } else {
+                    ChannelConnectionState::WaitChannelJoinRequest { remaining }
+                };
+
+                (Written::from_size(written)?, next_state)
+            }
+
+            ChannelConnectionState::AllJoined => return Err(Error::new("all channels are already joined")),
+        };
+
+        self.state = next_state;
+
+        Ok(written)
+    }
+
+    fn state(&self) -> &dyn State {
+        &self.state
+    }
+}
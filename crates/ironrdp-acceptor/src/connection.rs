@@ -0,0 +1,780 @@
+use core::fmt;
+use std::{io, mem};
+
+use ironrdp_connector::legacy::decode_share_control;
+use ironrdp_connector::CREDSSP_TS_REQUEST_HINT;
+use ironrdp_pdu::rdp::capability_sets::{CapabilitySet, SERVER_CHANNEL_ID};
+use ironrdp_pdu::rdp::headers::ShareControlPdu;
+use ironrdp_pdu::rdp::server_license;
+use ironrdp_pdu::{gcc, mcs, nego, rdp, PduHint};
+use sspi::credssp;
+
+use crate::channel_connection::ChannelConnectionSequence;
+use crate::finalization::FinalizationSequence;
+use crate::{
+    capabilities, legacy, Config, Credentials, DesktopSize, Error, ErrorKind, Result, Sequence, State, StaticChannels,
+    Written,
+};
+
+const IO_CHANNEL_ID: u16 = 1003;
+
+/// Identifier of the share created by the server, echoed by the client in every Share Control PDU
+const SHARE_ID: u32 = 0x0001_03ea;
+
+#[derive(Debug, Clone)]
+pub struct AcceptorResult {
+    pub io_channel_id: u16,
+    pub user_channel_id: u16,
+    pub share_id: u32,
+    pub static_channels: StaticChannels,
+    pub desktop_size: DesktopSize,
+    /// Capability sets advertised by the client in the Client Confirm Active PDU
+    pub client_capabilities: Vec<CapabilitySet>,
+    /// Credentials found in the Client Info PDU
+    ///
+    /// When CredSSP is used, the client usually leaves the password empty.
+    pub credentials: Credentials,
+}
+
+#[derive(Default, Debug)]
+#[non_exhaustive]
+pub enum AcceptorState {
+    #[default]
+    Consumed,
+
+    InitiationWaitRequest,
+    InitiationSendConfirm {
+        requested_protocol: nego::SecurityProtocol,
+        selected_protocol: nego::SecurityProtocol,
+    },
+    /// User code should match this variant and perform the TLS handshake, then call
+    /// [`Acceptor::attach_server_public_key`] and [`Acceptor::mark_security_upgrade_as_done`].
+    SecurityUpgrade {
+        requested_protocol: nego::SecurityProtocol,
+        selected_protocol: nego::SecurityProtocol,
+    },
+    CredsspInitial {
+        requested_protocol: nego::SecurityProtocol,
+        selected_protocol: nego::SecurityProtocol,
+    },
+    CredsspWaitRequest {
+        requested_protocol: nego::SecurityProtocol,
+        selected_protocol: nego::SecurityProtocol,
+        credssp_server: Box<CredsspServer>,
+    },
+    CredsspEarlyUserAuthResult {
+        requested_protocol: nego::SecurityProtocol,
+        selected_protocol: nego::SecurityProtocol,
+    },
+    BasicSettingsWaitInitial {
+        requested_protocol: nego::SecurityProtocol,
+        selected_protocol: nego::SecurityProtocol,
+    },
+    BasicSettingsSendResponse {
+        requested_protocol: nego::SecurityProtocol,
+        selected_protocol: nego::SecurityProtocol,
+        /// Static channels requested by the client, in the order of the Client Network Data
+        static_channels: Vec<(String, u16)>,
+    },
+    ChannelConnection {
+        selected_protocol: nego::SecurityProtocol,
+        user_channel_id: u16,
+        static_channels: StaticChannels,
+        channel_connection: ChannelConnectionSequence,
+    },
+    RdpSecurityCommencement {
+        selected_protocol: nego::SecurityProtocol,
+        user_channel_id: u16,
+        static_channels: StaticChannels,
+    },
+    SecureSettingsExchange {
+        selected_protocol: nego::SecurityProtocol,
+        user_channel_id: u16,
+        static_channels: StaticChannels,
+    },
+    LicensingExchange {
+        user_channel_id: u16,
+        static_channels: StaticChannels,
+        credentials: Credentials,
+    },
+    CapabilitiesSendServer {
+        user_channel_id: u16,
+        static_channels: StaticChannels,
+        credentials: Credentials,
+    },
+    CapabilitiesWaitConfirm {
+        user_channel_id: u16,
+        static_channels: StaticChannels,
+        credentials: Credentials,
+    },
+    ConnectionFinalization {
+        user_channel_id: u16,
+        static_channels: StaticChannels,
+        credentials: Credentials,
+        client_capabilities: Vec<CapabilitySet>,
+        finalization: FinalizationSequence,
+    },
+    Accepted {
+        result: AcceptorResult,
+    },
+}
+
+impl State for AcceptorState {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Consumed => "Consumed",
+            Self::InitiationWaitRequest => "InitiationWaitRequest",
+            Self::InitiationSendConfirm { .. } => "InitiationSendConfirm",
+            Self::SecurityUpgrade { .. } => "SecurityUpgrade",
+            Self::CredsspInitial { .. } => "CredsspInitial",
+            Self::CredsspWaitRequest { .. } => "CredsspWaitRequest",
+            Self::CredsspEarlyUserAuthResult { .. } => "CredsspEarlyUserAuthResult",
+            Self::BasicSettingsWaitInitial { .. } => "BasicSettingsWaitInitial",
+            Self::BasicSettingsSendResponse { .. } => "BasicSettingsSendResponse",
+            Self::ChannelConnection { .. } => "ChannelConnection",
+            Self::RdpSecurityCommencement { .. } => "RdpSecurityCommencement",
+            Self::SecureSettingsExchange { .. } => "SecureSettingsExchange",
+            Self::LicensingExchange { .. } => "LicensingExchange",
+            Self::CapabilitiesSendServer { .. } => "CapabilitiesSendServer",
+            Self::CapabilitiesWaitConfirm { .. } => "CapabilitiesWaitConfirm",
+            Self::ConnectionFinalization { .. } => "ConnectionFinalization",
+            Self::Accepted { .. } => "Accepted",
+        }
+    }
+
+    fn is_terminal(&self) -> bool {
+        matches!(self, Self::Accepted { .. })
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+}
+
+/// CredSSP server authenticating the client against the configured credentials
+pub struct CredsspServer(credssp::CredSspServer<CredentialsProxy>);
+
+impl fmt::Debug for CredsspServer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CredsspServer").finish_non_exhaustive()
+    }
+}
+
+struct CredentialsProxy {
+    credentials: Credentials,
+}
+
+impl credssp::CredentialsProxy for CredentialsProxy {
+    type AuthenticationData = sspi::AuthIdentity;
+
+    fn auth_data_by_user(&mut self, username: String, domain: Option<String>) -> io::Result<Self::AuthenticationData> {
+        if !username.eq_ignore_ascii_case(&self.credentials.username) {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "unknown user"));
+        }
+
+        Ok(sspi::AuthIdentity {
+            username,
+            password: self.credentials.password.clone().into(),
+            domain,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct Acceptor {
+    pub config: Config,
+    pub state: AcceptorState,
+    pub server_public_key: Option<Vec<u8>>,
+}
+
+impl Acceptor {
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            state: AcceptorState::InitiationWaitRequest,
+            server_public_key: None,
+        }
+    }
+
+    /// Must be set to the public key of the TLS certificate before the CredSSP exchange
+    pub fn attach_server_public_key(&mut self, server_public_key: Vec<u8>) {
+        self.server_public_key = Some(server_public_key);
+    }
+
+    pub fn should_perform_security_upgrade(&self) -> bool {
+        matches!(self.state, AcceptorState::SecurityUpgrade { .. })
+    }
+
+    pub fn mark_security_upgrade_as_done(&mut self) {
+        assert!(self.should_perform_security_upgrade());
+        self.step(&[], &mut Vec::new()).expect("transition to next state");
+        debug_assert!(!self.should_perform_security_upgrade());
+    }
+
+    pub fn is_credssp_step(&self) -> bool {
+        matches!(
+            &self.state,
+            AcceptorState::CredsspInitial { .. }
+                | AcceptorState::CredsspWaitRequest { .. }
+                | AcceptorState::CredsspEarlyUserAuthResult { .. }
+        )
+    }
+
+    fn select_security_protocol(&self, requested_protocol: nego::SecurityProtocol) -> Option<nego::SecurityProtocol> {
+        let supported = self.config.security_protocol & requested_protocol;
+
+        if self.config.credentials.is_some() {
+            if supported.contains(nego::SecurityProtocol::HYBRID_EX) {
+                return Some(nego::SecurityProtocol::HYBRID_EX);
+            }
+
+            if supported.contains(nego::SecurityProtocol::HYBRID) {
+                return Some(nego::SecurityProtocol::HYBRID);
+            }
+        }
+
+        if supported.contains(nego::SecurityProtocol::SSL) {
+            return Some(nego::SecurityProtocol::SSL);
+        }
+
+        None
+    }
+}
+
+impl Sequence for Acceptor {
+    fn next_pdu_hint(&self) -> Option<&dyn PduHint> {
+        match &self.state {
+            AcceptorState::Consumed => None,
+            AcceptorState::InitiationWaitRequest => Some(&ironrdp_pdu::X224_HINT),
+            AcceptorState::InitiationSendConfirm { .. } => None,
+            AcceptorState::SecurityUpgrade { .. } => None,
+            AcceptorState::CredsspInitial { .. } => None,
+            AcceptorState::CredsspWaitRequest { .. } => Some(&CREDSSP_TS_REQUEST_HINT),
+            AcceptorState::CredsspEarlyUserAuthResult { .. } => None,
+            AcceptorState::BasicSettingsWaitInitial { .. } => Some(&ironrdp_pdu::X224_HINT),
+            AcceptorState::BasicSettingsSendResponse { .. } => None,
+            AcceptorState::ChannelConnection { channel_connection, .. } => channel_connection.next_pdu_hint(),
+            AcceptorState::RdpSecurityCommencement { .. } => None,
+            AcceptorState::SecureSettingsExchange { .. } => Some(&ironrdp_pdu::X224_HINT),
+            AcceptorState::LicensingExchange { .. } => None,
+            AcceptorState::CapabilitiesSendServer { .. } => None,
+            AcceptorState::CapabilitiesWaitConfirm { .. } => Some(&ironrdp_pdu::X224_HINT),
+            AcceptorState::ConnectionFinalization { finalization, .. } => finalization.next_pdu_hint(),
+            AcceptorState::Accepted { .. } => None,
+        }
+    }
+
+    fn state(&self) -> &dyn State {
+        &self.state
+    }
+
+    fn step(&mut self, input: &[u8], output: &mut Vec<u8>) -> Result<Written> {
+        let (written, next_state) = match mem::take(&mut self.state) {
+            // Invalid state
+            AcceptorState::Consumed => return Err(Error::new("acceptor sequence state is consumed (this is a bug)")),
+
+            //== Connection Initiation ==//
+            // Select a security protocol among the ones requested by the client.
+            AcceptorState::InitiationWaitRequest => {
+                let connection_request = ironrdp_pdu::decode::<nego::ConnectionRequest>(input)?;
+
+                debug!(message = ?connection_request, "Received");
+
+                let requested_protocol = connection_request.protocol;
+
+                let selected_protocol = self.select_security_protocol(requested_protocol).ok_or_else(|| {
+                    Error::new("no common security protocol with the client")
+                        .with_reason(format!("client requested {requested_protocol:?}"))
+                })?;
+
+                info!(?requested_protocol, ?selected_protocol, "Security protocol selected");
+
+                (
+                    Written::Nothing,
+                    AcceptorState::InitiationSendConfirm {
+                        requested_protocol,
+                        selected_protocol,
+                    },
+                )
+            }
+            AcceptorState::InitiationSendConfirm {
+                requested_protocol,
+                selected_protocol,
+            } => {
+                let connection_confirm = nego::ConnectionConfirm::Response {
+                    flags: nego::ResponseFlags::EXTENDED_CLIENT_DATA_SUPPORTED,
+                    protocol: selected_protocol,
+                };
+
+                debug!(message = ?connection_confirm, "Send");
+
+                let written = ironrdp_pdu::encode_buf(&connection_confirm, output)?;
+
+                (
+                    Written::from_size(written)?,
+                    AcceptorState::SecurityUpgrade {
+                        requested_protocol,
+                        selected_protocol,
+                    },
+                )
+            }
+
+            //== Upgrade to Enhanced RDP Security ==//
+            // User code performs the TLS handshake before moving forward.
+            AcceptorState::SecurityUpgrade {
+                requested_protocol,
+                selected_protocol,
+            } => {
+                let next_state = if selected_protocol
+                    .intersects(nego::SecurityProtocol::HYBRID | nego::SecurityProtocol::HYBRID_EX)
+                {
+                    AcceptorState::CredsspInitial {
+                        requested_protocol,
+                        selected_protocol,
+                    }
+                } else {
+                    AcceptorState::BasicSettingsWaitInitial {
+                        requested_protocol,
+                        selected_protocol,
+                    }
+                };
+
+                (Written::Nothing, next_state)
+            }
+
+            //== CredSSP ==//
+            AcceptorState::CredsspInitial {
+                requested_protocol,
+                selected_protocol,
+            } => {
+                let credentials = self
+                    .config
+                    .credentials
+                    .clone()
+                    .ok_or(Error::new("credentials are missing"))?;
+
+                let server_public_key = self
+                    .server_public_key
+                    .take()
+                    .ok_or(Error::new("server public key is missing"))?;
+
+                let credssp_server = credssp::CredSspServer::new(
+                    server_public_key,
+                    CredentialsProxy { credentials },
+                    credssp::ClientMode::Ntlm(sspi::ntlm::NtlmConfig::default()),
+                )?;
+
+                (
+                    Written::Nothing,
+                    AcceptorState::CredsspWaitRequest {
+                        requested_protocol,
+                        selected_protocol,
+                        credssp_server: Box::new(CredsspServer(credssp_server)),
+                    },
+                )
+            }
+            AcceptorState::CredsspWaitRequest {
+                requested_protocol,
+                selected_protocol,
+                mut credssp_server,
+            } => {
+                let ts_request_from_client = credssp::TsRequest::from_buffer(input)
+                    .map_err(|e| Error::new("CredSSP").with_reason(format!("TsRequest decode: {e}")))?;
+
+                let result = credssp_server.0.process(ts_request_from_client).map_err(|e| {
+                    Error::new("CredSSP")
+                        .with_kind(ErrorKind::AccessDenied)
+                        .with_custom(e.error)
+                })?;
+
+                match result {
+                    credssp::ServerState::ReplyNeeded(ts_request_from_server) => {
+                        debug!(message = ?ts_request_from_server, "Send");
+
+                        let written = write_credssp_request(ts_request_from_server, output)?;
+
+                        (
+                            Written::from_size(written)?,
+                            AcceptorState::CredsspWaitRequest {
+                                requested_protocol,
+                                selected_protocol,
+                                credssp_server,
+                            },
+                        )
+                    }
+                    credssp::ServerState::Finished(identity) => {
+                        info!(username = %identity.username, "Client authenticated with CredSSP");
+
+                        let next_state = if selected_protocol.contains(nego::SecurityProtocol::HYBRID_EX) {
+                            AcceptorState::CredsspEarlyUserAuthResult {
+                                requested_protocol,
+                                selected_protocol,
+                            }
+                        } else {
+                            AcceptorState::BasicSettingsWaitInitial {
+                                requested_protocol,
+                                selected_protocol,
+                            }
+                        };
+
+                        (Written::Nothing, next_state)
+                    }
+                }
+            }
+            AcceptorState::CredsspEarlyUserAuthResult {
+                requested_protocol,
+                selected_protocol,
+            } => {
+                // Failures are reported by aborting the CredSSP exchange, so the result is always a success here
+                output.resize(EARLY_USER_AUTH_RESULT_SUCCESS.len(), 0);
+                output.copy_from_slice(&EARLY_USER_AUTH_RESULT_SUCCESS);
+
+                (
+                    Written::from_size(EARLY_USER_AUTH_RESULT_SUCCESS.len())?,
+                    AcceptorState::BasicSettingsWaitInitial {
+                        requested_protocol,
+                        selected_protocol,
+                    },
+                )
+            }
+
+            //== Basic Settings Exchange ==//
+            // Exchange basic settings including Core Data, Security Data and Network Data.
+            AcceptorState::BasicSettingsWaitInitial {
+                requested_protocol,
+                selected_protocol,
+            } => {
+                let connect_initial = legacy::decode_x224_packet::<mcs::ConnectInitial>(input)?;
+
+                debug!(message = ?connect_initial, "Received");
+
+                let client_selected_protocol = connect_initial
+                    .conference_create_request
+                    .gcc_blocks
+                    .core
+                    .optional_data
+                    .server_selected_protocol;
+
+                if matches!(client_selected_protocol, Some(protocol) if protocol != selected_protocol) {
+                    warn!(
+                        ?client_selected_protocol,
+                        ?selected_protocol,
+                        "Unexpected client selected protocol"
+                    );
+                }
+
+                let static_channels = connect_initial
+                    .channel_names()
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|channel| channel.name)
+                    .zip(IO_CHANNEL_ID + 1..)
+                    .collect::<Vec<_>>();
+
+                debug!(?static_channels);
+
+                (
+                    Written::Nothing,
+                    AcceptorState::BasicSettingsSendResponse {
+                        requested_protocol,
+                        selected_protocol,
+                        static_channels,
+                    },
+                )
+            }
+            AcceptorState::BasicSettingsSendResponse {
+                requested_protocol,
+                selected_protocol,
+                static_channels,
+            } => {
+                let channel_ids = static_channels
+                    .iter()
+                    .map(|(_, channel_id)| *channel_id)
+                    .collect::<Vec<_>>();
+
+                // The user channel comes right after the static channels
+                let user_channel_id = IO_CHANNEL_ID
+                    + 1
+                    + u16::try_from(channel_ids.len())
+                        .map_err(|_| Error::new("too many static channels requested by the client"))?;
+
+                let server_gcc_blocks = create_gcc_blocks(requested_protocol, channel_ids.clone());
+
+                let connect_response = mcs::ConnectResponse {
+                    conference_create_response: gcc::ConferenceCreateResponse {
+                        user_id: user_channel_id,
+                        gcc_blocks: server_gcc_blocks,
+                    },
+                    called_connect_id: 0,
+                    domain_parameters: mcs::DomainParameters::target(),
+                };
+
+                debug!(message = ?connect_response, "Send");
+
+                let written = legacy::encode_x224_packet(&connect_response, output)?;
+
+                (
+                    Written::from_size(written)?,
+                    AcceptorState::ChannelConnection {
+                        selected_protocol,
+                        user_channel_id,
+                        static_channels: static_channels.into_iter().collect(),
+                        channel_connection: ChannelConnectionSequence::new(user_channel_id, IO_CHANNEL_ID, channel_ids),
+                    },
+                )
+            }
+
+            //== Channel Connection ==//
+            // Answer the MCS requests until every channel is joined.
+            AcceptorState::ChannelConnection {
+                selected_protocol,
+                user_channel_id,
+                static_channels,
+                mut channel_connection,
+            } => {
+                let written = channel_connection.step(input, output)?;
+
+                let next_state = if channel_connection.state.is_terminal() {
+                    AcceptorState::RdpSecurityCommencement {
+                        selected_protocol,
+                        user_channel_id,
+                        static_channels,
+                    }
+                } else {
+                    AcceptorState::ChannelConnection {
+                        selected_protocol,
+                        user_channel_id,
+                        static_channels,
+                        channel_connection,
+                    }
+                };
+
+                (written, next_state)
+            }
+
+            //== RDP Security Commencement ==//
+            // NOTE: standard RDP security (RC4) is never selected, so no Security Exchange PDU is expected.
+            AcceptorState::RdpSecurityCommencement {
+                selected_protocol,
+                user_channel_id,
+                static_channels,
+            } => (
+                Written::Nothing,
+                AcceptorState::SecureSettingsExchange {
+                    selected_protocol,
+                    user_channel_id,
+                    static_channels,
+                },
+            ),
+
+            //== Secure Settings Exchange ==//
+            // Receive the Client Info PDU, which carries the credentials when CredSSP is not used.
+            AcceptorState::SecureSettingsExchange {
+                selected_protocol,
+                user_channel_id,
+                static_channels,
+            } => {
+                let ctx = legacy::decode_send_data_request(input)?;
+                let client_info = ctx.decode_user_data::<rdp::ClientInfoPdu>()?;
+
+                debug!(message = ?client_info, "Received");
+
+                let client_credentials = client_info.client_info.credentials;
+
+                let credssp_performed =
+                    selected_protocol.intersects(nego::SecurityProtocol::HYBRID | nego::SecurityProtocol::HYBRID_EX);
+
+                if let Some(expected) = self.config.credentials.as_ref().filter(|_| !credssp_performed) {
+                    let is_valid = client_credentials.username.eq_ignore_ascii_case(&expected.username)
+                        && client_credentials.password == expected.password
+                        && match (&expected.domain, &client_credentials.domain) {
+                            (Some(expected), Some(domain)) => domain.eq_ignore_ascii_case(expected),
+                            (Some(_), None) => false,
+                            (None, _) => true,
+                        };
+
+                    if !is_valid {
+                        return Err(
+                            Error::new("invalid credentials in Client Info PDU").with_kind(ErrorKind::AccessDenied)
+                        );
+                    }
+                }
+
+                (
+                    Written::Nothing,
+                    AcceptorState::LicensingExchange {
+                        user_channel_id,
+                        static_channels,
+                        credentials: Credentials {
+                            username: client_credentials.username,
+                            password: client_credentials.password,
+                            domain: client_credentials.domain,
+                        },
+                    },
+                )
+            }
+
+            //== Licensing ==//
+            // No license is required: a valid client status is sent right away.
+            AcceptorState::LicensingExchange {
+                user_channel_id,
+                static_channels,
+                credentials,
+            } => {
+                let license_message = server_license::InitialServerLicenseMessage::new_status_valid_client_message();
+
+                debug!(message = ?license_message, "Send");
+
+                let written =
+                    legacy::encode_send_data_indication(SERVER_CHANNEL_ID, IO_CHANNEL_ID, &license_message, output)?;
+
+                (
+                    Written::from_size(written)?,
+                    AcceptorState::CapabilitiesSendServer {
+                        user_channel_id,
+                        static_channels,
+                        credentials,
+                    },
+                )
+            }
+
+            //== Capabilities Exchange ==/
+            // The server advertises its capabilities, and the client answers with its own.
+            AcceptorState::CapabilitiesSendServer {
+                user_channel_id,
+                static_channels,
+                credentials,
+            } => {
+                let demand_active = capabilities::create_server_demand_active(&self.config);
+
+                debug!(message = ?demand_active, "Send");
+
+                let written = legacy::encode_share_control(
+                    IO_CHANNEL_ID,
+                    SHARE_ID,
+                    ShareControlPdu::ServerDemandActive(demand_active),
+                    output,
+                )?;
+
+                (
+                    Written::from_size(written)?,
+                    AcceptorState::CapabilitiesWaitConfirm {
+                        user_channel_id,
+                        static_channels,
+                        credentials,
+                    },
+                )
+            }
+            AcceptorState::CapabilitiesWaitConfirm {
+                user_channel_id,
+                static_channels,
+                credentials,
+            } => {
+                let ctx = legacy::decode_send_data_request(input)?;
+                let ctx = decode_share_control(ctx)?;
+
+                let ShareControlPdu::ClientConfirmActive(confirm_active) = ctx.pdu else {
+                    return Err(
+                        Error::new("unexpected Share Control PDU (expected Client Confirm Active)")
+                            .with_reason(ctx.pdu.as_short_name()),
+                    );
+                };
+
+                debug!(message = ?confirm_active, "Received");
+
+                (
+                    Written::Nothing,
+                    AcceptorState::ConnectionFinalization {
+                        user_channel_id,
+                        static_channels,
+                        credentials,
+                        client_capabilities: confirm_active.pdu.capability_sets,
+                        finalization: FinalizationSequence::new(IO_CHANNEL_ID, user_channel_id, SHARE_ID),
+                    },
+                )
+            }
+
+            //== Connection Finalization ==//
+            // Client and server exchange a few PDUs in order to finalize the connection.
+            AcceptorState::ConnectionFinalization {
+                user_channel_id,
+                static_channels,
+                credentials,
+                client_capabilities,
+                mut finalization,
+            } => {
+                let written = finalization.step(input, output)?;
+
+                let next_state = if finalization.state.is_terminal() {
+                    AcceptorState::Accepted {
+                        result: AcceptorResult {
+                            io_channel_id: IO_CHANNEL_ID,
+                            user_channel_id,
+                            share_id: SHARE_ID,
+                            static_channels,
+                            desktop_size: self.config.desktop_size.clone(),
+                            client_capabilities,
+                            credentials,
+                        },
+                    }
+                } else {
+                    AcceptorState::ConnectionFinalization {
+                        user_channel_id,
+                        static_channels,
+                        credentials,
+                        client_capabilities,
+                        finalization,
+                    }
+                };
+
+                (written, next_state)
+            }
+
+            //== Accepted ==//
+            // The acceptor job is done.
+            AcceptorState::Accepted { .. } => return Err(Error::new("connection already accepted")),
+        };
+
+        self.state = next_state;
+
+        Ok(written)
+    }
+}
+
+/// Early User Authorization Result PDU with the `SUCCESS` result code (u32, little-endian)
+const EARLY_USER_AUTH_RESULT_SUCCESS: [u8; 4] = [0x00, 0x00, 0x00, 0x00];
+
+fn create_gcc_blocks(requested_protocol: nego::SecurityProtocol, channel_ids: Vec<u16>) -> gcc::ServerGccBlocks {
+    use ironrdp_pdu::gcc::*;
+
+    ServerGccBlocks {
+        core: ServerCoreData {
+            version: RdpVersion::V5_PLUS,
+            optional_data: ServerCoreOptionalData {
+                client_requested_protocols: Some(requested_protocol),
+                early_capability_flags: None,
+            },
+        },
+        network: ServerNetworkData {
+            channel_ids,
+            io_channel: IO_CHANNEL_ID,
+        },
+        security: ServerSecurityData::no_security(),
+        message_channel: None,
+        multi_transport_channel: None,
+    }
+}
+
+fn write_credssp_request(ts_request: credssp::TsRequest, output: &mut Vec<u8>) -> Result<usize> {
+    let length = usize::from(ts_request.buffer_len());
+
+    if output.len() < length {
+        output.resize(length, 0);
+    }
+
+    ts_request
+        .encode_ts_request(output.as_mut_slice())
+        .map_err(|e| Error::new("CredSSP").with_reason(format!("TsRequest encode: {e}")))?;
+
+    Ok(length)
+}
@@ -0,0 +1,208 @@
+use std::mem;
+
+use ironrdp_connector::legacy::decode_share_data;
+use ironrdp_pdu::rdp::capability_sets::SERVER_CHANNEL_ID;
+use ironrdp_pdu::rdp::finalization_messages;
+use ironrdp_pdu::rdp::headers::ShareDataPdu;
+use ironrdp_pdu::PduHint;
+
+use crate::{legacy, Error, Result, Sequence, State, Written};
+
+#[derive(Default, Debug, Clone)]
+#[non_exhaustive]
+pub enum FinalizationState {
+    #[default]
+    Consumed,
+
+    WaitSynchronize,
+    WaitControlCooperate,
+    WaitRequestControl,
+    WaitFontList,
+
+    SendSynchronize,
+    SendControlCooperate,
+    SendGrantedControl,
+    SendFontMap,
+
+    Finished,
+}
+
+impl State for FinalizationState {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Consumed => "Consumed",
+            Self::WaitSynchronize => "WaitSynchronize",
+            Self::WaitControlCooperate => "WaitControlCooperate",
+            Self::WaitRequestControl => "WaitRequestControl",
+            Self::WaitFontList => "WaitFontList",
+            Self::SendSynchronize => "SendSynchronize",
+            Self::SendControlCooperate => "SendControlCooperate",
+            Self::SendGrantedControl => "SendGrantedControl",
+            Self::SendFontMap => "SendFontMap",
+            Self::Finished => "Finished",
+        }
+    }
+
+    fn is_terminal(&self) -> bool {
+        matches!(self, Self::Finished)
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+}
+
+/// Server side of the Connection Finalization phase
+///
+/// The client sends its finalization PDUs first, and the server answers once the Font List PDU is received.
+#[derive(Debug, Clone)]
+pub struct FinalizationSequence {
+    pub state: FinalizationState,
+    pub io_channel_id: u16,
+    pub user_channel_id: u16,
+    pub share_id: u32,
+}
+
+impl FinalizationSequence {
+    pub fn new(io_channel_id: u16, user_channel_id: u16, share_id: u32) -> Self {
+        Self {
+            state: FinalizationState::WaitSynchronize,
+            io_channel_id,
+            user_channel_id,
+            share_id,
+        }
+    }
+
+    fn encode(&self, message: ShareDataPdu, output: &mut Vec<u8>) -> Result<Written> {
+        debug!(?message, "Send");
+
+        let written = legacy::encode_share_data(self.io_channel_id, self.share_id, message, output)?;
+
+        Written::from_size(written)
+    }
+}
+
+impl Sequence for FinalizationSequence {
+    fn next_pdu_hint(&self) -> Option<&dyn PduHint> {
+        match self.state {
+            FinalizationState::Consumed => None,
+            FinalizationState::WaitSynchronize => Some(&ironrdp_pdu::X224_HINT),
+            FinalizationState::WaitControlCooperate => Some(&ironrdp_pdu::X224_HINT),
+            FinalizationState::WaitRequestControl => Some(&ironrdp_pdu::X224_HINT),
+            FinalizationState::WaitFontList => Some(&ironrdp_pdu::X224_HINT),
+            FinalizationState::SendSynchronize => None,
+            FinalizationState::SendControlCooperate => None,
+            FinalizationState::SendGrantedControl => None,
+            FinalizationState::SendFontMap => None,
+            FinalizationState::Finished => None,
+        }
+    }
+
+    fn state(&self) -> &dyn State {
+        &self.state
+    }
+
+    fn step(&mut self, input: &[u8], output: &mut Vec<u8>) -> Result<Written> {
+        let (written, next_state) = match mem::take(&mut self.state) {
+            FinalizationState::Consumed => {
+                return Err(Error::new("finalization sequence state is consumed (this is a bug)"))
+            }
+
+            FinalizationState::WaitSynchronize => {
+                let ShareDataPdu::Synchronize(synchronize) = decode_client_message(input)? else {
+                    return Err(Error::new("unexpected client message (expected Synchronize PDU)"));
+                };
+
+                debug!(message = ?synchronize, "Received");
+
+                (Written::Nothing, FinalizationState::WaitControlCooperate)
+            }
+
+            FinalizationState::WaitControlCooperate => match decode_client_message(input)? {
+                ShareDataPdu::Control(control_pdu)
+                    if control_pdu.action == finalization_messages::ControlAction::Cooperate =>
+                {
+                    debug!(message = ?control_pdu, "Received");
+                    (Written::Nothing, FinalizationState::WaitRequestControl)
+                }
+                _ => return Err(Error::new("unexpected client message (expected Control Cooperate PDU)")),
+            },
+
+            FinalizationState::WaitRequestControl => match decode_client_message(input)? {
+                ShareDataPdu::Control(control_pdu)
+                    if control_pdu.action == finalization_messages::ControlAction::RequestControl =>
+                {
+                    debug!(message = ?control_pdu, "Received");
+                    (Written::Nothing, FinalizationState::WaitFontList)
+                }
+                _ => {
+                    return Err(Error::new(
+                        "unexpected client message (expected Control Request Control PDU)",
+                    ))
+                }
+            },
+
+            FinalizationState::WaitFontList => {
+                let ShareDataPdu::FontList(font_list) = decode_client_message(input)? else {
+                    return Err(Error::new("unexpected client message (expected Font List PDU)"));
+                };
+
+                debug!(message = ?font_list, "Received");
+
+                (Written::Nothing, FinalizationState::SendSynchronize)
+            }
+
+            FinalizationState::SendSynchronize => {
+                let message = ShareDataPdu::Synchronize(finalization_messages::SynchronizePdu {
+                    target_user_id: self.user_channel_id,
+                });
+
+                (self.encode(message, output)?, FinalizationState::SendControlCooperate)
+            }
+
+            FinalizationState::SendControlCooperate => {
+                let message = ShareDataPdu::Control(finalization_messages::ControlPdu {
+                    action: finalization_messages::ControlAction::Cooperate,
+                    grant_id: 0,
+                    control_id: 0,
+                });
+
+                (self.encode(message, output)?, FinalizationState::SendGrantedControl)
+            }
+
+            FinalizationState::SendGrantedControl => {
+                let message = ShareDataPdu::Control(finalization_messages::ControlPdu {
+                    action: finalization_messages::ControlAction::GrantedControl,
+                    grant_id: self.user_channel_id,
+                    control_id: u32::from(SERVER_CHANNEL_ID),
+                });
+
+                (self.encode(message, output)?, FinalizationState::SendFontMap)
+            }
+
+            FinalizationState::SendFontMap => {
+                let message = ShareDataPdu::FontMap(finalization_messages::FontPdu {
+                    number: 0,
+                    total_number: 0,
+                    flags: finalization_messages::SequenceFlags::FIRST | finalization_messages::SequenceFlags::LAST,
+                    entry_size: 0x0004,
+                });
+
+                (self.encode(message, output)?, FinalizationState::Finished)
+            }
+
+            FinalizationState::Finished => return Err(Error::new("finalization already finished")),
+        };
+
+        self.state = next_state;
+
+        Ok(written)
+    }
+}
+
+fn decode_client_message(input: &[u8]) -> Result<ShareDataPdu> {
+    let ctx = legacy::decode_send_data_request(input)?;
+    let ctx = decode_share_data(ctx)?;
+
+    Ok(ctx.pdu)
+}
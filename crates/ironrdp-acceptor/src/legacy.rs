@@ -0,0 +1,96 @@
+//! Server-side counterparts of the connector legacy helpers, based on the old PduParsing trait
+
+use std::borrow::Cow;
+
+use ironrdp_connector::legacy::SendDataIndicationCtx;
+pub use ironrdp_connector::legacy::{decode_x224_packet, encode_x224_packet};
+use ironrdp_pdu::rdp::capability_sets::SERVER_CHANNEL_ID;
+use ironrdp_pdu::{rdp, PduParsing};
+
+pub fn encode_send_data_indication<T>(
+    initiator_id: u16,
+    channel_id: u16,
+    user_msg: &T,
+    buf: &mut Vec<u8>,
+) -> crate::Result<usize>
+where
+    T: PduParsing,
+    crate::Error: From<T::Error>,
+{
+    let user_data_len = user_msg.buffer_length();
+    let mut user_data = Vec::with_capacity(user_data_len);
+
+    user_msg.to_buffer(&mut user_data)?;
+
+    let pdu = ironrdp_pdu::mcs::SendDataIndication {
+        initiator_id,
+        channel_id,
+        user_data: Cow::Owned(user_data),
+    };
+
+    let written = ironrdp_pdu::encode_buf(&pdu, buf)?;
+
+    Ok(written)
+}
+
+/// Decodes a MCS Send Data Request PDU sent by the client.
+///
+/// The returned context is the one used by the client side, so it can be passed to
+/// [`decode_share_control`](ironrdp_connector::legacy::decode_share_control) and
+/// [`decode_share_data`](ironrdp_connector::legacy::decode_share_data).
+pub fn decode_send_data_request(src: &[u8]) -> crate::Result<SendDataIndicationCtx<'_>> {
+    use ironrdp_pdu::mcs::McsMessage;
+
+    let mcs_msg = ironrdp_pdu::decode::<McsMessage>(src)?;
+
+    match mcs_msg {
+        McsMessage::SendDataRequest(msg) => {
+            let Cow::Borrowed(user_data) = msg.user_data else {
+                unreachable!()
+            };
+
+            Ok(SendDataIndicationCtx {
+                initiator_id: msg.initiator_id,
+                channel_id: msg.channel_id,
+                user_data,
+            })
+        }
+        McsMessage::DisconnectProviderUltimatum(msg) => {
+            Err(crate::Error::new("received disconnect provider ultimatum").with_reason(format!("{:?}", msg.reason)))
+        }
+        unexpected => Err(crate::Error::new("unexpected MCS message").with_reason(ironrdp_pdu::name(&unexpected))),
+    }
+}
+
+pub fn encode_share_control(
+    channel_id: u16,
+    share_id: u32,
+    pdu: rdp::headers::ShareControlPdu,
+    buf: &mut Vec<u8>,
+) -> crate::Result<usize> {
+    let share_control_header = rdp::headers::ShareControlHeader {
+        share_control_pdu: pdu,
+        pdu_source: SERVER_CHANNEL_ID,
+        share_id,
+    };
+
+    encode_send_data_indication(SERVER_CHANNEL_ID, channel_id, &share_control_header, buf)
+}
+
+pub fn encode_share_data(
+    channel_id: u16,
+    share_id: u32,
+    pdu: rdp::headers::ShareDataPdu,
+    buf: &mut Vec<u8>,
+) -> crate::Result<usize> {
+    let share_data_header = rdp::headers::ShareDataHeader {
+        share_data_pdu: pdu,
+        stream_priority: rdp::headers::StreamPriority::Medium,
+        compression_flags: rdp::headers::CompressionFlags::empty(),
+        compression_type: rdp::client_info::CompressionType::K8, // ignored if CompressionFlags::empty()
+    };
+
+    let share_control_pdu = rdp::headers::ShareControlPdu::Data(share_data_header);
+
+    encode_share_control(channel_id, share_id, share_control_pdu, buf)
+}
@@ -0,0 +1,54 @@
+//! Server side of the RDP connection sequence.
+//!
+//! The [`Acceptor`] is the mirror of the [`ClientConnector`](ironrdp_connector::ClientConnector): it answers
+//! the client PDUs until the connection is finalized, and produces an [`AcceptorResult`] which is used to
+//! build the server [`ActiveStage`]. Like the other core crates, it never performs any I/O: the TLS
+//! handshake in particular is left to the user code.
+
+#[macro_use]
+extern crate tracing;
+
+pub mod legacy;
+
+mod active_stage;
+mod capabilities;
+mod channel_connection;
+mod connection;
+mod finalization;
+
+use std::collections::HashMap;
+
+pub use active_stage::{ActiveStage, ActiveStageOutput};
+pub use capabilities::create_server_demand_active;
+pub use channel_connection::{ChannelConnectionSequence, ChannelConnectionState};
+pub use connection::{Acceptor, AcceptorResult, AcceptorState};
+pub use finalization::{FinalizationSequence, FinalizationState};
+pub use ironrdp_connector::{DesktopSize, Error, ErrorKind, Result, Sequence, State, Written};
+use ironrdp_pdu::nego;
+use ironrdp_pdu::rdp::capability_sets::CapabilitySet;
+
+/// Static channels joined by the client, by name
+pub type StaticChannels = HashMap<String, u16>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Credentials {
+    pub username: String,
+    pub password: String,
+    pub domain: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Security protocols accepted by the server
+    ///
+    /// Standard RDP Security is not supported, and CredSSP (`HYBRID` and `HYBRID_EX`) is only
+    /// selected when `credentials` is set.
+    pub security_protocol: nego::SecurityProtocol,
+    pub desktop_size: DesktopSize,
+    /// Credentials the client must provide, or `None` to accept any client
+    pub credentials: Option<Credentials>,
+    /// Capability sets sent in the Server Demand Active PDU, in addition to the default ones
+    ///
+    /// A capability set replaces the default one of the same type.
+    pub capabilities: Vec<CapabilitySet>,
+}
@@ -0,0 +1,224 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use ironrdp_acceptor::{AcceptorResult, ActiveStage, ActiveStageOutput, Credentials, DesktopSize};
+use ironrdp_pdu::fast_path::{FastPathHeader, FastPathUpdate, FastPathUpdatePdu, Fragmentation, UpdateCode};
+use ironrdp_pdu::geometry::Rectangle;
+use ironrdp_pdu::input::fast_path::{FastPathInput, FastPathInputEvent, KeyboardFlags};
+use ironrdp_pdu::input::mouse::PointerFlags;
+use ironrdp_pdu::input::{InputEvent, InputEventPdu, MousePdu};
+use ironrdp_pdu::pointer::{Point16, PointerUpdateData};
+use ironrdp_pdu::rdp::headers::ShareDataPdu;
+use ironrdp_pdu::surface_commands::{ExtendedBitmapDataPdu, SurfaceBitsPdu, SurfaceCommand};
+use ironrdp_pdu::{mcs, Action, PduBufferParsing, PduParsing};
+
+const IO_CHANNEL_ID: u16 = 1003;
+const USER_CHANNEL_ID: u16 = 1005;
+const SHARE_ID: u32 = 0x0001_03ea;
+
+fn active_stage() -> ActiveStage {
+    ActiveStage::new(&AcceptorResult {
+        io_channel_id: IO_CHANNEL_ID,
+        user_channel_id: USER_CHANNEL_ID,
+        share_id: SHARE_ID,
+        static_channels: HashMap::from([("drdynvc".to_owned(), 1004)]),
+        desktop_size: DesktopSize {
+            width: 1920,
+            height: 1080,
+        },
+        client_capabilities: Vec::new(),
+        credentials: Credentials {
+            username: "user".to_owned(),
+            password: String::new(),
+            domain: None,
+        },
+    })
+}
+
+/// Returns the fast-path update PDUs of the frames written one after the other
+fn decode_update_frames(mut frames: &[u8]) -> Vec<FastPathUpdatePdu<'_>> {
+    let mut pdus = Vec::new();
+
+    while !frames.is_empty() {
+        let length = ironrdp_pdu::find_size(frames).unwrap().unwrap().length;
+        let (mut frame, rest) = frames.split_at(length);
+
+        let header = FastPathHeader::from_buffer(&mut frame).unwrap();
+        assert_eq!(header.data_length, frame.len());
+
+        pdus.push(FastPathUpdatePdu::from_buffer_consume(&mut frame).unwrap());
+        assert!(frame.is_empty());
+
+        frames = rest;
+    }
+
+    pdus
+}
+
+fn surface_bits(data: &[u8]) -> FastPathUpdate<'_> {
+    FastPathUpdate::SurfaceCommands(vec![SurfaceCommand::SetSurfaceBits(SurfaceBitsPdu {
+        destination: Rectangle {
+            left: 0,
+            top: 0,
+            right: 64,
+            bottom: 64,
+        },
+        extended_bitmap_data: ExtendedBitmapDataPdu {
+            bpp: 32,
+            codec_id: 0,
+            width: 64,
+            height: 64,
+            header: None,
+            data,
+        },
+    })])
+}
+
+//== Fast-Path Output ==//
+
+#[test]
+fn small_update_is_sent_in_a_single_fragment() {
+    let update = FastPathUpdate::Pointer(PointerUpdateData::SetPosition(Point16 { x: 12, y: 34 }));
+
+    let mut output = Vec::new();
+    let written = active_stage().encode_update(&update, &mut output).unwrap();
+
+    let pdus = decode_update_frames(&output[..written]);
+
+    assert_eq!(pdus.len(), 1);
+    assert_eq!(pdus[0].fragmentation, Fragmentation::Single);
+    assert_eq!(pdus[0].update_code, UpdateCode::PositionPointer);
+    assert_eq!(
+        FastPathUpdate::from_buffer_with_code(pdus[0].data, pdus[0].update_code).unwrap(),
+        update
+    );
+}
+
+#[test]
+fn update_without_payload_is_sent_as_an_empty_fragment() {
+    let update = FastPathUpdate::Pointer(PointerUpdateData::SetHidden);
+
+    let mut output = Vec::new();
+    let written = active_stage().encode_update(&update, &mut output).unwrap();
+
+    let pdus = decode_update_frames(&output[..written]);
+
+    assert_eq!(pdus.len(), 1);
+    assert_eq!(pdus[0].fragmentation, Fragmentation::Single);
+    assert_eq!(pdus[0].update_code, UpdateCode::HiddenPointer);
+    assert!(pdus[0].data.is_empty());
+}
+
+#[test]
+fn large_update_is_fragmented() {
+    // Large enough for three fragments of at most 0x3F80 bytes
+    let bitmap_data = (0..64 * 64 * 8).map(|i| i as u8).collect::<Vec<_>>();
+    let update = surface_bits(&bitmap_data);
+
+    let mut output = Vec::new();
+    let written = active_stage().encode_update(&update, &mut output).unwrap();
+
+    let pdus = decode_update_frames(&output[..written]);
+
+    assert_eq!(
+        pdus.iter().map(|pdu| pdu.fragmentation).collect::<Vec<_>>(),
+        [Fragmentation::First, Fragmentation::Next, Fragmentation::Last]
+    );
+    assert!(pdus.iter().all(|pdu| pdu.update_code == UpdateCode::SurfaceCommands));
+    assert!(pdus.iter().all(|pdu| pdu.data.len() <= 0x3F80));
+
+    let data = pdus.iter().flat_map(|pdu| pdu.data.iter().copied()).collect::<Vec<_>>();
+
+    assert_eq!(
+        FastPathUpdate::from_buffer_with_code(&data, UpdateCode::SurfaceCommands).unwrap(),
+        update
+    );
+}
+
+#[test]
+fn encoded_update_overwrites_the_start_of_the_output() {
+    let update = FastPathUpdate::Pointer(PointerUpdateData::SetDefault);
+
+    let mut output = vec![0xff; 64];
+    let written = active_stage().encode_update(&update, &mut output).unwrap();
+
+    assert_eq!(output.len(), 64);
+    assert_eq!(decode_update_frames(&output[..written]).len(), 1);
+}
+
+//== Client Input ==//
+
+#[test]
+fn fast_path_input_is_decoded() {
+    let events = vec![
+        FastPathInputEvent::KeyboardEvent(KeyboardFlags::empty(), 0x1e),
+        FastPathInputEvent::KeyboardEvent(KeyboardFlags::RELEASE, 0x1e),
+    ];
+
+    let mut frame = Vec::new();
+    FastPathInput(events.clone()).to_buffer(&mut frame).unwrap();
+
+    let output = active_stage().process(Action::FastPath, &frame).unwrap();
+
+    assert_eq!(output, [ActiveStageOutput::FastPathInput(events)]);
+}
+
+#[test]
+fn slow_path_input_is_decoded() {
+    let events = vec![InputEvent::Mouse(MousePdu {
+        flags: PointerFlags::MOVE,
+        number_of_wheel_rotation_units: 0,
+        x_position: 100,
+        y_position: 200,
+    })];
+
+    let mut frame = Vec::new();
+    ironrdp_connector::legacy::encode_share_data(
+        USER_CHANNEL_ID,
+        IO_CHANNEL_ID,
+        SHARE_ID,
+        ShareDataPdu::Input(InputEventPdu(events.clone())),
+        &mut frame,
+    )
+    .unwrap();
+
+    let output = active_stage().process(Action::X224, &frame).unwrap();
+
+    assert_eq!(output, [ActiveStageOutput::SlowPathInput(events)]);
+}
+
+#[test]
+fn static_channel_data_is_forwarded() {
+    let request = mcs::SendDataRequest {
+        initiator_id: USER_CHANNEL_ID,
+        channel_id: 1004,
+        user_data: Cow::Borrowed(b"channel data"),
+    };
+
+    let mut frame = Vec::new();
+    ironrdp_pdu::encode_buf(&request, &mut frame).unwrap();
+
+    let output = active_stage().process(Action::X224, &frame).unwrap();
+
+    assert_eq!(
+        output,
+        [ActiveStageOutput::ChannelData {
+            channel_id: 1004,
+            data: b"channel data".to_vec(),
+        }]
+    );
+}
+
+#[test]
+fn disconnect_provider_ultimatum_terminates_the_session() {
+    let ultimatum = mcs::DisconnectProviderUltimatum {
+        reason: mcs::DisconnectReason::UserRequested,
+    };
+
+    let mut frame = Vec::new();
+    ironrdp_pdu::encode_buf(&ultimatum, &mut frame).unwrap();
+
+    let output = active_stage().process(Action::X224, &frame).unwrap();
+
+    assert_eq!(output, [ActiveStageOutput::Terminate]);
+}
@@ -0,0 +1,421 @@
+use ironrdp_acceptor::{
+    Acceptor, AcceptorResult, AcceptorState, Config, Credentials, DesktopSize, Sequence, State as _,
+};
+use ironrdp_connector::{ClientConnector, ClientConnectorState, ConnectionResult};
+use ironrdp_pdu::gcc::{self, ChannelOptions};
+use ironrdp_pdu::nego::SecurityProtocol;
+use ironrdp_pdu::rdp::capability_sets::{CapabilitySet, MajorPlatformType};
+use ironrdp_pdu::rdp::client_info::PerformanceFlags;
+
+/// Drives a client connector and an acceptor against each other, without any TLS upgrade
+struct Harness {
+    client: ClientConnector,
+    acceptor: Acceptor,
+    to_server: Vec<u8>,
+    to_client: Vec<u8>,
+}
+
+impl Harness {
+    fn new(client_config: ironrdp_connector::Config, acceptor_config: Config) -> Self {
+        Self {
+            client: ClientConnector::new(client_config).with_server_addr(server_addr()),
+            acceptor: Acceptor::new(acceptor_config),
+            to_server: Vec::new(),
+            to_client: Vec::new(),
+        }
+    }
+
+    /// Steps one side at a time until `stop` returns true or neither side can make progress
+    fn run_until(&mut self, stop: impl Fn(&Self) -> bool) -> ironrdp_connector::Result<()> {
+        while !stop(self) {
+            if self.client.should_perform_security_upgrade() && self.acceptor.should_perform_security_upgrade() {
+                self.client.mark_security_upgrade_as_done();
+                self.acceptor.mark_security_upgrade_as_done();
+                continue;
+            }
+
+            if step(&mut self.client, &mut self.to_client, &mut self.to_server)? {
+                continue;
+            }
+
+            if !step(&mut self.acceptor, &mut self.to_server, &mut self.to_client)? {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn run_to_completion(&mut self) -> ironrdp_connector::Result<(ConnectionResult, AcceptorResult)> {
+        self.run_until(|harness| harness.client.state.is_terminal() && harness.acceptor.state.is_terminal())?;
+
+        let ClientConnectorState::Connected { result: client_result } = &self.client.state else {
+            panic!("client is not connected: {}", self.client.state.name());
+        };
+
+        let AcceptorState::Accepted {
+            result: acceptor_result,
+        } = &self.acceptor.state
+        else {
+            panic!("client is not accepted: {}", self.acceptor.state.name());
+        };
+
+        assert!(self.to_server.is_empty() && self.to_client.is_empty());
+
+        Ok((client_result.clone(), acceptor_result.clone()))
+    }
+}
+
+/// Performs a single step of `sequence` if it has something to send, or if a complete PDU was received
+fn step(sequence: &mut dyn Sequence, input: &mut Vec<u8>, output: &mut Vec<u8>) -> ironrdp_connector::Result<bool> {
+    if sequence.state().is_terminal() {
+        return Ok(false);
+    }
+
+    let frame = match sequence.next_pdu_hint() {
+        // Waiting for the TLS upgrade
+        None if input.is_empty() && is_security_upgrade(sequence) => return Ok(false),
+        None => Vec::new(),
+        Some(hint) => match hint.find_size(input)? {
+            Some(length) if length <= input.len() => input.drain(..length).collect(),
+            _ => return Ok(false),
+        },
+    };
+
+    let mut buf = Vec::new();
+    let written = sequence.step(&frame, &mut buf)?;
+
+    if let Some(size) = written.size() {
+        output.extend_from_slice(&buf[..size]);
+    }
+
+    Ok(true)
+}
+
+fn is_security_upgrade(sequence: &dyn Sequence) -> bool {
+    matches!(sequence.state().name(), "EnhancedSecurityUpgrade" | "SecurityUpgrade")
+}
+
+fn server_addr() -> std::net::SocketAddr {
+    "127.0.0.1:3389".parse().unwrap()
+}
+
+fn client_config() -> ironrdp_connector::Config {
+    ironrdp_connector::Config {
+        desktop_size: DesktopSize {
+            width: 1024,
+            height: 768,
+        },
+        security_protocol: SecurityProtocol::SSL,
+        username: "user".to_owned(),
+        password: "password".to_owned(),
+        domain: None,
+        client_build: 0,
+        client_name: "client".to_owned(),
+        keyboard_layout: 0,
+        keyboard_type: gcc::KeyboardType::IbmEnhanced,
+        keyboard_subtype: 0,
+        keyboard_functional_keys_count: 12,
+        ime_file_name: String::new(),
+        graphics: None,
+        bitmap: None,
+        dig_product_id: String::new(),
+        client_dir: "C:\\Windows\\System32\\mstscax.dll".to_owned(),
+        platform: MajorPlatformType::Unspecified,
+        enable_audio_playback: false,
+        pointer_cache_size: 32,
+        compression_type: None,
+        timezone: None,
+        performance_flags: PerformanceFlags::empty(),
+        standard_rdp_security: false,
+        static_channels: Vec::new(),
+        monitors: Vec::new(),
+    }
+}
+
+fn acceptor_config() -> Config {
+    Config {
+        security_protocol: SecurityProtocol::SSL,
+        desktop_size: DesktopSize {
+            width: 1920,
+            height: 1080,
+        },
+        credentials: None,
+        capabilities: Vec::new(),
+    }
+}
+
+fn credentials() -> Credentials {
+    Credentials {
+        username: "user".to_owned(),
+        password: "password".to_owned(),
+        domain: None,
+    }
+}
+
+//== Connection Initiation ==//
+
+#[test]
+fn negotiation_selects_ssl_without_credentials() {
+    let mut client_config = client_config();
+    client_config.security_protocol = SecurityProtocol::SSL | SecurityProtocol::HYBRID | SecurityProtocol::HYBRID_EX;
+
+    let mut harness = Harness::new(client_config, acceptor_config());
+    harness
+        .run_until(|harness| harness.client.should_perform_security_upgrade())
+        .unwrap();
+
+    assert!(matches!(
+        harness.acceptor.state,
+        AcceptorState::SecurityUpgrade { selected_protocol, .. } if selected_protocol == SecurityProtocol::SSL
+    ));
+    assert!(matches!(
+        harness.client.state,
+        ClientConnectorState::EnhancedSecurityUpgrade { selected_protocol } if selected_protocol == SecurityProtocol::SSL
+    ));
+}
+
+#[test]
+fn negotiation_selects_hybrid_ex_with_credentials() {
+    let mut client_config = client_config();
+    client_config.security_protocol = SecurityProtocol::SSL | SecurityProtocol::HYBRID | SecurityProtocol::HYBRID_EX;
+
+    let mut acceptor_config = acceptor_config();
+    acceptor_config.security_protocol = SecurityProtocol::SSL | SecurityProtocol::HYBRID | SecurityProtocol::HYBRID_EX;
+    acceptor_config.credentials = Some(credentials());
+
+    let mut harness = Harness::new(client_config, acceptor_config);
+    harness
+        .run_until(|harness| harness.client.should_perform_security_upgrade())
+        .unwrap();
+
+    assert!(matches!(
+        harness.client.state,
+        ClientConnectorState::EnhancedSecurityUpgrade { selected_protocol }
+            if selected_protocol == SecurityProtocol::HYBRID_EX
+    ));
+
+    harness.acceptor.mark_security_upgrade_as_done();
+    assert!(harness.acceptor.is_credssp_step());
+}
+
+#[test]
+fn negotiation_fails_without_common_security_protocol() {
+    let mut client_config = client_config();
+    client_config.security_protocol = SecurityProtocol::HYBRID | SecurityProtocol::HYBRID_EX;
+
+    let mut harness = Harness::new(client_config, acceptor_config());
+    let error = harness.run_to_completion().unwrap_err();
+
+    assert!(error.to_string().contains("no common security protocol"), "{error}");
+}
+
+//== CredSSP ==//
+
+#[test]
+fn credssp_requires_the_server_public_key() {
+    let mut acceptor_config = acceptor_config();
+    acceptor_config.security_protocol = SecurityProtocol::HYBRID_EX;
+    acceptor_config.credentials = Some(credentials());
+
+    let mut acceptor = Acceptor::new(acceptor_config);
+    acceptor.state = AcceptorState::CredsspInitial {
+        requested_protocol: SecurityProtocol::HYBRID_EX,
+        selected_protocol: SecurityProtocol::HYBRID_EX,
+    };
+
+    let error = acceptor.step(&[], &mut Vec::new()).unwrap_err();
+
+    assert!(error.to_string().contains("server public key is missing"), "{error}");
+}
+
+#[test]
+fn credssp_early_user_authorization_result_is_accepted_by_the_client() {
+    let mut acceptor = Acceptor::new(acceptor_config());
+    acceptor.state = AcceptorState::CredsspEarlyUserAuthResult {
+        requested_protocol: SecurityProtocol::HYBRID_EX,
+        selected_protocol: SecurityProtocol::HYBRID_EX,
+    };
+
+    let mut client_config = client_config();
+    client_config.security_protocol = SecurityProtocol::HYBRID_EX;
+
+    let mut client = ClientConnector::new(client_config);
+    client.state = ClientConnectorState::CredsspEarlyUserAuthResult {
+        selected_protocol: SecurityProtocol::HYBRID_EX,
+    };
+
+    let mut early_user_auth_result = Vec::new();
+    let written = acceptor.step(&[], &mut early_user_auth_result).unwrap();
+    assert_eq!(&early_user_auth_result[..written.size().unwrap()], [0, 0, 0, 0]);
+    assert!(matches!(acceptor.state, AcceptorState::BasicSettingsWaitInitial { .. }));
+
+    let length = client
+        .next_pdu_hint()
+        .unwrap()
+        .find_size(&early_user_auth_result)
+        .unwrap()
+        .unwrap();
+    client.step(&early_user_auth_result[..length], &mut Vec::new()).unwrap();
+    assert!(matches!(
+        client.state,
+        ClientConnectorState::BasicSettingsExchangeSendInitial { .. }
+    ));
+}
+
+//== Basic Settings Exchange ==//
+
+#[test]
+fn mcs_static_channel_ids_are_assigned_in_request_order() {
+    let mut client_config = client_config();
+    client_config.static_channels = vec![
+        gcc::Channel {
+            name: "cliprdr".to_owned(),
+            options: ChannelOptions::INITIALIZED | ChannelOptions::SHOW_PROTOCOL,
+        },
+        gcc::Channel {
+            name: "rdpsnd".to_owned(),
+            options: ChannelOptions::INITIALIZED,
+        },
+    ];
+
+    let mut harness = Harness::new(client_config, acceptor_config());
+    harness
+        .run_until(|harness| matches!(harness.acceptor.state, AcceptorState::ChannelConnection { .. }))
+        .unwrap();
+
+    let AcceptorState::ChannelConnection { static_channels, .. } = &harness.acceptor.state else {
+        unreachable!()
+    };
+
+    assert_eq!(static_channels.len(), 3);
+    assert_eq!(static_channels["drdynvc"], 1004);
+    assert_eq!(static_channels["cliprdr"], 1005);
+    assert_eq!(static_channels["rdpsnd"], 1006);
+
+    let (client_result, acceptor_result) = harness.run_to_completion().unwrap();
+
+    assert_eq!(client_result.static_channels, acceptor_result.static_channels);
+}
+
+//== Channel Connection ==//
+
+#[test]
+fn channel_join_agrees_on_the_channel_ids() {
+    let mut harness = Harness::new(client_config(), acceptor_config());
+    harness
+        .run_until(|harness| matches!(harness.acceptor.state, AcceptorState::RdpSecurityCommencement { .. }))
+        .unwrap();
+
+    let AcceptorState::RdpSecurityCommencement { user_channel_id, .. } = harness.acceptor.state else {
+        panic!("channels are not joined: {}", harness.acceptor.state.name());
+    };
+
+    // drdynvc is the only static channel
+    assert_eq!(user_channel_id, 1005);
+
+    let (client_result, acceptor_result) = harness.run_to_completion().unwrap();
+
+    assert_eq!(client_result.io_channel_id, acceptor_result.io_channel_id);
+    assert_eq!(client_result.user_channel_id, acceptor_result.user_channel_id);
+    assert_eq!(acceptor_result.user_channel_id, user_channel_id);
+}
+
+//== Secure Settings Exchange ==//
+
+#[test]
+fn client_info_credentials_are_checked_without_credssp() {
+    let mut acceptor_config = acceptor_config();
+    acceptor_config.credentials = Some(credentials());
+
+    let (_, acceptor_result) = Harness::new(client_config(), acceptor_config.clone())
+        .run_to_completion()
+        .unwrap();
+    assert_eq!(acceptor_result.credentials, credentials());
+
+    let mut client_config = client_config();
+    client_config.password = "wrong".to_owned();
+
+    let error = Harness::new(client_config, acceptor_config)
+        .run_to_completion()
+        .unwrap_err();
+    assert!(error.to_string().contains("invalid credentials"), "{error}");
+}
+
+//== Licensing ==//
+
+#[test]
+fn licensing_ends_with_a_valid_client_status() {
+    let mut harness = Harness::new(client_config(), acceptor_config());
+    harness
+        .run_until(|harness| matches!(harness.client.state, ClientConnectorState::CapabilitiesExchange { .. }))
+        .unwrap();
+
+    assert!(matches!(
+        harness.client.state,
+        ClientConnectorState::CapabilitiesExchange { .. }
+    ));
+    assert!(matches!(
+        harness.acceptor.state,
+        AcceptorState::CapabilitiesSendServer { .. }
+    ));
+}
+
+//== Capabilities Exchange ==//
+
+#[test]
+fn demand_active_advertises_the_desktop_size() {
+    let (client_result, acceptor_result) = Harness::new(client_config(), acceptor_config())
+        .run_to_completion()
+        .unwrap();
+
+    assert_eq!(
+        (client_result.desktop_size.width, client_result.desktop_size.height),
+        (1920, 1080)
+    );
+    assert_eq!(
+        (acceptor_result.desktop_size.width, acceptor_result.desktop_size.height),
+        (1920, 1080)
+    );
+}
+
+#[test]
+fn confirm_active_capabilities_are_returned() {
+    let (_, acceptor_result) = Harness::new(client_config(), acceptor_config())
+        .run_to_completion()
+        .unwrap();
+
+    assert!(acceptor_result
+        .client_capabilities
+        .iter()
+        .any(|capability| matches!(capability, CapabilitySet::General(_))));
+    assert!(acceptor_result
+        .client_capabilities
+        .iter()
+        .any(|capability| matches!(capability, CapabilitySet::Bitmap(_))));
+}
+
+//== Connection Finalization ==//
+
+#[test]
+fn finalization_completes_both_sides() {
+    let mut harness = Harness::new(client_config(), acceptor_config());
+    harness
+        .run_until(|harness| matches!(harness.acceptor.state, AcceptorState::ConnectionFinalization { .. }))
+        .unwrap();
+
+    assert!(matches!(
+        harness.client.state,
+        ClientConnectorState::ConnectionFinalization { .. }
+    ));
+
+    let (client_result, acceptor_result) = harness.run_to_completion().unwrap();
+
+    assert_eq!(client_result.connection_activation.state.name(), "Finalized");
+    assert_eq!(acceptor_result.share_id, 0x0001_03ea);
+    assert_eq!(acceptor_result.credentials.username, "user");
+
+    // The sequences are done
+    assert!(harness.client.next_pdu_hint().is_none());
+    assert!(harness.acceptor.step(&[], &mut Vec::new()).is_err());
+}
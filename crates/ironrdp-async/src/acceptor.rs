@@ -0,0 +1,99 @@
+use ironrdp_acceptor::{Acceptor, AcceptorResult, AcceptorState, Sequence as _, State as _};
+
+use crate::framed::{Framed, FramedRead, FramedWrite};
+use crate::{ShouldUpgrade, Upgraded};
+
+#[instrument(skip_all)]
+pub async fn accept_begin<S>(framed: &mut Framed<S>, acceptor: &mut Acceptor) -> ironrdp_acceptor::Result<ShouldUpgrade>
+where
+    S: Sync + FramedRead + FramedWrite,
+{
+    let mut buf = Vec::new();
+
+    info!("Begin acceptor procedure");
+
+    while !acceptor.should_perform_security_upgrade() {
+        single_accept_step(framed, acceptor, &mut buf).await?;
+    }
+
+    Ok(ShouldUpgrade::new())
+}
+
+#[instrument(skip_all)]
+pub fn mark_acceptor_as_upgraded(_: ShouldUpgrade, acceptor: &mut Acceptor, server_public_key: Vec<u8>) -> Upgraded {
+    trace!("marked as upgraded");
+    acceptor.attach_server_public_key(server_public_key);
+    acceptor.mark_security_upgrade_as_done();
+    Upgraded::new()
+}
+
+#[instrument(skip_all)]
+pub async fn accept_finalize<S>(
+    _: Upgraded,
+    framed: &mut Framed<S>,
+    mut acceptor: Acceptor,
+) -> ironrdp_acceptor::Result<AcceptorResult>
+where
+    S: FramedRead + FramedWrite,
+{
+    let mut buf = Vec::new();
+
+    debug!("CredSSP procedure");
+
+    while acceptor.is_credssp_step() {
+        single_accept_step(framed, &mut acceptor, &mut buf).await?;
+    }
+
+    debug!("Remaining of acceptor sequence");
+
+    let result = loop {
+        single_accept_step(framed, &mut acceptor, &mut buf).await?;
+
+        if let AcceptorState::Accepted { result } = acceptor.state {
+            break result;
+        }
+    };
+
+    info!("Accepted with success");
+
+    Ok(result)
+}
+
+pub async fn single_accept_step<S>(
+    framed: &mut Framed<S>,
+    acceptor: &mut Acceptor,
+    buf: &mut Vec<u8>,
+) -> ironrdp_acceptor::Result<ironrdp_acceptor::Written>
+where
+    S: FramedWrite + FramedRead,
+{
+    let written = if let Some(next_pdu_hint) = acceptor.next_pdu_hint() {
+        debug!(
+            acceptor.state = acceptor.state.name(),
+            hint = ?next_pdu_hint,
+            "Wait for PDU"
+        );
+
+        let pdu = framed
+            .read_by_hint(next_pdu_hint)
+            .await
+            .map_err(|e| ironrdp_acceptor::Error::new("read frame by hint").with_custom(e))?;
+
+        trace!(length = pdu.len(), "PDU received");
+
+        acceptor.step(&pdu, buf)?
+    } else {
+        acceptor.step_no_input(buf)?
+    };
+
+    if let Some(response_len) = written.size() {
+        let response = &buf[..response_len];
+        trace!(response_len, "Send response");
+        framed
+            .write_all(response)
+            .await
+            .map_err(|e| ironrdp_acceptor::Error::new("write all").with_custom(e))?;
+    }
+
+    Ok(written)
+}
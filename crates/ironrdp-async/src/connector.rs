@@ -1,4 +1,4 @@
-use ironrdp_connector::{ClientConnector, ClientConnectorState, ConnectionResult, Sequence as _, State as _};
+use ironrdp_connector::{ClientConnector, ClientConnectorState, ConnectionOutcome, Sequence as _, State as _};
 
 use crate::framed::{Framed, FramedRead, FramedWrite};
 
@@ -6,6 +6,12 @@ pub struct ShouldUpgrade {
     _priv: (),
 }
 
+impl ShouldUpgrade {
+    pub(crate) fn new() -> Self {
+        Self { _priv: () }
+    }
+}
+
 #[instrument(skip_all)]
 pub async fn connect_begin<S>(
     framed: &mut Framed<S>,
@@ -22,24 +28,30 @@ where
         single_connect_step(framed, connector, &mut buf).await?;
     }
 
-    Ok(ShouldUpgrade { _priv: () })
+    Ok(ShouldUpgrade::new())
 }
 
 pub fn skip_connect_begin(connector: &mut ClientConnector) -> ShouldUpgrade {
     assert!(connector.should_perform_security_upgrade());
-    ShouldUpgrade { _priv: () }
+    ShouldUpgrade::new()
 }
 
 pub struct Upgraded {
     _priv: (),
 }
 
+impl Upgraded {
+    pub(crate) fn new() -> Self {
+        Self { _priv: () }
+    }
+}
+
 #[instrument(skip_all)]
 pub fn mark_as_upgraded(_: ShouldUpgrade, connector: &mut ClientConnector, server_public_key: Vec<u8>) -> Upgraded {
     trace!("marked as upgraded");
     connector.attach_server_public_key(server_public_key);
     connector.mark_security_upgrade_as_done();
-    Upgraded { _priv: () }
+    Upgraded::new()
 }
 
 #[instrument(skip_all)]
@@ -47,7 +59,7 @@ pub async fn connect_finalize<S>(
     _: Upgraded,
     framed: &mut Framed<S>,
     mut connector: ClientConnector,
-) -> ironrdp_connector::Result<ConnectionResult>
+) -> ironrdp_connector::Result<ConnectionOutcome>
 where
     S: FramedRead + FramedWrite,
 {
@@ -61,17 +73,21 @@ where
 
     debug!("Remaining of connection sequence");
 
-    let result = loop {
+    let outcome = loop {
         single_connect_step(framed, &mut connector, &mut buf).await?;
 
-        if let ClientConnectorState::Connected { result } = connector.state {
-            break result;
+        match connector.state {
+            ClientConnectorState::Connected { result } => break ConnectionOutcome::Connected(result),
+            ClientConnectorState::Redirected { redirection } => break ConnectionOutcome::Redirected(redirection),
+            _ => {}
         }
     };
 
-    info!("Connected with success");
+    if let ConnectionOutcome::Connected(_) = outcome {
+        info!("Connected with success");
+    }
 
-    Ok(result)
+    Ok(outcome)
 }
 
 pub async fn single_connect_step<S>(
@@ -1,10 +1,14 @@
 #[macro_use]
 extern crate tracing;
 
+#[cfg(feature = "acceptor")]
+mod acceptor;
 mod connector;
 mod framed;
 mod session;
 
+#[cfg(feature = "acceptor")]
+pub use acceptor::*;
 pub use connector::*;
 pub use framed::*;
 pub use session::*;
@@ -1,5 +1,6 @@
 use std::io;
 use std::num::ParseIntError;
+use std::path::PathBuf;
 use std::str::FromStr;
 
 use anyhow::Context as _;
@@ -17,10 +18,13 @@ pub struct Config {
     pub log_file: String,
     pub destination: Destination,
     pub connector: connector::Config,
+    pub tls_verification: ironrdp_tls::CertificateVerification,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
 enum SecurityProtocol {
+    /// Standard RDP Security (RC4 encryption), without TLS
+    Rdp,
     Ssl,
     Hybrid,
     HybridEx,
@@ -29,6 +33,7 @@ enum SecurityProtocol {
 impl SecurityProtocol {
     fn parse(security_protocol: SecurityProtocol) -> pdu::nego::SecurityProtocol {
         match security_protocol {
+            SecurityProtocol::Rdp => pdu::nego::SecurityProtocol::RDP,
             SecurityProtocol::Ssl => pdu::nego::SecurityProtocol::SSL,
             SecurityProtocol::Hybrid => pdu::nego::SecurityProtocol::HYBRID,
             SecurityProtocol::HybridEx => pdu::nego::SecurityProtocol::HYBRID_EX,
@@ -61,6 +66,74 @@ impl KeyboardType {
     }
 }
 
+/// Bulk compression types supported by the session
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+enum CompressionType {
+    K8,
+    K64,
+    Rdp6,
+    Rdp61,
+}
+
+impl CompressionType {
+    fn parse(compression_type: CompressionType) -> pdu::rdp::client_info::CompressionType {
+        match compression_type {
+            CompressionType::K8 => pdu::rdp::client_info::CompressionType::K8,
+            CompressionType::K64 => pdu::rdp::client_info::CompressionType::K64,
+            CompressionType::Rdp6 => pdu::rdp::client_info::CompressionType::Rdp6,
+            CompressionType::Rdp61 => pdu::rdp::client_info::CompressionType::Rdp61,
+        }
+    }
+}
+
+/// A monitor given as `<WIDTH>x<HEIGHT>[<+|-><LEFT><+|-><TOP>]`, e.g.: `1920x1080-1920+0`
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct MonitorGeometry {
+    width: u32,
+    height: u32,
+    left: i32,
+    top: i32,
+}
+
+impl FromStr for MonitorGeometry {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (width, rest) = s.split_once('x').context("missing monitor height")?;
+        let width = width.parse().context("invalid monitor width")?;
+
+        let (height, left, top) = match rest.find(['+', '-']) {
+            Some(idx) => {
+                let (height, offsets) = rest.split_at(idx);
+                let top_idx = offsets[1..]
+                    .find(['+', '-'])
+                    .map(|idx| idx + 1)
+                    .context("missing monitor top offset")?;
+                let (left, top) = offsets.split_at(top_idx);
+                let left = left
+                    .trim_start_matches('+')
+                    .parse()
+                    .context("invalid monitor left offset")?;
+                let top = top
+                    .trim_start_matches('+')
+                    .parse()
+                    .context("invalid monitor top offset")?;
+                (height, left, top)
+            }
+            None => (rest, 0, 0),
+        };
+
+        let height = height.parse().context("invalid monitor height")?;
+
+        Ok(Self {
+            width,
+            height,
+            left,
+            top,
+        })
+    }
+}
+
 fn parse_hex(input: &str) -> Result<u32, ParseIntError> {
     if input.starts_with("0x") {
         u32::from_str_radix(input.get(2..).unwrap_or(""), 16)
@@ -114,6 +187,14 @@ impl Destination {
         self.port
     }
 
+    /// Returns the same destination with another host, e.g.: the target of a server redirection
+    pub fn with_name(&self, name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            port: self.port,
+        }
+    }
+
     pub fn lookup_addr(&self) -> io::Result<std::net::SocketAddr> {
         use std::net::ToSocketAddrs as _;
 
@@ -175,6 +256,11 @@ struct Args {
     #[clap(long, value_enum, value_parser, default_value_t = KeyboardType::IbmEnhanced)]
     keyboard_type: KeyboardType,
 
+    /// The keyboard layout identifier (KLID) in hexadecimal, e.g.: 407 for German.
+    /// Defaults to the layout of the user language
+    #[clap(long, value_parser = parse_hex)]
+    keyboard_layout: Option<u32>,
+
     /// The keyboard subtype (an original equipment manufacturer-dependent value)
     #[clap(long, value_parser, default_value_t = 0)]
     keyboard_subtype: u32,
@@ -191,11 +277,15 @@ struct Args {
     #[clap(long, value_parser, default_value_t = String::from(""))]
     dig_product_id: String,
 
-    /// Enable AVC444
+    /// Enable the RDP 8 graphics pipeline, rendered in software
+    #[clap(long)]
+    gfx: bool,
+
+    /// Enable AVC444, decoded when the client is built with the `openh264` feature
     #[clap(long, group = "avc")]
     avc444: bool,
 
-    /// Enable H264
+    /// Enable H264, decoded when the client is built with the `openh264` feature
     #[clap(long, group = "avc")]
     h264: bool,
 
@@ -212,7 +302,11 @@ struct Args {
     #[clap(long)]
     lossy_bitmap_compression: bool,
 
-    /// Set required color depth. Currently only 32 and 16 bit color depths are supported
+    /// Enable the bulk compression of the server PDUs, with the given compression type
+    #[clap(long, value_enum, value_parser)]
+    compression: Option<CompressionType>,
+
+    /// Set required color depth. Supported values are 8, 15, 16, 24 and 32
     #[clap(long)]
     color_depth: Option<u32>,
 
@@ -220,6 +314,27 @@ struct Args {
     /// starting from V8 to V10_7
     #[clap(long, value_parser = parse_hex, default_value_t = 0)]
     capabilities: u32,
+
+    /// Verify the server certificate against the CA certificates of this PEM file instead of the system ones
+    #[clap(long, group = "tls_verification")]
+    ca_file: Option<PathBuf>,
+
+    /// Only accept a server certificate with this SHA-256 fingerprint (may be repeated)
+    #[clap(long, group = "tls_verification")]
+    cert_fingerprint: Vec<ironrdp_tls::CertificateFingerprint>,
+
+    /// Trust server certificates on first use, and remember them in this file
+    #[clap(long, group = "tls_verification")]
+    known_hosts: Option<PathBuf>,
+
+    /// Span the session over several monitors, each given as <WIDTH>x<HEIGHT>[<+|-><LEFT><+|-><TOP>]
+    /// (e.g.: `--monitor 1920x1080 --monitor 1920x1080+1920+0`). The monitor at the origin is the primary one
+    #[clap(long = "monitor")]
+    monitors: Vec<MonitorGeometry>,
+
+    /// Do not verify the server certificate at all (insecure)
+    #[clap(long, group = "tls_verification")]
+    ignore_certificate: bool,
 }
 
 impl Config {
@@ -251,8 +366,8 @@ impl Config {
         };
 
         let bitmap = if let Some(color_depth) = args.color_depth {
-            if color_depth != 16 && color_depth != 32 {
-                anyhow::bail!("Invalid color depth. Only 16 and 32 bit color depths are supported.");
+            if ![8, 15, 16, 24, 32].contains(&color_depth) {
+                anyhow::bail!("Invalid color depth. Only 8, 15, 16, 24 and 32 bit color depths are supported.");
             }
 
             if color_depth != 32 && args.lossy_bitmap_compression {
@@ -267,7 +382,7 @@ impl Config {
             None
         };
 
-        let graphics = if args.avc444 || args.h264 {
+        let graphics = if args.gfx || args.avc444 || args.h264 {
             Some(connector::GraphicsConfig {
                 avc444: args.avc444,
                 h264: args.h264,
@@ -279,11 +394,48 @@ impl Config {
             None
         };
 
+        let monitors = args
+            .monitors
+            .iter()
+            .map(|monitor| connector::MonitorConfig {
+                left: monitor.left,
+                top: monitor.top,
+                width: monitor.width,
+                height: monitor.height,
+                is_primary: monitor.left == 0 && monitor.top == 0,
+                physical_width: 0,
+                physical_height: 0,
+                orientation: pdu::gcc::MonitorOrientation::Landscape,
+                desktop_scale_factor: 100,
+                device_scale_factor: 100,
+            })
+            .collect();
+
+        let tls_verification = if args.ignore_certificate {
+            ironrdp_tls::CertificateVerification::DangerousAcceptAny
+        } else if let Some(ca_file) = args.ca_file {
+            let pem = std::fs::read(&ca_file).with_context(|| format!("failed to read {}", ca_file.display()))?;
+            ironrdp_tls::CertificateVerification::custom_ca_from_pem(&pem).context("invalid CA file")?
+        } else if !args.cert_fingerprint.is_empty() {
+            ironrdp_tls::CertificateVerification::Fingerprints(args.cert_fingerprint)
+        } else if let Some(known_hosts) = args.known_hosts {
+            let known_hosts = ironrdp_tls::KnownHosts::load(known_hosts).context("failed to load known hosts")?;
+            let verifier = ironrdp_tls::TrustOnFirstUse::new(known_hosts, prompt_certificate_trust);
+            ironrdp_tls::CertificateVerification::Custom(std::sync::Arc::new(verifier))
+        } else {
+            ironrdp_tls::CertificateVerification::SystemRoots
+        };
+
         let connector = connector::Config {
             username,
             password,
             domain: args.domain,
             security_protocol: SecurityProtocol::parse(args.security_protocol),
+            keyboard_layout: args.keyboard_layout.unwrap_or_else(|| {
+                whoami::lang()
+                    .find_map(|lang| ironrdp::input::keyboard_layout::klid_from_locale(&lang))
+                    .unwrap_or(0)
+            }),
             keyboard_type: KeyboardType::parse(args.keyboard_type),
             keyboard_subtype: args.keyboard_subtype,
             keyboard_functional_keys_count: args.keyboard_functional_keys_count,
@@ -313,12 +465,45 @@ impl Config {
                 whoami::Platform::Android => MajorPlatformType::Android,
                 _ => MajorPlatformType::Unspecified,
             },
+            enable_audio_playback: false,
+            pointer_cache_size: 32,
+            compression_type: args.compression.map(CompressionType::parse),
+            timezone: None,
+            performance_flags: pdu::rdp::client_info::PerformanceFlags::empty(),
+            standard_rdp_security: args.security_protocol == SecurityProtocol::Rdp,
+            static_channels: Vec::new(),
+            monitors,
         };
 
         Ok(Self {
             log_file: args.log_file,
             destination,
             connector,
+            tls_verification,
         })
     }
 }
+
+fn prompt_certificate_trust(
+    server_name: &str,
+    fingerprint: &ironrdp_tls::CertificateFingerprint,
+    status: ironrdp_tls::HostStatus,
+) -> bool {
+    let message = match status {
+        ironrdp_tls::HostStatus::Mismatch { known } => format!(
+            "WARNING: the certificate of {server_name} changed (was {known}, is now {fingerprint}). Trust the new one?"
+        ),
+        ironrdp_tls::HostStatus::Unknown | ironrdp_tls::HostStatus::Trusted => {
+            format!("The certificate of {server_name} is not known yet ({fingerprint}). Trust it?")
+        }
+    };
+
+    // The verifier is called by rustls in the middle of the TLS handshake, on a runtime worker thread:
+    // let the runtime move its other tasks elsewhere while the prompt blocks on stdin
+    tokio::task::block_in_place(|| {
+        inquire::Confirm::new(&message)
+            .with_default(false)
+            .prompt()
+            .unwrap_or(false)
+    })
+}
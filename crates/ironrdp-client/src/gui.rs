@@ -1,6 +1,10 @@
+use std::sync::Arc;
+
 use anyhow::Context as _;
+use ironrdp::graphics::pointer::DecodedPointer;
 use softbuffer::GraphicsContext;
 use tokio::sync::mpsc;
+use winit::dpi::PhysicalPosition;
 use winit::event::{self, Event, WindowEvent};
 use winit::event_loop::{ControlFlow, EventLoop, EventLoopBuilder};
 use winit::window::{Window, WindowBuilder};
@@ -48,7 +52,12 @@ impl GuiContext {
         };
         let mut image_buffer = vec![0; usize::from(image_width) * usize::from(image_height)];
 
+        // winit can’t display custom cursors: the server pointer is drawn on top of the image instead
+        let mut pointer = Pointer::Default;
+        let mut pointer_position = (0, 0);
+
         let mut input_database = ironrdp::input::Database::new();
+        let mut touch_database = ironrdp::input::touch::TouchDatabase::new(ironrdp::session::MAX_TOUCH_CONTACTS);
 
         event_loop.run(move |event, _, control_flow| {
             *control_flow = ControlFlow::Wait;
@@ -56,6 +65,8 @@ impl GuiContext {
             let image_width = &mut image_width;
             let image_height = &mut image_height;
             let image_buffer = &mut image_buffer;
+            let pointer = &mut pointer;
+            let pointer_position = &mut pointer_position;
 
             match event {
                 Event::WindowEvent { window_id, event } if window_id == window.id() => match event {
@@ -75,6 +86,15 @@ impl GuiContext {
                         // TODO: Unicode mode
                     }
                     WindowEvent::KeyboardInput { input, .. } => {
+                        // On Linux, winit reports evdev keycodes (for both X11 and Wayland) while Windows
+                        // already uses the RDP scancodes
+                        #[cfg(target_os = "linux")]
+                        let Some(scancode) = ironrdp::input::Scancode::from_evdev_keycode(input.scancode) else {
+                            debug!(keycode = input.scancode, "Unmapped key");
+                            return;
+                        };
+
+                        #[cfg(not(target_os = "linux"))]
                         let scancode = ironrdp::input::Scancode::from_u16(u16::try_from(input.scancode).unwrap());
 
                         let operation = match input.state {
@@ -113,9 +133,21 @@ impl GuiContext {
                         send_fast_path_events(&input_event_sender, input_events);
                     }
                     WindowEvent::CursorMoved { position, .. } => {
+                        *pointer_position = (position.x as u16, position.y as u16);
+
+                        if let Some(bitmap) = pointer.bitmap() {
+                            present(
+                                &mut graphics_context,
+                                image_buffer,
+                                *image_width,
+                                *image_height,
+                                Some((bitmap, *pointer_position)),
+                            );
+                        }
+
                         let operation = ironrdp::input::Operation::MouseMove(ironrdp::input::MousePosition {
-                            x: position.x as u16,
-                            y: position.y as u16,
+                            x: pointer_position.0,
+                            y: pointer_position.1,
                         });
 
                         let input_events = input_database.apply(std::iter::once(operation));
@@ -195,17 +227,78 @@ impl GuiContext {
 
                         send_fast_path_events(&input_event_sender, input_events);
                     }
+                    WindowEvent::Touch(touch) => {
+                        use ironrdp::input::touch::{TouchOperation, TouchPoint};
+
+                        let point = TouchPoint {
+                            id: touch.id as u32,
+                            x: touch.location.x as i32,
+                            y: touch.location.y as i32,
+                            // The server expects a pressure between 0 and 1024
+                            pressure: touch.force.map(|force| (force.normalized() * 1024.0) as u32),
+                        };
+
+                        let operation = match touch.phase {
+                            event::TouchPhase::Started => TouchOperation::Down(point),
+                            event::TouchPhase::Moved => TouchOperation::Move(point),
+                            event::TouchPhase::Ended => TouchOperation::Up(point.id),
+                            event::TouchPhase::Cancelled => TouchOperation::Cancel(point.id),
+                        };
+
+                        let frames = touch_database.apply(std::iter::once(operation));
+
+                        if !frames.is_empty() {
+                            let _ = input_event_sender.send(RdpInputEvent::Touch(frames));
+                        }
+                    }
                     _ => {}
                 },
                 Event::RedrawRequested(window_id) if window_id == window.id() => {
-                    graphics_context.set_buffer(image_buffer, *image_width, *image_height);
+                    present(
+                        &mut graphics_context,
+                        image_buffer,
+                        *image_width,
+                        *image_height,
+                        pointer.bitmap().map(|bitmap| (bitmap, *pointer_position)),
+                    );
                 }
                 Event::UserEvent(RdpOutputEvent::Image { buffer, width, height }) => {
                     *image_buffer = buffer;
                     *image_width = width;
                     *image_height = height;
 
-                    graphics_context.set_buffer(image_buffer, width, height);
+                    present(
+                        &mut graphics_context,
+                        image_buffer,
+                        width,
+                        height,
+                        pointer.bitmap().map(|bitmap| (bitmap, *pointer_position)),
+                    );
+                }
+                Event::UserEvent(RdpOutputEvent::PointerDefault) => {
+                    *pointer = Pointer::Default;
+                    window.set_cursor_visible(true);
+                    window.request_redraw();
+                }
+                Event::UserEvent(RdpOutputEvent::PointerHidden) => {
+                    *pointer = Pointer::Hidden;
+                    window.set_cursor_visible(false);
+                    window.request_redraw();
+                }
+                Event::UserEvent(RdpOutputEvent::PointerBitmap(bitmap)) => {
+                    *pointer = Pointer::Bitmap(bitmap);
+                    window.set_cursor_visible(false);
+                    window.request_redraw();
+                }
+                Event::UserEvent(RdpOutputEvent::PointerPosition { x, y }) => {
+                    *pointer_position = (x, y);
+
+                    // Not supported on all platforms (e.g.: Wayland)
+                    if let Err(error) = window.set_cursor_position(PhysicalPosition::new(x, y)) {
+                        debug!(%error, "Failed to move the cursor");
+                    }
+
+                    window.request_redraw();
                 }
                 Event::UserEvent(RdpOutputEvent::ConnectionFailure(error)) => {
                     error!(%error);
@@ -248,3 +341,89 @@ fn send_fast_path_events(
         let _ = input_event_sender.send(RdpInputEvent::FastPath(input_events));
     }
 }
+
+enum Pointer {
+    /// The system pointer is displayed
+    Default,
+    Hidden,
+    Bitmap(Arc<DecodedPointer>),
+}
+
+impl Pointer {
+    fn bitmap(&self) -> Option<&DecodedPointer> {
+        match self {
+            Self::Bitmap(bitmap) => Some(bitmap),
+            Self::Default | Self::Hidden => None,
+        }
+    }
+}
+
+/// Displays the image, with the pointer drawn at the given position
+fn present(
+    graphics_context: &mut GraphicsContext,
+    image_buffer: &[u32],
+    width: u16,
+    height: u16,
+    pointer: Option<(&DecodedPointer, (u16, u16))>,
+) {
+    let Some((pointer, (x, y))) = pointer else {
+        graphics_context.set_buffer(image_buffer, width, height);
+        return;
+    };
+
+    // PERF: the whole image is copied on each pointer move
+    let mut buffer = image_buffer.to_vec();
+
+    let left = i32::from(x) - i32::from(pointer.hotspot_x);
+    let top = i32::from(y) - i32::from(pointer.hotspot_y);
+
+    for (row, pixels) in pointer
+        .bitmap_data
+        .chunks_exact(usize::from(pointer.width) * 4)
+        .enumerate()
+    {
+        let Ok(image_y) = usize::try_from(top + row as i32) else {
+            continue;
+        };
+
+        if image_y >= usize::from(height) {
+            break;
+        }
+
+        for (column, pixel) in pixels.chunks_exact(4).enumerate() {
+            let Ok(image_x) = usize::try_from(left + column as i32) else {
+                continue;
+            };
+
+            if image_x >= usize::from(width) {
+                break;
+            }
+
+            let Some(target) = buffer.get_mut(image_y * usize::from(width) + image_x) else {
+                continue;
+            };
+
+            *target = blend(*target, pixel);
+        }
+    }
+
+    graphics_context.set_buffer(&buffer, width, height);
+}
+
+/// Blends a straight alpha RGBA pixel over a 0RGB pixel
+fn blend(target: u32, rgba: &[u8]) -> u32 {
+    let alpha = u32::from(rgba[3]);
+
+    let [_, target_r, target_g, target_b] = target.to_be_bytes();
+
+    let mix = |source: u8, target: u8| -> u8 {
+        ((u32::from(source) * alpha + u32::from(target) * (255 - alpha)) / 255) as u8
+    };
+
+    u32::from_be_bytes([
+        0,
+        mix(rgba[0], target_r),
+        mix(rgba[1], target_g),
+        mix(rgba[2], target_b),
+    ])
+}
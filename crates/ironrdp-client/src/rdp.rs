@@ -1,5 +1,11 @@
+use std::sync::Arc;
+
 use ironrdp::graphics::image_processing::PixelFormat;
+use ironrdp::graphics::pointer::DecodedPointer;
 use ironrdp::pdu::input::fast_path::FastPathInputEvent;
+use ironrdp::pdu::rdp::server_redirection::ServerRedirectionPdu;
+use ironrdp::pdu::rdp::session_info::ServerAutoReconnect;
+use ironrdp::pdu::rdp::vc::dvc::rdpei::TouchFrame;
 use ironrdp::session::image::DecodedImage;
 use ironrdp::session::{ActiveStage, ActiveStageOutput};
 use ironrdp::{connector, session};
@@ -14,6 +20,10 @@ use crate::config::Config;
 #[derive(Debug)]
 pub enum RdpOutputEvent {
     Image { buffer: Vec<u32>, width: u16, height: u16 },
+    PointerDefault,
+    PointerHidden,
+    PointerPosition { x: u16, y: u16 },
+    PointerBitmap(Arc<DecodedPointer>),
     ConnectionFailure(connector::Error),
     Terminated(session::Result<()>),
 }
@@ -22,6 +32,7 @@ pub enum RdpOutputEvent {
 pub enum RdpInputEvent {
     Resize { width: u16, height: u16 },
     FastPath(SmallVec<[FastPathInputEvent; 2]>),
+    Touch(Vec<TouchFrame>),
     Close,
 }
 
@@ -39,14 +50,22 @@ pub struct RdpClient {
 
 impl RdpClient {
     pub async fn run(mut self) {
+        let mut auto_reconnect_cookie = None;
+        let mut redirection = None;
+
         loop {
-            let (connection_result, framed) = match connect(&self.config).await {
-                Ok(result) => result,
-                Err(e) => {
-                    let _ = self.event_loop_proxy.send_event(RdpOutputEvent::ConnectionFailure(e));
-                    break;
-                }
-            };
+            let (connection_result, framed) =
+                match connect(&self.config, auto_reconnect_cookie.take(), redirection.take()).await {
+                    Ok((connector::ConnectionOutcome::Connected(result), framed)) => (result, framed),
+                    Ok((connector::ConnectionOutcome::Redirected(server_redirection), _)) => {
+                        redirection = Some(self.redirect(*server_redirection));
+                        continue;
+                    }
+                    Err(e) => {
+                        let _ = self.event_loop_proxy.send_event(RdpOutputEvent::ConnectionFailure(e));
+                        break;
+                    }
+                };
 
             match active_session(
                 framed,
@@ -56,9 +75,24 @@ impl RdpClient {
             )
             .await
             {
-                Ok(RdpControlFlow::ReconnectWithNewSize { width, height }) => {
+                Ok(RdpControlFlow::ReconnectWithNewSize {
+                    width,
+                    height,
+                    auto_reconnect_cookie: cookie,
+                }) => {
                     self.config.connector.desktop_size.width = width;
                     self.config.connector.desktop_size.height = height;
+                    auto_reconnect_cookie = cookie;
+                }
+                Ok(RdpControlFlow::Reconnect {
+                    auto_reconnect_cookie: cookie,
+                }) => {
+                    auto_reconnect_cookie = Some(cookie);
+                }
+                Ok(RdpControlFlow::Redirect {
+                    redirection: server_redirection,
+                }) => {
+                    redirection = Some(self.redirect(*server_redirection));
                 }
                 Ok(RdpControlFlow::TerminatedGracefully) => {
                     let _ = self.event_loop_proxy.send_event(RdpOutputEvent::Terminated(Ok(())));
@@ -71,16 +105,54 @@ impl RdpClient {
             }
         }
     }
+
+    /// Points the configuration to the target of the redirection, which is then provided to the next connection.
+    fn redirect(&mut self, redirection: ServerRedirectionPdu) -> ServerRedirectionPdu {
+        if let Some(target_address) = redirection.target_address() {
+            info!(target_address, "Redirected by the server");
+            self.config.destination = self.config.destination.with_name(target_address);
+        }
+
+        if let Some(username) = &redirection.username {
+            self.config.connector.username = username.clone();
+        }
+
+        if let Some(domain) = &redirection.domain {
+            self.config.connector.domain = Some(domain.clone());
+        }
+
+        redirection
+    }
 }
 
 enum RdpControlFlow {
-    ReconnectWithNewSize { width: u16, height: u16 },
+    ReconnectWithNewSize {
+        width: u16,
+        height: u16,
+        auto_reconnect_cookie: Option<ServerAutoReconnect>,
+    },
+    /// The connection was lost, but the session can be resumed using the auto-reconnect cookie
+    Reconnect {
+        auto_reconnect_cookie: ServerAutoReconnect,
+    },
+    /// The server redirected the client to another server of the farm
+    Redirect {
+        redirection: Box<ServerRedirectionPdu>,
+    },
     TerminatedGracefully,
 }
 
-type UpgradedFramed = ironrdp_tokio::TokioFramed<ironrdp_tls::TlsStream<TcpStream>>;
+trait AsyncReadWrite: tokio::io::AsyncRead + tokio::io::AsyncWrite {}
 
-async fn connect(config: &Config) -> connector::Result<(connector::ConnectionResult, UpgradedFramed)> {
+impl<T> AsyncReadWrite for T where T: tokio::io::AsyncRead + tokio::io::AsyncWrite {}
+
+type UpgradedFramed = ironrdp_tokio::TokioFramed<Box<dyn AsyncReadWrite + Unpin + Send>>;
+
+async fn connect(
+    config: &Config,
+    auto_reconnect_cookie: Option<ServerAutoReconnect>,
+    redirection: Option<ServerRedirectionPdu>,
+) -> connector::Result<(connector::ConnectionOutcome, UpgradedFramed)> {
     let server_addr = config
         .destination
         .lookup_addr()
@@ -97,24 +169,47 @@ async fn connect(config: &Config) -> connector::Result<(connector::ConnectionRes
         .with_server_name(&config.destination)
         .with_credssp_client_factory(Box::new(RequestClientFactory));
 
-    let should_upgrade = ironrdp_tokio::connect_begin(&mut framed, &mut connector).await?;
+    if let Some(auto_reconnect_cookie) = auto_reconnect_cookie {
+        connector.attach_auto_reconnect_cookie(auto_reconnect_cookie);
+    }
 
-    debug!("TLS upgrade");
+    if let Some(redirection) = redirection {
+        connector.attach_redirection(redirection);
+    }
+
+    let should_upgrade = ironrdp_tokio::connect_begin(&mut framed, &mut connector).await?;
 
     // Ensure there is no leftover
     let initial_stream = framed.into_inner_no_leftover();
 
-    let (upgraded_stream, server_public_key) = ironrdp_tls::upgrade(initial_stream, config.destination.name())
-        .await
-        .map_err(|e| connector::Error::new("TLS upgrade").with_custom(e))?;
+    let standard_rdp_security = matches!(
+        connector.state,
+        connector::ClientConnectorState::EnhancedSecurityUpgrade { selected_protocol }
+            if selected_protocol == ironrdp::pdu::nego::SecurityProtocol::RDP
+    );
+
+    let (upgraded_stream, server_public_key): (Box<dyn AsyncReadWrite + Unpin + Send>, _) = if standard_rdp_security {
+        debug!("No TLS upgrade with standard RDP security");
+
+        (Box::new(initial_stream), Vec::new())
+    } else {
+        debug!("TLS upgrade");
+
+        let (upgraded_stream, server_public_key) =
+            ironrdp_tls::upgrade(initial_stream, config.destination.name(), &config.tls_verification)
+                .await
+                .map_err(|e| connector::Error::new("TLS upgrade").with_custom(e))?;
+
+        (Box::new(upgraded_stream), server_public_key)
+    };
 
     let upgraded = ironrdp_tokio::mark_as_upgraded(should_upgrade, &mut connector, server_public_key);
 
     let mut upgraded_framed = ironrdp_tokio::TokioFramed::new(upgraded_stream);
 
-    let connection_result = ironrdp_tokio::connect_finalize(upgraded, &mut upgraded_framed, connector).await?;
+    let connection_outcome = ironrdp_tokio::connect_finalize(upgraded, &mut upgraded_framed, connector).await?;
 
-    Ok((connection_result, upgraded_framed))
+    Ok((connection_outcome, upgraded_framed))
 }
 
 async fn active_session(
@@ -129,12 +224,30 @@ async fn active_session(
         connection_result.desktop_size.height,
     );
 
-    let mut active_stage = ActiveStage::new(connection_result, None);
+    if let Some(monitor_layout) = &connection_result.connection_activation.monitor_layout {
+        image.set_monitor_layout(monitor_layout.clone());
+    }
+
+    let mut active_stage = ActiveStage::new(connection_result, None, Vec::new(), Vec::new());
 
     'outer: loop {
         tokio::select! {
             frame = framed.read_pdu() => {
-                let (action, payload) = frame.map_err(|e| session::Error::new("read frame").with_custom(e))?;
+                let (action, payload) = match frame {
+                    Ok(frame) => frame,
+                    Err(e) => {
+                        // Resume the same session if the server gave us the means to
+                        if let Some(auto_reconnect_cookie) = active_stage.auto_reconnect_cookie() {
+                            warn!(error = %e, "Connection lost, attempting to auto-reconnect");
+
+                            return Ok(RdpControlFlow::Reconnect {
+                                auto_reconnect_cookie: auto_reconnect_cookie.clone(),
+                            });
+                        }
+
+                        return Err(session::Error::new("read frame").with_custom(e));
+                    }
+                };
                 trace!(?action, frame_length = payload.len(), "Frame received");
 
                 let outputs = active_stage.process(&mut image, action, &payload)?;
@@ -162,6 +275,37 @@ async fn active_session(
                                 })
                                 .map_err(|e| session::Error::new("event_loop_proxy").with_custom(e))?;
                         }
+                        ActiveStageOutput::PointerDefault => {
+                            event_loop_proxy
+                                .send_event(RdpOutputEvent::PointerDefault)
+                                .map_err(|e| session::Error::new("event_loop_proxy").with_custom(e))?;
+                        }
+                        ActiveStageOutput::PointerHidden => {
+                            event_loop_proxy
+                                .send_event(RdpOutputEvent::PointerHidden)
+                                .map_err(|e| session::Error::new("event_loop_proxy").with_custom(e))?;
+                        }
+                        ActiveStageOutput::PointerPosition { x, y } => {
+                            event_loop_proxy
+                                .send_event(RdpOutputEvent::PointerPosition { x, y })
+                                .map_err(|e| session::Error::new("event_loop_proxy").with_custom(e))?;
+                        }
+                        ActiveStageOutput::PointerBitmap(pointer) => {
+                            event_loop_proxy
+                                .send_event(RdpOutputEvent::PointerBitmap(pointer))
+                                .map_err(|e| session::Error::new("event_loop_proxy").with_custom(e))?;
+                        }
+                        ActiveStageOutput::Reactivated(desktop_size) => {
+                            // The image was resized by the active stage, the next graphics update will reflect it
+                            info!(desktop_size.width, desktop_size.height, "Session reactivated");
+                        }
+                        ActiveStageOutput::MonitorLayout(monitor_layout) => {
+                            // The window displays the whole virtual desktop, the viewports are only informative
+                            info!(monitor_count = monitor_layout.len(), "Monitor layout updated");
+                        }
+                        ActiveStageOutput::ServerRedirection(redirection) => {
+                            return Ok(RdpControlFlow::Redirect { redirection });
+                        }
                         ActiveStageOutput::Terminate => break 'outer,
                     }
                 }
@@ -171,10 +315,6 @@ async fn active_session(
 
                 match input_event {
                     RdpInputEvent::Resize { mut width, mut height } => {
-                        // TODO: Add support for Display Update Virtual Channel Extension
-                        // https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpedisp/d2954508-f487-48bc-8731-39743e0854a9
-                        // One approach when this extension is not available is to perform a connection from scratch again.
-
                         // Find the last resize event
                         while let Ok(newer_event) = input_event_receiver.try_recv() {
                             if let RdpInputEvent::Resize { width: newer_width, height: newer_height } = newer_event {
@@ -183,27 +323,47 @@ async fn active_session(
                             }
                         }
 
-                        // TODO: use the "auto-reconnect cookie": https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpbcgr/15b0d1c9-2891-4adb-a45e-deb4aeeeab7c
-
                         info!(width, height, "resize event");
 
-                        return Ok(RdpControlFlow::ReconnectWithNewSize { width, height })
+                        let mut frame = Vec::new();
+
+                        match active_stage.encode_resize(&mut frame, u32::from(width), u32::from(height), None, None) {
+                            Some(written) => {
+                                let written = written?;
+                                framed.write_all(&frame[..written]).await.map_err(|e| session::Error::new("write resize request").with_custom(e))?;
+                            }
+                            None => {
+                                // The Display Control Virtual Channel Extension is not available:
+                                // the only option left is to reconnect, resuming the same session when possible.
+                                return Ok(RdpControlFlow::ReconnectWithNewSize {
+                                    width,
+                                    height,
+                                    auto_reconnect_cookie: active_stage.auto_reconnect_cookie().cloned(),
+                                })
+                            }
+                        }
                     },
                     RdpInputEvent::FastPath(events) => {
-                        use ironrdp::pdu::input::fast_path::FastPathInput;
-                        use ironrdp::pdu::PduParsing as _;
-
                         trace!(?events);
 
+                        let mut frame = Vec::new();
                         // PERF: unnecessary copy
-                        let fastpath_input = FastPathInput(events.into_vec());
+                        let written = active_stage.encode_fastpath_input(&mut frame, events.into_vec())?;
 
-                        let mut frame = Vec::new();
-                        fastpath_input
-                            .to_buffer(&mut frame)
-                            .map_err(|e| session::Error::new("FastPathInput encode").with_custom(e))?;
+                        framed.write_all(&frame[..written]).await.map_err(|e| session::Error::new("write FastPathInput PDU").with_custom(e))?;
+                    }
+                    RdpInputEvent::Touch(frames) => {
+                        trace!(?frames);
+
+                        // The server may suspend touch input at any time
+                        if active_stage.touch_input_ready() {
+                            let mut frame = Vec::new();
 
-                        framed.write_all(&frame).await.map_err(|e| session::Error::new("write FastPathInput PDU").with_custom(e))?;
+                            if let Some(written) = active_stage.encode_touch(&mut frame, frames) {
+                                let written = written?;
+                                framed.write_all(&frame[..written]).await.map_err(|e| session::Error::new("write touch event").with_custom(e))?;
+                            }
+                        }
                     }
                     RdpInputEvent::Close => {
                         // TODO: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpbcgr/27915739-8f77-487e-9927-55008af7fd68
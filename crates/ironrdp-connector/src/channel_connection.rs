@@ -125,7 +125,8 @@ impl Sequence for ChannelConnectionSequence {
 
                 debug!(message = ?attach_user_confirm, user_channel_id, "Received");
 
-                debug_assert!(!self.channel_ids.is_empty());
+                // The user channel is joined first, followed by the I/O channel and the static channels
+                self.channel_ids.insert(0, user_channel_id);
 
                 (
                     Written::Nothing,
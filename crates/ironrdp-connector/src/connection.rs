@@ -1,14 +1,19 @@
 use std::mem;
 use std::net::SocketAddr;
 
-use ironrdp_pdu::rdp::capability_sets::CapabilitySet;
+use ironrdp_pdu::rdp::server_redirection::ServerRedirectionPdu;
+use ironrdp_pdu::rdp::session_info::ServerAutoReconnect;
+use ironrdp_pdu::rdp::standard_security::{EncryptionContext, SecurityExchangePdu, CLIENT_RANDOM_SIZE};
 use ironrdp_pdu::{gcc, mcs, nego, rdp, PduHint};
+use rand_core::{OsRng, RngCore as _};
 use sspi::credssp;
 
 use crate::channel_connection::{ChannelConnectionSequence, ChannelConnectionState};
-use crate::connection_finalization::ConnectionFinalizationSequence;
+use crate::connection_activation::{ConnectionActivationSequence, ConnectionActivationState};
 use crate::license_exchange::LicenseExchangeSequence;
-use crate::{legacy, Config, DesktopSize, Error, Result, Sequence, ServerName, State, StaticChannels, Written};
+use crate::{
+    legacy, Config, DesktopSize, Error, Result, Sequence, ServerName, StandardSecurity, State, StaticChannels, Written,
+};
 
 #[derive(Clone, Copy, Debug)]
 pub struct CredsspTsRequestHint;
@@ -44,6 +49,20 @@ pub struct ConnectionResult {
     pub static_channels: StaticChannels,
     pub desktop_size: DesktopSize,
     pub graphics_config: Option<crate::GraphicsConfig>,
+    /// Finalized connection activation sequence, to be reused upon Deactivation-Reactivation.
+    pub connection_activation: ConnectionActivationSequence,
+    /// Encryption of the frames when Standard RDP Security is in effect, to be used by the session
+    #[cfg_attr(feature = "arbitrary", arbitrary(default))]
+    pub standard_security: Option<StandardSecurity>,
+}
+
+/// Terminal outcome of the connection sequence
+#[derive(Debug, Clone)]
+#[allow(clippy::large_enum_variant)] // Returned only once, at the end of the connection sequence
+pub enum ConnectionOutcome {
+    Connected(ConnectionResult),
+    /// A connection broker redirected the client, which should reconnect to the target of the redirection
+    Redirected(Box<ServerRedirectionPdu>),
 }
 
 #[derive(Default, Debug)]
@@ -78,12 +97,14 @@ pub enum ClientConnectorState {
     },
     ChannelConnection {
         selected_protocol: nego::SecurityProtocol,
+        server_security_data: gcc::ServerSecurityData,
         io_channel_id: u16,
         static_channels: StaticChannels,
         channel_connection: ChannelConnectionSequence,
     },
     RdpSecurityCommencement {
         selected_protocol: nego::SecurityProtocol,
+        server_security_data: gcc::ServerSecurityData,
         io_channel_id: u16,
         user_channel_id: u16,
         static_channels: StaticChannels,
@@ -113,17 +134,21 @@ pub enum ClientConnectorState {
         io_channel_id: u16,
         user_channel_id: u16,
         static_channels: StaticChannels,
+        connection_activation: ConnectionActivationSequence,
     },
     ConnectionFinalization {
         io_channel_id: u16,
         user_channel_id: u16,
         static_channels: StaticChannels,
-        desktop_size: DesktopSize,
-        connection_finalization: ConnectionFinalizationSequence,
+        connection_activation: ConnectionActivationSequence,
     },
     Connected {
         result: ConnectionResult,
     },
+    /// A connection broker redirected the client, which should reconnect to the target of the redirection
+    Redirected {
+        redirection: Box<ServerRedirectionPdu>,
+    },
 }
 
 impl State for ClientConnectorState {
@@ -147,11 +172,12 @@ impl State for ClientConnectorState {
             Self::CapabilitiesExchange { .. } => "CapabilitiesExchange",
             Self::ConnectionFinalization { .. } => "ConnectionFinalization",
             Self::Connected { .. } => "Connected",
+            Self::Redirected { .. } => "Redirected",
         }
     }
 
     fn is_terminal(&self) -> bool {
-        matches!(self, Self::Connected { .. })
+        matches!(self, Self::Connected { .. } | Self::Redirected { .. })
     }
 
     fn as_any(&self) -> &dyn core::any::Any {
@@ -168,6 +194,18 @@ pub struct ClientConnector {
     pub server_name: Option<ServerName>,
     pub network_client_factory: Option<Box<dyn sspi::network_client::NetworkClientFactory>>,
     pub server_public_key: Option<Vec<u8>>,
+    /// Auto-reconnect packet received from the server during a previous connection
+    pub auto_reconnect_cookie: Option<ServerAutoReconnect>,
+    /// Encryption of the frames, once the Security Exchange PDU is sent when using Standard RDP Security
+    #[cfg_attr(feature = "arbitrary", arbitrary(default))]
+    pub standard_security: Option<StandardSecurity>,
+    /// Server Redirection PDU received during a previous connection, when reconnecting to its target
+    #[cfg_attr(feature = "arbitrary", arbitrary(default))]
+    pub redirection: Option<ServerRedirectionPdu>,
+    /// Client random sent in the Security Exchange PDU when using Standard RDP Security, also used to compute the
+    /// auto-reconnect verifier
+    #[cfg_attr(feature = "arbitrary", arbitrary(default))]
+    pub client_random: Option<[u8; CLIENT_RANDOM_SIZE]>,
 }
 
 impl ClientConnector {
@@ -179,6 +217,10 @@ impl ClientConnector {
             server_name: None,
             network_client_factory: None,
             server_public_key: None,
+            auto_reconnect_cookie: None,
+            standard_security: None,
+            redirection: None,
+            client_random: None,
         }
     }
 
@@ -219,6 +261,30 @@ impl ClientConnector {
         self.network_client_factory = Some(network_client_factory);
     }
 
+    /// Resumes the session the auto-reconnect packet was issued for, without prompting for credentials again
+    pub fn with_auto_reconnect_cookie(mut self, auto_reconnect_cookie: ServerAutoReconnect) -> Self {
+        self.auto_reconnect_cookie = Some(auto_reconnect_cookie);
+        self
+    }
+
+    /// Resumes the session the auto-reconnect packet was issued for, without prompting for credentials again
+    pub fn attach_auto_reconnect_cookie(&mut self, auto_reconnect_cookie: ServerAutoReconnect) {
+        self.auto_reconnect_cookie = Some(auto_reconnect_cookie);
+    }
+
+    /// Sends the routing token and the session ID of the Server Redirection PDU to the target of the redirection.
+    ///
+    /// The credentials provided by the redirection, if any, are not used: the configuration must be updated instead.
+    pub fn with_redirection(mut self, redirection: ServerRedirectionPdu) -> Self {
+        self.redirection = Some(redirection);
+        self
+    }
+
+    /// See [`Self::with_redirection`].
+    pub fn attach_redirection(&mut self, redirection: ServerRedirectionPdu) {
+        self.redirection = Some(redirection);
+    }
+
     pub fn attach_server_public_key(&mut self, server_public_key: Vec<u8>) {
         self.server_public_key = Some(server_public_key);
     }
@@ -241,6 +307,47 @@ impl ClientConnector {
                 | ClientConnectorState::CredsspEarlyUserAuthResult { .. }
         )
     }
+
+    /// Decrypts the input frame and encrypts the output frames around a regular step.
+    ///
+    /// The Client Info PDU already has a security header, and the licensing PDUs sent by the client are not
+    /// encrypted.
+    fn step_with_standard_security(
+        &mut self,
+        mut standard_security: StandardSecurity,
+        input: &[u8],
+        output: &mut Vec<u8>,
+    ) -> Result<Written> {
+        let has_security_header = matches!(self.state, ClientConnectorState::SecureSettingsExchange { .. });
+        let encrypts_output = !matches!(self.state, ClientConnectorState::LicensingExchange { .. });
+
+        let input = if input.is_empty() {
+            Vec::new()
+        } else {
+            standard_security.decrypt_frame(input)?
+        };
+
+        let written = self.step(&input, output)?;
+
+        let written = match written.size() {
+            Some(size) if encrypts_output => {
+                let frames = output[..size].to_vec();
+                output.clear();
+
+                let size = standard_security.encrypt_frames(&frames, has_security_header, output)?;
+                Written::from_size(size)?
+            }
+            _ => written,
+        };
+
+        if let ClientConnectorState::Connected { result } = &mut self.state {
+            result.standard_security = Some(standard_security);
+        } else {
+            self.standard_security = Some(standard_security);
+        }
+
+        Ok(written)
+    }
 }
 
 impl Sequence for ClientConnector {
@@ -261,12 +368,14 @@ impl Sequence for ClientConnector {
             ClientConnectorState::ConnectTimeAutoDetection { .. } => None,
             ClientConnectorState::LicensingExchange { license_exchange, .. } => license_exchange.next_pdu_hint(),
             ClientConnectorState::MultitransportBootstrapping { .. } => None,
-            ClientConnectorState::CapabilitiesExchange { .. } => Some(&ironrdp_pdu::X224_HINT),
+            ClientConnectorState::CapabilitiesExchange {
+                connection_activation, ..
+            } => connection_activation.next_pdu_hint(),
             ClientConnectorState::ConnectionFinalization {
-                connection_finalization,
-                ..
-            } => connection_finalization.next_pdu_hint(),
+                connection_activation, ..
+            } => connection_activation.next_pdu_hint(),
             ClientConnectorState::Connected { .. } => None,
+            ClientConnectorState::Redirected { .. } => None,
         }
     }
 
@@ -275,6 +384,10 @@ impl Sequence for ClientConnector {
     }
 
     fn step(&mut self, input: &[u8], output: &mut Vec<u8>) -> Result<Written> {
+        if let Some(standard_security) = self.standard_security.take() {
+            return self.step_with_standard_security(standard_security, input, output);
+        }
+
         let (written, next_state) = match mem::take(&mut self.state) {
             // Invalid state
             ClientConnectorState::Consumed => {
@@ -284,8 +397,18 @@ impl Sequence for ClientConnector {
             //== Connection Initiation ==//
             // Exchange supported security protocols and a few other connection flags.
             ClientConnectorState::ConnectionInitiationSendRequest => {
+                // The connection broker finds the session to reconnect to using the routing token
+                let nego_data = match self
+                    .redirection
+                    .as_ref()
+                    .and_then(|redirection| redirection.load_balance_info.as_deref())
+                {
+                    Some(load_balance_info) => nego::NegoRequestData::routing_token(routing_token(load_balance_info)),
+                    None => nego::NegoRequestData::cookie(self.config.username.clone()),
+                };
+
                 let connection_request = nego::ConnectionRequest {
-                    nego_data: Some(nego::NegoRequestData::cookie(self.config.username.clone())),
+                    nego_data: Some(nego_data),
                     flags: nego::RequestFlags::empty(),
                     protocol: self.config.security_protocol,
                 };
@@ -327,8 +450,8 @@ impl Sequence for ClientConnector {
             }
 
             //== Upgrade to Enhanced RDP Security ==//
-            // NOTE: we assume the selected protocol is never the standard RDP security (RC4).
             // User code should match this variant and perform the appropriate upgrade (TLS handshake, etc).
+            // No upgrade is performed when the selected protocol is the standard RDP security (RC4).
             ClientConnectorState::EnhancedSecurityUpgrade { selected_protocol } => {
                 let next_state = if selected_protocol.contains(nego::SecurityProtocol::HYBRID)
                     || selected_protocol.contains(nego::SecurityProtocol::HYBRID_EX)
@@ -454,7 +577,7 @@ impl Sequence for ClientConnector {
             //== Basic Settings Exchange ==//
             // Exchange basic settings including Core Data, Security Data and Network Data.
             ClientConnectorState::BasicSettingsExchangeSendInitial { selected_protocol } => {
-                let client_gcc_blocks = create_gcc_blocks(&self.config, selected_protocol);
+                let client_gcc_blocks = create_gcc_blocks(&self.config, selected_protocol, self.redirection.as_ref())?;
                 let connect_initial = mcs::ConnectInitial::with_gcc_blocks(client_gcc_blocks);
 
                 debug!(message = ?connect_initial, "Send");
@@ -512,6 +635,7 @@ impl Sequence for ClientConnector {
                     Written::Nothing,
                     ClientConnectorState::ChannelConnection {
                         selected_protocol,
+                        server_security_data: server_gcc_blocks.security,
                         io_channel_id,
                         static_channels,
                         channel_connection: ChannelConnectionSequence::new(io_channel_id, static_channel_ids),
@@ -523,6 +647,7 @@ impl Sequence for ClientConnector {
             // Connect every individual channel.
             ClientConnectorState::ChannelConnection {
                 selected_protocol,
+                server_security_data,
                 io_channel_id,
                 static_channels,
                 mut channel_connection,
@@ -535,6 +660,7 @@ impl Sequence for ClientConnector {
 
                     ClientConnectorState::RdpSecurityCommencement {
                         selected_protocol,
+                        server_security_data,
                         io_channel_id,
                         user_channel_id,
                         static_channels,
@@ -542,6 +668,7 @@ impl Sequence for ClientConnector {
                 } else {
                     ClientConnectorState::ChannelConnection {
                         selected_protocol,
+                        server_security_data,
                         io_channel_id,
                         static_channels,
                         channel_connection,
@@ -553,19 +680,47 @@ impl Sequence for ClientConnector {
 
             //== RDP Security Commencement ==//
             // When using standard RDP security (RC4), a Security Exchange PDU is sent at this point.
-            // NOTE: IronRDP does not support RC4 security.
+            // All the following PDUs are then encrypted, unless the server selected no encryption method.
             ClientConnectorState::RdpSecurityCommencement {
                 selected_protocol,
+                server_security_data,
                 io_channel_id,
                 user_channel_id,
                 static_channels,
             } => {
-                if selected_protocol == nego::SecurityProtocol::RDP {
-                    return Err(Error::new("standard RDP Security (RC4 encryption) is not supported"));
-                }
+                let written = if selected_protocol != nego::SecurityProtocol::RDP {
+                    Written::Nothing
+                } else if !self.config.standard_rdp_security {
+                    return Err(Error::new("standard RDP Security (RC4 encryption) is not enabled"));
+                } else if server_security_data.encryption_method.is_empty() {
+                    warn!("Server selected no encryption method");
+                    Written::Nothing
+                } else {
+                    let server_random = server_security_data
+                        .server_random
+                        .ok_or(Error::new("server random is missing"))?;
+
+                    let mut client_random = [0u8; CLIENT_RANDOM_SIZE];
+                    OsRng.fill_bytes(&mut client_random);
+
+                    let security_exchange =
+                        SecurityExchangePdu::new(&client_random, &server_security_data.server_cert)?;
+                    let encryption_context =
+                        EncryptionContext::new(server_security_data.encryption_method, &client_random, &server_random)?;
+
+                    debug!(message = ?security_exchange, "Send");
+
+                    let written =
+                        legacy::encode_send_data_request(user_channel_id, io_channel_id, &security_exchange, output)?;
+
+                    self.standard_security = Some(StandardSecurity::new(encryption_context));
+                    self.client_random = Some(client_random);
+
+                    Written::from_size(written)?
+                };
 
                 (
-                    Written::Nothing,
+                    written,
                     ClientConnectorState::SecureSettingsExchange {
                         io_channel_id,
                         user_channel_id,
@@ -586,7 +741,19 @@ impl Sequence for ClientConnector {
                     .as_ref()
                     .ok_or(Error::new("server address is missing"))?;
 
-                let client_info = create_client_info_pdu(&self.config, routing_addr);
+                let client_random = self
+                    .client_random
+                    .as_ref()
+                    .map_or(ENHANCED_SECURITY_CLIENT_RANDOM.as_slice(), |client_random| {
+                        client_random.as_slice()
+                    });
+
+                let client_info = create_client_info_pdu(
+                    &self.config,
+                    routing_addr,
+                    self.auto_reconnect_cookie.as_ref(),
+                    client_random,
+                );
 
                 debug!(message = ?client_info, "Send");
 
@@ -663,6 +830,11 @@ impl Sequence for ClientConnector {
                     io_channel_id,
                     user_channel_id,
                     static_channels,
+                    connection_activation: ConnectionActivationSequence::new(
+                        self.config.clone(),
+                        io_channel_id,
+                        user_channel_id,
+                    ),
                 },
             ),
 
@@ -672,65 +844,25 @@ impl Sequence for ClientConnector {
                 io_channel_id,
                 user_channel_id,
                 static_channels,
+                mut connection_activation,
             } => {
-                let send_data_indication_ctx = legacy::decode_send_data_indication(input)?;
-                let share_control_ctx = legacy::decode_share_control(send_data_indication_ctx)?;
-
-                debug!(message = ?share_control_ctx.pdu, "Received");
-
-                if share_control_ctx.channel_id != io_channel_id {
-                    warn!(
-                        io_channel_id,
-                        share_control_ctx.channel_id, "Unexpected channel ID for received Share Control Pdu"
-                    );
-                }
-
-                let capability_sets = if let rdp::headers::ShareControlPdu::ServerDemandActive(server_demand_active) =
-                    share_control_ctx.pdu
-                {
-                    server_demand_active.pdu.capability_sets
-                } else {
-                    return Err(Error::new("unexpected Share Control Pdu (expected ServerDemandActive)"));
-                };
-
-                let desktop_size = capability_sets
-                    .iter()
-                    .find_map(|c| match c {
-                        rdp::capability_sets::CapabilitySet::Bitmap(b) => Some(DesktopSize {
-                            width: b.desktop_width,
-                            height: b.desktop_height,
-                        }),
-                        _ => None,
-                    })
-                    .unwrap_or(DesktopSize {
-                        width: self.config.desktop_size.width,
-                        height: self.config.desktop_size.height,
-                    });
-
-                let client_confirm_active = rdp::headers::ShareControlPdu::ClientConfirmActive(
-                    create_client_confirm_active(&self.config, capability_sets),
-                );
-
-                debug!(message = ?client_confirm_active, "Send");
-
-                let written = legacy::encode_share_control(
-                    user_channel_id,
-                    io_channel_id,
-                    share_control_ctx.share_id,
-                    client_confirm_active,
-                    output,
-                )?;
+                let written = connection_activation.step(input, output)?;
+
+                let next_state =
+                    if let ConnectionActivationState::Redirected { redirection } = &connection_activation.state {
+                        ClientConnectorState::Redirected {
+                            redirection: redirection.clone(),
+                        }
+                    } else {
+                        ClientConnectorState::ConnectionFinalization {
+                            io_channel_id,
+                            user_channel_id,
+                            static_channels,
+                            connection_activation,
+                        }
+                    };
 
-                (
-                    Written::from_size(written)?,
-                    ClientConnectorState::ConnectionFinalization {
-                        io_channel_id,
-                        user_channel_id,
-                        static_channels,
-                        desktop_size,
-                        connection_finalization: ConnectionFinalizationSequence::new(io_channel_id, user_channel_id),
-                    },
-                )
+                (written, next_state)
             }
 
             //== Connection Finalization ==//
@@ -740,29 +872,33 @@ impl Sequence for ClientConnector {
                 io_channel_id,
                 user_channel_id,
                 static_channels,
-                desktop_size,
-                mut connection_finalization,
+                mut connection_activation,
             } => {
-                let written = connection_finalization.step(input, output)?;
+                let written = connection_activation.step(input, output)?;
 
-                let next_state = if connection_finalization.state.is_terminal() {
-                    ClientConnectorState::Connected {
+                let next_state = match &connection_activation.state {
+                    ConnectionActivationState::Finalized { desktop_size, .. } => ClientConnectorState::Connected {
                         result: ConnectionResult {
                             io_channel_id,
                             user_channel_id,
                             static_channels,
-                            desktop_size,
+                            desktop_size: desktop_size.clone(),
                             graphics_config: self.config.graphics.clone(),
+                            connection_activation,
+                            // Moved in by `step_with_standard_security`
+                            standard_security: None,
                         },
-                    }
-                } else {
-                    ClientConnectorState::ConnectionFinalization {
+                    },
+                    // The Monitor Layout PDU or an informative Server Redirection PDU may precede the redirection
+                    ConnectionActivationState::Redirected { redirection } => ClientConnectorState::Redirected {
+                        redirection: redirection.clone(),
+                    },
+                    _ => ClientConnectorState::ConnectionFinalization {
                         io_channel_id,
                         user_channel_id,
                         static_channels,
-                        desktop_size,
-                        connection_finalization,
-                    }
+                        connection_activation,
+                    },
                 };
 
                 (written, next_state)
@@ -771,6 +907,7 @@ impl Sequence for ClientConnector {
             //== Connected ==//
             // The client connector job is done.
             ClientConnectorState::Connected { .. } => return Err(Error::new("already connected")),
+            ClientConnectorState::Redirected { .. } => return Err(Error::new("already redirected")),
         };
 
         self.state = next_state;
@@ -779,29 +916,65 @@ impl Sequence for ClientConnector {
     }
 }
 
-fn create_gcc_blocks(config: &Config, selected_protocol: nego::SecurityProtocol) -> gcc::ClientGccBlocks {
+/// Returns the routing token of the X.224 Connection Request PDU, found in the load balancing information of the
+/// Server Redirection PDU.
+///
+/// The token is opaque to the client: only the prefix and the line ending, added back when encoding the request, are
+/// removed.
+fn routing_token(load_balance_info: &[u8]) -> Vec<u8> {
+    const ROUTING_TOKEN_PREFIX: &[u8] = b"Cookie: msts=";
+
+    let mut routing_token = load_balance_info;
+
+    while let [rest @ .., b'\r' | b'\n' | b'\0'] = routing_token {
+        routing_token = rest;
+    }
+
+    routing_token
+        .strip_prefix(ROUTING_TOKEN_PREFIX)
+        .unwrap_or(routing_token)
+        .to_vec()
+}
+
+fn create_gcc_blocks(
+    config: &Config,
+    selected_protocol: nego::SecurityProtocol,
+    redirection: Option<&ServerRedirectionPdu>,
+) -> Result<gcc::ClientGccBlocks> {
     use ironrdp_pdu::gcc::*;
 
-    let color_depth = config
-        .bitmap
-        .as_ref()
-        .map(|bitmap| match bitmap.color_depth {
-            15 => SupportedColorDepths::BPP15,
-            16 => SupportedColorDepths::BPP16,
-            24 => SupportedColorDepths::BPP24,
-            32 => SupportedColorDepths::BPP32,
-            _ => panic!("Unsupported color depth: {}", bitmap.color_depth),
-        })
-        .unwrap_or(SupportedColorDepths::BPP16);
-
-    ClientGccBlocks {
+    // The server picks the high color depth if it supports it, otherwise it falls back to the closest
+    // supported color depth. A 32 bpp session is only obtained with the WANT_32_BPP_SESSION flag.
+    let color_depth = config.bitmap.as_ref().map_or(16, |bitmap| bitmap.color_depth);
+
+    let high_color_depth = match color_depth {
+        8 => HighColorDepth::Bpp8,
+        15 => HighColorDepth::Rgb555Bpp16,
+        16 => HighColorDepth::Rgb565Bpp16,
+        24 | 32 => HighColorDepth::Bpp24,
+        unsupported => {
+            return Err(
+                Error::new("invalid configuration").with_reason(format!("unsupported color depth: {unsupported}"))
+            )
+        }
+    };
+
+    let (monitor, monitor_extended) = create_monitor_data(&config.monitors)?;
+
+    let supported_color_depths = if color_depth == 32 {
+        SupportedColorDepths::all()
+    } else {
+        SupportedColorDepths::BPP15 | SupportedColorDepths::BPP16 | SupportedColorDepths::BPP24
+    };
+
+    Ok(ClientGccBlocks {
         core: ClientCoreData {
             version: RdpVersion::V5_PLUS,
-            desktop_width: config.desktop_size.width,
-            desktop_height: config.desktop_size.height,
+            desktop_width: config.virtual_desktop_size().width,
+            desktop_height: config.virtual_desktop_size().height,
             color_depth: ColorDepth::Bpp4, // ignored because we use the optional core data below
             sec_access_sequence: SecureAccessSequence::Del,
-            keyboard_layout: 0, // the server SHOULD use the default active input locale identifier
+            keyboard_layout: config.keyboard_layout, // when 0, the server SHOULD use its default input locale
             client_build: config.client_build,
             client_name: config.client_name.clone(),
             keyboard_type: config.keyboard_type,
@@ -812,8 +985,8 @@ fn create_gcc_blocks(config: &Config, selected_protocol: nego::SecurityProtocol)
                 post_beta2_color_depth: Some(ColorDepth::Bpp4), // ignored because we set high_color_depth
                 client_product_id: Some(1),
                 serial_number: Some(0),
-                high_color_depth: Some(HighColorDepth::Bpp24),
-                supported_color_depths: Some(color_depth),
+                high_color_depth: Some(high_color_depth),
+                supported_color_depths: Some(supported_color_depths),
                 early_capability_flags: {
                     let mut early_capability_flags = ClientEarlyCapabilityFlags::VALID_CONNECTION_TYPE
                         | ClientEarlyCapabilityFlags::SUPPORT_ERR_INFO_PDU;
@@ -822,6 +995,14 @@ fn create_gcc_blocks(config: &Config, selected_protocol: nego::SecurityProtocol)
                         early_capability_flags |= ClientEarlyCapabilityFlags::SUPPORT_DYN_VC_GFX_PROTOCOL;
                     }
 
+                    if color_depth == 32 {
+                        early_capability_flags |= ClientEarlyCapabilityFlags::WANT_32_BPP_SESSION;
+                    }
+
+                    if monitor.is_some() {
+                        early_capability_flags |= ClientEarlyCapabilityFlags::SUPPORT_MONITOR_LAYOUT_PDU;
+                    }
+
                     Some(early_capability_flags)
                 },
                 dig_product_id: Some(config.dig_product_id.clone()),
@@ -834,26 +1015,88 @@ fn create_gcc_blocks(config: &Config, selected_protocol: nego::SecurityProtocol)
                 device_scale_factor: None,
             },
         },
-        security: ClientSecurityData::no_security(),
-        network: if config.graphics.is_some() {
-            Some(ClientNetworkData {
-                channels: vec![Channel {
-                    name: "drdynvc".to_owned(),
-                    options: ChannelOptions::COMPRESS_RDP,
-                }],
-            })
+        security: if config.standard_rdp_security && selected_protocol == nego::SecurityProtocol::RDP {
+            ClientSecurityData {
+                encryption_methods: EncryptionMethod::BIT_40 | EncryptionMethod::BIT_56 | EncryptionMethod::BIT_128,
+                ext_encryption_methods: 0,
+            }
         } else {
-            Some(ClientNetworkData { channels: Vec::new() })
+            ClientSecurityData::no_security()
         },
-        cluster: None,
-        monitor: None,
+        // The dynamic virtual channel is always requested: besides the graphics pipeline, it is used by
+        // extensions such as the Display Control channel.
+        network: Some(ClientNetworkData {
+            channels: std::iter::once(Channel {
+                name: "drdynvc".to_owned(),
+                options: ChannelOptions::COMPRESS_RDP,
+            })
+            .chain(config.static_channels.iter().cloned())
+            .collect(),
+        }),
+        // Advertising the support of the server redirection allows the connection broker to redirect the client
+        cluster: Some(ClientClusterData {
+            flags: if redirection.is_some() {
+                RedirectionFlags::REDIRECTION_SUPPORTED | RedirectionFlags::REDIRECTED_SESSION_FIELD_VALID
+            } else {
+                RedirectionFlags::REDIRECTION_SUPPORTED
+            },
+            redirection_version: RedirectionVersion::V4,
+            redirected_session_id: redirection.map_or(0, |redirection| redirection.session_id),
+        }),
+        monitor,
         message_channel: None,
         multi_transport_channel: None,
-        monitor_extended: None,
+        monitor_extended,
+    })
+}
+
+fn create_monitor_data(
+    monitors: &[crate::MonitorConfig],
+) -> Result<(Option<gcc::ClientMonitorData>, Option<gcc::ClientMonitorExtendedData>)> {
+    const MONITOR_COUNT_MAX: usize = 16;
+
+    if monitors.is_empty() {
+        return Ok((None, None));
     }
+
+    let invalid_configuration = |reason: &str| Error::new("invalid configuration").with_reason(reason.to_owned());
+
+    if monitors.len() > MONITOR_COUNT_MAX {
+        return Err(invalid_configuration("more than 16 monitors"));
+    }
+
+    let mut primary_monitors = monitors.iter().filter(|monitor| monitor.is_primary);
+
+    match (primary_monitors.next(), primary_monitors.next()) {
+        (Some(primary), None) if primary.left == 0 && primary.top == 0 => {}
+        (Some(_), None) => return Err(invalid_configuration("primary monitor is not at the origin")),
+        _ => return Err(invalid_configuration("exactly one monitor must be primary")),
+    }
+
+    if monitors.iter().any(|monitor| monitor.width == 0 || monitor.height == 0) {
+        return Err(invalid_configuration("empty monitor"));
+    }
+
+    let monitor = gcc::ClientMonitorData {
+        monitors: monitors.iter().map(crate::MonitorConfig::to_monitor).collect(),
+    };
+
+    let monitor_extended = gcc::ClientMonitorExtendedData {
+        extended_monitors_info: monitors
+            .iter()
+            .map(crate::MonitorConfig::to_extended_monitor_info)
+            .collect(),
+    };
+
+    Ok((Some(monitor), Some(monitor_extended)))
 }
 
-fn create_client_info_pdu(config: &Config, routing_addr: &SocketAddr) -> rdp::ClientInfoPdu {
+fn create_client_info_pdu(
+    config: &Config,
+    routing_addr: &SocketAddr,
+    auto_reconnect_cookie: Option<&ServerAutoReconnect>,
+    client_random: &[u8],
+) -> rdp::ClientInfoPdu {
     use ironrdp_pdu::rdp::client_info::{
         AddressFamily, ClientInfo, ClientInfoFlags, CompressionType, Credentials, ExtendedClientInfo,
         ExtendedClientOptionalInfo,
@@ -865,6 +1108,20 @@ fn create_client_info_pdu(config: &Config, routing_addr: &SocketAddr) -> rdp::Cl
         flags: BasicSecurityHeaderFlags::INFO_PKT,
     };
 
+    let mut flags = ClientInfoFlags::UNICODE
+        | ClientInfoFlags::DISABLE_CTRL_ALT_DEL
+        | ClientInfoFlags::LOGON_NOTIFY
+        | ClientInfoFlags::LOGON_ERRORS
+        | ClientInfoFlags::VIDEO_DISABLE;
+
+    if !config.enable_audio_playback {
+        flags |= ClientInfoFlags::NO_AUDIO_PLAYBACK;
+    }
+
+    if config.compression_type.is_some() {
+        flags |= ClientInfoFlags::COMPRESSION;
+    }
+
     let client_info = ClientInfo {
         credentials: Credentials {
             username: config.username.clone(),
@@ -872,13 +1129,9 @@ fn create_client_info_pdu(config: &Config, routing_addr: &SocketAddr) -> rdp::Cl
             domain: config.domain.clone(),
         },
         code_page: 0, // ignored if the keyboardLayout field of the Client Core Data is set to zero
-        flags: ClientInfoFlags::UNICODE
-            | ClientInfoFlags::DISABLE_CTRL_ALT_DEL
-            | ClientInfoFlags::LOGON_NOTIFY
-            | ClientInfoFlags::LOGON_ERRORS
-            | ClientInfoFlags::NO_AUDIO_PLAYBACK
-            | ClientInfoFlags::VIDEO_DISABLE,
-        compression_type: CompressionType::K8, // ignored if ClientInfoFlags::COMPRESSION is not set
+        flags,
+        // ignored if ClientInfoFlags::COMPRESSION is not set
+        compression_type: config.compression_type.unwrap_or(CompressionType::K8),
         alternate_shell: String::new(),
         work_dir: String::new(),
         extra_info: ExtendedClientInfo {
@@ -888,7 +1141,12 @@ fn create_client_info_pdu(config: &Config, routing_addr: &SocketAddr) -> rdp::Cl
             },
             address: routing_addr.ip().to_string(),
             dir: config.client_dir.clone(),
-            optional_data: ExtendedClientOptionalInfo::default(),
+            optional_data: ExtendedClientOptionalInfo {
+                reconnect_cookie: auto_reconnect_cookie.map(|auto_reconnect_cookie| {
+                    create_client_auto_reconnect_packet(auto_reconnect_cookie, client_random)
+                }),
+                ..create_extended_client_optional_info(config)
+            },
         },
     };
 
@@ -898,129 +1156,51 @@ fn create_client_info_pdu(config: &Config, routing_addr: &SocketAddr) -> rdp::Cl
     }
 }
 
-fn create_client_confirm_active(
-    config: &Config,
-    mut server_capability_sets: Vec<CapabilitySet>,
-) -> rdp::capability_sets::ClientConfirmActive {
-    use ironrdp_pdu::rdp::capability_sets::*;
-
-    server_capability_sets.retain(|capability_set| matches!(capability_set, CapabilitySet::MultiFragmentUpdate(_)));
-
-    let lossy_bitmap_compression = config
-        .bitmap
-        .as_ref()
-        .map(|bitmap| bitmap.lossy_compression)
-        .unwrap_or(false);
-
-    let drawing_flags = if lossy_bitmap_compression {
-        BitmapDrawingFlags::ALLOW_SKIP_ALPHA
-            | BitmapDrawingFlags::ALLOW_DYNAMIC_COLOR_FIDELITY
-            | BitmapDrawingFlags::ALLOW_COLOR_SUBSAMPLING
-    } else {
-        BitmapDrawingFlags::ALLOW_SKIP_ALPHA
-    };
-
-    server_capability_sets.extend_from_slice(&[
-        CapabilitySet::General(General {
-            major_platform_type: config.platform,
-            minor_platform_type: MinorPlatformType::Unspecified,
-            extra_flags: GeneralExtraFlags::FASTPATH_OUTPUT_SUPPORTED | GeneralExtraFlags::NO_BITMAP_COMPRESSION_HDR,
-            refresh_rect_support: false,
-            suppress_output_support: false,
-        }),
-        CapabilitySet::Bitmap(Bitmap {
-            pref_bits_per_pix: 32,
-            desktop_width: config.desktop_size.width,
-            desktop_height: config.desktop_size.height,
-            desktop_resize_flag: false,
-            drawing_flags,
-        }),
-        CapabilitySet::Order(Order::new(
-            OrderFlags::NEGOTIATE_ORDER_SUPPORT | OrderFlags::ZERO_BOUNDS_DELTAS_SUPPORT,
-            OrderSupportExFlags::empty(),
-            0,
-            0,
-        )),
-        CapabilitySet::BitmapCache(BitmapCache {
-            caches: [CacheEntry {
-                entries: 0,
-                max_cell_size: 0,
-            }; BITMAP_CACHE_ENTRIES_NUM],
-        }),
-        CapabilitySet::Input(Input {
-            input_flags: InputFlags::all(),
-            keyboard_layout: 0,
-            keyboard_type: Some(config.keyboard_type),
-            keyboard_subtype: config.keyboard_subtype,
-            keyboard_function_key: config.keyboard_functional_keys_count,
-            keyboard_ime_filename: config.ime_file_name.clone(),
-        }),
-        CapabilitySet::Pointer(Pointer {
-            color_pointer_cache_size: 0,
-            pointer_cache_size: 0,
-        }),
-        CapabilitySet::Brush(Brush {
-            support_level: SupportLevel::Default,
-        }),
-        CapabilitySet::GlyphCache(GlyphCache {
-            glyph_cache: [CacheDefinition {
-                entries: 0,
-                max_cell_size: 0,
-            }; GLYPH_CACHE_NUM],
-            frag_cache: CacheDefinition {
-                entries: 0,
-                max_cell_size: 0,
-            },
-            glyph_support_level: GlyphSupportLevel::None,
-        }),
-        CapabilitySet::OffscreenBitmapCache(OffscreenBitmapCache {
-            is_supported: false,
-            cache_size: 0,
-            cache_entries: 0,
-        }),
-        CapabilitySet::VirtualChannel(VirtualChannel {
-            flags: VirtualChannelFlags::NO_COMPRESSION,
-            chunk_size: Some(0), // ignored
-        }),
-        CapabilitySet::Sound(Sound {
-            flags: SoundFlags::empty(),
-        }),
-        CapabilitySet::LargePointer(LargePointer {
-            flags: LargePointerSupportFlags::UP_TO_96X96_PIXELS,
-        }),
-        CapabilitySet::SurfaceCommands(SurfaceCommands {
-            flags: CmdFlags::SET_SURFACE_BITS | CmdFlags::STREAM_SURFACE_BITS | CmdFlags::FRAME_MARKER,
-        }),
-        CapabilitySet::BitmapCodecs(BitmapCodecs(vec![Codec {
-            id: 0x03, // RemoteFX
-            property: CodecProperty::RemoteFx(RemoteFxContainer::ClientContainer(RfxClientCapsContainer {
-                capture_flags: CaptureFlags::empty(),
-                caps_data: RfxCaps(RfxCapset(vec![RfxICap {
-                    flags: RfxICapFlags::empty(),
-                    entropy_bits: EntropyBits::Rlgr3,
-                }])),
-            })),
-        }])),
-        CapabilitySet::FrameAcknowledge(FrameAcknowledge {
-            max_unacknowledged_frame_count: 2,
-        }),
-    ]);
-
-    if !server_capability_sets
-        .iter()
-        .any(|c| matches!(&c, CapabilitySet::MultiFragmentUpdate(_)))
-    {
-        server_capability_sets.push(CapabilitySet::MultiFragmentUpdate(MultifragmentUpdate {
-            max_request_size: 1024,
-        }));
+/// Builds the optional fields of the Extended Info Packet from the configuration, without the auto-reconnect cookie.
+///
+/// The fields are always sent, since the auto-reconnect cookie can only follow them.
+fn create_extended_client_optional_info(config: &Config) -> rdp::client_info::ExtendedClientOptionalInfo {
+    use ironrdp_pdu::rdp::client_info::{ExtendedClientOptionalInfo, TimezoneInfo};
+
+    let timezone = config.timezone.clone().unwrap_or(TimezoneInfo {
+        bias: 0,
+        standard_name: String::new(),
+        standard_date: None,
+        standard_bias: 0,
+        daylight_name: String::new(),
+        daylight_date: None,
+        daylight_bias: 0,
+    });
+
+    ExtendedClientOptionalInfo {
+        timezone: Some(timezone),
+        session_id: Some(0), // reserved, must be set to zero
+        performance_flags: Some(config.performance_flags),
+        reconnect_cookie: None,
     }
+}
 
-    ClientConfirmActive {
-        originator_id: SERVER_CHANNEL_ID,
-        pdu: DemandActive {
-            source_descriptor: "IRONRDP".to_owned(),
-            capability_sets: server_capability_sets,
-        },
+/// When Enhanced RDP Security is in effect, no client random is exchanged and
+/// an array of zeros is used in its place to compute the auto-reconnect verifier.
+const ENHANCED_SECURITY_CLIENT_RANDOM: [u8; CLIENT_RANDOM_SIZE] = [0; CLIENT_RANDOM_SIZE];
+
+/// Computes the client auto-reconnect packet from the one previously sent by the server.
+///
+/// [5.5 Automatic Reconnection](https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpbcgr/15b0d1c9-2891-4adb-a45e-deb4aeeeab7c)
+fn create_client_auto_reconnect_packet(
+    auto_reconnect_cookie: &ServerAutoReconnect,
+    client_random: &[u8],
+) -> rdp::client_info::ClientAutoReconnectPacket {
+    use hmac::{Hmac, Mac as _};
+
+    // SecurityVerifier = HMAC_MD5(key = ArcRandomBits, data = ClientRandom)
+    let mut mac =
+        Hmac::<md5::Md5>::new_from_slice(&auto_reconnect_cookie.random_bits).expect("HMAC can take a key of any size");
+    mac.update(client_random);
+
+    rdp::client_info::ClientAutoReconnectPacket {
+        logon_id: auto_reconnect_cookie.logon_id,
+        security_verifier: mac.finalize().into_bytes().into(),
     }
 }
 
@@ -1037,3 +1217,135 @@ fn write_credssp_request(ts_request: credssp::TsRequest, output: &mut Vec<u8>) -
 
     Ok(length)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn routing_token_is_passed_through() {
+        assert_eq!(
+            routing_token(b"Cookie: msts=3640205228.15629.0000\r\n"),
+            b"3640205228.15629.0000"
+        );
+
+        // Not necessarily UTF-8
+        assert_eq!(routing_token(b"\xFF\xFE\x00token\r\n\0"), b"\xFF\xFE\x00token");
+    }
+
+    #[test]
+    fn auto_reconnect_verifier_is_hmac_md5_of_the_client_random() {
+        // RFC 2202, test case 1
+        let cookie = ServerAutoReconnect {
+            logon_id: 2,
+            random_bits: [0x0b; 16],
+        };
+
+        let packet = create_client_auto_reconnect_packet(&cookie, b"Hi There");
+
+        assert_eq!(packet.logon_id, 2);
+        assert_eq!(
+            packet.security_verifier,
+            [0x92, 0x94, 0x72, 0x7a, 0x36, 0x38, 0xbb, 0x1c, 0x13, 0xf4, 0x8e, 0xf8, 0x15, 0x8b, 0xfc, 0x9d]
+        );
+    }
+
+    #[test]
+    fn auto_reconnect_verifier_with_enhanced_security() {
+        let cookie = ServerAutoReconnect {
+            logon_id: 0x1234_5678,
+            random_bits: core::array::from_fn(|i| i as u8),
+        };
+
+        // The client random is zeroed when Enhanced RDP Security is used
+        let packet = create_client_auto_reconnect_packet(&cookie, &ENHANCED_SECURITY_CLIENT_RANDOM);
+
+        assert_eq!(packet.logon_id, 0x1234_5678);
+        assert_eq!(
+            packet.security_verifier,
+            [0xb6, 0x39, 0xc8, 0x73, 0x16, 0x38, 0x61, 0x8b, 0x70, 0x79, 0x72, 0xaa, 0x6e, 0x96, 0xcf, 0x90]
+        );
+    }
+
+    #[test]
+    fn auto_reconnect_keeps_the_extended_client_info_of_the_configuration() {
+        use ironrdp_pdu::rdp::client_info::{ExtendedClientOptionalInfo, PerformanceFlags, TimezoneInfo};
+
+        let timezone = TimezoneInfo {
+            bias: 0xFFFF_FFC4, // UTC+1
+            standard_name: "W. Europe Standard Time".to_owned(),
+            standard_date: None,
+            standard_bias: 0,
+            daylight_name: "W. Europe Daylight Time".to_owned(),
+            daylight_date: None,
+            daylight_bias: 0xFFFF_FFC4,
+        };
+
+        let config = Config {
+            desktop_size: DesktopSize {
+                width: 1024,
+                height: 768,
+            },
+            security_protocol: nego::SecurityProtocol::SSL,
+            username: "user".to_owned(),
+            password: "password".to_owned(),
+            domain: None,
+            client_build: 0,
+            client_name: "client".to_owned(),
+            keyboard_layout: 0,
+            keyboard_type: gcc::KeyboardType::IbmEnhanced,
+            keyboard_subtype: 0,
+            keyboard_functional_keys_count: 12,
+            ime_file_name: String::new(),
+            graphics: None,
+            bitmap: None,
+            dig_product_id: String::new(),
+            client_dir: String::new(),
+            platform: ironrdp_pdu::rdp::capability_sets::MajorPlatformType::Unspecified,
+            enable_audio_playback: false,
+            pointer_cache_size: 32,
+            compression_type: None,
+            timezone: Some(timezone.clone()),
+            performance_flags: PerformanceFlags::DISABLE_WALLPAPER | PerformanceFlags::ENABLE_FONT_SMOOTHING,
+            standard_rdp_security: false,
+            static_channels: Vec::new(),
+            monitors: Vec::new(),
+        };
+
+        let routing_addr = SocketAddr::from(([192, 168, 1, 2], 3389));
+        let cookie = ServerAutoReconnect {
+            logon_id: 2,
+            random_bits: [0x0b; 16],
+        };
+
+        let connection = create_client_info_pdu(&config, &routing_addr, None, &ENHANCED_SECURITY_CLIENT_RANDOM);
+        let reconnection =
+            create_client_info_pdu(&config, &routing_addr, Some(&cookie), &ENHANCED_SECURITY_CLIENT_RANDOM);
+
+        let optional_data = connection.client_info.extra_info.optional_data;
+        assert_eq!(optional_data.timezone.as_ref(), Some(&timezone));
+        assert_eq!(optional_data.session_id, Some(0));
+        assert_eq!(
+            optional_data.performance_flags,
+            Some(PerformanceFlags::DISABLE_WALLPAPER | PerformanceFlags::ENABLE_FONT_SMOOTHING)
+        );
+        assert_eq!(optional_data.reconnect_cookie, None);
+
+        // Only the auto-reconnect cookie is added
+        let reconnection_optional_data = reconnection.client_info.extra_info.optional_data;
+        assert_eq!(
+            reconnection_optional_data.reconnect_cookie,
+            Some(create_client_auto_reconnect_packet(
+                &cookie,
+                &ENHANCED_SECURITY_CLIENT_RANDOM
+            ))
+        );
+        assert_eq!(
+            ExtendedClientOptionalInfo {
+                reconnect_cookie: None,
+                ..reconnection_optional_data
+            },
+            optional_data
+        );
+    }
+}
@@ -0,0 +1,663 @@
+use std::mem;
+use std::sync::{Arc, Mutex};
+
+use ironrdp_graphics::bulk;
+use ironrdp_pdu::rdp::capability_sets::{CapabilitySet, InputFlags};
+use ironrdp_pdu::rdp::server_redirection::{ServerRedirectionFlags, ServerRedirectionPdu};
+use ironrdp_pdu::{gcc, rdp, PduHint};
+
+use crate::{legacy, Config, ConnectionFinalizationSequence, DesktopSize, Error, Result, Sequence, State, Written};
+
+/// Represents the Capability Exchange and Connection Finalization phases
+/// of the connection sequence (section [1.3.1.1]).
+///
+/// This is abstracted away into its own sequence because it is also used
+/// as part of the Deactivation-Reactivation Sequence (section [1.3.1.3]),
+/// which may be initiated by the server at any time during the active stage.
+///
+/// [1.3.1.1]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpbcgr/023f1e69-cfe8-4ee6-9ee0-7e759fb4e4ee
+/// [1.3.1.3]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpbcgr/dfc234ce-481a-4674-9a5d-2a7bafb14432
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct ConnectionActivationSequence {
+    pub state: ConnectionActivationState,
+    pub config: Config,
+    pub io_channel_id: u16,
+    pub user_channel_id: u16,
+    /// Monitor layout sent by the server in the Monitor Layout PDU, if any
+    #[cfg_attr(feature = "arbitrary", arbitrary(default))]
+    pub monitor_layout: Option<Vec<gcc::Monitor>>,
+    /// Input flags of the server Input Capability Set, e.g.: to know whether relative mouse events are supported
+    #[cfg_attr(feature = "arbitrary", arbitrary(default))]
+    pub server_input_flags: Option<InputFlags>,
+    /// Bulk decompressor of the server PDUs, shared with the active stage as the server compresses all the PDUs
+    /// with the same history
+    #[cfg_attr(feature = "arbitrary", arbitrary(default))]
+    pub bulk_decompressor: Arc<Mutex<bulk::Decompressor>>,
+}
+
+impl ConnectionActivationSequence {
+    pub fn new(config: Config, io_channel_id: u16, user_channel_id: u16) -> Self {
+        Self {
+            state: ConnectionActivationState::CapabilitiesExchange,
+            config,
+            io_channel_id,
+            user_channel_id,
+            monitor_layout: None,
+            server_input_flags: None,
+            bulk_decompressor: Arc::new(Mutex::new(bulk::Decompressor::new())),
+        }
+    }
+
+    /// Restarts the sequence from the Capabilities Exchange phase.
+    ///
+    /// Must be called upon reception of the Server Deactivate All PDU.
+    pub fn reset(&mut self) {
+        self.state = ConnectionActivationState::CapabilitiesExchange;
+        self.monitor_layout = None;
+        self.server_input_flags = None;
+    }
+}
+
+impl Sequence for ConnectionActivationSequence {
+    fn next_pdu_hint(&self) -> Option<&dyn PduHint> {
+        match &self.state {
+            ConnectionActivationState::Consumed => None,
+            ConnectionActivationState::CapabilitiesExchange => Some(&ironrdp_pdu::X224_HINT),
+            ConnectionActivationState::ConnectionFinalization {
+                connection_finalization,
+                ..
+            } => connection_finalization.next_pdu_hint(),
+            ConnectionActivationState::Finalized { .. } => None,
+            ConnectionActivationState::Redirected { .. } => None,
+        }
+    }
+
+    fn state(&self) -> &dyn State {
+        &self.state
+    }
+
+    fn step(&mut self, input: &[u8], output: &mut Vec<u8>) -> Result<Written> {
+        let (written, next_state) = match mem::take(&mut self.state) {
+            ConnectionActivationState::Consumed => {
+                return Err(Error::new(
+                    "connection activation sequence state is consumed (this is a bug)",
+                ))
+            }
+
+            //== Capabilities Exchange ==/
+            // The server sends the set of capabilities it supports to the client.
+            ConnectionActivationState::CapabilitiesExchange => {
+                let send_data_indication_ctx = legacy::decode_send_data_indication(input)?;
+                let share_control_ctx = legacy::decode_share_control(send_data_indication_ctx)?;
+
+                debug!(message = ?share_control_ctx.pdu, "Received");
+
+                if share_control_ctx.channel_id != self.io_channel_id {
+                    warn!(
+                        io_channel_id = self.io_channel_id,
+                        share_control_ctx.channel_id, "Unexpected channel ID for received Share Control Pdu"
+                    );
+                }
+
+                let capability_sets = match share_control_ctx.pdu {
+                    rdp::headers::ShareControlPdu::ServerDemandActive(server_demand_active) => {
+                        server_demand_active.pdu.capability_sets
+                    }
+                    // A connection broker redirects the client instead of activating the session
+                    rdp::headers::ShareControlPdu::ServerRedirect(redirection)
+                        if !redirection.flags.contains(ServerRedirectionFlags::NO_REDIRECT) =>
+                    {
+                        info!(redirection.session_id, "Redirected by the server");
+
+                        self.state = ConnectionActivationState::Redirected {
+                            redirection: Box::new(redirection),
+                        };
+                        return Ok(Written::Nothing);
+                    }
+                    rdp::headers::ShareControlPdu::ServerRedirect(_) => {
+                        debug!("Ignoring informative Server Redirection PDU");
+
+                        self.state = ConnectionActivationState::CapabilitiesExchange;
+                        return Ok(Written::Nothing);
+                    }
+                    // Normally sent after the Demand Active PDU, but accepted before it as well
+                    rdp::headers::ShareControlPdu::Data(rdp::headers::ShareDataHeader {
+                        share_data_pdu: rdp::headers::ShareDataPdu::MonitorLayout(monitor_layout),
+                        ..
+                    }) => {
+                        self.monitor_layout = Some(monitor_layout.monitors);
+
+                        self.state = ConnectionActivationState::CapabilitiesExchange;
+                        return Ok(Written::Nothing);
+                    }
+                    _ => return Err(Error::new("unexpected Share Control Pdu (expected ServerDemandActive)")),
+                };
+
+                let desktop_size = server_desktop_size(&self.config, &capability_sets);
+
+                self.server_input_flags = capability_sets.iter().find_map(|c| match c {
+                    CapabilitySet::Input(input) => Some(input.input_flags),
+                    _ => None,
+                });
+
+                // The server Virtual Channel Capability Set sets the maximum size of the chunks on static channels
+                let vc_chunk_size = capability_sets
+                    .iter()
+                    .find_map(|c| match c {
+                        CapabilitySet::VirtualChannel(v) => v.chunk_size,
+                        _ => None,
+                    })
+                    .map(|chunk_size| chunk_size as usize)
+                    .unwrap_or(rdp::vc::CHANNEL_CHUNK_LENGTH);
+
+                let client_confirm_active = rdp::headers::ShareControlPdu::ClientConfirmActive(
+                    create_client_confirm_active(&self.config, capability_sets),
+                );
+
+                debug!(message = ?client_confirm_active, "Send");
+
+                let written = legacy::encode_share_control(
+                    self.user_channel_id,
+                    self.io_channel_id,
+                    share_control_ctx.share_id,
+                    client_confirm_active,
+                    output,
+                )?;
+
+                (
+                    Written::from_size(written)?,
+                    ConnectionActivationState::ConnectionFinalization {
+                        desktop_size,
+                        vc_chunk_size,
+                        connection_finalization: ConnectionFinalizationSequence::new(
+                            self.io_channel_id,
+                            self.user_channel_id,
+                            Arc::clone(&self.bulk_decompressor),
+                        ),
+                    },
+                )
+            }
+
+            //== Connection Finalization ==//
+            // Client and server exchange a few PDUs in order to finalize the connection.
+            // Client may send PDUs one after the other without waiting for a response in order to speed up the process.
+            ConnectionActivationState::ConnectionFinalization {
+                desktop_size,
+                vc_chunk_size,
+                mut connection_finalization,
+            } => {
+                let written = connection_finalization.step(input, output)?;
+
+                if let Some(monitor_layout) = connection_finalization.monitor_layout.take() {
+                    self.monitor_layout = Some(monitor_layout);
+                }
+
+                let next_state = if connection_finalization.state.is_terminal() {
+                    ConnectionActivationState::Finalized {
+                        desktop_size,
+                        vc_chunk_size,
+                    }
+                } else {
+                    ConnectionActivationState::ConnectionFinalization {
+                        desktop_size,
+                        vc_chunk_size,
+                        connection_finalization,
+                    }
+                };
+
+                (written, next_state)
+            }
+
+            ConnectionActivationState::Finalized { .. } => return Err(Error::new("connection already finalized")),
+            ConnectionActivationState::Redirected { .. } => return Err(Error::new("connection already redirected")),
+        };
+
+        self.state = next_state;
+
+        Ok(written)
+    }
+}
+
+#[derive(Default, Debug, Clone)]
+#[non_exhaustive]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum ConnectionActivationState {
+    #[default]
+    Consumed,
+
+    CapabilitiesExchange,
+    ConnectionFinalization {
+        desktop_size: DesktopSize,
+        /// Maximum size of the static virtual channel chunks
+        vc_chunk_size: usize,
+        connection_finalization: ConnectionFinalizationSequence,
+    },
+    Finalized {
+        desktop_size: DesktopSize,
+        /// Maximum size of the static virtual channel chunks
+        vc_chunk_size: usize,
+    },
+    /// The server redirected the client instead of activating the session
+    Redirected {
+        redirection: Box<ServerRedirectionPdu>,
+    },
+}
+
+impl State for ConnectionActivationState {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Consumed => "Consumed",
+            Self::CapabilitiesExchange => "CapabilitiesExchange",
+            Self::ConnectionFinalization { .. } => "ConnectionFinalization",
+            Self::Finalized { .. } => "Finalized",
+            Self::Redirected { .. } => "Redirected",
+        }
+    }
+
+    fn is_terminal(&self) -> bool {
+        matches!(self, Self::Finalized { .. } | Self::Redirected { .. })
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+}
+
+/// Desktop size of the server Bitmap Capability Set, or the requested one if the server did not send it
+fn server_desktop_size(config: &Config, server_capability_sets: &[CapabilitySet]) -> DesktopSize {
+    server_capability_sets
+        .iter()
+        .find_map(|capability_set| match capability_set {
+            CapabilitySet::Bitmap(bitmap) => Some(DesktopSize {
+                width: bitmap.desktop_width,
+                height: bitmap.desktop_height,
+            }),
+            _ => None,
+        })
+        .unwrap_or_else(|| config.virtual_desktop_size())
+}
+
+pub fn create_client_confirm_active(
+    config: &Config,
+    mut server_capability_sets: Vec<CapabilitySet>,
+) -> rdp::capability_sets::ClientConfirmActive {
+    use ironrdp_pdu::rdp::capability_sets::*;
+
+    // The client echoes the desktop size of the server, which may have changed it upon reactivation
+    let desktop_size = server_desktop_size(config, &server_capability_sets);
+
+    server_capability_sets.retain(|capability_set| matches!(capability_set, CapabilitySet::MultiFragmentUpdate(_)));
+
+    let lossy_bitmap_compression = config
+        .bitmap
+        .as_ref()
+        .map(|bitmap| bitmap.lossy_compression)
+        .unwrap_or(false);
+
+    let drawing_flags = if lossy_bitmap_compression {
+        BitmapDrawingFlags::ALLOW_SKIP_ALPHA
+            | BitmapDrawingFlags::ALLOW_DYNAMIC_COLOR_FIDELITY
+            | BitmapDrawingFlags::ALLOW_COLOR_SUBSAMPLING
+    } else {
+        BitmapDrawingFlags::ALLOW_SKIP_ALPHA
+    };
+
+    server_capability_sets.extend_from_slice(&[
+        CapabilitySet::General(General {
+            major_platform_type: config.platform,
+            minor_platform_type: MinorPlatformType::Unspecified,
+            extra_flags: GeneralExtraFlags::FASTPATH_OUTPUT_SUPPORTED | GeneralExtraFlags::NO_BITMAP_COMPRESSION_HDR,
+            refresh_rect_support: false,
+            suppress_output_support: false,
+        }),
+        CapabilitySet::Bitmap(Bitmap {
+            pref_bits_per_pix: config.bitmap.as_ref().map_or(32, |bitmap| bitmap.color_depth as u16),
+            desktop_width: desktop_size.width,
+            desktop_height: desktop_size.height,
+            // The server may change the desktop size through the Deactivation-Reactivation Sequence
+            desktop_resize_flag: true,
+            drawing_flags,
+        }),
+        CapabilitySet::Order(Order::new(
+            OrderFlags::NEGOTIATE_ORDER_SUPPORT | OrderFlags::ZERO_BOUNDS_DELTAS_SUPPORT,
+            OrderSupportExFlags::empty(),
+            0,
+            0,
+        )),
+        CapabilitySet::BitmapCache(BitmapCache {
+            caches: [CacheEntry {
+                entries: 0,
+                max_cell_size: 0,
+            }; BITMAP_CACHE_ENTRIES_NUM],
+        }),
+        CapabilitySet::Input(Input {
+            input_flags: InputFlags::all(),
+            keyboard_layout: config.keyboard_layout,
+            keyboard_type: Some(config.keyboard_type),
+            keyboard_subtype: config.keyboard_subtype,
+            keyboard_function_key: config.keyboard_functional_keys_count,
+            keyboard_ime_filename: config.ime_file_name.clone(),
+        }),
+        CapabilitySet::Pointer(Pointer {
+            color_pointer_cache_size: config.pointer_cache_size,
+            pointer_cache_size: config.pointer_cache_size,
+        }),
+        CapabilitySet::Brush(Brush {
+            support_level: SupportLevel::Default,
+        }),
+        CapabilitySet::GlyphCache(GlyphCache {
+            glyph_cache: [CacheDefinition {
+                entries: 0,
+                max_cell_size: 0,
+            }; GLYPH_CACHE_NUM],
+            frag_cache: CacheDefinition {
+                entries: 0,
+                max_cell_size: 0,
+            },
+            glyph_support_level: GlyphSupportLevel::None,
+        }),
+        CapabilitySet::OffscreenBitmapCache(OffscreenBitmapCache {
+            is_supported: false,
+            cache_size: 0,
+            cache_entries: 0,
+        }),
+        CapabilitySet::VirtualChannel(VirtualChannel {
+            flags: VirtualChannelFlags::NO_COMPRESSION,
+            chunk_size: Some(0), // ignored
+        }),
+        CapabilitySet::Sound(Sound {
+            flags: SoundFlags::empty(),
+        }),
+        CapabilitySet::LargePointer(LargePointer {
+            flags: LargePointerSupportFlags::UP_TO_96X96_PIXELS,
+        }),
+        CapabilitySet::SurfaceCommands(SurfaceCommands {
+            flags: CmdFlags::SET_SURFACE_BITS | CmdFlags::STREAM_SURFACE_BITS | CmdFlags::FRAME_MARKER,
+        }),
+        CapabilitySet::BitmapCodecs(BitmapCodecs(vec![Codec {
+            id: 0x03, // RemoteFX
+            property: CodecProperty::RemoteFx(RemoteFxContainer::ClientContainer(RfxClientCapsContainer {
+                capture_flags: CaptureFlags::empty(),
+                caps_data: RfxCaps(RfxCapset(vec![RfxICap {
+                    flags: RfxICapFlags::empty(),
+                    entropy_bits: EntropyBits::Rlgr3,
+                }])),
+            })),
+        }])),
+        CapabilitySet::FrameAcknowledge(FrameAcknowledge {
+            max_unacknowledged_frame_count: 2,
+        }),
+    ]);
+
+    if !server_capability_sets
+        .iter()
+        .any(|c| matches!(&c, CapabilitySet::MultiFragmentUpdate(_)))
+    {
+        server_capability_sets.push(CapabilitySet::MultiFragmentUpdate(MultifragmentUpdate {
+            max_request_size: 1024,
+        }));
+    }
+
+    ClientConfirmActive {
+        originator_id: SERVER_CHANNEL_ID,
+        pdu: DemandActive {
+            source_descriptor: "IRONRDP".to_owned(),
+            capability_sets: server_capability_sets,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use ironrdp_pdu::rdp::capability_sets::{Bitmap, BitmapDrawingFlags, DemandActive, SERVER_CHANNEL_ID};
+    use ironrdp_pdu::rdp::finalization_messages::{FontPdu, MonitorLayoutPdu, SequenceFlags};
+    use ironrdp_pdu::rdp::headers::{ShareControlHeader, ShareControlPdu, ShareDataHeader, ShareDataPdu};
+    use ironrdp_pdu::{mcs, PduParsing as _};
+
+    use super::*;
+    use crate::MonitorConfig;
+
+    const IO_CHANNEL_ID: u16 = 1003;
+    const USER_CHANNEL_ID: u16 = 1007;
+    const SHARE_ID: u32 = 0x0001_03ea;
+
+    fn monitor(left: i32, is_primary: bool) -> MonitorConfig {
+        MonitorConfig {
+            left,
+            top: 0,
+            width: 1920,
+            height: 1080,
+            is_primary,
+            physical_width: 0,
+            physical_height: 0,
+            orientation: gcc::MonitorOrientation::Landscape,
+            desktop_scale_factor: 100,
+            device_scale_factor: 100,
+        }
+    }
+
+    fn config() -> Config {
+        Config {
+            desktop_size: DesktopSize {
+                width: 1024,
+                height: 768,
+            },
+            security_protocol: ironrdp_pdu::nego::SecurityProtocol::SSL,
+            username: "user".to_owned(),
+            password: "password".to_owned(),
+            domain: None,
+            client_build: 0,
+            client_name: "client".to_owned(),
+            keyboard_layout: 0,
+            keyboard_type: gcc::KeyboardType::IbmEnhanced,
+            keyboard_subtype: 0,
+            keyboard_functional_keys_count: 12,
+            ime_file_name: String::new(),
+            graphics: None,
+            bitmap: None,
+            dig_product_id: String::new(),
+            client_dir: String::new(),
+            platform: rdp::capability_sets::MajorPlatformType::Unspecified,
+            enable_audio_playback: false,
+            pointer_cache_size: 32,
+            compression_type: None,
+            timezone: None,
+            performance_flags: rdp::client_info::PerformanceFlags::empty(),
+            standard_rdp_security: false,
+            static_channels: Vec::new(),
+            monitors: vec![monitor(0, true), monitor(1920, false)],
+        }
+    }
+
+    fn server_share_control(pdu: ShareControlPdu) -> Vec<u8> {
+        let header = ShareControlHeader {
+            share_control_pdu: pdu,
+            pdu_source: SERVER_CHANNEL_ID,
+            share_id: SHARE_ID,
+        };
+
+        let mut user_data = Vec::new();
+        header.to_buffer(&mut user_data).unwrap();
+
+        let indication = mcs::SendDataIndication {
+            initiator_id: SERVER_CHANNEL_ID,
+            channel_id: IO_CHANNEL_ID,
+            user_data: Cow::Owned(user_data),
+        };
+
+        let mut frame = Vec::new();
+        ironrdp_pdu::encode_buf(&indication, &mut frame).unwrap();
+        frame
+    }
+
+    fn server_share_data(pdu: ShareDataPdu) -> Vec<u8> {
+        server_share_control(ShareControlPdu::Data(ShareDataHeader {
+            share_data_pdu: pdu,
+            stream_priority: rdp::headers::StreamPriority::Medium,
+            compression_flags: rdp::headers::CompressionFlags::empty(),
+            compression_type: rdp::client_info::CompressionType::K8,
+        }))
+    }
+
+    fn server_demand_active(width: u16, height: u16) -> Vec<u8> {
+        server_share_control(ShareControlPdu::ServerDemandActive(
+            rdp::capability_sets::ServerDemandActive {
+                pdu: DemandActive {
+                    source_descriptor: "RDP".to_owned(),
+                    capability_sets: vec![CapabilitySet::Bitmap(Bitmap {
+                        pref_bits_per_pix: 32,
+                        desktop_width: width,
+                        desktop_height: height,
+                        desktop_resize_flag: true,
+                        drawing_flags: BitmapDrawingFlags::empty(),
+                    })],
+                },
+            },
+        ))
+    }
+
+    fn server_monitor_layout(monitors: Vec<gcc::Monitor>) -> Vec<u8> {
+        server_share_data(ShareDataPdu::MonitorLayout(MonitorLayoutPdu { monitors }))
+    }
+
+    fn server_font_map() -> Vec<u8> {
+        server_share_data(ShareDataPdu::FontMap(FontPdu {
+            number: 0,
+            total_number: 0,
+            flags: SequenceFlags::FIRST | SequenceFlags::LAST,
+            entry_size: 4,
+        }))
+    }
+
+    /// Steps the sequence with the server frames, sending the client PDUs which need no input in between
+    fn activate(sequence: &mut ConnectionActivationSequence, server_frames: &[Vec<u8>]) {
+        let mut output = Vec::new();
+
+        for frame in server_frames {
+            while sequence.next_pdu_hint().is_none() && !sequence.state.is_terminal() {
+                sequence.step_no_input(&mut output).unwrap();
+            }
+
+            sequence.step(frame, &mut output).unwrap();
+        }
+    }
+
+    fn monitor_layout() -> Vec<gcc::Monitor> {
+        vec![monitor(0, true).to_monitor(), monitor(1920, false).to_monitor()]
+    }
+
+    #[test]
+    fn monitor_layout_is_accepted_after_the_demand_active_pdu() {
+        let mut sequence = ConnectionActivationSequence::new(config(), IO_CHANNEL_ID, USER_CHANNEL_ID);
+
+        activate(
+            &mut sequence,
+            &[
+                server_demand_active(3840, 1080),
+                server_monitor_layout(monitor_layout()),
+                server_font_map(),
+            ],
+        );
+
+        assert!(matches!(sequence.state, ConnectionActivationState::Finalized { .. }));
+        assert_eq!(sequence.monitor_layout, Some(monitor_layout()));
+    }
+
+    #[test]
+    fn monitor_layout_is_accepted_before_the_demand_active_pdu() {
+        let mut sequence = ConnectionActivationSequence::new(config(), IO_CHANNEL_ID, USER_CHANNEL_ID);
+
+        activate(
+            &mut sequence,
+            &[
+                server_monitor_layout(monitor_layout()),
+                server_demand_active(3840, 1080),
+                server_font_map(),
+            ],
+        );
+
+        assert!(matches!(sequence.state, ConnectionActivationState::Finalized { .. }));
+        assert_eq!(sequence.monitor_layout, Some(monitor_layout()));
+    }
+
+    #[test]
+    fn monitor_layout_is_received_again_on_reactivation() {
+        let mut sequence = ConnectionActivationSequence::new(config(), IO_CHANNEL_ID, USER_CHANNEL_ID);
+
+        activate(
+            &mut sequence,
+            &[
+                server_demand_active(3840, 1080),
+                server_monitor_layout(monitor_layout()),
+                server_font_map(),
+            ],
+        );
+
+        sequence.reset();
+        assert_eq!(sequence.monitor_layout, None);
+
+        let single_monitor = vec![monitor(0, true).to_monitor()];
+
+        activate(
+            &mut sequence,
+            &[
+                server_demand_active(1920, 1080),
+                server_monitor_layout(single_monitor.clone()),
+                server_font_map(),
+            ],
+        );
+
+        assert!(matches!(sequence.state, ConnectionActivationState::Finalized { .. }));
+        assert_eq!(sequence.monitor_layout, Some(single_monitor));
+    }
+
+    /// Returns the desktop size of the Confirm Active PDU sent in response to the Demand Active PDU
+    fn confirm_active_desktop_size(sequence: &mut ConnectionActivationSequence, demand_active: &[u8]) -> (u16, u16) {
+        let mut output = Vec::new();
+        let written = sequence.step(demand_active, &mut output).unwrap();
+
+        let request = ironrdp_pdu::decode::<mcs::SendDataRequest<'_>>(&output[..written.size().unwrap()]).unwrap();
+        let header = ShareControlHeader::from_buffer(request.user_data.as_ref()).unwrap();
+
+        let ShareControlPdu::ClientConfirmActive(confirm_active) = header.share_control_pdu else {
+            panic!("expected a Confirm Active PDU");
+        };
+
+        confirm_active
+            .pdu
+            .capability_sets
+            .iter()
+            .find_map(|capability_set| match capability_set {
+                CapabilitySet::Bitmap(bitmap) => Some((bitmap.desktop_width, bitmap.desktop_height)),
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn confirm_active_echoes_the_desktop_size_of_the_server() {
+        let mut sequence = ConnectionActivationSequence::new(config(), IO_CHANNEL_ID, USER_CHANNEL_ID);
+
+        let desktop_size = confirm_active_desktop_size(&mut sequence, &server_demand_active(3840, 1080));
+        assert_eq!(desktop_size, (3840, 1080));
+
+        activate(&mut sequence, &[server_font_map()]);
+
+        // The server resized the desktop before reactivating the session
+        sequence.reset();
+
+        let desktop_size = confirm_active_desktop_size(&mut sequence, &server_demand_active(2560, 1440));
+        assert_eq!(desktop_size, (2560, 1440));
+
+        activate(&mut sequence, &[server_font_map()]);
+
+        assert!(matches!(
+            &sequence.state,
+            ConnectionActivationState::Finalized { desktop_size, .. }
+                if desktop_size.width == 2560 && desktop_size.height == 1440
+        ));
+    }
+}
@@ -1,13 +1,15 @@
 use std::mem;
+use std::sync::{Arc, Mutex};
 
+use ironrdp_graphics::bulk;
 use ironrdp_pdu::rdp::capability_sets::SERVER_CHANNEL_ID;
-use ironrdp_pdu::rdp::headers::ShareDataPdu;
+use ironrdp_pdu::rdp::headers::{ShareControlPdu, ShareDataPdu};
 use ironrdp_pdu::rdp::{finalization_messages, server_error_info};
-use ironrdp_pdu::PduHint;
+use ironrdp_pdu::{gcc, PduHint};
 
 use crate::{legacy, Error, Result, Sequence, State, Written};
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone)]
 #[non_exhaustive]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum ConnectionFinalizationState {
@@ -46,20 +48,28 @@ impl State for ConnectionFinalizationState {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct ConnectionFinalizationSequence {
     pub state: ConnectionFinalizationState,
     pub io_channel_id: u16,
     pub user_channel_id: u16,
+    /// Bulk decompressor of the server PDUs, whose history is kept by the active stage
+    #[cfg_attr(feature = "arbitrary", arbitrary(default))]
+    pub bulk_decompressor: Arc<Mutex<bulk::Decompressor>>,
+    /// Monitor layout sent by the server in the Monitor Layout PDU, if any
+    #[cfg_attr(feature = "arbitrary", arbitrary(default))]
+    pub monitor_layout: Option<Vec<gcc::Monitor>>,
 }
 
 impl ConnectionFinalizationSequence {
-    pub fn new(io_channel_id: u16, user_channel_id: u16) -> Self {
+    pub fn new(io_channel_id: u16, user_channel_id: u16, bulk_decompressor: Arc<Mutex<bulk::Decompressor>>) -> Self {
         Self {
             state: ConnectionFinalizationState::SendSynchronize,
             io_channel_id,
             user_channel_id,
+            bulk_decompressor,
+            monitor_layout: None,
         }
     }
 }
@@ -155,11 +165,20 @@ impl Sequence for ConnectionFinalizationSequence {
 
             ConnectionFinalizationState::WaitForResponse => {
                 let ctx = legacy::decode_send_data_indication(input)?;
-                let ctx = legacy::decode_share_data(ctx)?;
+                let ctx = legacy::decode_share_control(ctx)?;
 
-                debug!(message = ?ctx.pdu, "Received");
+                let ShareControlPdu::Data(share_data_header) = ctx.pdu else {
+                    return Err(Error::new(
+                        "received unexpected Share Control PDU (expected Share Data Header)",
+                    ));
+                };
+
+                // The server may already compress the PDUs of the connection finalization
+                let pdu = legacy::decompress_share_data(share_data_header, &self.bulk_decompressor)?;
+
+                debug!(message = ?pdu, "Received");
 
-                let next_state = match ctx.pdu {
+                let next_state = match pdu {
                     ShareDataPdu::Synchronize(_) => {
                         debug!("Server Synchronize");
                         ConnectionFinalizationState::WaitForResponse
@@ -204,6 +223,12 @@ impl Sequence for ConnectionFinalizationSequence {
                             }
                         }
                     }
+                    // Sent after the Demand Active PDU when the client advertised several monitors
+                    ShareDataPdu::MonitorLayout(monitor_layout) => {
+                        debug!(monitor_count = monitor_layout.monitors.len(), "Server Monitor Layout");
+                        self.monitor_layout = Some(monitor_layout.monitors);
+                        ConnectionFinalizationState::WaitForResponse
+                    }
                     ShareDataPdu::FontMap(_) => {
                         // https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpbcgr/023f1e69-cfe8-4ee6-9ee0-7e759fb4e4ee
                         //
@@ -1,7 +1,9 @@
 //! Legacy compat layer based on the old PduParsing trait
 
 use std::borrow::Cow;
+use std::sync::{Mutex, PoisonError};
 
+use ironrdp_graphics::bulk;
 use ironrdp_pdu::{rdp, x224, PduParsing};
 
 pub fn encode_x224_packet<T: PduParsing>(x224_msg: &T, buf: &mut Vec<u8>) -> crate::Result<usize>
@@ -184,6 +186,43 @@ pub fn decode_share_data(ctx: SendDataIndicationCtx<'_>) -> crate::Result<ShareD
     })
 }
 
+/// Returns the PDU of the Share Data Header, decoded once decompressed.
+///
+/// The compression flags are processed even for uncompressed PDUs, as they may flush the history.
+pub fn decompress_share_data(
+    share_data_header: rdp::headers::ShareDataHeader,
+    bulk_decompressor: &Mutex<bulk::Decompressor>,
+) -> crate::Result<rdp::headers::ShareDataPdu> {
+    if share_data_header.compression_flags.is_empty() {
+        return Ok(share_data_header.share_data_pdu);
+    }
+
+    let input = match &share_data_header.share_data_pdu {
+        rdp::headers::ShareDataPdu::Compressed(pdu) => pdu.data.as_slice(),
+        _ => &[],
+    };
+
+    let mut data = Vec::new();
+    bulk_decompressor
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .decompress(
+            input,
+            share_data_header.compression_type,
+            share_data_header.compression_flags,
+            &mut data,
+        )
+        .map_err(|e| crate::Error::new("failed to decompress the Share Data PDU").with_custom(e))?;
+
+    match share_data_header.share_data_pdu {
+        rdp::headers::ShareDataPdu::Compressed(pdu) => {
+            rdp::headers::ShareDataPdu::from_type(data.as_slice(), pdu.pdu_type)
+                .map_err(|e| crate::Error::new("invalid decompressed Share Data PDU").with_custom(e))
+        }
+        share_data_pdu => Ok(share_data_pdu),
+    }
+}
+
 impl From<ironrdp_pdu::mcs::McsError> for crate::Error {
     fn from(e: ironrdp_pdu::mcs::McsError) -> Self {
         Self::new("MCS").with_reason(e.to_string())
@@ -202,8 +241,61 @@ impl From<ironrdp_pdu::rdp::RdpError> for crate::Error {
     }
 }
 
+impl From<ironrdp_pdu::rdp::server_redirection::ServerRedirectionError> for crate::Error {
+    fn from(e: ironrdp_pdu::rdp::server_redirection::ServerRedirectionError) -> Self {
+        Self::new("server redirection").with_reason(e.to_string())
+    }
+}
+
+impl From<ironrdp_pdu::rdp::standard_security::StandardSecurityError> for crate::Error {
+    fn from(e: ironrdp_pdu::rdp::standard_security::StandardSecurityError) -> Self {
+        Self::new("standard RDP security").with_reason(e.to_string())
+    }
+}
+
 impl From<ironrdp_pdu::rdp::vc::ChannelError> for crate::Error {
     fn from(e: ironrdp_pdu::rdp::vc::ChannelError) -> Self {
         Self::new("virtual channel").with_reason(e.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use ironrdp_pdu::rdp::client_info::CompressionType;
+    use ironrdp_pdu::rdp::finalization_messages::{FontPdu, SequenceFlags};
+    use ironrdp_pdu::rdp::headers::{
+        CompressedShareDataPdu, CompressionFlags, ShareDataHeader, ShareDataPdu, ShareDataPduType, StreamPriority,
+    };
+
+    use super::*;
+
+    #[test]
+    fn compressed_share_data_is_decoded() {
+        let bulk_decompressor = Mutex::new(bulk::Decompressor::new());
+
+        // MPPC encodes the bytes lower than 0x80 as themselves
+        let font_map = vec![0x00, 0x00, 0x00, 0x00, 0x03, 0x00, 0x04, 0x00];
+        let share_data_header = ShareDataHeader {
+            share_data_pdu: ShareDataPdu::Compressed(CompressedShareDataPdu {
+                pdu_type: ShareDataPduType::FontMap,
+                uncompressed_length: 8,
+                data: font_map,
+            }),
+            stream_priority: StreamPriority::Medium,
+            compression_flags: CompressionFlags::COMPRESSED | CompressionFlags::FLUSHED,
+            compression_type: CompressionType::K8,
+        };
+
+        let pdu = decompress_share_data(share_data_header, &bulk_decompressor).unwrap();
+
+        assert_eq!(
+            pdu,
+            ShareDataPdu::FontMap(FontPdu {
+                number: 0,
+                total_number: 0,
+                flags: SequenceFlags::FIRST | SequenceFlags::LAST,
+                entry_size: 4,
+            })
+        );
+    }
+}
@@ -5,24 +5,33 @@ pub mod legacy;
 
 mod channel_connection;
 mod connection;
+mod connection_activation;
 mod connection_finalization;
 mod license_exchange;
 mod server_name;
+mod standard_security;
 
 use core::any::Any;
 use core::fmt;
 
-use ironrdp_pdu::rdp::capability_sets;
+use ironrdp_pdu::rdp::{capability_sets, client_info};
 use ironrdp_pdu::{gcc, nego, PduHint};
 
 type StaticChannels = std::collections::HashMap<String, u16>;
 
 pub use channel_connection::{ChannelConnectionSequence, ChannelConnectionState};
-pub use connection::{ClientConnector, ClientConnectorState, ConnectionResult};
+pub use connection::{
+    ClientConnector, ClientConnectorState, ConnectionOutcome, ConnectionResult, CredsspTsRequestHint,
+    CREDSSP_TS_REQUEST_HINT,
+};
+pub use connection_activation::{
+    create_client_confirm_active, ConnectionActivationSequence, ConnectionActivationState,
+};
 pub use connection_finalization::{ConnectionFinalizationSequence, ConnectionFinalizationState};
 pub use license_exchange::{LicenseExchangeSequence, LicenseExchangeState};
 pub use server_name::ServerName;
 pub use sspi;
+pub use standard_security::StandardSecurity;
 
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
@@ -41,16 +50,67 @@ pub struct GraphicsConfig {
     pub capabilities: u32,
 }
 
+/// A monitor of the client, in the coordinates of the virtual desktop
+///
+/// The top-left corner of the primary monitor is the origin of the virtual desktop, so other monitors may have
+/// negative coordinates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MonitorConfig {
+    pub left: i32,
+    pub top: i32,
+    pub width: u32,
+    pub height: u32,
+    pub is_primary: bool,
+    /// Physical width of the monitor in millimeters, or 0 if unknown
+    pub physical_width: u32,
+    /// Physical height of the monitor in millimeters, or 0 if unknown
+    pub physical_height: u32,
+    pub orientation: gcc::MonitorOrientation,
+    /// Desktop scale factor in percent, from 100 to 500
+    pub desktop_scale_factor: u32,
+    /// Device scale factor in percent: 100, 140 or 180
+    pub device_scale_factor: u32,
+}
+
+impl MonitorConfig {
+    /// Inclusive bounds of the monitor, as sent in the Client Monitor Data
+    pub fn to_monitor(&self) -> gcc::Monitor {
+        gcc::Monitor {
+            left: self.left,
+            top: self.top,
+            right: self.left.saturating_add_unsigned(self.width).saturating_sub(1),
+            bottom: self.top.saturating_add_unsigned(self.height).saturating_sub(1),
+            flags: if self.is_primary {
+                gcc::MonitorFlags::PRIMARY
+            } else {
+                gcc::MonitorFlags::empty()
+            },
+        }
+    }
+
+    pub fn to_extended_monitor_info(&self) -> gcc::ExtendedMonitorInfo {
+        gcc::ExtendedMonitorInfo {
+            physical_width: self.physical_width,
+            physical_height: self.physical_height,
+            orientation: self.orientation,
+            desktop_scale_factor: self.desktop_scale_factor,
+            device_scale_factor: self.device_scale_factor,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct BitmapConfig {
     pub lossy_compression: bool,
+    /// Requested color depth in bits per pixel: 8, 15, 16, 24 or 32
     pub color_depth: u32,
 }
 
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct Config {
+    /// Size of the desktop, ignored in favor of the bounding box of `monitors` when some are provided
     pub desktop_size: DesktopSize,
     pub security_protocol: nego::SecurityProtocol,
     pub username: String,
@@ -60,6 +120,8 @@ pub struct Config {
     pub client_build: u32,
     /// Name of the client computer. Truncated to the 15 first characters.
     pub client_name: String,
+    /// Active input locale identifier (KLID) of the client, e.g.: 0x0407 for German, or 0 for the server default
+    pub keyboard_layout: u32,
     pub keyboard_type: gcc::KeyboardType,
     pub keyboard_subtype: u32,
     pub keyboard_functional_keys_count: u32,
@@ -69,6 +131,55 @@ pub struct Config {
     pub dig_product_id: String,
     pub client_dir: String,
     pub platform: capability_sets::MajorPlatformType,
+    /// Whether the server should redirect the audio output to the client
+    pub enable_audio_playback: bool,
+    /// Number of entries of the pointer cache advertised to the server, or 0 to disable the pointer updates
+    pub pointer_cache_size: u16,
+    /// Bulk compression of the server PDUs advertised in the Client Info PDU, or `None` to disable it
+    #[cfg_attr(feature = "arbitrary", arbitrary(default))]
+    pub compression_type: Option<client_info::CompressionType>,
+    /// Time zone of the client sent in the Client Info PDU, or `None` for UTC
+    #[cfg_attr(feature = "arbitrary", arbitrary(default))]
+    pub timezone: Option<client_info::TimezoneInfo>,
+    /// Visual effects of the session to disable or enable, e.g.: the wallpaper or the font smoothing
+    #[cfg_attr(feature = "arbitrary", arbitrary(default))]
+    pub performance_flags: client_info::PerformanceFlags,
+    /// Whether Standard RDP Security (RSA key exchange and RC4 encryption) is accepted when selected by the server
+    ///
+    /// It is only selected when `security_protocol` is `SecurityProtocol::RDP`, in which case no TLS upgrade must be
+    /// performed. The FIPS encryption method is not supported.
+    pub standard_rdp_security: bool,
+    /// Static virtual channels to request, besides the dynamic virtual channel
+    ///
+    /// The processors of the channels are registered when creating the session.
+    #[cfg_attr(feature = "arbitrary", arbitrary(default))]
+    pub static_channels: Vec<gcc::Channel>,
+    /// Monitors spanned by the session, or an empty list for a single monitor of `desktop_size`
+    ///
+    /// Exactly one monitor must be primary, with its top-left corner at the origin. Up to 16 monitors are supported.
+    #[cfg_attr(feature = "arbitrary", arbitrary(default))]
+    pub monitors: Vec<MonitorConfig>,
+}
+
+impl Config {
+    /// Size of the virtual desktop, that is the bounding box of the monitors if any
+    pub fn virtual_desktop_size(&self) -> DesktopSize {
+        if self.monitors.is_empty() {
+            return self.desktop_size.clone();
+        }
+
+        let monitors = self.monitors.iter().map(MonitorConfig::to_monitor);
+
+        let left = monitors.clone().map(|monitor| monitor.left).min().unwrap_or(0);
+        let top = monitors.clone().map(|monitor| monitor.top).min().unwrap_or(0);
+        let right = monitors.clone().map(|monitor| monitor.right).max().unwrap_or(0);
+        let bottom = monitors.map(|monitor| monitor.bottom).max().unwrap_or(0);
+
+        DesktopSize {
+            width: u16::try_from(i64::from(right) - i64::from(left) + 1).unwrap_or(u16::MAX),
+            height: u16::try_from(i64::from(bottom) - i64::from(top) + 1).unwrap_or(u16::MAX),
+        }
+    }
 }
 
 pub trait State: Send + Sync + core::fmt::Debug {
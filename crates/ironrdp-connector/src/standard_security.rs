@@ -0,0 +1,366 @@
+//! Encryption of the PDUs once Standard RDP Security is in effect
+//!
+//! Every slow-path PDU exchanged after the Security Exchange PDU starts with a security header, and the encrypted
+//! PDUs carry the MAC signature of their plain data.
+
+use std::borrow::Cow;
+
+use ironrdp_pdu::fast_path::EncryptionFlags;
+use ironrdp_pdu::mcs::{McsMessage, SendDataIndication, SendDataRequest};
+use ironrdp_pdu::rdp::headers::{
+    BasicSecurityHeaderFlags, ShareControlHeader, ShareControlPdu, BASIC_SECURITY_HEADER_SIZE,
+};
+use ironrdp_pdu::rdp::server_redirection::ServerRedirectionPdu;
+use ironrdp_pdu::rdp::standard_security::{EncryptionContext, SIGNATURE_SIZE};
+use ironrdp_pdu::{Action, PduParsing as _};
+
+use crate::{Error, Result};
+
+/// Fast-path header with a two-byte length field
+const FAST_PATH_HEADER_SIZE: usize = 3;
+
+/// Encrypts the frames sent to the server and decrypts the frames received from it
+#[derive(Debug, Clone)]
+pub struct StandardSecurity {
+    context: EncryptionContext,
+}
+
+impl StandardSecurity {
+    pub fn new(context: EncryptionContext) -> Self {
+        Self { context }
+    }
+
+    /// Decrypts a complete slow-path or fast-path frame received from the server.
+    ///
+    /// The security header is removed from the slow-path PDUs, except for the licensing PDUs which keep a Basic
+    /// Security Header without the encryption flags. The Server Redirection PDU is turned into its Enhanced Security
+    /// form.
+    pub fn decrypt_frame(&mut self, frame: &[u8]) -> Result<Vec<u8>> {
+        let action = frame
+            .first()
+            .map(|header| Action::from_fp_output_header(*header))
+            .ok_or(Error::new("empty frame"))?
+            .map_err(|_| Error::new("invalid action code"))?;
+
+        match action {
+            Action::X224 => self.decrypt_slow_path(frame),
+            Action::FastPath => self.decrypt_fast_path(frame),
+        }
+    }
+
+    /// Encrypts the complete frames sent to the server, and appends them to `output`.
+    ///
+    /// When `has_security_header` is set, the slow-path PDUs already start with a Basic Security Header (e.g.:
+    /// Client Info PDU) whose flags are kept. Returns the number of bytes written.
+    pub fn encrypt_frames(
+        &mut self,
+        mut frames: &[u8],
+        has_security_header: bool,
+        output: &mut Vec<u8>,
+    ) -> Result<usize> {
+        let initial_length = output.len();
+
+        while !frames.is_empty() {
+            let pdu_info = ironrdp_pdu::find_size(frames)?.ok_or(Error::new("truncated frame"))?;
+            let (frame, remaining) = frames.split_at(pdu_info.length);
+
+            match pdu_info.action {
+                Action::X224 => self.encrypt_slow_path(frame, has_security_header, output)?,
+                Action::FastPath => self.encrypt_fast_path(frame, output)?,
+            }
+
+            frames = remaining;
+        }
+
+        Ok(output.len() - initial_length)
+    }
+
+    fn decrypt_slow_path(&mut self, frame: &[u8]) -> Result<Vec<u8>> {
+        let McsMessage::SendDataIndication(indication) = ironrdp_pdu::decode::<McsMessage<'_>>(frame)? else {
+            return Ok(frame.to_vec());
+        };
+
+        let user_data = indication.user_data.as_ref();
+        if user_data.len() < BASIC_SECURITY_HEADER_SIZE {
+            return Err(Error::new("security header is missing"));
+        }
+
+        let flags = BasicSecurityHeaderFlags::from_bits_truncate(u16::from_le_bytes([user_data[0], user_data[1]]));
+        let data = &user_data[BASIC_SECURITY_HEADER_SIZE..];
+
+        let data = if flags.contains(BasicSecurityHeaderFlags::ENCRYPT) {
+            let (signature, encrypted) = split_signature(data)?;
+
+            self.context.decrypt(
+                encrypted,
+                signature,
+                flags.contains(BasicSecurityHeaderFlags::SECURE_CHECKSUM),
+            )?
+        } else {
+            data.to_vec()
+        };
+
+        let user_data = if flags.contains(BasicSecurityHeaderFlags::LICENSE_PKT) {
+            let flags = flags - (BasicSecurityHeaderFlags::ENCRYPT | BasicSecurityHeaderFlags::SECURE_CHECKSUM);
+            security_header_with_data(flags, &[&data])
+        } else if flags.contains(BasicSecurityHeaderFlags::REDIRECTION_PKT) {
+            enhanced_security_server_redirection(indication.initiator_id, &data)?
+        } else {
+            data
+        };
+
+        let indication = McsMessage::SendDataIndication(SendDataIndication {
+            initiator_id: indication.initiator_id,
+            channel_id: indication.channel_id,
+            user_data: Cow::Owned(user_data),
+        });
+
+        let mut output = Vec::new();
+        let written = ironrdp_pdu::encode_buf(&indication, &mut output)?;
+        output.truncate(written);
+
+        Ok(output)
+    }
+
+    fn decrypt_fast_path(&mut self, frame: &[u8]) -> Result<Vec<u8>> {
+        let (header, payload) = split_fast_path_header(frame)?;
+
+        let flags = EncryptionFlags::from_bits_truncate(header >> 6);
+        if !flags.contains(EncryptionFlags::ENCRYPTED) {
+            return Ok(frame.to_vec());
+        }
+
+        let (signature, encrypted) = split_signature(payload)?;
+        let data = self
+            .context
+            .decrypt(encrypted, signature, flags.contains(EncryptionFlags::SECURE_CHECKSUM))?;
+
+        let mut output = Vec::with_capacity(FAST_PATH_HEADER_SIZE + data.len());
+        write_fast_path_header(header & 0x3F, data.len(), &mut output)?;
+        output.extend_from_slice(&data);
+
+        Ok(output)
+    }
+
+    fn encrypt_slow_path(&mut self, frame: &[u8], has_security_header: bool, output: &mut Vec<u8>) -> Result<()> {
+        let McsMessage::SendDataRequest(request) = ironrdp_pdu::decode::<McsMessage<'_>>(frame)? else {
+            output.extend_from_slice(frame);
+            return Ok(());
+        };
+
+        let user_data = request.user_data.as_ref();
+
+        let (flags, data) = if has_security_header {
+            if user_data.len() < BASIC_SECURITY_HEADER_SIZE {
+                return Err(Error::new("security header is missing"));
+            }
+
+            let flags = BasicSecurityHeaderFlags::from_bits_truncate(u16::from_le_bytes([user_data[0], user_data[1]]));
+            (flags, &user_data[BASIC_SECURITY_HEADER_SIZE..])
+        } else {
+            (BasicSecurityHeaderFlags::empty(), user_data)
+        };
+
+        let (signature, encrypted) = self.context.encrypt(data);
+
+        let request = McsMessage::SendDataRequest(SendDataRequest {
+            initiator_id: request.initiator_id,
+            channel_id: request.channel_id,
+            user_data: Cow::Owned(security_header_with_data(
+                flags | BasicSecurityHeaderFlags::ENCRYPT,
+                &[&signature, &encrypted],
+            )),
+        });
+
+        let mut encoded = Vec::new();
+        let written = ironrdp_pdu::encode_buf(&request, &mut encoded)?;
+        output.extend_from_slice(&encoded[..written]);
+
+        Ok(())
+    }
+
+    fn encrypt_fast_path(&mut self, frame: &[u8], output: &mut Vec<u8>) -> Result<()> {
+        let (header, payload) = split_fast_path_header(frame)?;
+
+        // The number of events, when not in the header, is encrypted along with the events
+        let (signature, encrypted) = self.context.encrypt(payload);
+
+        let header = header | (EncryptionFlags::ENCRYPTED.bits() << 6);
+        write_fast_path_header(header, SIGNATURE_SIZE + encrypted.len(), output)?;
+        output.extend_from_slice(&signature);
+        output.extend_from_slice(&encrypted);
+
+        Ok(())
+    }
+}
+
+fn security_header_with_data(flags: BasicSecurityHeaderFlags, data: &[&[u8]]) -> Vec<u8> {
+    let mut user_data = Vec::with_capacity(BASIC_SECURITY_HEADER_SIZE + data.iter().map(|d| d.len()).sum::<usize>());
+    user_data.extend_from_slice(&flags.bits().to_le_bytes());
+    user_data.extend_from_slice(&0u16.to_le_bytes()); // flags_hi
+
+    for data in data {
+        user_data.extend_from_slice(data);
+    }
+
+    user_data
+}
+
+/// Turns the Standard Security Server Redirection PDU into the Enhanced Security one, so that the redirection is
+/// processed the same way regardless of the security in effect.
+fn enhanced_security_server_redirection(initiator_id: u16, data: &[u8]) -> Result<Vec<u8>> {
+    let share_control_header = ShareControlHeader {
+        share_control_pdu: ShareControlPdu::ServerRedirect(ServerRedirectionPdu::from_buffer(data)?),
+        pdu_source: initiator_id,
+        share_id: 0,
+    };
+
+    let mut user_data = Vec::with_capacity(share_control_header.buffer_length());
+    share_control_header.to_buffer(&mut user_data)?;
+
+    Ok(user_data)
+}
+
+fn split_signature(data: &[u8]) -> Result<(&[u8], &[u8])> {
+    if data.len() < SIGNATURE_SIZE {
+        return Err(Error::new("MAC signature is missing"));
+    }
+
+    Ok(data.split_at(SIGNATURE_SIZE))
+}
+
+/// Returns the first byte of the fast-path header, and the data following the length field.
+fn split_fast_path_header(frame: &[u8]) -> Result<(u8, &[u8])> {
+    let (header_length, header) = match frame {
+        [header, length, _, ..] if length & 0x80 != 0 => (3, *header),
+        [header, _, ..] => (2, *header),
+        _ => return Err(Error::new("truncated fast-path header")),
+    };
+
+    Ok((header, &frame[header_length..]))
+}
+
+fn write_fast_path_header(header: u8, data_length: usize, output: &mut Vec<u8>) -> Result<()> {
+    let length = u16::try_from(FAST_PATH_HEADER_SIZE + data_length)
+        .ok()
+        .filter(|length| *length <= 0x7FFF)
+        .ok_or(Error::new("fast-path PDU is too big"))?;
+
+    output.push(header);
+    output.extend_from_slice(&(length | 0x8000).to_be_bytes());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use ironrdp_pdu::gcc::EncryptionMethod;
+    use ironrdp_pdu::rdp::server_redirection::ServerRedirectionFlags;
+
+    use super::*;
+
+    fn standard_security() -> StandardSecurity {
+        let context = EncryptionContext::new(EncryptionMethod::BIT_128, &[0x11; 32], &[0x22; 32]).unwrap();
+        StandardSecurity::new(context)
+    }
+
+    #[test]
+    fn encrypts_send_data_requests() {
+        let mut security = standard_security();
+
+        let mut frame = Vec::new();
+        crate::legacy::encode_send_data_request(
+            1007,
+            1003,
+            &ironrdp_pdu::rdp::headers::BasicSecurityHeader {
+                flags: BasicSecurityHeaderFlags::INFO_PKT,
+            },
+            &mut frame,
+        )
+        .unwrap();
+
+        let mut output = Vec::new();
+        let written = security.encrypt_frames(&frame, true, &mut output).unwrap();
+        assert_eq!(written, output.len());
+
+        let McsMessage::SendDataRequest(request) = ironrdp_pdu::decode::<McsMessage<'_>>(&output).unwrap() else {
+            panic!("unexpected MCS message");
+        };
+
+        assert_eq!(request.channel_id, 1003);
+        assert_eq!(request.user_data.len(), BASIC_SECURITY_HEADER_SIZE + SIGNATURE_SIZE);
+        assert_eq!(
+            request.user_data[..2],
+            (BasicSecurityHeaderFlags::INFO_PKT | BasicSecurityHeaderFlags::ENCRYPT)
+                .bits()
+                .to_le_bytes()
+        );
+    }
+
+    #[test]
+    fn encrypts_fast_path_input() {
+        let mut security = standard_security();
+
+        // One event in the header, followed by a synchronize event
+        let frame = [0x04, 0x03, 0x60];
+
+        let mut output = Vec::new();
+        security.encrypt_frames(&frame, false, &mut output).unwrap();
+
+        assert_eq!(output.len(), FAST_PATH_HEADER_SIZE + SIGNATURE_SIZE + 1);
+        assert_eq!(output[..3], [0x84, 0x80, 0x0C]);
+        assert_ne!(output[11], 0x60);
+    }
+
+    #[test]
+    fn passes_unencrypted_fast_path_output_through() {
+        let mut security = standard_security();
+
+        let frame = [0x00, 0x05, 0x01, 0x02, 0x03];
+
+        assert_eq!(security.decrypt_frame(&frame).unwrap(), frame);
+    }
+
+    #[test]
+    fn converts_server_redirection_to_enhanced_security() {
+        let mut security = standard_security();
+
+        let redirection = ServerRedirectionPdu {
+            session_id: 7,
+            flags: ServerRedirectionFlags::TARGET_NET_ADDRESS,
+            target_net_address: Some(String::from("192.168.1.2")),
+            load_balance_info: None,
+            username: None,
+            domain: None,
+            password: None,
+            target_fqdn: None,
+            target_netbios_name: None,
+            tsv_url: None,
+            redirection_guid: None,
+            target_certificate: None,
+            target_net_addresses: None,
+        };
+
+        let mut redirection_packet = Vec::new();
+        redirection.to_buffer(&mut redirection_packet).unwrap();
+
+        let indication = McsMessage::SendDataIndication(SendDataIndication {
+            initiator_id: 1002,
+            channel_id: 1003,
+            user_data: Cow::Owned(security_header_with_data(
+                BasicSecurityHeaderFlags::REDIRECTION_PKT,
+                &[&redirection_packet],
+            )),
+        });
+
+        let mut frame = Vec::new();
+        let written = ironrdp_pdu::encode_buf(&indication, &mut frame).unwrap();
+
+        let frame = security.decrypt_frame(&frame[..written]).unwrap();
+
+        let ctx = crate::legacy::decode_send_data_indication(&frame).unwrap();
+        let ctx = crate::legacy::decode_share_control(ctx).unwrap();
+
+        assert_eq!(ctx.pdu, ShareControlPdu::ServerRedirect(redirection));
+    }
+}
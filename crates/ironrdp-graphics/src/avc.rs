@@ -0,0 +1,431 @@
+//! AVC420 and AVC444 codecs ([MS-RDPEGFX] 2.2.4.4, 2.2.4.5 and 2.2.4.6)
+//!
+//! AVC420 bitmaps are single H.264 pictures in the YUV 4:2:0 format. AVC444 bitmaps send YUV 4:4:4 pictures as
+//! two YUV 4:2:0 pictures decoded by the same H.264 decoder:
+//!
+//! - the main view (luma), holding the luma and the subsampled chroma, which is displayed as an AVC420 picture;
+//! - the auxiliary view (chroma), holding the chroma samples missing from the main view.
+//!
+//! A bitmap may only carry one of the views, so the YUV 4:4:4 planes are kept between the bitmaps. The samples
+//! of the auxiliary view are laid out differently by AVC444 ([MS-RDPEGFX] 3.3.8.3.2) and AVC444v2
+//! ([MS-RDPEGFX] 3.3.8.3.3).
+//!
+//! Only the areas covered by the rectangles of the metablocks are converted to BGRA, the rest of the pictures
+//! is left as is on the surface.
+
+use core::fmt;
+
+use ironrdp_pdu::dvc::gfx::{Avc420BitmapStream, Avc444BitmapStream, Encoding};
+use ironrdp_pdu::geometry::Rectangle;
+use thiserror::Error;
+
+use crate::h264::{H264Decoder, H264Error, YuvFrame};
+
+const BYTES_PER_PIXEL: usize = 4;
+
+/// Smallest difference between a chroma sample and the one reconstructed by the chroma filter for the latter
+/// to be used ([MS-RDPEGFX] 3.3.8.3.2)
+const CHROMA_FILTER_THRESHOLD: i32 = 30;
+
+#[derive(Debug, Error)]
+pub enum AvcError {
+    #[error("H.264 decoder error: {0}")]
+    H264(#[from] H264Error),
+    #[error("decoded picture of {width}x{height} pixels is smaller than the surface")]
+    InvalidFrameSize { width: usize, height: usize },
+    #[error("output buffer is not large enough to hold the surface")]
+    InvalidOutputSize,
+    #[error("invalid AVC444 encoding: {0:#04x}")]
+    InvalidEncoding(u8),
+}
+
+/// Layout of the auxiliary view of AVC444 bitmaps
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Avc444Version {
+    /// RDPGFX_CODECID_AVC444
+    V1,
+    /// RDPGFX_CODECID_AVC444V2
+    V2,
+}
+
+/// Decoder of the AVC bitmaps drawn on a surface
+///
+/// The bitmaps are decoded into a BGRA buffer of the size of the surface, 4 bytes per pixel, one row after the
+/// other.
+pub struct AvcDecoder {
+    decoder: Box<dyn H264Decoder>,
+    width: usize,
+    height: usize,
+    /// YUV 4:4:4 planes of the AVC444 bitmaps, with an even width and height, allocated on the first AVC444 bitmap
+    yuv444: Option<Yuv444Planes>,
+}
+
+impl fmt::Debug for AvcDecoder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AvcDecoder")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AvcDecoder {
+    /// Creates a decoder for a `width` x `height` surface.
+    pub fn new(decoder: Box<dyn H264Decoder>, width: usize, height: usize) -> Self {
+        Self {
+            decoder,
+            width,
+            height,
+            yuv444: None,
+        }
+    }
+
+    /// Decodes an AVC420 bitmap into `output`.
+    ///
+    /// Returns the updated areas of `output`, with exclusive right and bottom bounds.
+    pub fn decode_avc420(
+        &mut self,
+        stream: &Avc420BitmapStream<'_>,
+        output: &mut [u8],
+    ) -> Result<Vec<Rectangle>, AvcError> {
+        self.check_output(output)?;
+
+        let Some(frame) = self.decoder.decode(stream.data)? else {
+            return Ok(Vec::new());
+        };
+        self.check_frame(&frame)?;
+
+        let regions = self.regions(&stream.rectangles);
+
+        for region in &regions {
+            for y in region.top..region.bottom {
+                let y_row = &frame.y[y * frame.y_stride..];
+                let u_row = &frame.u[y / 2 * frame.uv_stride..];
+                let v_row = &frame.v[y / 2 * frame.uv_stride..];
+                let output_row = &mut output[(y * self.width + region.left) * BYTES_PER_PIXEL..];
+
+                for (x, pixel) in (region.left..region.right).zip(output_row.chunks_exact_mut(BYTES_PER_PIXEL)) {
+                    write_bgra(pixel, y_row[x], u_row[x / 2], v_row[x / 2]);
+                }
+            }
+        }
+
+        Ok(regions.into_iter().map(Region::to_rectangle).collect())
+    }
+
+    /// Decodes an AVC444 or AVC444v2 bitmap into `output`.
+    ///
+    /// Returns the updated areas of `output`, with exclusive right and bottom bounds.
+    pub fn decode_avc444(
+        &mut self,
+        stream: &Avc444BitmapStream<'_>,
+        version: Avc444Version,
+        output: &mut [u8],
+    ) -> Result<Vec<Rectangle>, AvcError> {
+        self.check_output(output)?;
+
+        // LUMA_AND_CHROMA is zero, so the encoding is compared rather than tested with `contains`
+        let (main_view, auxiliary_view) = if stream.encoding == Encoding::LUMA_AND_CHROMA {
+            (Some(&stream.stream1), stream.stream2.as_ref())
+        } else if stream.encoding == Encoding::LUMA {
+            (Some(&stream.stream1), None)
+        } else if stream.encoding == Encoding::CHROMA {
+            (None, Some(&stream.stream1))
+        } else {
+            return Err(AvcError::InvalidEncoding(stream.encoding.bits()));
+        };
+
+        let (width, height) = (self.width, self.height);
+        let mut updated_regions = Vec::new();
+
+        if let Some(main_view) = main_view {
+            if let Some(frame) = self.decoder.decode(main_view.data)? {
+                self.check_frame(&frame)?;
+
+                let regions = self.regions(&main_view.rectangles);
+                let planes = self.yuv444.get_or_insert_with(|| Yuv444Planes::new(width, height));
+
+                for region in &regions {
+                    planes.write_main_view(&frame, region.to_even());
+                }
+
+                updated_regions.extend(regions);
+            }
+        }
+
+        if let Some(auxiliary_view) = auxiliary_view {
+            if let Some(frame) = self.decoder.decode(auxiliary_view.data)? {
+                self.check_frame(&frame)?;
+
+                let regions = self.regions(&auxiliary_view.rectangles);
+                let planes = self.yuv444.get_or_insert_with(|| Yuv444Planes::new(width, height));
+
+                for region in &regions {
+                    let region = region.to_even();
+
+                    match version {
+                        Avc444Version::V1 => planes.write_auxiliary_view_v1(&frame, region)?,
+                        Avc444Version::V2 => planes.write_auxiliary_view_v2(&frame, width, region),
+                    }
+
+                    planes.filter_chroma(region);
+                }
+
+                updated_regions.extend(regions);
+            }
+        }
+
+        if let Some(planes) = &self.yuv444 {
+            for region in &updated_regions {
+                planes.write_bgra(region, width, output);
+            }
+        }
+
+        Ok(updated_regions.into_iter().map(Region::to_rectangle).collect())
+    }
+
+    fn check_output(&self, output: &[u8]) -> Result<(), AvcError> {
+        if output.len() < self.width * self.height * BYTES_PER_PIXEL {
+            return Err(AvcError::InvalidOutputSize);
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the planes of the picture cover the surface, once rounded up to an even size.
+    fn check_frame(&self, frame: &YuvFrame) -> Result<(), AvcError> {
+        let chroma_width = frame.width / 2 + frame.width % 2;
+        let chroma_height = frame.height / 2 + frame.height % 2;
+
+        let is_valid = frame.width >= round_up_to_even(self.width)
+            && frame.height >= round_up_to_even(self.height)
+            && frame.y_stride >= frame.width
+            && frame.uv_stride >= chroma_width
+            && frame.y.len() >= frame.y_stride * frame.height
+            && frame.u.len() >= frame.uv_stride * chroma_height
+            && frame.v.len() >= frame.uv_stride * chroma_height;
+
+        if !is_valid {
+            return Err(AvcError::InvalidFrameSize {
+                width: frame.width,
+                height: frame.height,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Non-empty parts of the metablock rectangles which are inside of the surface.
+    fn regions(&self, rectangles: &[Rectangle]) -> Vec<Region> {
+        rectangles
+            .iter()
+            .map(|rectangle| Region {
+                left: usize::from(rectangle.left),
+                top: usize::from(rectangle.top),
+                right: usize::from(rectangle.right).min(self.width),
+                bottom: usize::from(rectangle.bottom).min(self.height),
+            })
+            .filter(|region| region.left < region.right && region.top < region.bottom)
+            .collect()
+    }
+}
+
+/// Area of the surface, with exclusive right and bottom bounds
+#[derive(Debug, Clone, Copy)]
+struct Region {
+    left: usize,
+    top: usize,
+    right: usize,
+    bottom: usize,
+}
+
+impl Region {
+    /// Smallest region with even bounds containing this one, as the chroma samples are recombined by 2x2 blocks.
+    fn to_even(self) -> Self {
+        Self {
+            left: self.left & !1,
+            top: self.top & !1,
+            right: round_up_to_even(self.right),
+            bottom: round_up_to_even(self.bottom),
+        }
+    }
+
+    fn to_rectangle(self) -> Rectangle {
+        // Bounds rounded up to even may exceed the range of a rectangle by one
+        let clamp = |bound: usize| u16::try_from(bound).unwrap_or(u16::MAX);
+
+        Rectangle {
+            left: clamp(self.left),
+            top: clamp(self.top),
+            right: clamp(self.right),
+            bottom: clamp(self.bottom),
+        }
+    }
+}
+
+/// Full resolution Y, U and V planes, `stride` bytes per row
+struct Yuv444Planes {
+    stride: usize,
+    y: Vec<u8>,
+    u: Vec<u8>,
+    v: Vec<u8>,
+}
+
+impl Yuv444Planes {
+    fn new(width: usize, height: usize) -> Self {
+        let stride = round_up_to_even(width);
+        let size = stride * round_up_to_even(height);
+
+        Self {
+            stride,
+            y: vec![0; size],
+            u: vec![128; size],
+            v: vec![128; size],
+        }
+    }
+
+    /// Copies the luma, and upsamples the chroma of the main view.
+    fn write_main_view(&mut self, frame: &YuvFrame, region: Region) {
+        for y in region.top..region.bottom {
+            let row = y * self.stride;
+            let y_row = &frame.y[y * frame.y_stride..];
+            let u_row = &frame.u[y / 2 * frame.uv_stride..];
+            let v_row = &frame.v[y / 2 * frame.uv_stride..];
+
+            for x in region.left..region.right {
+                self.y[row + x] = y_row[x];
+                self.u[row + x] = u_row[x / 2];
+                self.v[row + x] = v_row[x / 2];
+            }
+        }
+    }
+
+    /// Fills the chroma samples missing from the main view with the auxiliary view of AVC444 ([MS-RDPEGFX] 3.3.8.3.2).
+    ///
+    /// The luma of the auxiliary view holds the odd rows of the chroma, by blocks of 16 rows: the first 8 rows of a
+    /// block are rows of U, the last 8 ones rows of V. Its chroma holds the odd columns of the even rows.
+    fn write_auxiliary_view_v1(&mut self, frame: &YuvFrame, region: Region) -> Result<(), AvcError> {
+        for y in region.top..region.bottom {
+            let row = y * self.stride;
+
+            if y % 2 == 1 {
+                let index = y / 2;
+                let u_source_row = index / 8 * 16 + index % 8;
+                let v_source_row = u_source_row + 8;
+
+                if v_source_row >= frame.height {
+                    return Err(AvcError::InvalidFrameSize {
+                        width: frame.width,
+                        height: frame.height,
+                    });
+                }
+
+                let u_row = &frame.y[u_source_row * frame.y_stride..];
+                let v_row = &frame.y[v_source_row * frame.y_stride..];
+
+                self.u[row + region.left..row + region.right].copy_from_slice(&u_row[region.left..region.right]);
+                self.v[row + region.left..row + region.right].copy_from_slice(&v_row[region.left..region.right]);
+            } else {
+                let u_row = &frame.u[y / 2 * frame.uv_stride..];
+                let v_row = &frame.v[y / 2 * frame.uv_stride..];
+
+                for x in (region.left + 1..region.right).step_by(2) {
+                    self.u[row + x] = u_row[x / 2];
+                    self.v[row + x] = v_row[x / 2];
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Fills the chroma samples missing from the main view with the auxiliary view of AVC444v2
+    /// ([MS-RDPEGFX] 3.3.8.3.3).
+    ///
+    /// The left and right halves of the luma of the auxiliary view hold the odd columns of U and V. The left and
+    /// right quarters of its chroma hold the even columns of the odd rows of U and V: the columns 4n in the U
+    /// plane, and the columns 4n + 2 in the V plane.
+    fn write_auxiliary_view_v2(&mut self, frame: &YuvFrame, width: usize, region: Region) {
+        let half_width = width / 2;
+        let quarter_width = width / 4;
+
+        for y in region.top..region.bottom {
+            let row = y * self.stride;
+            let y_row = &frame.y[y * frame.y_stride..];
+
+            for x in (region.left + 1..region.right).step_by(2) {
+                self.u[row + x] = y_row[x / 2];
+                self.v[row + x] = y_row[half_width + x / 2];
+            }
+
+            if y % 2 == 1 {
+                let u_row = &frame.u[y / 2 * frame.uv_stride..];
+                let v_row = &frame.v[y / 2 * frame.uv_stride..];
+
+                for x in (region.left..region.right).step_by(2) {
+                    let source_row = if x % 4 == 0 { u_row } else { v_row };
+
+                    self.u[row + x] = source_row[x / 4];
+                    self.v[row + x] = source_row[quarter_width + x / 4];
+                }
+            }
+        }
+    }
+
+    /// Reconstructs the chroma samples of the even rows and columns from the average sent in the main view, where
+    /// it differs enough from the upsampled value ([MS-RDPEGFX] 3.3.8.3.2).
+    fn filter_chroma(&mut self, region: Region) {
+        let stride = self.stride;
+
+        for plane in [&mut self.u, &mut self.v] {
+            for y in (region.top..region.bottom).step_by(2) {
+                let row = y * stride;
+                let next_row = row + stride;
+
+                for x in (region.left..region.right).step_by(2) {
+                    let sample = i32::from(plane[row + x]);
+                    let average = sample * 4
+                        - i32::from(plane[row + x + 1])
+                        - i32::from(plane[next_row + x])
+                        - i32::from(plane[next_row + x + 1]);
+                    let filtered = average.clamp(0, 255);
+
+                    if (filtered - sample).abs() >= CHROMA_FILTER_THRESHOLD {
+                        plane[row + x] = filtered as u8;
+                    }
+                }
+            }
+        }
+    }
+
+    fn write_bgra(&self, region: &Region, width: usize, output: &mut [u8]) {
+        for y in region.top..region.bottom {
+            let row = y * self.stride;
+            let output_row = &mut output[(y * width + region.left) * BYTES_PER_PIXEL..];
+
+            for (x, pixel) in (region.left..region.right).zip(output_row.chunks_exact_mut(BYTES_PER_PIXEL)) {
+                write_bgra(pixel, self.y[row + x], self.u[row + x], self.v[row + x]);
+            }
+        }
+    }
+}
+
+/// Converts a BT.709 YUV sample to a BGRA pixel, using 8-bit fixed-point coefficients.
+fn write_bgra(pixel: &mut [u8], y: u8, u: u8, v: u8) {
+    let y = i32::from(y) * 256;
+    let d = i32::from(u) - 128;
+    let e = i32::from(v) - 128;
+
+    let r = (y + 403 * e) >> 8;
+    let g = (y - 48 * d - 120 * e) >> 8;
+    let b = (y + 475 * d) >> 8;
+
+    pixel[0] = b.clamp(0, 255) as u8;
+    pixel[1] = g.clamp(0, 255) as u8;
+    pixel[2] = r.clamp(0, 255) as u8;
+    pixel[3] = 0xFF;
+}
+
+fn round_up_to_even(value: usize) -> usize {
+    value + value % 2
+}
@@ -0,0 +1,136 @@
+//! Bulk Data Compression of the slow-path and fast-path PDUs (MPPC, RDP 6.0 and RDP 6.1)
+
+mod mppc;
+mod ncrush;
+mod xcrush;
+
+use std::{fmt, io};
+
+use ironrdp_pdu::rdp::client_info::CompressionType;
+use ironrdp_pdu::rdp::headers::CompressionFlags;
+use thiserror::Error;
+
+pub use self::mppc::Mppc;
+use self::mppc::{HISTORY_SIZE_64K, HISTORY_SIZE_8K};
+pub use self::ncrush::NCrush;
+pub use self::xcrush::XCrush;
+
+/// Decompressor of the data sent with any of the compression types, the contexts being created on first use
+#[derive(Default)]
+pub struct Decompressor {
+    mppc_8k: Option<Mppc>,
+    mppc_64k: Option<Mppc>,
+    ncrush: Option<NCrush>,
+    xcrush: Option<XCrush>,
+}
+
+impl fmt::Debug for Decompressor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Decompressor").finish_non_exhaustive()
+    }
+}
+
+impl Decompressor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends the decompressed `input` to `output`, and returns the number of bytes written.
+    ///
+    /// The flags are those of the Share Data Header or of the fast-path update, and update the history even when
+    /// the data is not compressed.
+    pub fn decompress(
+        &mut self,
+        input: &[u8],
+        compression_type: CompressionType,
+        flags: CompressionFlags,
+        output: &mut Vec<u8>,
+    ) -> Result<usize, BulkError> {
+        let decompressed = match compression_type {
+            CompressionType::K8 => self
+                .mppc_8k
+                .get_or_insert_with(|| Mppc::new(HISTORY_SIZE_8K))
+                .decompress(input, flags)?,
+            CompressionType::K64 => self
+                .mppc_64k
+                .get_or_insert_with(|| Mppc::new(HISTORY_SIZE_64K))
+                .decompress(input, flags)?,
+            CompressionType::Rdp61 if flags.contains(CompressionFlags::COMPRESSED) => {
+                self.xcrush.get_or_insert_with(XCrush::new).decompress(input, flags)?
+            }
+            CompressionType::Rdp61 => {
+                if flags.contains(CompressionFlags::FLUSHED) {
+                    self.xcrush = None;
+                }
+
+                input
+            }
+            CompressionType::Rdp6 => self.ncrush.get_or_insert_with(NCrush::new).decompress(input, flags)?,
+        };
+
+        output.extend_from_slice(decompressed);
+
+        Ok(decompressed.len())
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum BulkError {
+    #[error("IO error")]
+    IOError(#[from] io::Error),
+    #[error("Not enough bits to decode the token")]
+    NotEnoughBits,
+    #[error("Not enough bytes to decode the data")]
+    NotEnoughBytes,
+    #[error("Invalid length of match")]
+    InvalidLengthOfMatch,
+    #[error("Invalid copy offset: {0}")]
+    InvalidCopyOffset(usize),
+    #[error("The decompressed data overflows the history buffer")]
+    HistoryOverflow,
+    #[error("Invalid Level-1 compression flags: 0x{0:02X}")]
+    InvalidLevel1Flags(u8),
+    #[error("Invalid match output offset: {0}")]
+    InvalidMatchOutputOffset(usize),
+    #[error("Not enough history to move its end at the front")]
+    NotEnoughHistory,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn passes_uncompressed_data_through() {
+        let mut decompressor = Decompressor::new();
+        let mut output = b"previous".to_vec();
+
+        let written = decompressor
+            .decompress(b"data", CompressionType::Rdp61, CompressionFlags::FLUSHED, &mut output)
+            .unwrap();
+
+        assert_eq!(written, 4);
+        assert_eq!(output, b"previousdata");
+    }
+
+    #[test]
+    fn flushes_rdp6_history() {
+        let mut decompressor = Decompressor::new();
+
+        let result = decompressor.decompress(
+            &[],
+            CompressionType::Rdp6,
+            CompressionFlags::COMPRESSED | CompressionFlags::AT_FRONT,
+            &mut Vec::new(),
+        );
+        assert!(matches!(result, Err(BulkError::NotEnoughHistory)));
+
+        let mut output = Vec::new();
+        let written = decompressor
+            .decompress(b"data", CompressionType::Rdp6, CompressionFlags::FLUSHED, &mut output)
+            .unwrap();
+
+        assert_eq!(written, 4);
+        assert_eq!(output, b"data");
+    }
+}
@@ -0,0 +1,263 @@
+//! MPPC decompression, with the 8 KB history of RDP 4.0 and the 64 KB history of RDP 5.0
+
+use bitvec::field::BitField as _;
+use bitvec::slice::BitSlice;
+use ironrdp_pdu::rdp::headers::CompressionFlags;
+
+use super::BulkError;
+use crate::utils::Bits;
+
+pub(crate) const HISTORY_SIZE_8K: usize = 8 * 1024;
+pub(crate) const HISTORY_SIZE_64K: usize = 64 * 1024;
+
+/// MPPC decompression context
+pub struct Mppc {
+    history: Vec<u8>,
+    history_offset: usize,
+}
+
+impl Mppc {
+    /// The history size selects the variant of the encoding: 8 KB for RDP 4.0, 64 KB for RDP 5.0.
+    pub(crate) fn new(history_size: usize) -> Self {
+        debug_assert!(history_size == HISTORY_SIZE_8K || history_size == HISTORY_SIZE_64K);
+
+        Self {
+            history: vec![0; history_size],
+            history_offset: 0,
+        }
+    }
+
+    /// Decompresses `input`, and returns the decompressed data, which is stored in the history buffer.
+    ///
+    /// The flags are processed even when the data is not compressed, in which case `input` is returned as is.
+    pub fn decompress<'a>(&'a mut self, input: &'a [u8], flags: CompressionFlags) -> Result<&'a [u8], BulkError> {
+        if flags.contains(CompressionFlags::FLUSHED) {
+            self.history.fill(0);
+            self.history_offset = 0;
+        }
+
+        if flags.contains(CompressionFlags::AT_FRONT) {
+            self.history_offset = 0;
+        }
+
+        if !flags.contains(CompressionFlags::COMPRESSED) {
+            return Ok(input);
+        }
+
+        let start = self.history_offset;
+        let mut bits = Bits::new(BitSlice::from_slice(input));
+
+        // The last byte is padded with less than 8 bits, while the shortest token takes 8 bits
+        while bits.len() >= 8 {
+            match self.read_token(&mut bits)? {
+                Token::Literal(value) => self.write_literal(value)?,
+                Token::Copy { offset, length } => self.copy_match(offset, length)?,
+            }
+        }
+
+        Ok(&self.history[start..self.history_offset])
+    }
+
+    fn is_64k(&self) -> bool {
+        self.history.len() == HISTORY_SIZE_64K
+    }
+
+    fn read_token(&self, bits: &mut Bits<'_>) -> Result<Token, BulkError> {
+        // Literals are encoded with the "0" and "10" prefixes, copy offsets with longer runs of ones
+        let max_prefix_ones = if self.is_64k() { 5 } else { 4 };
+        let prefix_ones = bits.leading_ones().min(max_prefix_ones);
+        skip_prefix(bits, prefix_ones, max_prefix_ones)?;
+
+        let offset = match (self.is_64k(), prefix_ones) {
+            (_, 0) => return Ok(Token::Literal(read_bits(bits, 7)? as u8)),
+            (_, 1) => return Ok(Token::Literal(0x80 | read_bits(bits, 7)? as u8)),
+            (false, 2) => 320 + read_bits(bits, 13)?,
+            (false, 3) => 64 + read_bits(bits, 8)?,
+            (false, _) => read_bits(bits, 6)?,
+            (true, 2) => 2368 + read_bits(bits, 16)?,
+            (true, 3) => 320 + read_bits(bits, 11)?,
+            (true, 4) => 64 + read_bits(bits, 8)?,
+            (true, _) => read_bits(bits, 6)?,
+        };
+
+        let length = self.read_length(bits)?;
+
+        Ok(Token::Copy {
+            offset: offset as usize,
+            length,
+        })
+    }
+
+    fn read_length(&self, bits: &mut Bits<'_>) -> Result<usize, BulkError> {
+        // The length is encoded with k ones followed by a zero, and k + 1 bits added to 2^(k + 1),
+        // except for the "0" prefix which stands for a length of 3
+        let max_prefix_ones = if self.is_64k() { 14 } else { 11 };
+
+        let prefix_ones = bits.leading_ones();
+        if prefix_ones > max_prefix_ones {
+            return Err(BulkError::InvalidLengthOfMatch);
+        }
+        skip_prefix(bits, prefix_ones, max_prefix_ones + 1)?;
+
+        if prefix_ones == 0 {
+            Ok(3)
+        } else {
+            Ok((1 << (prefix_ones + 1)) + read_bits(bits, prefix_ones + 1)? as usize)
+        }
+    }
+
+    fn write_literal(&mut self, value: u8) -> Result<(), BulkError> {
+        let destination = self
+            .history
+            .get_mut(self.history_offset)
+            .ok_or(BulkError::HistoryOverflow)?;
+        *destination = value;
+        self.history_offset += 1;
+
+        Ok(())
+    }
+
+    fn copy_match(&mut self, offset: usize, length: usize) -> Result<(), BulkError> {
+        let history_size = self.history.len();
+
+        if offset == 0 || offset > history_size {
+            return Err(BulkError::InvalidCopyOffset(offset));
+        }
+
+        if self.history_offset + length > history_size {
+            return Err(BulkError::HistoryOverflow);
+        }
+
+        // The source wraps around the end of the history, and may overlap the copied bytes
+        let mut source = (self.history_offset + history_size - offset) % history_size;
+        for _ in 0..length {
+            self.history[self.history_offset] = self.history[source];
+            self.history_offset += 1;
+            source = (source + 1) % history_size;
+        }
+
+        Ok(())
+    }
+}
+
+enum Token {
+    Literal(u8),
+    Copy { offset: usize, length: usize },
+}
+
+/// Skips the ones of a prefix, and the zero ending it when it is shorter than `max_length`.
+fn skip_prefix(bits: &mut Bits<'_>, ones: usize, max_length: usize) -> Result<(), BulkError> {
+    let length = if ones < max_length { ones + 1 } else { ones };
+    if bits.len() < length {
+        return Err(BulkError::NotEnoughBits);
+    }
+    bits.split_to(length);
+
+    Ok(())
+}
+
+fn read_bits(bits: &mut Bits<'_>, count: usize) -> Result<u32, BulkError> {
+    if bits.len() < count {
+        return Err(BulkError::NotEnoughBits);
+    }
+
+    Ok(bits.split_to(count).load_be::<u32>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Packs the bits of a string of `0` and `1` characters, ignoring the spaces.
+    fn pack(bits: &str) -> Vec<u8> {
+        let bits = bits.chars().filter(|c| !c.is_whitespace()).collect::<Vec<_>>();
+
+        bits.chunks(8)
+            .map(|byte| {
+                byte.iter()
+                    .chain(std::iter::repeat(&'0'))
+                    .take(8)
+                    .fold(0, |value, bit| (value << 1) | u8::from(*bit == '1'))
+            })
+            .collect()
+    }
+
+    const COMPRESSED: CompressionFlags = CompressionFlags::COMPRESSED;
+
+    #[test]
+    fn decompresses_literals_and_copies_with_the_8k_history() {
+        let mut mppc = Mppc::new(HISTORY_SIZE_8K);
+        // 'a' 'b' 'c', then a copy of 6 bytes at offset 3, then 0xE9
+        let input = pack("0 1100001  0 1100010  0 1100011  1111 000011 10 10  10 1101001");
+
+        let output = mppc.decompress(&input, COMPRESSED).unwrap();
+
+        assert_eq!(output, b"abcabcabc\xE9");
+    }
+
+    #[test]
+    fn decompresses_copy_offsets_with_the_64k_history() {
+        let mut mppc = Mppc::new(HISTORY_SIZE_64K);
+        mppc.history[..400]
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, v)| *v = i as u8);
+        mppc.history_offset = 400;
+
+        // Offset 400 with a length of 3, then offset 70 with a length of 4
+        let input = pack("1110 00001010000 0  11110 00000110 10 00");
+
+        let output = mppc.decompress(&input, COMPRESSED).unwrap();
+
+        assert_eq!(output, [0, 1, 2, 77, 78, 79, 80]);
+    }
+
+    #[test]
+    fn continues_from_the_previous_packet_unless_flushed() {
+        let mut mppc = Mppc::new(HISTORY_SIZE_8K);
+        mppc.decompress(&pack("0 1100001  0 1100010"), COMPRESSED).unwrap();
+
+        // Copy of 3 bytes at offset 2
+        let copy = pack("1111 000010 0");
+        assert_eq!(mppc.decompress(&copy, COMPRESSED).unwrap(), b"aba");
+
+        let output = mppc.decompress(&copy, COMPRESSED | CompressionFlags::FLUSHED).unwrap();
+        assert_eq!(output, [0, 0, 0]);
+    }
+
+    #[test]
+    fn restarts_at_the_front_of_the_history() {
+        let mut mppc = Mppc::new(HISTORY_SIZE_8K);
+        mppc.history_offset = HISTORY_SIZE_8K - 1;
+        mppc.decompress(&pack("0 1100001"), COMPRESSED).unwrap();
+
+        // The copy source wraps around the end of the history
+        let input = pack("1111 000001 0");
+        let output = mppc
+            .decompress(&input, COMPRESSED | CompressionFlags::AT_FRONT)
+            .unwrap();
+
+        assert_eq!(output, b"aaa");
+    }
+
+    #[test]
+    fn returns_uncompressed_data_as_is() {
+        let mut mppc = Mppc::new(HISTORY_SIZE_8K);
+
+        assert_eq!(
+            mppc.decompress(b"raw", CompressionFlags::FLUSHED).unwrap(),
+            b"raw".as_slice()
+        );
+    }
+
+    #[test]
+    fn rejects_writes_past_the_end_of_the_history() {
+        let mut mppc = Mppc::new(HISTORY_SIZE_8K);
+        mppc.history_offset = HISTORY_SIZE_8K;
+
+        let input = pack("0 1100001");
+        let result = mppc.decompress(&input, COMPRESSED);
+
+        assert!(matches!(result, Err(BulkError::HistoryOverflow)));
+    }
+}
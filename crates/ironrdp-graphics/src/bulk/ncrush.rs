@@ -0,0 +1,458 @@
+//! RDP 6.0 bulk decompression (NCRUSH)
+//!
+//! Literals, copy offsets and lengths of match are Huffman-encoded, the bits being read from the least significant
+//! one. The four last copy offsets are cached, and referred to by dedicated symbols.
+
+use ironrdp_pdu::rdp::headers::CompressionFlags;
+
+use super::BulkError;
+
+const HISTORY_SIZE: usize = 64 * 1024;
+
+/// Number of bytes kept at the front of the history when a packet restarts there
+const HISTORY_KEPT_AT_FRONT: usize = 32 * 1024;
+
+const END_OF_STREAM: usize = 256;
+const FIRST_COPY_OFFSET: usize = 257;
+const FIRST_OFFSET_CACHE_INDEX: usize = 289;
+
+/// Code lengths of the literals (0 to 255), the end of stream (256), the copy offsets (257 to 288)
+/// and the indices of the offset cache (289 to 292)
+const LEC_CODE_LENGTHS: [u8; 294] = [
+    6, 6, 6, 7, 7, 7, 7, 7, 7, 7, 7, 8, 8, 8, 8, 8, 8, 8, 9, 8, 9, 9, 9, 9, 8, 8, 9, 9, 9, 9, 9, 9, 8, 9, 9, 10, 9, 9,
+    9, 9, 9, 9, 9, 9, 9, 9, 9, 10, 10, 10, 9, 9, 10, 9, 10, 9, 10, 9, 9, 10, 10, 10, 9, 10, 9, 10, 9, 9, 9, 9, 9, 10,
+    10, 10, 9, 9, 10, 10, 10, 10, 10, 10, 10, 9, 10, 10, 10, 10, 10, 10, 9, 10, 10, 10, 10, 10, 10, 10, 9, 10, 10, 10,
+    10, 10, 10, 10, 10, 10, 10, 10, 10, 10, 9, 10, 10, 10, 9, 10, 10, 10, 9, 10, 10, 10, 9, 10, 10, 10, 8, 10, 11, 11,
+    10, 10, 10, 10, 10, 10, 10, 10, 11, 12, 10, 10, 10, 10, 10, 10, 10, 10, 10, 10, 10, 10, 10, 10, 10, 10, 10, 10, 9,
+    10, 10, 10, 10, 10, 10, 10, 10, 10, 10, 10, 10, 10, 10, 10, 9, 10, 10, 10, 10, 10, 10, 10, 10, 10, 10, 10, 10, 10,
+    10, 10, 9, 10, 10, 10, 10, 10, 10, 10, 9, 10, 10, 10, 10, 10, 10, 10, 9, 10, 10, 10, 10, 10, 10, 10, 9, 10, 10, 10,
+    10, 10, 10, 10, 9, 10, 10, 10, 10, 10, 10, 10, 9, 10, 10, 10, 10, 10, 10, 10, 9, 10, 10, 10, 9, 10, 9, 10, 9, 10,
+    9, 10, 9, 13, 8, 8, 13, 7, 7, 7, 7, 7, 6, 7, 6, 6, 6, 5, 6, 6, 6, 5, 6, 5, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6,
+    6, 8, 5, 6, 7, 7, 8,
+];
+
+/// Code lengths of the lengths of match
+const LOM_CODE_LENGTHS: [u8; 32] = [
+    4, 2, 3, 4, 3, 4, 4, 5, 4, 5, 5, 6, 6, 7, 7, 8, 7, 8, 8, 9, 9, 8, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9,
+];
+
+const COPY_OFFSET_BITS: [usize; 32] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13, 14, 14,
+];
+
+const COPY_OFFSET_BASE: [usize; 32] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145,
+    8193, 12289, 16385, 24577, 32769, 49153,
+];
+
+const LENGTH_OF_MATCH_BITS: [usize; 28] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 6, 6, 8, 8,
+];
+
+const LENGTH_OF_MATCH_BASE: [usize; 28] = [
+    2, 3, 4, 5, 6, 7, 8, 9, 10, 12, 14, 16, 18, 22, 26, 30, 34, 42, 50, 58, 66, 82, 98, 114, 130, 194, 258, 514,
+];
+
+/// NCRUSH decompression context
+pub struct NCrush {
+    history: Vec<u8>,
+    history_offset: usize,
+    offset_cache: [usize; 4],
+    lec_table: HuffmanTable,
+    lom_table: HuffmanTable,
+}
+
+impl NCrush {
+    pub(crate) fn new() -> Self {
+        Self {
+            history: vec![0; HISTORY_SIZE],
+            history_offset: 0,
+            offset_cache: [0; 4],
+            lec_table: HuffmanTable::new(&LEC_CODE_LENGTHS),
+            lom_table: HuffmanTable::new(&LOM_CODE_LENGTHS),
+        }
+    }
+
+    /// Decompresses `input`, and returns the decompressed data, which is stored in the history buffer.
+    ///
+    /// The flags are processed even when the data is not compressed, in which case `input` is returned as is.
+    pub fn decompress<'a>(&'a mut self, input: &'a [u8], flags: CompressionFlags) -> Result<&'a [u8], BulkError> {
+        if flags.contains(CompressionFlags::AT_FRONT) {
+            // The end of the history is kept, so that the next packets can still refer to it
+            let kept = self
+                .history_offset
+                .checked_sub(HISTORY_KEPT_AT_FRONT)
+                .ok_or(BulkError::NotEnoughHistory)?;
+            self.history.copy_within(kept..self.history_offset, 0);
+            self.history[HISTORY_KEPT_AT_FRONT..].fill(0);
+            self.history_offset = HISTORY_KEPT_AT_FRONT;
+        }
+
+        if flags.contains(CompressionFlags::FLUSHED) {
+            self.history.fill(0);
+            self.history_offset = 0;
+            self.offset_cache = [0; 4];
+        }
+
+        if !flags.contains(CompressionFlags::COMPRESSED) {
+            return Ok(input);
+        }
+
+        let start = self.history_offset;
+        let mut bits = BitReader::new(input);
+
+        loop {
+            let symbol = self.lec_table.decode(&mut bits)?;
+
+            let offset = match symbol {
+                END_OF_STREAM => break,
+                0..=255 => {
+                    self.write_literal(symbol as u8)?;
+                    continue;
+                }
+                FIRST_COPY_OFFSET..=288 => {
+                    let index = symbol - FIRST_COPY_OFFSET;
+                    let offset = COPY_OFFSET_BASE[index] + bits.read(COPY_OFFSET_BITS[index])?;
+
+                    self.offset_cache.copy_within(0..3, 1);
+                    self.offset_cache[0] = offset;
+
+                    offset
+                }
+                _ => {
+                    let index = symbol - FIRST_OFFSET_CACHE_INDEX;
+                    let offset = *self
+                        .offset_cache
+                        .get(index)
+                        .ok_or(BulkError::InvalidCopyOffset(index))?;
+
+                    // The most recently used offset comes first
+                    self.offset_cache.swap(0, index);
+
+                    offset
+                }
+            };
+
+            let length = self.read_length_of_match(&mut bits)?;
+            self.copy_match(offset, length)?;
+        }
+
+        Ok(&self.history[start..self.history_offset])
+    }
+
+    fn read_length_of_match(&self, bits: &mut BitReader<'_>) -> Result<usize, BulkError> {
+        let index = self.lom_table.decode(bits)?;
+
+        let base = LENGTH_OF_MATCH_BASE.get(index).ok_or(BulkError::InvalidLengthOfMatch)?;
+
+        Ok(base + bits.read(LENGTH_OF_MATCH_BITS[index])?)
+    }
+
+    fn write_literal(&mut self, value: u8) -> Result<(), BulkError> {
+        let destination = self
+            .history
+            .get_mut(self.history_offset)
+            .ok_or(BulkError::HistoryOverflow)?;
+        *destination = value;
+        self.history_offset += 1;
+
+        Ok(())
+    }
+
+    fn copy_match(&mut self, offset: usize, length: usize) -> Result<(), BulkError> {
+        if offset == 0 || offset > self.history_offset {
+            return Err(BulkError::InvalidCopyOffset(offset));
+        }
+
+        if self.history_offset + length > HISTORY_SIZE {
+            return Err(BulkError::HistoryOverflow);
+        }
+
+        // The source may overlap the copied bytes
+        let source = self.history_offset - offset;
+        for i in 0..length {
+            self.history[self.history_offset + i] = self.history[source + i];
+        }
+        self.history_offset += length;
+
+        Ok(())
+    }
+}
+
+/// Reader of the bits of a byte slice, starting from the least significant bit of each byte
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    fn remaining(&self) -> usize {
+        self.data.len() * 8 - self.position
+    }
+
+    /// Returns the next `count` bits without consuming them, padded with zeros past the end of the data.
+    fn peek(&self, count: usize) -> usize {
+        (0..count).fold(0, |value, i| {
+            let position = self.position + i;
+            let bit = self
+                .data
+                .get(position / 8)
+                .map_or(0, |byte| usize::from((byte >> (position % 8)) & 1));
+
+            value | (bit << i)
+        })
+    }
+
+    fn read(&mut self, count: usize) -> Result<usize, BulkError> {
+        if count > self.remaining() {
+            return Err(BulkError::NotEnoughBits);
+        }
+
+        let value = self.peek(count);
+        self.position += count;
+
+        Ok(value)
+    }
+}
+
+/// Decoding table of a canonical Huffman code whose codes are read starting from their last bit
+struct HuffmanTable {
+    /// Symbol and code length, indexed by the next `max_length` bits
+    lookup: Vec<(usize, usize)>,
+    max_length: usize,
+}
+
+impl HuffmanTable {
+    fn new(code_lengths: &[u8]) -> Self {
+        let max_length = usize::from(*code_lengths.iter().max().expect("code lengths"));
+        let mut lookup = vec![(0, 0); 1 << max_length];
+
+        // The codes are assigned in increasing order of length, then of symbol
+        let mut code = 0;
+        for length in 1..=max_length {
+            for (symbol, _) in code_lengths
+                .iter()
+                .enumerate()
+                .filter(|(_, code_length)| usize::from(**code_length) == length)
+            {
+                let reversed_code = reverse_bits(code, length);
+
+                for suffix in 0..1 << (max_length - length) {
+                    lookup[reversed_code | (suffix << length)] = (symbol, length);
+                }
+
+                code += 1;
+            }
+
+            code <<= 1;
+        }
+
+        Self { lookup, max_length }
+    }
+
+    fn decode(&self, bits: &mut BitReader<'_>) -> Result<usize, BulkError> {
+        let (symbol, length) = self.lookup[bits.peek(self.max_length)];
+
+        if length > bits.remaining() {
+            return Err(BulkError::NotEnoughBits);
+        }
+        bits.position += length;
+
+        Ok(symbol)
+    }
+}
+
+fn reverse_bits(value: usize, count: usize) -> usize {
+    (0..count).fold(0, |reversed, i| (reversed << 1) | ((value >> i) & 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The vectors are written by hand from the Huffman codes of MS-RDPEGDI 3.1.8.1.4.1, from the least significant bit:
+    // - '!', '$', '%', '&', '*' and '+': 0x1BD, 0x17D, 0x0FD, 0x1FD, 0x183 and 0x043 (9 bits)
+    // - copy offset 5 or 6: 0x05 (7 bits), then 1 extra bit
+    // - index 0 of the offset cache: 0x18 (5 bits)
+    // - end of stream: 0x1FFF (13 bits)
+    // - length of match 2, 3, 5 and 7: 0x1 (4 bits), 0x0 (2 bits), 0x9 (4 bits) and 0x5 (4 bits)
+    // - length of match 514 and more: 0x1BF (9 bits), then 8 extra bits
+
+    /// "!$%&*" and the end of stream
+    const LITERALS: [u8; 8] = [0xbd, 0xfb, 0xf6, 0xeb, 0x3f, 0xf8, 0xff, 0x03];
+
+    /// A match of 5 bytes at offset 5 and the end of stream
+    const MATCH: [u8; 4] = [0x05, 0xf9, 0xff, 0x01];
+
+    /// Codes of the lengths of match (HuffCodeLOM)
+    const LOM_CODES: [u16; 32] = [
+        0x0001, 0x0000, 0x0002, 0x0009, 0x0006, 0x0005, 0x000D, 0x000B, 0x0003, 0x001B, 0x0007, 0x0017, 0x0037, 0x000F,
+        0x004F, 0x006F, 0x002F, 0x00EF, 0x001F, 0x005F, 0x015F, 0x009F, 0x00DF, 0x01DF, 0x003F, 0x013F, 0x00BF, 0x01BF,
+        0x007F, 0x017F, 0x00FF, 0x01FF,
+    ];
+
+    /// Codes of the literals 0x00 to 0x22 (first entries of HuffCodeLEC)
+    const FIRST_LITERAL_CODES: [u16; 35] = [
+        0x004, 0x024, 0x014, 0x011, 0x051, 0x031, 0x071, 0x009, 0x049, 0x029, 0x069, 0x015, 0x095, 0x055, 0x0D5, 0x035,
+        0x0B5, 0x075, 0x01D, 0x0F5, 0x11D, 0x09D, 0x19D, 0x05D, 0x00D, 0x08D, 0x15D, 0x0DD, 0x1DD, 0x03D, 0x13D, 0x0BD,
+        0x04D, 0x1BD, 0x07D,
+    ];
+
+    fn decode_code(table: &HuffmanTable, code: u16) -> usize {
+        table.decode(&mut BitReader::new(&code.to_le_bytes())).unwrap()
+    }
+
+    #[test]
+    fn code_lengths_form_complete_prefix_codes() {
+        for code_lengths in [LEC_CODE_LENGTHS.as_ref(), LOM_CODE_LENGTHS.as_ref()] {
+            let kraft_sum: u32 = code_lengths.iter().map(|length| 1 << (13 - length)).sum();
+            assert_eq!(kraft_sum, 1 << 13);
+        }
+    }
+
+    #[test]
+    fn code_lengths_match_the_specified_codes() {
+        let lom_table = HuffmanTable::new(&LOM_CODE_LENGTHS);
+        for (symbol, code) in LOM_CODES.iter().enumerate() {
+            assert_eq!(decode_code(&lom_table, *code), symbol);
+        }
+
+        let lec_table = HuffmanTable::new(&LEC_CODE_LENGTHS);
+        for (symbol, code) in FIRST_LITERAL_CODES.iter().enumerate() {
+            assert_eq!(decode_code(&lec_table, *code), symbol);
+        }
+        assert_eq!(decode_code(&lec_table, 0x1FFF), END_OF_STREAM);
+    }
+
+    #[test]
+    fn decodes_a_packet() {
+        let mut ncrush = NCrush::new();
+
+        // "Hello, ", a match of 5 bytes at offset 7, "!" and the end of stream
+        let data = [
+            0x9b, 0xee, 0x7b, 0xc8, 0x21, 0x87, 0x0f, 0x6d, 0xa2, 0x65, 0xef, 0xff, 0x0f,
+        ];
+
+        assert_eq!(
+            ncrush.decompress(&data, CompressionFlags::COMPRESSED).unwrap(),
+            b"Hello, Hello!"
+        );
+        assert_eq!(ncrush.offset_cache, [7, 0, 0, 0]);
+    }
+
+    #[test]
+    fn decodes_literals_and_matches() {
+        let mut ncrush = NCrush::new();
+
+        // "!$%&*", then 7 bytes copied from 5 bytes back, overlapping the output
+        let data = [0xbd, 0xfb, 0xf6, 0xeb, 0x3f, 0xb8, 0xa0, 0xfe, 0x3f];
+
+        assert_eq!(
+            ncrush.decompress(&data, CompressionFlags::COMPRESSED).unwrap(),
+            b"!$%&*!$%&*!$"
+        );
+    }
+
+    #[test]
+    fn decodes_long_matches_across_packets() {
+        let mut ncrush = NCrush::new();
+
+        assert_eq!(
+            ncrush.decompress(&LITERALS, CompressionFlags::COMPRESSED).unwrap(),
+            b"!$%&*"
+        );
+
+        // A match of 600 bytes (514 + 86) at offset 5
+        let data = [0x05, 0xbf, 0xad, 0xfe, 0x3f];
+
+        let expected: Vec<u8> = b"!$%&*".iter().copied().cycle().take(600).collect();
+        assert_eq!(
+            ncrush.decompress(&data, CompressionFlags::COMPRESSED).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn reuses_cached_offsets() {
+        let mut ncrush = NCrush::new();
+
+        // "!$%&*", a match of 2 bytes at offset 5, "+", a match of 2 bytes at offset 6,
+        // then a match of 3 bytes at the offset first in the cache (6)
+        let data = [
+            0xbd, 0xfb, 0xf6, 0xeb, 0x3f, 0xb8, 0x20, 0x86, 0x14, 0x06, 0xe6, 0xff, 0x03,
+        ];
+
+        assert_eq!(
+            ncrush.decompress(&data, CompressionFlags::COMPRESSED).unwrap(),
+            b"!$%&*!$+%&*!$"
+        );
+        assert_eq!(ncrush.offset_cache, [6, 5, 0, 0]);
+    }
+
+    #[test]
+    fn passes_uncompressed_data_through() {
+        let mut ncrush = NCrush::new();
+
+        assert_eq!(ncrush.decompress(b"data", CompressionFlags::empty()).unwrap(), b"data");
+        assert_eq!(ncrush.history_offset, 0);
+    }
+
+    #[test]
+    fn restarts_at_the_front_of_the_history() {
+        let mut ncrush = NCrush::new();
+
+        ncrush.history[..39_995].fill(b'x');
+        ncrush.history[39_995..40_000].copy_from_slice(b"!$%&*");
+        ncrush.history_offset = 40_000;
+
+        // The end of the history is now right before the new data
+        assert_eq!(
+            ncrush
+                .decompress(&MATCH, CompressionFlags::COMPRESSED | CompressionFlags::AT_FRONT)
+                .unwrap(),
+            b"!$%&*"
+        );
+        assert_eq!(ncrush.history_offset, HISTORY_KEPT_AT_FRONT + 5);
+    }
+
+    #[test]
+    fn flushes_the_history() {
+        let mut ncrush = NCrush::new();
+
+        ncrush.decompress(&LITERALS, CompressionFlags::COMPRESSED).unwrap();
+
+        assert!(matches!(
+            ncrush.decompress(&MATCH, CompressionFlags::COMPRESSED | CompressionFlags::FLUSHED),
+            Err(BulkError::InvalidCopyOffset(5))
+        ));
+    }
+
+    #[test]
+    fn fails_without_end_of_stream() {
+        let mut ncrush = NCrush::new();
+
+        // "!$%&*" only
+        let data = [0xbd, 0xfb, 0xf6, 0xeb, 0x3f, 0x18];
+
+        assert!(matches!(
+            ncrush.decompress(&data, CompressionFlags::COMPRESSED),
+            Err(BulkError::NotEnoughBits)
+        ));
+    }
+
+    #[test]
+    fn fails_on_history_overflow() {
+        let mut ncrush = NCrush::new();
+
+        ncrush.history_offset = HISTORY_SIZE - 3;
+
+        assert!(matches!(
+            ncrush.decompress(&LITERALS, CompressionFlags::COMPRESSED),
+            Err(BulkError::HistoryOverflow)
+        ));
+    }
+}
@@ -0,0 +1,215 @@
+//! RDP 6.1 bulk decompression (XCRUSH)
+//!
+//! The data goes through two levels of compression: the first one finds long matches in a 2 MB history, and
+//! its output is compressed again by the 64 KB variant of MPPC.
+
+use byteorder::{LittleEndian, ReadBytesExt as _};
+use ironrdp_pdu::rdp::headers::CompressionFlags;
+
+use super::mppc::{Mppc, HISTORY_SIZE_64K};
+use super::BulkError;
+
+const HISTORY_SIZE: usize = 2_000_000;
+
+const L1_COMPRESSED: u8 = 0x01;
+const L1_NO_COMPRESSION: u8 = 0x02;
+const L1_PACKET_AT_FRONT: u8 = 0x04;
+
+/// Size of an RDP61_MATCH_DETAILS structure
+const MATCH_DETAILS_SIZE: usize = 8;
+
+/// XCRUSH decompression context
+pub struct XCrush {
+    history: Vec<u8>,
+    history_offset: usize,
+    mppc: Mppc,
+}
+
+impl XCrush {
+    pub(crate) fn new() -> Self {
+        Self {
+            history: vec![0; HISTORY_SIZE],
+            history_offset: 0,
+            mppc: Mppc::new(HISTORY_SIZE_64K),
+        }
+    }
+
+    /// Decompresses an RDP61_COMPRESSED_DATA structure, and returns the decompressed data, which is stored in the
+    /// history buffer.
+    pub fn decompress(&mut self, input: &[u8], flags: CompressionFlags) -> Result<&[u8], BulkError> {
+        if flags.contains(CompressionFlags::FLUSHED) {
+            self.history.fill(0);
+            self.history_offset = 0;
+        }
+
+        let [level_1_flags, level_2_flags, ref data @ ..] = *input else {
+            return Err(BulkError::NotEnoughBytes);
+        };
+
+        // The second level is plain MPPC, whose flags are processed even when the data is not compressed
+        let data = self
+            .mppc
+            .decompress(data, CompressionFlags::from_bits_truncate(level_2_flags))?;
+
+        decompress_level_1(&mut self.history, &mut self.history_offset, data, level_1_flags)
+    }
+}
+
+fn decompress_level_1<'a>(
+    history: &'a mut [u8],
+    history_offset: &mut usize,
+    mut input: &[u8],
+    flags: u8,
+) -> Result<&'a [u8], BulkError> {
+    if flags & L1_PACKET_AT_FRONT != 0 {
+        *history_offset = 0;
+    }
+
+    let start = *history_offset;
+    let mut offset = start;
+
+    let write = |history: &mut [u8], offset: &mut usize, source: &[u8]| {
+        let destination = history
+            .get_mut(*offset..*offset + source.len())
+            .ok_or(BulkError::HistoryOverflow)?;
+        destination.copy_from_slice(source);
+        *offset += source.len();
+
+        Ok::<_, BulkError>(())
+    };
+
+    if flags & L1_NO_COMPRESSION == 0 {
+        if flags & L1_COMPRESSED == 0 {
+            return Err(BulkError::InvalidLevel1Flags(flags));
+        }
+
+        let match_count = usize::from(input.read_u16::<LittleEndian>()?);
+        if input.len() < match_count * MATCH_DETAILS_SIZE {
+            return Err(BulkError::NotEnoughBytes);
+        }
+        let (mut match_details, mut literals) = input.split_at(match_count * MATCH_DETAILS_SIZE);
+
+        // Number of bytes output by this packet, which is where the matches are placed
+        let mut output_offset = 0;
+
+        for _ in 0..match_count {
+            let match_length = usize::from(match_details.read_u16::<LittleEndian>()?);
+            let match_output_offset = usize::from(match_details.read_u16::<LittleEndian>()?);
+            let match_history_offset = match_details.read_u32::<LittleEndian>()? as usize;
+
+            // The bytes preceding the match are literals
+            let literals_length = match_output_offset
+                .checked_sub(output_offset)
+                .ok_or(BulkError::InvalidMatchOutputOffset(match_output_offset))?;
+            if literals.len() < literals_length {
+                return Err(BulkError::NotEnoughBytes);
+            }
+            let (preceding_literals, remaining) = literals.split_at(literals_length);
+            write(history, &mut offset, preceding_literals)?;
+            literals = remaining;
+
+            // The source may overlap the copied bytes
+            let match_history_end = match_history_offset
+                .checked_add(match_length)
+                .ok_or(BulkError::InvalidMatchOutputOffset(match_output_offset))?;
+            if match_history_end > history.len() || offset + match_length > history.len() {
+                return Err(BulkError::HistoryOverflow);
+            }
+            for i in 0..match_length {
+                history[offset + i] = history[match_history_offset + i];
+            }
+            offset += match_length;
+
+            output_offset = match_output_offset + match_length;
+        }
+
+        input = literals;
+    }
+
+    write(history, &mut offset, input)?;
+    *history_offset = offset;
+
+    Ok(&history[start..offset])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rdp61_data(level_1_flags: u8, matches: &[(u16, u16, u32)], literals: &[u8]) -> Vec<u8> {
+        let mut data = vec![level_1_flags, 0];
+
+        if level_1_flags & L1_COMPRESSED != 0 {
+            data.extend_from_slice(&(matches.len() as u16).to_le_bytes());
+            for (length, output_offset, history_offset) in matches {
+                data.extend_from_slice(&length.to_le_bytes());
+                data.extend_from_slice(&output_offset.to_le_bytes());
+                data.extend_from_slice(&history_offset.to_le_bytes());
+            }
+        }
+
+        data.extend_from_slice(literals);
+        data
+    }
+
+    #[test]
+    fn copies_matches_from_the_history_between_literals() {
+        let mut xcrush = XCrush::new();
+
+        let first = rdp61_data(L1_NO_COMPRESSION, &[], b"Hello, world");
+        assert_eq!(
+            xcrush.decompress(&first, CompressionFlags::COMPRESSED).unwrap(),
+            b"Hello, world"
+        );
+
+        // "Hello" at offset 2, then ", " at offset 9
+        let second = rdp61_data(L1_COMPRESSED, &[(5, 2, 0), (2, 9, 5)], b">>..!");
+        assert_eq!(
+            xcrush.decompress(&second, CompressionFlags::COMPRESSED).unwrap(),
+            b">>Hello.., !"
+        );
+    }
+
+    #[test]
+    fn restarts_at_the_front_of_the_history() {
+        let mut xcrush = XCrush::new();
+        xcrush
+            .decompress(
+                &rdp61_data(L1_NO_COMPRESSION, &[], b"abc"),
+                CompressionFlags::COMPRESSED,
+            )
+            .unwrap();
+
+        // The match overlaps the bytes it outputs
+        let data = rdp61_data(L1_COMPRESSED | L1_PACKET_AT_FRONT, &[(4, 1, 0)], b"x");
+        let output = xcrush.decompress(&data, CompressionFlags::COMPRESSED).unwrap();
+
+        assert_eq!(output, b"xxxxx");
+    }
+
+    #[test]
+    fn decompresses_the_second_level_with_mppc() {
+        let mut xcrush = XCrush::new();
+
+        // MPPC literals 'a' and 'b', with the 64 KB history
+        let data = [
+            L1_NO_COMPRESSION,
+            CompressionFlags::COMPRESSED.bits() | 0x01,
+            0x61,
+            0x62,
+        ];
+        let output = xcrush.decompress(&data, CompressionFlags::COMPRESSED).unwrap();
+
+        assert_eq!(output, b"ab");
+    }
+
+    #[test]
+    fn rejects_matches_going_backward() {
+        let mut xcrush = XCrush::new();
+
+        let data = rdp61_data(L1_COMPRESSED, &[(3, 4, 0), (3, 2, 0)], b"abcd");
+        let result = xcrush.decompress(&data, CompressionFlags::COMPRESSED);
+
+        assert!(matches!(result, Err(BulkError::InvalidMatchOutputOffset(2))));
+    }
+}
@@ -0,0 +1,640 @@
+//! ClearCodec decoder ([MS-RDPEGFX] 2.2.4.1)
+//!
+//! ClearCodec is the lossless codec used by the graphics pipeline for text and user interface elements.
+//! A bitmap is built from three layers, each one drawn over the previous one:
+//!
+//! - the residual layer, a run-length encoded background covering the whole bitmap;
+//! - the bands layer, made of vertical bars of pixels which are cached across bitmaps;
+//! - the subcodec layer, made of rectangles which are uncompressed, or encoded with NSCodec or RLEX.
+//!
+//! Small bitmaps (glyphs) can also be stored in a glyph cache, and drawn again without sending their content.
+//!
+//! The caches are shared by all the bitmaps drawn on the same surface, so a decoder must be kept for each
+//! surface.
+
+use core::fmt;
+
+use ironrdp_pdu::cursor::ReadCursor;
+use thiserror::Error;
+
+use crate::color_conversion::Rgb;
+use crate::image_processing::{ImageRegionMut, Rgba};
+use crate::nscodec::{self, NsCodecError};
+
+const FLAG_GLYPH_INDEX: u8 = 0x01;
+const FLAG_GLYPH_HIT: u8 = 0x02;
+const FLAG_CACHE_RESET: u8 = 0x04;
+
+const GLYPH_CACHE_SIZE: usize = 4000;
+/// Largest bitmap which can be stored in the glyph cache, in pixels
+const MAX_GLYPH_SIZE: usize = 1024 * 1024;
+
+const VBAR_CACHE_SIZE: usize = 32768;
+const SHORT_VBAR_CACHE_SIZE: usize = 16384;
+/// Largest height of a band, and therefore of a vertical bar
+const MAX_VBAR_HEIGHT: usize = 52;
+
+const COMPOSITE_PAYLOAD_HEADER_SIZE: usize = 12;
+const BAND_HEADER_SIZE: usize = 11;
+const SUBCODEC_HEADER_SIZE: usize = 13;
+
+const SUBCODEC_UNCOMPRESSED: u8 = 0;
+const SUBCODEC_NSCODEC: u8 = 1;
+const SUBCODEC_RLEX: u8 = 2;
+
+const MAX_RLEX_PALETTE_SIZE: usize = 127;
+
+const BLACK: Rgb = Rgb { r: 0, g: 0, b: 0 };
+
+#[derive(Debug, Error)]
+pub enum ClearCodecError {
+    #[error("not enough data to decode the {0}")]
+    NotEnoughData(&'static str),
+    #[error("glyph hit without glyph index")]
+    GlyphHitWithoutIndex,
+    #[error("invalid glyph index: {0}")]
+    InvalidGlyphIndex(u16),
+    #[error("glyph {0} is not cached, or is smaller than the bitmap")]
+    InvalidGlyph(u16),
+    #[error("bitmap is too large to be cached as a glyph")]
+    GlyphTooLarge,
+    #[error("residual layer does not match the bitmap size")]
+    InvalidResidualLayer,
+    #[error("invalid band: {0}")]
+    InvalidBand(&'static str),
+    #[error("invalid vertical bar: {0}")]
+    InvalidVBar(&'static str),
+    #[error("subcodec rectangle is out of the bitmap bounds")]
+    InvalidSubcodecRectangle,
+    #[error("invalid subcodec ID: {0}")]
+    InvalidSubcodecId(u8),
+    #[error("invalid RLEX data: {0}")]
+    InvalidRlex(&'static str),
+    #[error("invalid uncompressed subcodec data size")]
+    InvalidUncompressedDataSize,
+    #[error("failed to decode NSCodec subcodec: {0}")]
+    NsCodec(#[from] NsCodecError),
+    #[error("destination region is not large enough to hold the decoded bitmap")]
+    InvalidDestinationRegion,
+}
+
+#[derive(Debug, Clone)]
+struct Glyph {
+    pixels: Vec<Rgb>,
+}
+
+/// ClearCodec decoder, keeping the caches of a surface
+pub struct ClearCodecDecoder {
+    vbar_cache: Vec<Vec<Rgb>>,
+    vbar_cursor: usize,
+    short_vbar_cache: Vec<Vec<Rgb>>,
+    short_vbar_cursor: usize,
+    glyph_cache: Vec<Option<Glyph>>,
+}
+
+impl fmt::Debug for ClearCodecDecoder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClearCodecDecoder")
+            .field("vbar_cursor", &self.vbar_cursor)
+            .field("short_vbar_cursor", &self.short_vbar_cursor)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for ClearCodecDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ClearCodecDecoder {
+    pub fn new() -> Self {
+        Self {
+            vbar_cache: vec![Vec::new(); VBAR_CACHE_SIZE],
+            vbar_cursor: 0,
+            short_vbar_cache: vec![Vec::new(); SHORT_VBAR_CACHE_SIZE],
+            short_vbar_cursor: 0,
+            glyph_cache: vec![None; GLYPH_CACHE_SIZE],
+        }
+    }
+
+    /// Decodes a `RDPGFX_CLEARCODEC_BITMAP_STREAM` of `width` x `height` pixels at the top-left corner of
+    /// the `dst` region.
+    ///
+    /// The layers are drawn over the current content of the region. Pixels falling outside of the region
+    /// are decoded but not written, so the region may be smaller than the bitmap.
+    pub fn decode(
+        &mut self,
+        src: &[u8],
+        width: usize,
+        height: usize,
+        dst: &mut ImageRegionMut<'_>,
+    ) -> Result<(), ClearCodecError> {
+        let mut src = ReadCursor::new(src);
+
+        if src.len() < 2 {
+            return Err(ClearCodecError::NotEnoughData("bitmap stream header"));
+        }
+
+        let glyph_flags = src.read_u8();
+        let _seq_number = src.read_u8();
+
+        if glyph_flags & FLAG_CACHE_RESET != 0 {
+            self.vbar_cursor = 0;
+            self.short_vbar_cursor = 0;
+        }
+
+        let glyph_index = if glyph_flags & FLAG_GLYPH_INDEX != 0 {
+            if src.len() < 2 {
+                return Err(ClearCodecError::NotEnoughData("glyph index"));
+            }
+
+            let glyph_index = src.read_u16();
+
+            if usize::from(glyph_index) >= GLYPH_CACHE_SIZE {
+                return Err(ClearCodecError::InvalidGlyphIndex(glyph_index));
+            }
+
+            if width * height > MAX_GLYPH_SIZE {
+                return Err(ClearCodecError::GlyphTooLarge);
+            }
+
+            Some(glyph_index)
+        } else {
+            None
+        };
+
+        let mut destination = Destination::new(dst, width, height)?;
+
+        if glyph_flags & FLAG_GLYPH_HIT != 0 {
+            let glyph_index = glyph_index.ok_or(ClearCodecError::GlyphHitWithoutIndex)?;
+
+            let glyph = self.glyph_cache[usize::from(glyph_index)]
+                .as_ref()
+                .filter(|glyph| glyph.pixels.len() >= width * height)
+                .ok_or(ClearCodecError::InvalidGlyph(glyph_index))?;
+
+            destination.write(&glyph.pixels[..width * height]);
+
+            return Ok(());
+        }
+
+        let mut pixels = destination.read();
+
+        if !src.is_empty() {
+            self.decode_composite_payload(&mut src, width, height, &mut pixels)?;
+        }
+
+        destination.write(&pixels);
+
+        if let Some(glyph_index) = glyph_index {
+            self.glyph_cache[usize::from(glyph_index)] = Some(Glyph { pixels });
+        }
+
+        Ok(())
+    }
+
+    fn decode_composite_payload(
+        &mut self,
+        src: &mut ReadCursor<'_>,
+        width: usize,
+        height: usize,
+        pixels: &mut [Rgb],
+    ) -> Result<(), ClearCodecError> {
+        if src.len() < COMPOSITE_PAYLOAD_HEADER_SIZE {
+            return Err(ClearCodecError::NotEnoughData("composite payload header"));
+        }
+
+        let residual_byte_count = src.read_u32() as usize;
+        let bands_byte_count = src.read_u32() as usize;
+        let subcodec_byte_count = src.read_u32() as usize;
+
+        if src.len() < residual_byte_count + bands_byte_count + subcodec_byte_count {
+            return Err(ClearCodecError::NotEnoughData("composite payload"));
+        }
+
+        let residual_data = src.read_slice(residual_byte_count);
+        let bands_data = src.read_slice(bands_byte_count);
+        let subcodec_data = src.read_slice(subcodec_byte_count);
+
+        if !residual_data.is_empty() {
+            decode_residual_layer(residual_data, pixels)?;
+        }
+
+        if !bands_data.is_empty() {
+            self.decode_bands_layer(bands_data, width, height, pixels)?;
+        }
+
+        if !subcodec_data.is_empty() {
+            decode_subcodec_layer(subcodec_data, width, height, pixels)?;
+        }
+
+        Ok(())
+    }
+
+    fn decode_bands_layer(
+        &mut self,
+        data: &[u8],
+        width: usize,
+        height: usize,
+        pixels: &mut [Rgb],
+    ) -> Result<(), ClearCodecError> {
+        let mut src = ReadCursor::new(data);
+
+        while !src.is_empty() {
+            if src.len() < BAND_HEADER_SIZE {
+                return Err(ClearCodecError::NotEnoughData("band header"));
+            }
+
+            let x_start = usize::from(src.read_u16());
+            let x_end = usize::from(src.read_u16());
+            let y_start = usize::from(src.read_u16());
+            let y_end = usize::from(src.read_u16());
+            let background = read_bgr(&mut src);
+
+            if x_end < x_start || y_end < y_start {
+                return Err(ClearCodecError::InvalidBand("end is before start"));
+            }
+
+            let vbar_height = y_end - y_start + 1;
+
+            if vbar_height > MAX_VBAR_HEIGHT {
+                return Err(ClearCodecError::InvalidBand("too high"));
+            }
+
+            for x in x_start..=x_end {
+                let vbar = self.decode_vbar(&mut src, vbar_height, background)?;
+
+                if x >= width {
+                    continue;
+                }
+
+                for (y, color) in (y_start..height).zip(vbar.iter().copied()) {
+                    pixels[y * width + x] = color;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads a vertical bar of the current band, updating the vertical bar caches.
+    fn decode_vbar(
+        &mut self,
+        src: &mut ReadCursor<'_>,
+        vbar_height: usize,
+        background: Rgb,
+    ) -> Result<&[Rgb], ClearCodecError> {
+        if src.len() < 2 {
+            return Err(ClearCodecError::NotEnoughData("vertical bar header"));
+        }
+
+        let vbar_header = src.read_u16();
+
+        let (y_on, short_vbar_index) = match vbar_header & 0xC000 {
+            // SHORT_VBAR_CACHE_HIT
+            0x4000 => {
+                let short_vbar_index = usize::from(vbar_header & 0x3FFF);
+
+                if src.is_empty() {
+                    return Err(ClearCodecError::NotEnoughData("short vertical bar"));
+                }
+
+                (usize::from(src.read_u8()), short_vbar_index)
+            }
+            // SHORT_VBAR_CACHE_MISS
+            0x0000 => {
+                let y_on = usize::from(vbar_header & 0xFF);
+                let y_off = usize::from((vbar_header >> 8) & 0x3F);
+
+                if y_off < y_on {
+                    return Err(ClearCodecError::InvalidVBar("short vertical bar ends before its start"));
+                }
+
+                let pixel_count = y_off - y_on;
+
+                if pixel_count > MAX_VBAR_HEIGHT {
+                    return Err(ClearCodecError::InvalidVBar("short vertical bar is too high"));
+                }
+
+                if src.len() < pixel_count * 3 {
+                    return Err(ClearCodecError::NotEnoughData("short vertical bar"));
+                }
+
+                let short_vbar_index = self.short_vbar_cursor;
+                self.short_vbar_cache[short_vbar_index] = (0..pixel_count).map(|_| read_bgr(src)).collect();
+                self.short_vbar_cursor = (self.short_vbar_cursor + 1) % SHORT_VBAR_CACHE_SIZE;
+
+                (y_on, short_vbar_index)
+            }
+            // VBAR_CACHE_HIT
+            _ => {
+                let vbar = &mut self.vbar_cache[usize::from(vbar_header & 0x7FFF)];
+
+                // Entries may be empty after a cache reset, or have been stored for a band of another height
+                vbar.resize(vbar_height, BLACK);
+
+                return Ok(vbar);
+            }
+        };
+
+        // A new vertical bar is made of the short vertical bar, over the background color of the band
+        let short_vbar = &self.short_vbar_cache[short_vbar_index];
+
+        let vbar = (0..vbar_height)
+            .map(|y| {
+                y.checked_sub(y_on)
+                    .and_then(|idx| short_vbar.get(idx))
+                    .copied()
+                    .unwrap_or(background)
+            })
+            .collect();
+
+        let vbar_index = self.vbar_cursor;
+        self.vbar_cache[vbar_index] = vbar;
+        self.vbar_cursor = (self.vbar_cursor + 1) % VBAR_CACHE_SIZE;
+
+        Ok(&self.vbar_cache[vbar_index])
+    }
+}
+
+/// Run-length encoded colors, filling the bitmap from left to right and top to bottom.
+fn decode_residual_layer(data: &[u8], pixels: &mut [Rgb]) -> Result<(), ClearCodecError> {
+    let mut src = ReadCursor::new(data);
+    let mut pixel_idx = 0;
+
+    while !src.is_empty() {
+        if src.len() < 4 {
+            return Err(ClearCodecError::NotEnoughData("residual segment"));
+        }
+
+        let color = read_bgr(&mut src);
+        let run_length = read_run_length(&mut src)?;
+
+        let run = pixels
+            .get_mut(pixel_idx..pixel_idx + run_length)
+            .ok_or(ClearCodecError::InvalidResidualLayer)?;
+        run.fill(color);
+
+        pixel_idx += run_length;
+    }
+
+    if pixel_idx != pixels.len() {
+        return Err(ClearCodecError::InvalidResidualLayer);
+    }
+
+    Ok(())
+}
+
+fn decode_subcodec_layer(data: &[u8], width: usize, height: usize, pixels: &mut [Rgb]) -> Result<(), ClearCodecError> {
+    let mut src = ReadCursor::new(data);
+
+    while !src.is_empty() {
+        if src.len() < SUBCODEC_HEADER_SIZE {
+            return Err(ClearCodecError::NotEnoughData("subcodec header"));
+        }
+
+        let x_start = usize::from(src.read_u16());
+        let y_start = usize::from(src.read_u16());
+        let subcodec_width = usize::from(src.read_u16());
+        let subcodec_height = usize::from(src.read_u16());
+        let bitmap_data_byte_count = src.read_u32() as usize;
+        let subcodec_id = src.read_u8();
+
+        if x_start + subcodec_width > width || y_start + subcodec_height > height {
+            return Err(ClearCodecError::InvalidSubcodecRectangle);
+        }
+
+        if src.len() < bitmap_data_byte_count {
+            return Err(ClearCodecError::NotEnoughData("subcodec bitmap data"));
+        }
+
+        let bitmap_data = src.read_slice(bitmap_data_byte_count);
+
+        let subcodec_pixels = match subcodec_id {
+            SUBCODEC_UNCOMPRESSED => {
+                if bitmap_data.len() != subcodec_width * subcodec_height * 3 {
+                    return Err(ClearCodecError::InvalidUncompressedDataSize);
+                }
+
+                let mut src = ReadCursor::new(bitmap_data);
+                (0..subcodec_width * subcodec_height)
+                    .map(|_| read_bgr(&mut src))
+                    .collect()
+            }
+            SUBCODEC_NSCODEC => nscodec::decode(bitmap_data, subcodec_width, subcodec_height)?
+                .chunks_exact(4)
+                .map(|bgra| Rgb {
+                    r: bgra[2],
+                    g: bgra[1],
+                    b: bgra[0],
+                })
+                .collect(),
+            SUBCODEC_RLEX => decode_rlex(bitmap_data, subcodec_width * subcodec_height)?,
+            subcodec_id => return Err(ClearCodecError::InvalidSubcodecId(subcodec_id)),
+        };
+
+        if subcodec_width == 0 {
+            continue;
+        }
+
+        for (row_idx, row) in subcodec_pixels.chunks_exact(subcodec_width).enumerate() {
+            let start = (y_start + row_idx) * width + x_start;
+            pixels[start..start + subcodec_width].copy_from_slice(row);
+        }
+    }
+
+    Ok(())
+}
+
+/// Decodes a `CLEARCODEC_SUBCODEC_RLEX` bitmap of `pixel_count` pixels.
+///
+/// Each segment is a run of a palette color, followed by a suite of consecutive palette colors.
+fn decode_rlex(data: &[u8], pixel_count: usize) -> Result<Vec<Rgb>, ClearCodecError> {
+    let mut src = ReadCursor::new(data);
+
+    if src.is_empty() {
+        return Err(ClearCodecError::NotEnoughData("RLEX palette"));
+    }
+
+    let palette_count = usize::from(src.read_u8());
+
+    if palette_count == 0 || palette_count > MAX_RLEX_PALETTE_SIZE {
+        return Err(ClearCodecError::InvalidRlex("invalid palette size"));
+    }
+
+    if src.len() < palette_count * 3 {
+        return Err(ClearCodecError::NotEnoughData("RLEX palette"));
+    }
+
+    let palette = (0..palette_count).map(|_| read_bgr(&mut src)).collect::<Vec<_>>();
+
+    // The stop index is stored in the lowest bits, and the suite depth in the remaining bits
+    let stop_index_bits = if palette_count == 1 {
+        1
+    } else {
+        usize::BITS - (palette_count - 1).leading_zeros()
+    };
+    let stop_index_mask = (1u8 << stop_index_bits) - 1;
+
+    let mut pixels = Vec::with_capacity(pixel_count);
+
+    while !src.is_empty() {
+        if src.len() < 2 {
+            return Err(ClearCodecError::NotEnoughData("RLEX segment"));
+        }
+
+        let indexes = src.read_u8();
+        let stop_index = usize::from(indexes & stop_index_mask);
+        let suite_depth = usize::from(indexes >> stop_index_bits);
+        let run_length = read_run_length(&mut src)?;
+
+        let start_index = stop_index
+            .checked_sub(suite_depth)
+            .ok_or(ClearCodecError::InvalidRlex("suite starts before the palette"))?;
+
+        if stop_index >= palette_count {
+            return Err(ClearCodecError::InvalidRlex("palette index out of bounds"));
+        }
+
+        if pixels.len() + run_length + suite_depth + 1 > pixel_count {
+            return Err(ClearCodecError::InvalidRlex("too many pixels"));
+        }
+
+        pixels.resize(pixels.len() + run_length, palette[start_index]);
+        pixels.extend_from_slice(&palette[start_index..=stop_index]);
+    }
+
+    if pixels.len() != pixel_count {
+        return Err(ClearCodecError::InvalidRlex("not enough pixels"));
+    }
+
+    Ok(pixels)
+}
+
+/// Reads a run length, encoded on 1, 3 or 7 bytes.
+fn read_run_length(src: &mut ReadCursor<'_>) -> Result<usize, ClearCodecError> {
+    if src.is_empty() {
+        return Err(ClearCodecError::NotEnoughData("run length"));
+    }
+
+    let factor = src.read_u8();
+    if factor < 0xFF {
+        return Ok(usize::from(factor));
+    }
+
+    if src.len() < 2 {
+        return Err(ClearCodecError::NotEnoughData("run length"));
+    }
+
+    let factor = src.read_u16();
+    if factor < 0xFFFF {
+        return Ok(usize::from(factor));
+    }
+
+    if src.len() < 4 {
+        return Err(ClearCodecError::NotEnoughData("run length"));
+    }
+
+    Ok(src.read_u32() as usize)
+}
+
+/// Reads a color stored in blue, green and red order. The caller ensures that 3 bytes are available.
+fn read_bgr(src: &mut ReadCursor<'_>) -> Rgb {
+    let [b, g, r] = src.read_array();
+    Rgb { r, g, b }
+}
+
+/// Part of the destination region covered by the bitmap
+struct Destination<'a, 'b> {
+    region: &'a mut ImageRegionMut<'b>,
+    /// Bitmap width
+    width: usize,
+    /// Bitmap height
+    height: usize,
+    /// Width of the bitmap part inside of the region
+    visible_width: usize,
+    /// Height of the bitmap part inside of the region
+    visible_height: usize,
+    step: usize,
+    bytes_per_pixel: usize,
+}
+
+impl<'a, 'b> Destination<'a, 'b> {
+    fn new(region: &'a mut ImageRegionMut<'b>, width: usize, height: usize) -> Result<Self, ClearCodecError> {
+        let bytes_per_pixel = usize::from(region.pixel_format.bytes_per_pixel());
+        let visible_width = width.min(usize::from(region.region.width()));
+        let visible_height = height.min(usize::from(region.region.height()));
+        let step = if region.step == 0 {
+            usize::from(region.region.width()) * bytes_per_pixel
+        } else {
+            usize::from(region.step)
+        };
+
+        let required_length = if visible_width == 0 || visible_height == 0 {
+            0
+        } else {
+            (usize::from(region.region.top) + visible_height - 1) * step
+                + (usize::from(region.region.left) + visible_width) * bytes_per_pixel
+        };
+
+        if region.data.len() < required_length {
+            return Err(ClearCodecError::InvalidDestinationRegion);
+        }
+
+        Ok(Self {
+            region,
+            width,
+            height,
+            visible_width,
+            visible_height,
+            step,
+            bytes_per_pixel,
+        })
+    }
+
+    fn offset(&self, x: usize, y: usize) -> usize {
+        (usize::from(self.region.region.top) + y) * self.step
+            + (usize::from(self.region.region.left) + x) * self.bytes_per_pixel
+    }
+
+    /// Current pixels of the region, with black pixels outside of it.
+    fn read(&self) -> Vec<Rgb> {
+        let mut pixels = vec![BLACK; self.width * self.height];
+
+        for y in 0..self.visible_height {
+            for x in 0..self.visible_width {
+                // The destination size was checked when creating the destination
+                if let Ok(color) = self
+                    .region
+                    .pixel_format
+                    .read_color(&self.region.data[self.offset(x, y)..])
+                {
+                    pixels[y * self.width + x] = Rgb {
+                        r: color.r,
+                        g: color.g,
+                        b: color.b,
+                    };
+                }
+            }
+        }
+
+        pixels
+    }
+
+    /// Writes the opaque pixels of the bitmap which are inside of the region.
+    fn write(&mut self, pixels: &[Rgb]) {
+        for y in 0..self.visible_height {
+            for x in 0..self.visible_width {
+                let Rgb { r, g, b } = pixels[y * self.width + x];
+                let offset = self.offset(x, y);
+
+                // The destination size was checked when creating the destination
+                let _ = self
+                    .region
+                    .pixel_format
+                    .write_color(Rgba { r, g, b, a: 0xFF }, &mut self.region.data[offset..]);
+            }
+        }
+    }
+}
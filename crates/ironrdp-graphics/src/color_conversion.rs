@@ -14,6 +14,28 @@ pub fn ycbcr_to_bgra(input: YCbCrBuffer<'_>, mut output: &mut [u8]) -> io::Resul
     Ok(())
 }
 
+/// Converts BGRA pixels to the YCbCr components used by RemoteFX, the inverse of [`ycbcr_to_bgra`].
+///
+/// The components are written to `y`, `cb` and `cr`, one value per pixel, as long as all of them have room left.
+pub fn bgra_to_ycbcr(input: &[u8], y: &mut [i16], cb: &mut [i16], cr: &mut [i16]) {
+    for (((pixel, y), cb), cr) in input
+        .chunks_exact(4)
+        .zip(y.iter_mut())
+        .zip(cb.iter_mut())
+        .zip(cr.iter_mut())
+    {
+        let ycbcr = YCbCr::from(Rgb {
+            r: pixel[2],
+            g: pixel[1],
+            b: pixel[0],
+        });
+
+        *y = ycbcr.y;
+        *cb = ycbcr.cb;
+        *cr = ycbcr.cr;
+    }
+}
+
 fn clip(v: i32) -> u8 {
     min(max(v, 0), 255) as u8
 }
@@ -91,6 +113,26 @@ impl From<YCbCr> for Rgb {
     }
 }
 
+impl From<Rgb> for YCbCr {
+    /// The components are fixed-point values with 5 fractional bits, Y being shifted to be centered on zero.
+    fn from(Rgb { r, g, b }: Rgb) -> Self {
+        let r = i32::from(r);
+        let g = i32::from(g);
+        let b = i32::from(b);
+
+        // BT.601 coefficients, multiplied by 2^15
+        let y = ((r * 9798 + g * 19235 + b * 3735) >> 10) - 4096;
+        let cb = (r * -5535 + g * -10868 + b * 16403) >> 10;
+        let cr = (r * 16377 + g * -13714 + b * -2663) >> 10;
+
+        Self {
+            y: y.clamp(-4096, 4095) as i16,
+            cb: cb.clamp(-4096, 4095) as i16,
+            cr: cr.clamp(-4096, 4095) as i16,
+        }
+    }
+}
+
 impl From<YCoCg> for Rgb {
     fn from(YCoCg { y, co, cg }: YCoCg) -> Self {
         let y = i16::from(y);
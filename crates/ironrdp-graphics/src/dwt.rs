@@ -6,6 +6,23 @@ pub fn decode(buffer: &mut [i16], temp_buffer: &mut [i16]) {
     decode_block(&mut *buffer, temp_buffer, 32);
 }
 
+/// Inverse reduce-extrapolate DWT, used by the RemoteFX Progressive codec ([MS-RDPEGFX] 3.2.8.1.2.1)
+///
+/// The subbands of each level are not square: the low-pass bands have one more coefficient than the high-pass
+/// ones (two more at the first level), which avoids the artifacts of the classic DWT on the tile edges.
+pub fn decode_reduce_extrapolate(buffer: &mut [i16], temp_buffer: &mut [i16]) {
+    decode_reduce_extrapolate_block(&mut buffer[3807..], temp_buffer, 3);
+    decode_reduce_extrapolate_block(&mut buffer[3007..], temp_buffer, 2);
+    decode_reduce_extrapolate_block(buffer, temp_buffer, 1);
+}
+
+/// Forward DWT, the inverse of [`decode`]
+pub fn encode(buffer: &mut [i16], temp_buffer: &mut [i16]) {
+    encode_block(&mut *buffer, temp_buffer, 32);
+    encode_block(&mut buffer[3072..], temp_buffer, 16);
+    encode_block(&mut buffer[3840..], temp_buffer, 8);
+}
+
 fn decode_block(buffer: &mut [i16], temp_buffer: &mut [i16], subband_width: usize) {
     inverse_horizontal(buffer, temp_buffer, subband_width);
     inverse_vertical(buffer, temp_buffer, subband_width);
@@ -90,3 +107,181 @@ fn inverse_vertical(mut buffer: &mut [i16], mut temp_buffer: &[i16], subband_wid
         buffer = &mut buffer[1..];
     }
 }
+
+fn encode_block(buffer: &mut [i16], temp_buffer: &mut [i16], subband_width: usize) {
+    forward_vertical(buffer, temp_buffer, subband_width);
+    forward_horizontal(temp_buffer, buffer, subband_width);
+}
+
+// Forward DWT in vertical direction, results in 2 sub-bands in L, H order in the temp buffer
+fn forward_vertical(buffer: &[i16], temp_buffer: &mut [i16], subband_width: usize) {
+    let total_width = subband_width * 2;
+    let (l_dst, h_dst) = temp_buffer.split_at_mut(subband_width * total_width);
+
+    for x in 0..total_width {
+        for n in 0..subband_width {
+            let y = n * 2;
+            let even = i32::from(buffer[y * total_width + x]);
+            let odd = i32::from(buffer[(y + 1) * total_width + x]);
+            let next_even = if n < subband_width - 1 {
+                i32::from(buffer[(y + 2) * total_width + x])
+            } else {
+                even
+            };
+
+            let high = (odd - ((even + next_even) >> 1)) >> 1;
+            let previous_high = if n == 0 {
+                high
+            } else {
+                i32::from(h_dst[(n - 1) * total_width + x])
+            };
+
+            h_dst[n * total_width + x] = high as i16;
+            l_dst[n * total_width + x] = (even + ((previous_high + high) >> 1)) as i16;
+        }
+    }
+}
+
+// Forward DWT in horizontal direction, results in 4 sub-bands in HL(0), LH(1), HH(2), LL(3) order in the buffer.
+// The lower part L generates LL(3) and HL(0).
+// The higher part H generates LH(1) and HH(2).
+fn forward_horizontal(temp_buffer: &[i16], buffer: &mut [i16], subband_width: usize) {
+    let squared_subband_width = subband_width.pow(2);
+
+    let (hl, buffer) = buffer.split_at_mut(squared_subband_width);
+    let (lh, buffer) = buffer.split_at_mut(squared_subband_width);
+    let (hh, ll) = buffer.split_at_mut(squared_subband_width);
+    let (l_src, h_src) = temp_buffer.split_at(squared_subband_width * 2);
+
+    forward_horizontal_band(l_src, ll, hl, subband_width);
+    forward_horizontal_band(h_src, lh, hh, subband_width);
+}
+
+fn forward_horizontal_band(src: &[i16], l_dst: &mut [i16], h_dst: &mut [i16], subband_width: usize) {
+    for ((src, l_dst), h_dst) in src
+        .chunks_exact(subband_width * 2)
+        .zip(l_dst.chunks_exact_mut(subband_width))
+        .zip(h_dst.chunks_exact_mut(subband_width))
+    {
+        for n in 0..subband_width {
+            let x = n * 2;
+            let even = i32::from(src[x]);
+            let odd = i32::from(src[x + 1]);
+            let next_even = if n < subband_width - 1 {
+                i32::from(src[x + 2])
+            } else {
+                even
+            };
+
+            let high = (odd - ((even + next_even) >> 1)) >> 1;
+            let previous_high = if n == 0 { high } else { i32::from(h_dst[n - 1]) };
+
+            h_dst[n] = high as i16;
+            l_dst[n] = (even + ((previous_high + high) >> 1)) as i16;
+        }
+    }
+}
+
+/// Returns the number of low-pass and high-pass coefficients of a reduce-extrapolate DWT level.
+fn reduce_extrapolate_band_lengths(level: usize) -> (usize, usize) {
+    let low = (64 >> level) + 1;
+    let high = if level == 1 {
+        31
+    } else {
+        (64 + (1 << (level - 1))) >> level
+    };
+
+    (low, high)
+}
+
+// The 4 sub-bands are stored in HL (high x low), LH (low x high), HH (high x high) and LL (low x low) order,
+// where the first dimension is the width.
+fn decode_reduce_extrapolate_block(buffer: &mut [i16], temp_buffer: &mut [i16], level: usize) {
+    let (low, high) = reduce_extrapolate_band_lengths(level);
+    let total = low + high;
+
+    let lh_start = high * low;
+    let hh_start = lh_start + low * high;
+    let ll_start = hh_start + high * high;
+
+    let (l_dst, h_dst) = temp_buffer.split_at_mut(low * total);
+
+    // Horizontal direction: LL and HL into L, LH and HH into H
+    for row in 0..low {
+        inverse_reduce_extrapolate_line(
+            |n| buffer[ll_start + row * low + n],
+            |n| buffer[row * high + n],
+            low,
+            high,
+            |x, value| l_dst[row * total + x] = value,
+        );
+    }
+
+    for row in 0..high {
+        inverse_reduce_extrapolate_line(
+            |n| buffer[lh_start + row * low + n],
+            |n| buffer[hh_start + row * high + n],
+            low,
+            high,
+            |x, value| h_dst[row * total + x] = value,
+        );
+    }
+
+    // Vertical direction: L and H into the whole block
+    for column in 0..total {
+        inverse_reduce_extrapolate_line(
+            |n| l_dst[n * total + column],
+            |n| h_dst[n * total + column],
+            low,
+            high,
+            |y, value| buffer[y * total + column] = value,
+        );
+    }
+}
+
+/// Reconstructs `low_length + high_length` values from the low-pass and high-pass coefficients of a line.
+fn inverse_reduce_extrapolate_line(
+    low: impl Fn(usize) -> i16,
+    high: impl Fn(usize) -> i16,
+    low_length: usize,
+    high_length: usize,
+    mut write: impl FnMut(usize, i16),
+) {
+    let low = |n| i32::from(low(n));
+    let high = |n| i32::from(high(n));
+
+    let mut h0 = high(0);
+    let mut x0 = i32::from((low(0) - h0) as i16);
+    let mut x2 = x0;
+
+    for n in 1..high_length {
+        let h1 = high(n);
+        x2 = i32::from((low(n) - (h0 + h1) / 2) as i16);
+
+        write(2 * n - 2, x0 as i16);
+        write(2 * n - 1, ((x0 + x2) / 2 + 2 * h0) as i16);
+
+        x0 = x2;
+        h0 = h1;
+    }
+
+    let x = 2 * high_length - 2;
+
+    if low_length <= high_length {
+        write(x, x2 as i16);
+        write(x + 1, (x2 + 2 * h0) as i16);
+    } else if low_length == high_length + 1 {
+        let x0 = i32::from((low(high_length) - h0) as i16);
+
+        write(x, x2 as i16);
+        write(x + 1, ((x0 + x2) / 2 + 2 * h0) as i16);
+        write(x + 2, x0 as i16);
+    } else {
+        let x0 = i32::from((low(high_length) - h0 / 2) as i16);
+
+        write(x, x2 as i16);
+        write(x + 1, ((x0 + x2) / 2 + 2 * h0) as i16);
+        write(x + 2, x0 as i16);
+        write(x + 3, ((x0 + low(high_length + 1)) / 2) as i16);
+    }
+}
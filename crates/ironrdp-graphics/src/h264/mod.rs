@@ -0,0 +1,45 @@
+//! H.264 decoders
+//!
+//! The AVC420 and AVC444 codecs of the graphics pipeline carry H.264 bitstreams, which are decoded by an
+//! implementation of [`H264Decoder`]. The `openh264` feature provides an implementation backed by the OpenH264
+//! library, other decoders (hardware accelerated, platform specific...) can be plugged by implementing the trait.
+
+#[cfg(feature = "openh264")]
+mod openh264;
+
+use thiserror::Error;
+
+#[cfg(feature = "openh264")]
+pub use self::openh264::OpenH264Decoder;
+
+#[derive(Debug, Error)]
+pub enum H264Error {
+    #[error("failed to create the H.264 decoder: {0}")]
+    Creation(#[source] Box<dyn std::error::Error + Send + Sync>),
+    #[error("failed to decode the H.264 bitstream: {0}")]
+    Decoding(#[source] Box<dyn std::error::Error + Send + Sync>),
+}
+
+/// Picture decoded from an H.264 bitstream, in the planar YUV 4:2:0 format (I420)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct YuvFrame {
+    pub width: usize,
+    pub height: usize,
+    /// Luma plane, `y_stride` bytes per row
+    pub y: Vec<u8>,
+    /// Blue-difference chroma plane, subsampled by two in both directions, `uv_stride` bytes per row
+    pub u: Vec<u8>,
+    /// Red-difference chroma plane, subsampled by two in both directions, `uv_stride` bytes per row
+    pub v: Vec<u8>,
+    pub y_stride: usize,
+    pub uv_stride: usize,
+}
+
+/// Decoder of the H.264 bitstreams sent with the AVC420 and AVC444 codecs
+///
+/// The bitstreams are in the Annex B format, and every one of them is expected to produce a picture
+/// (the servers encode with no frame reordering).
+pub trait H264Decoder: Send {
+    /// Decodes the next access unit of the bitstream, and returns the decoded picture, if any.
+    fn decode(&mut self, bitstream: &[u8]) -> Result<Option<YuvFrame>, H264Error>;
+}
@@ -0,0 +1,41 @@
+use openh264::decoder::Decoder;
+
+use super::{H264Decoder, H264Error, YuvFrame};
+
+/// H.264 decoder backed by the OpenH264 library
+pub struct OpenH264Decoder {
+    decoder: Decoder,
+}
+
+impl OpenH264Decoder {
+    pub fn new() -> Result<Self, H264Error> {
+        let decoder = Decoder::new().map_err(|e| H264Error::Creation(Box::new(e)))?;
+
+        Ok(Self { decoder })
+    }
+}
+
+impl H264Decoder for OpenH264Decoder {
+    fn decode(&mut self, bitstream: &[u8]) -> Result<Option<YuvFrame>, H264Error> {
+        let Some(yuv) = self
+            .decoder
+            .decode(bitstream)
+            .map_err(|e| H264Error::Decoding(Box::new(e)))?
+        else {
+            return Ok(None);
+        };
+
+        let (width, height) = yuv.dimension_rgb();
+        let (y_stride, uv_stride, _) = yuv.strides_yuv();
+
+        Ok(Some(YuvFrame {
+            width,
+            height,
+            y: yuv.y_with_stride().to_vec(),
+            u: yuv.u_with_stride().to_vec(),
+            v: yuv.v_with_stride().to_vec(),
+            y_stride,
+            uv_stride,
+        }))
+    }
+}
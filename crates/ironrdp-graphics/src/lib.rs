@@ -1,9 +1,17 @@
+pub mod avc;
+pub mod bulk;
+pub mod clearcodec;
 pub mod color_conversion;
 pub mod dwt;
+pub mod h264;
 pub mod image_processing;
+pub mod nscodec;
+pub mod pointer;
 pub mod quantization;
 pub mod rdp6;
 pub mod rectangle_processing;
+pub mod rfx_encoder;
+pub mod rfx_progressive;
 pub mod rle;
 pub mod rlgr;
 pub mod subband_reconstruction;
@@ -0,0 +1,175 @@
+//! NSCodec decoder ([MS-RDPNSC])
+//!
+//! NSCodec encodes a bitmap as four planes (luma, orange chroma, green chroma and alpha), each of
+//! them optionally run-length encoded. The chroma planes may be subsampled, and their precision
+//! reduced according to the color loss level.
+
+use ironrdp_pdu::cursor::ReadCursor;
+use thiserror::Error;
+
+/// Size of the `NSCODEC_BITMAP_STREAM` header
+const HEADER_SIZE: usize = 20;
+
+/// Number of raw bytes ending a run-length encoded plane
+const RLE_RAW_SUFFIX_SIZE: usize = 4;
+
+#[derive(Debug, Error)]
+pub enum NsCodecError {
+    #[error("not enough data to decode the {0}")]
+    NotEnoughData(&'static str),
+    #[error("invalid color loss level: {0}")]
+    InvalidColorLossLevel(u8),
+    #[error("run-length encoded plane is larger than the bitmap")]
+    InvalidRunLength,
+}
+
+/// Decodes a `NSCODEC_BITMAP_STREAM` into a top-down bitmap of `width` x `height` pixels.
+///
+/// The pixels are written in blue, green, red and alpha order.
+pub fn decode(src: &[u8], width: usize, height: usize) -> Result<Vec<u8>, NsCodecError> {
+    let mut src = ReadCursor::new(src);
+
+    if src.len() < HEADER_SIZE {
+        return Err(NsCodecError::NotEnoughData("bitmap stream header"));
+    }
+
+    let plane_byte_counts = [src.read_u32(), src.read_u32(), src.read_u32(), src.read_u32()];
+    let color_loss_level = src.read_u8();
+    let chroma_subsampling = src.read_u8() != 0;
+    let _reserved = src.read_u16();
+
+    if !(1..=7).contains(&color_loss_level) {
+        return Err(NsCodecError::InvalidColorLossLevel(color_loss_level));
+    }
+
+    // With chroma subsampling, the luma plane width is rounded up to a multiple of 8 and the chroma planes
+    // have half the width and height of the rounded luma plane
+    let luma_width = if chroma_subsampling { (width + 7) & !7 } else { width };
+    let (chroma_width, chroma_height) = if chroma_subsampling {
+        (luma_width / 2, height / 2 + height % 2)
+    } else {
+        (width, height)
+    };
+
+    let original_sizes = [
+        luma_width * height,
+        chroma_width * chroma_height,
+        chroma_width * chroma_height,
+        width * height,
+    ];
+
+    let mut planes: [Vec<u8>; 4] = Default::default();
+
+    for ((plane, plane_byte_count), original_size) in planes.iter_mut().zip(plane_byte_counts).zip(original_sizes) {
+        let plane_byte_count = plane_byte_count as usize;
+
+        if src.len() < plane_byte_count {
+            return Err(NsCodecError::NotEnoughData("color plane"));
+        }
+
+        decode_plane(src.read_slice(plane_byte_count), original_size, plane)?;
+    }
+
+    let [luma_plane, co_plane, cg_plane, alpha_plane] = planes;
+
+    let shift = color_loss_level - 1;
+    let mut dst = Vec::with_capacity(width * height * 4);
+
+    for y in 0..height {
+        let chroma_y = if chroma_subsampling { y / 2 } else { y };
+
+        for x in 0..width {
+            let chroma_x = if chroma_subsampling { x / 2 } else { x };
+            let chroma_idx = chroma_y * chroma_width + chroma_x;
+
+            // The chroma values are stored shifted right by the color loss level, and are signed
+            let luma = i16::from(luma_plane[y * luma_width + x]);
+            let co = i16::from((co_plane[chroma_idx] << shift) as i8);
+            let cg = i16::from((cg_plane[chroma_idx] << shift) as i8);
+
+            let r = clamp(luma + co - cg);
+            let g = clamp(luma + cg);
+            let b = clamp(luma - co - cg);
+
+            dst.extend_from_slice(&[b, g, r, alpha_plane[y * width + x]]);
+        }
+    }
+
+    Ok(dst)
+}
+
+/// Decodes a plane of `original_size` bytes.
+///
+/// Empty planes are filled with 0xFF, and planes as large as the original size are not compressed.
+fn decode_plane(src: &[u8], original_size: usize, dst: &mut Vec<u8>) -> Result<(), NsCodecError> {
+    dst.clear();
+
+    if src.is_empty() {
+        dst.resize(original_size, 0xFF);
+    } else if src.len() >= original_size {
+        dst.extend_from_slice(&src[..original_size]);
+    } else {
+        decode_rle_plane(src, original_size, dst)?;
+    }
+
+    Ok(())
+}
+
+/// Decodes a run-length encoded plane (MS-RDPNSC 3.1.8.1).
+///
+/// A byte is repeated when it is followed by the same byte and a run length. The last four bytes of the plane
+/// are never encoded.
+fn decode_rle_plane(src: &[u8], original_size: usize, dst: &mut Vec<u8>) -> Result<(), NsCodecError> {
+    let mut src = ReadCursor::new(src);
+    let mut left = original_size;
+
+    while left > RLE_RAW_SUFFIX_SIZE {
+        if src.is_empty() {
+            return Err(NsCodecError::NotEnoughData("run-length encoded plane"));
+        }
+
+        let value = src.read_u8();
+
+        if left == RLE_RAW_SUFFIX_SIZE + 1 || src.is_empty() || src.peek_u8() != value {
+            dst.push(value);
+            left -= 1;
+            continue;
+        }
+
+        src.advance(1);
+
+        if src.is_empty() {
+            return Err(NsCodecError::NotEnoughData("run length"));
+        }
+
+        let run_length = match src.read_u8() {
+            0xFF => {
+                if src.len() < 4 {
+                    return Err(NsCodecError::NotEnoughData("run length"));
+                }
+
+                src.read_u32() as usize
+            }
+            factor => usize::from(factor) + 2,
+        };
+
+        if run_length > left {
+            return Err(NsCodecError::InvalidRunLength);
+        }
+
+        dst.resize(dst.len() + run_length, value);
+        left -= run_length;
+    }
+
+    if src.len() < left {
+        return Err(NsCodecError::NotEnoughData("run-length encoded plane"));
+    }
+
+    dst.extend_from_slice(src.read_slice(left));
+
+    Ok(())
+}
+
+fn clamp(value: i16) -> u8 {
+    value.clamp(0, 0xFF) as u8
+}
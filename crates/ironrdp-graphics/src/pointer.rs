@@ -0,0 +1,216 @@
+//! Pointer (mouse cursor) shape decoding
+//!
+//! Pointer shapes are described by a XOR mask holding the colors, and by a 1 bpp AND mask telling which
+//! pixels are transparent. Both masks are stored bottom-up, with each scan line padded to a multiple of
+//! 2 bytes.
+//!
+//! See the Color Pointer Update (TS_COLORPOINTERATTRIBUTE) in MS-RDPBCGR.
+
+use ironrdp_pdu::pointer::{ColorPointerAttribute, LargePointerAttribute, PointerAttribute};
+use thiserror::Error;
+
+/// Color depth of the XOR mask of the Color Pointer Update
+const COLOR_POINTER_XOR_BPP: u16 = 24;
+
+const TRANSPARENT: [u8; 4] = [0x00, 0x00, 0x00, 0x00];
+const BLACK: [u8; 4] = [0x00, 0x00, 0x00, 0xff];
+
+/// Pointer shape converted to RGBA
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedPointer {
+    pub width: u16,
+    pub height: u16,
+    pub hotspot_x: u16,
+    pub hotspot_y: u16,
+    /// Top-down RGBA pixels, with straight alpha
+    pub bitmap_data: Vec<u8>,
+}
+
+impl DecodedPointer {
+    /// Decodes a pointer from the Color Pointer Update, which has a 24 bpp XOR mask
+    pub fn decode_color_pointer(pointer: &ColorPointerAttribute<'_>) -> Result<Self, PointerError> {
+        Self::decode(
+            pointer.width,
+            pointer.height,
+            pointer.hot_spot.x,
+            pointer.hot_spot.y,
+            COLOR_POINTER_XOR_BPP,
+            pointer.xor_mask,
+            pointer.and_mask,
+        )
+    }
+
+    /// Decodes a pointer from the New Pointer Update
+    pub fn decode_pointer(pointer: &PointerAttribute<'_>) -> Result<Self, PointerError> {
+        let color_pointer = &pointer.color_pointer;
+
+        Self::decode(
+            color_pointer.width,
+            color_pointer.height,
+            color_pointer.hot_spot.x,
+            color_pointer.hot_spot.y,
+            pointer.xor_bpp,
+            color_pointer.xor_mask,
+            color_pointer.and_mask,
+        )
+    }
+
+    /// Decodes a pointer from the Large Pointer Update
+    pub fn decode_large_pointer(pointer: &LargePointerAttribute<'_>) -> Result<Self, PointerError> {
+        Self::decode(
+            pointer.width,
+            pointer.height,
+            pointer.hot_spot.x,
+            pointer.hot_spot.y,
+            pointer.xor_bpp,
+            pointer.xor_mask,
+            pointer.and_mask,
+        )
+    }
+
+    fn decode(
+        width: u16,
+        height: u16,
+        hotspot_x: u16,
+        hotspot_y: u16,
+        xor_bpp: u16,
+        xor_mask: &[u8],
+        and_mask: &[u8],
+    ) -> Result<Self, PointerError> {
+        Ok(Self {
+            width,
+            height,
+            hotspot_x,
+            hotspot_y,
+            bitmap_data: decode_masks(width, height, xor_bpp, xor_mask, and_mask)?,
+        })
+    }
+}
+
+/// Size in bytes of a scan line padded to a multiple of 2 bytes
+fn stride(width: usize, bpp: usize) -> usize {
+    ((width * bpp + 15) >> 4) << 1
+}
+
+fn get_bit(data: &[u8], row_offset: usize, x: usize) -> bool {
+    data[row_offset + x / 8] & (0x80 >> (x % 8)) != 0
+}
+
+/// Decodes the XOR and AND masks to top-down RGBA pixels
+///
+/// When the AND mask bit is set, a black XOR pixel is transparent while a white one inverts
+/// the screen. As inversion can't be expressed with RGBA, inverted pixels are rendered black.
+/// 32 bpp XOR masks with an alpha channel are used as is, and the AND mask is ignored.
+///
+/// An empty AND mask is allowed, in which case the pointer is opaque.
+pub fn decode_masks(
+    width: u16,
+    height: u16,
+    xor_bpp: u16,
+    xor_mask: &[u8],
+    and_mask: &[u8],
+) -> Result<Vec<u8>, PointerError> {
+    let width = usize::from(width);
+    let height = usize::from(height);
+
+    if !matches!(xor_bpp, 1 | 16 | 24 | 32) {
+        return Err(PointerError::UnsupportedBpp(xor_bpp));
+    }
+
+    let xor_stride = stride(width, usize::from(xor_bpp));
+    check_mask_length("XOR", xor_mask, xor_stride * height)?;
+
+    let and_stride = stride(width, 1);
+    if !and_mask.is_empty() {
+        check_mask_length("AND", and_mask, and_stride * height)?;
+    }
+
+    let has_alpha = xor_bpp == 32 && xor_mask.chunks_exact(4).any(|pixel| pixel[3] != 0);
+
+    let mut rgba = Vec::with_capacity(width * height * 4);
+
+    for y in 0..height {
+        // Masks are stored bottom-up
+        let row = height - 1 - y;
+        let xor_row_offset = row * xor_stride;
+        let and_row_offset = row * and_stride;
+
+        for x in 0..width {
+            let [r, g, b, a] = match xor_bpp {
+                1 => {
+                    if get_bit(xor_mask, xor_row_offset, x) {
+                        [0xff, 0xff, 0xff, 0xff]
+                    } else {
+                        BLACK
+                    }
+                }
+                16 => {
+                    let offset = xor_row_offset + x * 2;
+                    let pixel = u16::from_le_bytes([xor_mask[offset], xor_mask[offset + 1]]);
+
+                    // RGB565
+                    let r = ((pixel >> 11) & 0x1f) as u8;
+                    let g = ((pixel >> 5) & 0x3f) as u8;
+                    let b = (pixel & 0x1f) as u8;
+
+                    [(r << 3) | (r >> 2), (g << 2) | (g >> 4), (b << 3) | (b >> 2), 0xff]
+                }
+                24 => {
+                    let offset = xor_row_offset + x * 3;
+
+                    [xor_mask[offset + 2], xor_mask[offset + 1], xor_mask[offset], 0xff]
+                }
+                _ => {
+                    let offset = xor_row_offset + x * 4;
+
+                    [
+                        xor_mask[offset + 2],
+                        xor_mask[offset + 1],
+                        xor_mask[offset],
+                        xor_mask[offset + 3],
+                    ]
+                }
+            };
+
+            let pixel = if has_alpha {
+                [r, g, b, a]
+            } else if !and_mask.is_empty() && get_bit(and_mask, and_row_offset, x) {
+                match [r, g, b] {
+                    [0x00, 0x00, 0x00] => TRANSPARENT,
+                    [0xff, 0xff, 0xff] => BLACK,
+                    _ => [r, g, b, 0xff],
+                }
+            } else {
+                [r, g, b, 0xff]
+            };
+
+            rgba.extend_from_slice(&pixel);
+        }
+    }
+
+    Ok(rgba)
+}
+
+fn check_mask_length(mask: &'static str, data: &[u8], expected: usize) -> Result<(), PointerError> {
+    if data.len() < expected {
+        Err(PointerError::InvalidMaskLength {
+            mask,
+            actual: data.len(),
+            expected,
+        })
+    } else {
+        Ok(())
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum PointerError {
+    #[error("unsupported XOR mask color depth: {0} bpp")]
+    UnsupportedBpp(u16),
+    #[error("{mask} mask is too short: {actual} < {expected}")]
+    InvalidMaskLength {
+        mask: &'static str,
+        actual: usize,
+        expected: usize,
+    },
+}
@@ -7,6 +7,9 @@ const THIRD_LEVEL_SIZE: usize = 64;
 const FIRST_LEVEL_SUBBANDS_COUNT: usize = 3;
 const SECOND_LEVEL_SUBBANDS_COUNT: usize = 3;
 
+/// Sizes of the subbands of the reduce-extrapolate DWT, in HL1, LH1, HH1, HL2, LH2, HH2, HL3, LH3, HH3 and LL3 order
+pub const REDUCE_EXTRAPOLATE_SUBBAND_SIZES: [usize; 10] = [1023, 1023, 961, 272, 272, 256, 72, 72, 64, 81];
+
 pub fn decode(buffer: &mut [i16], quant: &Quant) {
     let (first_level, buffer) = buffer.split_at_mut(FIRST_LEVEL_SUBBANDS_COUNT * FIRST_LEVEL_SIZE);
     let (second_level, third_level) = buffer.split_at_mut(SECOND_LEVEL_SUBBANDS_COUNT * SECOND_LEVEL_SIZE);
@@ -29,10 +32,59 @@ pub fn decode(buffer: &mut [i16], quant: &Quant) {
         .for_each(decode_chunk);
 }
 
+/// Same as `decode`, for the subbands of the reduce-extrapolate DWT used by the RemoteFX Progressive codec.
+pub fn decode_reduce_extrapolate(mut buffer: &mut [i16], quant: &Quant) {
+    let factors = [
+        quant.hl1, quant.lh1, quant.hh1, quant.hl2, quant.lh2, quant.hh2, quant.hl3, quant.lh3, quant.hh3, quant.ll3,
+    ];
+
+    for (size, factor) in REDUCE_EXTRAPOLATE_SUBBAND_SIZES.into_iter().zip(factors) {
+        let (subband, remaining) = buffer.split_at_mut(size);
+        decode_block(subband, i16::from(factor) - 1);
+        buffer = remaining;
+    }
+}
+
+/// Quantizes the coefficients of a forward DWT, the inverse of [`decode`].
+pub fn encode(buffer: &mut [i16], quant: &Quant) {
+    let (first_level, buffer) = buffer.split_at_mut(FIRST_LEVEL_SUBBANDS_COUNT * FIRST_LEVEL_SIZE);
+    let (second_level, third_level) = buffer.split_at_mut(SECOND_LEVEL_SUBBANDS_COUNT * SECOND_LEVEL_SIZE);
+
+    let encode_chunk = |a: (&mut [i16], u8)| encode_block(a.0, a.1 as i16 - 1);
+
+    first_level
+        .chunks_mut(FIRST_LEVEL_SIZE)
+        .zip([quant.hl1, quant.lh1, quant.hh1].iter().copied())
+        .for_each(encode_chunk);
+
+    second_level
+        .chunks_mut(SECOND_LEVEL_SIZE)
+        .zip([quant.hl2, quant.lh2, quant.hh2].iter().copied())
+        .for_each(encode_chunk);
+
+    third_level
+        .chunks_mut(THIRD_LEVEL_SIZE)
+        .zip([quant.hl3, quant.lh3, quant.hh3, quant.ll3].iter().copied())
+        .for_each(encode_chunk);
+}
+
 fn decode_block(buffer: &mut [i16], factor: i16) {
     if factor > 0 {
+        // The progressive codec adds its own quantization values, and may shift out all the bits
+        let factor = factor as u32;
+
+        for value in buffer {
+            *value = value.checked_shl(factor).unwrap_or(0);
+        }
+    }
+}
+
+fn encode_block(buffer: &mut [i16], factor: i16) {
+    if factor > 0 {
+        let half = 1 << (factor - 1);
+
         for value in buffer {
-            *value <<= factor;
+            *value = ((i32::from(*value) + half) >> factor) as i16;
         }
     }
 }
@@ -62,6 +114,46 @@ mod tests {
         assert_eq!(expected.as_ref(), buffer.as_ref());
     }
 
+    #[test]
+    fn decode_reduce_extrapolate_uses_the_extrapolated_subband_sizes() {
+        let mut buffer = [1; 4096];
+        let quant = Quant {
+            ll3: 10,
+            lh3: 9,
+            hl3: 8,
+            hh3: 7,
+            lh2: 6,
+            hl2: 5,
+            hh2: 4,
+            lh1: 3,
+            hl1: 2,
+            hh1: 17,
+        };
+
+        decode_reduce_extrapolate(&mut buffer, &quant);
+
+        let expected_subbands: [(usize, i16); 10] = [
+            (1023, 1 << 1),
+            (1023, 1 << 2),
+            (961, 0),
+            (272, 1 << 4),
+            (272, 1 << 5),
+            (256, 1 << 3),
+            (72, 1 << 7),
+            (72, 1 << 8),
+            (64, 1 << 6),
+            (81, 1 << 9),
+        ];
+
+        let mut subbands = buffer.as_ref();
+        for (size, value) in expected_subbands {
+            let (subband, remaining) = subbands.split_at(size);
+            assert!(subband.iter().all(|v| *v == value));
+            subbands = remaining;
+        }
+        assert!(subbands.is_empty());
+    }
+
     #[test]
     fn decode_works_with_not_empty_quant_values() {
         let mut buffer = QUANTIZED_BUFFER;
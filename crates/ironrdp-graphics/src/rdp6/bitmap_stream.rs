@@ -1,5 +1,6 @@
 use crate::{
     color_conversion::{Rgb, YCoCg},
+    image_processing::{ImageRegionMut, Rgba},
     rdp6::rle::{decompress_8bpp_plane, RleError},
 };
 use ironrdp_pdu::{
@@ -16,6 +17,8 @@ pub enum BitmapDecodeError {
     Rle(#[from] RleError),
     #[error("Color plane data size provided in PDU is not sufficient to reconstruct the bitmap")]
     InvalidUncompressedDataSize,
+    #[error("Destination region is not large enough to hold the decoded bitmap")]
+    InvalidDestinationRegion,
 }
 
 /// Implements decoding of RDP6 bitmap stream PDU (see [`BitmapStreamPdu`])
@@ -44,6 +47,14 @@ struct AYCoCgParams {
     alpha: bool,
 }
 
+/// Uncompressed planes of the bitmap
+struct Planes<'a> {
+    /// Alpha plane, absent when the bitmap has no alpha channel
+    alpha: Option<&'a [u8]>,
+    /// R/G/B or Y/Co/Cg planes, one after the other
+    color: &'a [u8],
+}
+
 impl<'a> BitmapStreamDecoderImpl<'a> {
     pub fn init(bitmap: BitmapStreamPdu<'a>, image_width: usize, image_height: usize) -> Self {
         let (chroma_width, chroma_height) = if bitmap.has_subsampled_chroma() {
@@ -80,21 +91,25 @@ impl<'a> BitmapStreamDecoderImpl<'a> {
         }
     }
 
-    fn decompress_planes(&'a self, aux_buffer: &'a mut Vec<u8>) -> Result<&'a [u8], BitmapDecodeError> {
+    fn decompress_planes(&'a self, aux_buffer: &'a mut Vec<u8>) -> Result<Planes<'a>, BitmapDecodeError> {
         let planes = if self.bitmap.enable_rle_compression {
+            // The alpha plane, if any, is stored after the color planes
+            let alpha_plane_size = if self.bitmap.use_alpha { self.full_plane_size } else { 0 };
+            let buffer_size = self.uncompressed_planes_size + alpha_plane_size;
+
             // We don't care for the previous content, just resize it to fit the data
-            aux_buffer.resize(self.uncompressed_planes_size, 0);
-            let uncompressed_planes_buffer = &mut aux_buffer[..self.uncompressed_planes_size];
+            aux_buffer.resize(buffer_size, 0);
+            let (uncompressed_planes_buffer, alpha_plane_buffer) =
+                aux_buffer[..buffer_size].split_at_mut(self.uncompressed_planes_size);
 
             let compressed = self.bitmap.color_panes_data();
             let mut src_offset = 0;
 
             // Decompress Alpha plane
             if self.bitmap.use_alpha {
-                // Decompress alpha alpha, but discard it (always 0xFF)
                 src_offset += decompress_8bpp_plane(
                     &compressed[src_offset..],
-                    uncompressed_planes_buffer,
+                    alpha_plane_buffer,
                     self.image_width,
                     self.image_height,
                 )?;
@@ -124,73 +139,102 @@ impl<'a> BitmapStreamDecoderImpl<'a> {
                 self.chroma_height,
             )?;
 
-            &uncompressed_planes_buffer[..self.uncompressed_planes_size]
+            Planes {
+                alpha: self.bitmap.use_alpha.then_some(&*alpha_plane_buffer),
+                color: uncompressed_planes_buffer,
+            }
         } else {
-            // Discard alpha plane
             let color_planes_offset = if self.bitmap.use_alpha { self.full_plane_size } else { 0 };
 
             let expected_data_size = color_planes_offset + self.uncompressed_planes_size;
 
-            if self.bitmap.color_panes_data().len() < expected_data_size {
+            let data = self.bitmap.color_panes_data();
+
+            if data.len() < expected_data_size {
                 return Err(BitmapDecodeError::InvalidUncompressedDataSize);
             }
 
-            &self.bitmap.color_panes_data()[color_planes_offset..]
+            Planes {
+                alpha: self.bitmap.use_alpha.then(|| &data[..self.full_plane_size]),
+                color: &data[color_planes_offset..],
+            }
         };
 
         Ok(planes)
     }
 
-    fn write_argb_planes_to_rgb24(&self, planes: &[u8], dst: &mut Vec<u8>) {
-        // For ARGB comversion is simple - just copy data in correct order
-        let (r_offset, g_offset, b_offset) = (
-            self.color_plane_offsets[0],
-            self.color_plane_offsets[1],
-            self.color_plane_offsets[2],
-        );
+    /// Calls `put_pixel` with the index and color of each pixel, from left to right and top to bottom.
+    ///
+    /// Pixels of bitmaps without alpha plane are opaque.
+    fn for_each_pixel(&self, planes: &Planes<'_>, mut put_pixel: impl FnMut(usize, Rgba)) {
+        let alpha = |idx: usize| planes.alpha.map_or(0xFF, |alpha_plane| alpha_plane[idx]);
 
-        let r_plane = &planes[r_offset..r_offset + self.full_plane_size];
-        let g_plane = &planes[g_offset..g_offset + self.full_plane_size];
-        let b_plane = &planes[b_offset..b_offset + self.full_plane_size];
-
-        for i in 0..self.full_plane_size {
-            let (r, g, b) = (r_plane[i], g_plane[i], b_plane[i]);
+        match self.bitmap.color_planes {
+            ColorPlanes::Argb { .. } => {
+                // For ARGB comversion is simple - just copy data in correct order
+                let (r_offset, g_offset, b_offset) = (
+                    self.color_plane_offsets[0],
+                    self.color_plane_offsets[1],
+                    self.color_plane_offsets[2],
+                );
+
+                let r_plane = &planes.color[r_offset..r_offset + self.full_plane_size];
+                let g_plane = &planes.color[g_offset..g_offset + self.full_plane_size];
+                let b_plane = &planes.color[b_offset..b_offset + self.full_plane_size];
+
+                for i in 0..self.full_plane_size {
+                    put_pixel(
+                        i,
+                        Rgba {
+                            r: r_plane[i],
+                            g: g_plane[i],
+                            b: b_plane[i],
+                            a: alpha(i),
+                        },
+                    );
+                }
+            }
+            ColorPlanes::AYCoCg {
+                color_loss_level,
+                use_chroma_subsampling,
+                ..
+            } => {
+                let params = AYCoCgParams {
+                    color_loss_level,
+                    chroma_subsampling: use_chroma_subsampling,
+                    alpha: self.bitmap.use_alpha,
+                };
 
-            dst.extend_from_slice(&[r, g, b]);
-        }
-    }
+                // For AYCoCg we need to take color loss level and subsampling into account
+                let chroma_shift = (params.color_loss_level - 1) as usize;
+                let sample_shift = params.chroma_subsampling as usize;
 
-    fn write_aycocg_planes_to_rgb24(&self, params: AYCoCgParams, planes: &[u8], dst: &mut Vec<u8>) {
-        // For AYCoCg we need to take color loss level and subsampling into account
-        let chroma_shift = (params.color_loss_level - 1) as usize;
-        let sample_shift = params.chroma_subsampling as usize;
+                let (y_offset, co_offset, cg_offset) = (
+                    self.color_plane_offsets[0],
+                    self.color_plane_offsets[1],
+                    self.color_plane_offsets[2],
+                );
 
-        let (y_offset, co_offset, cg_offset) = (
-            self.color_plane_offsets[0],
-            self.color_plane_offsets[1],
-            self.color_plane_offsets[2],
-        );
+                let y_plane = &planes.color[y_offset..y_offset + self.full_plane_size];
+                let co_plane = &planes.color[co_offset..co_offset + self.chroma_plane_size];
+                let cg_plane = &planes.color[cg_offset..cg_offset + self.chroma_plane_size];
 
-        let y_plane = &planes[y_offset..y_offset + self.full_plane_size];
-        let co_plane = &planes[co_offset..co_offset + self.chroma_plane_size];
-        let cg_plane = &planes[cg_offset..cg_offset + self.chroma_plane_size];
+                for (idx, y) in y_plane.iter().copied().enumerate() {
+                    let chroma_row = (idx / self.image_width) >> sample_shift;
+                    let chroma_col = (idx % self.image_width) >> sample_shift;
+                    let chroma_idx = chroma_row * self.chroma_width + chroma_col;
 
-        for (idx, y) in y_plane.iter().copied().enumerate() {
-            let chroma_row = (idx / self.image_width) >> sample_shift;
-            let chroma_col = (idx % self.image_width) >> sample_shift;
-            let chroma_idx = chroma_row * self.chroma_width + chroma_col;
+                    let co = (co_plane[chroma_idx] << chroma_shift) as i8;
+                    let cg = (cg_plane[chroma_idx] << chroma_shift) as i8;
 
-            let co = (co_plane[chroma_idx] << chroma_shift) as i8;
-            let cg = (cg_plane[chroma_idx] << chroma_shift) as i8;
+                    let Rgb { r, g, b } = YCoCg { y, co, cg }.into();
 
-            let Rgb { r, g, b } = YCoCg { y, co, cg }.into();
+                    // As described in 3.1.9.1.2 [MS-RDPEGDI], R and B channels are swapped for
+                    // AYCoCg when 24-bit image is used (no alpha). We swap them back here
+                    let (r, b) = if params.alpha { (r, b) } else { (b, r) };
 
-            // As described in 3.1.9.1.2 [MS-RDPEGDI], R and B channels are swapped for
-            // AYCoCg when 24-bit image is used (no alpha). We swap them back here
-            if params.alpha {
-                dst.extend_from_slice(&[r, g, b]);
-            } else {
-                dst.extend_from_slice(&[b, g, r]);
+                    put_pixel(idx, Rgba { r, g, b, a: alpha(idx) });
+                }
             }
         }
     }
@@ -199,28 +243,55 @@ impl<'a> BitmapStreamDecoderImpl<'a> {
         // Reserve enough space for decoded RGB channels data
         dst.reserve(self.image_height * self.image_width * 3);
 
-        match self.bitmap.color_planes {
-            ColorPlanes::Argb { .. } => {
-                let color_planes = self.decompress_planes(aux_buffer)?;
-                self.write_argb_planes_to_rgb24(color_planes, dst);
-            }
-            ColorPlanes::AYCoCg {
-                color_loss_level,
-                use_chroma_subsampling,
-                ..
-            } => {
-                let params = AYCoCgParams {
-                    color_loss_level,
-                    chroma_subsampling: use_chroma_subsampling,
-                    alpha: self.bitmap.use_alpha,
-                };
-                let color_planes = self.decompress_planes(aux_buffer)?;
-                self.write_aycocg_planes_to_rgb24(params, color_planes, dst);
-            }
-        }
+        let planes = self.decompress_planes(aux_buffer)?;
+
+        self.for_each_pixel(&planes, |_, Rgba { r, g, b, .. }| dst.extend_from_slice(&[r, g, b]));
 
         Ok(())
     }
+
+    fn decode_to_region(
+        self,
+        dst: &mut ImageRegionMut<'_>,
+        aux_buffer: &'a mut Vec<u8>,
+    ) -> Result<(), BitmapDecodeError> {
+        let bytes_per_pixel = usize::from(dst.pixel_format.bytes_per_pixel());
+        let step = if dst.step == 0 {
+            self.image_width * bytes_per_pixel
+        } else {
+            usize::from(dst.step)
+        };
+        let left = usize::from(dst.region.left);
+        let top = usize::from(dst.region.top);
+
+        let fits_in_region = usize::from(dst.region.width()) >= self.image_width
+            && usize::from(dst.region.height()) >= self.image_height;
+        let required_length = if self.full_plane_size == 0 {
+            0
+        } else {
+            (top + self.image_height - 1) * step + (left + self.image_width) * bytes_per_pixel
+        };
+
+        if !fits_in_region || dst.data.len() < required_length {
+            return Err(BitmapDecodeError::InvalidDestinationRegion);
+        }
+
+        let planes = self.decompress_planes(aux_buffer)?;
+
+        let pixel_format = dst.pixel_format;
+        let data = &mut *dst.data;
+        let mut result = Ok(());
+
+        self.for_each_pixel(&planes, |idx, color| {
+            let offset = (top + idx / self.image_width) * step + (left + idx % self.image_width) * bytes_per_pixel;
+
+            if result.is_ok() {
+                result = pixel_format.write_color(color, &mut data[offset..]);
+            }
+        });
+
+        result.map_err(|_| BitmapDecodeError::InvalidDestinationRegion)
+    }
 }
 
 impl BitmapStreamDecoder {
@@ -239,13 +310,37 @@ impl BitmapStreamDecoder {
 
         decoder.decode(dst, &mut self.planes_buffer)
     }
+
+    /// Performs decoding of bitmap stream PDU from `bitmap_data` and writes the decoded image,
+    /// including its alpha channel, at the top-left corner of the `dst` region.
+    ///
+    /// Bitmaps without alpha plane are decoded as opaque. The region must be at least as large
+    /// as the image, and any 32 bpp pixel format may be used (e.g.:
+    /// [`PixelFormat::BgrA32`](crate::image_processing::PixelFormat::BgrA32) for the ARGB surfaces
+    /// of the graphics pipeline).
+    pub fn decode_bitmap_stream_to_region(
+        &mut self,
+        bitmap_data: &[u8],
+        dst: &mut ImageRegionMut<'_>,
+        image_width: usize,
+        image_height: usize,
+    ) -> Result<(), BitmapDecodeError> {
+        let bitmap = decode::<BitmapStreamPdu>(bitmap_data)?;
+
+        let decoder = BitmapStreamDecoderImpl::init(bitmap, image_width, image_height);
+
+        decoder.decode_to_region(dst, &mut self.planes_buffer)
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use ironrdp_pdu::geometry::Rectangle;
+
     use super::*;
+    use crate::image_processing::PixelFormat;
 
-    fn assert_decoded_image(pdu: &[u8], expected_bmp: &[u8], width: usize, height: usize) {
+    fn expected_rgb24(expected_bmp: &[u8], width: usize, height: usize) -> Vec<u8> {
         let expected_bmp = bmp::from_reader(&mut std::io::Cursor::new(expected_bmp)).unwrap();
         let mut expected_buffer = vec![0; width * height * 3];
         for (idx, (x, y)) in expected_bmp.coordinates().enumerate() {
@@ -257,6 +352,12 @@ mod tests {
             expected_buffer[offset + 2] = pixel.b;
         }
 
+        expected_buffer
+    }
+
+    fn assert_decoded_image(pdu: &[u8], expected_bmp: &[u8], width: usize, height: usize) {
+        let expected_buffer = expected_rgb24(expected_bmp, width, height);
+
         let mut actual = Vec::new();
 
         BitmapStreamDecoder::default()
@@ -266,6 +367,86 @@ mod tests {
         assert_eq!(actual.as_slice(), expected_buffer.as_slice());
     }
 
+    /// Decodes the bitmap in a RGBA region one pixel away from the top-left corner of a larger image.
+    fn decode_to_rgba32_region(pdu: &[u8], width: usize, height: usize) -> Vec<u8> {
+        let step = (width + 1) * 4;
+        let mut data = vec![0; step * (height + 1)];
+
+        let mut region = ImageRegionMut {
+            region: Rectangle {
+                left: 1,
+                top: 1,
+                right: width as u16,
+                bottom: height as u16,
+            },
+            step: step as u16,
+            pixel_format: PixelFormat::RgbA32,
+            data: &mut data,
+        };
+
+        BitmapStreamDecoder::default()
+            .decode_bitmap_stream_to_region(pdu, &mut region, width, height)
+            .unwrap();
+
+        assert!(data[..step].iter().all(|value| *value == 0));
+
+        data.chunks_exact(step)
+            .skip(1)
+            .flat_map(|row| row[4..].iter().copied())
+            .collect()
+    }
+
+    #[test]
+    fn decode_64x24_argb_rle_to_region() {
+        let expected = expected_rgb24(include_bytes!("test_assets/64x24_argb_rle.bmp"), 64, 24);
+        let actual = decode_to_rgba32_region(include_bytes!("test_assets/64x24_argb_rle.bin"), 64, 24);
+
+        let actual_rgb = actual
+            .chunks_exact(4)
+            .flat_map(|pixel| pixel[..3].iter().copied())
+            .collect::<Vec<_>>();
+
+        assert_eq!(actual_rgb, expected);
+    }
+
+    #[test]
+    fn decode_64x57_ycocg_rle_ss_to_region_is_opaque() {
+        // No alpha plane: every pixel is opaque
+        let expected = expected_rgb24(include_bytes!("test_assets/64x57_ycocg_rle_ss.bmp"), 64, 57);
+        let actual = decode_to_rgba32_region(include_bytes!("test_assets/64x57_ycocg_rle_ss.bin"), 64, 57);
+
+        for (actual, expected) in actual.chunks_exact(4).zip(expected.chunks_exact(3)) {
+            assert_eq!(&actual[..3], expected);
+            assert_eq!(actual[3], 0xFF);
+        }
+    }
+
+    #[test]
+    fn decode_to_too_small_region_fails() {
+        let mut data = vec![0; 32 * 32 * 4];
+
+        let mut region = ImageRegionMut {
+            region: Rectangle {
+                left: 0,
+                top: 0,
+                right: 31,
+                bottom: 31,
+            },
+            step: 32 * 4,
+            pixel_format: PixelFormat::BgrA32,
+            data: &mut data,
+        };
+
+        let result = BitmapStreamDecoder::default().decode_bitmap_stream_to_region(
+            include_bytes!("test_assets/32x64_rgb_raw.bin"),
+            &mut region,
+            32,
+            64,
+        );
+
+        assert!(matches!(result, Err(BitmapDecodeError::InvalidDestinationRegion)));
+    }
+
     #[test]
     fn decode_32x64_rgb_raw() {
         // RGB (No alpha), no RLE
@@ -0,0 +1,244 @@
+//! RemoteFX encoder
+//!
+//! Encodes BGRA images in the messages of the RemoteFX codec, the forward path of the decoding done by the
+//! session: color conversion, DWT, quantization, subband differencing of LL3 and RLGR encoding of every 64x64 tile.
+
+use std::collections::BTreeSet;
+
+use ironrdp_pdu::codecs::rfx::{
+    Channel, ChannelsPdu, CodecVersionsPdu, ContextPdu, EntropyAlgorithm, FrameBeginPdu, FrameEndPdu, OperatingMode,
+    Quant, RegionPdu, RfxError, RfxRectangle, SyncPdu, Tile, TileSetPdu,
+};
+use ironrdp_pdu::PduBufferParsing;
+use thiserror::Error;
+
+use crate::rlgr::RlgrError;
+use crate::{color_conversion, dwt, quantization, rlgr, subband_reconstruction};
+
+const TILE_SIZE: usize = 64;
+const TILE_PIXELS: usize = TILE_SIZE * TILE_SIZE;
+
+/// Size of the buffer the components are encoded in, the tile data lengths being sent on 16 bits
+const MAX_COMPONENT_SIZE: usize = u16::MAX as usize;
+
+#[derive(Debug, Error)]
+pub enum RfxEncoderError {
+    #[error("failed to encode the RLGR data: {0}")]
+    Rlgr(#[from] RlgrError),
+    #[error("failed to encode the RemoteFX message: {0}")]
+    Rfx(#[from] RfxError),
+    #[error("invalid image size: {width}x{height} with a stride of {stride} bytes, for {data_length} bytes of data")]
+    InvalidImageSize {
+        width: u16,
+        height: u16,
+        stride: usize,
+        data_length: usize,
+    },
+}
+
+/// Image in the BGRA pixel format, `stride` bytes per row
+#[derive(Debug, Clone, Copy)]
+pub struct BgraImage<'a> {
+    pub data: &'a [u8],
+    pub width: u16,
+    pub height: u16,
+    pub stride: usize,
+}
+
+/// Encoder of the RemoteFX messages, using a single quantization table for all the components
+pub struct RfxEncoder {
+    entropy_algorithm: EntropyAlgorithm,
+    quant: Quant,
+    tile_pixels: Vec<u8>,
+    ycbcr_buffer: [Vec<i16>; 3],
+    temp_buffer: Vec<i16>,
+}
+
+impl Default for RfxEncoder {
+    /// RLGR3 entropy encoding, with the same quantization values for the three components
+    fn default() -> Self {
+        Self::new(
+            EntropyAlgorithm::Rlgr3,
+            Quant {
+                ll3: 6,
+                lh3: 6,
+                hl3: 6,
+                hh3: 6,
+                lh2: 7,
+                hl2: 7,
+                hh2: 8,
+                lh1: 8,
+                hl1: 8,
+                hh1: 9,
+            },
+        )
+    }
+}
+
+impl RfxEncoder {
+    pub fn new(entropy_algorithm: EntropyAlgorithm, quant: Quant) -> Self {
+        Self {
+            entropy_algorithm,
+            quant,
+            tile_pixels: vec![0; TILE_PIXELS * 4],
+            ycbcr_buffer: [vec![0; TILE_PIXELS], vec![0; TILE_PIXELS], vec![0; TILE_PIXELS]],
+            temp_buffer: vec![0; TILE_PIXELS],
+        }
+    }
+
+    /// Appends the header messages (Sync, CodecVersions, Channels and Context) to `output`.
+    ///
+    /// They are sent before the first frame, and before every frame in image mode.
+    pub fn encode_headers(
+        &self,
+        width: u16,
+        height: u16,
+        flags: OperatingMode,
+        output: &mut Vec<u8>,
+    ) -> Result<(), RfxEncoderError> {
+        write_pdu(&SyncPdu, output)?;
+        write_pdu(&CodecVersionsPdu, output)?;
+        write_pdu(
+            &ChannelsPdu(vec![Channel {
+                width: width as i16,
+                height: height as i16,
+            }]),
+            output,
+        )?;
+        write_pdu(
+            &ContextPdu {
+                flags,
+                entropy_algorithm: self.entropy_algorithm,
+            },
+            output,
+        )?;
+
+        Ok(())
+    }
+
+    /// Appends the data messages of a frame updating the `rectangles` of the image to `output`.
+    ///
+    /// All the tiles intersecting the rectangles are encoded, the pixels outside of the image being black.
+    pub fn encode_frame(
+        &mut self,
+        frame_index: u32,
+        image: BgraImage<'_>,
+        rectangles: &[RfxRectangle],
+        output: &mut Vec<u8>,
+    ) -> Result<(), RfxEncoderError> {
+        let width = usize::from(image.width);
+        let height = usize::from(image.height);
+
+        if image.stride < width * 4 || (height > 0 && image.data.len() < (height - 1) * image.stride + width * 4) {
+            return Err(RfxEncoderError::InvalidImageSize {
+                width: image.width,
+                height: image.height,
+                stride: image.stride,
+                data_length: image.data.len(),
+            });
+        }
+
+        let tile_positions = rectangles
+            .iter()
+            .filter(|r| r.width > 0 && r.height > 0)
+            .flat_map(|r| {
+                let right = (usize::from(r.x) + usize::from(r.width)).min(width);
+                let bottom = (usize::from(r.y) + usize::from(r.height)).min(height);
+
+                let columns = usize::from(r.x) / TILE_SIZE..right / TILE_SIZE + usize::from(right % TILE_SIZE != 0);
+                let rows = usize::from(r.y) / TILE_SIZE..bottom / TILE_SIZE + usize::from(bottom % TILE_SIZE != 0);
+
+                rows.flat_map(move |y| columns.clone().map(move |x| (y, x)))
+            })
+            .collect::<BTreeSet<_>>();
+
+        let mut tiles_data = Vec::with_capacity(tile_positions.len());
+        for &(y, x) in &tile_positions {
+            self.copy_tile_pixels(image, x * TILE_SIZE, y * TILE_SIZE);
+            tiles_data.push(self.encode_tile()?);
+        }
+
+        let tiles = tile_positions
+            .iter()
+            .zip(&tiles_data)
+            .map(|(&(y, x), [y_data, cb_data, cr_data])| Tile {
+                y_quant_index: 0,
+                cb_quant_index: 0,
+                cr_quant_index: 0,
+                x: x as u16,
+                y: y as u16,
+                y_data,
+                cb_data,
+                cr_data,
+            })
+            .collect();
+
+        write_pdu(
+            &FrameBeginPdu {
+                index: frame_index,
+                number_of_regions: 1,
+            },
+            output,
+        )?;
+        write_pdu(
+            &RegionPdu {
+                rectangles: rectangles.to_vec(),
+            },
+            output,
+        )?;
+        write_pdu(
+            &TileSetPdu {
+                entropy_algorithm: self.entropy_algorithm,
+                quants: vec![self.quant.clone()],
+                tiles,
+            },
+            output,
+        )?;
+        write_pdu(&FrameEndPdu, output)?;
+
+        Ok(())
+    }
+
+    fn copy_tile_pixels(&mut self, image: BgraImage<'_>, left: usize, top: usize) {
+        self.tile_pixels.fill(0);
+
+        let width = usize::from(image.width).saturating_sub(left).min(TILE_SIZE);
+        let height = usize::from(image.height).saturating_sub(top).min(TILE_SIZE);
+
+        for (row, tile_row) in self
+            .tile_pixels
+            .chunks_exact_mut(TILE_SIZE * 4)
+            .take(height)
+            .enumerate()
+        {
+            let start = (top + row) * image.stride + left * 4;
+            tile_row[..width * 4].copy_from_slice(&image.data[start..start + width * 4]);
+        }
+    }
+
+    fn encode_tile(&mut self) -> Result<[Vec<u8>; 3], RfxEncoderError> {
+        let [y, cb, cr] = &mut self.ycbcr_buffer;
+        color_conversion::bgra_to_ycbcr(&self.tile_pixels, y, cb, cr);
+
+        let mut encode_component = |component: &mut Vec<i16>| -> Result<Vec<u8>, RfxEncoderError> {
+            dwt::encode(component, &mut self.temp_buffer);
+            quantization::encode(component, &self.quant);
+            subband_reconstruction::encode(&mut component[4032..]);
+
+            let mut data = vec![0; MAX_COMPONENT_SIZE];
+            let length = rlgr::encode(self.entropy_algorithm, component, &mut data)?;
+            data.truncate(length);
+
+            Ok(data)
+        };
+
+        Ok([encode_component(y)?, encode_component(cb)?, encode_component(cr)?])
+    }
+}
+
+fn write_pdu<'a>(pdu: &impl PduBufferParsing<'a, Error = RfxError>, output: &mut Vec<u8>) -> Result<(), RfxError> {
+    let start = output.len();
+    output.resize(start + pdu.buffer_length(), 0);
+
+    pdu.to_buffer_consume(&mut &mut output[start..])
+}
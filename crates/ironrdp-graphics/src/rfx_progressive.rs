@@ -0,0 +1,381 @@
+//! RemoteFX Progressive codec ([MS-RDPEGFX] 2.2.4.2)
+//!
+//! A tile is first sent with coarsely quantized coefficients (first pass), which are then refined by any
+//! number of upgrade passes. Each upgrade pass sends the next bits of the coefficients, so the decoder has
+//! to keep the coefficients and their signs of every tile component between the passes.
+//!
+//! The upgrade passes use two bit streams:
+//!
+//! - the RAW stream, holding the new bits of the coefficients for which the sign is already known;
+//! - the SRL (Simplified Run-Length) stream, holding the new bits of the coefficients which were zero so far.
+
+use ironrdp_pdu::codecs::rfx::{EntropyAlgorithm, Quant};
+use thiserror::Error;
+
+use crate::quantization::REDUCE_EXTRAPOLATE_SUBBAND_SIZES;
+use crate::rlgr::{self, RlgrError};
+use crate::{dwt, quantization, subband_reconstruction};
+
+/// Number of coefficients of a 64x64 tile component
+pub const COEFFICIENTS_COUNT: usize = 4096;
+
+/// Sizes of the subbands of the classic DWT, in HL1, LH1, HH1, HL2, LH2, HH2, HL3, LH3, HH3 and LL3 order
+const CLASSIC_SUBBAND_SIZES: [usize; 10] = [1024, 1024, 1024, 256, 256, 256, 64, 64, 64, 64];
+
+const SRL_KP_MAX: u32 = 80;
+const SRL_UPDATE_KP_ZERO: u32 = 4;
+const SRL_UPDATE_KP_NON_ZERO: u32 = 6;
+
+#[derive(Debug, Error)]
+pub enum RfxProgressiveError {
+    #[error("failed to decode the RLGR data: {0}")]
+    Rlgr(#[from] RlgrError),
+    #[error("upgrade pass before the first pass")]
+    MissingFirstPass,
+    #[error("upgrade pass has a lower bit position than the previous pass")]
+    InvalidBitPosition,
+    #[error("buffers are not large enough to hold a tile component")]
+    InvalidBufferSize,
+}
+
+/// Progressive decoding state of a tile component (Y, Cb or Cr)
+#[derive(Debug, Clone)]
+pub struct ComponentState {
+    coefficients: Vec<i16>,
+    signs: Vec<i16>,
+    /// Number of bits which are not yet known for each subband, `None` before the first pass
+    bit_positions: Option<Quant>,
+}
+
+impl Default for ComponentState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ComponentState {
+    pub fn new() -> Self {
+        Self {
+            coefficients: vec![0; COEFFICIENTS_COUNT],
+            signs: vec![0; COEFFICIENTS_COUNT],
+            bit_positions: None,
+        }
+    }
+
+    /// Decodes the first pass of a tile component into `output`.
+    ///
+    /// `difference` tells if the coefficients are relative to the ones of the previous pass on this tile.
+    /// `extrapolate` tells if the reduce-extrapolate DWT is used instead of the classic one.
+    #[allow(clippy::too_many_arguments)]
+    pub fn decode_first_pass(
+        &mut self,
+        data: &[u8],
+        quant: &Quant,
+        quality_quant: &Quant,
+        difference: bool,
+        extrapolate: bool,
+        output: &mut [i16],
+        temp: &mut [i16],
+    ) -> Result<(), RfxProgressiveError> {
+        check_buffers(output, temp)?;
+        let output = &mut output[..COEFFICIENTS_COUNT];
+
+        match rlgr::decode(EntropyAlgorithm::Rlgr1, data, output) {
+            Ok(()) => (),
+            // all the coefficients of the component are zero
+            Err(RlgrError::EmptyTile) => output.fill(0),
+            Err(e) => return Err(e.into()),
+        }
+
+        self.signs.copy_from_slice(output);
+
+        let shift = add_quants(quant, quality_quant);
+
+        if extrapolate {
+            subband_reconstruction::decode(&mut output[COEFFICIENTS_COUNT - 81..]);
+            quantization::decode_reduce_extrapolate(output, &shift);
+        } else {
+            subband_reconstruction::decode(&mut output[COEFFICIENTS_COUNT - 64..]);
+            quantization::decode(output, &shift);
+        }
+
+        if difference {
+            for (value, previous) in output.iter_mut().zip(self.coefficients.iter()) {
+                *value = value.wrapping_add(*previous);
+            }
+        }
+
+        self.coefficients.copy_from_slice(output);
+        self.bit_positions = Some(shift);
+
+        inverse_dwt(output, temp, extrapolate);
+
+        Ok(())
+    }
+
+    /// Decodes an upgrade pass of a tile component into `output`.
+    ///
+    /// The quantization values are the ones of the previous passes, with the quality of this pass.
+    #[allow(clippy::too_many_arguments)]
+    pub fn decode_upgrade_pass(
+        &mut self,
+        srl_data: &[u8],
+        raw_data: &[u8],
+        quant: &Quant,
+        quality_quant: &Quant,
+        extrapolate: bool,
+        output: &mut [i16],
+        temp: &mut [i16],
+    ) -> Result<(), RfxProgressiveError> {
+        check_buffers(output, temp)?;
+        let output = &mut output[..COEFFICIENTS_COUNT];
+
+        let previous_bit_positions = self
+            .bit_positions
+            .as_ref()
+            .ok_or(RfxProgressiveError::MissingFirstPass)?;
+        let bit_positions = add_quants(quant, quality_quant);
+
+        let previous_bit_positions = quant_to_subbands(previous_bit_positions);
+        let new_bit_positions = quant_to_subbands(&bit_positions);
+
+        let subband_sizes = if extrapolate {
+            REDUCE_EXTRAPOLATE_SUBBAND_SIZES
+        } else {
+            CLASSIC_SUBBAND_SIZES
+        };
+
+        let mut srl = SrlDecoder::new(srl_data);
+        let mut raw = BitReader::new(raw_data);
+
+        let mut start = 0;
+        for (subband, size) in subband_sizes.into_iter().enumerate() {
+            let num_bits = previous_bit_positions[subband]
+                .checked_sub(new_bit_positions[subband])
+                .ok_or(RfxProgressiveError::InvalidBitPosition)?;
+            let shift = u32::from(new_bit_positions[subband].saturating_sub(1));
+
+            let coefficients = &mut self.coefficients[start..start + size];
+            let signs = &mut self.signs[start..start + size];
+            start += size;
+
+            if num_bits == 0 {
+                continue;
+            }
+
+            let num_bits = u32::from(num_bits);
+            // LL3 is the last subband, and its coefficients are always read from the RAW stream
+            let is_ll3 = subband == subband_sizes.len() - 1;
+
+            for (coefficient, sign) in coefficients.iter_mut().zip(signs.iter_mut()) {
+                let input = if is_ll3 || *sign > 0 {
+                    raw.read_bits(num_bits) as i32
+                } else if *sign < 0 {
+                    -(raw.read_bits(num_bits) as i32)
+                } else {
+                    let input = srl.read(num_bits);
+                    *sign = input;
+                    i32::from(input)
+                };
+
+                *coefficient = coefficient.wrapping_add(input.wrapping_shl(shift) as i16);
+            }
+        }
+
+        output.copy_from_slice(&self.coefficients);
+        self.bit_positions = Some(bit_positions);
+
+        inverse_dwt(output, temp, extrapolate);
+
+        Ok(())
+    }
+}
+
+fn check_buffers(output: &[i16], temp: &[i16]) -> Result<(), RfxProgressiveError> {
+    if output.len() < COEFFICIENTS_COUNT || temp.len() < COEFFICIENTS_COUNT {
+        Err(RfxProgressiveError::InvalidBufferSize)
+    } else {
+        Ok(())
+    }
+}
+
+fn inverse_dwt(buffer: &mut [i16], temp: &mut [i16], extrapolate: bool) {
+    if extrapolate {
+        dwt::decode_reduce_extrapolate(buffer, temp);
+    } else {
+        dwt::decode(buffer, temp);
+    }
+}
+
+fn add_quants(a: &Quant, b: &Quant) -> Quant {
+    Quant {
+        ll3: a.ll3 + b.ll3,
+        lh3: a.lh3 + b.lh3,
+        hl3: a.hl3 + b.hl3,
+        hh3: a.hh3 + b.hh3,
+        lh2: a.lh2 + b.lh2,
+        hl2: a.hl2 + b.hl2,
+        hh2: a.hh2 + b.hh2,
+        lh1: a.lh1 + b.lh1,
+        hl1: a.hl1 + b.hl1,
+        hh1: a.hh1 + b.hh1,
+    }
+}
+
+/// Returns the quantization values in the order of the subbands in the coefficients buffer.
+fn quant_to_subbands(quant: &Quant) -> [u8; 10] {
+    [
+        quant.hl1, quant.lh1, quant.hh1, quant.hl2, quant.lh2, quant.hh2, quant.hl3, quant.lh3, quant.hh3, quant.ll3,
+    ]
+}
+
+/// MSB-first bit reader, returning zeros past the end of the data
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    fn read_bit(&mut self) -> u32 {
+        let bit = self
+            .data
+            .get(self.position / 8)
+            .map(|byte| (byte >> (7 - self.position % 8)) & 1)
+            .unwrap_or(0);
+        self.position += 1;
+
+        u32::from(bit)
+    }
+
+    fn read_bits(&mut self, count: u32) -> u32 {
+        (0..count).fold(0, |value, _| (value << 1) | self.read_bit())
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum SrlMode {
+    Zero,
+    Unary,
+}
+
+/// Decoder of the SRL stream ([MS-RDPEGFX] 3.2.8.1.3.2)
+///
+/// Zero values are encoded as runs with an adaptive length, and other values as a sign bit followed by the
+/// magnitude in unary.
+struct SrlDecoder<'a> {
+    reader: BitReader<'a>,
+    kp: u32,
+    zeros: u32,
+    mode: SrlMode,
+}
+
+impl<'a> SrlDecoder<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            reader: BitReader::new(data),
+            kp: 8,
+            zeros: 0,
+            mode: SrlMode::Zero,
+        }
+    }
+
+    fn read(&mut self, num_bits: u32) -> i16 {
+        if self.zeros > 0 {
+            self.zeros -= 1;
+            return 0;
+        }
+
+        let k = self.kp / 8;
+
+        if self.mode == SrlMode::Zero {
+            if self.reader.read_bit() == 0 {
+                // a full run of 2^k zeros
+                self.zeros = (1 << k) - 1;
+                self.kp = (self.kp + SRL_UPDATE_KP_ZERO).min(SRL_KP_MAX);
+                return 0;
+            }
+
+            // a shorter run of zeros, followed by a non-zero value
+            self.mode = SrlMode::Unary;
+            self.zeros = self.reader.read_bits(k);
+
+            if self.zeros > 0 {
+                self.zeros -= 1;
+                return 0;
+            }
+        }
+
+        self.mode = SrlMode::Zero;
+
+        let is_negative = self.reader.read_bit() == 1;
+        self.kp = self.kp.saturating_sub(SRL_UPDATE_KP_NON_ZERO);
+
+        let magnitude = if num_bits == 1 {
+            1
+        } else {
+            let max = (1 << num_bits) - 1;
+            let mut magnitude = 1;
+            while magnitude < max && self.reader.read_bit() == 0 {
+                magnitude += 1;
+            }
+
+            magnitude
+        };
+
+        if is_negative {
+            -magnitude
+        } else {
+            magnitude
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bit_reader_reads_msb_first_and_pads_with_zeros() {
+        let mut reader = BitReader::new(&[0b1011_0001]);
+
+        assert_eq!(0b101, reader.read_bits(3));
+        assert_eq!(0b10001, reader.read_bits(5));
+        assert_eq!(0, reader.read_bits(4));
+    }
+
+    #[test]
+    fn srl_decodes_full_zero_runs() {
+        // kp = 8, so k = 1 and a '0' bit is a run of 2 zeros, then kp = 12 (k = 1 again)
+        let mut srl = SrlDecoder::new(&[0b0000_0000]);
+
+        for _ in 0..4 {
+            assert_eq!(0, srl.read(4));
+        }
+        assert_eq!(16, srl.kp);
+    }
+
+    #[test]
+    fn srl_decodes_values_after_short_zero_runs() {
+        // '1' bit, 1 zero (k = 1 bit), negative sign, magnitude 3 ("001")
+        // then '1' bit, no zero (k = 0 bit), positive sign, magnitude 1 ("1")
+        let mut srl = SrlDecoder::new(&[0b1110_0110, 0b1000_0000]);
+
+        assert_eq!(0, srl.read(4));
+        assert_eq!(-3, srl.read(4));
+        assert_eq!(1, srl.read(4));
+        assert_eq!(0, srl.kp);
+    }
+
+    #[test]
+    fn srl_magnitude_stops_at_the_maximum_value() {
+        // '1' bit, no zero, positive sign, then the magnitude reaches 2^2 - 1 without a '1' bit
+        let mut srl = SrlDecoder::new(&[0b1000_0000]);
+
+        assert_eq!(3, srl.read(2));
+        assert_eq!(5, srl.reader.position);
+    }
+}
@@ -116,6 +116,152 @@ pub fn decode(mode: EntropyAlgorithm, tile: &[u8], mut output: &mut [i16]) -> Re
     Ok(())
 }
 
+/// Encodes the coefficients with the given RLGR variant, and returns the number of bytes written to `output`.
+pub fn encode(mode: EntropyAlgorithm, mut input: &[i16], output: &mut [u8]) -> Result<usize, RlgrError> {
+    let mut k: u32 = 1;
+    let mut kp: u32 = k << LS_GR;
+    let mut krp: u32 = 1 << LS_GR;
+
+    let mut writer = BitWriter::new(output);
+
+    while !input.is_empty() {
+        match CompressionMode::from(k) {
+            CompressionMode::RunLength => {
+                let number_of_zeros = input.iter().take_while(|&&value| value == 0).count();
+                input = &input[number_of_zeros..];
+
+                let mut run = number_of_zeros as u32;
+                while run >= 1 << k {
+                    writer.write_bit(false)?;
+                    run -= 1 << k;
+                    kp = min(kp + UP_GR, KP_MAX);
+                    k = kp >> LS_GR;
+                }
+
+                // A run reaching the end of the input is terminated like the other ones, as if followed by a 1
+                let value = match input.split_first() {
+                    Some((&value, remaining)) => {
+                        input = remaining;
+                        value
+                    }
+                    None => 1,
+                };
+
+                writer.write_bit(true)?;
+                writer.write_bits(run, k)?;
+                writer.write_bit(value < 0)?;
+                write_gr_code(&mut writer, u32::from(value.unsigned_abs()) - 1, &mut krp)?;
+
+                kp = kp.saturating_sub(DN_GR);
+                k = kp >> LS_GR;
+            }
+            CompressionMode::GolombRice => match mode {
+                EntropyAlgorithm::Rlgr1 => {
+                    let code = two_magnitude_sign(input[0]);
+                    input = &input[1..];
+
+                    write_gr_code(&mut writer, code, &mut krp)?;
+
+                    if code == 0 {
+                        kp = min(kp + UQ_GR, KP_MAX);
+                    } else {
+                        kp = kp.saturating_sub(DQ_GR);
+                    }
+                    k = kp >> LS_GR;
+                }
+                EntropyAlgorithm::Rlgr3 => {
+                    let code1 = two_magnitude_sign(input[0]);
+                    let code2 = input.get(1).copied().map(two_magnitude_sign).unwrap_or(0);
+                    input = &input[min(2, input.len())..];
+
+                    let sum = code1 + code2;
+                    write_gr_code(&mut writer, sum, &mut krp)?;
+                    writer.write_bits(code1, compute_n_index(sum) as u32)?;
+
+                    if code1 != 0 && code2 != 0 {
+                        kp = kp.saturating_sub(2 * DQ_GR);
+                        k = kp >> LS_GR;
+                    } else if code1 == 0 && code2 == 0 {
+                        kp = min(kp + 2 * UQ_GR, KP_MAX);
+                        k = kp >> LS_GR;
+                    }
+                }
+            },
+        }
+    }
+
+    Ok(writer.bytes_written())
+}
+
+/// Writes the Golomb-Rice code of `value`: the quotient by 2^kr in unary, followed by the remainder on kr bits.
+fn write_gr_code(writer: &mut BitWriter<'_>, value: u32, krp: &mut u32) -> Result<(), RlgrError> {
+    let mut kr = *krp >> LS_GR;
+    let number_of_ones = value >> kr;
+
+    for _ in 0..number_of_ones {
+        writer.write_bit(true)?;
+    }
+    writer.write_bit(false)?;
+    writer.write_bits(value & ((1 << kr) - 1), kr)?;
+
+    update_parameters_according_to_number_of_ones(number_of_ones as usize, &mut kr, krp);
+
+    Ok(())
+}
+
+/// Maps the value to 2 * magnitude - sign, so that small values of both signs have small codes.
+fn two_magnitude_sign(value: i16) -> u32 {
+    if value < 0 {
+        u32::from(value.unsigned_abs()) * 2 - 1
+    } else {
+        u32::from(value.unsigned_abs()) * 2
+    }
+}
+
+struct BitWriter<'a> {
+    output: &'a mut [u8],
+    bit_position: usize,
+}
+
+impl<'a> BitWriter<'a> {
+    fn new(output: &'a mut [u8]) -> Self {
+        Self {
+            output,
+            bit_position: 0,
+        }
+    }
+
+    fn write_bit(&mut self, bit: bool) -> Result<(), RlgrError> {
+        let byte = self
+            .output
+            .get_mut(self.bit_position / 8)
+            .ok_or(RlgrError::OutputTooSmall)?;
+
+        let shift = 7 - self.bit_position % 8;
+        if shift == 7 {
+            *byte = 0;
+        }
+        *byte |= u8::from(bit) << shift;
+
+        self.bit_position += 1;
+
+        Ok(())
+    }
+
+    /// Writes the `count` least significant bits of `value`, most significant first.
+    fn write_bits(&mut self, value: u32, count: u32) -> Result<(), RlgrError> {
+        for i in (0..count).rev() {
+            self.write_bit((value >> i) & 1 != 0)?;
+        }
+
+        Ok(())
+    }
+
+    fn bytes_written(&self) -> usize {
+        self.bit_position / 8 + usize::from(self.bit_position & 7 != 0)
+    }
+}
+
 fn fill(buffer: &mut [i16], value: i16) {
     for v in buffer {
         *v = value;
@@ -231,4 +377,6 @@ pub enum RlgrError {
     IoError(#[from] io::Error),
     #[error("The input tile is empty")]
     EmptyTile,
+    #[error("The output buffer is too small")]
+    OutputTooSmall,
 }
@@ -4,6 +4,13 @@ pub fn decode(buffer: &mut [i16]) {
     }
 }
 
+/// Replaces the coefficients with their differences to the previous ones, the inverse of [`decode`].
+pub fn encode(buffer: &mut [i16]) {
+    for i in (1..buffer.len()).rev() {
+        buffer[i] = buffer[i].overflowing_sub(buffer[i - 1]).0;
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
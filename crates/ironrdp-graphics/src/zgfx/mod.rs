@@ -56,6 +56,15 @@ impl Decompressor {
         }
     }
 
+    /// Decompresses a single RDP8_BULK_ENCODED_DATA structure, which is not wrapped in an RDP_SEGMENTED_DATA structure.
+    ///
+    /// This is how the compressed data PDUs of the dynamic virtual channels are encoded (RDP 8.0 Lite).
+    pub fn decompress_bulk_encoded_data(&mut self, input: &[u8], output: &mut Vec<u8>) -> Result<usize, ZgfxError> {
+        let segment = BulkEncodedData::from_buffer(input)?;
+
+        self.handle_segment(&segment, output)
+    }
+
     fn handle_segment(&mut self, segment: &BulkEncodedData<'_>, output: &mut Vec<u8>) -> Result<usize, ZgfxError> {
         if !segment.data.is_empty() {
             if segment.compression_flags.contains(CompressionFlags::COMPRESSED) {
@@ -483,6 +492,20 @@ mod tests {
         }
     }
 
+    #[test]
+    fn zgfx_decompresses_bulk_encoded_data() {
+        let mut zgfx = Decompressor::new();
+        let mut decompressed = Vec::with_capacity(DECODED_ZGFX_SINGLE[0].len());
+
+        // Skip the RDP_SEGMENTED_DATA descriptor of the single segment
+        let bytes_written = zgfx
+            .decompress_bulk_encoded_data(&ENCODED_ZGFX_SINGLE[0][1..], &mut decompressed)
+            .unwrap();
+
+        assert_eq!(DECODED_ZGFX_SINGLE[0].len(), bytes_written);
+        assert_eq!(decompressed, DECODED_ZGFX_SINGLE[0]);
+    }
+
     #[test]
     fn zgfx_decopresses_only_one_literal() {
         let buffer = [0b1100_1000, 0x03];
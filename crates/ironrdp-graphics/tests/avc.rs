@@ -0,0 +1,239 @@
+use std::collections::VecDeque;
+
+use ironrdp_graphics::avc::*;
+use ironrdp_graphics::h264::{H264Decoder, H264Error, YuvFrame};
+use ironrdp_pdu::dvc::gfx::{Avc420BitmapStream, Avc444BitmapStream, Encoding};
+use ironrdp_pdu::geometry::Rectangle;
+
+const FRAME_SIZE: usize = 16;
+
+/// Returns the queued pictures, one for each bitstream.
+struct QueuedFrames(VecDeque<YuvFrame>);
+
+impl H264Decoder for QueuedFrames {
+    fn decode(&mut self, _bitstream: &[u8]) -> Result<Option<YuvFrame>, H264Error> {
+        Ok(self.0.pop_front())
+    }
+}
+
+fn decoder(frames: Vec<YuvFrame>, width: usize, height: usize) -> AvcDecoder {
+    AvcDecoder::new(Box::new(QueuedFrames(frames.into())), width, height)
+}
+
+/// Builds a 16x16 picture, the sample functions being called with the coordinates in their plane.
+fn frame(y: impl Fn(usize, usize) -> u8, u: impl Fn(usize, usize) -> u8, v: impl Fn(usize, usize) -> u8) -> YuvFrame {
+    let plane = |size: usize, sample: &dyn Fn(usize, usize) -> u8| {
+        (0..size)
+            .flat_map(|row| (0..size).map(move |column| (column, row)))
+            .map(|(column, row)| sample(column, row))
+            .collect()
+    };
+
+    YuvFrame {
+        width: FRAME_SIZE,
+        height: FRAME_SIZE,
+        y: plane(FRAME_SIZE, &y),
+        u: plane(FRAME_SIZE / 2, &u),
+        v: plane(FRAME_SIZE / 2, &v),
+        y_stride: FRAME_SIZE,
+        uv_stride: FRAME_SIZE / 2,
+    }
+}
+
+fn stream(rectangles: Vec<Rectangle>) -> Avc420BitmapStream<'static> {
+    Avc420BitmapStream {
+        quant_qual_vals: Vec::new(),
+        rectangles,
+        data: &[],
+    }
+}
+
+fn rectangle(left: u16, top: u16, right: u16, bottom: u16) -> Rectangle {
+    Rectangle {
+        left,
+        top,
+        right,
+        bottom,
+    }
+}
+
+/// Same conversion as the decoder, to check where the chroma samples ended up.
+fn bgra(y: u8, u: u8, v: u8) -> [u8; 4] {
+    let y = i32::from(y) * 256;
+    let d = i32::from(u) - 128;
+    let e = i32::from(v) - 128;
+
+    [
+        ((y + 475 * d) >> 8).clamp(0, 255) as u8,
+        ((y - 48 * d - 120 * e) >> 8).clamp(0, 255) as u8,
+        ((y + 403 * e) >> 8).clamp(0, 255) as u8,
+        0xFF,
+    ]
+}
+
+fn pixel(output: &[u8], width: usize, x: usize, y: usize) -> &[u8] {
+    let start = (y * width + x) * 4;
+    &output[start..start + 4]
+}
+
+#[test]
+fn avc420_converts_the_metablock_rectangles() {
+    let mut decoder = decoder(vec![frame(|_, _| 81, |_, _| 90, |_, _| 240)], 4, 4);
+    let mut output = vec![0; 4 * 4 * 4];
+
+    let updated = decoder
+        .decode_avc420(
+            &stream(vec![rectangle(1, 1, 3, 3), rectangle(3, 0, 20, 1)]),
+            &mut output,
+        )
+        .unwrap();
+
+    assert_eq!(updated, vec![rectangle(1, 1, 3, 3), rectangle(3, 0, 4, 1)]);
+    assert_eq!(pixel(&output, 4, 1, 1), [10, 35, 255, 255]);
+    assert_eq!(pixel(&output, 4, 2, 2), [10, 35, 255, 255]);
+    assert_eq!(pixel(&output, 4, 3, 0), [10, 35, 255, 255]);
+    assert_eq!(pixel(&output, 4, 0, 0), [0, 0, 0, 0]);
+    assert_eq!(pixel(&output, 4, 3, 3), [0, 0, 0, 0]);
+}
+
+#[test]
+fn avc420_rejects_pictures_smaller_than_the_surface() {
+    let mut decoder = decoder(vec![frame(|_, _| 0, |_, _| 0, |_, _| 0)], 32, 4);
+    let mut output = vec![0; 32 * 4 * 4];
+
+    let result = decoder.decode_avc420(&stream(vec![rectangle(0, 0, 1, 1)]), &mut output);
+
+    assert!(matches!(result, Err(AvcError::InvalidFrameSize { .. })));
+}
+
+#[test]
+fn avc444_combines_the_views_sent_in_separate_bitmaps() {
+    let main_view = frame(|x, y| ((x + y * 2) * 10) as u8, |_, _| 128, |_, _| 128);
+    // The first 8 rows of the luma are the odd rows of U, the next 8 ones the odd rows of V
+    let auxiliary_view = frame(|_, y| if y < 8 { 132 } else { 124 }, |_, _| 130, |_, _| 126);
+
+    let mut decoder = decoder(vec![main_view, auxiliary_view], 2, 2);
+    let mut output = vec![0; 2 * 2 * 4];
+
+    let luma = Avc444BitmapStream {
+        encoding: Encoding::LUMA,
+        stream1: stream(vec![rectangle(0, 0, 2, 2)]),
+        stream2: None,
+    };
+    let updated = decoder.decode_avc444(&luma, Avc444Version::V1, &mut output).unwrap();
+
+    assert_eq!(updated, vec![rectangle(0, 0, 2, 2)]);
+    assert_eq!(pixel(&output, 2, 1, 1), bgra(30, 128, 128));
+
+    let chroma = Avc444BitmapStream {
+        encoding: Encoding::CHROMA,
+        stream1: stream(vec![rectangle(0, 0, 2, 2)]),
+        stream2: None,
+    };
+    decoder.decode_avc444(&chroma, Avc444Version::V1, &mut output).unwrap();
+
+    // The even samples are too close to the filtered values to be replaced
+    assert_eq!(pixel(&output, 2, 0, 0), bgra(0, 128, 128));
+    assert_eq!(pixel(&output, 2, 1, 0), bgra(10, 130, 126));
+    assert_eq!(pixel(&output, 2, 0, 1), bgra(20, 132, 124));
+    assert_eq!(pixel(&output, 2, 1, 1), bgra(30, 132, 124));
+}
+
+#[test]
+fn avc444v2_combines_the_views_of_a_bitmap() {
+    let main_view = frame(|_, _| 100, |_, _| 128, |_, _| 128);
+    // The halves of the luma are the odd columns of U and V, the quarters of the chroma the even columns of the odd
+    // rows of U and V
+    let auxiliary_view = frame(
+        |x, _| [130, 131, 124, 125].get(x).copied().unwrap_or(0),
+        |x, _| [132, 126].get(x).copied().unwrap_or(0),
+        |x, _| [133, 127].get(x).copied().unwrap_or(0),
+    );
+
+    let mut decoder = decoder(vec![main_view, auxiliary_view], 4, 2);
+    let mut output = vec![0; 4 * 2 * 4];
+
+    let bitmap = Avc444BitmapStream {
+        encoding: Encoding::LUMA_AND_CHROMA,
+        stream1: stream(vec![rectangle(0, 0, 4, 2)]),
+        stream2: Some(stream(vec![rectangle(0, 0, 4, 2)])),
+    };
+    let updated = decoder.decode_avc444(&bitmap, Avc444Version::V2, &mut output).unwrap();
+
+    assert_eq!(updated, vec![rectangle(0, 0, 4, 2), rectangle(0, 0, 4, 2)]);
+
+    let expected_chroma = [
+        [(128, 128), (130, 124), (128, 128), (131, 125)],
+        [(132, 126), (130, 124), (133, 127), (131, 125)],
+    ];
+
+    for (y, row) in expected_chroma.iter().enumerate() {
+        for (x, &(u, v)) in row.iter().enumerate() {
+            assert_eq!(pixel(&output, 4, x, y), bgra(100, u, v), "pixel at ({x}, {y})");
+        }
+    }
+}
+
+#[test]
+fn avc444_chroma_filter_restores_sharp_chroma() {
+    let main_view = frame(|_, _| 100, |_, _| 128, |_, _| 128);
+    // The auxiliary view sends 0 for the three other samples of the 2x2 block, so the top left U sample is
+    // reconstructed from the average sent in the main view
+    let auxiliary_view = frame(|_, y| if y < 8 { 0 } else { 128 }, |_, _| 0, |_, _| 128);
+
+    let mut decoder = decoder(vec![main_view, auxiliary_view], 2, 2);
+    let mut output = vec![0; 2 * 2 * 4];
+
+    let bitmap = Avc444BitmapStream {
+        encoding: Encoding::LUMA_AND_CHROMA,
+        stream1: stream(vec![rectangle(0, 0, 2, 2)]),
+        stream2: Some(stream(vec![rectangle(0, 0, 2, 2)])),
+    };
+    decoder.decode_avc444(&bitmap, Avc444Version::V1, &mut output).unwrap();
+
+    assert_eq!(pixel(&output, 2, 0, 0), bgra(100, 255, 128));
+    assert_eq!(pixel(&output, 2, 1, 0), bgra(100, 0, 128));
+}
+
+#[test]
+fn avc444_rejects_unknown_encodings() {
+    let mut decoder = decoder(Vec::new(), 2, 2);
+    let mut output = vec![0; 2 * 2 * 4];
+
+    let bitmap = Avc444BitmapStream {
+        encoding: Encoding::LUMA | Encoding::CHROMA,
+        stream1: stream(Vec::new()),
+        stream2: None,
+    };
+    let result = decoder.decode_avc444(&bitmap, Avc444Version::V1, &mut output);
+
+    assert!(matches!(result, Err(AvcError::InvalidEncoding(0x03))));
+}
+
+#[test]
+fn avc444_handles_regions_ending_at_the_largest_coordinate() {
+    const WIDTH: usize = 65535;
+
+    let wide_frame = || YuvFrame {
+        width: WIDTH + 1,
+        height: 2,
+        y: vec![100; (WIDTH + 1) * 2],
+        u: vec![128; (WIDTH + 1) / 2],
+        v: vec![128; (WIDTH + 1) / 2],
+        y_stride: WIDTH + 1,
+        uv_stride: (WIDTH + 1) / 2,
+    };
+
+    let mut decoder = decoder(vec![wide_frame()], WIDTH, 2);
+    let mut output = vec![0; WIDTH * 2 * 4];
+
+    let luma = Avc444BitmapStream {
+        encoding: Encoding::LUMA,
+        stream1: stream(vec![rectangle(65533, 0, 65535, 2)]),
+        stream2: None,
+    };
+    let updated = decoder.decode_avc444(&luma, Avc444Version::V1, &mut output).unwrap();
+
+    assert_eq!(updated, vec![rectangle(65533, 0, 65535, 2)]);
+    assert_eq!(pixel(&output, WIDTH, 65534, 1), bgra(100, 128, 128));
+}
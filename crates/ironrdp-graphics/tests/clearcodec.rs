@@ -0,0 +1,331 @@
+use ironrdp_graphics::clearcodec::*;
+use ironrdp_graphics::image_processing::{ImageRegionMut, PixelFormat};
+use ironrdp_pdu::geometry::Rectangle;
+
+const BACKGROUND: [u8; 4] = [0x11, 0x22, 0x33, 0xFF];
+
+/// Decodes a bitmap into a BGRA image of the same size, initially filled with `BACKGROUND`.
+fn decode_bitmap(
+    decoder: &mut ClearCodecDecoder,
+    src: &[u8],
+    width: u16,
+    height: u16,
+) -> Result<Vec<u8>, ClearCodecError> {
+    decode_bitmap_into(decoder, src, width, height, width, height)
+}
+
+fn decode_bitmap_into(
+    decoder: &mut ClearCodecDecoder,
+    src: &[u8],
+    width: u16,
+    height: u16,
+    image_width: u16,
+    image_height: u16,
+) -> Result<Vec<u8>, ClearCodecError> {
+    let mut data = BACKGROUND.repeat(usize::from(image_width) * usize::from(image_height));
+
+    let mut dst = ImageRegionMut {
+        region: Rectangle {
+            left: 0,
+            top: 0,
+            right: image_width - 1,
+            bottom: image_height - 1,
+        },
+        step: image_width * 4,
+        pixel_format: PixelFormat::BgrA32,
+        data: &mut data,
+    };
+
+    decoder.decode(src, usize::from(width), usize::from(height), &mut dst)?;
+
+    Ok(data)
+}
+
+/// Builds a bitmap stream without glyph flags from its three layers.
+fn composite(residual: &[u8], bands: &[u8], subcodecs: &[u8]) -> Vec<u8> {
+    let mut stream = vec![0x00, 0x00];
+    stream.extend_from_slice(&(residual.len() as u32).to_le_bytes());
+    stream.extend_from_slice(&(bands.len() as u32).to_le_bytes());
+    stream.extend_from_slice(&(subcodecs.len() as u32).to_le_bytes());
+    stream.extend_from_slice(residual);
+    stream.extend_from_slice(bands);
+    stream.extend_from_slice(subcodecs);
+    stream
+}
+
+fn subcodec(x: u16, y: u16, width: u16, height: u16, id: u8, data: &[u8]) -> Vec<u8> {
+    let mut subcodec = Vec::new();
+    for value in [x, y, width, height] {
+        subcodec.extend_from_slice(&value.to_le_bytes());
+    }
+    subcodec.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    subcodec.push(id);
+    subcodec.extend_from_slice(data);
+    subcodec
+}
+
+#[test]
+fn decode_empty_bitmap_keeps_destination() {
+    let mut decoder = ClearCodecDecoder::new();
+
+    let output = decode_bitmap(&mut decoder, &[0x00, 0x00], 2, 2).unwrap();
+
+    assert_eq!(output, BACKGROUND.repeat(4));
+}
+
+#[test]
+fn decode_residual_layer() {
+    let mut decoder = ClearCodecDecoder::new();
+
+    #[rustfmt::skip]
+    let residual = [
+        0x01, 0x02, 0x03, 0x01, // one blue-ish pixel
+        0x04, 0x05, 0x06, 0x03, // three other pixels
+    ];
+    let output = decode_bitmap(&mut decoder, &composite(&residual, &[], &[]), 2, 2).unwrap();
+
+    #[rustfmt::skip]
+    let expected = [
+        0x01, 0x02, 0x03, 0xFF, 0x04, 0x05, 0x06, 0xFF,
+        0x04, 0x05, 0x06, 0xFF, 0x04, 0x05, 0x06, 0xFF,
+    ];
+    assert_eq!(output, expected);
+}
+
+#[test]
+fn decode_residual_layer_with_long_run() {
+    let mut decoder = ClearCodecDecoder::new();
+
+    let residual = [0x01, 0x02, 0x03, 0xFF, 0x2C, 0x01];
+    let output = decode_bitmap(&mut decoder, &composite(&residual, &[], &[]), 20, 15).unwrap();
+
+    assert_eq!(output, [0x01, 0x02, 0x03, 0xFF].repeat(300));
+}
+
+#[test]
+fn decode_residual_layer_not_matching_bitmap_size_fails() {
+    let mut decoder = ClearCodecDecoder::new();
+
+    let residual = [0x01, 0x02, 0x03, 0x03];
+    let result = decode_bitmap(&mut decoder, &composite(&residual, &[], &[]), 2, 2);
+
+    assert!(matches!(result, Err(ClearCodecError::InvalidResidualLayer)));
+}
+
+#[test]
+fn decode_bands_layer_with_vbar_caches() {
+    let mut decoder = ClearCodecDecoder::new();
+
+    #[rustfmt::skip]
+    let bands = [
+        0x00, 0x00, 0x01, 0x00, // xStart = 0, xEnd = 1
+        0x00, 0x00, 0x02, 0x00, // yStart = 0, yEnd = 2
+        0x09, 0x09, 0x09, // background
+        // Short vertical bar cache miss, from y = 1 to y = 2
+        0x01, 0x02, 0x01, 0x02, 0x03,
+        // Short vertical bar cache hit, index 0 at y = 0
+        0x00, 0x40, 0x00,
+    ];
+    let output = decode_bitmap(&mut decoder, &composite(&[], &bands, &[]), 2, 3).unwrap();
+
+    #[rustfmt::skip]
+    let expected = [
+        0x09, 0x09, 0x09, 0xFF, 0x01, 0x02, 0x03, 0xFF,
+        0x01, 0x02, 0x03, 0xFF, 0x09, 0x09, 0x09, 0xFF,
+        0x09, 0x09, 0x09, 0xFF, 0x09, 0x09, 0x09, 0xFF,
+    ];
+    assert_eq!(output, expected);
+
+    #[rustfmt::skip]
+    let bands = [
+        0x00, 0x00, 0x00, 0x00, // xStart = 0, xEnd = 0
+        0x00, 0x00, 0x02, 0x00, // yStart = 0, yEnd = 2
+        0x07, 0x07, 0x07, // background
+        // Vertical bar cache hit, index 1 (second column of the previous bitmap)
+        0x01, 0x80,
+    ];
+    let output = decode_bitmap(&mut decoder, &composite(&[], &bands, &[]), 1, 3).unwrap();
+
+    #[rustfmt::skip]
+    let expected = [
+        0x01, 0x02, 0x03, 0xFF,
+        0x09, 0x09, 0x09, 0xFF,
+        0x09, 0x09, 0x09, 0xFF,
+    ];
+    assert_eq!(output, expected);
+}
+
+#[test]
+fn decode_band_too_high_fails() {
+    let mut decoder = ClearCodecDecoder::new();
+
+    #[rustfmt::skip]
+    let bands = [
+        0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x40, 0x00, // yEnd = 64
+        0x00, 0x00, 0x00,
+        0x00, 0x00,
+    ];
+    let result = decode_bitmap(&mut decoder, &composite(&[], &bands, &[]), 1, 65);
+
+    assert!(matches!(result, Err(ClearCodecError::InvalidBand(_))));
+}
+
+#[test]
+fn decode_uncompressed_subcodec() {
+    let mut decoder = ClearCodecDecoder::new();
+
+    let subcodecs = subcodec(1, 0, 1, 2, 0, &[0x01, 0x02, 0x03, 0x04, 0x05, 0x06]);
+    let output = decode_bitmap(&mut decoder, &composite(&[], &[], &subcodecs), 2, 2).unwrap();
+
+    #[rustfmt::skip]
+    let expected = [
+        0x11, 0x22, 0x33, 0xFF, 0x01, 0x02, 0x03, 0xFF,
+        0x11, 0x22, 0x33, 0xFF, 0x04, 0x05, 0x06, 0xFF,
+    ];
+    assert_eq!(output, expected);
+}
+
+#[test]
+fn decode_rlex_subcodec() {
+    let mut decoder = ClearCodecDecoder::new();
+
+    #[rustfmt::skip]
+    let rlex = [
+        0x02, // two palette entries
+        0x0A, 0x0B, 0x0C,
+        0x0D, 0x0E, 0x0F,
+        // Stop index 1, suite depth 1, preceded by a run of two pixels of the first color
+        0x03, 0x02,
+    ];
+    let subcodecs = subcodec(0, 0, 4, 1, 2, &rlex);
+    let output = decode_bitmap(&mut decoder, &composite(&[], &[], &subcodecs), 4, 1).unwrap();
+
+    #[rustfmt::skip]
+    let expected = [
+        0x0A, 0x0B, 0x0C, 0xFF, 0x0A, 0x0B, 0x0C, 0xFF,
+        0x0A, 0x0B, 0x0C, 0xFF, 0x0D, 0x0E, 0x0F, 0xFF,
+    ];
+    assert_eq!(output, expected);
+}
+
+#[test]
+fn decode_rlex_subcodec_with_invalid_palette_index_fails() {
+    let mut decoder = ClearCodecDecoder::new();
+
+    #[rustfmt::skip]
+    let rlex = [
+        0x03,
+        0x0A, 0x0B, 0x0C,
+        0x0D, 0x0E, 0x0F,
+        0x10, 0x11, 0x12,
+        // Stop index 3 is out of the palette
+        0x03, 0x00,
+    ];
+    let subcodecs = subcodec(0, 0, 1, 1, 2, &rlex);
+    let result = decode_bitmap(&mut decoder, &composite(&[], &[], &subcodecs), 1, 1);
+
+    assert!(matches!(result, Err(ClearCodecError::InvalidRlex(_))));
+}
+
+#[test]
+fn decode_nscodec_subcodec() {
+    let mut decoder = ClearCodecDecoder::new();
+
+    #[rustfmt::skip]
+    let nscodec = [
+        0x02, 0x00, 0x00, 0x00, // luma plane
+        0x02, 0x00, 0x00, 0x00, // orange chroma plane
+        0x02, 0x00, 0x00, 0x00, // green chroma plane
+        0x00, 0x00, 0x00, 0x00, // no alpha plane
+        0x01, 0x00, 0x00, 0x00, // color loss level 1, no chroma subsampling
+        0x64, 0xC8, // luma
+        0x00, 0x10, // orange chroma
+        0x00, 0x00, // green chroma
+    ];
+    let subcodecs = subcodec(0, 0, 2, 1, 1, &nscodec);
+    let output = decode_bitmap(&mut decoder, &composite(&[], &[], &subcodecs), 2, 1).unwrap();
+
+    assert_eq!(output, [0x64, 0x64, 0x64, 0xFF, 0xB8, 0xC8, 0xD8, 0xFF]);
+}
+
+#[test]
+fn decode_subcodec_out_of_bitmap_fails() {
+    let mut decoder = ClearCodecDecoder::new();
+
+    let subcodecs = subcodec(1, 0, 2, 1, 0, &[0x00; 6]);
+    let result = decode_bitmap(&mut decoder, &composite(&[], &[], &subcodecs), 2, 1);
+
+    assert!(matches!(result, Err(ClearCodecError::InvalidSubcodecRectangle)));
+}
+
+#[test]
+fn decode_layers_in_order() {
+    let mut decoder = ClearCodecDecoder::new();
+
+    let residual = [0x01, 0x01, 0x01, 0x03];
+    #[rustfmt::skip]
+    let bands = [
+        0x01, 0x00, 0x01, 0x00,
+        0x00, 0x00, 0x00, 0x00,
+        0x02, 0x02, 0x02,
+        0x00, 0x00, // short vertical bar without pixels, leaving the background
+    ];
+    let subcodecs = subcodec(2, 0, 1, 1, 0, &[0x03, 0x03, 0x03]);
+    let output = decode_bitmap(&mut decoder, &composite(&residual, &bands, &subcodecs), 3, 1).unwrap();
+
+    assert_eq!(
+        output,
+        [0x01, 0x01, 0x01, 0xFF, 0x02, 0x02, 0x02, 0xFF, 0x03, 0x03, 0x03, 0xFF]
+    );
+}
+
+#[test]
+fn decode_glyph_from_cache() {
+    let mut decoder = ClearCodecDecoder::new();
+
+    let mut stream = composite(&[0x01, 0x02, 0x03, 0x02], &[], &[]);
+    stream.splice(0..2, [0x01, 0x00, 0x05, 0x00]); // glyph index 5
+    let output = decode_bitmap(&mut decoder, &stream, 2, 1).unwrap();
+
+    assert_eq!(output, [0x01, 0x02, 0x03, 0xFF].repeat(2));
+
+    let output = decode_bitmap(&mut decoder, &[0x03, 0x01, 0x05, 0x00], 2, 1).unwrap();
+
+    assert_eq!(output, [0x01, 0x02, 0x03, 0xFF].repeat(2));
+}
+
+#[test]
+fn decode_missing_glyph_fails() {
+    let mut decoder = ClearCodecDecoder::new();
+
+    let result = decode_bitmap(&mut decoder, &[0x03, 0x00, 0x07, 0x00], 2, 1);
+    assert!(matches!(result, Err(ClearCodecError::InvalidGlyph(7))));
+
+    let result = decode_bitmap(&mut decoder, &[0x02, 0x00], 2, 1);
+    assert!(matches!(result, Err(ClearCodecError::GlyphHitWithoutIndex)));
+
+    let result = decode_bitmap(&mut decoder, &[0x03, 0x00, 0xA0, 0x0F], 2, 1);
+    assert!(matches!(result, Err(ClearCodecError::InvalidGlyphIndex(4000))));
+}
+
+#[test]
+fn decode_clips_bitmap_to_destination() {
+    let mut decoder = ClearCodecDecoder::new();
+
+    let residual = [0x01, 0x02, 0x03, 0x06];
+    let output = decode_bitmap_into(&mut decoder, &composite(&residual, &[], &[]), 3, 2, 2, 1).unwrap();
+
+    assert_eq!(output, [0x01, 0x02, 0x03, 0xFF].repeat(2));
+}
+
+#[test]
+fn decode_truncated_stream_fails() {
+    let mut decoder = ClearCodecDecoder::new();
+
+    let mut stream = composite(&[0x01, 0x02, 0x03, 0x04], &[], &[]);
+    stream.pop();
+    let result = decode_bitmap(&mut decoder, &stream, 2, 2);
+
+    assert!(matches!(result, Err(ClearCodecError::NotEnoughData(_))));
+}
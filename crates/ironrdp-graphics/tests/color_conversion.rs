@@ -76,6 +76,42 @@ fn ycbcr_to_rgb_converts_large_buffer() {
     assert_eq!(expected, output.as_slice());
 }
 
+#[test]
+fn ycbcr_from_rgb_works_for_gray() {
+    let rgb = Rgb { r: 128, g: 128, b: 128 };
+
+    let expected = YCbCr { y: 0, cb: 0, cr: 0 };
+    let actual = YCbCr::from(rgb);
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn bgra_to_ycbcr_then_ycbcr_to_bgra_restores_the_colors() {
+    let input = (0..=255u8)
+        .flat_map(|v| [v, v.wrapping_mul(7), 255 - v, 255])
+        .collect::<Vec<_>>();
+
+    let mut y = vec![0; 256];
+    let mut cb = vec![0; 256];
+    let mut cr = vec![0; 256];
+    bgra_to_ycbcr(&input, &mut y, &mut cb, &mut cr);
+
+    let mut output = vec![0; input.len()];
+    ycbcr_to_bgra(
+        YCbCrBuffer {
+            y: &y,
+            cb: &cb,
+            cr: &cr,
+        },
+        output.as_mut(),
+    )
+    .unwrap();
+
+    for (actual, expected) in output.iter().zip(input.iter()) {
+        assert!(actual.abs_diff(*expected) <= 2, "{actual} != {expected}");
+    }
+}
+
 const YCBCR_BUFFER_Y: [i16; 4096] = [
     -32, 16, 64, 272, -32, -16, 0, -16, -32, -24, -16, -8, 0, -24, -48, -72, -96, -90, -84, -78, -72, -98, -124, -150,
     -176, -192, -208, -224, -240, -256, -272, -288, -304, -304, -304, -304, -304, -336, -368, -400, -432, -450, -468,
@@ -30,6 +30,46 @@ fn decode_works_for_regular_values() {
     assert_eq!(expected.as_ref(), buffer.as_ref());
 }
 
+#[test]
+fn decode_reduce_extrapolate_does_not_change_zeroed_buffer() {
+    let mut buffer = [0; 4096];
+
+    let mut temp = vec![0; 4096];
+    decode_reduce_extrapolate(&mut buffer, temp.as_mut_slice());
+    assert!(buffer.iter().all(|v| *v == 0));
+}
+
+#[test]
+fn decode_reduce_extrapolate_spreads_ll3_without_high_frequencies() {
+    // LL3 is the last 9x9 subband
+    let mut buffer = [0; 4096];
+    buffer[4015..].fill(100);
+
+    let mut temp = vec![0; 4096];
+    decode_reduce_extrapolate(&mut buffer, temp.as_mut_slice());
+    assert!(buffer.iter().all(|v| *v == 100));
+}
+
+#[test]
+fn encode_works_for_regular_values() {
+    let mut buffer = DECODED_DWT;
+    let expected = ENCODED_DWT;
+
+    let mut temp = vec![0; 4096];
+    encode(&mut buffer, temp.as_mut_slice());
+    assert_eq!(expected.as_ref(), buffer.as_ref());
+}
+
+#[test]
+fn encode_puts_a_constant_tile_in_ll3() {
+    let mut buffer = [100; 4096];
+
+    let mut temp = vec![0; 4096];
+    encode(&mut buffer, temp.as_mut_slice());
+    assert!(buffer[..4032].iter().all(|v| *v == 0));
+    assert!(buffer[4032..].iter().all(|v| *v == 100));
+}
+
 const DECODED_DWT_FOR_MAX_VALUES: [i16; 4096] = [
     2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2,
     2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 4092, 8191, -4100, -16383, -4100,
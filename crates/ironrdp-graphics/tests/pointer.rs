@@ -0,0 +1,67 @@
+use ironrdp_graphics::pointer::*;
+
+#[test]
+fn monochrome_pointer_is_decoded() {
+    // 2x2 pointer, bottom-up: white and inverted pixels on the bottom row,
+    // black and transparent pixels on the top row
+    let xor_mask = [0xc0, 0x00, 0x00, 0x00];
+    let and_mask = [0x40, 0x00, 0x40, 0x00];
+
+    let rgba = decode_masks(2, 2, 1, &xor_mask, &and_mask).unwrap();
+
+    assert_eq!(
+        rgba,
+        [
+            0x00, 0x00, 0x00, 0xff, // black
+            0x00, 0x00, 0x00, 0x00, // transparent
+            0xff, 0xff, 0xff, 0xff, // white
+            0x00, 0x00, 0x00, 0xff, // inverted
+        ]
+    );
+}
+
+#[test]
+fn color_pointer_without_and_mask_is_opaque() {
+    // 1x1 pointer, 24 bpp BGR padded to 4 bytes
+    let xor_mask = [0x30, 0x20, 0x10, 0x00];
+
+    let rgba = decode_masks(1, 1, 24, &xor_mask, &[]).unwrap();
+
+    assert_eq!(rgba, [0x10, 0x20, 0x30, 0xff]);
+}
+
+#[test]
+fn rgb565_pointer_is_decoded() {
+    let xor_mask = [0x00, 0xf8];
+    let and_mask = [0x00, 0x00];
+
+    let rgba = decode_masks(1, 1, 16, &xor_mask, &and_mask).unwrap();
+
+    assert_eq!(rgba, [0xff, 0x00, 0x00, 0xff]);
+}
+
+#[test]
+fn alpha_channel_of_32_bpp_pointer_is_kept() {
+    let xor_mask = [0x30, 0x20, 0x10, 0x80, 0x00, 0x00, 0x00, 0x00];
+    let and_mask = [0xc0, 0x00];
+
+    let rgba = decode_masks(2, 1, 32, &xor_mask, &and_mask).unwrap();
+
+    assert_eq!(rgba, [0x10, 0x20, 0x30, 0x80, 0x00, 0x00, 0x00, 0x00]);
+}
+
+#[test]
+fn truncated_xor_mask_is_rejected() {
+    assert!(matches!(
+        decode_masks(2, 2, 24, &[0; 11], &[]),
+        Err(PointerError::InvalidMaskLength { mask: "XOR", .. })
+    ));
+}
+
+#[test]
+fn palette_based_pointer_is_not_supported() {
+    assert!(matches!(
+        decode_masks(1, 1, 8, &[0; 2], &[0; 2]),
+        Err(PointerError::UnsupportedBpp(8))
+    ));
+}
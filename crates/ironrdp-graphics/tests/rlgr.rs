@@ -95,6 +95,84 @@ fn decode_correctly_decodes_rlgr1() {
     assert_eq!(expected.as_ref(), output.as_slice());
 }
 
+#[test]
+fn encode_then_decode_round_trips() {
+    let inputs = [Y_DATA_DECODED, CB_DATA_DECODED, CR_DATA_DECODED];
+
+    for (mode, input) in [EntropyAlgorithm::Rlgr1, EntropyAlgorithm::Rlgr3]
+        .into_iter()
+        .flat_map(|mode| inputs.iter().map(move |input| (mode, input)))
+    {
+        let mut encoded = vec![0; 4096];
+        let length = encode(mode, input, &mut encoded).unwrap();
+
+        let mut decoded = vec![0; 4096];
+        decode(mode, &encoded[..length], &mut decoded).unwrap();
+        assert_eq!(input.as_ref(), decoded.as_slice());
+    }
+}
+
+#[test]
+fn encode_then_decode_round_trips_extreme_values() {
+    let input = [
+        0,
+        -i16::MAX,
+        i16::MAX,
+        -1,
+        1,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        5,
+        -i16::MAX,
+        0,
+    ];
+
+    for mode in [EntropyAlgorithm::Rlgr1, EntropyAlgorithm::Rlgr3] {
+        let mut encoded = vec![0; 16384];
+        let length = encode(mode, &input, &mut encoded).unwrap();
+
+        let mut decoded = vec![0; input.len()];
+        decode(mode, &encoded[..length], &mut decoded).unwrap();
+        assert_eq!(input.as_ref(), decoded.as_slice());
+    }
+}
+
+#[test]
+fn encode_terminates_a_zeroed_tile() {
+    let input = [0; 4096];
+
+    let mut encoded = vec![0; 64];
+    let length = encode(EntropyAlgorithm::Rlgr3, &input, &mut encoded).unwrap();
+    assert_ne!(length, 0);
+
+    let mut decoded = vec![1; 4096];
+    decode(EntropyAlgorithm::Rlgr3, &encoded[..length], &mut decoded).unwrap();
+    assert_eq!(input.as_ref(), decoded.as_slice());
+}
+
+#[test]
+fn encode_fails_when_the_output_is_too_small() {
+    let input = [100; 64];
+    let mut output = [0; 8];
+
+    assert!(matches!(
+        encode(EntropyAlgorithm::Rlgr3, &input, &mut output),
+        Err(RlgrError::OutputTooSmall)
+    ));
+}
+
 const Y_DATA_ENCODED: [u8; 942] = [
     0xc0, 0x01, 0x01, 0x15, 0x48, 0x99, 0xc7, 0x41, 0xa1, 0x12, 0x68, 0x11, 0xdc, 0x22, 0x29, 0x74, 0xef, 0xfd, 0x20,
     0x92, 0xe0, 0x4e, 0xa8, 0x69, 0x3b, 0xfd, 0x41, 0x83, 0xbf, 0x28, 0x53, 0x0c, 0x1f, 0xe2, 0x54, 0x0c, 0x77, 0x7c,
@@ -0,0 +1,140 @@
+//! Keyboard layout identifiers (KLID) as advertised to the server in the `keyboardLayout` fields of the
+//! Client Core Data and of the Input Capability Set.
+//!
+//! See [Windows keyboard identifiers].
+//!
+//! [Windows keyboard identifiers]: https://learn.microsoft.com/en-us/windows-hardware/manufacture/desktop/windows-language-pack-default-values
+
+pub const ARABIC: u32 = 0x0000_0401;
+pub const BULGARIAN: u32 = 0x0000_0402;
+pub const CHINESE_TRADITIONAL: u32 = 0x0000_0404;
+pub const CZECH: u32 = 0x0000_0405;
+pub const DANISH: u32 = 0x0000_0406;
+pub const GERMAN: u32 = 0x0000_0407;
+pub const GREEK: u32 = 0x0000_0408;
+pub const US: u32 = 0x0000_0409;
+pub const SPANISH: u32 = 0x0000_040A;
+pub const FINNISH: u32 = 0x0000_040B;
+pub const FRENCH: u32 = 0x0000_040C;
+pub const HEBREW: u32 = 0x0000_040D;
+pub const HUNGARIAN: u32 = 0x0000_040E;
+pub const ICELANDIC: u32 = 0x0000_040F;
+pub const ITALIAN: u32 = 0x0000_0410;
+pub const JAPANESE: u32 = 0x0000_0411;
+pub const KOREAN: u32 = 0x0000_0412;
+pub const DUTCH: u32 = 0x0000_0413;
+pub const NORWEGIAN: u32 = 0x0000_0414;
+pub const POLISH_PROGRAMMERS: u32 = 0x0000_0415;
+pub const PORTUGUESE_BRAZIL: u32 = 0x0000_0416;
+pub const ROMANIAN: u32 = 0x0000_0418;
+pub const RUSSIAN: u32 = 0x0000_0419;
+pub const CROATIAN: u32 = 0x0000_041A;
+pub const SLOVAK: u32 = 0x0000_041B;
+pub const SWEDISH: u32 = 0x0000_041D;
+pub const TURKISH_Q: u32 = 0x0000_041F;
+pub const UKRAINIAN: u32 = 0x0000_0422;
+pub const SLOVENIAN: u32 = 0x0000_0424;
+pub const ESTONIAN: u32 = 0x0000_0425;
+pub const LATVIAN: u32 = 0x0000_0426;
+pub const LITHUANIAN: u32 = 0x0000_0427;
+pub const CHINESE_SIMPLIFIED: u32 = 0x0000_0804;
+pub const SWISS_GERMAN: u32 = 0x0000_0807;
+pub const UNITED_KINGDOM: u32 = 0x0000_0809;
+pub const LATIN_AMERICAN: u32 = 0x0000_080A;
+pub const BELGIAN_FRENCH: u32 = 0x0000_080C;
+pub const BELGIAN_PERIOD: u32 = 0x0000_0813;
+pub const PORTUGUESE: u32 = 0x0000_0816;
+pub const CANADIAN_FRENCH: u32 = 0x0000_1009;
+pub const SWISS_FRENCH: u32 = 0x0000_100C;
+pub const IRISH: u32 = 0x0000_1809;
+pub const US_INTERNATIONAL: u32 = 0x0002_0409;
+
+/// Default keyboard layout of each locale, formatted as `ll-RR`, the first entry of a language being its default.
+const LOCALES: &[(&str, u32)] = &[
+    ("ar-SA", ARABIC),
+    ("bg-BG", BULGARIAN),
+    ("cs-CZ", CZECH),
+    ("da-DK", DANISH),
+    ("de-DE", GERMAN),
+    ("de-AT", GERMAN),
+    ("de-CH", SWISS_GERMAN),
+    ("de-LI", SWISS_GERMAN),
+    ("de-LU", GERMAN),
+    ("el-GR", GREEK),
+    ("en-US", US),
+    ("en-AU", US),
+    ("en-CA", US),
+    ("en-GB", UNITED_KINGDOM),
+    ("en-IE", IRISH),
+    ("en-NZ", US),
+    ("es-ES", SPANISH),
+    ("es-AR", LATIN_AMERICAN),
+    ("es-CL", LATIN_AMERICAN),
+    ("es-CO", LATIN_AMERICAN),
+    ("es-MX", LATIN_AMERICAN),
+    ("et-EE", ESTONIAN),
+    ("fi-FI", FINNISH),
+    ("fr-FR", FRENCH),
+    ("fr-BE", BELGIAN_FRENCH),
+    ("fr-CA", CANADIAN_FRENCH),
+    ("fr-CH", SWISS_FRENCH),
+    ("fr-LU", SWISS_FRENCH),
+    ("he-IL", HEBREW),
+    ("hr-HR", CROATIAN),
+    ("hu-HU", HUNGARIAN),
+    ("is-IS", ICELANDIC),
+    ("it-IT", ITALIAN),
+    ("ja-JP", JAPANESE),
+    ("ko-KR", KOREAN),
+    ("lt-LT", LITHUANIAN),
+    ("lv-LV", LATVIAN),
+    ("nb-NO", NORWEGIAN),
+    ("nl-NL", US_INTERNATIONAL),
+    ("nl-BE", BELGIAN_PERIOD),
+    ("nn-NO", NORWEGIAN),
+    ("no-NO", NORWEGIAN),
+    ("pl-PL", POLISH_PROGRAMMERS),
+    ("pt-PT", PORTUGUESE),
+    ("pt-BR", PORTUGUESE_BRAZIL),
+    ("ro-RO", ROMANIAN),
+    ("ru-RU", RUSSIAN),
+    ("sk-SK", SLOVAK),
+    ("sl-SI", SLOVENIAN),
+    ("sv-SE", SWEDISH),
+    ("sv-FI", SWEDISH),
+    ("tr-TR", TURKISH_Q),
+    ("uk-UA", UKRAINIAN),
+    ("zh-CN", CHINESE_SIMPLIFIED),
+    ("zh-TW", CHINESE_TRADITIONAL),
+];
+
+/// Returns the default keyboard layout identifier for the given locale.
+///
+/// Both BCP 47 language tags (e.g.: `de-DE`, as returned by `navigator.language`) and POSIX locale names
+/// (e.g.: `de_DE.UTF-8`, as found in the `LANG` environment variable) are accepted. When the region is
+/// unknown or missing, the default layout of the language is returned.
+pub fn klid_from_locale(locale: &str) -> Option<u32> {
+    // Strip the POSIX codeset and modifier, e.g.: `.UTF-8` or `@euro`
+    let locale = locale.split(['.', '@']).next().unwrap_or_default();
+
+    let (language, region) = match locale.split_once(['-', '_']) {
+        Some((language, region)) => (language, Some(region)),
+        None => (locale, None),
+    };
+
+    if language.is_empty() {
+        return None;
+    }
+
+    let same_language = |tag: &str| tag[..2].eq_ignore_ascii_case(language);
+
+    let exact_match = region.and_then(|region| {
+        LOCALES
+            .iter()
+            .find(|(tag, _)| same_language(tag) && tag[3..].eq_ignore_ascii_case(region))
+    });
+
+    exact_match
+        .or_else(|| LOCALES.iter().find(|(tag, _)| same_language(tag)))
+        .map(|(_, klid)| *klid)
+}
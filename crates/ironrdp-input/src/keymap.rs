@@ -0,0 +1,245 @@
+//! Translation of platform key codes into RDP scancodes.
+//!
+//! RDP scancodes are IBM PC/AT "set 1" scancodes, with the extended flag standing for the `0xE0` prefix.
+//! Keys without an RDP scancode, such as Pause whose make code uses the `0xE1` prefix, are not mapped.
+
+use crate::Scancode;
+
+/// Offset between X11 keycodes and Linux evdev keycodes.
+const X11_KEYCODE_OFFSET: u32 = 8;
+
+impl Scancode {
+    /// Translates a W3C `KeyboardEvent.code` value (e.g.: `"KeyA"`, `"ArrowLeft"`) into an RDP scancode.
+    ///
+    /// Since `code` identifies the physical key, the remote keyboard layout is in charge of producing the
+    /// character, just like with a local keyboard.
+    pub fn from_web_code(code: &str) -> Option<Self> {
+        let scancode = match code {
+            "Escape" => 0x01,
+            "Digit1" => 0x02,
+            "Digit2" => 0x03,
+            "Digit3" => 0x04,
+            "Digit4" => 0x05,
+            "Digit5" => 0x06,
+            "Digit6" => 0x07,
+            "Digit7" => 0x08,
+            "Digit8" => 0x09,
+            "Digit9" => 0x0A,
+            "Digit0" => 0x0B,
+            "Minus" => 0x0C,
+            "Equal" => 0x0D,
+            "Backspace" => 0x0E,
+            "Tab" => 0x0F,
+            "KeyQ" => 0x10,
+            "KeyW" => 0x11,
+            "KeyE" => 0x12,
+            "KeyR" => 0x13,
+            "KeyT" => 0x14,
+            "KeyY" => 0x15,
+            "KeyU" => 0x16,
+            "KeyI" => 0x17,
+            "KeyO" => 0x18,
+            "KeyP" => 0x19,
+            "BracketLeft" => 0x1A,
+            "BracketRight" => 0x1B,
+            "Enter" => 0x1C,
+            "ControlLeft" => 0x1D,
+            "KeyA" => 0x1E,
+            "KeyS" => 0x1F,
+            "KeyD" => 0x20,
+            "KeyF" => 0x21,
+            "KeyG" => 0x22,
+            "KeyH" => 0x23,
+            "KeyJ" => 0x24,
+            "KeyK" => 0x25,
+            "KeyL" => 0x26,
+            "Semicolon" => 0x27,
+            "Quote" => 0x28,
+            "Backquote" => 0x29,
+            "ShiftLeft" => 0x2A,
+            "Backslash" => 0x2B,
+            "KeyZ" => 0x2C,
+            "KeyX" => 0x2D,
+            "KeyC" => 0x2E,
+            "KeyV" => 0x2F,
+            "KeyB" => 0x30,
+            "KeyN" => 0x31,
+            "KeyM" => 0x32,
+            "Comma" => 0x33,
+            "Period" => 0x34,
+            "Slash" => 0x35,
+            "ShiftRight" => 0x36,
+            "NumpadMultiply" => 0x37,
+            "AltLeft" => 0x38,
+            "Space" => 0x39,
+            "CapsLock" => 0x3A,
+            "F1" => 0x3B,
+            "F2" => 0x3C,
+            "F3" => 0x3D,
+            "F4" => 0x3E,
+            "F5" => 0x3F,
+            "F6" => 0x40,
+            "F7" => 0x41,
+            "F8" => 0x42,
+            "F9" => 0x43,
+            "F10" => 0x44,
+            "NumLock" => 0x45,
+            "ScrollLock" => 0x46,
+            "Numpad7" => 0x47,
+            "Numpad8" => 0x48,
+            "Numpad9" => 0x49,
+            "NumpadSubtract" => 0x4A,
+            "Numpad4" => 0x4B,
+            "Numpad5" => 0x4C,
+            "Numpad6" => 0x4D,
+            "NumpadAdd" => 0x4E,
+            "Numpad1" => 0x4F,
+            "Numpad2" => 0x50,
+            "Numpad3" => 0x51,
+            "Numpad0" => 0x52,
+            "NumpadDecimal" => 0x53,
+            "IntlBackslash" => 0x56,
+            "F11" => 0x57,
+            "F12" => 0x58,
+            "NumpadEqual" => 0x59,
+            "F13" => 0x64,
+            "F14" => 0x65,
+            "F15" => 0x66,
+            "F16" => 0x67,
+            "F17" => 0x68,
+            "F18" => 0x69,
+            "F19" => 0x6A,
+            "F20" => 0x6B,
+            "F21" => 0x6C,
+            "F22" => 0x6D,
+            "F23" => 0x6E,
+            "KanaMode" => 0x70,
+            "Lang2" => 0x71,
+            "Lang1" => 0x72,
+            "IntlRo" => 0x73,
+            "F24" => 0x76,
+            "Convert" => 0x79,
+            "NonConvert" => 0x7B,
+            "IntlYen" => 0x7D,
+            "NumpadComma" => 0x7E,
+            "MediaTrackPrevious" => 0xE010,
+            "MediaTrackNext" => 0xE019,
+            "NumpadEnter" => 0xE01C,
+            "ControlRight" => 0xE01D,
+            "AudioVolumeMute" => 0xE020,
+            "LaunchApp2" => 0xE021,
+            "MediaPlayPause" => 0xE022,
+            "MediaStop" => 0xE024,
+            "AudioVolumeDown" => 0xE02E,
+            "AudioVolumeUp" => 0xE030,
+            "BrowserHome" => 0xE032,
+            "NumpadDivide" => 0xE035,
+            "PrintScreen" => 0xE037,
+            "AltRight" => 0xE038,
+            "Home" => 0xE047,
+            "ArrowUp" => 0xE048,
+            "PageUp" => 0xE049,
+            "ArrowLeft" => 0xE04B,
+            "ArrowRight" => 0xE04D,
+            "End" => 0xE04F,
+            "ArrowDown" => 0xE050,
+            "PageDown" => 0xE051,
+            "Insert" => 0xE052,
+            "Delete" => 0xE053,
+            // "OSLeft" and "OSRight" are the names used by older versions of Firefox
+            "MetaLeft" | "OSLeft" => 0xE05B,
+            "MetaRight" | "OSRight" => 0xE05C,
+            "ContextMenu" => 0xE05D,
+            "Power" => 0xE05E,
+            "Sleep" => 0xE05F,
+            "WakeUp" => 0xE063,
+            "BrowserSearch" => 0xE065,
+            "BrowserFavorites" => 0xE066,
+            "BrowserRefresh" => 0xE067,
+            "BrowserStop" => 0xE068,
+            "BrowserForward" => 0xE069,
+            "BrowserBack" => 0xE06A,
+            "LaunchApp1" => 0xE06B,
+            "LaunchMail" => 0xE06C,
+            "MediaSelect" => 0xE06D,
+            _ => return None,
+        };
+
+        Some(Self::from_u16(scancode))
+    }
+
+    /// Translates a Linux evdev keycode (`KEY_*` constants of `linux/input-event-codes.h`) into an RDP scancode.
+    ///
+    /// This is also the keycode reported by Wayland compositors.
+    pub fn from_evdev_keycode(keycode: u32) -> Option<Self> {
+        let scancode = match keycode {
+            // From KEY_ESC to KEY_KPDOT, evdev keycodes are the set 1 scancodes
+            1..=83 => keycode as u16,
+            86 => 0x56,    // KEY_102ND
+            87 => 0x57,    // KEY_F11
+            88 => 0x58,    // KEY_F12
+            89 => 0x73,    // KEY_RO
+            92 => 0x79,    // KEY_HENKAN
+            93 => 0x70,    // KEY_KATAKANAHIRAGANA
+            94 => 0x7B,    // KEY_MUHENKAN
+            96 => 0xE01C,  // KEY_KPENTER
+            97 => 0xE01D,  // KEY_RIGHTCTRL
+            98 => 0xE035,  // KEY_KPSLASH
+            99 => 0xE037,  // KEY_SYSRQ
+            100 => 0xE038, // KEY_RIGHTALT
+            102 => 0xE047, // KEY_HOME
+            103 => 0xE048, // KEY_UP
+            104 => 0xE049, // KEY_PAGEUP
+            105 => 0xE04B, // KEY_LEFT
+            106 => 0xE04D, // KEY_RIGHT
+            107 => 0xE04F, // KEY_END
+            108 => 0xE050, // KEY_DOWN
+            109 => 0xE051, // KEY_PAGEDOWN
+            110 => 0xE052, // KEY_INSERT
+            111 => 0xE053, // KEY_DELETE
+            113 => 0xE020, // KEY_MUTE
+            114 => 0xE02E, // KEY_VOLUMEDOWN
+            115 => 0xE030, // KEY_VOLUMEUP
+            116 => 0xE05E, // KEY_POWER
+            117 => 0x59,   // KEY_KPEQUAL
+            121 => 0x7E,   // KEY_KPCOMMA
+            122 => 0x72,   // KEY_HANGEUL
+            123 => 0x71,   // KEY_HANJA
+            124 => 0x7D,   // KEY_YEN
+            125 => 0xE05B, // KEY_LEFTMETA
+            126 => 0xE05C, // KEY_RIGHTMETA
+            127 => 0xE05D, // KEY_COMPOSE
+            140 => 0xE021, // KEY_CALC
+            142 => 0xE05F, // KEY_SLEEP
+            143 => 0xE063, // KEY_WAKEUP
+            155 => 0xE06C, // KEY_MAIL
+            156 => 0xE066, // KEY_BOOKMARKS
+            157 => 0xE06B, // KEY_COMPUTER
+            158 => 0xE06A, // KEY_BACK
+            159 => 0xE069, // KEY_FORWARD
+            163 => 0xE019, // KEY_NEXTSONG
+            164 => 0xE022, // KEY_PLAYPAUSE
+            165 => 0xE010, // KEY_PREVIOUSSONG
+            166 => 0xE024, // KEY_STOPCD
+            172 => 0xE032, // KEY_HOMEPAGE
+            173 => 0xE067, // KEY_REFRESH
+            // From KEY_F13 to KEY_F23
+            183..=193 => 0x64 + (keycode - 183) as u16,
+            194 => 0x76,   // KEY_F24
+            217 => 0xE065, // KEY_SEARCH
+            226 => 0xE06D, // KEY_MEDIA
+            _ => return None,
+        };
+
+        Some(Self::from_u16(scancode))
+    }
+
+    /// Translates an X11 keycode, as found in `XKeyEvent::keycode`, into an RDP scancode.
+    ///
+    /// This assumes the X server uses the evdev driver, which is the case of all modern X servers and Xwayland.
+    pub fn from_x11_keycode(keycode: u32) -> Option<Self> {
+        keycode
+            .checked_sub(X11_KEYCODE_OFFSET)
+            .and_then(Self::from_evdev_keycode)
+    }
+}
@@ -1,12 +1,19 @@
+use std::collections::BTreeSet;
+use std::mem;
+
 use bitvec::array::BitArray;
 use bitvec::BitArr;
 use ironrdp_pdu::input::fast_path::{FastPathInputEvent, KeyboardFlags};
 use ironrdp_pdu::input::mouse::PointerFlags;
+use ironrdp_pdu::input::mouse_rel::PointerRelFlags;
 use ironrdp_pdu::input::mouse_x::PointerXFlags;
-use ironrdp_pdu::input::{MousePdu, MouseXPdu};
+use ironrdp_pdu::input::{MousePdu, MouseRelPdu, MouseXPdu};
+use ironrdp_pdu::rdp::capability_sets::InputFlags;
 use smallvec::SmallVec;
 
-// TODO: unicode keyboard event support
+pub mod keyboard_layout;
+mod keymap;
+pub mod touch;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[repr(u8)]
@@ -118,7 +125,16 @@ pub struct MousePosition {
     pub y: u16,
 }
 
+/// Relative movement of a mouse device, e.g.: while the pointer is locked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MouseDelta {
+    pub x: i16,
+    pub y: i16,
+}
+
 /// Mouse wheel rotations.
+///
+/// A notch of a standard wheel is 120 units. Positive values scroll up, or right for the horizontal wheel.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct WheelRotations {
     pub is_vertical: bool,
@@ -130,19 +146,74 @@ pub enum Operation {
     MouseButtonPressed(MouseButton),
     MouseButtonReleased(MouseButton),
     MouseMove(MousePosition),
+    /// Moves the mouse relatively to its current position.
+    ///
+    /// Relative mouse events are sent when the server supports them, in which case the subsequent button
+    /// events are relative as well, until the next absolute [`Operation::MouseMove`]. Otherwise, the movement
+    /// is applied to the current position and sent as an absolute one.
+    MouseMoveRelative(MouseDelta),
     WheelRotations(WheelRotations),
     KeyPressed(Scancode),
     KeyReleased(Scancode),
+    /// A character typed independently of the keyboard layout, e.g.: from an IME.
+    ///
+    /// Characters outside of the Basic Multilingual Plane are sent as a surrogate pair.
+    UnicodeKeyPressed(char),
+    UnicodeKeyReleased(char),
+}
+
+/// Returns the operations typing the given text, one character after the other.
+///
+/// Characters are sent as unicode keyboard events, except for line breaks and tabulations which are sent
+/// as the Enter and Tab keys respectively, because applications usually don't handle them as characters.
+/// A `\r\n` sequence is typed as a single line break.
+pub fn type_text(text: &str) -> impl Iterator<Item = Operation> + '_ {
+    const ENTER: Scancode = Scancode::from_u8(false, 0x1C);
+    const TAB: Scancode = Scancode::from_u8(false, 0x0F);
+
+    let mut chars = text.chars().peekable();
+
+    core::iter::from_fn(move || {
+        let ch = chars.next()?;
+
+        let operations = match ch {
+            '\r' | '\n' => {
+                if ch == '\r' {
+                    chars.next_if_eq(&'\n');
+                }
+
+                [Operation::KeyPressed(ENTER), Operation::KeyReleased(ENTER)]
+            }
+            '\t' => [Operation::KeyPressed(TAB), Operation::KeyReleased(TAB)],
+            ch => [Operation::UnicodeKeyPressed(ch), Operation::UnicodeKeyReleased(ch)],
+        };
+
+        Some(operations)
+    })
+    .flatten()
 }
 
 pub type KeyboardState = BitArr!(for 512);
 pub type MouseButtonsState = BitArr!(for 5);
 
+/// Input flags assumed for the server until [`Database::set_server_input_flags`] is called.
+const DEFAULT_SERVER_INPUT_FLAGS: InputFlags = InputFlags::SCANCODES
+    .union(InputFlags::MOUSEX)
+    .union(InputFlags::UNICODE)
+    .union(InputFlags::TS_MOUSE_HWHEEL);
+
+/// Largest wheel rotation that fits in a single mouse event.
+const MAX_WHEEL_ROTATION_UNITS: i16 = 255;
+
 /// In-memory database for maintaining the current keyboard and mouse state.
 pub struct Database {
     keyboard: KeyboardState,
+    unicode_keyboard: BTreeSet<char>,
     mouse_buttons: MouseButtonsState,
     mouse_position: MousePosition,
+    /// Whether the last movement was sent as a relative mouse event
+    relative_mouse: bool,
+    server_input_flags: InputFlags,
 }
 
 impl Default for Database {
@@ -155,11 +226,30 @@ impl Database {
     pub fn new() -> Self {
         Self {
             keyboard: BitArray::ZERO,
+            unicode_keyboard: BTreeSet::new(),
             mouse_buttons: BitArray::ZERO,
             mouse_position: MousePosition { x: 0, y: 0 },
+            relative_mouse: false,
+            server_input_flags: DEFAULT_SERVER_INPUT_FLAGS,
         }
     }
 
+    /// Sets the input flags advertised by the server in its Input Capability Set.
+    ///
+    /// Relative mouse events are only sent when `InputFlags::MOUSE_RELATIVE` is set, and the events of
+    /// the extended buttons and of the horizontal wheel are dropped when not supported.
+    pub fn set_server_input_flags(&mut self, flags: InputFlags) {
+        self.server_input_flags = flags;
+
+        if !flags.contains(InputFlags::MOUSE_RELATIVE) {
+            self.relative_mouse = false;
+        }
+    }
+
+    pub fn server_input_flags(&self) -> InputFlags {
+        self.server_input_flags
+    }
+
     pub fn is_key_pressed(&self, scancode: Scancode) -> bool {
         self.keyboard
             .get(scancode.as_idx())
@@ -168,6 +258,10 @@ impl Database {
             .unwrap_or(false)
     }
 
+    pub fn is_unicode_key_pressed(&self, character: char) -> bool {
+        self.unicode_keyboard.contains(&character)
+    }
+
     pub fn is_mouse_button_pressed(&self, button: MouseButton) -> bool {
         self.mouse_buttons
             .get(button.as_idx())
@@ -200,46 +294,19 @@ impl Database {
                     let was_pressed = self.mouse_buttons.replace(button.as_idx(), true);
 
                     if !was_pressed {
-                        let event = match MouseButtonFlags::from(button) {
-                            MouseButtonFlags::Button(flags) => FastPathInputEvent::MouseEvent(MousePdu {
-                                flags: PointerFlags::DOWN | flags,
-                                number_of_wheel_rotation_units: 0,
-                                x_position: self.mouse_position.x,
-                                y_position: self.mouse_position.y,
-                            }),
-                            MouseButtonFlags::Pointer(flags) => FastPathInputEvent::MouseEventEx(MouseXPdu {
-                                flags: PointerXFlags::DOWN | flags,
-                                x_position: self.mouse_position.x,
-                                y_position: self.mouse_position.y,
-                            }),
-                        };
-
-                        events.push(event)
+                        events.extend(self.mouse_button_event(button, true));
                     }
                 }
                 Operation::MouseButtonReleased(button) => {
                     let was_pressed = self.mouse_buttons.replace(button.as_idx(), false);
 
                     if was_pressed {
-                        let event = match MouseButtonFlags::from(button) {
-                            MouseButtonFlags::Button(flags) => FastPathInputEvent::MouseEvent(MousePdu {
-                                flags,
-                                number_of_wheel_rotation_units: 0,
-                                x_position: self.mouse_position.x,
-                                y_position: self.mouse_position.y,
-                            }),
-                            MouseButtonFlags::Pointer(flags) => FastPathInputEvent::MouseEventEx(MouseXPdu {
-                                flags,
-                                x_position: self.mouse_position.x,
-                                y_position: self.mouse_position.y,
-                            }),
-                        };
-
-                        events.push(event)
+                        events.extend(self.mouse_button_event(button, false));
                     }
                 }
                 Operation::MouseMove(position) => {
-                    if position != self.mouse_position {
+                    if position != self.mouse_position || self.relative_mouse {
+                        self.relative_mouse = false;
                         self.mouse_position = position;
                         events.push(FastPathInputEvent::MouseEvent(MousePdu {
                             flags: PointerFlags::MOVE,
@@ -249,16 +316,59 @@ impl Database {
                         }))
                     }
                 }
-                Operation::WheelRotations(rotations) => events.push(FastPathInputEvent::MouseEvent(MousePdu {
-                    flags: if rotations.is_vertical {
-                        PointerFlags::VERTICAL_WHEEL
+                Operation::MouseMoveRelative(delta) => {
+                    if delta.x == 0 && delta.y == 0 {
+                        continue;
+                    }
+
+                    if self.server_input_flags.contains(InputFlags::MOUSE_RELATIVE) {
+                        self.relative_mouse = true;
+                        events.push(FastPathInputEvent::MouseEventRel(MouseRelPdu {
+                            flags: PointerRelFlags::MOVE,
+                            x_delta: delta.x,
+                            y_delta: delta.y,
+                        }))
                     } else {
+                        let position = MousePosition {
+                            x: self.mouse_position.x.saturating_add_signed(delta.x),
+                            y: self.mouse_position.y.saturating_add_signed(delta.y),
+                        };
+
+                        if position != self.mouse_position {
+                            self.mouse_position = position;
+                            events.push(FastPathInputEvent::MouseEvent(MousePdu {
+                                flags: PointerFlags::MOVE,
+                                number_of_wheel_rotation_units: 0,
+                                x_position: position.x,
+                                y_position: position.y,
+                            }))
+                        }
+                    }
+                }
+                Operation::WheelRotations(rotations) => {
+                    let flags = if rotations.is_vertical {
+                        PointerFlags::VERTICAL_WHEEL
+                    } else if self.server_input_flags.contains(InputFlags::TS_MOUSE_HWHEEL) {
                         PointerFlags::HORIZONTAL_WHEEL
-                    },
-                    number_of_wheel_rotation_units: rotations.rotation_units,
-                    x_position: self.mouse_position.x,
-                    y_position: self.mouse_position.y,
-                })),
+                    } else {
+                        continue;
+                    };
+
+                    // Large rotations are split into several events
+                    let mut remaining = rotations.rotation_units;
+
+                    while remaining != 0 {
+                        let units = remaining.clamp(-MAX_WHEEL_ROTATION_UNITS, MAX_WHEEL_ROTATION_UNITS);
+                        remaining -= units;
+
+                        events.push(FastPathInputEvent::MouseEvent(MousePdu {
+                            flags,
+                            number_of_wheel_rotation_units: units,
+                            x_position: self.mouse_position.x,
+                            y_position: self.mouse_position.y,
+                        }))
+                    }
+                }
                 Operation::KeyPressed(scancode) => {
                     let was_pressed = self.keyboard.replace(scancode.as_idx(), true);
 
@@ -290,6 +400,22 @@ impl Database {
                         events.push(FastPathInputEvent::KeyboardEvent(flags, scancode.code));
                     }
                 }
+                Operation::UnicodeKeyPressed(character) => {
+                    let was_pressed = !self.unicode_keyboard.insert(character);
+
+                    if was_pressed {
+                        push_unicode_events(&mut events, KeyboardFlags::RELEASE, character);
+                    }
+
+                    push_unicode_events(&mut events, KeyboardFlags::empty(), character);
+                }
+                Operation::UnicodeKeyReleased(character) => {
+                    let was_pressed = self.unicode_keyboard.remove(&character);
+
+                    if was_pressed {
+                        push_unicode_events(&mut events, KeyboardFlags::RELEASE, character);
+                    }
+                }
             }
         }
 
@@ -302,22 +428,7 @@ impl Database {
 
         for idx in self.mouse_buttons.iter_ones() {
             let button = MouseButton::from_idx(idx).expect("in-range index");
-
-            let event = match MouseButtonFlags::from(button) {
-                MouseButtonFlags::Button(flags) => FastPathInputEvent::MouseEvent(MousePdu {
-                    flags,
-                    number_of_wheel_rotation_units: 0,
-                    x_position: self.mouse_position.x,
-                    y_position: self.mouse_position.y,
-                }),
-                MouseButtonFlags::Pointer(flags) => FastPathInputEvent::MouseEventEx(MouseXPdu {
-                    flags,
-                    x_position: self.mouse_position.x,
-                    y_position: self.mouse_position.y,
-                }),
-            };
-
-            events.push(event)
+            events.extend(self.mouse_button_event(button, false));
         }
 
         for idx in self.keyboard.iter_ones() {
@@ -336,11 +447,64 @@ impl Database {
             events.push(FastPathInputEvent::KeyboardEvent(flags, scancode));
         }
 
+        for character in mem::take(&mut self.unicode_keyboard) {
+            push_unicode_events(&mut events, KeyboardFlags::RELEASE, character);
+        }
+
         self.mouse_buttons = BitArray::ZERO;
         self.keyboard = BitArray::ZERO;
 
         events
     }
+
+    /// Returns the RDP input event for a button state change, if supported by the server.
+    ///
+    /// Relative mouse events are used when the pointer is moved relatively, so that the server does not
+    /// warp the pointer back to the last absolute position.
+    fn mouse_button_event(&self, button: MouseButton, pressed: bool) -> Option<FastPathInputEvent> {
+        if self.relative_mouse {
+            let mut flags = PointerRelFlags::from(button);
+
+            if pressed {
+                flags |= PointerRelFlags::DOWN;
+            }
+
+            return Some(FastPathInputEvent::MouseEventRel(MouseRelPdu {
+                flags,
+                x_delta: 0,
+                y_delta: 0,
+            }));
+        }
+
+        let event = match MouseButtonFlags::from(button) {
+            MouseButtonFlags::Button(mut flags) => {
+                if pressed {
+                    flags |= PointerFlags::DOWN;
+                }
+
+                FastPathInputEvent::MouseEvent(MousePdu {
+                    flags,
+                    number_of_wheel_rotation_units: 0,
+                    x_position: self.mouse_position.x,
+                    y_position: self.mouse_position.y,
+                })
+            }
+            MouseButtonFlags::Pointer(_) if !self.server_input_flags.contains(InputFlags::MOUSEX) => return None,
+            MouseButtonFlags::Pointer(mut flags) => {
+                if pressed {
+                    flags |= PointerXFlags::DOWN;
+                }
+
+                FastPathInputEvent::MouseEventEx(MouseXPdu {
+                    flags,
+                    x_position: self.mouse_position.x,
+                    y_position: self.mouse_position.y,
+                })
+            }
+        };
+
+        Some(event)
+    }
 }
 
 /// Returns the RDP input event to send in order to synchronize lock keys.
@@ -368,6 +532,15 @@ pub fn synchronize_event(scroll_lock: bool, num_lock: bool, caps_lock: bool, kan
     FastPathInputEvent::SyncEvent(flags)
 }
 
+/// Pushes one unicode keyboard event per UTF-16 code unit of the character.
+fn push_unicode_events(events: &mut SmallVec<[FastPathInputEvent; 2]>, flags: KeyboardFlags, character: char) {
+    let mut buf = [0; 2];
+
+    for code_unit in character.encode_utf16(&mut buf) {
+        events.push(FastPathInputEvent::UnicodeKeyboardEvent(flags, *code_unit));
+    }
+}
+
 enum MouseButtonFlags {
     Button(PointerFlags),
     Pointer(PointerXFlags),
@@ -384,3 +557,15 @@ impl From<MouseButton> for MouseButtonFlags {
         }
     }
 }
+
+impl From<MouseButton> for PointerRelFlags {
+    fn from(value: MouseButton) -> Self {
+        match value {
+            MouseButton::Left => Self::BUTTON1,
+            MouseButton::Right => Self::BUTTON2,
+            MouseButton::Middle => Self::BUTTON3,
+            MouseButton::X1 => Self::XBUTTON1,
+            MouseButton::X2 => Self::XBUTTON2,
+        }
+    }
+}
@@ -0,0 +1,229 @@
+//! Tracking of touch contacts for the Input Virtual Channel Extension ([MS-RDPEI]).
+//!
+//! The server only accepts a few transitions between the states of a contact (out of range, hovering and
+//! engaged), and expects every frame to report all the active contacts. [`TouchDatabase`] turns the raw touch
+//! points reported by the platform into frames satisfying these rules.
+//!
+//! [MS-RDPEI]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpei/a9a6fc8c-2a4b-4c9d-8e28-0cbd7b5a0b39
+
+use std::collections::BTreeMap;
+use std::mem;
+
+use ironrdp_pdu::rdp::vc::dvc::rdpei::{ContactFlags, TouchContact, TouchFrame};
+
+/// Touch point as reported by the platform.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TouchPoint {
+    /// Identifier of the contact, stable from the time it is detected until it is lifted
+    /// (e.g.: `Touch.identifier` on the web).
+    pub id: u32,
+    pub x: i32,
+    pub y: i32,
+    /// Normalized pressure, from 0 to 1024.
+    pub pressure: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TouchOperation {
+    /// The contact touched the surface.
+    Down(TouchPoint),
+    /// The contact moved, either on the surface or hovering over it.
+    Move(TouchPoint),
+    /// The contact with the given ID was lifted.
+    Up(u32),
+    /// The contact with the given ID is not a deliberate input anymore (e.g.: palm rejection).
+    Cancel(u32),
+}
+
+impl TouchOperation {
+    fn id(&self) -> u32 {
+        match self {
+            TouchOperation::Down(point) | TouchOperation::Move(point) => point.id,
+            TouchOperation::Up(id) | TouchOperation::Cancel(id) => *id,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Contact {
+    contact_id: u8,
+    x: i32,
+    y: i32,
+    pressure: Option<u32>,
+    /// Whether the contact touches the surface, as opposed to hovering over it
+    engaged: bool,
+}
+
+impl Contact {
+    fn to_pdu(self, flags: ContactFlags) -> TouchContact {
+        TouchContact {
+            contact_id: self.contact_id,
+            x: self.x,
+            y: self.y,
+            flags,
+            contact_rect: None,
+            orientation: None,
+            pressure: self.pressure,
+        }
+    }
+
+    fn update_flags(&self) -> ContactFlags {
+        if self.engaged {
+            ContactFlags::UPDATE | ContactFlags::IN_RANGE | ContactFlags::IN_CONTACT
+        } else {
+            ContactFlags::UPDATE | ContactFlags::IN_RANGE
+        }
+    }
+}
+
+/// Contacts changed by the operations applied since the last frame
+#[derive(Default)]
+struct PendingFrame {
+    ids: Vec<u32>,
+    /// Changed contacts, including the ones which went out of range, by contact ID
+    contacts: BTreeMap<u8, TouchContact>,
+}
+
+/// In-memory database for maintaining the state of the touch contacts.
+pub struct TouchDatabase {
+    max_contacts: usize,
+    /// Active contacts, by platform ID
+    contacts: BTreeMap<u32, Contact>,
+}
+
+impl TouchDatabase {
+    /// `max_contacts` is the number of simultaneous contacts advertised to the server. Contacts detected
+    /// while this number is reached are ignored until they are lifted.
+    pub fn new(max_contacts: u16) -> Self {
+        Self {
+            max_contacts: usize::from(max_contacts).min(usize::from(u8::MAX) + 1),
+            contacts: BTreeMap::new(),
+        }
+    }
+
+    pub fn active_contacts(&self) -> usize {
+        self.contacts.len()
+    }
+
+    /// Apply a transaction (list of operations) and returns the touch frames to send.
+    ///
+    /// Every frame reports all the active contacts, and a new frame is started whenever a contact changes
+    /// twice. Operations that would cause no state change, or an invalid transition, are ignored.
+    pub fn apply(&mut self, transaction: impl IntoIterator<Item = TouchOperation>) -> Vec<TouchFrame> {
+        let mut frames = Vec::new();
+        let mut pending = PendingFrame::default();
+
+        for operation in transaction {
+            if pending.ids.contains(&operation.id()) {
+                frames.push(self.finish_frame(mem::take(&mut pending)));
+            }
+
+            if let Some(contact) = self.apply_operation(operation, &pending) {
+                pending.ids.push(operation.id());
+                pending.contacts.insert(contact.contact_id, contact);
+            }
+        }
+
+        if !pending.ids.is_empty() {
+            frames.push(self.finish_frame(pending));
+        }
+
+        frames
+    }
+
+    /// Cancels all the active contacts. Returns the touch frames to send.
+    pub fn release_all(&mut self) -> Vec<TouchFrame> {
+        let ids = self.contacts.keys().copied().collect::<Vec<_>>();
+        self.apply(ids.into_iter().map(TouchOperation::Cancel))
+    }
+
+    /// Returns the changed contact, if any
+    fn apply_operation(&mut self, operation: TouchOperation, pending: &PendingFrame) -> Option<TouchContact> {
+        match operation {
+            TouchOperation::Down(point) | TouchOperation::Move(point) => {
+                let engaged = matches!(operation, TouchOperation::Down(_));
+
+                if let Some(contact) = self.contacts.get_mut(&point.id) {
+                    let newly_engaged = engaged && !contact.engaged;
+                    let moved = (contact.x, contact.y, contact.pressure) != (point.x, point.y, point.pressure);
+
+                    if !newly_engaged && !moved {
+                        return None;
+                    }
+
+                    contact.x = point.x;
+                    contact.y = point.y;
+                    contact.pressure = point.pressure;
+
+                    let flags = if newly_engaged {
+                        contact.engaged = true;
+                        ContactFlags::DOWN | ContactFlags::IN_RANGE | ContactFlags::IN_CONTACT
+                    } else {
+                        contact.update_flags()
+                    };
+
+                    Some(contact.to_pdu(flags))
+                } else {
+                    let contact_id = self.free_contact_id(pending)?;
+
+                    let contact = Contact {
+                        contact_id,
+                        x: point.x,
+                        y: point.y,
+                        pressure: point.pressure,
+                        engaged,
+                    };
+
+                    self.contacts.insert(point.id, contact);
+
+                    let flags = if engaged {
+                        ContactFlags::DOWN | ContactFlags::IN_RANGE | ContactFlags::IN_CONTACT
+                    } else {
+                        contact.update_flags()
+                    };
+
+                    Some(contact.to_pdu(flags))
+                }
+            }
+            TouchOperation::Up(id) | TouchOperation::Cancel(id) => {
+                let contact = self.contacts.remove(&id)?;
+
+                // A hovering contact goes out of range with a plain update
+                let mut flags = if contact.engaged {
+                    ContactFlags::UP
+                } else {
+                    ContactFlags::UPDATE
+                };
+
+                if matches!(operation, TouchOperation::Cancel(_)) {
+                    flags |= ContactFlags::CANCELED;
+                }
+
+                Some(contact.to_pdu(flags))
+            }
+        }
+    }
+
+    /// Smallest contact ID neither used by an active contact nor by a contact removed in the pending frame
+    fn free_contact_id(&self, pending: &PendingFrame) -> Option<u8> {
+        (0..=u8::MAX).take(self.max_contacts).find(|contact_id| {
+            !pending.contacts.contains_key(contact_id)
+                && self.contacts.values().all(|contact| contact.contact_id != *contact_id)
+        })
+    }
+
+    fn finish_frame(&self, mut pending: PendingFrame) -> TouchFrame {
+        // Unchanged contacts are reported as well
+        for contact in self.contacts.values() {
+            pending
+                .contacts
+                .entry(contact.contact_id)
+                .or_insert_with(|| contact.to_pdu(contact.update_flags()));
+        }
+
+        TouchFrame {
+            frame_offset: 0,
+            contacts: pending.contacts.into_values().collect(),
+        }
+    }
+}
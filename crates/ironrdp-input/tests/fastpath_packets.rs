@@ -1,8 +1,10 @@
 use ironrdp_input::*;
 use ironrdp_pdu::input::fast_path::{FastPathInputEvent, KeyboardFlags, SynchronizeFlags};
 use ironrdp_pdu::input::mouse::PointerFlags;
+use ironrdp_pdu::input::mouse_rel::PointerRelFlags;
 use ironrdp_pdu::input::mouse_x::PointerXFlags;
-use ironrdp_pdu::input::{MousePdu, MouseXPdu};
+use ironrdp_pdu::input::{MousePdu, MouseRelPdu, MouseXPdu};
+use ironrdp_pdu::rdp::capability_sets::InputFlags;
 use rstest::rstest;
 
 enum MouseFlags {
@@ -328,3 +330,206 @@ fn wheel_rotations() {
 
     assert_eq!(actual_inputs.as_slice(), expected_inputs.as_slice());
 }
+
+#[test]
+fn unicode_keyboard() {
+    let mut db = Database::default();
+
+    let ops = [
+        Operation::UnicodeKeyPressed('é'),
+        Operation::UnicodeKeyPressed('😀'),
+        Operation::UnicodeKeyReleased('é'),
+        Operation::UnicodeKeyReleased('é'),
+    ];
+
+    let expected_inputs = [
+        FastPathInputEvent::UnicodeKeyboardEvent(KeyboardFlags::empty(), 0x00E9),
+        FastPathInputEvent::UnicodeKeyboardEvent(KeyboardFlags::empty(), 0xD83D),
+        FastPathInputEvent::UnicodeKeyboardEvent(KeyboardFlags::empty(), 0xDE00),
+        FastPathInputEvent::UnicodeKeyboardEvent(KeyboardFlags::RELEASE, 0x00E9),
+    ];
+
+    let actual_inputs = db.apply(ops);
+
+    assert_eq!(actual_inputs.as_slice(), expected_inputs.as_slice());
+    assert!(!db.is_unicode_key_pressed('é'));
+    assert!(db.is_unicode_key_pressed('😀'));
+
+    let expected_inputs = [
+        FastPathInputEvent::UnicodeKeyboardEvent(KeyboardFlags::RELEASE, 0xD83D),
+        FastPathInputEvent::UnicodeKeyboardEvent(KeyboardFlags::RELEASE, 0xDE00),
+    ];
+
+    let actual_inputs = db.release_all();
+
+    assert_eq!(actual_inputs.as_slice(), expected_inputs.as_slice());
+    assert!(!db.is_unicode_key_pressed('😀'));
+}
+
+#[test]
+fn type_text_operations() {
+    let mut db = Database::default();
+
+    let actual_inputs = db.apply(type_text("a😀\r\n\t"));
+
+    let expected_inputs = [
+        FastPathInputEvent::UnicodeKeyboardEvent(KeyboardFlags::empty(), 0x0061),
+        FastPathInputEvent::UnicodeKeyboardEvent(KeyboardFlags::RELEASE, 0x0061),
+        FastPathInputEvent::UnicodeKeyboardEvent(KeyboardFlags::empty(), 0xD83D),
+        FastPathInputEvent::UnicodeKeyboardEvent(KeyboardFlags::empty(), 0xDE00),
+        FastPathInputEvent::UnicodeKeyboardEvent(KeyboardFlags::RELEASE, 0xD83D),
+        FastPathInputEvent::UnicodeKeyboardEvent(KeyboardFlags::RELEASE, 0xDE00),
+        FastPathInputEvent::KeyboardEvent(KeyboardFlags::empty(), 0x1C),
+        FastPathInputEvent::KeyboardEvent(KeyboardFlags::RELEASE, 0x1C),
+        FastPathInputEvent::KeyboardEvent(KeyboardFlags::empty(), 0x0F),
+        FastPathInputEvent::KeyboardEvent(KeyboardFlags::RELEASE, 0x0F),
+    ];
+
+    assert_eq!(actual_inputs.as_slice(), expected_inputs.as_slice());
+    assert!(db.release_all().is_empty());
+}
+
+#[test]
+fn relative_mouse() {
+    let mut db = Database::default();
+    db.set_server_input_flags(InputFlags::all());
+
+    let actual_inputs = db.apply([
+        Operation::MouseMoveRelative(MouseDelta { x: -5, y: 3 }),
+        Operation::MouseMoveRelative(MouseDelta { x: 0, y: 0 }),
+        Operation::MouseButtonPressed(MouseButton::Left),
+        Operation::MouseButtonPressed(MouseButton::X1),
+    ]);
+
+    let expected_inputs = [
+        FastPathInputEvent::MouseEventRel(MouseRelPdu {
+            flags: PointerRelFlags::MOVE,
+            x_delta: -5,
+            y_delta: 3,
+        }),
+        FastPathInputEvent::MouseEventRel(MouseRelPdu {
+            flags: PointerRelFlags::DOWN | PointerRelFlags::BUTTON1,
+            x_delta: 0,
+            y_delta: 0,
+        }),
+        FastPathInputEvent::MouseEventRel(MouseRelPdu {
+            flags: PointerRelFlags::DOWN | PointerRelFlags::XBUTTON1,
+            x_delta: 0,
+            y_delta: 0,
+        }),
+    ];
+
+    assert_eq!(actual_inputs.as_slice(), expected_inputs.as_slice());
+    assert_eq!(db.mouse_position(), MousePosition { x: 0, y: 0 });
+
+    // Going back to absolute positioning, even at the same position
+    let actual_inputs = db.apply([
+        Operation::MouseMove(MousePosition { x: 0, y: 0 }),
+        Operation::MouseButtonReleased(MouseButton::Left),
+    ]);
+
+    let expected_inputs = [
+        FastPathInputEvent::MouseEvent(MousePdu {
+            flags: PointerFlags::MOVE,
+            number_of_wheel_rotation_units: 0,
+            x_position: 0,
+            y_position: 0,
+        }),
+        FastPathInputEvent::MouseEvent(MousePdu {
+            flags: PointerFlags::LEFT_BUTTON,
+            number_of_wheel_rotation_units: 0,
+            x_position: 0,
+            y_position: 0,
+        }),
+    ];
+
+    assert_eq!(actual_inputs.as_slice(), expected_inputs.as_slice());
+}
+
+#[test]
+fn relative_mouse_unsupported() {
+    let mut db = Database::default();
+
+    let actual_inputs = db.apply([
+        Operation::MouseMove(MousePosition { x: 10, y: 10 }),
+        Operation::MouseMoveRelative(MouseDelta { x: -20, y: 5 }),
+    ]);
+
+    let expected_inputs = [
+        FastPathInputEvent::MouseEvent(MousePdu {
+            flags: PointerFlags::MOVE,
+            number_of_wheel_rotation_units: 0,
+            x_position: 10,
+            y_position: 10,
+        }),
+        FastPathInputEvent::MouseEvent(MousePdu {
+            flags: PointerFlags::MOVE,
+            number_of_wheel_rotation_units: 0,
+            x_position: 0,
+            y_position: 15,
+        }),
+    ];
+
+    assert_eq!(actual_inputs.as_slice(), expected_inputs.as_slice());
+    assert_eq!(db.mouse_position(), MousePosition { x: 0, y: 15 });
+}
+
+#[test]
+fn extended_buttons_unsupported() {
+    let mut db = Database::default();
+    db.set_server_input_flags(InputFlags::SCANCODES | InputFlags::FASTPATH_INPUT);
+
+    let actual_inputs = db.apply([Operation::MouseButtonPressed(MouseButton::X2)]);
+
+    assert!(actual_inputs.is_empty());
+    assert!(db.is_mouse_button_pressed(MouseButton::X2));
+    assert!(db.release_all().is_empty());
+}
+
+#[test]
+fn wheel_rotations_server_limits() {
+    let mut db = Database::default();
+
+    let actual_inputs = db.apply([
+        Operation::WheelRotations(WheelRotations {
+            is_vertical: true,
+            rotation_units: -360,
+        }),
+        Operation::WheelRotations(WheelRotations {
+            is_vertical: false,
+            rotation_units: 120,
+        }),
+    ]);
+
+    let expected_inputs = [
+        FastPathInputEvent::MouseEvent(MousePdu {
+            flags: PointerFlags::VERTICAL_WHEEL,
+            number_of_wheel_rotation_units: -255,
+            x_position: 0,
+            y_position: 0,
+        }),
+        FastPathInputEvent::MouseEvent(MousePdu {
+            flags: PointerFlags::VERTICAL_WHEEL,
+            number_of_wheel_rotation_units: -105,
+            x_position: 0,
+            y_position: 0,
+        }),
+        FastPathInputEvent::MouseEvent(MousePdu {
+            flags: PointerFlags::HORIZONTAL_WHEEL,
+            number_of_wheel_rotation_units: 120,
+            x_position: 0,
+            y_position: 0,
+        }),
+    ];
+
+    assert_eq!(actual_inputs.as_slice(), expected_inputs.as_slice());
+
+    db.set_server_input_flags(InputFlags::SCANCODES | InputFlags::FASTPATH_INPUT);
+
+    let actual_inputs = db.apply([Operation::WheelRotations(WheelRotations {
+        is_vertical: false,
+        rotation_units: 120,
+    })]);
+
+    assert!(actual_inputs.is_empty());
+}
@@ -0,0 +1,93 @@
+use ironrdp_input::keyboard_layout::{self, klid_from_locale};
+use ironrdp_input::Scancode;
+use rstest::rstest;
+
+#[rstest]
+#[case("Escape", Scancode::from_u8(false, 0x01))]
+#[case("KeyA", Scancode::from_u8(false, 0x1E))]
+#[case("Enter", Scancode::from_u8(false, 0x1C))]
+#[case("NumpadEnter", Scancode::from_u8(true, 0x1C))]
+#[case("ControlRight", Scancode::from_u8(true, 0x1D))]
+#[case("ArrowLeft", Scancode::from_u8(true, 0x4B))]
+#[case("IntlBackslash", Scancode::from_u8(false, 0x56))]
+#[case("MetaLeft", Scancode::from_u8(true, 0x5B))]
+#[case("OSLeft", Scancode::from_u8(true, 0x5B))]
+#[case("F24", Scancode::from_u8(false, 0x76))]
+fn web_code(#[case] code: &str, #[case] expected: Scancode) {
+    assert_eq!(Scancode::from_web_code(code), Some(expected));
+}
+
+#[test]
+fn unknown_web_code() {
+    assert_eq!(Scancode::from_web_code("Pause"), None);
+    assert_eq!(Scancode::from_web_code("keya"), None);
+    assert_eq!(Scancode::from_web_code(""), None);
+}
+
+#[rstest]
+#[case(1, Scancode::from_u8(false, 0x01))] // KEY_ESC
+#[case(30, Scancode::from_u8(false, 0x1E))] // KEY_A
+#[case(83, Scancode::from_u8(false, 0x53))] // KEY_KPDOT
+#[case(86, Scancode::from_u8(false, 0x56))] // KEY_102ND
+#[case(96, Scancode::from_u8(true, 0x1C))] // KEY_KPENTER
+#[case(100, Scancode::from_u8(true, 0x38))] // KEY_RIGHTALT
+#[case(105, Scancode::from_u8(true, 0x4B))] // KEY_LEFT
+#[case(125, Scancode::from_u8(true, 0x5B))] // KEY_LEFTMETA
+#[case(183, Scancode::from_u8(false, 0x64))] // KEY_F13
+#[case(193, Scancode::from_u8(false, 0x6E))] // KEY_F23
+fn evdev_keycode(#[case] keycode: u32, #[case] expected: Scancode) {
+    assert_eq!(Scancode::from_evdev_keycode(keycode), Some(expected));
+    assert_eq!(Scancode::from_x11_keycode(keycode + 8), Some(expected));
+}
+
+#[test]
+fn unknown_evdev_keycode() {
+    assert_eq!(Scancode::from_evdev_keycode(0), None);
+    assert_eq!(Scancode::from_evdev_keycode(119), None); // KEY_PAUSE
+    assert_eq!(Scancode::from_x11_keycode(3), None);
+}
+
+#[test]
+fn web_code_and_evdev_keycode_agree() {
+    let pairs = [
+        ("Backquote", 41),
+        ("ShiftRight", 54),
+        ("NumpadDivide", 98),
+        ("PrintScreen", 99),
+        ("Delete", 111),
+        ("ContextMenu", 127),
+        ("AudioVolumeUp", 115),
+        ("MediaPlayPause", 164),
+    ];
+
+    for (code, keycode) in pairs {
+        assert_eq!(
+            Scancode::from_web_code(code),
+            Scancode::from_evdev_keycode(keycode),
+            "{code}"
+        );
+    }
+}
+
+#[rstest]
+#[case("de-DE", keyboard_layout::GERMAN)]
+#[case("de_DE.UTF-8", keyboard_layout::GERMAN)]
+#[case("de_AT@euro", keyboard_layout::GERMAN)]
+#[case("de-CH", keyboard_layout::SWISS_GERMAN)]
+#[case("DE", keyboard_layout::GERMAN)]
+#[case("en-us", keyboard_layout::US)]
+#[case("en-GB", keyboard_layout::UNITED_KINGDOM)]
+#[case("en-ZA", keyboard_layout::US)]
+#[case("fr-CA", keyboard_layout::CANADIAN_FRENCH)]
+#[case("fr", keyboard_layout::FRENCH)]
+fn locale_to_klid(#[case] locale: &str, #[case] expected: u32) {
+    assert_eq!(klid_from_locale(locale), Some(expected));
+}
+
+#[test]
+fn unknown_locale() {
+    assert_eq!(klid_from_locale("C"), None);
+    assert_eq!(klid_from_locale("xx-XX"), None);
+    assert_eq!(klid_from_locale(""), None);
+    assert_eq!(klid_from_locale("_DE"), None);
+}
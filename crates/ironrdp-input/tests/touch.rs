@@ -0,0 +1,143 @@
+use ironrdp_input::touch::{TouchDatabase, TouchOperation, TouchPoint};
+use ironrdp_pdu::rdp::vc::dvc::rdpei::{ContactFlags, TouchFrame};
+
+const ENGAGED: ContactFlags = ContactFlags::UPDATE
+    .union(ContactFlags::IN_RANGE)
+    .union(ContactFlags::IN_CONTACT);
+const HOVERING: ContactFlags = ContactFlags::UPDATE.union(ContactFlags::IN_RANGE);
+const DOWN: ContactFlags = ContactFlags::DOWN
+    .union(ContactFlags::IN_RANGE)
+    .union(ContactFlags::IN_CONTACT);
+
+fn point(id: u32, x: i32, y: i32) -> TouchPoint {
+    TouchPoint {
+        id,
+        x,
+        y,
+        pressure: None,
+    }
+}
+
+/// Contact ID, position and flags of each contact, frame by frame
+fn summary(frames: &[TouchFrame]) -> Vec<Vec<(u8, i32, i32, ContactFlags)>> {
+    frames
+        .iter()
+        .map(|frame| {
+            frame
+                .contacts
+                .iter()
+                .map(|contact| (contact.contact_id, contact.x, contact.y, contact.flags))
+                .collect()
+        })
+        .collect()
+}
+
+#[test]
+fn tap() {
+    let mut db = TouchDatabase::new(10);
+
+    let frames = db.apply([TouchOperation::Down(point(7, 10, 20))]);
+    assert_eq!(summary(&frames), vec![vec![(0, 10, 20, DOWN)]]);
+
+    let frames = db.apply([TouchOperation::Move(point(7, 11, 21))]);
+    assert_eq!(summary(&frames), vec![vec![(0, 11, 21, ENGAGED)]]);
+
+    let frames = db.apply([TouchOperation::Up(7)]);
+    assert_eq!(summary(&frames), vec![vec![(0, 11, 21, ContactFlags::UP)]]);
+    assert_eq!(db.active_contacts(), 0);
+}
+
+#[test]
+fn hovering_contact() {
+    let mut db = TouchDatabase::new(10);
+
+    let frames = db.apply([
+        TouchOperation::Move(point(1, 10, 20)),
+        TouchOperation::Down(point(1, 10, 20)),
+        TouchOperation::Up(1),
+    ]);
+
+    assert_eq!(
+        summary(&frames),
+        vec![
+            vec![(0, 10, 20, HOVERING)],
+            vec![(0, 10, 20, DOWN)],
+            vec![(0, 10, 20, ContactFlags::UP)],
+        ]
+    );
+
+    let frames = db.apply([TouchOperation::Move(point(2, 0, 0)), TouchOperation::Cancel(2)]);
+
+    assert_eq!(
+        summary(&frames),
+        vec![
+            vec![(0, 0, 0, HOVERING)],
+            vec![(0, 0, 0, ContactFlags::UPDATE | ContactFlags::CANCELED)],
+        ]
+    );
+}
+
+#[test]
+fn frames_report_all_active_contacts() {
+    let mut db = TouchDatabase::new(10);
+
+    let frames = db.apply([
+        TouchOperation::Down(point(100, 1, 1)),
+        TouchOperation::Down(point(200, 2, 2)),
+    ]);
+    assert_eq!(summary(&frames), vec![vec![(0, 1, 1, DOWN), (1, 2, 2, DOWN)]]);
+
+    let frames = db.apply([TouchOperation::Move(point(200, 3, 3))]);
+    assert_eq!(summary(&frames), vec![vec![(0, 1, 1, ENGAGED), (1, 3, 3, ENGAGED)]]);
+
+    let frames = db.apply([TouchOperation::Up(100), TouchOperation::Down(point(300, 4, 4))]);
+    assert_eq!(
+        summary(&frames),
+        vec![vec![(0, 1, 1, ContactFlags::UP), (1, 3, 3, ENGAGED), (2, 4, 4, DOWN)]]
+    );
+
+    // The contact ID is free again once the frame reporting the lifted contact is sent
+    let frames = db.apply([TouchOperation::Down(point(400, 5, 5))]);
+    assert_eq!(
+        summary(&frames),
+        vec![vec![(0, 5, 5, DOWN), (1, 3, 3, ENGAGED), (2, 4, 4, ENGAGED)]]
+    );
+}
+
+#[test]
+fn ignored_operations() {
+    let mut db = TouchDatabase::new(1);
+
+    assert!(db.apply([TouchOperation::Up(1), TouchOperation::Cancel(1)]).is_empty());
+
+    db.apply([TouchOperation::Down(point(1, 0, 0))]);
+
+    // No state change
+    assert!(db.apply([TouchOperation::Move(point(1, 0, 0))]).is_empty());
+    assert!(db.apply([TouchOperation::Down(point(1, 0, 0))]).is_empty());
+
+    // Maximum number of contacts reached
+    assert!(db.apply([TouchOperation::Down(point(2, 0, 0))]).is_empty());
+    assert_eq!(db.active_contacts(), 1);
+}
+
+#[test]
+fn release_all() {
+    let mut db = TouchDatabase::new(10);
+
+    db.apply([
+        TouchOperation::Down(point(1, 1, 1)),
+        TouchOperation::Move(point(2, 2, 2)),
+    ]);
+
+    let frames = db.release_all();
+    assert_eq!(
+        summary(&frames),
+        vec![vec![
+            (0, 1, 1, ContactFlags::UP | ContactFlags::CANCELED),
+            (1, 2, 2, ContactFlags::UPDATE | ContactFlags::CANCELED),
+        ]]
+    );
+
+    assert!(db.release_all().is_empty());
+}
@@ -144,6 +144,29 @@ lazy_static::lazy_static! {
         client_info
     };
 
+    pub static ref CLIENT_INFO_UNICODE_WITH_RECONNECT_COOKIE: ClientInfo = {
+        let mut client_info = CLIENT_INFO_UNICODE.clone();
+        client_info.extra_info.optional_data.reconnect_cookie = Some(ClientAutoReconnectPacket {
+            logon_id: 0x0000_0002,
+            security_verifier: [
+                0x45, 0x2c, 0x4a, 0x76, 0x40, 0x3c, 0x5d, 0x4f, 0x8e, 0x0a, 0xdf, 0xb4, 0x61, 0x3a, 0x91, 0x0e,
+            ],
+        });
+        client_info
+    };
+
+    pub static ref CLIENT_INFO_BUFFER_UNICODE_WITH_RECONNECT_COOKIE: Vec<u8> = {
+        let mut buffer = CLIENT_INFO_BUFFER_UNICODE.to_vec();
+        buffer.extend_from_slice(&[
+            0x1c, 0x00, // reconnect cookie size
+            0x1c, 0x00, 0x00, 0x00, // cbLen
+            0x01, 0x00, 0x00, 0x00, // version
+            0x02, 0x00, 0x00, 0x00, // logon id
+            0x45, 0x2c, 0x4a, 0x76, 0x40, 0x3c, 0x5d, 0x4f, 0x8e, 0x0a, 0xdf, 0xb4, 0x61, 0x3a, 0x91, 0x0e, // security verifier
+        ]);
+        buffer
+    };
+
     pub static ref CLIENT_INFO_BUFFER_UNICODE_WITHOUT_OPTIONAL_FIELDS: Vec<u8> = {
         let mut buffer = CLIENT_INFO_BUFFER_UNICODE.to_vec();
         buffer.truncate(CLIENT_INFO_BUFFER_UNICODE_WITHOUT_OPTIONAL_FIELDS_LEN);
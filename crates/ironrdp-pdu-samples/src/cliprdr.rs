@@ -0,0 +1,109 @@
+use ironrdp_pdu::rdp::vc::cliprdr::*;
+
+pub const MONITOR_READY_BUFFER: [u8; 8] = [
+    0x01, 0x00, // msgType
+    0x00, 0x00, // msgFlags
+    0x00, 0x00, 0x00, 0x00, // dataLen
+];
+
+pub const CAPABILITIES_BUFFER: [u8; 24] = [
+    0x07, 0x00, // msgType
+    0x00, 0x00, // msgFlags
+    0x10, 0x00, 0x00, 0x00, // dataLen
+    0x01, 0x00, // cCapabilitiesSets
+    0x00, 0x00, // pad1
+    0x01, 0x00, // capabilitySetType
+    0x0c, 0x00, // lengthCapability
+    0x02, 0x00, 0x00, 0x00, // version
+    0x0e, 0x00, 0x00, 0x00, // generalFlags
+];
+
+pub const FORMAT_LIST_BUFFER: [u8; 42] = [
+    0x02, 0x00, // msgType
+    0x00, 0x00, // msgFlags
+    0x22, 0x00, 0x00, 0x00, // dataLen
+    0x0d, 0x00, 0x00, 0x00, // formatId
+    0x00, 0x00, // wszFormatName
+    0xbc, 0xc0, 0x00, 0x00, // formatId
+    0x48, 0x00, 0x54, 0x00, 0x4d, 0x00, 0x4c, 0x00, 0x20, 0x00, 0x46, 0x00, 0x6f, 0x00, 0x72, 0x00, 0x6d, 0x00, 0x61,
+    0x00, 0x74, 0x00, 0x00, 0x00, // wszFormatName
+];
+
+pub const FORMAT_LIST_RESPONSE_BUFFER: [u8; 8] = [
+    0x03, 0x00, // msgType
+    0x01, 0x00, // msgFlags
+    0x00, 0x00, 0x00, 0x00, // dataLen
+];
+
+pub const FORMAT_DATA_REQUEST_BUFFER: [u8; 12] = [
+    0x04, 0x00, // msgType
+    0x00, 0x00, // msgFlags
+    0x04, 0x00, 0x00, 0x00, // dataLen
+    0x0d, 0x00, 0x00, 0x00, // requestedFormatId
+];
+
+pub const FORMAT_DATA_RESPONSE_BUFFER: [u8; 14] = [
+    0x05, 0x00, // msgType
+    0x01, 0x00, // msgFlags
+    0x06, 0x00, 0x00, 0x00, // dataLen
+    0x68, 0x00, 0x69, 0x00, 0x00, 0x00, // requestedFormatData
+];
+
+pub const FILE_CONTENTS_REQUEST_BUFFER: [u8; 36] = [
+    0x08, 0x00, // msgType
+    0x00, 0x00, // msgFlags
+    0x1c, 0x00, 0x00, 0x00, // dataLen
+    0x02, 0x00, 0x00, 0x00, // streamId
+    0x01, 0x00, 0x00, 0x00, // lindex
+    0x02, 0x00, 0x00, 0x00, // dwFlags
+    0x00, 0x10, 0x00, 0x00, // nPositionLow
+    0x01, 0x00, 0x00, 0x00, // nPositionHigh
+    0x00, 0x00, 0x01, 0x00, // cbRequested
+    0x07, 0x00, 0x00, 0x00, // clipDataId
+];
+
+pub const FILE_CONTENTS_RESPONSE_BUFFER: [u8; 20] = [
+    0x09, 0x00, // msgType
+    0x01, 0x00, // msgFlags
+    0x0c, 0x00, 0x00, 0x00, // dataLen
+    0x02, 0x00, 0x00, 0x00, // streamId
+    0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, // requestedFileContentsData
+];
+
+pub const LOCK_DATA_BUFFER: [u8; 12] = [
+    0x0a, 0x00, // msgType
+    0x00, 0x00, // msgFlags
+    0x04, 0x00, 0x00, 0x00, // dataLen
+    0x07, 0x00, 0x00, 0x00, // clipDataId
+];
+
+lazy_static! {
+    pub static ref CAPABILITIES: ClipboardPdu = ClipboardPdu::Capabilities(Capabilities::new(
+        ClipboardProtocolVersion::V2,
+        ClipboardGeneralCapabilityFlags::USE_LONG_FORMAT_NAMES
+            | ClipboardGeneralCapabilityFlags::STREAM_FILECLIP_ENABLED
+            | ClipboardGeneralCapabilityFlags::FILECLIP_NO_FILE_PATHS,
+    ));
+    pub static ref FORMATS: Vec<ClipboardFormat> = vec![
+        ClipboardFormat::new(ClipboardFormatId::CF_UNICODETEXT),
+        ClipboardFormat::new(ClipboardFormatId(0xC0BC)).with_name(HTML_FORMAT_NAME),
+    ];
+    pub static ref FORMAT_LIST: ClipboardPdu = ClipboardPdu::FormatList(FormatList::new(&FORMATS, true));
+    pub static ref FORMAT_LIST_RESPONSE: ClipboardPdu = ClipboardPdu::FormatListResponse(FormatListResponse::Ok);
+    pub static ref FORMAT_DATA_REQUEST: ClipboardPdu = ClipboardPdu::FormatDataRequest(FormatDataRequest {
+        format: ClipboardFormatId::CF_UNICODETEXT,
+    });
+    pub static ref FORMAT_DATA_RESPONSE: ClipboardPdu =
+        ClipboardPdu::FormatDataResponse(FormatDataResponse::new_unicode_string("hi"));
+    pub static ref FILE_CONTENTS_REQUEST: ClipboardPdu = ClipboardPdu::FileContentsRequest(FileContentsRequest {
+        stream_id: 2,
+        index: 1,
+        flags: FileContentsFlags::RANGE,
+        position: 0x0000_0001_0000_1000,
+        requested_size: 0x0001_0000,
+        data_id: Some(7),
+    });
+    pub static ref FILE_CONTENTS_RESPONSE: ClipboardPdu =
+        ClipboardPdu::FileContentsResponse(FileContentsResponse::new_size(2, 0x0001_0000));
+    pub static ref LOCK_DATA: ClipboardPdu = ClipboardPdu::LockData(LockDataId(7));
+}
@@ -8,6 +8,7 @@ mod macros;
 
 pub mod capsets;
 pub mod client_info;
+pub mod cliprdr;
 pub mod cluster_data;
 pub mod conference_create;
 pub mod core_data;
@@ -21,6 +22,7 @@ pub mod monitor_extended_data;
 pub mod multi_transport_channel_data;
 pub mod network_data;
 pub mod rdp;
+pub mod rdpsnd;
 pub mod security_data;
 
 #[doc(hidden)]
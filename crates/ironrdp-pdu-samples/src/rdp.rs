@@ -57,6 +57,20 @@ pub const CLIENT_SYNCHRONIZE_BUFFER: [u8; 22] = [
     0xea, 0x03, // target user
 ];
 
+pub const SERVER_COMPRESSED_SAVE_SESSION_INFO_BUFFER: [u8; 22] = [
+    0x16, 0x00, // ShareControlHeader::totalLength
+    0x17, 0x00, // ShareControlHeader::pduType
+    0xea, 0x03, // ShareControlHeader::PduSource
+    0xea, 0x03, 0x01, 0x00, // share id
+    0x00, // padding
+    0x01, // stream id
+    0x0c, 0x00, // uncompressed length
+    0x26, // pdu type
+    0x21, // compression type
+    0x08, 0x00, // compressed length
+    0x34, 0x72, 0x00, 0x00, // compressed data
+];
+
 pub const CONTROL_COOPERATE_BUFFER: [u8; 26] = [
     0x1a, 0x00, // ShareControlHeader::totalLength
     0x17, 0x00, // ShareControlHeader::pduType
@@ -122,6 +136,15 @@ pub const CLIENT_FONT_LIST_BUFFER: [u8; 26] = [
     0x32, 0x00, // entry size
 ];
 
+pub const SERVER_DEACTIVATE_ALL_BUFFER: [u8; 13] = [
+    0x0d, 0x00, // ShareControlHeader::totalLength
+    0x16, 0x00, // ShareControlHeader::pduType
+    0xea, 0x03, // ShareControlHeader::PduSource
+    0xea, 0x03, 0x01, 0x00, // share id
+    0x01, 0x00, // length source descriptor
+    0x00, // source descriptor
+];
+
 pub const SERVER_FONT_MAP_BUFFER: [u8; 26] = [
     0x1a, 0x00, // ShareControlHeader::totalLength
     0x17, 0x00, // ShareControlHeader::pduType
@@ -188,6 +211,20 @@ lazy_static! {
         pdu_source: 1007,
         share_id: 66_538,
     };
+    pub static ref SERVER_COMPRESSED_SAVE_SESSION_INFO: ShareControlHeader = ShareControlHeader {
+        share_control_pdu: ShareControlPdu::Data(ShareDataHeader {
+            share_data_pdu: ShareDataPdu::Compressed(CompressedShareDataPdu {
+                pdu_type: ShareDataPduType::SaveSessionInfo,
+                uncompressed_length: 12,
+                data: vec![0x34, 0x72, 0x00, 0x00],
+            }),
+            stream_priority: StreamPriority::Low,
+            compression_flags: CompressionFlags::COMPRESSED,
+            compression_type: client_info::CompressionType::K64,
+        }),
+        pdu_source: 1002,
+        share_id: 66_538,
+    };
     pub static ref CONTROL_COOPERATE: ShareControlHeader = ShareControlHeader {
         share_control_pdu: ShareControlPdu::Data(ShareDataHeader {
             share_data_pdu: ShareDataPdu::Control(ControlPdu {
@@ -260,6 +297,11 @@ lazy_static! {
         pdu_source: 1002,
         share_id: 66_538,
     };
+    pub static ref SERVER_DEACTIVATE_ALL: ShareControlHeader = ShareControlHeader {
+        share_control_pdu: ShareControlPdu::ServerDeactivateAll(capability_sets::ServerDeactivateAll),
+        pdu_source: 1002,
+        share_id: 66_538,
+    };
     pub static ref MONITOR_LAYOUT_PDU: ShareControlHeader = ShareControlHeader {
         share_control_pdu: ShareControlPdu::Data(ShareDataHeader {
             share_data_pdu: ShareDataPdu::MonitorLayout(MonitorLayoutPdu {
@@ -0,0 +1,190 @@
+use ironrdp_pdu::rdp::vc::rdpsnd::*;
+
+pub const SERVER_FORMATS_BUFFER: [u8; 62] = [
+    0x07, 0x00, // msgType, bPad
+    0x3a, 0x00, // BodySize
+    0x00, 0x00, 0x00, 0x00, // dwFlags
+    0x00, 0x00, 0x00, 0x00, // dwVolume
+    0x00, 0x00, 0x00, 0x00, // dwPitch
+    0x00, 0x00, // wDGramPort
+    0x02, 0x00, // wNumberOfFormats
+    0x00, // cLastBlockConfirmed
+    0x08, 0x00, // wVersion
+    0x00, // bPad
+    0x01, 0x00, // wFormatTag
+    0x02, 0x00, // nChannels
+    0x44, 0xac, 0x00, 0x00, // nSamplesPerSec
+    0x10, 0xb1, 0x02, 0x00, // nAvgBytesPerSec
+    0x04, 0x00, // nBlockAlign
+    0x10, 0x00, // wBitsPerSample
+    0x00, 0x00, // cbSize
+    0x11, 0x00, // wFormatTag
+    0x02, 0x00, // nChannels
+    0x22, 0x56, 0x00, 0x00, // nSamplesPerSec
+    0x27, 0x57, 0x00, 0x00, // nAvgBytesPerSec
+    0x00, 0x08, // nBlockAlign
+    0x04, 0x00, // wBitsPerSample
+    0x02, 0x00, // cbSize
+    0xf9, 0x07, // data
+];
+
+pub const CLIENT_FORMATS_BUFFER: [u8; 42] = [
+    0x07, 0x00, // msgType, bPad
+    0x26, 0x00, // BodySize
+    0x03, 0x00, 0x00, 0x00, // dwFlags
+    0xff, 0xff, 0xff, 0xff, // dwVolume
+    0x00, 0x00, 0x00, 0x00, // dwPitch
+    0x00, 0x00, // wDGramPort
+    0x01, 0x00, // wNumberOfFormats
+    0x00, // cLastBlockConfirmed
+    0x08, 0x00, // wVersion
+    0x00, // bPad
+    0x01, 0x00, // wFormatTag
+    0x02, 0x00, // nChannels
+    0x44, 0xac, 0x00, 0x00, // nSamplesPerSec
+    0x10, 0xb1, 0x02, 0x00, // nAvgBytesPerSec
+    0x04, 0x00, // nBlockAlign
+    0x10, 0x00, // wBitsPerSample
+    0x00, 0x00, // cbSize
+];
+
+pub const TRAINING_BUFFER: [u8; 8] = [
+    0x06, 0x00, // msgType, bPad
+    0x04, 0x00, // BodySize
+    0x34, 0x12, // wTimeStamp
+    0x00, 0x00, // wPackSize
+];
+
+pub const TRAINING_CONFIRM_BUFFER: [u8; 8] = [
+    0x06, 0x00, // msgType, bPad
+    0x04, 0x00, // BodySize
+    0x34, 0x12, // wTimeStamp
+    0x00, 0x00, // wPackSize
+];
+
+pub const WAVE_INFO_BUFFER: [u8; 16] = [
+    0x02, 0x00, // msgType, bPad
+    0x10, 0x00, // BodySize
+    0x78, 0x56, // wTimeStamp
+    0x00, 0x00, // wFormatNo
+    0x03, // cBlockNo
+    0x00, 0x00, 0x00, // bPad
+    0x01, 0x02, 0x03, 0x04, // Data
+];
+
+pub const WAVE_BUFFER: [u8; 8] = [
+    0x00, 0x00, 0x00, 0x00, // bPad
+    0x05, 0x06, 0x07, 0x08, // data
+];
+
+pub const WAVE2_BUFFER: [u8; 20] = [
+    0x0d, 0x00, // msgType, bPad
+    0x10, 0x00, // BodySize
+    0x78, 0x56, // wTimeStamp
+    0x01, 0x00, // wFormatNo
+    0x04, // cBlockNo
+    0x00, 0x00, 0x00, // bPad
+    0x00, 0x10, 0x00, 0x00, // dwAudioTimeStamp
+    0x01, 0x02, 0x03, 0x04, // Data
+];
+
+pub const WAVE_CONFIRM_BUFFER: [u8; 8] = [
+    0x05, 0x00, // msgType, bPad
+    0x04, 0x00, // BodySize
+    0x78, 0x56, // wTimeStamp
+    0x03, // cConfirmedBlockNo
+    0x00, // bPad
+];
+
+pub const VOLUME_BUFFER: [u8; 8] = [
+    0x03, 0x00, // msgType, bPad
+    0x04, 0x00, // BodySize
+    0xff, 0xff, 0x00, 0x80, // dwVolume
+];
+
+pub const QUALITY_MODE_BUFFER: [u8; 8] = [
+    0x0c, 0x00, // msgType, bPad
+    0x04, 0x00, // BodySize
+    0x02, 0x00, // wQualityMode
+    0x00, 0x00, // Reserved
+];
+
+pub const CLOSE_BUFFER: [u8; 4] = [
+    0x01, 0x00, // msgType, bPad
+    0x00, 0x00, // BodySize
+];
+
+lazy_static! {
+    pub static ref PCM_FORMAT: AudioFormat = AudioFormat {
+        format: WaveFormat::PCM,
+        n_channels: 2,
+        n_samples_per_sec: 44100,
+        n_avg_bytes_per_sec: 176_400,
+        n_block_align: 4,
+        bits_per_sample: 16,
+        data: Vec::new(),
+    };
+    pub static ref SERVER_FORMATS: ServerAudioOutputPdu = ServerAudioOutputPdu::Formats(AudioFormatPdu {
+        flags: AudioFormatFlags::empty(),
+        volume: 0,
+        pitch: 0,
+        dgram_port: 0,
+        last_block_confirmed: 0,
+        version: AudioVersion::V8,
+        formats: vec![
+            PCM_FORMAT.clone(),
+            AudioFormat {
+                format: WaveFormat::DVI_ADPCM,
+                n_channels: 2,
+                n_samples_per_sec: 22050,
+                n_avg_bytes_per_sec: 22311,
+                n_block_align: 2048,
+                bits_per_sample: 4,
+                data: vec![0xf9, 0x07],
+            },
+        ],
+    });
+    pub static ref CLIENT_FORMATS: ClientAudioOutputPdu = ClientAudioOutputPdu::Formats(AudioFormatPdu {
+        flags: AudioFormatFlags::ALIVE | AudioFormatFlags::VOLUME,
+        volume: 0xFFFF_FFFF,
+        pitch: 0,
+        dgram_port: 0,
+        last_block_confirmed: 0,
+        version: AudioVersion::V8,
+        formats: vec![PCM_FORMAT.clone()],
+    });
+    pub static ref TRAINING: ServerAudioOutputPdu = ServerAudioOutputPdu::Training(TrainingPdu {
+        timestamp: 0x1234,
+        pack_size: 0,
+        data: Vec::new(),
+    });
+    pub static ref TRAINING_CONFIRM: ClientAudioOutputPdu = ClientAudioOutputPdu::TrainingConfirm(TrainingConfirmPdu {
+        timestamp: 0x1234,
+        pack_size: 0,
+    });
+    pub static ref WAVE_INFO: ServerAudioOutputPdu = ServerAudioOutputPdu::WaveInfo(WaveInfoPdu {
+        timestamp: 0x5678,
+        format_no: 0,
+        block_no: 3,
+        initial_data: [0x01, 0x02, 0x03, 0x04],
+        data_size: 8,
+    });
+    pub static ref WAVE2: ServerAudioOutputPdu = ServerAudioOutputPdu::Wave2(Wave2Pdu {
+        timestamp: 0x5678,
+        format_no: 1,
+        block_no: 4,
+        audio_timestamp: 0x1000,
+        data: vec![0x01, 0x02, 0x03, 0x04],
+    });
+    pub static ref WAVE_CONFIRM: ClientAudioOutputPdu = ClientAudioOutputPdu::WaveConfirm(WaveConfirmPdu {
+        timestamp: 0x5678,
+        confirmed_block_no: 3,
+    });
+    pub static ref VOLUME: ServerAudioOutputPdu = ServerAudioOutputPdu::Volume(VolumePdu {
+        volume_left: 0xFFFF,
+        volume_right: 0x8000,
+    });
+    pub static ref QUALITY_MODE: ClientAudioOutputPdu = ClientAudioOutputPdu::QualityMode(QualityModePdu {
+        quality_mode: QualityMode::High,
+    });
+}
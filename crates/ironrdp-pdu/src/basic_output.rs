@@ -1,3 +1,5 @@
 pub mod bitmap;
 pub mod fast_path;
+pub mod palette;
+pub mod pointer;
 pub mod surface_commands;
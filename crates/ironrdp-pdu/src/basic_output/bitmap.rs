@@ -15,9 +15,12 @@ use crate::utils::SplitTo;
 use crate::{PduBufferParsing, PduParsing};
 
 pub const COMPRESSED_DATA_HEADER_SIZE: usize = 8;
-pub const BITMAP_DATA_MAIN_DATA_SIZE: usize = 12;
+pub const BITMAP_DATA_MAIN_DATA_SIZE: usize = 18;
 pub const FIRST_ROW_SIZE_VALUE: u16 = 0;
 
+/// updateType and numberRectangles fields
+const BITMAP_UPDATE_HEADER_SIZE: usize = 4;
+
 /// TS_UPDATE_BITMAP_DATA
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct BitmapUpdateData<'a> {
@@ -54,7 +57,7 @@ impl<'a> PduBufferParsing<'a> for BitmapUpdateData<'a> {
     }
 
     fn buffer_length(&self) -> usize {
-        self.rectangles.iter().map(|b| b.buffer_length()).sum::<usize>()
+        BITMAP_UPDATE_HEADER_SIZE + self.rectangles.iter().map(|b| b.buffer_length()).sum::<usize>()
     }
 }
 
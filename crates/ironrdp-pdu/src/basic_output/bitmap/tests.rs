@@ -71,6 +71,11 @@ fn to_buffer_bitmap_data_serializes_correctly() {
     assert_eq!(expected, buffer.as_slice());
 }
 
+#[test]
+fn buffer_length_is_correct_for_bitmap_data() {
+    assert_eq!(BITMAP_BUFFER.len(), BITMAP.buffer_length());
+}
+
 #[test]
 fn bitmap_data_length_is_correct() {
     let actual = BitmapUpdateData::from_buffer(BITMAP_BUFFER.as_ref()).unwrap();
@@ -11,6 +11,11 @@ use num_traits::{FromPrimitive, ToPrimitive};
 use thiserror::Error;
 
 use super::bitmap::{BitmapError, BitmapUpdateData};
+use super::palette::{PaletteError, PaletteUpdateData};
+use super::pointer::{
+    CachedPointerAttribute, ColorPointerAttribute, LargePointerAttribute, Point16, PointerAttribute, PointerError,
+    PointerUpdateData,
+};
 use super::surface_commands::{SurfaceCommand, SurfaceCommandsError, SURFACE_COMMAND_HEADER_SIZE};
 use crate::rdp::client_info::CompressionType;
 use crate::rdp::headers::{CompressionFlags, SHARE_DATA_HEADER_COMPRESSION_MASK};
@@ -26,6 +31,14 @@ pub struct FastPathHeader {
 }
 
 impl FastPathHeader {
+    pub fn new(flags: EncryptionFlags, data_length: usize) -> Self {
+        Self {
+            flags,
+            data_length,
+            forced_long_length: false,
+        }
+    }
+
     fn minimal_buffer_length(&self) -> usize {
         1 + per::sizeof_length(self.data_length as u16)
     }
@@ -163,6 +176,8 @@ impl<'a> PduBufferParsing<'a> for FastPathUpdatePdu<'a> {
 pub enum FastPathUpdate<'a> {
     SurfaceCommands(Vec<SurfaceCommand<'a>>),
     Bitmap(BitmapUpdateData<'a>),
+    Pointer(PointerUpdateData<'a>),
+    Palette(PaletteUpdateData),
 }
 
 impl<'a> FastPathUpdate<'a> {
@@ -184,6 +199,32 @@ impl<'a> FastPathUpdate<'a> {
                 let bitmap = BitmapUpdateData::from_buffer_consume(buffer).map_err(FastPathError::BitmapError)?;
                 Ok(Self::Bitmap(bitmap))
             }
+            UpdateCode::Palette => {
+                let palette = PaletteUpdateData::from_buffer_consume(buffer)?;
+                Ok(Self::Palette(palette))
+            }
+            UpdateCode::HiddenPointer => Ok(Self::Pointer(PointerUpdateData::SetHidden)),
+            UpdateCode::DefaultPointer => Ok(Self::Pointer(PointerUpdateData::SetDefault)),
+            UpdateCode::PositionPointer => {
+                let position = Point16::from_buffer_consume(buffer)?;
+                Ok(Self::Pointer(PointerUpdateData::SetPosition(position)))
+            }
+            UpdateCode::ColorPointer => {
+                let pointer = ColorPointerAttribute::from_buffer_consume(buffer)?;
+                Ok(Self::Pointer(PointerUpdateData::Color(pointer)))
+            }
+            UpdateCode::CachedPointer => {
+                let pointer = CachedPointerAttribute::from_buffer_consume(buffer)?;
+                Ok(Self::Pointer(PointerUpdateData::Cached(pointer)))
+            }
+            UpdateCode::NewPointer => {
+                let pointer = PointerAttribute::from_buffer_consume(buffer)?;
+                Ok(Self::Pointer(PointerUpdateData::New(pointer)))
+            }
+            UpdateCode::LargePointer => {
+                let pointer = LargePointerAttribute::from_buffer_consume(buffer)?;
+                Ok(Self::Pointer(PointerUpdateData::Large(pointer)))
+            }
             _ => Err(FastPathError::UnsupportedFastPathUpdate(code)),
         }
     }
@@ -198,6 +239,12 @@ impl<'a> FastPathUpdate<'a> {
             Self::Bitmap(ref bitmap) => {
                 bitmap.to_buffer_consume(buffer)?;
             }
+            Self::Pointer(ref pointer) => {
+                pointer.to_buffer_consume(buffer)?;
+            }
+            Self::Palette(ref palette) => {
+                palette.to_buffer_consume(buffer)?;
+            }
         }
 
         Ok(())
@@ -207,6 +254,8 @@ impl<'a> FastPathUpdate<'a> {
         match self {
             Self::SurfaceCommands(commands) => commands.iter().map(|c| c.buffer_length()).sum::<usize>(),
             Self::Bitmap(bitmap) => bitmap.buffer_length(),
+            Self::Pointer(pointer) => pointer.buffer_length(),
+            Self::Palette(palette) => palette.buffer_length(),
         }
     }
 
@@ -214,6 +263,8 @@ impl<'a> FastPathUpdate<'a> {
         match self {
             Self::SurfaceCommands(_) => "Surface Commands",
             Self::Bitmap(_) => "Bitmap",
+            Self::Pointer(_) => "Pointer",
+            Self::Palette(_) => "Palette",
         }
     }
 }
@@ -239,6 +290,16 @@ impl<'a> From<&FastPathUpdate<'a>> for UpdateCode {
         match update {
             FastPathUpdate::SurfaceCommands(_) => Self::SurfaceCommands,
             FastPathUpdate::Bitmap(_) => Self::Bitmap,
+            FastPathUpdate::Pointer(pointer) => match pointer {
+                PointerUpdateData::SetHidden => Self::HiddenPointer,
+                PointerUpdateData::SetDefault => Self::DefaultPointer,
+                PointerUpdateData::SetPosition(_) => Self::PositionPointer,
+                PointerUpdateData::Color(_) => Self::ColorPointer,
+                PointerUpdateData::Cached(_) => Self::CachedPointer,
+                PointerUpdateData::New(_) => Self::NewPointer,
+                PointerUpdateData::Large(_) => Self::LargePointer,
+            },
+            FastPathUpdate::Palette(_) => Self::Palette,
         }
     }
 }
@@ -276,6 +337,10 @@ pub enum FastPathError {
     SurfaceCommandsError(#[from] SurfaceCommandsError),
     #[error("Bitmap error: {0}")]
     BitmapError(#[from] BitmapError),
+    #[error("Pointer error: {0}")]
+    PointerError(#[from] PointerError),
+    #[error("Palette error: {0}")]
+    PaletteError(#[from] PaletteError),
     /// Used in the length-related error during Fast-Path parsing.
     #[error("Received invalid Fast-Path package with 0 length")]
     NullLength { bytes_read: usize },
@@ -0,0 +1,108 @@
+#[cfg(test)]
+mod tests;
+
+use std::io::{self, Write};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use thiserror::Error;
+
+use crate::PduBufferParsing;
+
+const UPDATE_TYPE_PALETTE: u16 = 0x0002;
+const PALETTE_UPDATE_HEADER_SIZE: usize = 2 + 2 + 4;
+const PALETTE_ENTRY_SIZE: usize = 3;
+
+/// Maximum number of colors in a palette (8 bpp)
+pub const MAX_PALETTE_COLORS: usize = 256;
+
+/// TS_UPDATE_PALETTE_DATA
+///
+/// Color table used to interpret 8 bpp bitmaps.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaletteUpdateData {
+    pub entries: Vec<PaletteEntry>,
+}
+
+impl<'a> PduBufferParsing<'a> for PaletteUpdateData {
+    type Error = PaletteError;
+
+    fn from_buffer_consume(buffer: &mut &'a [u8]) -> Result<Self, Self::Error> {
+        let update_type = buffer.read_u16::<LittleEndian>()?;
+        if update_type != UPDATE_TYPE_PALETTE {
+            return Err(PaletteError::InvalidUpdateType(update_type));
+        }
+
+        let _padding = buffer.read_u16::<LittleEndian>()?;
+
+        let number_colors = buffer.read_u32::<LittleEndian>()? as usize;
+        if number_colors > MAX_PALETTE_COLORS {
+            return Err(PaletteError::TooManyColors(number_colors));
+        }
+
+        let entries = (0..number_colors)
+            .map(|_| PaletteEntry::from_buffer_consume(buffer))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self { entries })
+    }
+
+    fn to_buffer_consume(&self, buffer: &mut &mut [u8]) -> Result<(), Self::Error> {
+        if self.entries.len() > MAX_PALETTE_COLORS {
+            return Err(PaletteError::TooManyColors(self.entries.len()));
+        }
+
+        buffer.write_u16::<LittleEndian>(UPDATE_TYPE_PALETTE)?;
+        buffer.write_u16::<LittleEndian>(0)?; // padding
+        buffer.write_u32::<LittleEndian>(self.entries.len() as u32)?;
+
+        for entry in self.entries.iter() {
+            entry.to_buffer_consume(buffer)?;
+        }
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        PALETTE_UPDATE_HEADER_SIZE + self.entries.len() * PALETTE_ENTRY_SIZE
+    }
+}
+
+/// TS_PALETTE_ENTRY
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct PaletteEntry {
+    pub red: u8,
+    pub green: u8,
+    pub blue: u8,
+}
+
+impl<'a> PduBufferParsing<'a> for PaletteEntry {
+    type Error = PaletteError;
+
+    fn from_buffer_consume(buffer: &mut &'a [u8]) -> Result<Self, Self::Error> {
+        let red = buffer.read_u8()?;
+        let green = buffer.read_u8()?;
+        let blue = buffer.read_u8()?;
+
+        Ok(Self { red, green, blue })
+    }
+
+    fn to_buffer_consume(&self, buffer: &mut &mut [u8]) -> Result<(), Self::Error> {
+        buffer.write_all(&[self.red, self.green, self.blue])?;
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        PALETTE_ENTRY_SIZE
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum PaletteError {
+    #[error("IO error")]
+    IOError(#[from] io::Error),
+    #[error("Invalid palette update type: {0}")]
+    InvalidUpdateType(u16),
+    #[error("Too many palette colors: {0}")]
+    TooManyColors(usize),
+}
@@ -0,0 +1,84 @@
+use super::*;
+use crate::fast_path::{FastPathUpdate, UpdateCode};
+
+const PALETTE_BUFFER: [u8; 17] = [
+    0x02, 0x00, // updateType
+    0x00, 0x00, // pad2Octets
+    0x03, 0x00, 0x00, 0x00, // numberColors
+    0x00, 0x00, 0x00, // black
+    0xff, 0x00, 0x00, // red
+    0x10, 0x20, 0x30, // arbitrary color
+];
+
+fn palette() -> PaletteUpdateData {
+    PaletteUpdateData {
+        entries: vec![
+            PaletteEntry {
+                red: 0x00,
+                green: 0x00,
+                blue: 0x00,
+            },
+            PaletteEntry {
+                red: 0xff,
+                green: 0x00,
+                blue: 0x00,
+            },
+            PaletteEntry {
+                red: 0x10,
+                green: 0x20,
+                blue: 0x30,
+            },
+        ],
+    }
+}
+
+#[test]
+fn from_buffer_correctly_parses_palette_update() {
+    assert_eq!(
+        palette(),
+        PaletteUpdateData::from_buffer(PALETTE_BUFFER.as_ref()).unwrap()
+    );
+}
+
+#[test]
+fn to_buffer_correctly_serializes_palette_update() {
+    let palette = palette();
+    let mut buffer = vec![0; palette.buffer_length()];
+    palette.to_buffer_consume(&mut buffer.as_mut_slice()).unwrap();
+
+    assert_eq!(PALETTE_BUFFER.as_ref(), buffer.as_slice());
+}
+
+#[test]
+fn buffer_length_is_correct_for_palette_update() {
+    assert_eq!(PALETTE_BUFFER.len(), palette().buffer_length());
+}
+
+#[test]
+fn fast_path_update_dispatches_palette_code() {
+    let update = FastPathUpdate::from_buffer_with_code(PALETTE_BUFFER.as_ref(), UpdateCode::Palette).unwrap();
+
+    assert_eq!(FastPathUpdate::Palette(palette()), update);
+    assert_eq!(UpdateCode::Palette, UpdateCode::from(&update));
+}
+
+#[test]
+fn from_buffer_rejects_invalid_update_type() {
+    let mut buffer = PALETTE_BUFFER;
+    buffer[0] = 0x01;
+
+    assert!(matches!(
+        PaletteUpdateData::from_buffer(buffer.as_ref()),
+        Err(PaletteError::InvalidUpdateType(0x0001))
+    ));
+}
+
+#[test]
+fn from_buffer_rejects_too_many_colors() {
+    let buffer = [0x02, 0x00, 0x00, 0x00, 0x01, 0x01, 0x00, 0x00];
+
+    assert!(matches!(
+        PaletteUpdateData::from_buffer(buffer.as_ref()),
+        Err(PaletteError::TooManyColors(257))
+    ));
+}
@@ -0,0 +1,320 @@
+#[cfg(test)]
+mod tests;
+
+use std::fmt;
+use std::io::{self, Write};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use thiserror::Error;
+
+use crate::utils::SplitTo;
+use crate::PduBufferParsing;
+
+const POINT16_SIZE: usize = 4;
+const COLOR_POINTER_ATTRIBUTE_HEADER_SIZE: usize = 2 + POINT16_SIZE + 8;
+const LARGE_POINTER_ATTRIBUTE_HEADER_SIZE: usize = 4 + POINT16_SIZE + 12;
+
+/// Fast-Path pointer update, identified by the update code of the Fast-Path Update PDU
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PointerUpdateData<'a> {
+    /// TS_FP_SYSTEMPOINTERHIDDENATTRIBUTE
+    SetHidden,
+    /// TS_FP_SYSTEMPOINTERDEFAULTATTRIBUTE
+    SetDefault,
+    /// TS_FP_POINTERPOSATTRIBUTE
+    SetPosition(Point16),
+    /// TS_FP_COLORPOINTERATTRIBUTE
+    Color(ColorPointerAttribute<'a>),
+    /// TS_FP_CACHEDPOINTERATTRIBUTE
+    Cached(CachedPointerAttribute),
+    /// TS_FP_POINTERATTRIBUTE
+    New(PointerAttribute<'a>),
+    /// TS_FP_LARGEPOINTERATTRIBUTE
+    Large(LargePointerAttribute<'a>),
+}
+
+impl PointerUpdateData<'_> {
+    pub fn to_buffer_consume(&self, buffer: &mut &mut [u8]) -> Result<(), PointerError> {
+        match self {
+            Self::SetHidden | Self::SetDefault => Ok(()),
+            Self::SetPosition(position) => position.to_buffer_consume(buffer),
+            Self::Color(pointer) => pointer.to_buffer_consume(buffer),
+            Self::Cached(pointer) => pointer.to_buffer_consume(buffer),
+            Self::New(pointer) => pointer.to_buffer_consume(buffer),
+            Self::Large(pointer) => pointer.to_buffer_consume(buffer),
+        }
+    }
+
+    pub fn buffer_length(&self) -> usize {
+        match self {
+            Self::SetHidden | Self::SetDefault => 0,
+            Self::SetPosition(position) => position.buffer_length(),
+            Self::Color(pointer) => pointer.buffer_length(),
+            Self::Cached(pointer) => pointer.buffer_length(),
+            Self::New(pointer) => pointer.buffer_length(),
+            Self::Large(pointer) => pointer.buffer_length(),
+        }
+    }
+}
+
+/// TS_POINT16
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct Point16 {
+    pub x: u16,
+    pub y: u16,
+}
+
+impl<'a> PduBufferParsing<'a> for Point16 {
+    type Error = PointerError;
+
+    fn from_buffer_consume(buffer: &mut &'a [u8]) -> Result<Self, Self::Error> {
+        let x = buffer.read_u16::<LittleEndian>()?;
+        let y = buffer.read_u16::<LittleEndian>()?;
+
+        Ok(Self { x, y })
+    }
+
+    fn to_buffer_consume(&self, buffer: &mut &mut [u8]) -> Result<(), Self::Error> {
+        buffer.write_u16::<LittleEndian>(self.x)?;
+        buffer.write_u16::<LittleEndian>(self.y)?;
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        POINT16_SIZE
+    }
+}
+
+/// TS_COLORPOINTERATTRIBUTE
+///
+/// Both masks are stored bottom-up, with each scan line padded to a multiple of 2 bytes.
+/// The XOR mask has 24 bits per pixel, unless wrapped in a [`PointerAttribute`].
+#[derive(Clone, PartialEq, Eq)]
+pub struct ColorPointerAttribute<'a> {
+    pub cache_index: u16,
+    pub hot_spot: Point16,
+    pub width: u16,
+    pub height: u16,
+    pub xor_mask: &'a [u8],
+    /// 1 bit per pixel
+    pub and_mask: &'a [u8],
+}
+
+impl<'a> PduBufferParsing<'a> for ColorPointerAttribute<'a> {
+    type Error = PointerError;
+
+    fn from_buffer_consume(buffer: &mut &'a [u8]) -> Result<Self, Self::Error> {
+        let cache_index = buffer.read_u16::<LittleEndian>()?;
+        let hot_spot = Point16::from_buffer_consume(buffer)?;
+        let width = buffer.read_u16::<LittleEndian>()?;
+        let height = buffer.read_u16::<LittleEndian>()?;
+        let and_mask_length = usize::from(buffer.read_u16::<LittleEndian>()?);
+        let xor_mask_length = usize::from(buffer.read_u16::<LittleEndian>()?);
+
+        let (xor_mask, and_mask) = read_masks(buffer, xor_mask_length, and_mask_length)?;
+
+        Ok(Self {
+            cache_index,
+            hot_spot,
+            width,
+            height,
+            xor_mask,
+            and_mask,
+        })
+    }
+
+    fn to_buffer_consume(&self, buffer: &mut &mut [u8]) -> Result<(), Self::Error> {
+        buffer.write_u16::<LittleEndian>(self.cache_index)?;
+        self.hot_spot.to_buffer_consume(buffer)?;
+        buffer.write_u16::<LittleEndian>(self.width)?;
+        buffer.write_u16::<LittleEndian>(self.height)?;
+        buffer.write_u16::<LittleEndian>(
+            u16::try_from(self.and_mask.len()).map_err(|_| PointerError::MaskTooLarge(self.and_mask.len()))?,
+        )?;
+        buffer.write_u16::<LittleEndian>(
+            u16::try_from(self.xor_mask.len()).map_err(|_| PointerError::MaskTooLarge(self.xor_mask.len()))?,
+        )?;
+        buffer.write_all(self.xor_mask)?;
+        buffer.write_all(self.and_mask)?;
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        COLOR_POINTER_ATTRIBUTE_HEADER_SIZE + self.xor_mask.len() + self.and_mask.len()
+    }
+}
+
+impl fmt::Debug for ColorPointerAttribute<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ColorPointerAttribute")
+            .field("cache_index", &self.cache_index)
+            .field("hot_spot", &self.hot_spot)
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("xor_mask.len()", &self.xor_mask.len())
+            .field("and_mask.len()", &self.and_mask.len())
+            .finish()
+    }
+}
+
+/// TS_POINTERATTRIBUTE, a color pointer with an arbitrary color depth
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PointerAttribute<'a> {
+    /// Color depth of the XOR mask
+    pub xor_bpp: u16,
+    pub color_pointer: ColorPointerAttribute<'a>,
+}
+
+impl<'a> PduBufferParsing<'a> for PointerAttribute<'a> {
+    type Error = PointerError;
+
+    fn from_buffer_consume(buffer: &mut &'a [u8]) -> Result<Self, Self::Error> {
+        let xor_bpp = buffer.read_u16::<LittleEndian>()?;
+        let color_pointer = ColorPointerAttribute::from_buffer_consume(buffer)?;
+
+        Ok(Self { xor_bpp, color_pointer })
+    }
+
+    fn to_buffer_consume(&self, buffer: &mut &mut [u8]) -> Result<(), Self::Error> {
+        buffer.write_u16::<LittleEndian>(self.xor_bpp)?;
+        self.color_pointer.to_buffer_consume(buffer)
+    }
+
+    fn buffer_length(&self) -> usize {
+        2 + self.color_pointer.buffer_length()
+    }
+}
+
+/// TS_CACHEDPOINTERATTRIBUTE
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CachedPointerAttribute {
+    pub cache_index: u16,
+}
+
+impl<'a> PduBufferParsing<'a> for CachedPointerAttribute {
+    type Error = PointerError;
+
+    fn from_buffer_consume(buffer: &mut &'a [u8]) -> Result<Self, Self::Error> {
+        Ok(Self {
+            cache_index: buffer.read_u16::<LittleEndian>()?,
+        })
+    }
+
+    fn to_buffer_consume(&self, buffer: &mut &mut [u8]) -> Result<(), Self::Error> {
+        buffer.write_u16::<LittleEndian>(self.cache_index)?;
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        2
+    }
+}
+
+/// TS_LARGEPOINTERATTRIBUTE, a pointer of up to 384x384 pixels
+#[derive(Clone, PartialEq, Eq)]
+pub struct LargePointerAttribute<'a> {
+    /// Color depth of the XOR mask
+    pub xor_bpp: u16,
+    pub cache_index: u16,
+    pub hot_spot: Point16,
+    pub width: u16,
+    pub height: u16,
+    pub xor_mask: &'a [u8],
+    /// 1 bit per pixel
+    pub and_mask: &'a [u8],
+}
+
+impl<'a> PduBufferParsing<'a> for LargePointerAttribute<'a> {
+    type Error = PointerError;
+
+    fn from_buffer_consume(buffer: &mut &'a [u8]) -> Result<Self, Self::Error> {
+        let xor_bpp = buffer.read_u16::<LittleEndian>()?;
+        let cache_index = buffer.read_u16::<LittleEndian>()?;
+        let hot_spot = Point16::from_buffer_consume(buffer)?;
+        let width = buffer.read_u16::<LittleEndian>()?;
+        let height = buffer.read_u16::<LittleEndian>()?;
+        let and_mask_length = buffer.read_u32::<LittleEndian>()? as usize;
+        let xor_mask_length = buffer.read_u32::<LittleEndian>()? as usize;
+
+        let (xor_mask, and_mask) = read_masks(buffer, xor_mask_length, and_mask_length)?;
+
+        Ok(Self {
+            xor_bpp,
+            cache_index,
+            hot_spot,
+            width,
+            height,
+            xor_mask,
+            and_mask,
+        })
+    }
+
+    fn to_buffer_consume(&self, buffer: &mut &mut [u8]) -> Result<(), Self::Error> {
+        buffer.write_u16::<LittleEndian>(self.xor_bpp)?;
+        buffer.write_u16::<LittleEndian>(self.cache_index)?;
+        self.hot_spot.to_buffer_consume(buffer)?;
+        buffer.write_u16::<LittleEndian>(self.width)?;
+        buffer.write_u16::<LittleEndian>(self.height)?;
+        buffer.write_u32::<LittleEndian>(
+            u32::try_from(self.and_mask.len()).map_err(|_| PointerError::MaskTooLarge(self.and_mask.len()))?,
+        )?;
+        buffer.write_u32::<LittleEndian>(
+            u32::try_from(self.xor_mask.len()).map_err(|_| PointerError::MaskTooLarge(self.xor_mask.len()))?,
+        )?;
+        buffer.write_all(self.xor_mask)?;
+        buffer.write_all(self.and_mask)?;
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        LARGE_POINTER_ATTRIBUTE_HEADER_SIZE + self.xor_mask.len() + self.and_mask.len()
+    }
+}
+
+impl fmt::Debug for LargePointerAttribute<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LargePointerAttribute")
+            .field("xor_bpp", &self.xor_bpp)
+            .field("cache_index", &self.cache_index)
+            .field("hot_spot", &self.hot_spot)
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("xor_mask.len()", &self.xor_mask.len())
+            .field("and_mask.len()", &self.and_mask.len())
+            .finish()
+    }
+}
+
+/// Reads the XOR mask followed by the AND mask
+fn read_masks<'a>(
+    buffer: &mut &'a [u8],
+    xor_mask_length: usize,
+    and_mask_length: usize,
+) -> Result<(&'a [u8], &'a [u8]), PointerError> {
+    let expected = xor_mask_length + and_mask_length;
+    if buffer.len() < expected {
+        return Err(PointerError::InvalidDataLength {
+            actual: buffer.len(),
+            expected,
+        });
+    }
+
+    let xor_mask = buffer.split_to(xor_mask_length);
+    let and_mask = buffer.split_to(and_mask_length);
+
+    Ok((xor_mask, and_mask))
+}
+
+#[derive(Debug, Error)]
+pub enum PointerError {
+    #[error("IO error")]
+    IOError(#[from] io::Error),
+    #[error("Input buffer len is shorter than the data length: {} < {}", actual, expected)]
+    InvalidDataLength { actual: usize, expected: usize },
+    #[error("Pointer mask is too large: {0} bytes")]
+    MaskTooLarge(usize),
+}
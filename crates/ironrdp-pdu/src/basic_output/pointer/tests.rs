@@ -0,0 +1,120 @@
+use super::*;
+use crate::fast_path::{FastPathUpdate, UpdateCode};
+
+const POSITION_POINTER_BUFFER: [u8; 4] = [0x20, 0x00, 0x10, 0x00];
+
+const CACHED_POINTER_BUFFER: [u8; 2] = [0x03, 0x00];
+
+const NEW_POINTER_BUFFER: [u8; 36] = [
+    0x20, 0x00, // xorBpp
+    0x01, 0x00, // cacheIndex
+    0x01, 0x00, 0x00, 0x00, // hotSpot
+    0x02, 0x00, // width
+    0x02, 0x00, // height
+    0x04, 0x00, // lengthAndMask
+    0x10, 0x00, // lengthXorMask
+    0x00, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff, 0xff, // xorMaskData (bottom row)
+    0x00, 0x00, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00, // xorMaskData (top row)
+    0x40, 0x00, // andMaskData (bottom row)
+    0x00, 0x00, // andMaskData (top row)
+];
+
+const LARGE_POINTER_BUFFER: [u8; 26] = [
+    0x18, 0x00, // xorBpp
+    0x02, 0x00, // cacheIndex
+    0x00, 0x00, 0x00, 0x00, // hotSpot
+    0x01, 0x00, // width
+    0x01, 0x00, // height
+    0x02, 0x00, 0x00, 0x00, // lengthAndMask
+    0x04, 0x00, 0x00, 0x00, // lengthXorMask
+    0x00, 0x00, 0xff, 0x00, // xorMaskData
+    0x00, 0x00, // andMaskData
+];
+
+const POSITION_POINTER: PointerUpdateData<'static> = PointerUpdateData::SetPosition(Point16 { x: 0x20, y: 0x10 });
+
+const CACHED_POINTER: PointerUpdateData<'static> = PointerUpdateData::Cached(CachedPointerAttribute { cache_index: 3 });
+
+fn new_pointer() -> PointerUpdateData<'static> {
+    PointerUpdateData::New(PointerAttribute {
+        xor_bpp: 32,
+        color_pointer: ColorPointerAttribute {
+            cache_index: 1,
+            hot_spot: Point16 { x: 1, y: 0 },
+            width: 2,
+            height: 2,
+            xor_mask: &NEW_POINTER_BUFFER[16..32],
+            and_mask: &NEW_POINTER_BUFFER[32..],
+        },
+    })
+}
+
+fn large_pointer() -> PointerUpdateData<'static> {
+    PointerUpdateData::Large(LargePointerAttribute {
+        xor_bpp: 24,
+        cache_index: 2,
+        hot_spot: Point16 { x: 0, y: 0 },
+        width: 1,
+        height: 1,
+        xor_mask: &LARGE_POINTER_BUFFER[20..24],
+        and_mask: &LARGE_POINTER_BUFFER[24..],
+    })
+}
+
+fn assert_round_trip(pdu: &PointerUpdateData<'_>, code: UpdateCode, buffer: &[u8]) {
+    let FastPathUpdate::Pointer(decoded) = FastPathUpdate::from_buffer_with_code(buffer, code).unwrap() else {
+        panic!("expected a pointer update");
+    };
+    assert_eq!(*pdu, decoded);
+    assert_eq!(code, UpdateCode::from(&FastPathUpdate::Pointer(decoded)));
+
+    let mut encoded = vec![0; buffer.len()];
+    pdu.to_buffer_consume(&mut encoded.as_mut_slice()).unwrap();
+    assert_eq!(buffer, encoded.as_slice());
+
+    assert_eq!(buffer.len(), pdu.buffer_length());
+}
+
+#[test]
+fn hidden_and_default_pointers_have_no_data() {
+    assert_round_trip(&PointerUpdateData::SetHidden, UpdateCode::HiddenPointer, &[]);
+    assert_round_trip(&PointerUpdateData::SetDefault, UpdateCode::DefaultPointer, &[]);
+}
+
+#[test]
+fn position_pointer_round_trip() {
+    assert_round_trip(&POSITION_POINTER, UpdateCode::PositionPointer, &POSITION_POINTER_BUFFER);
+}
+
+#[test]
+fn cached_pointer_round_trip() {
+    assert_round_trip(&CACHED_POINTER, UpdateCode::CachedPointer, &CACHED_POINTER_BUFFER);
+}
+
+#[test]
+fn new_pointer_round_trip() {
+    assert_round_trip(&new_pointer(), UpdateCode::NewPointer, &NEW_POINTER_BUFFER);
+}
+
+#[test]
+fn color_pointer_round_trip() {
+    let PointerUpdateData::New(pointer) = new_pointer() else {
+        unreachable!()
+    };
+
+    assert_round_trip(
+        &PointerUpdateData::Color(pointer.color_pointer),
+        UpdateCode::ColorPointer,
+        &NEW_POINTER_BUFFER[2..],
+    );
+}
+
+#[test]
+fn large_pointer_round_trip() {
+    assert_round_trip(&large_pointer(), UpdateCode::LargePointer, &LARGE_POINTER_BUFFER);
+}
+
+#[test]
+fn from_buffer_fails_on_truncated_masks() {
+    assert!(FastPathUpdate::from_buffer_with_code(&NEW_POINTER_BUFFER[..34], UpdateCode::NewPointer).is_err());
+}
@@ -1 +1,2 @@
 pub mod rfx;
+pub mod rfx_progressive;
@@ -0,0 +1,727 @@
+//! RemoteFX Progressive codec messages ([MS-RDPEGFX] 2.2.4.2)
+//!
+//! The progressive codec is used by the graphics pipeline through `RDPGFX_WIRE_TO_SURFACE_PDU_2`. Its bitmap data
+//! is a sequence of blocks: a synchronization block and a context block, followed by frames made of regions of
+//! tiles. A tile is sent once in full (simple tile) or in several passes, the first pass being upgraded by the
+//! next ones until the tile reaches its final quality.
+
+use std::io::{self, Read, Write};
+
+use bitflags::bitflags;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use num_derive::{FromPrimitive, ToPrimitive};
+use num_traits::{FromPrimitive as _, ToPrimitive as _};
+use thiserror::Error;
+
+use super::rfx::{Quant, RfxError, RfxRectangle};
+use crate::utils::SplitTo;
+use crate::PduBufferParsing;
+
+const BLOCK_HEADER_SIZE: usize = 6;
+const SYNC_MAGIC: u32 = 0xCACC_ACCA;
+const SYNC_VERSION: u16 = 0x0100;
+const TILE_SIZE: u16 = 64;
+
+const SYNC_SIZE: usize = 6;
+const FRAME_BEGIN_SIZE: usize = 6;
+const CONTEXT_SIZE: usize = 4;
+const REGION_FIXED_PART_SIZE: usize = 12;
+const RECTANGLE_SIZE: usize = 8;
+const COMPONENT_QUANT_SIZE: usize = 5;
+const QUALITY_QUANT_SIZE: usize = 1 + 3 * COMPONENT_QUANT_SIZE;
+const TILE_SIMPLE_FIXED_PART_SIZE: usize = 16;
+const TILE_FIRST_FIXED_PART_SIZE: usize = 17;
+const TILE_UPGRADE_FIXED_PART_SIZE: usize = 20;
+
+/// Quality of the tiles which are not progressively encoded
+pub const FULL_QUALITY: u8 = 0xFF;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+#[repr(u16)]
+pub enum BlockType {
+    Sync = 0xCCC0,
+    FrameBegin = 0xCCC1,
+    FrameEnd = 0xCCC2,
+    Context = 0xCCC3,
+    Region = 0xCCC4,
+    TileSimple = 0xCCC5,
+    TileFirst = 0xCCC6,
+    TileUpgrade = 0xCCC7,
+}
+
+/// Block of the progressive bitmap data. Tiles are only found inside of regions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Block<'a> {
+    /// RFX_PROGRESSIVE_SYNC
+    Sync,
+    /// RFX_PROGRESSIVE_FRAME_BEGIN
+    FrameBegin(FrameBeginPdu),
+    /// RFX_PROGRESSIVE_FRAME_END
+    FrameEnd,
+    /// RFX_PROGRESSIVE_CONTEXT
+    Context(ContextPdu),
+    /// RFX_PROGRESSIVE_REGION
+    Region(RegionPdu<'a>),
+}
+
+impl<'a> PduBufferParsing<'a> for Block<'a> {
+    type Error = RfxProgressiveError;
+
+    fn from_buffer_consume(buffer: &mut &'a [u8]) -> Result<Self, Self::Error> {
+        let (ty, mut buffer) = read_block_header(buffer)?;
+
+        match ty {
+            BlockType::Sync => {
+                let magic = buffer.read_u32::<LittleEndian>()?;
+                if magic != SYNC_MAGIC {
+                    return Err(RfxProgressiveError::InvalidMagicNumber(magic));
+                }
+
+                let version = buffer.read_u16::<LittleEndian>()?;
+                if version != SYNC_VERSION {
+                    return Err(RfxProgressiveError::InvalidSyncVersion(version));
+                }
+
+                Ok(Self::Sync)
+            }
+            BlockType::FrameBegin => Ok(Self::FrameBegin(FrameBeginPdu::from_buffer_consume(&mut buffer)?)),
+            BlockType::FrameEnd => Ok(Self::FrameEnd),
+            BlockType::Context => Ok(Self::Context(ContextPdu::from_buffer_consume(&mut buffer)?)),
+            BlockType::Region => Ok(Self::Region(RegionPdu::from_buffer_consume(&mut buffer)?)),
+            BlockType::TileSimple | BlockType::TileFirst | BlockType::TileUpgrade => {
+                Err(RfxProgressiveError::UnexpectedBlockType(ty))
+            }
+        }
+    }
+
+    fn to_buffer_consume(&self, buffer: &mut &mut [u8]) -> Result<(), Self::Error> {
+        let ty = match self {
+            Self::Sync => BlockType::Sync,
+            Self::FrameBegin(_) => BlockType::FrameBegin,
+            Self::FrameEnd => BlockType::FrameEnd,
+            Self::Context(_) => BlockType::Context,
+            Self::Region(_) => BlockType::Region,
+        };
+
+        write_block_header(buffer, ty, self.buffer_length())?;
+
+        match self {
+            Self::Sync => {
+                buffer.write_u32::<LittleEndian>(SYNC_MAGIC)?;
+                buffer.write_u16::<LittleEndian>(SYNC_VERSION)?;
+
+                Ok(())
+            }
+            Self::FrameBegin(pdu) => pdu.to_buffer_consume(buffer),
+            Self::FrameEnd => Ok(()),
+            Self::Context(pdu) => pdu.to_buffer_consume(buffer),
+            Self::Region(pdu) => pdu.to_buffer_consume(buffer),
+        }
+    }
+
+    fn buffer_length(&self) -> usize {
+        BLOCK_HEADER_SIZE
+            + match self {
+                Self::Sync => SYNC_SIZE,
+                Self::FrameBegin(pdu) => pdu.buffer_length(),
+                Self::FrameEnd => 0,
+                Self::Context(pdu) => pdu.buffer_length(),
+                Self::Region(pdu) => pdu.buffer_length(),
+            }
+    }
+}
+
+/// RFX_PROGRESSIVE_FRAME_BEGIN, without its block header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameBeginPdu {
+    pub frame_index: u32,
+    pub region_count: u16,
+}
+
+impl<'a> PduBufferParsing<'a> for FrameBeginPdu {
+    type Error = RfxProgressiveError;
+
+    fn from_buffer_consume(buffer: &mut &'a [u8]) -> Result<Self, Self::Error> {
+        let frame_index = buffer.read_u32::<LittleEndian>()?;
+        let region_count = buffer.read_u16::<LittleEndian>()?;
+
+        Ok(Self {
+            frame_index,
+            region_count,
+        })
+    }
+
+    fn to_buffer_consume(&self, buffer: &mut &mut [u8]) -> Result<(), Self::Error> {
+        buffer.write_u32::<LittleEndian>(self.frame_index)?;
+        buffer.write_u16::<LittleEndian>(self.region_count)?;
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        FRAME_BEGIN_SIZE
+    }
+}
+
+/// RFX_PROGRESSIVE_CONTEXT, without its block header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextPdu {
+    pub context_id: u8,
+    pub flags: ContextFlags,
+}
+
+impl<'a> PduBufferParsing<'a> for ContextPdu {
+    type Error = RfxProgressiveError;
+
+    fn from_buffer_consume(buffer: &mut &'a [u8]) -> Result<Self, Self::Error> {
+        let context_id = buffer.read_u8()?;
+
+        let tile_size = buffer.read_u16::<LittleEndian>()?;
+        if tile_size != TILE_SIZE {
+            return Err(RfxProgressiveError::InvalidTileSize(tile_size));
+        }
+
+        let flags = ContextFlags::from_bits_truncate(buffer.read_u8()?);
+
+        Ok(Self { context_id, flags })
+    }
+
+    fn to_buffer_consume(&self, buffer: &mut &mut [u8]) -> Result<(), Self::Error> {
+        buffer.write_u8(self.context_id)?;
+        buffer.write_u16::<LittleEndian>(TILE_SIZE)?;
+        buffer.write_u8(self.flags.bits())?;
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        CONTEXT_SIZE
+    }
+}
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct ContextFlags: u8 {
+        const SUBBAND_DIFFING = 0x01;
+    }
+}
+
+/// RFX_PROGRESSIVE_REGION, without its block header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegionPdu<'a> {
+    pub rectangles: Vec<RfxRectangle>,
+    pub quants: Vec<Quant>,
+    pub quality_quants: Vec<QualityQuant>,
+    pub flags: RegionFlags,
+    pub tiles: Vec<Tile<'a>>,
+}
+
+impl<'a> PduBufferParsing<'a> for RegionPdu<'a> {
+    type Error = RfxProgressiveError;
+
+    fn from_buffer_consume(buffer: &mut &'a [u8]) -> Result<Self, Self::Error> {
+        let tile_size = u16::from(buffer.read_u8()?);
+        if tile_size != TILE_SIZE {
+            return Err(RfxProgressiveError::InvalidTileSize(tile_size));
+        }
+
+        let number_of_rectangles = usize::from(buffer.read_u16::<LittleEndian>()?);
+        let number_of_quants = usize::from(buffer.read_u8()?);
+        let number_of_quality_quants = usize::from(buffer.read_u8()?);
+        let flags = RegionFlags::from_bits_truncate(buffer.read_u8()?);
+        let number_of_tiles = usize::from(buffer.read_u16::<LittleEndian>()?);
+        let tiles_data_size = buffer.read_u32::<LittleEndian>()? as usize;
+
+        let expected_length = number_of_rectangles * RECTANGLE_SIZE
+            + number_of_quants * COMPONENT_QUANT_SIZE
+            + number_of_quality_quants * QUALITY_QUANT_SIZE
+            + tiles_data_size;
+        ensure_length(buffer, expected_length)?;
+
+        let rectangles = (0..number_of_rectangles)
+            .map(|_| RfxRectangle::from_buffer_consume(buffer))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let quants = (0..number_of_quants)
+            .map(|_| read_component_quant(buffer))
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let quality_quants = (0..number_of_quality_quants)
+            .map(|_| QualityQuant::from_buffer_consume(buffer))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut tiles_buffer = buffer.split_to(tiles_data_size);
+        let tiles = (0..number_of_tiles)
+            .map(|_| Tile::from_buffer_consume(&mut tiles_buffer))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            rectangles,
+            quants,
+            quality_quants,
+            flags,
+            tiles,
+        })
+    }
+
+    fn to_buffer_consume(&self, buffer: &mut &mut [u8]) -> Result<(), Self::Error> {
+        buffer.write_u8(TILE_SIZE as u8)?;
+        buffer.write_u16::<LittleEndian>(self.rectangles.len() as u16)?;
+        buffer.write_u8(self.quants.len() as u8)?;
+        buffer.write_u8(self.quality_quants.len() as u8)?;
+        buffer.write_u8(self.flags.bits())?;
+        buffer.write_u16::<LittleEndian>(self.tiles.len() as u16)?;
+        buffer.write_u32::<LittleEndian>(self.tiles_data_size() as u32)?;
+
+        for rectangle in &self.rectangles {
+            rectangle.to_buffer_consume(buffer)?;
+        }
+
+        for quant in &self.quants {
+            write_component_quant(buffer, quant)?;
+        }
+
+        for quality_quant in &self.quality_quants {
+            quality_quant.to_buffer_consume(buffer)?;
+        }
+
+        for tile in &self.tiles {
+            tile.to_buffer_consume(buffer)?;
+        }
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        REGION_FIXED_PART_SIZE
+            + self.rectangles.len() * RECTANGLE_SIZE
+            + self.quants.len() * COMPONENT_QUANT_SIZE
+            + self.quality_quants.len() * QUALITY_QUANT_SIZE
+            + self.tiles_data_size()
+    }
+}
+
+impl RegionPdu<'_> {
+    fn tiles_data_size(&self) -> usize {
+        self.tiles.iter().map(|tile| tile.buffer_length()).sum()
+    }
+}
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct RegionFlags: u8 {
+        /// The tiles are transformed with the reduce-extrapolate DWT
+        const DWT_REDUCE_EXTRAPOLATE = 0x01;
+    }
+}
+
+/// RFX_PROGRESSIVE_CODEC_QUANT
+///
+/// Quantization values added to the ones of the tile for a given quality.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QualityQuant {
+    pub quality: u8,
+    pub y: Quant,
+    pub cb: Quant,
+    pub cr: Quant,
+}
+
+impl<'a> PduBufferParsing<'a> for QualityQuant {
+    type Error = RfxProgressiveError;
+
+    fn from_buffer_consume(buffer: &mut &'a [u8]) -> Result<Self, Self::Error> {
+        let quality = buffer.read_u8()?;
+        let y = read_component_quant(buffer)?;
+        let cb = read_component_quant(buffer)?;
+        let cr = read_component_quant(buffer)?;
+
+        Ok(Self { quality, y, cb, cr })
+    }
+
+    fn to_buffer_consume(&self, buffer: &mut &mut [u8]) -> Result<(), Self::Error> {
+        buffer.write_u8(self.quality)?;
+        write_component_quant(buffer, &self.y)?;
+        write_component_quant(buffer, &self.cb)?;
+        write_component_quant(buffer, &self.cr)?;
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        QUALITY_QUANT_SIZE
+    }
+}
+
+/// Tile of a region
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Tile<'a> {
+    /// RFX_PROGRESSIVE_TILE_SIMPLE
+    Simple(TileSimple<'a>),
+    /// RFX_PROGRESSIVE_TILE_FIRST
+    First(TileFirst<'a>),
+    /// RFX_PROGRESSIVE_TILE_UPGRADE
+    Upgrade(TileUpgrade<'a>),
+}
+
+impl<'a> PduBufferParsing<'a> for Tile<'a> {
+    type Error = RfxProgressiveError;
+
+    fn from_buffer_consume(buffer: &mut &'a [u8]) -> Result<Self, Self::Error> {
+        let (ty, mut buffer) = read_block_header(buffer)?;
+
+        match ty {
+            BlockType::TileSimple => Ok(Self::Simple(TileSimple::from_buffer_consume(&mut buffer)?)),
+            BlockType::TileFirst => Ok(Self::First(TileFirst::from_buffer_consume(&mut buffer)?)),
+            BlockType::TileUpgrade => Ok(Self::Upgrade(TileUpgrade::from_buffer_consume(&mut buffer)?)),
+            _ => Err(RfxProgressiveError::UnexpectedBlockType(ty)),
+        }
+    }
+
+    fn to_buffer_consume(&self, buffer: &mut &mut [u8]) -> Result<(), Self::Error> {
+        match self {
+            Self::Simple(tile) => {
+                write_block_header(buffer, BlockType::TileSimple, self.buffer_length())?;
+                tile.to_buffer_consume(buffer)
+            }
+            Self::First(tile) => {
+                write_block_header(buffer, BlockType::TileFirst, self.buffer_length())?;
+                tile.to_buffer_consume(buffer)
+            }
+            Self::Upgrade(tile) => {
+                write_block_header(buffer, BlockType::TileUpgrade, self.buffer_length())?;
+                tile.to_buffer_consume(buffer)
+            }
+        }
+    }
+
+    fn buffer_length(&self) -> usize {
+        BLOCK_HEADER_SIZE
+            + match self {
+                Self::Simple(tile) => tile.buffer_length(),
+                Self::First(tile) => tile.buffer_length(),
+                Self::Upgrade(tile) => tile.buffer_length(),
+            }
+    }
+}
+
+/// Position and quantization values common to all the tiles
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TileHeader {
+    pub y_quant_index: u8,
+    pub cb_quant_index: u8,
+    pub cr_quant_index: u8,
+    /// Horizontal position of the tile, in tiles
+    pub x: u16,
+    /// Vertical position of the tile, in tiles
+    pub y: u16,
+}
+
+impl TileHeader {
+    fn from_buffer_consume(buffer: &mut &[u8]) -> io::Result<Self> {
+        Ok(Self {
+            y_quant_index: buffer.read_u8()?,
+            cb_quant_index: buffer.read_u8()?,
+            cr_quant_index: buffer.read_u8()?,
+            x: buffer.read_u16::<LittleEndian>()?,
+            y: buffer.read_u16::<LittleEndian>()?,
+        })
+    }
+
+    fn to_buffer_consume(&self, buffer: &mut &mut [u8]) -> io::Result<()> {
+        buffer.write_u8(self.y_quant_index)?;
+        buffer.write_u8(self.cb_quant_index)?;
+        buffer.write_u8(self.cr_quant_index)?;
+        buffer.write_u16::<LittleEndian>(self.x)?;
+        buffer.write_u16::<LittleEndian>(self.y)?;
+
+        Ok(())
+    }
+}
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct TileFlags: u8 {
+        /// The coefficients are added to the ones of the previous version of the tile
+        const DIFFERENCE = 0x01;
+    }
+}
+
+/// RFX_PROGRESSIVE_TILE_SIMPLE, without its block header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TileSimple<'a> {
+    pub header: TileHeader,
+    pub flags: TileFlags,
+    pub y_data: &'a [u8],
+    pub cb_data: &'a [u8],
+    pub cr_data: &'a [u8],
+    pub tail_data: &'a [u8],
+}
+
+impl<'a> PduBufferParsing<'a> for TileSimple<'a> {
+    type Error = RfxProgressiveError;
+
+    fn from_buffer_consume(buffer: &mut &'a [u8]) -> Result<Self, Self::Error> {
+        let header = TileHeader::from_buffer_consume(buffer)?;
+        let flags = TileFlags::from_bits_truncate(buffer.read_u8()?);
+        let [y_data, cb_data, cr_data, tail_data] = read_tile_data(buffer)?;
+
+        Ok(Self {
+            header,
+            flags,
+            y_data,
+            cb_data,
+            cr_data,
+            tail_data,
+        })
+    }
+
+    fn to_buffer_consume(&self, buffer: &mut &mut [u8]) -> Result<(), Self::Error> {
+        self.header.to_buffer_consume(buffer)?;
+        buffer.write_u8(self.flags.bits())?;
+        write_tile_data(buffer, [self.y_data, self.cb_data, self.cr_data, self.tail_data])?;
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        TILE_SIMPLE_FIXED_PART_SIZE + self.y_data.len() + self.cb_data.len() + self.cr_data.len() + self.tail_data.len()
+    }
+}
+
+/// RFX_PROGRESSIVE_TILE_FIRST, without its block header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TileFirst<'a> {
+    pub header: TileHeader,
+    pub flags: TileFlags,
+    /// Index of the quality quantization values of the region, or `FULL_QUALITY`
+    pub quality: u8,
+    pub y_data: &'a [u8],
+    pub cb_data: &'a [u8],
+    pub cr_data: &'a [u8],
+    pub tail_data: &'a [u8],
+}
+
+impl<'a> PduBufferParsing<'a> for TileFirst<'a> {
+    type Error = RfxProgressiveError;
+
+    fn from_buffer_consume(buffer: &mut &'a [u8]) -> Result<Self, Self::Error> {
+        let header = TileHeader::from_buffer_consume(buffer)?;
+        let flags = TileFlags::from_bits_truncate(buffer.read_u8()?);
+        let quality = buffer.read_u8()?;
+        let [y_data, cb_data, cr_data, tail_data] = read_tile_data(buffer)?;
+
+        Ok(Self {
+            header,
+            flags,
+            quality,
+            y_data,
+            cb_data,
+            cr_data,
+            tail_data,
+        })
+    }
+
+    fn to_buffer_consume(&self, buffer: &mut &mut [u8]) -> Result<(), Self::Error> {
+        self.header.to_buffer_consume(buffer)?;
+        buffer.write_u8(self.flags.bits())?;
+        buffer.write_u8(self.quality)?;
+        write_tile_data(buffer, [self.y_data, self.cb_data, self.cr_data, self.tail_data])?;
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        TILE_FIRST_FIXED_PART_SIZE + self.y_data.len() + self.cb_data.len() + self.cr_data.len() + self.tail_data.len()
+    }
+}
+
+/// RFX_PROGRESSIVE_TILE_UPGRADE, without its block header
+///
+/// Each component is upgraded with bits read from its SRL (simplified run-length) data for the coefficients which
+/// were zero so far, and from its raw data for the other ones.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TileUpgrade<'a> {
+    pub header: TileHeader,
+    /// Index of the quality quantization values of the region, or `FULL_QUALITY`
+    pub quality: u8,
+    pub y_srl_data: &'a [u8],
+    pub y_raw_data: &'a [u8],
+    pub cb_srl_data: &'a [u8],
+    pub cb_raw_data: &'a [u8],
+    pub cr_srl_data: &'a [u8],
+    pub cr_raw_data: &'a [u8],
+}
+
+impl<'a> PduBufferParsing<'a> for TileUpgrade<'a> {
+    type Error = RfxProgressiveError;
+
+    fn from_buffer_consume(buffer: &mut &'a [u8]) -> Result<Self, Self::Error> {
+        let header = TileHeader::from_buffer_consume(buffer)?;
+        let quality = buffer.read_u8()?;
+
+        let mut lengths = [0; 6];
+        for length in lengths.iter_mut() {
+            *length = usize::from(buffer.read_u16::<LittleEndian>()?);
+        }
+
+        ensure_length(buffer, lengths.iter().sum())?;
+
+        let [y_srl_data, y_raw_data, cb_srl_data, cb_raw_data, cr_srl_data, cr_raw_data] =
+            lengths.map(|length| buffer.split_to(length));
+
+        Ok(Self {
+            header,
+            quality,
+            y_srl_data,
+            y_raw_data,
+            cb_srl_data,
+            cb_raw_data,
+            cr_srl_data,
+            cr_raw_data,
+        })
+    }
+
+    fn to_buffer_consume(&self, buffer: &mut &mut [u8]) -> Result<(), Self::Error> {
+        self.header.to_buffer_consume(buffer)?;
+        buffer.write_u8(self.quality)?;
+
+        let data = [
+            self.y_srl_data,
+            self.y_raw_data,
+            self.cb_srl_data,
+            self.cb_raw_data,
+            self.cr_srl_data,
+            self.cr_raw_data,
+        ];
+
+        for data in data {
+            buffer.write_u16::<LittleEndian>(data.len() as u16)?;
+        }
+
+        for data in data {
+            buffer.write_all(data)?;
+        }
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        TILE_UPGRADE_FIXED_PART_SIZE
+            + self.y_srl_data.len()
+            + self.y_raw_data.len()
+            + self.cb_srl_data.len()
+            + self.cb_raw_data.len()
+            + self.cr_srl_data.len()
+            + self.cr_raw_data.len()
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum RfxProgressiveError {
+    #[error("IO error")]
+    IoError(#[from] io::Error),
+    #[error("RemoteFX error")]
+    Rfx(#[from] RfxError),
+    #[error("Got invalid block type: {0}")]
+    InvalidBlockType(u16),
+    #[error("Got unexpected block type: {0:?}")]
+    UnexpectedBlockType(BlockType),
+    #[error("Got invalid block length: {0}")]
+    InvalidBlockLength(usize),
+    #[error("Got invalid Sync magic number: {0}")]
+    InvalidMagicNumber(u32),
+    #[error("Got invalid Sync version: {0}")]
+    InvalidSyncVersion(u16),
+    #[error("Got invalid tile size: {0}")]
+    InvalidTileSize(u16),
+    #[error("Input buffer is shorter than the data length: {actual} < {expected}")]
+    InvalidDataLength { expected: usize, actual: usize },
+}
+
+/// Reads a block header, and returns the block type with the data of the block.
+fn read_block_header<'a>(buffer: &mut &'a [u8]) -> Result<(BlockType, &'a [u8]), RfxProgressiveError> {
+    let ty = buffer.read_u16::<LittleEndian>()?;
+    let ty = BlockType::from_u16(ty).ok_or(RfxProgressiveError::InvalidBlockType(ty))?;
+
+    let block_length = buffer.read_u32::<LittleEndian>()? as usize;
+    let data_length = block_length
+        .checked_sub(BLOCK_HEADER_SIZE)
+        .ok_or(RfxProgressiveError::InvalidBlockLength(block_length))?;
+
+    ensure_length(buffer, data_length)?;
+
+    Ok((ty, buffer.split_to(data_length)))
+}
+
+fn write_block_header(buffer: &mut &mut [u8], ty: BlockType, block_length: usize) -> io::Result<()> {
+    buffer.write_u16::<LittleEndian>(ty.to_u16().unwrap())?;
+    buffer.write_u32::<LittleEndian>(block_length as u32)?;
+
+    Ok(())
+}
+
+/// Reads the Y, Cb, Cr and tail data of a simple or first tile, preceded by their lengths.
+fn read_tile_data<'a>(buffer: &mut &'a [u8]) -> Result<[&'a [u8]; 4], RfxProgressiveError> {
+    let mut lengths = [0; 4];
+    for length in lengths.iter_mut() {
+        *length = usize::from(buffer.read_u16::<LittleEndian>()?);
+    }
+
+    ensure_length(buffer, lengths.iter().sum())?;
+
+    Ok(lengths.map(|length| buffer.split_to(length)))
+}
+
+fn write_tile_data(buffer: &mut &mut [u8], data: [&[u8]; 4]) -> io::Result<()> {
+    for data in data {
+        buffer.write_u16::<LittleEndian>(data.len() as u16)?;
+    }
+
+    for data in data {
+        buffer.write_all(data)?;
+    }
+
+    Ok(())
+}
+
+/// Reads a RFX_COMPONENT_CODEC_QUANT, whose values are not ordered as in the RemoteFX TS_RFX_CODEC_QUANT.
+fn read_component_quant(buffer: &mut &[u8]) -> io::Result<Quant> {
+    let mut values = [0; COMPONENT_QUANT_SIZE];
+    buffer.read_exact(&mut values)?;
+
+    let [ll3_hl3, lh3_hh3, hl2_lh2, hh2_hl1, lh1_hh1] = values;
+
+    Ok(Quant {
+        ll3: ll3_hl3 & 0x0F,
+        hl3: ll3_hl3 >> 4,
+        lh3: lh3_hh3 & 0x0F,
+        hh3: lh3_hh3 >> 4,
+        hl2: hl2_lh2 & 0x0F,
+        lh2: hl2_lh2 >> 4,
+        hh2: hh2_hl1 & 0x0F,
+        hl1: hh2_hl1 >> 4,
+        lh1: lh1_hh1 & 0x0F,
+        hh1: lh1_hh1 >> 4,
+    })
+}
+
+fn write_component_quant(buffer: &mut &mut [u8], quant: &Quant) -> io::Result<()> {
+    buffer.write_all(&[
+        (quant.hl3 << 4) | (quant.ll3 & 0x0F),
+        (quant.hh3 << 4) | (quant.lh3 & 0x0F),
+        (quant.lh2 << 4) | (quant.hl2 & 0x0F),
+        (quant.hl1 << 4) | (quant.hh2 & 0x0F),
+        (quant.hh1 << 4) | (quant.lh1 & 0x0F),
+    ])
+}
+
+fn ensure_length(buffer: &[u8], expected: usize) -> Result<(), RfxProgressiveError> {
+    if buffer.len() < expected {
+        return Err(RfxProgressiveError::InvalidDataLength {
+            expected,
+            actual: buffer.len(),
+        });
+    }
+
+    Ok(())
+}
@@ -4,6 +4,34 @@ use der_parser::parse_der;
 use num_bigint::BigUint;
 
 pub fn encrypt_with_public_key(message: &[u8], public_key_der: &[u8]) -> io::Result<Vec<u8>> {
+    let (n, e) = decode_public_key(public_key_der)?;
+
+    let m = BigUint::from_bytes_le(message);
+    let c = m.modpow(&e, &n);
+
+    let mut result = c.to_bytes_le();
+    result.resize(result.len() + 8, 0u8);
+
+    Ok(result)
+}
+
+/// Encrypts the little-endian `message` with the raw RSA public key.
+///
+/// The little-endian result is padded with zeroes to the size of the modulus, followed by 8 more zero bytes.
+pub fn encrypt_with_public_key_components(message: &[u8], modulus: &BigUint, exponent: &BigUint) -> Vec<u8> {
+    let m = BigUint::from_bytes_le(message);
+    let c = m.modpow(exponent, modulus);
+
+    let modulus_length = (modulus.bits() as usize + 7) / 8;
+
+    let mut result = c.to_bytes_le();
+    result.resize(modulus_length + 8, 0u8);
+
+    result
+}
+
+/// Returns the modulus and the exponent of a DER-encoded RSA public key.
+pub fn decode_public_key(public_key_der: &[u8]) -> io::Result<(BigUint, BigUint)> {
     let (_, der_object) = parse_der(public_key_der).map_err(|err| {
         io::Error::new(
             io::ErrorKind::InvalidData,
@@ -39,13 +67,5 @@ pub fn encrypt_with_public_key(message: &[u8], public_key_der: &[u8]) -> io::Res
         )
     })?;
 
-    let n = BigUint::from_bytes_be(n);
-    let e = BigUint::from_bytes_be(e);
-    let m = BigUint::from_bytes_le(message);
-    let c = m.modpow(&e, &n);
-
-    let mut result = c.to_bytes_le();
-    result.resize(result.len() + 8, 0u8);
-
-    Ok(result)
+    Ok((BigUint::from_bytes_be(n), BigUint::from_bytes_be(e)))
 }
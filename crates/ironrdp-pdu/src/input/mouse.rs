@@ -9,6 +9,7 @@ use crate::PduParsing;
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct MousePdu {
     pub flags: PointerFlags,
+    /// Wheel rotation, in the range from -256 to 255, for `VERTICAL_WHEEL` and `HORIZONTAL_WHEEL` events
     pub number_of_wheel_rotation_units: i16,
     pub x_position: u16,
     pub y_position: u16,
@@ -20,16 +21,20 @@ impl PduParsing for MousePdu {
     fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
         let flags_raw = stream.read_u16::<LittleEndian>()?;
 
-        let flags = PointerFlags::from_bits_truncate(flags_raw);
+        let mut flags = PointerFlags::from_bits_truncate(flags_raw);
 
         let wheel_rotations_bits = flags_raw as u8; // truncate
 
+        // The wheel rotation is a 9-bit two's complement value, `WHEEL_NEGATIVE` being its sign bit
         let number_of_wheel_rotation_units = if flags.contains(PointerFlags::WHEEL_NEGATIVE) {
-            -i16::from(wheel_rotations_bits)
+            i16::from(wheel_rotations_bits) - 0x100
         } else {
             i16::from(wheel_rotations_bits)
         };
 
+        // Conveyed by the sign of `number_of_wheel_rotation_units`
+        flags.remove(PointerFlags::WHEEL_NEGATIVE);
+
         let x_position = stream.read_u16::<LittleEndian>()?;
         let y_position = stream.read_u16::<LittleEndian>()?;
 
@@ -27,7 +27,7 @@ pub(crate) mod ber;
 pub(crate) mod crypto;
 pub(crate) mod per;
 
-pub use crate::basic_output::{bitmap, fast_path, surface_commands};
+pub use crate::basic_output::{bitmap, fast_path, palette, pointer, surface_commands};
 pub use crate::rdp::vc::dvc;
 
 pub type Result<T> = core::result::Result<T, Error>;
@@ -96,7 +96,7 @@ pub enum NegoRequestData {
 }
 
 impl NegoRequestData {
-    pub fn routing_token(value: String) -> Self {
+    pub fn routing_token(value: Vec<u8>) -> Self {
         Self::RoutingToken(RoutingToken(value))
     }
 
@@ -133,11 +133,21 @@ impl Cookie {
     const PREFIX: &str = "Cookie: mstshash=";
 
     pub fn read(src: &mut ReadCursor<'_>) -> Result<Option<Self>> {
-        read_nego_data(src, "Cookie", Self::PREFIX)?.map(Self).pipe(Ok)
+        let Some(data) = read_nego_data(src, "Cookie", Self::PREFIX)? else {
+            return Ok(None);
+        };
+
+        let data = String::from_utf8(data).map_err(|_| Error::InvalidMessage {
+            name: "Cookie",
+            field: "identifier",
+            reason: "not valid UTF-8",
+        })?;
+
+        Ok(Some(Self(data)))
     }
 
     pub fn write(&self, dst: &mut WriteCursor<'_>) -> Result<()> {
-        write_nego_data(dst, "Cookie", Self::PREFIX, &self.0)
+        write_nego_data(dst, "Cookie", Self::PREFIX, self.0.as_bytes())
     }
 
     pub fn size(&self) -> usize {
@@ -146,7 +156,8 @@ impl Cookie {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct RoutingToken(pub String);
+/// Routing token, e.g.: the load balancing information of a Server Redirection PDU, which is opaque to the client
+pub struct RoutingToken(pub Vec<u8>);
 
 impl RoutingToken {
     const PREFIX: &str = "Cookie: msts=";
@@ -225,8 +236,14 @@ impl<'de> X224Pdu<'de> for ConnectionRequest {
 
         let nego_data = NegoRequestData::read(src)?;
 
-        let Some(variable_part_rest_size) = variable_part_size.checked_sub(nego_data.as_ref().map(|data| data.size()).unwrap_or(0)) else {
-            return Err(Error::InvalidMessage { name: Self::NAME, field: "TPDU header variable part", reason: "advertised size too small" })
+        let Some(variable_part_rest_size) =
+            variable_part_size.checked_sub(nego_data.as_ref().map(|data| data.size()).unwrap_or(0))
+        else {
+            return Err(Error::InvalidMessage {
+                name: Self::NAME,
+                field: "TPDU header variable part",
+                reason: "advertised size too small",
+            });
         };
 
         if variable_part_rest_size >= usize::from(Self::RDP_NEG_REQ_SIZE) {
@@ -374,7 +391,7 @@ impl<'de> X224Pdu<'de> for ConnectionConfirm {
     }
 }
 
-fn read_nego_data(src: &mut ReadCursor<'_>, name: &'static str, prefix: &str) -> Result<Option<String>> {
+fn read_nego_data(src: &mut ReadCursor<'_>, name: &'static str, prefix: &str) -> Result<Option<Vec<u8>>> {
     ensure_size!(name: name, in: src, size: prefix.len() + 2);
 
     if src.peek_slice(prefix.len()) != prefix.as_bytes() {
@@ -394,22 +411,14 @@ fn read_nego_data(src: &mut ReadCursor<'_>, name: &'static str, prefix: &str) ->
 
     src.advance(2);
 
-    let data = core::str::from_utf8(&src.inner()[identifier_start..identifier_end])
-        .map_err(|_| Error::InvalidMessage {
-            name,
-            field: "identifier",
-            reason: "not valid UTF-8",
-        })?
-        .to_owned();
-
-    Ok(Some(data))
+    Ok(Some(src.inner()[identifier_start..identifier_end].to_vec()))
 }
 
-fn write_nego_data(dst: &mut WriteCursor<'_>, name: &'static str, prefix: &str, value: &str) -> Result<()> {
+fn write_nego_data(dst: &mut WriteCursor<'_>, name: &'static str, prefix: &str, value: &[u8]) -> Result<()> {
     ensure_size!(name: name, in: dst, size: prefix.len() + value.len() + 2);
 
     dst.write_slice(prefix.as_bytes());
-    dst.write_slice(value.as_bytes());
+    dst.write_slice(value);
     dst.write_u16(0x0A0D);
 
     Ok(())
@@ -9,6 +9,7 @@ use crate::rdp::finalization_messages::FinalizationMessagesError;
 use crate::rdp::headers::{BasicSecurityHeader, BasicSecurityHeaderFlags, ShareControlPduType, ShareDataPduType};
 use crate::rdp::server_error_info::ServerSetErrorInfoError;
 use crate::rdp::server_license::ServerLicenseError;
+use crate::rdp::server_redirection::ServerRedirectionError;
 use crate::PduParsing;
 
 pub mod capability_sets;
@@ -17,7 +18,9 @@ pub mod finalization_messages;
 pub mod headers;
 pub mod server_error_info;
 pub mod server_license;
+pub mod server_redirection;
 pub mod session_info;
+pub mod standard_security;
 pub mod vc;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -87,6 +90,8 @@ pub enum RdpError {
     ServerSetErrorInfoError(#[from] ServerSetErrorInfoError),
     #[error("Input event PDU error")]
     InputEventError(#[from] InputEventError),
+    #[error("Server redirection PDU error")]
+    ServerRedirectionError(#[from] ServerRedirectionError),
     #[error("Not enough bytes")]
     NotEnoughBytes,
 }
@@ -86,6 +86,39 @@ impl PduParsing for ServerDemandActive {
     }
 }
 
+/// [2.2.3.1] Server Deactivate All PDU
+///
+/// Sent by the server to initiate the Deactivation-Reactivation Sequence. The source descriptor
+/// is ignored by the client and is written as a single null byte.
+///
+/// [2.2.3.1]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpbcgr/8a29971a-df3c-48da-add2-8ed9a05edc89
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerDeactivateAll;
+
+impl PduParsing for ServerDeactivateAll {
+    type Error = CapabilitySetsError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let source_descriptor_length = stream.read_u16::<LittleEndian>()? as usize;
+        // The source descriptor is ignored
+        let mut source_descriptor_buffer = vec![0; source_descriptor_length];
+        stream.read_exact(source_descriptor_buffer.as_mut())?;
+
+        Ok(Self)
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        stream.write_u16::<LittleEndian>(NULL_TERMINATOR.len() as u16)?;
+        stream.write_all(NULL_TERMINATOR.as_bytes())?;
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        SOURCE_DESCRIPTOR_LENGTH_FIELD_SIZE + NULL_TERMINATOR.len()
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ClientConfirmActive {
     /// According to [MSDN](https://docs.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpbcgr/4e9722c3-ad83-43f5-af5a-529f73d88b48),
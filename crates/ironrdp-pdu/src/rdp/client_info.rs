@@ -11,6 +11,8 @@ use crate::utils::CharacterSet;
 use crate::{try_read_optional, try_write_optional, utils, PduParsing};
 
 const RECONNECT_COOKIE_LEN: usize = 28;
+const AUTO_RECONNECT_VERSION_1: u32 = 0x0000_0001;
+const AUTO_RECONNECT_SECURITY_VERIFIER_SIZE: usize = 16;
 const TIMEZONE_INFO_NAME_LEN: usize = 64;
 const COMPRESSION_TYPE_MASK: u32 = 0x0000_1E00;
 
@@ -233,7 +235,7 @@ pub struct ExtendedClientOptionalInfo {
     pub timezone: Option<TimezoneInfo>,
     pub session_id: Option<u32>,
     pub performance_flags: Option<PerformanceFlags>,
-    pub reconnect_cookie: Option<[u8; RECONNECT_COOKIE_LEN]>,
+    pub reconnect_cookie: Option<ClientAutoReconnectPacket>,
     // other fields are read by RdpVersion::Ten+
 }
 
@@ -266,7 +268,7 @@ impl PduParsing for ExtendedClientOptionalInfo {
 
         let mut reconnect_cookie = [0; RECONNECT_COOKIE_LEN];
         try_read_optional!(stream.read_exact(&mut reconnect_cookie), optional_data);
-        optional_data.reconnect_cookie = Some(reconnect_cookie);
+        optional_data.reconnect_cookie = Some(ClientAutoReconnectPacket::from_buffer(reconnect_cookie.as_ref())?);
 
         try_read_optional!(stream.read_u16::<LittleEndian>(), optional_data); // reserved1
         try_read_optional!(stream.read_u16::<LittleEndian>(), optional_data); // reserved2
@@ -280,9 +282,9 @@ impl PduParsing for ExtendedClientOptionalInfo {
         try_write_optional!(self.performance_flags, |value: &PerformanceFlags| {
             stream.write_u32::<LittleEndian>(value.bits())
         });
-        if let Some(reconnection_cookie) = self.reconnect_cookie {
-            stream.write_u16::<LittleEndian>(reconnection_cookie.len() as u16)?;
-            stream.write_all(reconnection_cookie.as_ref())?;
+        if let Some(ref reconnection_cookie) = self.reconnect_cookie {
+            stream.write_u16::<LittleEndian>(reconnection_cookie.buffer_length() as u16)?;
+            reconnection_cookie.to_buffer(&mut stream)?;
         }
 
         Ok(())
@@ -308,6 +310,54 @@ impl PduParsing for ExtendedClientOptionalInfo {
     }
 }
 
+/// ARC_CS_PRIVATE_PACKET
+///
+/// [Doc](https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpbcgr/b7dde1ce-9e80-4ba3-a5d6-6c6fe4fb7ff0)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientAutoReconnectPacket {
+    pub logon_id: u32,
+    /// HMAC-MD5 of the client random, keyed with the random bits of the server auto-reconnect packet.
+    pub security_verifier: [u8; AUTO_RECONNECT_SECURITY_VERIFIER_SIZE],
+}
+
+impl PduParsing for ClientAutoReconnectPacket {
+    type Error = ClientInfoError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let length = stream.read_u32::<LittleEndian>()?;
+        if length != RECONNECT_COOKIE_LEN as u32 {
+            return Err(ClientInfoError::InvalidReconnectCookie);
+        }
+
+        let version = stream.read_u32::<LittleEndian>()?;
+        if version != AUTO_RECONNECT_VERSION_1 {
+            return Err(ClientInfoError::InvalidReconnectCookie);
+        }
+
+        let logon_id = stream.read_u32::<LittleEndian>()?;
+        let mut security_verifier = [0; AUTO_RECONNECT_SECURITY_VERIFIER_SIZE];
+        stream.read_exact(&mut security_verifier)?;
+
+        Ok(Self {
+            logon_id,
+            security_verifier,
+        })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        stream.write_u32::<LittleEndian>(RECONNECT_COOKIE_LEN as u32)?;
+        stream.write_u32::<LittleEndian>(AUTO_RECONNECT_VERSION_1)?;
+        stream.write_u32::<LittleEndian>(self.logon_id)?;
+        stream.write_all(self.security_verifier.as_ref())?;
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        RECONNECT_COOKIE_LEN
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TimezoneInfo {
     pub bias: u32,
@@ -496,7 +546,7 @@ pub enum DayOfWeekOccurrence {
 }
 
 bitflags! {
-    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
     pub struct PerformanceFlags: u32 {
         const DISABLE_WALLPAPER = 0x0000_0001;
         const DISABLE_FULLWINDOWDRAG = 0x0000_0002;
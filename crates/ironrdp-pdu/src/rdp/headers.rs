@@ -7,9 +7,10 @@ use num_traits::{FromPrimitive, ToPrimitive};
 
 use crate::codecs::rfx::FrameAcknowledgePdu;
 use crate::input::InputEventPdu;
-use crate::rdp::capability_sets::{ClientConfirmActive, ServerDemandActive};
+use crate::rdp::capability_sets::{ClientConfirmActive, ServerDeactivateAll, ServerDemandActive};
 use crate::rdp::finalization_messages::{ControlPdu, FontPdu, MonitorLayoutPdu, SynchronizePdu};
 use crate::rdp::server_error_info::ServerSetErrorInfoPdu;
+use crate::rdp::server_redirection::ServerRedirectionPdu;
 use crate::rdp::session_info::SaveSessionInfoPdu;
 use crate::rdp::{client_info, RdpError};
 use crate::PduParsing;
@@ -21,6 +22,9 @@ const SHARE_CONTROL_HEADER_SIZE: usize = 2 * 3 + 4;
 
 const PROTOCOL_VERSION: u16 = 0x10;
 
+// Enhanced Security Server Redirection PDU
+const SERVER_REDIRECTION_PADDING_SIZE: usize = 2;
+
 // ShareDataHeader
 const PADDING_FIELD_SIZE: usize = 1;
 const STREAM_ID_FIELD_SIZE: usize = 1;
@@ -89,10 +93,13 @@ impl PduParsing for ShareControlHeader {
             share_id,
         };
 
-        if pdu_type == ShareControlPduType::DataPdu {
+        if matches!(
+            pdu_type,
+            ShareControlPduType::DataPdu | ShareControlPduType::ServerRedirect
+        ) {
             // Some windows version have an issue where
             // there is some padding not part of the inner unit.
-            // Consume that data
+            // Consume that data (the Server Redirection PDU also ends with an optional padding byte)
             let header_length = header.buffer_length();
 
             if header_length != total_length {
@@ -131,6 +138,8 @@ pub enum ShareControlPdu {
     ServerDemandActive(ServerDemandActive),
     ClientConfirmActive(ClientConfirmActive),
     Data(ShareDataHeader),
+    ServerDeactivateAll(ServerDeactivateAll),
+    ServerRedirect(ServerRedirectionPdu),
 }
 
 impl ShareControlPdu {
@@ -139,6 +148,8 @@ impl ShareControlPdu {
             ShareControlPdu::ServerDemandActive(_) => "Server Demand Active PDU",
             ShareControlPdu::ClientConfirmActive(_) => "Client Confirm Active PDU",
             ShareControlPdu::Data(_) => "Data PDU",
+            ShareControlPdu::ServerDeactivateAll(_) => "Server Deactivate All PDU",
+            ShareControlPdu::ServerRedirect(_) => "Server Redirection PDU",
         }
     }
 }
@@ -153,7 +164,16 @@ impl ShareControlPdu {
                 ClientConfirmActive::from_buffer(&mut stream)?,
             )),
             ShareControlPduType::DataPdu => Ok(ShareControlPdu::Data(ShareDataHeader::from_buffer(&mut stream)?)),
-            _ => Err(RdpError::UnexpectedShareControlPdu(share_type)),
+            ShareControlPduType::DeactivateAllPdu => Ok(ShareControlPdu::ServerDeactivateAll(
+                ServerDeactivateAll::from_buffer(&mut stream)?,
+            )),
+            ShareControlPduType::ServerRedirect => {
+                let _padding = stream.read_u16::<LittleEndian>()?;
+
+                Ok(ShareControlPdu::ServerRedirect(ServerRedirectionPdu::from_buffer(
+                    &mut stream,
+                )?))
+            }
         }
     }
     pub fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), RdpError> {
@@ -161,6 +181,11 @@ impl ShareControlPdu {
             ShareControlPdu::ServerDemandActive(pdu) => pdu.to_buffer(&mut stream).map_err(RdpError::from),
             ShareControlPdu::ClientConfirmActive(pdu) => pdu.to_buffer(&mut stream).map_err(RdpError::from),
             ShareControlPdu::Data(share_data_header) => share_data_header.to_buffer(&mut stream),
+            ShareControlPdu::ServerDeactivateAll(pdu) => pdu.to_buffer(&mut stream).map_err(RdpError::from),
+            ShareControlPdu::ServerRedirect(pdu) => {
+                stream.write_u16::<LittleEndian>(0)?; // padding
+                pdu.to_buffer(&mut stream).map_err(RdpError::from)
+            }
         }
     }
     pub fn buffer_length(&self) -> usize {
@@ -168,6 +193,8 @@ impl ShareControlPdu {
             ShareControlPdu::ServerDemandActive(pdu) => pdu.buffer_length(),
             ShareControlPdu::ClientConfirmActive(pdu) => pdu.buffer_length(),
             ShareControlPdu::Data(share_data_header) => share_data_header.buffer_length(),
+            ShareControlPdu::ServerDeactivateAll(pdu) => pdu.buffer_length(),
+            ShareControlPdu::ServerRedirect(pdu) => SERVER_REDIRECTION_PADDING_SIZE + pdu.buffer_length(),
         }
     }
     pub fn share_header_type(&self) -> ShareControlPduType {
@@ -175,6 +202,8 @@ impl ShareControlPdu {
             ShareControlPdu::ServerDemandActive(_) => ShareControlPduType::DemandActivePdu,
             ShareControlPdu::ClientConfirmActive(_) => ShareControlPduType::ConfirmActivePdu,
             ShareControlPdu::Data(_) => ShareControlPduType::DataPdu,
+            ShareControlPdu::ServerDeactivateAll(_) => ShareControlPduType::DeactivateAllPdu,
+            ShareControlPdu::ServerRedirect(_) => ShareControlPduType::ServerRedirect,
         }
     }
 }
@@ -194,7 +223,7 @@ impl PduParsing for ShareDataHeader {
         let _padding = stream.read_u8()?;
         let stream_priority = StreamPriority::from_u8(stream.read_u8()?)
             .ok_or_else(|| RdpError::InvalidShareDataHeader(String::from("Invalid stream priority")))?;
-        let _uncompressed_length = stream.read_u16::<LittleEndian>()?;
+        let uncompressed_length = stream.read_u16::<LittleEndian>()?;
         let pdu_type = ShareDataPduType::from_u8(stream.read_u8()?)
             .ok_or_else(|| RdpError::InvalidShareDataHeader(String::from("Invalid pdu type")))?;
         let compression_flags_with_type = stream.read_u8()?;
@@ -206,7 +235,19 @@ impl PduParsing for ShareDataHeader {
                 .ok_or_else(|| RdpError::InvalidShareDataHeader(String::from("Invalid compression type")))?;
         let _compressed_length = stream.read_u16::<LittleEndian>()?;
 
-        let share_data_pdu = ShareDataPdu::from_type(&mut stream, pdu_type)?;
+        // The data is decompressed by the session, which keeps the compression history
+        let share_data_pdu = if compression_flags.contains(CompressionFlags::COMPRESSED) {
+            let mut data = Vec::new();
+            stream.read_to_end(&mut data)?;
+
+            ShareDataPdu::Compressed(CompressedShareDataPdu {
+                pdu_type,
+                uncompressed_length,
+                data,
+            })
+        } else {
+            ShareDataPdu::from_type(&mut stream, pdu_type)?
+        };
 
         Ok(Self {
             share_data_pdu,
@@ -217,27 +258,31 @@ impl PduParsing for ShareDataHeader {
     }
 
     fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
-        if self.compression_flags.is_empty() {
-            let compression_flags_with_type = self.compression_flags.bits() | self.compression_type.to_u8().unwrap();
-
-            stream.write_u8(0)?; // padding
-            stream.write_u8(self.stream_priority.to_u8().unwrap())?;
-            stream.write_u16::<LittleEndian>(
-                (self.share_data_pdu.buffer_length()
-                    + PDU_TYPE_FIELD_SIZE
-                    + COMPRESSION_TYPE_FIELD_SIZE
-                    + COMPRESSED_LENGTH_FIELD_SIZE) as u16,
-            )?;
-            stream.write_u8(self.share_data_pdu.share_header_type().to_u8().unwrap())?;
-            stream.write_u8(compression_flags_with_type)?;
-            stream.write_u16::<LittleEndian>(0)?; // compressed length
-
-            self.share_data_pdu.to_buffer(&mut stream)
-        } else {
-            Err(RdpError::InvalidShareDataHeader(String::from(
-                "Compression is not implemented",
-            )))
-        }
+        let length = (self.share_data_pdu.buffer_length()
+            + PDU_TYPE_FIELD_SIZE
+            + COMPRESSION_TYPE_FIELD_SIZE
+            + COMPRESSED_LENGTH_FIELD_SIZE) as u16;
+
+        let (uncompressed_length, compressed_length) = match (&self.share_data_pdu, self.compression_flags.is_empty()) {
+            (ShareDataPdu::Compressed(pdu), false) => (pdu.uncompressed_length, length),
+            (ShareDataPdu::Compressed(_), true) | (_, false) => {
+                return Err(RdpError::InvalidShareDataHeader(String::from(
+                    "Compression is not implemented",
+                )))
+            }
+            (_, true) => (length, 0),
+        };
+
+        let compression_flags_with_type = self.compression_flags.bits() | self.compression_type.to_u8().unwrap();
+
+        stream.write_u8(0)?; // padding
+        stream.write_u8(self.stream_priority.to_u8().unwrap())?;
+        stream.write_u16::<LittleEndian>(uncompressed_length)?;
+        stream.write_u8(self.share_data_pdu.share_header_type().to_u8().unwrap())?;
+        stream.write_u8(compression_flags_with_type)?;
+        stream.write_u16::<LittleEndian>(compressed_length)?;
+
+        self.share_data_pdu.to_buffer(&mut stream)
     }
 
     fn buffer_length(&self) -> usize {
@@ -262,6 +307,17 @@ pub enum ShareDataPdu {
     FrameAcknowledge(FrameAcknowledgePdu),
     ServerSetErrorInfo(ServerSetErrorInfoPdu),
     Input(InputEventPdu),
+    Compressed(CompressedShareDataPdu),
+}
+
+/// Data of a Share Data PDU compressed with the bulk compression, decoded with [`ShareDataPdu::from_type`] once
+/// decompressed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompressedShareDataPdu {
+    pub pdu_type: ShareDataPduType,
+    /// Value of the uncompressedLength field of the Share Data Header
+    pub uncompressed_length: u16,
+    pub data: Vec<u8>,
 }
 
 impl ShareDataPdu {
@@ -276,6 +332,7 @@ impl ShareDataPdu {
             ShareDataPdu::FrameAcknowledge(_) => "Frame Acknowledge PDU",
             ShareDataPdu::ServerSetErrorInfo(_) => "Server Set Error Info PDU",
             ShareDataPdu::Input(_) => "Server Input PDU",
+            ShareDataPdu::Compressed(_) => "Compressed Share Data PDU",
         }
     }
 }
@@ -331,6 +388,7 @@ impl ShareDataPdu {
             ShareDataPdu::FrameAcknowledge(pdu) => pdu.to_buffer(&mut stream).map_err(RdpError::from),
             ShareDataPdu::ServerSetErrorInfo(pdu) => pdu.to_buffer(&mut stream).map_err(RdpError::from),
             ShareDataPdu::Input(pdu) => pdu.to_buffer(&mut stream).map_err(RdpError::from),
+            ShareDataPdu::Compressed(pdu) => stream.write_all(&pdu.data).map_err(RdpError::from),
         }
     }
 
@@ -344,6 +402,7 @@ impl ShareDataPdu {
             ShareDataPdu::FrameAcknowledge(pdu) => pdu.buffer_length(),
             ShareDataPdu::ServerSetErrorInfo(pdu) => pdu.buffer_length(),
             ShareDataPdu::Input(pdu) => pdu.buffer_length(),
+            ShareDataPdu::Compressed(pdu) => pdu.data.len(),
         }
     }
     pub fn share_header_type(&self) -> ShareDataPduType {
@@ -357,6 +416,7 @@ impl ShareDataPdu {
             ShareDataPdu::FrameAcknowledge(_) => ShareDataPduType::FrameAcknowledgePdu,
             ShareDataPdu::ServerSetErrorInfo(_) => ShareDataPduType::SetErrorInfoPdu,
             ShareDataPdu::Input(_) => ShareDataPduType::Input,
+            ShareDataPdu::Compressed(pdu) => pdu.pdu_type,
         }
     }
 }
@@ -20,9 +20,11 @@ mod server_license_request;
 mod server_platform_challenge;
 mod server_upgrade_license;
 
+pub(crate) use self::client_new_license_request::{compute_master_secret, salted_hash};
 pub use self::client_new_license_request::{ClientNewLicenseRequest, PLATFORM_ID};
 pub use self::client_platform_challenge_response::ClientPlatformChallengeResponse;
 pub use self::licensing_error_message::{LicenseErrorCode, LicensingErrorMessage, LicensingStateTransition};
+pub(crate) use self::server_license_request::ServerCertificate;
 pub use self::server_license_request::{InitialMessageType, InitialServerLicenseMessage, ServerLicenseRequest};
 pub use self::server_platform_challenge::ServerPlatformChallenge;
 pub use self::server_upgrade_license::ServerUpgradeLicense;
@@ -6,6 +6,7 @@ use std::io;
 
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use cert::{CertificateType, ProprietaryCertificate, X509CertificateChain};
+use num_bigint::BigUint;
 
 use super::{
     BasicSecurityHeader, BasicSecurityHeaderFlags, BlobHeader, BlobType, LicenseErrorCode, LicenseHeader,
@@ -13,6 +14,7 @@ use super::{
     BLOB_LENGTH_SIZE, BLOB_TYPE_SIZE, KEY_EXCHANGE_ALGORITHM_RSA, PREAMBLE_SIZE, RANDOM_NUMBER_SIZE,
     UTF16_NULL_TERMINATOR_SIZE, UTF8_NULL_TERMINATOR_SIZE,
 };
+use crate::crypto::rsa::decode_public_key;
 use crate::{utils, PduParsing};
 
 const CERT_VERSION_FIELD_SIZE: usize = 4;
@@ -297,6 +299,17 @@ impl ServerCertificate {
             }
         }
     }
+
+    /// Returns the modulus and the exponent of the RSA public key.
+    pub(crate) fn rsa_public_key(&self) -> Result<(BigUint, BigUint), ServerLicenseError> {
+        match &self.certificate {
+            CertificateType::Proprietary(certificate) => Ok((
+                BigUint::from_bytes_le(&certificate.public_key.modulus),
+                BigUint::from(certificate.public_key.public_exponent),
+            )),
+            CertificateType::X509(_) => Ok(decode_public_key(&self.get_public_key()?)?),
+        }
+    }
 }
 
 impl PduParsing for ServerCertificate {
@@ -0,0 +1,410 @@
+//! Server Redirection Packet (RDP_SERVER_REDIRECTION_PACKET), sent by an RD Connection Broker to have the client
+//! reconnect to another server of the farm
+
+use std::io;
+
+use bitflags::bitflags;
+use byteorder::{LittleEndian, ReadBytesExt as _, WriteBytesExt as _};
+use thiserror::Error;
+
+use crate::{utils, PduParsing};
+
+const SEC_REDIRECTION_PKT: u16 = 0x0400;
+
+const HEADER_SIZE: usize = 2 /* flags */ + 2 /* length */ + 4 /* sessionId */ + 4 /* redirFlags */;
+const FIELD_LENGTH_SIZE: usize = 4;
+const ADDRESS_COUNT_SIZE: usize = 4;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerRedirectionPdu {
+    /// ID of the session to reconnect to, sent back in the Client Cluster Data of the next connection
+    pub session_id: u32,
+    pub flags: ServerRedirectionFlags,
+    pub target_net_address: Option<String>,
+    /// Routing token to send back in the X.224 Connection Request PDU of the next connection
+    pub load_balance_info: Option<Vec<u8>>,
+    pub username: Option<String>,
+    pub domain: Option<String>,
+    /// Password of the user, or an opaque cookie, possibly encrypted with the public key of the target server
+    /// (see `ServerRedirectionFlags::PASSWORD_IS_PK_ENCRYPTED`)
+    pub password: Option<Vec<u8>>,
+    pub target_fqdn: Option<String>,
+    pub target_netbios_name: Option<String>,
+    pub tsv_url: Option<Vec<u8>>,
+    /// Base64-encoded GUID, in UTF-16, identifying the redirection
+    pub redirection_guid: Option<Vec<u8>>,
+    pub target_certificate: Option<Vec<u8>>,
+    pub target_net_addresses: Option<Vec<String>>,
+}
+
+impl ServerRedirectionPdu {
+    /// Returns the address of the server to reconnect to, by order of preference.
+    pub fn target_address(&self) -> Option<&str> {
+        self.target_net_address
+            .as_deref()
+            .or_else(|| {
+                self.target_net_addresses
+                    .as_ref()
+                    .and_then(|addresses| addresses.first())
+                    .map(String::as_str)
+            })
+            .or(self.target_fqdn.as_deref())
+            .or(self.target_netbios_name.as_deref())
+    }
+
+    fn fields_length(&self) -> usize {
+        let unicode_length = |value: &Option<String>| {
+            value
+                .as_ref()
+                .map_or(0, |value| FIELD_LENGTH_SIZE + unicode_string_length(value))
+        };
+        let bytes_length = |value: &Option<Vec<u8>>| value.as_ref().map_or(0, |value| FIELD_LENGTH_SIZE + value.len());
+
+        unicode_length(&self.target_net_address)
+            + bytes_length(&self.load_balance_info)
+            + unicode_length(&self.username)
+            + unicode_length(&self.domain)
+            + bytes_length(&self.password)
+            + unicode_length(&self.target_fqdn)
+            + unicode_length(&self.target_netbios_name)
+            + bytes_length(&self.tsv_url)
+            + bytes_length(&self.redirection_guid)
+            + bytes_length(&self.target_certificate)
+            + self.target_net_addresses.as_ref().map_or(0, |addresses| {
+                FIELD_LENGTH_SIZE + target_net_addresses_length(addresses)
+            })
+    }
+}
+
+impl PduParsing for ServerRedirectionPdu {
+    type Error = ServerRedirectionError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let security_flags = stream.read_u16::<LittleEndian>()?;
+        if security_flags != SEC_REDIRECTION_PKT {
+            return Err(ServerRedirectionError::InvalidSecurityFlags(security_flags));
+        }
+
+        let length = usize::from(stream.read_u16::<LittleEndian>()?);
+        let session_id = stream.read_u32::<LittleEndian>()?;
+        let flags = ServerRedirectionFlags::from_bits_truncate(stream.read_u32::<LittleEndian>()?);
+
+        // The fields are followed by optional padding, included in the length
+        let fields_length = length
+            .checked_sub(HEADER_SIZE)
+            .ok_or(ServerRedirectionError::InvalidLength(length))?;
+        let mut fields = vec![0; fields_length];
+        stream.read_exact(&mut fields)?;
+        let mut fields = fields.as_slice();
+
+        let target_net_address = read_unicode_field(&mut fields, flags, ServerRedirectionFlags::TARGET_NET_ADDRESS)?;
+        let load_balance_info = read_bytes_field(&mut fields, flags, ServerRedirectionFlags::LOAD_BALANCE_INFO)?;
+        let username = read_unicode_field(&mut fields, flags, ServerRedirectionFlags::USERNAME)?;
+        let domain = read_unicode_field(&mut fields, flags, ServerRedirectionFlags::DOMAIN)?;
+        let password = read_bytes_field(&mut fields, flags, ServerRedirectionFlags::PASSWORD)?;
+        let target_fqdn = read_unicode_field(&mut fields, flags, ServerRedirectionFlags::TARGET_FQDN)?;
+        let target_netbios_name = read_unicode_field(&mut fields, flags, ServerRedirectionFlags::TARGET_NETBIOS_NAME)?;
+        let tsv_url = read_bytes_field(&mut fields, flags, ServerRedirectionFlags::CLIENT_TSV_URL)?;
+        let redirection_guid = read_bytes_field(&mut fields, flags, ServerRedirectionFlags::REDIRECTION_GUID)?;
+        let target_certificate = read_bytes_field(&mut fields, flags, ServerRedirectionFlags::TARGET_CERTIFICATE)?;
+
+        let target_net_addresses = if flags.contains(ServerRedirectionFlags::TARGET_NET_ADDRESSES) {
+            let _length = fields.read_u32::<LittleEndian>()?;
+            let address_count = fields.read_u32::<LittleEndian>()?;
+
+            let addresses = (0..address_count)
+                .map(|_| {
+                    let length = read_field_length(&mut fields)?;
+                    Ok(read_unicode_string(&mut fields, length)?)
+                })
+                .collect::<Result<Vec<_>, ServerRedirectionError>>()?;
+
+            Some(addresses)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            session_id,
+            flags,
+            target_net_address,
+            load_balance_info,
+            username,
+            domain,
+            password,
+            target_fqdn,
+            target_netbios_name,
+            tsv_url,
+            redirection_guid,
+            target_certificate,
+            target_net_addresses,
+        })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        let length = u16::try_from(self.buffer_length())
+            .map_err(|_| ServerRedirectionError::InvalidLength(self.buffer_length()))?;
+
+        stream.write_u16::<LittleEndian>(SEC_REDIRECTION_PKT)?;
+        stream.write_u16::<LittleEndian>(length)?;
+        stream.write_u32::<LittleEndian>(self.session_id)?;
+        stream.write_u32::<LittleEndian>(self.flags.bits())?;
+
+        write_unicode_field(&mut stream, self.target_net_address.as_deref())?;
+        write_bytes_field(&mut stream, self.load_balance_info.as_deref())?;
+        write_unicode_field(&mut stream, self.username.as_deref())?;
+        write_unicode_field(&mut stream, self.domain.as_deref())?;
+        write_bytes_field(&mut stream, self.password.as_deref())?;
+        write_unicode_field(&mut stream, self.target_fqdn.as_deref())?;
+        write_unicode_field(&mut stream, self.target_netbios_name.as_deref())?;
+        write_bytes_field(&mut stream, self.tsv_url.as_deref())?;
+        write_bytes_field(&mut stream, self.redirection_guid.as_deref())?;
+        write_bytes_field(&mut stream, self.target_certificate.as_deref())?;
+
+        if let Some(addresses) = &self.target_net_addresses {
+            stream.write_u32::<LittleEndian>(target_net_addresses_length(addresses) as u32)?;
+            stream.write_u32::<LittleEndian>(addresses.len() as u32)?;
+
+            for address in addresses {
+                write_unicode_field(&mut stream, Some(address))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        HEADER_SIZE + self.fields_length()
+    }
+}
+
+bitflags! {
+    /// Fields present in the Server Redirection PDU, and a few options
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct ServerRedirectionFlags: u32 {
+        const TARGET_NET_ADDRESS = 0x0000_0001;
+        const LOAD_BALANCE_INFO = 0x0000_0002;
+        const USERNAME = 0x0000_0004;
+        const DOMAIN = 0x0000_0008;
+        const PASSWORD = 0x0000_0010;
+        const DONT_STORE_USERNAME = 0x0000_0020;
+        const SMARTCARD_LOGON = 0x0000_0040;
+        /// The PDU is only informative, and the client must not reconnect
+        const NO_REDIRECT = 0x0000_0080;
+        const TARGET_FQDN = 0x0000_0100;
+        const TARGET_NETBIOS_NAME = 0x0000_0200;
+        const TARGET_NET_ADDRESSES = 0x0000_0800;
+        const CLIENT_TSV_URL = 0x0000_1000;
+        const SERVER_TSV_CAPABLE = 0x0000_2000;
+        const PASSWORD_IS_PK_ENCRYPTED = 0x0000_4000;
+        const REDIRECTION_GUID = 0x0000_8000;
+        const TARGET_CERTIFICATE = 0x0001_0000;
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ServerRedirectionError {
+    #[error("IO error")]
+    IOError(#[from] io::Error),
+    #[error("invalid security flags: 0x{0:04X}")]
+    InvalidSecurityFlags(u16),
+    #[error("invalid length: {0}")]
+    InvalidLength(usize),
+}
+
+fn unicode_string_length(value: &str) -> usize {
+    (value.encode_utf16().count() + 1) * 2
+}
+
+fn target_net_addresses_length(addresses: &[String]) -> usize {
+    ADDRESS_COUNT_SIZE
+        + addresses
+            .iter()
+            .map(|address| FIELD_LENGTH_SIZE + unicode_string_length(address))
+            .sum::<usize>()
+}
+
+/// Reads the length of a field, which cannot exceed the remaining bytes of the fields.
+fn read_field_length(fields: &mut &[u8]) -> Result<usize, ServerRedirectionError> {
+    let length = fields.read_u32::<LittleEndian>()? as usize;
+
+    if length > fields.len() {
+        return Err(ServerRedirectionError::InvalidLength(length));
+    }
+
+    Ok(length)
+}
+
+fn read_bytes_field(
+    fields: &mut &[u8],
+    flags: ServerRedirectionFlags,
+    field_flag: ServerRedirectionFlags,
+) -> Result<Option<Vec<u8>>, ServerRedirectionError> {
+    if !flags.contains(field_flag) {
+        return Ok(None);
+    }
+
+    let length = read_field_length(fields)?;
+    let (value, rest) = fields.split_at(length);
+    *fields = rest;
+
+    Ok(Some(value.to_vec()))
+}
+
+fn read_unicode_field(
+    fields: &mut &[u8],
+    flags: ServerRedirectionFlags,
+    field_flag: ServerRedirectionFlags,
+) -> Result<Option<String>, ServerRedirectionError> {
+    if !flags.contains(field_flag) {
+        return Ok(None);
+    }
+
+    let length = read_field_length(fields)?;
+
+    Ok(Some(read_unicode_string(fields, length)?))
+}
+
+/// Reads a UTF-16 string whose length, in bytes, includes the null terminator.
+fn read_unicode_string(stream: impl io::Read, length: usize) -> io::Result<String> {
+    utils::read_string(stream, length, utils::CharacterSet::Unicode, false)
+}
+
+fn write_bytes_field(mut stream: impl io::Write, value: Option<&[u8]>) -> io::Result<()> {
+    if let Some(value) = value {
+        stream.write_u32::<LittleEndian>(value.len() as u32)?;
+        stream.write_all(value)?;
+    }
+
+    Ok(())
+}
+
+fn write_unicode_field(mut stream: impl io::Write, value: Option<&str>) -> io::Result<()> {
+    if let Some(value) = value {
+        stream.write_u32::<LittleEndian>(unicode_string_length(value) as u32)?;
+        utils::write_string_with_null_terminator(stream, value, utils::CharacterSet::Unicode)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[rustfmt::skip]
+    const SERVER_REDIRECTION_BUFFER: [u8; 74] = [
+        0x00, 0x04, // flags
+        0x4a, 0x00, // length
+        0x2a, 0x00, 0x00, 0x00, // sessionId
+        0x03, 0x08, 0x00, 0x00, // redirFlags
+        0x0a, 0x00, 0x00, 0x00, // targetNetAddressLength
+        b'1', 0x00, b'0', 0x00, b'.', 0x00, b'1', 0x00, 0x00, 0x00, // targetNetAddress
+        0x0c, 0x00, 0x00, 0x00, // loadBalanceInfoLength
+        b'C', b'o', b'o', b'k', b'i', b'e', b':', b' ', b'm', b's', b't', b's', // loadBalanceInfo (truncated)
+        0x1c, 0x00, 0x00, 0x00, // targetNetAddressesLength
+        0x02, 0x00, 0x00, 0x00, // addressCount
+        0x08, 0x00, 0x00, 0x00, // addressLength
+        b'a', 0x00, b'b', 0x00, b'c', 0x00, 0x00, 0x00, // address
+        0x08, 0x00, 0x00, 0x00, // addressLength
+        b'x', 0x00, b'y', 0x00, b'z', 0x00, 0x00, 0x00, // address
+    ];
+
+    fn server_redirection() -> ServerRedirectionPdu {
+        ServerRedirectionPdu {
+            session_id: 42,
+            flags: ServerRedirectionFlags::TARGET_NET_ADDRESS
+                | ServerRedirectionFlags::LOAD_BALANCE_INFO
+                | ServerRedirectionFlags::TARGET_NET_ADDRESSES,
+            target_net_address: Some(String::from("10.1")),
+            load_balance_info: Some(b"Cookie: msts".to_vec()),
+            username: None,
+            domain: None,
+            password: None,
+            target_fqdn: None,
+            target_netbios_name: None,
+            tsv_url: None,
+            redirection_guid: None,
+            target_certificate: None,
+            target_net_addresses: Some(vec![String::from("abc"), String::from("xyz")]),
+        }
+    }
+
+    #[test]
+    fn from_buffer_correctly_parses_server_redirection() {
+        assert_eq!(
+            server_redirection(),
+            ServerRedirectionPdu::from_buffer(SERVER_REDIRECTION_BUFFER.as_ref()).unwrap()
+        );
+    }
+
+    #[test]
+    fn to_buffer_correctly_serializes_server_redirection() {
+        let mut buffer = Vec::new();
+        server_redirection().to_buffer(&mut buffer).unwrap();
+
+        assert_eq!(SERVER_REDIRECTION_BUFFER.as_ref(), buffer.as_slice());
+    }
+
+    #[test]
+    fn buffer_length_is_correct_for_server_redirection() {
+        assert_eq!(SERVER_REDIRECTION_BUFFER.len(), server_redirection().buffer_length());
+    }
+
+    #[test]
+    fn from_buffer_skips_the_padding() {
+        let mut buffer = SERVER_REDIRECTION_BUFFER.to_vec();
+        buffer[2] += 8;
+        buffer.extend_from_slice(&[0; 8]);
+
+        let mut stream = buffer.as_slice();
+        ServerRedirectionPdu::from_buffer(&mut stream).unwrap();
+
+        assert!(stream.is_empty());
+    }
+
+    #[test]
+    fn target_address_prefers_the_target_net_address() {
+        let mut redirection = server_redirection();
+        assert_eq!(redirection.target_address(), Some("10.1"));
+
+        redirection.target_net_address = None;
+        assert_eq!(redirection.target_address(), Some("abc"));
+    }
+
+    #[test]
+    fn from_buffer_rejects_a_field_longer_than_the_pdu() {
+        let mut buffer = SERVER_REDIRECTION_BUFFER.to_vec();
+        // loadBalanceInfoLength
+        buffer[26..30].copy_from_slice(&u32::MAX.to_le_bytes());
+
+        assert!(matches!(
+            ServerRedirectionPdu::from_buffer(buffer.as_slice()),
+            Err(ServerRedirectionError::InvalidLength(length)) if length == u32::MAX as usize
+        ));
+    }
+
+    #[test]
+    fn from_buffer_rejects_a_unicode_field_longer_than_the_pdu() {
+        let mut buffer = SERVER_REDIRECTION_BUFFER.to_vec();
+        // targetNetAddressLength
+        buffer[12..16].copy_from_slice(&0x8000_0000_u32.to_le_bytes());
+
+        assert!(matches!(
+            ServerRedirectionPdu::from_buffer(buffer.as_slice()),
+            Err(ServerRedirectionError::InvalidLength(0x8000_0000))
+        ));
+    }
+
+    #[test]
+    fn from_buffer_rejects_an_address_longer_than_the_pdu() {
+        let mut buffer = SERVER_REDIRECTION_BUFFER.to_vec();
+        // addressLength of the last address
+        buffer[62..66].copy_from_slice(&9_u32.to_le_bytes());
+
+        assert!(matches!(
+            ServerRedirectionPdu::from_buffer(buffer.as_slice()),
+            Err(ServerRedirectionError::InvalidLength(9))
+        ));
+    }
+}
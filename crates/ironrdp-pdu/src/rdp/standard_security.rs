@@ -0,0 +1,415 @@
+//! Standard RDP Security: RSA key exchange, and RC4 encryption of the PDUs with MAC signatures
+//!
+//! The session keys are derived from the client and server randoms as described in [MS-RDPBCGR] 5.3.5, and are
+//! updated every 4096 packets. The FIPS encryption method is not supported.
+
+use std::io;
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use md5::Digest;
+use thiserror::Error;
+
+use crate::crypto::rc4::Rc4;
+use crate::crypto::rsa::encrypt_with_public_key_components;
+use crate::gcc::EncryptionMethod;
+use crate::rdp::headers::{BasicSecurityHeader, BasicSecurityHeaderFlags, BASIC_SECURITY_HEADER_SIZE};
+use crate::rdp::server_license::{compute_master_secret, salted_hash, ServerCertificate, ServerLicenseError};
+use crate::PduParsing;
+
+pub const CLIENT_RANDOM_SIZE: usize = 32;
+pub const SIGNATURE_SIZE: usize = 8;
+
+const SECURITY_EXCHANGE_LENGTH_FIELD_SIZE: usize = 4;
+const PREMASTER_SECRET_RANDOM_SIZE: usize = 24;
+const KEY_UPDATE_INTERVAL: u32 = 4096;
+
+const PAD1: [u8; 40] = [0x36; 40];
+const PAD2: [u8; 48] = [0x5C; 48];
+const SALT_40_BIT: [u8; 3] = [0xD1, 0x26, 0x9E];
+const SALT_56_BIT: [u8; 1] = [0xD1];
+
+/// Security Exchange PDU, carrying the client random encrypted with the public key of the server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecurityExchangePdu {
+    pub encrypted_client_random: Vec<u8>,
+}
+
+impl SecurityExchangePdu {
+    /// Encrypts the client random with the public key of the server certificate sent in the Server Security Data.
+    pub fn new(client_random: &[u8], server_certificate: &[u8]) -> Result<Self, StandardSecurityError> {
+        let server_certificate = ServerCertificate::from_buffer(server_certificate)?;
+        let (modulus, exponent) = server_certificate.rsa_public_key()?;
+
+        Ok(Self {
+            encrypted_client_random: encrypt_with_public_key_components(client_random, &modulus, &exponent),
+        })
+    }
+}
+
+impl PduParsing for SecurityExchangePdu {
+    type Error = StandardSecurityError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let security_header =
+            BasicSecurityHeader::from_buffer(&mut stream).map_err(|_| StandardSecurityError::InvalidSecurityHeader)?;
+        if !security_header.flags.contains(BasicSecurityHeaderFlags::EXCHANGE_PKT) {
+            return Err(StandardSecurityError::InvalidSecurityHeader);
+        }
+
+        let length = stream.read_u32::<LittleEndian>()?;
+        let mut encrypted_client_random = vec![0; length as usize];
+        stream.read_exact(&mut encrypted_client_random)?;
+
+        Ok(Self {
+            encrypted_client_random,
+        })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        let security_header = BasicSecurityHeader {
+            flags: BasicSecurityHeaderFlags::EXCHANGE_PKT,
+        };
+        security_header
+            .to_buffer(&mut stream)
+            .map_err(|_| StandardSecurityError::InvalidSecurityHeader)?;
+
+        stream.write_u32::<LittleEndian>(self.encrypted_client_random.len() as u32)?;
+        stream.write_all(&self.encrypted_client_random)?;
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        BASIC_SECURITY_HEADER_SIZE + SECURITY_EXCHANGE_LENGTH_FIELD_SIZE + self.encrypted_client_random.len()
+    }
+}
+
+/// Session keys of the client, used to encrypt the PDUs sent to the server and to decrypt the PDUs received from it
+#[derive(Debug, Clone)]
+pub struct EncryptionContext {
+    encryption_method: EncryptionMethod,
+    mac_key: Vec<u8>,
+    encrypt: SessionKey,
+    decrypt: SessionKey,
+}
+
+impl EncryptionContext {
+    pub fn new(
+        encryption_method: EncryptionMethod,
+        client_random: &[u8],
+        server_random: &[u8],
+    ) -> Result<Self, StandardSecurityError> {
+        let key_length = match encryption_method {
+            EncryptionMethod::BIT_128 => 16,
+            EncryptionMethod::BIT_40 | EncryptionMethod::BIT_56 => 8,
+            _ => return Err(StandardSecurityError::UnsupportedEncryptionMethod(encryption_method)),
+        };
+
+        if client_random.len() < PREMASTER_SECRET_RANDOM_SIZE || server_random.len() < PREMASTER_SECRET_RANDOM_SIZE {
+            return Err(StandardSecurityError::InvalidRandomLength);
+        }
+
+        let premaster_secret = [
+            &client_random[..PREMASTER_SECRET_RANDOM_SIZE],
+            &server_random[..PREMASTER_SECRET_RANDOM_SIZE],
+        ]
+        .concat();
+        let master_secret = compute_master_secret(&premaster_secret, client_random, server_random);
+
+        let session_key_blob = [
+            salted_hash(&master_secret, client_random, server_random, b"X"),
+            salted_hash(&master_secret, client_random, server_random, b"YY"),
+            salted_hash(&master_secret, client_random, server_random, b"ZZZ"),
+        ]
+        .concat();
+
+        let final_hash = |key: &[u8]| {
+            let mut md5 = md5::Md5::new();
+            md5.update([key, client_random, server_random].concat().as_slice());
+            md5.finalize().to_vec()
+        };
+
+        let mut mac_key = session_key_blob[..key_length].to_vec();
+        salt_key(&mut mac_key, encryption_method);
+
+        let mut decrypt_key = final_hash(&session_key_blob[16..32]);
+        decrypt_key.truncate(key_length);
+        salt_key(&mut decrypt_key, encryption_method);
+
+        let mut encrypt_key = final_hash(&session_key_blob[32..48]);
+        encrypt_key.truncate(key_length);
+        salt_key(&mut encrypt_key, encryption_method);
+
+        Ok(Self {
+            encryption_method,
+            mac_key,
+            encrypt: SessionKey::new(encrypt_key),
+            decrypt: SessionKey::new(decrypt_key),
+        })
+    }
+
+    pub fn encryption_method(&self) -> EncryptionMethod {
+        self.encryption_method
+    }
+
+    /// Encrypts the data sent to the server, and returns the MAC signature computed over the plain data.
+    pub fn encrypt(&mut self, data: &[u8]) -> ([u8; SIGNATURE_SIZE], Vec<u8>) {
+        let signature = mac_signature(&self.mac_key, data, None);
+        let encrypted = self.encrypt.process(data, self.encryption_method);
+
+        (signature, encrypted)
+    }
+
+    /// Decrypts the data received from the server, and checks its MAC signature.
+    ///
+    /// `salted` tells whether the signature includes the encryption count (`SECURE_CHECKSUM` flag).
+    pub fn decrypt(&mut self, data: &[u8], signature: &[u8], salted: bool) -> Result<Vec<u8>, StandardSecurityError> {
+        let encryption_count = self.decrypt.encryption_count;
+        let decrypted = self.decrypt.process(data, self.encryption_method);
+
+        let expected_signature = mac_signature(&self.mac_key, &decrypted, salted.then_some(encryption_count));
+        if expected_signature != signature {
+            return Err(StandardSecurityError::InvalidMacSignature);
+        }
+
+        Ok(decrypted)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct SessionKey {
+    initial_key: Vec<u8>,
+    current_key: Vec<u8>,
+    rc4: Rc4,
+    /// Number of packets processed with the current key
+    use_count: u32,
+    /// Total number of packets processed, used by the salted MAC signatures
+    encryption_count: u32,
+}
+
+impl SessionKey {
+    fn new(key: Vec<u8>) -> Self {
+        Self {
+            rc4: Rc4::new(&key),
+            initial_key: key.clone(),
+            current_key: key,
+            use_count: 0,
+            encryption_count: 0,
+        }
+    }
+
+    fn process(&mut self, data: &[u8], encryption_method: EncryptionMethod) -> Vec<u8> {
+        if self.use_count == KEY_UPDATE_INTERVAL {
+            self.update(encryption_method);
+        }
+
+        self.use_count += 1;
+        self.encryption_count = self.encryption_count.wrapping_add(1);
+
+        self.rc4.process(data)
+    }
+
+    // According to [MS-RDPBCGR] 5.3.7.1 Non-FIPS
+    fn update(&mut self, encryption_method: EncryptionMethod) {
+        let mut sha1 = sha1::Sha1::new();
+        sha1.update(
+            [&self.initial_key, PAD1.as_slice(), &self.current_key]
+                .concat()
+                .as_slice(),
+        );
+        let sha_component = sha1.finalize();
+
+        let mut md5 = md5::Md5::new();
+        md5.update(
+            [&self.initial_key, PAD2.as_slice(), sha_component.as_ref()]
+                .concat()
+                .as_slice(),
+        );
+        let mut temp_key = md5.finalize().to_vec();
+        temp_key.truncate(self.initial_key.len());
+
+        let mut new_key = Rc4::new(&temp_key).process(&temp_key);
+        salt_key(&mut new_key, encryption_method);
+
+        self.rc4 = Rc4::new(&new_key);
+        self.current_key = new_key;
+        self.use_count = 0;
+    }
+}
+
+fn salt_key(key: &mut [u8], encryption_method: EncryptionMethod) {
+    if encryption_method == EncryptionMethod::BIT_40 {
+        key[..SALT_40_BIT.len()].copy_from_slice(&SALT_40_BIT);
+    } else if encryption_method == EncryptionMethod::BIT_56 {
+        key[..SALT_56_BIT.len()].copy_from_slice(&SALT_56_BIT);
+    }
+}
+
+// According to [MS-RDPBCGR] 5.3.6.1 and 5.3.6.1.1, the encryption count being only included in salted signatures
+fn mac_signature(mac_key: &[u8], data: &[u8], encryption_count: Option<u32>) -> [u8; SIGNATURE_SIZE] {
+    let mut sha1 = sha1::Sha1::new();
+    sha1.update(mac_key);
+    sha1.update(PAD1);
+    sha1.update((data.len() as u32).to_le_bytes());
+    sha1.update(data);
+    if let Some(encryption_count) = encryption_count {
+        sha1.update(encryption_count.to_le_bytes());
+    }
+    let sha_result = sha1.finalize();
+
+    let mut md5 = md5::Md5::new();
+    md5.update(mac_key);
+    md5.update(PAD2);
+    md5.update(sha_result);
+    let md5_result = md5.finalize();
+
+    let mut signature = [0; SIGNATURE_SIZE];
+    signature.copy_from_slice(&md5_result[..SIGNATURE_SIZE]);
+
+    signature
+}
+
+#[derive(Debug, Error)]
+pub enum StandardSecurityError {
+    #[error("IO error")]
+    IOError(#[from] io::Error),
+    #[error("Invalid server certificate")]
+    InvalidServerCertificate(#[from] ServerLicenseError),
+    #[error("Invalid security header")]
+    InvalidSecurityHeader,
+    #[error("Unsupported encryption method: {0:?}")]
+    UnsupportedEncryptionMethod(EncryptionMethod),
+    #[error("Invalid length of the client or server random")]
+    InvalidRandomLength,
+    #[error("MAC signature generated over decrypted data does not match the signature of the PDU")]
+    InvalidMacSignature,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLIENT_RANDOM: [u8; 32] = [0x11; 32];
+    const SERVER_RANDOM: [u8; 32] = [0x22; 32];
+    const MAC_KEY_128: [u8; 16] = [
+        0x3D, 0xA4, 0x55, 0xFE, 0xEF, 0x93, 0xFC, 0xEE, 0x99, 0x7E, 0xD2, 0x89, 0x85, 0xAF, 0x5B, 0x91,
+    ];
+    const ENCRYPT_KEY_128: [u8; 16] = [
+        0x92, 0x84, 0xFC, 0xD1, 0xF9, 0xC1, 0xEB, 0x77, 0xFC, 0xDF, 0xEE, 0x49, 0xDB, 0x4F, 0x2D, 0x61,
+    ];
+    const DECRYPT_KEY_128: [u8; 16] = [
+        0x5F, 0x77, 0xD2, 0xD7, 0xA1, 0xEA, 0xAA, 0xE7, 0x75, 0x1D, 0xD1, 0x32, 0x06, 0xE5, 0x5D, 0x75,
+    ];
+    const DATA_SIGNATURE_128: [u8; 8] = [0xB9, 0xEB, 0x1C, 0xAC, 0x89, 0xBF, 0x48, 0xFD];
+
+    /// Context of the server, whose keys are those of the client in the opposite direction
+    fn server_context(client: &EncryptionContext) -> EncryptionContext {
+        let mut server = client.clone();
+        std::mem::swap(&mut server.encrypt, &mut server.decrypt);
+        server
+    }
+
+    #[test]
+    fn derives_128_bit_session_keys() {
+        let context = EncryptionContext::new(EncryptionMethod::BIT_128, &CLIENT_RANDOM, &SERVER_RANDOM).unwrap();
+
+        assert_eq!(context.mac_key, MAC_KEY_128);
+        assert_eq!(context.encrypt.current_key, ENCRYPT_KEY_128);
+        assert_eq!(context.decrypt.current_key, DECRYPT_KEY_128);
+    }
+
+    #[test]
+    fn salts_40_bit_session_keys() {
+        let context = EncryptionContext::new(EncryptionMethod::BIT_40, &CLIENT_RANDOM, &SERVER_RANDOM).unwrap();
+
+        assert_eq!(context.mac_key[..3], SALT_40_BIT);
+        assert_eq!(context.mac_key[3..], MAC_KEY_128[3..8]);
+        assert_eq!(context.encrypt.current_key[..3], SALT_40_BIT);
+        assert_eq!(context.encrypt.current_key[3..], ENCRYPT_KEY_128[3..8]);
+    }
+
+    #[test]
+    fn rejects_fips_encryption() {
+        let result = EncryptionContext::new(EncryptionMethod::FIPS, &CLIENT_RANDOM, &SERVER_RANDOM);
+
+        assert!(matches!(
+            result,
+            Err(StandardSecurityError::UnsupportedEncryptionMethod(
+                EncryptionMethod::FIPS
+            ))
+        ));
+    }
+
+    #[test]
+    fn signs_data_with_the_mac_key() {
+        let mut context = EncryptionContext::new(EncryptionMethod::BIT_128, &CLIENT_RANDOM, &SERVER_RANDOM).unwrap();
+
+        let (signature, _) = context.encrypt(b"data");
+
+        assert_eq!(signature, DATA_SIGNATURE_128);
+    }
+
+    #[test]
+    fn decrypts_data_across_key_updates() {
+        for encryption_method in [
+            EncryptionMethod::BIT_40,
+            EncryptionMethod::BIT_56,
+            EncryptionMethod::BIT_128,
+        ] {
+            let mut client = EncryptionContext::new(encryption_method, &CLIENT_RANDOM, &SERVER_RANDOM).unwrap();
+            let mut server = server_context(&client);
+
+            for i in 0..(KEY_UPDATE_INTERVAL * 2 + 1) {
+                let data = i.to_le_bytes();
+
+                let (signature, encrypted) = client.encrypt(&data);
+                let decrypted = server.decrypt(&encrypted, &signature, false).unwrap();
+
+                assert_eq!(decrypted, data);
+            }
+
+            assert_ne!(client.encrypt.current_key, client.encrypt.initial_key);
+        }
+    }
+
+    #[test]
+    fn checks_salted_signatures() {
+        let mut client = EncryptionContext::new(EncryptionMethod::BIT_128, &CLIENT_RANDOM, &SERVER_RANDOM).unwrap();
+        let mut server = server_context(&client);
+
+        for (encryption_count, data) in [b"first".as_slice(), b"second".as_slice()].into_iter().enumerate() {
+            let encrypted = server.encrypt.process(data, EncryptionMethod::BIT_128);
+            let signature = mac_signature(&server.mac_key, data, Some(encryption_count as u32));
+
+            assert_eq!(client.decrypt(&encrypted, &signature, true).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_signatures() {
+        let mut client = EncryptionContext::new(EncryptionMethod::BIT_128, &CLIENT_RANDOM, &SERVER_RANDOM).unwrap();
+        let mut server = server_context(&client);
+
+        let (mut signature, encrypted) = server.encrypt(b"data");
+        signature[0] ^= 0xFF;
+
+        assert!(matches!(
+            client.decrypt(&encrypted, &signature, false),
+            Err(StandardSecurityError::InvalidMacSignature)
+        ));
+    }
+
+    #[test]
+    fn security_exchange_pdu_round_trip() {
+        let pdu = SecurityExchangePdu {
+            encrypted_client_random: vec![0xAB; 72],
+        };
+
+        let mut buffer = Vec::new();
+        pdu.to_buffer(&mut buffer).unwrap();
+
+        assert_eq!(buffer.len(), pdu.buffer_length());
+        assert_eq!(buffer[..8], [0x01, 0x00, 0x00, 0x00, 0x48, 0x00, 0x00, 0x00]);
+        assert_eq!(SecurityExchangePdu::from_buffer(buffer.as_slice()).unwrap(), pdu);
+    }
+}
@@ -1,4 +1,6 @@
+pub mod cliprdr;
 pub mod dvc;
+pub mod rdpsnd;
 
 #[cfg(test)]
 mod tests;
@@ -13,6 +15,9 @@ use crate::PduParsing;
 
 pub const DRDYNVC_CHANNEL_NAME: &str = "drdynvc";
 
+/// Default maximum size of the data carried by a single Virtual Channel PDU (CHANNEL_CHUNK_LENGTH)
+pub const CHANNEL_CHUNK_LENGTH: usize = 1600;
+
 const CHANNEL_PDU_HEADER_SIZE: usize = 8;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -84,6 +89,8 @@ pub enum ChannelError {
     InvalidDvcDataLength,
     #[error("Invalid DVC capabilities version")]
     InvalidDvcCapabilitiesVersion,
+    #[error("Invalid DVC priority")]
+    InvalidDvcPriority,
     #[error("Invalid DVC message size")]
     InvalidDvcMessageSize,
     #[error("Invalid DVC total message size: actual ({actual}) > expected ({expected})")]
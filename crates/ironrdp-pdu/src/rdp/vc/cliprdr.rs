@@ -0,0 +1,297 @@
+//! Clipboard Virtual Channel Extension PDUs ([MS-RDPECLIP])
+//!
+//! [MS-RDPECLIP]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpeclip/fb9b7e0b-6db4-41c2-b83c-f889c1ee7688
+
+mod capabilities;
+mod file_contents;
+mod format_data;
+mod format_list;
+
+use std::io;
+
+use bitflags::bitflags;
+use byteorder::{LittleEndian, ReadBytesExt as _, WriteBytesExt as _};
+use num_derive::{FromPrimitive, ToPrimitive};
+use num_traits::FromPrimitive as _;
+use thiserror::Error;
+
+pub use self::capabilities::{
+    Capabilities, CapabilitySet, ClipboardGeneralCapabilityFlags, ClipboardProtocolVersion, GeneralCapabilitySet,
+};
+pub use self::file_contents::{
+    FileAttributes, FileContentsFlags, FileContentsRequest, FileContentsResponse, FileDescriptor, PackedFileList,
+};
+pub use self::format_data::{FormatDataRequest, FormatDataResponse};
+pub use self::format_list::{
+    ClipboardFormat, ClipboardFormatId, FormatList, FormatListResponse, FILE_CONTENTS_FORMAT_NAME,
+    FILE_GROUP_DESCRIPTOR_W_FORMAT_NAME, HTML_FORMAT_NAME,
+};
+use crate::{utils, PduParsing};
+
+pub const CLIPRDR_CHANNEL_NAME: &str = "cliprdr";
+
+const CLIPRDR_HEADER_SIZE: usize = 8;
+const TEMPORARY_DIRECTORY_SIZE: usize = 520;
+const LOCK_DATA_ID_SIZE: usize = 4;
+
+#[repr(u16)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+enum ClipboardPduType {
+    MonitorReady = 0x0001,
+    FormatList = 0x0002,
+    FormatListResponse = 0x0003,
+    FormatDataRequest = 0x0004,
+    FormatDataResponse = 0x0005,
+    TemporaryDirectory = 0x0006,
+    Capabilities = 0x0007,
+    FileContentsRequest = 0x0008,
+    FileContentsResponse = 0x0009,
+    LockData = 0x000A,
+    UnlockData = 0x000B,
+}
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct ClipboardPduFlags: u16 {
+        const RESPONSE_OK = 0x0001;
+        const RESPONSE_FAIL = 0x0002;
+        const ASCII_NAMES = 0x0004;
+    }
+}
+
+/// Clipboard PDU, along with its Clipboard PDU Header (CLIPRDR_HEADER)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClipboardPdu {
+    MonitorReady,
+    FormatList(FormatList),
+    FormatListResponse(FormatListResponse),
+    FormatDataRequest(FormatDataRequest),
+    FormatDataResponse(FormatDataResponse),
+    TemporaryDirectory(ClientTemporaryDirectory),
+    Capabilities(Capabilities),
+    FileContentsRequest(FileContentsRequest),
+    FileContentsResponse(FileContentsResponse),
+    LockData(LockDataId),
+    UnlockData(LockDataId),
+}
+
+impl ClipboardPdu {
+    fn pdu_type(&self) -> ClipboardPduType {
+        match self {
+            Self::MonitorReady => ClipboardPduType::MonitorReady,
+            Self::FormatList(_) => ClipboardPduType::FormatList,
+            Self::FormatListResponse(_) => ClipboardPduType::FormatListResponse,
+            Self::FormatDataRequest(_) => ClipboardPduType::FormatDataRequest,
+            Self::FormatDataResponse(_) => ClipboardPduType::FormatDataResponse,
+            Self::TemporaryDirectory(_) => ClipboardPduType::TemporaryDirectory,
+            Self::Capabilities(_) => ClipboardPduType::Capabilities,
+            Self::FileContentsRequest(_) => ClipboardPduType::FileContentsRequest,
+            Self::FileContentsResponse(_) => ClipboardPduType::FileContentsResponse,
+            Self::LockData(_) => ClipboardPduType::LockData,
+            Self::UnlockData(_) => ClipboardPduType::UnlockData,
+        }
+    }
+
+    fn flags(&self) -> ClipboardPduFlags {
+        match self {
+            Self::FormatList(format_list) if format_list.use_ascii => ClipboardPduFlags::ASCII_NAMES,
+            Self::FormatListResponse(FormatListResponse::Ok) => ClipboardPduFlags::RESPONSE_OK,
+            Self::FormatListResponse(FormatListResponse::Fail) => ClipboardPduFlags::RESPONSE_FAIL,
+            Self::FormatDataResponse(response) => response_flags(response.is_error),
+            Self::FileContentsResponse(response) => response_flags(response.is_error),
+            _ => ClipboardPduFlags::empty(),
+        }
+    }
+
+    fn body_length(&self) -> usize {
+        match self {
+            Self::MonitorReady | Self::FormatListResponse(_) => 0,
+            Self::FormatList(format_list) => format_list.encoded_formats.len(),
+            Self::FormatDataRequest(request) => request.buffer_length(),
+            Self::FormatDataResponse(response) => response.data.len(),
+            Self::TemporaryDirectory(temporary_directory) => temporary_directory.buffer_length(),
+            Self::Capabilities(capabilities) => capabilities.buffer_length(),
+            Self::FileContentsRequest(request) => request.buffer_length(),
+            Self::FileContentsResponse(response) => response.buffer_length(),
+            Self::LockData(id) | Self::UnlockData(id) => id.buffer_length(),
+        }
+    }
+}
+
+impl PduParsing for ClipboardPdu {
+    type Error = ClipboardError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let pdu_type = ClipboardPduType::from_u16(stream.read_u16::<LittleEndian>()?)
+            .ok_or(ClipboardError::InvalidClipboardPduType)?;
+        let flags = ClipboardPduFlags::from_bits_truncate(stream.read_u16::<LittleEndian>()?);
+        let data_length = stream.read_u32::<LittleEndian>()? as usize;
+
+        let mut body = vec![0; data_length];
+        stream.read_exact(&mut body)?;
+        let mut body = body.as_slice();
+
+        let is_error = flags.contains(ClipboardPduFlags::RESPONSE_FAIL);
+
+        let pdu = match pdu_type {
+            ClipboardPduType::MonitorReady => Self::MonitorReady,
+            ClipboardPduType::FormatList => Self::FormatList(FormatList {
+                use_ascii: flags.contains(ClipboardPduFlags::ASCII_NAMES),
+                encoded_formats: body.to_vec(),
+            }),
+            ClipboardPduType::FormatListResponse => Self::FormatListResponse(if is_error {
+                FormatListResponse::Fail
+            } else {
+                FormatListResponse::Ok
+            }),
+            ClipboardPduType::FormatDataRequest => Self::FormatDataRequest(FormatDataRequest::from_buffer(body)?),
+            ClipboardPduType::FormatDataResponse => Self::FormatDataResponse(FormatDataResponse {
+                is_error,
+                data: body.to_vec(),
+            }),
+            ClipboardPduType::TemporaryDirectory => {
+                Self::TemporaryDirectory(ClientTemporaryDirectory::from_buffer(body)?)
+            }
+            ClipboardPduType::Capabilities => Self::Capabilities(Capabilities::from_buffer(body)?),
+            ClipboardPduType::FileContentsRequest => Self::FileContentsRequest(FileContentsRequest::from_buffer(body)?),
+            ClipboardPduType::FileContentsResponse => {
+                let stream_id = body.read_u32::<LittleEndian>()?;
+
+                Self::FileContentsResponse(FileContentsResponse {
+                    is_error,
+                    stream_id,
+                    data: body.to_vec(),
+                })
+            }
+            ClipboardPduType::LockData => Self::LockData(LockDataId::from_buffer(body)?),
+            ClipboardPduType::UnlockData => Self::UnlockData(LockDataId::from_buffer(body)?),
+        };
+
+        Ok(pdu)
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        stream.write_u16::<LittleEndian>(self.pdu_type() as u16)?;
+        stream.write_u16::<LittleEndian>(self.flags().bits())?;
+        stream.write_u32::<LittleEndian>(self.body_length() as u32)?;
+
+        match self {
+            Self::MonitorReady | Self::FormatListResponse(_) => (),
+            Self::FormatList(format_list) => stream.write_all(&format_list.encoded_formats)?,
+            Self::FormatDataRequest(request) => request.to_buffer(&mut stream)?,
+            Self::FormatDataResponse(response) => stream.write_all(&response.data)?,
+            Self::TemporaryDirectory(temporary_directory) => temporary_directory.to_buffer(&mut stream)?,
+            Self::Capabilities(capabilities) => capabilities.to_buffer(&mut stream)?,
+            Self::FileContentsRequest(request) => request.to_buffer(&mut stream)?,
+            Self::FileContentsResponse(response) => response.to_buffer(&mut stream)?,
+            Self::LockData(id) | Self::UnlockData(id) => id.to_buffer(&mut stream)?,
+        }
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        CLIPRDR_HEADER_SIZE + self.body_length()
+    }
+}
+
+fn response_flags(is_error: bool) -> ClipboardPduFlags {
+    if is_error {
+        ClipboardPduFlags::RESPONSE_FAIL
+    } else {
+        ClipboardPduFlags::RESPONSE_OK
+    }
+}
+
+/// Client Temporary Directory PDU (CLIPRDR_TEMP_DIRECTORY)
+///
+/// Informs the server of a location on the client file system where temporary files can be stored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientTemporaryDirectory {
+    pub path: String,
+}
+
+impl PduParsing for ClientTemporaryDirectory {
+    type Error = ClipboardError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let mut path_buffer = [0; TEMPORARY_DIRECTORY_SIZE];
+        stream.read_exact(&mut path_buffer)?;
+
+        let path = utils::from_utf16_bytes(&path_buffer).trim_end_matches('\0').to_owned();
+
+        Ok(Self { path })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        let mut path_buffer = utils::to_utf16_bytes(&self.path);
+
+        // The path is null-terminated and the remaining bytes are filled with zeros
+        if path_buffer.len() >= TEMPORARY_DIRECTORY_SIZE {
+            return Err(ClipboardError::TemporaryDirectoryTooLong);
+        }
+        path_buffer.resize(TEMPORARY_DIRECTORY_SIZE, 0);
+
+        stream.write_all(&path_buffer)?;
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        TEMPORARY_DIRECTORY_SIZE
+    }
+}
+
+/// Identifies a locked clipboard data set (Lock Clipboard Data PDU and Unlock Clipboard Data PDU)
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct LockDataId(pub u32);
+
+impl PduParsing for LockDataId {
+    type Error = ClipboardError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        Ok(Self(stream.read_u32::<LittleEndian>()?))
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        stream.write_u32::<LittleEndian>(self.0)?;
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        LOCK_DATA_ID_SIZE
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ClipboardError {
+    #[error("IO error")]
+    IOError(#[from] io::Error),
+    #[error("Invalid clipboard PDU type")]
+    InvalidClipboardPduType,
+    #[error("Invalid format list length: {0} is not a multiple of the short format name entry size")]
+    InvalidFormatListLength(usize),
+    #[error("Invalid clipboard capability set type")]
+    InvalidCapabilitySetType,
+    #[error("Invalid clipboard capability set length")]
+    InvalidCapabilitySetLength,
+    #[error("Invalid clipboard protocol version")]
+    InvalidProtocolVersion,
+    #[error("Temporary directory path is too long")]
+    TemporaryDirectoryTooLong,
+    #[error("Invalid file contents request flags")]
+    InvalidFileContentsRequestFlags,
+    #[error("Invalid file size in file contents response: expected 8 bytes, got {0}")]
+    InvalidFileContentsSize(usize),
+    #[error("File name is too long")]
+    FileNameTooLong,
+    #[error("Invalid HTML clipboard format data")]
+    InvalidHtmlFormat,
+}
+
+impl From<ClipboardError> for io::Error {
+    fn from(e: ClipboardError) -> io::Error {
+        io::Error::new(io::ErrorKind::Other, format!("Clipboard error: {e}"))
+    }
+}
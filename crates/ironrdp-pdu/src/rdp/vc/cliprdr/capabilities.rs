@@ -0,0 +1,148 @@
+use std::io;
+
+use bitflags::bitflags;
+use byteorder::{LittleEndian, ReadBytesExt as _, WriteBytesExt as _};
+use num_derive::{FromPrimitive, ToPrimitive};
+use num_traits::FromPrimitive as _;
+
+use super::ClipboardError;
+use crate::PduParsing;
+
+const CAPABILITIES_HEADER_SIZE: usize = 4;
+const CAPABILITY_SET_GENERAL_TYPE: u16 = 0x0001;
+const GENERAL_CAPABILITY_SET_SIZE: usize = 12;
+
+/// Clipboard Capabilities PDU (CLIPRDR_CAPS)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capabilities {
+    pub capabilities: Vec<CapabilitySet>,
+}
+
+impl Capabilities {
+    pub fn new(version: ClipboardProtocolVersion, general_flags: ClipboardGeneralCapabilityFlags) -> Self {
+        Self {
+            capabilities: vec![CapabilitySet::General(GeneralCapabilitySet { version, general_flags })],
+        }
+    }
+
+    pub fn general(&self) -> Option<&GeneralCapabilitySet> {
+        self.capabilities.first().map(|CapabilitySet::General(general)| general)
+    }
+
+    /// Keeps only the capabilities supported by both sides
+    pub fn downgrade(&mut self, peer: &Capabilities) {
+        let peer_general = peer.general().copied().unwrap_or(GeneralCapabilitySet {
+            version: ClipboardProtocolVersion::V1,
+            general_flags: ClipboardGeneralCapabilityFlags::empty(),
+        });
+
+        for CapabilitySet::General(general) in self.capabilities.iter_mut() {
+            general.version = general.version.min(peer_general.version);
+            general.general_flags &= peer_general.general_flags;
+        }
+    }
+
+    pub fn flags(&self) -> ClipboardGeneralCapabilityFlags {
+        self.general()
+            .map(|general| general.general_flags)
+            .unwrap_or_else(ClipboardGeneralCapabilityFlags::empty)
+    }
+}
+
+impl PduParsing for Capabilities {
+    type Error = ClipboardError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let capabilities_count = stream.read_u16::<LittleEndian>()?;
+        let _padding = stream.read_u16::<LittleEndian>()?;
+
+        let capabilities = (0..capabilities_count)
+            .map(|_| CapabilitySet::from_buffer(&mut stream))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self { capabilities })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        stream.write_u16::<LittleEndian>(self.capabilities.len() as u16)?;
+        stream.write_u16::<LittleEndian>(0)?; // padding
+
+        for capability_set in self.capabilities.iter() {
+            capability_set.to_buffer(&mut stream)?;
+        }
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        CAPABILITIES_HEADER_SIZE + self.capabilities.iter().map(PduParsing::buffer_length).sum::<usize>()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CapabilitySet {
+    General(GeneralCapabilitySet),
+}
+
+impl PduParsing for CapabilitySet {
+    type Error = ClipboardError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let capability_set_type = stream.read_u16::<LittleEndian>()?;
+        let length = stream.read_u16::<LittleEndian>()? as usize;
+
+        if capability_set_type != CAPABILITY_SET_GENERAL_TYPE {
+            return Err(ClipboardError::InvalidCapabilitySetType);
+        }
+
+        if length != GENERAL_CAPABILITY_SET_SIZE {
+            return Err(ClipboardError::InvalidCapabilitySetLength);
+        }
+
+        let version = ClipboardProtocolVersion::from_u32(stream.read_u32::<LittleEndian>()?)
+            .ok_or(ClipboardError::InvalidProtocolVersion)?;
+        let general_flags = ClipboardGeneralCapabilityFlags::from_bits_truncate(stream.read_u32::<LittleEndian>()?);
+
+        Ok(Self::General(GeneralCapabilitySet { version, general_flags }))
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        let Self::General(general) = self;
+
+        stream.write_u16::<LittleEndian>(CAPABILITY_SET_GENERAL_TYPE)?;
+        stream.write_u16::<LittleEndian>(GENERAL_CAPABILITY_SET_SIZE as u16)?;
+        stream.write_u32::<LittleEndian>(general.version as u32)?;
+        stream.write_u32::<LittleEndian>(general.general_flags.bits())?;
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        GENERAL_CAPABILITY_SET_SIZE
+    }
+}
+
+/// General Capability Set (CLIPRDR_GENERAL_CAPABILITY)
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct GeneralCapabilitySet {
+    pub version: ClipboardProtocolVersion,
+    pub general_flags: ClipboardGeneralCapabilityFlags,
+}
+
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, FromPrimitive, ToPrimitive)]
+pub enum ClipboardProtocolVersion {
+    V1 = 0x0000_0001,
+    V2 = 0x0000_0002,
+}
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct ClipboardGeneralCapabilityFlags: u32 {
+        const USE_LONG_FORMAT_NAMES = 0x0000_0002;
+        const STREAM_FILECLIP_ENABLED = 0x0000_0004;
+        const FILECLIP_NO_FILE_PATHS = 0x0000_0008;
+        const CAN_LOCK_CLIPDATA = 0x0000_0010;
+        const HUGE_FILE_SUPPORT_ENABLED = 0x0000_0020;
+    }
+}
@@ -0,0 +1,305 @@
+use std::io;
+
+use bitflags::bitflags;
+use byteorder::{LittleEndian, ReadBytesExt as _, WriteBytesExt as _};
+
+use super::ClipboardError;
+use crate::{utils, PduParsing};
+
+const FILE_CONTENTS_REQUEST_SIZE: usize = 24;
+const DATA_ID_SIZE: usize = 4;
+const STREAM_ID_SIZE: usize = 4;
+const FILE_SIZE_RESPONSE_DATA_SIZE: usize = 8;
+
+const FILE_LIST_HEADER_SIZE: usize = 4;
+const FILE_DESCRIPTOR_SIZE: usize = 592;
+const FILE_DESCRIPTOR_FIRST_RESERVED_SIZE: usize = 32;
+const FILE_DESCRIPTOR_SECOND_RESERVED_SIZE: usize = 16;
+const FILE_NAME_SIZE: usize = 520;
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct FileContentsFlags: u32 {
+        /// The size of the file is requested
+        const SIZE = 0x0000_0001;
+        /// A range of bytes of the file is requested
+        const RANGE = 0x0000_0002;
+    }
+}
+
+/// File Contents Request PDU (CLIPRDR_FILECONTENTS_REQUEST)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileContentsRequest {
+    pub stream_id: u32,
+    /// Index of the file in the file list
+    pub index: i32,
+    pub flags: FileContentsFlags,
+    pub position: u64,
+    pub requested_size: u32,
+    /// Identifies the locked clipboard data, if any
+    pub data_id: Option<u32>,
+}
+
+impl PduParsing for FileContentsRequest {
+    type Error = ClipboardError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let stream_id = stream.read_u32::<LittleEndian>()?;
+        let index = stream.read_i32::<LittleEndian>()?;
+        let flags = FileContentsFlags::from_bits_truncate(stream.read_u32::<LittleEndian>()?);
+        let position_low = stream.read_u32::<LittleEndian>()?;
+        let position_high = stream.read_u32::<LittleEndian>()?;
+        let requested_size = stream.read_u32::<LittleEndian>()?;
+
+        if flags.contains(FileContentsFlags::SIZE | FileContentsFlags::RANGE) {
+            return Err(ClipboardError::InvalidFileContentsRequestFlags);
+        }
+
+        let mut request = Self {
+            stream_id,
+            index,
+            flags,
+            position: u64::from(position_high) << 32 | u64::from(position_low),
+            requested_size,
+            data_id: None,
+        };
+
+        request.data_id = Some(try_read_optional!(stream.read_u32::<LittleEndian>(), request));
+
+        Ok(request)
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        stream.write_u32::<LittleEndian>(self.stream_id)?;
+        stream.write_i32::<LittleEndian>(self.index)?;
+        stream.write_u32::<LittleEndian>(self.flags.bits())?;
+        stream.write_u32::<LittleEndian>(self.position as u32)?;
+        stream.write_u32::<LittleEndian>((self.position >> 32) as u32)?;
+        stream.write_u32::<LittleEndian>(self.requested_size)?;
+
+        if let Some(data_id) = self.data_id {
+            stream.write_u32::<LittleEndian>(data_id)?;
+        }
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        FILE_CONTENTS_REQUEST_SIZE + self.data_id.map(|_| DATA_ID_SIZE).unwrap_or(0)
+    }
+}
+
+/// File Contents Response PDU (CLIPRDR_FILECONTENTS_RESPONSE)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileContentsResponse {
+    pub is_error: bool,
+    pub stream_id: u32,
+    pub data: Vec<u8>,
+}
+
+impl FileContentsResponse {
+    /// Creates a response to a [`FileContentsFlags::SIZE`] request
+    pub fn new_size(stream_id: u32, size: u64) -> Self {
+        Self {
+            is_error: false,
+            stream_id,
+            data: size.to_le_bytes().to_vec(),
+        }
+    }
+
+    /// Creates a response to a [`FileContentsFlags::RANGE`] request
+    pub fn new_data(stream_id: u32, data: Vec<u8>) -> Self {
+        Self {
+            is_error: false,
+            stream_id,
+            data,
+        }
+    }
+
+    pub fn new_error(stream_id: u32) -> Self {
+        Self {
+            is_error: true,
+            stream_id,
+            data: Vec::new(),
+        }
+    }
+
+    /// Reads the file size from the response to a [`FileContentsFlags::SIZE`] request
+    pub fn data_as_size(&self) -> Result<u64, ClipboardError> {
+        let size: [u8; FILE_SIZE_RESPONSE_DATA_SIZE] = self
+            .data
+            .as_slice()
+            .try_into()
+            .map_err(|_| ClipboardError::InvalidFileContentsSize(self.data.len()))?;
+
+        Ok(u64::from_le_bytes(size))
+    }
+}
+
+impl PduParsing for FileContentsResponse {
+    type Error = ClipboardError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let stream_id = stream.read_u32::<LittleEndian>()?;
+
+        let mut data = Vec::new();
+        stream.read_to_end(&mut data)?;
+
+        Ok(Self {
+            is_error: false,
+            stream_id,
+            data,
+        })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        stream.write_u32::<LittleEndian>(self.stream_id)?;
+        stream.write_all(&self.data)?;
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        STREAM_ID_SIZE + self.data.len()
+    }
+}
+
+/// Packed File List (CLIPRDR_FILELIST)
+///
+/// Transferred as the data of the "FileGroupDescriptorW" registered clipboard format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackedFileList {
+    pub files: Vec<FileDescriptor>,
+}
+
+impl PduParsing for PackedFileList {
+    type Error = ClipboardError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let files_count = stream.read_u32::<LittleEndian>()?;
+
+        let files = (0..files_count)
+            .map(|_| FileDescriptor::from_buffer(&mut stream))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self { files })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        stream.write_u32::<LittleEndian>(self.files.len() as u32)?;
+
+        for file in self.files.iter() {
+            file.to_buffer(&mut stream)?;
+        }
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        FILE_LIST_HEADER_SIZE + self.files.len() * FILE_DESCRIPTOR_SIZE
+    }
+}
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    struct FileDescriptorFlags: u32 {
+        const ATTRIBUTES = 0x0000_0004;
+        const WRITESTIME = 0x0000_0020;
+        const FILESIZE = 0x0000_0040;
+        const SHOWPROGRESSUI = 0x0000_4000;
+    }
+}
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct FileAttributes: u32 {
+        const READONLY = 0x0000_0001;
+        const HIDDEN = 0x0000_0002;
+        const SYSTEM = 0x0000_0004;
+        const DIRECTORY = 0x0000_0010;
+        const ARCHIVE = 0x0000_0020;
+        const NORMAL = 0x0000_0080;
+    }
+}
+
+/// File Descriptor (CLIPRDR_FILEDESCRIPTOR)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileDescriptor {
+    pub attributes: Option<FileAttributes>,
+    /// Last write time, as the number of 100-nanosecond intervals since January 1, 1601 (UTC)
+    pub last_write_time: Option<u64>,
+    pub file_size: Option<u64>,
+    /// Path of the file, relative to the root of the copied file tree
+    pub name: String,
+}
+
+impl PduParsing for FileDescriptor {
+    type Error = ClipboardError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let flags = FileDescriptorFlags::from_bits_truncate(stream.read_u32::<LittleEndian>()?);
+
+        let mut reserved = [0; FILE_DESCRIPTOR_FIRST_RESERVED_SIZE];
+        stream.read_exact(&mut reserved)?;
+
+        let attributes = FileAttributes::from_bits_truncate(stream.read_u32::<LittleEndian>()?);
+
+        let mut reserved = [0; FILE_DESCRIPTOR_SECOND_RESERVED_SIZE];
+        stream.read_exact(&mut reserved)?;
+
+        let last_write_time = stream.read_u64::<LittleEndian>()?;
+        let file_size_high = stream.read_u32::<LittleEndian>()?;
+        let file_size_low = stream.read_u32::<LittleEndian>()?;
+
+        let mut name_buffer = [0; FILE_NAME_SIZE];
+        stream.read_exact(&mut name_buffer)?;
+        let name = utils::from_utf16_bytes(&name_buffer)
+            .split('\0')
+            .next()
+            .unwrap_or_default()
+            .to_owned();
+
+        Ok(Self {
+            attributes: flags.contains(FileDescriptorFlags::ATTRIBUTES).then_some(attributes),
+            last_write_time: flags
+                .contains(FileDescriptorFlags::WRITESTIME)
+                .then_some(last_write_time),
+            file_size: flags
+                .contains(FileDescriptorFlags::FILESIZE)
+                .then_some(u64::from(file_size_high) << 32 | u64::from(file_size_low)),
+            name,
+        })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        let mut name_buffer = utils::to_utf16_bytes(&self.name);
+
+        // The file name is null-terminated and the remaining bytes are filled with zeros
+        if name_buffer.len() >= FILE_NAME_SIZE {
+            return Err(ClipboardError::FileNameTooLong);
+        }
+        name_buffer.resize(FILE_NAME_SIZE, 0);
+
+        let mut flags = FileDescriptorFlags::SHOWPROGRESSUI;
+        flags.set(FileDescriptorFlags::ATTRIBUTES, self.attributes.is_some());
+        flags.set(FileDescriptorFlags::WRITESTIME, self.last_write_time.is_some());
+        flags.set(FileDescriptorFlags::FILESIZE, self.file_size.is_some());
+
+        let file_size = self.file_size.unwrap_or(0);
+
+        stream.write_u32::<LittleEndian>(flags.bits())?;
+        stream.write_all(&[0; FILE_DESCRIPTOR_FIRST_RESERVED_SIZE])?;
+        stream.write_u32::<LittleEndian>(self.attributes.map(|a| a.bits()).unwrap_or(0))?;
+        stream.write_all(&[0; FILE_DESCRIPTOR_SECOND_RESERVED_SIZE])?;
+        stream.write_u64::<LittleEndian>(self.last_write_time.unwrap_or(0))?;
+        stream.write_u32::<LittleEndian>((file_size >> 32) as u32)?;
+        stream.write_u32::<LittleEndian>(file_size as u32)?;
+        stream.write_all(&name_buffer)?;
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        FILE_DESCRIPTOR_SIZE
+    }
+}
@@ -0,0 +1,149 @@
+use std::io;
+
+use byteorder::{LittleEndian, ReadBytesExt as _, WriteBytesExt as _};
+
+use super::{ClipboardError, ClipboardFormatId, PackedFileList};
+use crate::{utils, PduParsing};
+
+const FORMAT_DATA_REQUEST_SIZE: usize = 4;
+
+const HTML_START_FRAGMENT_MARKER: &str = "StartFragment:";
+const HTML_END_FRAGMENT_MARKER: &str = "EndFragment:";
+
+/// Format Data Request PDU (CLIPRDR_FORMAT_DATA_REQUEST)
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FormatDataRequest {
+    pub format: ClipboardFormatId,
+}
+
+impl PduParsing for FormatDataRequest {
+    type Error = ClipboardError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let format = ClipboardFormatId(stream.read_u32::<LittleEndian>()?);
+
+        Ok(Self { format })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        stream.write_u32::<LittleEndian>(self.format.0)?;
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        FORMAT_DATA_REQUEST_SIZE
+    }
+}
+
+/// Format Data Response PDU (CLIPRDR_FORMAT_DATA_RESPONSE)
+///
+/// The data layout depends on the requested format: helpers are provided for the text,
+/// HTML and file list formats.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatDataResponse {
+    pub is_error: bool,
+    pub data: Vec<u8>,
+}
+
+impl FormatDataResponse {
+    pub fn new_data(data: Vec<u8>) -> Self {
+        Self { is_error: false, data }
+    }
+
+    pub fn new_error() -> Self {
+        Self {
+            is_error: true,
+            data: Vec::new(),
+        }
+    }
+
+    /// Creates a response for the CF_UNICODETEXT format (null-terminated UTF-16 string)
+    pub fn new_unicode_string(value: &str) -> Self {
+        let mut data = utils::to_utf16_bytes(value);
+        data.extend_from_slice(&[0, 0]);
+
+        Self::new_data(data)
+    }
+
+    /// Creates a response for the CF_TEXT format (null-terminated ANSI string)
+    pub fn new_ansi_string(value: &str) -> Self {
+        let mut data = value.as_bytes().to_vec();
+        data.push(0);
+
+        Self::new_data(data)
+    }
+
+    /// Creates a response for the "HTML Format" registered format (CF_HTML) from an HTML fragment
+    pub fn new_html(fragment: &str) -> Self {
+        const PREFIX: &str = "<html>\r\n<body>\r\n<!--StartFragment-->";
+        const SUFFIX: &str = "<!--EndFragment-->\r\n</body>\r\n</html>";
+
+        let header_length = format_html_header(0, 0, 0, 0).len();
+
+        let start_html = header_length;
+        let start_fragment = start_html + PREFIX.len();
+        let end_fragment = start_fragment + fragment.len();
+        let end_html = end_fragment + SUFFIX.len();
+
+        let mut data = format_html_header(start_html, end_html, start_fragment, end_fragment).into_bytes();
+        data.extend_from_slice(PREFIX.as_bytes());
+        data.extend_from_slice(fragment.as_bytes());
+        data.extend_from_slice(SUFFIX.as_bytes());
+        data.push(0);
+
+        Self::new_data(data)
+    }
+
+    pub fn new_file_list(file_list: &PackedFileList) -> Result<Self, ClipboardError> {
+        let mut data = Vec::with_capacity(file_list.buffer_length());
+        file_list.to_buffer(&mut data)?;
+
+        Ok(Self::new_data(data))
+    }
+
+    pub fn to_unicode_string(&self) -> String {
+        utils::from_utf16_bytes(&self.data)
+            .split('\0')
+            .next()
+            .unwrap_or_default()
+            .to_owned()
+    }
+
+    pub fn to_ansi_string(&self) -> String {
+        let length = self.data.iter().position(|&c| c == 0).unwrap_or(self.data.len());
+
+        String::from_utf8_lossy(&self.data[..length]).into_owned()
+    }
+
+    /// Extracts the HTML fragment from CF_HTML data
+    pub fn to_html(&self) -> Result<String, ClipboardError> {
+        let length = self.data.iter().position(|&c| c == 0).unwrap_or(self.data.len());
+        let data = std::str::from_utf8(&self.data[..length]).map_err(|_| ClipboardError::InvalidHtmlFormat)?;
+
+        let start_fragment = find_html_header_value(data, HTML_START_FRAGMENT_MARKER)?;
+        let end_fragment = find_html_header_value(data, HTML_END_FRAGMENT_MARKER)?;
+
+        data.get(start_fragment..end_fragment)
+            .map(str::to_owned)
+            .ok_or(ClipboardError::InvalidHtmlFormat)
+    }
+
+    pub fn to_file_list(&self) -> Result<PackedFileList, ClipboardError> {
+        PackedFileList::from_buffer(self.data.as_slice())
+    }
+}
+
+fn format_html_header(start_html: usize, end_html: usize, start_fragment: usize, end_fragment: usize) -> String {
+    format!(
+        "Version:0.9\r\nStartHTML:{start_html:010}\r\nEndHTML:{end_html:010}\r\n\
+         {HTML_START_FRAGMENT_MARKER}{start_fragment:010}\r\n{HTML_END_FRAGMENT_MARKER}{end_fragment:010}\r\n"
+    )
+}
+
+fn find_html_header_value(data: &str, marker: &str) -> Result<usize, ClipboardError> {
+    data.lines()
+        .find_map(|line| line.strip_prefix(marker))
+        .and_then(|value| value.trim().parse().ok())
+        .ok_or(ClipboardError::InvalidHtmlFormat)
+}
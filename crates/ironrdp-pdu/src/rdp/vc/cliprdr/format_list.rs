@@ -0,0 +1,180 @@
+use std::io::Write as _;
+
+use byteorder::{LittleEndian, ReadBytesExt as _, WriteBytesExt as _};
+
+use super::ClipboardError;
+use crate::utils;
+
+/// Name of the registered clipboard format used to transfer HTML fragments (CF_HTML)
+pub const HTML_FORMAT_NAME: &str = "HTML Format";
+/// Name of the registered clipboard format used to transfer a list of file descriptors
+pub const FILE_GROUP_DESCRIPTOR_W_FORMAT_NAME: &str = "FileGroupDescriptorW";
+/// Name of the registered clipboard format used to transfer file contents
+pub const FILE_CONTENTS_FORMAT_NAME: &str = "FileContents";
+
+const SHORT_FORMAT_NAME_SIZE: usize = 32;
+const SHORT_FORMAT_ENTRY_SIZE: usize = 4 + SHORT_FORMAT_NAME_SIZE;
+
+/// Clipboard format identifier
+///
+/// Standard formats are predefined by the operating system while registered formats
+/// (with an identifier in the 0xC000-0xFFFF range) are identified by their name.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ClipboardFormatId(pub u32);
+
+impl ClipboardFormatId {
+    pub const CF_TEXT: Self = Self(1);
+    pub const CF_BITMAP: Self = Self(2);
+    pub const CF_METAFILEPICT: Self = Self(3);
+    pub const CF_OEMTEXT: Self = Self(7);
+    pub const CF_DIB: Self = Self(8);
+    pub const CF_PALETTE: Self = Self(9);
+    pub const CF_UNICODETEXT: Self = Self(13);
+    pub const CF_ENHMETAFILE: Self = Self(14);
+    pub const CF_HDROP: Self = Self(15);
+    pub const CF_LOCALE: Self = Self(16);
+    pub const CF_DIBV5: Self = Self(17);
+
+    pub fn is_registered(self) -> bool {
+        (0xC000..=0xFFFF).contains(&self.0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClipboardFormat {
+    pub id: ClipboardFormatId,
+    pub name: Option<String>,
+}
+
+impl ClipboardFormat {
+    pub fn new(id: ClipboardFormatId) -> Self {
+        Self { id, name: None }
+    }
+
+    pub fn with_name(self, name: impl Into<String>) -> Self {
+        Self {
+            name: Some(name.into()),
+            ..self
+        }
+    }
+}
+
+/// Format List PDU (CLIPRDR_FORMAT_LIST)
+///
+/// The layout of the format list depends on whether the Long Format Names capability
+/// was negotiated or not, which is not part of the PDU itself. For this reason, the list
+/// is kept encoded and decoded lazily with [`FormatList::get_formats`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatList {
+    /// Whether short format names are ASCII-8 encoded (CB_ASCII_NAMES flag) instead of Unicode
+    pub use_ascii: bool,
+    pub encoded_formats: Vec<u8>,
+}
+
+impl FormatList {
+    /// Encodes the given formats using Unicode format names
+    pub fn new(formats: &[ClipboardFormat], use_long_format_names: bool) -> Self {
+        let mut encoded_formats = Vec::new();
+
+        for format in formats {
+            encoded_formats
+                .write_u32::<LittleEndian>(format.id.0)
+                .expect("write to Vec cannot fail");
+
+            let mut name = utils::to_utf16_bytes(format.name.as_deref().unwrap_or_default());
+
+            if use_long_format_names {
+                name.extend_from_slice(&[0, 0]);
+            } else {
+                // Short format names are truncated and must leave room for the null terminator
+                name.truncate(SHORT_FORMAT_NAME_SIZE - 2);
+                name.resize(SHORT_FORMAT_NAME_SIZE, 0);
+            }
+
+            encoded_formats.write_all(&name).expect("write to Vec cannot fail");
+        }
+
+        Self {
+            use_ascii: false,
+            encoded_formats,
+        }
+    }
+
+    pub fn get_formats(&self, use_long_format_names: bool) -> Result<Vec<ClipboardFormat>, ClipboardError> {
+        if use_long_format_names {
+            self.get_long_formats()
+        } else {
+            self.get_short_formats()
+        }
+    }
+
+    fn get_long_formats(&self) -> Result<Vec<ClipboardFormat>, ClipboardError> {
+        let mut stream = self.encoded_formats.as_slice();
+        let mut formats = Vec::new();
+
+        while !stream.is_empty() {
+            let id = ClipboardFormatId(stream.read_u32::<LittleEndian>()?);
+
+            let mut name = Vec::new();
+            loop {
+                let c = stream.read_u16::<LittleEndian>()?;
+                if c == 0 {
+                    break;
+                }
+                name.push(c);
+            }
+
+            formats.push(ClipboardFormat {
+                id,
+                name: to_format_name(String::from_utf16_lossy(&name)),
+            });
+        }
+
+        Ok(formats)
+    }
+
+    fn get_short_formats(&self) -> Result<Vec<ClipboardFormat>, ClipboardError> {
+        let entries = self.encoded_formats.chunks_exact(SHORT_FORMAT_ENTRY_SIZE);
+
+        if !entries.remainder().is_empty() {
+            return Err(ClipboardError::InvalidFormatListLength(self.encoded_formats.len()));
+        }
+
+        entries
+            .map(|mut entry| {
+                let id = ClipboardFormatId(entry.read_u32::<LittleEndian>()?);
+
+                let name = if self.use_ascii {
+                    let length = entry.iter().position(|&c| c == 0).unwrap_or(entry.len());
+                    String::from_utf8_lossy(&entry[..length]).into_owned()
+                } else {
+                    utils::from_utf16_bytes(entry)
+                        .split('\0')
+                        .next()
+                        .unwrap_or_default()
+                        .to_owned()
+                };
+
+                Ok(ClipboardFormat {
+                    id,
+                    name: to_format_name(name),
+                })
+            })
+            .collect()
+    }
+}
+
+fn to_format_name(name: String) -> Option<String> {
+    if name.is_empty() {
+        None
+    } else {
+        Some(name)
+    }
+}
+
+/// Format List Response PDU (CLIPRDR_FORMAT_LIST_RESPONSE)
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FormatListResponse {
+    Ok,
+    Fail,
+}
@@ -14,6 +14,7 @@ mod tests;
 
 pub mod display;
 pub mod gfx;
+pub mod rdpei;
 
 mod capabilities;
 mod close;
@@ -23,12 +24,16 @@ mod data_first;
 
 pub use self::capabilities::{CapabilitiesRequestPdu, CapabilitiesResponsePdu, CapsVersion};
 pub use self::close::ClosePdu;
-pub use self::create::{CreateRequestPdu, CreateResponsePdu, DVC_CREATION_STATUS_NO_LISTENER, DVC_CREATION_STATUS_OK};
+pub use self::create::{
+    ChannelPriority, CreateRequestPdu, CreateResponsePdu, DVC_CREATION_STATUS_NO_LISTENER, DVC_CREATION_STATUS_OK,
+};
 pub use self::data::DataPdu;
 pub use self::data_first::DataFirstPdu;
 
 const HEADER_SIZE: usize = 1;
-const PDU_WITH_DATA_MAX_SIZE: usize = 1600;
+
+/// Maximum size of a DVC PDU carrying data, header included
+pub const PDU_WITH_DATA_MAX_SIZE: usize = 1600;
 
 const UNUSED_U8: u8 = 0;
 
@@ -42,6 +47,8 @@ pub enum PduType {
     Data = 0x03,
     Close = 0x04,
     Capabilities = 0x05,
+    DataFirstCompressed = 0x06,
+    DataCompressed = 0x07,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -51,6 +58,10 @@ pub enum ServerPdu {
     DataFirst(DataFirstPdu),
     Data(DataPdu),
     CloseRequest(ClosePdu),
+    /// Sent by servers supporting DRDYNVC version 3, the data is compressed with RDP 8.0 Lite bulk compression
+    DataFirstCompressed(DataFirstPdu),
+    /// Sent by servers supporting DRDYNVC version 3, the data is compressed with RDP 8.0 Lite bulk compression
+    DataCompressed(DataPdu),
 }
 
 impl ServerPdu {
@@ -68,6 +79,7 @@ impl ServerPdu {
             PduType::Create => Ok(ServerPdu::CreateRequest(CreateRequestPdu::from_buffer(
                 &mut stream,
                 channel_id_type,
+                ChannelPriority::from_u8(dvc_header.pdu_dependent).ok_or(ChannelError::InvalidDvcPriority)?,
                 dvc_data_size,
             )?)),
             PduType::DataFirst => {
@@ -90,6 +102,22 @@ impl ServerPdu {
                 &mut stream,
                 channel_id_type,
             )?)),
+            PduType::DataFirstCompressed => {
+                let data_length_type =
+                    FieldType::from_u8(dvc_header.pdu_dependent).ok_or(ChannelError::InvalidDvcDataLength)?;
+
+                Ok(ServerPdu::DataFirstCompressed(DataFirstPdu::from_buffer_compressed(
+                    &mut stream,
+                    channel_id_type,
+                    data_length_type,
+                    dvc_data_size,
+                )?))
+            }
+            PduType::DataCompressed => Ok(ServerPdu::DataCompressed(DataPdu::from_buffer(
+                &mut stream,
+                channel_id_type,
+                dvc_data_size,
+            )?)),
         }
     }
 
@@ -100,6 +128,8 @@ impl ServerPdu {
             ServerPdu::DataFirst(data_first) => data_first.to_buffer(&mut stream)?,
             ServerPdu::Data(data) => data.to_buffer(&mut stream)?,
             ServerPdu::CloseRequest(close_request) => close_request.to_buffer(&mut stream)?,
+            ServerPdu::DataFirstCompressed(data_first) => data_first.to_buffer_compressed(&mut stream)?,
+            ServerPdu::DataCompressed(data) => data.to_buffer_compressed(&mut stream)?,
         };
 
         Ok(())
@@ -112,6 +142,8 @@ impl ServerPdu {
             ServerPdu::DataFirst(data_first) => data_first.buffer_length(),
             ServerPdu::Data(data) => data.buffer_length(),
             ServerPdu::CloseRequest(close_request) => close_request.buffer_length(),
+            ServerPdu::DataFirstCompressed(data_first) => data_first.buffer_length(),
+            ServerPdu::DataCompressed(data) => data.buffer_length(),
         }
     }
 
@@ -122,6 +154,8 @@ impl ServerPdu {
             ServerPdu::DataFirst(_) => "Data First PDU",
             ServerPdu::Data(_) => "Data PDU",
             ServerPdu::CloseRequest(_) => "Close Request PDU",
+            ServerPdu::DataFirstCompressed(_) => "Data First Compressed PDU",
+            ServerPdu::DataCompressed(_) => "Data Compressed PDU",
         }
     }
 }
@@ -171,6 +205,8 @@ impl ClientPdu {
                 &mut stream,
                 channel_id_type,
             )?)),
+            // Compressed data is only sent by the server
+            PduType::DataFirstCompressed | PduType::DataCompressed => Err(ChannelError::InvalidDvcPduType),
         }
     }
 
@@ -4,6 +4,7 @@ mod tests;
 use std::io;
 
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use num_derive::{FromPrimitive, ToPrimitive};
 
 use super::{FieldType, Header, PduType, HEADER_SIZE, UNUSED_U8};
 use crate::rdp::vc::ChannelError;
@@ -14,17 +15,32 @@ pub const DVC_CREATION_STATUS_NO_LISTENER: u32 = 0xC000_0001;
 
 const DVC_CREATION_STATUS_SIZE: usize = 4;
 
+/// Priority class of a dynamic virtual channel, used by the server to share the bandwidth between channels
+/// (DRDYNVC version 2 and above).
+///
+/// With version 1, the priority is always `High`.
+#[repr(u8)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, FromPrimitive, ToPrimitive)]
+pub enum ChannelPriority {
+    High = 0x00,
+    Medium = 0x01,
+    Low = 0x02,
+    Lowest = 0x03,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct CreateRequestPdu {
     pub channel_id_type: FieldType,
     pub channel_id: u32,
     pub channel_name: String,
+    pub priority: ChannelPriority,
 }
 
 impl CreateRequestPdu {
     pub fn from_buffer(
         mut stream: impl io::Read,
         channel_id_type: FieldType,
+        priority: ChannelPriority,
         mut data_size: usize,
     ) -> Result<Self, ChannelError> {
         let channel_id = channel_id_type.read_buffer_according_to_type(&mut stream)?;
@@ -36,13 +52,14 @@ impl CreateRequestPdu {
             channel_id_type,
             channel_id,
             channel_name,
+            priority,
         })
     }
 
     pub fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), ChannelError> {
         let dvc_header = Header {
             channel_id_type: self.channel_id_type as u8,
-            pdu_dependent: UNUSED_U8, // because DYNVC_CAPS_VERSION1
+            pdu_dependent: self.priority as u8,
             pdu_type: PduType::Create,
         };
         dvc_header.to_buffer(&mut stream)?;
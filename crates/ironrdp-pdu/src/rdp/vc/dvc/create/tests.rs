@@ -17,7 +17,8 @@ lazy_static! {
     static ref DVC_CREATE_REQUEST: CreateRequestPdu = CreateRequestPdu {
         channel_id_type: FieldType::U8,
         channel_id: TEST_CHANNEL_ID,
-        channel_name: String::from("testdvc")
+        channel_name: String::from("testdvc"),
+        priority: ChannelPriority::High,
     };
     static ref DVC_CREATE_RESPONSE: CreateResponsePdu = CreateResponsePdu {
         channel_id_type: FieldType::U8,
@@ -33,6 +34,7 @@ fn from_buffer_correct_parses_dvc_create_request_pdu() {
         CreateRequestPdu::from_buffer(
             &DVC_CREATE_REQUEST_BUFFER[1..],
             FieldType::U8,
+            ChannelPriority::High,
             DVC_CREATE_REQUEST_BUFFER_SIZE - DVC_TEST_HEADER_SIZE
         )
         .unwrap(),
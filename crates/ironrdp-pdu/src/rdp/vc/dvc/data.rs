@@ -36,11 +36,20 @@ impl DataPdu {
         }
     }
 
-    pub fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), ChannelError> {
+    pub fn to_buffer(&self, stream: impl io::Write) -> Result<(), ChannelError> {
+        self.encode(stream, PduType::Data)
+    }
+
+    /// Serializes the header of a DYNVC_DATA_COMPRESSED PDU.
+    pub fn to_buffer_compressed(&self, stream: impl io::Write) -> Result<(), ChannelError> {
+        self.encode(stream, PduType::DataCompressed)
+    }
+
+    fn encode(&self, mut stream: impl io::Write, pdu_type: PduType) -> Result<(), ChannelError> {
         let dvc_header = Header {
             channel_id_type: self.channel_id_type as u8,
             pdu_dependent: UNUSED_U8,
-            pdu_type: PduType::Data,
+            pdu_type,
         };
         dvc_header.to_buffer(&mut stream)?;
         self.channel_id_type
@@ -18,16 +18,39 @@ pub struct DataFirstPdu {
 
 impl DataFirstPdu {
     pub fn from_buffer(
+        stream: impl io::Read,
+        channel_id_type: FieldType,
+        total_data_size_type: FieldType,
+        data_size: usize,
+    ) -> Result<Self, ChannelError> {
+        Self::decode(stream, channel_id_type, total_data_size_type, data_size, false)
+    }
+
+    /// Parses the body of a DYNVC_DATA_FIRST_COMPRESSED PDU.
+    ///
+    /// The total data size is the size of the uncompressed message, it is not compared with the size of the
+    /// compressed data.
+    pub fn from_buffer_compressed(
+        stream: impl io::Read,
+        channel_id_type: FieldType,
+        total_data_size_type: FieldType,
+        data_size: usize,
+    ) -> Result<Self, ChannelError> {
+        Self::decode(stream, channel_id_type, total_data_size_type, data_size, true)
+    }
+
+    fn decode(
         mut stream: impl io::Read,
         channel_id_type: FieldType,
         total_data_size_type: FieldType,
         mut data_size: usize,
+        is_compressed: bool,
     ) -> Result<Self, ChannelError> {
         let channel_id = channel_id_type.read_buffer_according_to_type(&mut stream)?;
         let total_data_size = total_data_size_type.read_buffer_according_to_type(&mut stream)?;
 
         data_size -= channel_id_type.get_type_size() + total_data_size_type.get_type_size();
-        if data_size > total_data_size as usize {
+        if !is_compressed && data_size > total_data_size as usize {
             return Err(ChannelError::InvalidDvcTotalMessageSize {
                 actual: data_size,
                 expected: total_data_size as usize,
@@ -50,11 +73,20 @@ impl DataFirstPdu {
         }
     }
 
-    pub fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), ChannelError> {
+    pub fn to_buffer(&self, stream: impl io::Write) -> Result<(), ChannelError> {
+        self.encode(stream, PduType::DataFirst)
+    }
+
+    /// Serializes the header of a DYNVC_DATA_FIRST_COMPRESSED PDU.
+    pub fn to_buffer_compressed(&self, stream: impl io::Write) -> Result<(), ChannelError> {
+        self.encode(stream, PduType::DataFirstCompressed)
+    }
+
+    fn encode(&self, mut stream: impl io::Write, pdu_type: PduType) -> Result<(), ChannelError> {
         let dvc_header = Header {
             channel_id_type: self.channel_id_type as u8,
             pdu_dependent: self.total_data_size_type as u8,
-            pdu_type: PduType::DataFirst,
+            pdu_type,
         };
         dvc_header.to_buffer(&mut stream)?;
         self.channel_id_type
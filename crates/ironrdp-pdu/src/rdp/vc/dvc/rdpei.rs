@@ -0,0 +1,999 @@
+//! Input Virtual Channel Extension ([MS-RDPEI]), carrying multitouch and pen input.
+//!
+//! [MS-RDPEI]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpei/a9a6fc8c-2a4b-4c9d-8e28-0cbd7b5a0b39
+
+use std::io;
+
+use bitflags::bitflags;
+use byteorder::{LittleEndian, ReadBytesExt as _, WriteBytesExt as _};
+use num_derive::{FromPrimitive, ToPrimitive};
+use num_traits::{FromPrimitive as _, ToPrimitive as _};
+use thiserror::Error;
+
+use crate::PduParsing;
+
+const RDPINPUT_HEADER_SIZE: usize = 6;
+
+pub const PROTOCOL_V100: u32 = 0x0001_0000;
+pub const PROTOCOL_V101: u32 = 0x0001_0001;
+/// First version supporting pen input
+pub const PROTOCOL_V200: u32 = 0x0002_0000;
+/// First version carrying the supported features in the SC_READY PDU
+pub const PROTOCOL_V300: u32 = 0x0003_0000;
+
+/// Largest value of a TWO_BYTE_UNSIGNED_INTEGER
+pub const TWO_BYTE_UNSIGNED_MAX: u16 = 0x7FFF;
+/// Largest magnitude of a TWO_BYTE_SIGNED_INTEGER
+pub const TWO_BYTE_SIGNED_MAX: i16 = 0x3FFF;
+/// Largest value of a FOUR_BYTE_UNSIGNED_INTEGER
+pub const FOUR_BYTE_UNSIGNED_MAX: u32 = 0x3FFF_FFFF;
+/// Largest magnitude of a FOUR_BYTE_SIGNED_INTEGER
+pub const FOUR_BYTE_SIGNED_MAX: i32 = 0x1FFF_FFFF;
+/// Largest value of an EIGHT_BYTE_UNSIGNED_INTEGER
+pub const EIGHT_BYTE_UNSIGNED_MAX: u64 = 0x1FFF_FFFF_FFFF_FFFF;
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct ScReadyFeatures: u32 {
+        const MULTIPEN_INJECTION_SUPPORTED = 0x0000_0001;
+    }
+}
+
+/// RDPINPUT_SC_READY_PDU, sent by the server when the channel is opened
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScReadyPdu {
+    pub protocol_version: u32,
+    /// Only present from version 3.0.0 of the protocol
+    pub supported_features: Option<ScReadyFeatures>,
+}
+
+impl PduParsing for ScReadyPdu {
+    type Error = RdpeiError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let protocol_version = stream.read_u32::<LittleEndian>()?;
+
+        let supported_features = if protocol_version >= PROTOCOL_V300 {
+            Some(ScReadyFeatures::from_bits_truncate(stream.read_u32::<LittleEndian>()?))
+        } else {
+            None
+        };
+
+        Ok(Self {
+            protocol_version,
+            supported_features,
+        })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        stream.write_u32::<LittleEndian>(self.protocol_version)?;
+
+        if let Some(supported_features) = self.supported_features {
+            stream.write_u32::<LittleEndian>(supported_features.bits())?;
+        }
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        4 + if self.supported_features.is_some() { 4 } else { 0 }
+    }
+}
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct CsReadyFlags: u32 {
+        const SHOW_TOUCH_VISUALS = 0x0000_0001;
+        const DISABLE_TIMESTAMP_INJECTION = 0x0000_0002;
+        const ENABLE_MULTIPEN_INJECTION = 0x0000_0004;
+    }
+}
+
+/// RDPINPUT_CS_READY_PDU, sent by the client in response to the SC_READY PDU
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsReadyPdu {
+    pub flags: CsReadyFlags,
+    pub protocol_version: u32,
+    /// Maximum number of simultaneous touch contacts supported by the client
+    pub max_touch_contacts: u16,
+}
+
+impl PduParsing for CsReadyPdu {
+    type Error = RdpeiError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let flags = CsReadyFlags::from_bits_truncate(stream.read_u32::<LittleEndian>()?);
+        let protocol_version = stream.read_u32::<LittleEndian>()?;
+        let max_touch_contacts = stream.read_u16::<LittleEndian>()?;
+
+        Ok(Self {
+            flags,
+            protocol_version,
+            max_touch_contacts,
+        })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        stream.write_u32::<LittleEndian>(self.flags.bits())?;
+        stream.write_u32::<LittleEndian>(self.protocol_version)?;
+        stream.write_u16::<LittleEndian>(self.max_touch_contacts)?;
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        10
+    }
+}
+
+bitflags! {
+    /// State of a touch or pen contact
+    ///
+    /// Only a few combinations are valid, depending on the previous state of the contact:
+    /// see the contact state diagram of MS-RDPEI 3.1.1.1.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct ContactFlags: u32 {
+        const DOWN = 0x0001;
+        const UPDATE = 0x0002;
+        const UP = 0x0004;
+        const IN_RANGE = 0x0008;
+        const IN_CONTACT = 0x0010;
+        const CANCELED = 0x0020;
+    }
+}
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    struct TouchFieldsPresent: u16 {
+        const CONTACT_RECT = 0x0001;
+        const ORIENTATION = 0x0002;
+        const PRESSURE = 0x0004;
+    }
+}
+
+/// Bounding box of a touch contact, relative to its position
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContactRect {
+    pub left: i16,
+    pub top: i16,
+    pub right: i16,
+    pub bottom: i16,
+}
+
+/// RDPINPUT_CONTACT_DATA
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TouchContact {
+    pub contact_id: u8,
+    pub x: i32,
+    pub y: i32,
+    pub flags: ContactFlags,
+    pub contact_rect: Option<ContactRect>,
+    /// Orientation in degrees, from 0 to 359
+    pub orientation: Option<u32>,
+    /// Normalized pressure, from 0 to 1024
+    pub pressure: Option<u32>,
+}
+
+impl TouchContact {
+    fn fields_present(&self) -> TouchFieldsPresent {
+        let mut fields_present = TouchFieldsPresent::empty();
+        fields_present.set(TouchFieldsPresent::CONTACT_RECT, self.contact_rect.is_some());
+        fields_present.set(TouchFieldsPresent::ORIENTATION, self.orientation.is_some());
+        fields_present.set(TouchFieldsPresent::PRESSURE, self.pressure.is_some());
+        fields_present
+    }
+}
+
+impl PduParsing for TouchContact {
+    type Error = RdpeiError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let contact_id = stream.read_u8()?;
+        let fields_present = TouchFieldsPresent::from_bits_truncate(read_two_byte_unsigned(&mut stream)?);
+        let x = read_four_byte_signed(&mut stream)?;
+        let y = read_four_byte_signed(&mut stream)?;
+        let flags = ContactFlags::from_bits_truncate(read_four_byte_unsigned(&mut stream)?);
+
+        let contact_rect = if fields_present.contains(TouchFieldsPresent::CONTACT_RECT) {
+            Some(ContactRect {
+                left: read_two_byte_signed(&mut stream)?,
+                top: read_two_byte_signed(&mut stream)?,
+                right: read_two_byte_signed(&mut stream)?,
+                bottom: read_two_byte_signed(&mut stream)?,
+            })
+        } else {
+            None
+        };
+
+        let orientation = if fields_present.contains(TouchFieldsPresent::ORIENTATION) {
+            Some(read_four_byte_unsigned(&mut stream)?)
+        } else {
+            None
+        };
+
+        let pressure = if fields_present.contains(TouchFieldsPresent::PRESSURE) {
+            Some(read_four_byte_unsigned(&mut stream)?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            contact_id,
+            x,
+            y,
+            flags,
+            contact_rect,
+            orientation,
+            pressure,
+        })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        stream.write_u8(self.contact_id)?;
+        write_two_byte_unsigned(&mut stream, self.fields_present().bits())?;
+        write_four_byte_signed(&mut stream, self.x)?;
+        write_four_byte_signed(&mut stream, self.y)?;
+        write_four_byte_unsigned(&mut stream, self.flags.bits())?;
+
+        if let Some(contact_rect) = self.contact_rect {
+            write_two_byte_signed(&mut stream, contact_rect.left)?;
+            write_two_byte_signed(&mut stream, contact_rect.top)?;
+            write_two_byte_signed(&mut stream, contact_rect.right)?;
+            write_two_byte_signed(&mut stream, contact_rect.bottom)?;
+        }
+
+        if let Some(orientation) = self.orientation {
+            write_four_byte_unsigned(&mut stream, orientation)?;
+        }
+
+        if let Some(pressure) = self.pressure {
+            write_four_byte_unsigned(&mut stream, pressure)?;
+        }
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        1 + sizeof_two_byte_unsigned(self.fields_present().bits())
+            + sizeof_four_byte_signed(self.x)
+            + sizeof_four_byte_signed(self.y)
+            + sizeof_four_byte_unsigned(self.flags.bits())
+            + self.contact_rect.map_or(0, |rect| {
+                sizeof_two_byte_signed(rect.left)
+                    + sizeof_two_byte_signed(rect.top)
+                    + sizeof_two_byte_signed(rect.right)
+                    + sizeof_two_byte_signed(rect.bottom)
+            })
+            + self.orientation.map_or(0, sizeof_four_byte_unsigned)
+            + self.pressure.map_or(0, sizeof_four_byte_unsigned)
+    }
+}
+
+/// RDPINPUT_TOUCH_FRAME, the state of all the active contacts at a given time
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TouchFrame {
+    /// Time elapsed since the previous frame, in microseconds
+    pub frame_offset: u64,
+    pub contacts: Vec<TouchContact>,
+}
+
+impl PduParsing for TouchFrame {
+    type Error = RdpeiError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let contact_count = read_two_byte_unsigned(&mut stream)?;
+        let frame_offset = read_eight_byte_unsigned(&mut stream)?;
+        let contacts = (0..contact_count)
+            .map(|_| TouchContact::from_buffer(&mut stream))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self { frame_offset, contacts })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        write_two_byte_unsigned(&mut stream, count_to_u16(self.contacts.len())?)?;
+        write_eight_byte_unsigned(&mut stream, self.frame_offset)?;
+
+        for contact in &self.contacts {
+            contact.to_buffer(&mut stream)?;
+        }
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        sizeof_two_byte_unsigned(self.contacts.len() as u16)
+            + sizeof_eight_byte_unsigned(self.frame_offset)
+            + self.contacts.iter().map(PduParsing::buffer_length).sum::<usize>()
+    }
+}
+
+/// RDPINPUT_TOUCH_EVENT_PDU
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TouchEventPdu {
+    /// Time elapsed between the generation of the oldest frame and the encoding of the PDU, in milliseconds
+    pub encode_time: u32,
+    pub frames: Vec<TouchFrame>,
+}
+
+impl PduParsing for TouchEventPdu {
+    type Error = RdpeiError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let encode_time = read_four_byte_unsigned(&mut stream)?;
+        let frame_count = read_two_byte_unsigned(&mut stream)?;
+        let frames = (0..frame_count)
+            .map(|_| TouchFrame::from_buffer(&mut stream))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self { encode_time, frames })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        write_four_byte_unsigned(&mut stream, self.encode_time)?;
+        write_two_byte_unsigned(&mut stream, count_to_u16(self.frames.len())?)?;
+
+        for frame in &self.frames {
+            frame.to_buffer(&mut stream)?;
+        }
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        sizeof_four_byte_unsigned(self.encode_time)
+            + sizeof_two_byte_unsigned(self.frames.len() as u16)
+            + self.frames.iter().map(PduParsing::buffer_length).sum::<usize>()
+    }
+}
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct PenFlags: u32 {
+        const BARREL_PRESSED = 0x0001;
+        const ERASER_PRESSED = 0x0002;
+        const INVERTED = 0x0004;
+    }
+}
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    struct PenFieldsPresent: u16 {
+        const PEN_FLAGS = 0x0001;
+        const PRESSURE = 0x0002;
+        const ROTATION = 0x0004;
+        const TILT_X = 0x0008;
+        const TILT_Y = 0x0010;
+    }
+}
+
+/// RDPINPUT_PEN_CONTACT
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PenContact {
+    pub device_id: u8,
+    pub x: i32,
+    pub y: i32,
+    pub flags: ContactFlags,
+    pub pen_flags: Option<PenFlags>,
+    /// Normalized pressure, from 0 to 1024
+    pub pressure: Option<u32>,
+    /// Clockwise rotation in degrees, from 0 to 359
+    pub rotation: Option<u16>,
+    /// Tilt along the X axis in degrees, from -90 to 90
+    pub tilt_x: Option<i16>,
+    /// Tilt along the Y axis in degrees, from -90 to 90
+    pub tilt_y: Option<i16>,
+}
+
+impl PenContact {
+    fn fields_present(&self) -> PenFieldsPresent {
+        let mut fields_present = PenFieldsPresent::empty();
+        fields_present.set(PenFieldsPresent::PEN_FLAGS, self.pen_flags.is_some());
+        fields_present.set(PenFieldsPresent::PRESSURE, self.pressure.is_some());
+        fields_present.set(PenFieldsPresent::ROTATION, self.rotation.is_some());
+        fields_present.set(PenFieldsPresent::TILT_X, self.tilt_x.is_some());
+        fields_present.set(PenFieldsPresent::TILT_Y, self.tilt_y.is_some());
+        fields_present
+    }
+}
+
+impl PduParsing for PenContact {
+    type Error = RdpeiError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let device_id = stream.read_u8()?;
+        let fields_present = PenFieldsPresent::from_bits_truncate(read_two_byte_unsigned(&mut stream)?);
+        let x = read_four_byte_signed(&mut stream)?;
+        let y = read_four_byte_signed(&mut stream)?;
+        let flags = ContactFlags::from_bits_truncate(read_four_byte_unsigned(&mut stream)?);
+
+        let pen_flags = if fields_present.contains(PenFieldsPresent::PEN_FLAGS) {
+            Some(PenFlags::from_bits_truncate(read_four_byte_unsigned(&mut stream)?))
+        } else {
+            None
+        };
+
+        let pressure = if fields_present.contains(PenFieldsPresent::PRESSURE) {
+            Some(read_four_byte_unsigned(&mut stream)?)
+        } else {
+            None
+        };
+
+        let rotation = if fields_present.contains(PenFieldsPresent::ROTATION) {
+            Some(read_two_byte_unsigned(&mut stream)?)
+        } else {
+            None
+        };
+
+        let tilt_x = if fields_present.contains(PenFieldsPresent::TILT_X) {
+            Some(read_two_byte_signed(&mut stream)?)
+        } else {
+            None
+        };
+
+        let tilt_y = if fields_present.contains(PenFieldsPresent::TILT_Y) {
+            Some(read_two_byte_signed(&mut stream)?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            device_id,
+            x,
+            y,
+            flags,
+            pen_flags,
+            pressure,
+            rotation,
+            tilt_x,
+            tilt_y,
+        })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        stream.write_u8(self.device_id)?;
+        write_two_byte_unsigned(&mut stream, self.fields_present().bits())?;
+        write_four_byte_signed(&mut stream, self.x)?;
+        write_four_byte_signed(&mut stream, self.y)?;
+        write_four_byte_unsigned(&mut stream, self.flags.bits())?;
+
+        if let Some(pen_flags) = self.pen_flags {
+            write_four_byte_unsigned(&mut stream, pen_flags.bits())?;
+        }
+
+        if let Some(pressure) = self.pressure {
+            write_four_byte_unsigned(&mut stream, pressure)?;
+        }
+
+        if let Some(rotation) = self.rotation {
+            write_two_byte_unsigned(&mut stream, rotation)?;
+        }
+
+        if let Some(tilt_x) = self.tilt_x {
+            write_two_byte_signed(&mut stream, tilt_x)?;
+        }
+
+        if let Some(tilt_y) = self.tilt_y {
+            write_two_byte_signed(&mut stream, tilt_y)?;
+        }
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        1 + sizeof_two_byte_unsigned(self.fields_present().bits())
+            + sizeof_four_byte_signed(self.x)
+            + sizeof_four_byte_signed(self.y)
+            + sizeof_four_byte_unsigned(self.flags.bits())
+            + self
+                .pen_flags
+                .map_or(0, |pen_flags| sizeof_four_byte_unsigned(pen_flags.bits()))
+            + self.pressure.map_or(0, sizeof_four_byte_unsigned)
+            + self.rotation.map_or(0, sizeof_two_byte_unsigned)
+            + self.tilt_x.map_or(0, sizeof_two_byte_signed)
+            + self.tilt_y.map_or(0, sizeof_two_byte_signed)
+    }
+}
+
+/// RDPINPUT_PEN_FRAME
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PenFrame {
+    /// Time elapsed since the previous frame, in microseconds
+    pub frame_offset: u64,
+    pub contacts: Vec<PenContact>,
+}
+
+impl PduParsing for PenFrame {
+    type Error = RdpeiError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let contact_count = read_two_byte_unsigned(&mut stream)?;
+        let frame_offset = read_eight_byte_unsigned(&mut stream)?;
+        let contacts = (0..contact_count)
+            .map(|_| PenContact::from_buffer(&mut stream))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self { frame_offset, contacts })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        write_two_byte_unsigned(&mut stream, count_to_u16(self.contacts.len())?)?;
+        write_eight_byte_unsigned(&mut stream, self.frame_offset)?;
+
+        for contact in &self.contacts {
+            contact.to_buffer(&mut stream)?;
+        }
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        sizeof_two_byte_unsigned(self.contacts.len() as u16)
+            + sizeof_eight_byte_unsigned(self.frame_offset)
+            + self.contacts.iter().map(PduParsing::buffer_length).sum::<usize>()
+    }
+}
+
+/// RDPINPUT_PEN_EVENT_PDU, from version 2.0.0 of the protocol
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PenEventPdu {
+    /// Time elapsed between the generation of the oldest frame and the encoding of the PDU, in milliseconds
+    pub encode_time: u32,
+    pub frames: Vec<PenFrame>,
+}
+
+impl PduParsing for PenEventPdu {
+    type Error = RdpeiError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let encode_time = read_four_byte_unsigned(&mut stream)?;
+        let frame_count = read_two_byte_unsigned(&mut stream)?;
+        let frames = (0..frame_count)
+            .map(|_| PenFrame::from_buffer(&mut stream))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self { encode_time, frames })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        write_four_byte_unsigned(&mut stream, self.encode_time)?;
+        write_two_byte_unsigned(&mut stream, count_to_u16(self.frames.len())?)?;
+
+        for frame in &self.frames {
+            frame.to_buffer(&mut stream)?;
+        }
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        sizeof_four_byte_unsigned(self.encode_time)
+            + sizeof_two_byte_unsigned(self.frames.len() as u16)
+            + self.frames.iter().map(PduParsing::buffer_length).sum::<usize>()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServerPdu {
+    ScReady(ScReadyPdu),
+    /// The client must stop sending touch and pen events until the input is resumed
+    SuspendInput,
+    ResumeInput,
+}
+
+impl PduParsing for ServerPdu {
+    type Error = RdpeiError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let event_id = stream.read_u16::<LittleEndian>()?;
+        let pdu_type = ServerPduType::from_u16(event_id).ok_or(RdpeiError::InvalidEventId(event_id))?;
+        let pdu_length = stream.read_u32::<LittleEndian>()? as usize;
+
+        let server_pdu = match pdu_type {
+            ServerPduType::ScReady => ServerPdu::ScReady(ScReadyPdu::from_buffer(&mut stream)?),
+            ServerPduType::SuspendInput => ServerPdu::SuspendInput,
+            ServerPduType::ResumeInput => ServerPdu::ResumeInput,
+        };
+        let buffer_length = server_pdu.buffer_length();
+
+        if buffer_length != pdu_length {
+            Err(RdpeiError::InvalidPduLength {
+                expected: pdu_length,
+                actual: buffer_length,
+            })
+        } else {
+            Ok(server_pdu)
+        }
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        stream.write_u16::<LittleEndian>(ServerPduType::from(self).to_u16().unwrap())?;
+        stream.write_u32::<LittleEndian>(self.buffer_length() as u32)?;
+
+        match self {
+            ServerPdu::ScReady(pdu) => pdu.to_buffer(&mut stream),
+            ServerPdu::SuspendInput | ServerPdu::ResumeInput => Ok(()),
+        }
+    }
+
+    fn buffer_length(&self) -> usize {
+        RDPINPUT_HEADER_SIZE
+            + match self {
+                ServerPdu::ScReady(pdu) => pdu.buffer_length(),
+                ServerPdu::SuspendInput | ServerPdu::ResumeInput => 0,
+            }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+pub enum ServerPduType {
+    ScReady = 0x0001,
+    SuspendInput = 0x0004,
+    ResumeInput = 0x0005,
+}
+
+impl<'a> From<&'a ServerPdu> for ServerPduType {
+    fn from(s: &'a ServerPdu) -> Self {
+        match s {
+            ServerPdu::ScReady(_) => Self::ScReady,
+            ServerPdu::SuspendInput => Self::SuspendInput,
+            ServerPdu::ResumeInput => Self::ResumeInput,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClientPdu {
+    CsReady(CsReadyPdu),
+    Touch(TouchEventPdu),
+    /// Tells the server that the hovering contact with the given ID left the range of the digitizer
+    DismissHoveringContact(u8),
+    Pen(PenEventPdu),
+}
+
+impl PduParsing for ClientPdu {
+    type Error = RdpeiError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let event_id = stream.read_u16::<LittleEndian>()?;
+        let pdu_type = ClientPduType::from_u16(event_id).ok_or(RdpeiError::InvalidEventId(event_id))?;
+        let pdu_length = stream.read_u32::<LittleEndian>()? as usize;
+
+        let client_pdu = match pdu_type {
+            ClientPduType::CsReady => ClientPdu::CsReady(CsReadyPdu::from_buffer(&mut stream)?),
+            ClientPduType::Touch => ClientPdu::Touch(TouchEventPdu::from_buffer(&mut stream)?),
+            ClientPduType::DismissHoveringContact => ClientPdu::DismissHoveringContact(stream.read_u8()?),
+            ClientPduType::Pen => ClientPdu::Pen(PenEventPdu::from_buffer(&mut stream)?),
+        };
+        let buffer_length = client_pdu.buffer_length();
+
+        if buffer_length != pdu_length {
+            Err(RdpeiError::InvalidPduLength {
+                expected: pdu_length,
+                actual: buffer_length,
+            })
+        } else {
+            Ok(client_pdu)
+        }
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        stream.write_u16::<LittleEndian>(ClientPduType::from(self).to_u16().unwrap())?;
+        stream.write_u32::<LittleEndian>(self.buffer_length() as u32)?;
+
+        match self {
+            ClientPdu::CsReady(pdu) => pdu.to_buffer(&mut stream),
+            ClientPdu::Touch(pdu) => pdu.to_buffer(&mut stream),
+            ClientPdu::DismissHoveringContact(contact_id) => Ok(stream.write_u8(*contact_id)?),
+            ClientPdu::Pen(pdu) => pdu.to_buffer(&mut stream),
+        }
+    }
+
+    fn buffer_length(&self) -> usize {
+        RDPINPUT_HEADER_SIZE
+            + match self {
+                ClientPdu::CsReady(pdu) => pdu.buffer_length(),
+                ClientPdu::Touch(pdu) => pdu.buffer_length(),
+                ClientPdu::DismissHoveringContact(_) => 1,
+                ClientPdu::Pen(pdu) => pdu.buffer_length(),
+            }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+pub enum ClientPduType {
+    CsReady = 0x0002,
+    Touch = 0x0003,
+    DismissHoveringContact = 0x0006,
+    Pen = 0x0008,
+}
+
+impl<'a> From<&'a ClientPdu> for ClientPduType {
+    fn from(s: &'a ClientPdu) -> Self {
+        match s {
+            ClientPdu::CsReady(_) => Self::CsReady,
+            ClientPdu::Touch(_) => Self::Touch,
+            ClientPdu::DismissHoveringContact(_) => Self::DismissHoveringContact,
+            ClientPdu::Pen(_) => Self::Pen,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum RdpeiError {
+    #[error("IO error")]
+    IOError(#[from] io::Error),
+    #[error("Invalid event ID: {0}")]
+    InvalidEventId(u16),
+    #[error("Invalid PDU length: expected ({expected}) != actual ({actual})")]
+    InvalidPduLength { expected: usize, actual: usize },
+    #[error("Value out of the range of its variable-length encoding: {0}")]
+    ValueOutOfRange(i64),
+}
+
+// Variable-length integers (MS-RDPEI 2.2.2)
+//
+// The first byte holds the number of additional bytes and the sign, if any, in its most significant bits.
+// Its remaining bits are the most significant bits of the magnitude, followed by the additional bytes
+// in big-endian order.
+
+fn count_to_u16(count: usize) -> Result<u16, RdpeiError> {
+    u16::try_from(count)
+        .ok()
+        .filter(|count| *count <= TWO_BYTE_UNSIGNED_MAX)
+        .ok_or(RdpeiError::ValueOutOfRange(count as i64))
+}
+
+/// Reads the magnitude of a variable-length integer whose first byte keeps `value_bits` bits for the value
+fn read_magnitude(mut stream: impl io::Read, first_byte: u8, value_bits: u32, extra_bytes: usize) -> io::Result<u64> {
+    let mut magnitude = u64::from(first_byte & ((1 << value_bits) - 1));
+
+    for _ in 0..extra_bytes {
+        magnitude = (magnitude << 8) | u64::from(stream.read_u8()?);
+    }
+
+    Ok(magnitude)
+}
+
+/// Writes the magnitude of a variable-length integer, `header` holding the bits preceding the value in the first byte
+fn write_magnitude(mut stream: impl io::Write, header: u8, magnitude: u64, extra_bytes: usize) -> io::Result<()> {
+    stream.write_u8(header | (magnitude >> (8 * extra_bytes)) as u8)?;
+
+    for idx in (0..extra_bytes).rev() {
+        stream.write_u8((magnitude >> (8 * idx)) as u8)?;
+    }
+
+    Ok(())
+}
+
+/// Number of bytes following the first one, for a magnitude whose first byte keeps `value_bits` bits for the value
+fn extra_bytes(magnitude: u64, value_bits: u32) -> usize {
+    let mut extra_bytes = 0;
+
+    while magnitude >> (value_bits + 8 * extra_bytes as u32) != 0 {
+        extra_bytes += 1;
+    }
+
+    extra_bytes
+}
+
+fn read_two_byte_unsigned(mut stream: impl io::Read) -> io::Result<u16> {
+    let first_byte = stream.read_u8()?;
+    let extra_bytes = usize::from(first_byte >> 7);
+    let value = read_magnitude(stream, first_byte, 7, extra_bytes)?;
+
+    Ok(value as u16)
+}
+
+fn write_two_byte_unsigned(stream: impl io::Write, value: u16) -> Result<(), RdpeiError> {
+    if value > TWO_BYTE_UNSIGNED_MAX {
+        return Err(RdpeiError::ValueOutOfRange(i64::from(value)));
+    }
+
+    let extra_bytes = extra_bytes(u64::from(value), 7);
+    write_magnitude(stream, (extra_bytes as u8) << 7, u64::from(value), extra_bytes)?;
+
+    Ok(())
+}
+
+fn sizeof_two_byte_unsigned(value: u16) -> usize {
+    1 + extra_bytes(u64::from(value), 7)
+}
+
+fn read_two_byte_signed(mut stream: impl io::Read) -> io::Result<i16> {
+    let first_byte = stream.read_u8()?;
+    let extra_bytes = usize::from(first_byte >> 7);
+    let magnitude = read_magnitude(stream, first_byte, 6, extra_bytes)? as i16;
+
+    if first_byte & 0x40 != 0 {
+        Ok(-magnitude)
+    } else {
+        Ok(magnitude)
+    }
+}
+
+fn write_two_byte_signed(stream: impl io::Write, value: i16) -> Result<(), RdpeiError> {
+    if !(-TWO_BYTE_SIGNED_MAX..=TWO_BYTE_SIGNED_MAX).contains(&value) {
+        return Err(RdpeiError::ValueOutOfRange(i64::from(value)));
+    }
+
+    let magnitude = u64::from(value.unsigned_abs());
+    let extra_bytes = extra_bytes(magnitude, 6);
+    let sign = if value < 0 { 0x40 } else { 0 };
+    write_magnitude(stream, (extra_bytes as u8) << 7 | sign, magnitude, extra_bytes)?;
+
+    Ok(())
+}
+
+fn sizeof_two_byte_signed(value: i16) -> usize {
+    1 + extra_bytes(u64::from(value.unsigned_abs()), 6)
+}
+
+fn read_four_byte_unsigned(mut stream: impl io::Read) -> io::Result<u32> {
+    let first_byte = stream.read_u8()?;
+    let extra_bytes = usize::from(first_byte >> 6);
+    let value = read_magnitude(stream, first_byte, 6, extra_bytes)?;
+
+    Ok(value as u32)
+}
+
+fn write_four_byte_unsigned(stream: impl io::Write, value: u32) -> Result<(), RdpeiError> {
+    if value > FOUR_BYTE_UNSIGNED_MAX {
+        return Err(RdpeiError::ValueOutOfRange(i64::from(value)));
+    }
+
+    let extra_bytes = extra_bytes(u64::from(value), 6);
+    write_magnitude(stream, (extra_bytes as u8) << 6, u64::from(value), extra_bytes)?;
+
+    Ok(())
+}
+
+fn sizeof_four_byte_unsigned(value: u32) -> usize {
+    1 + extra_bytes(u64::from(value), 6)
+}
+
+fn read_four_byte_signed(mut stream: impl io::Read) -> io::Result<i32> {
+    let first_byte = stream.read_u8()?;
+    let extra_bytes = usize::from(first_byte >> 6);
+    let magnitude = read_magnitude(stream, first_byte, 5, extra_bytes)? as i32;
+
+    if first_byte & 0x20 != 0 {
+        Ok(-magnitude)
+    } else {
+        Ok(magnitude)
+    }
+}
+
+fn write_four_byte_signed(stream: impl io::Write, value: i32) -> Result<(), RdpeiError> {
+    if !(-FOUR_BYTE_SIGNED_MAX..=FOUR_BYTE_SIGNED_MAX).contains(&value) {
+        return Err(RdpeiError::ValueOutOfRange(i64::from(value)));
+    }
+
+    let magnitude = u64::from(value.unsigned_abs());
+    let extra_bytes = extra_bytes(magnitude, 5);
+    let sign = if value < 0 { 0x20 } else { 0 };
+    write_magnitude(stream, (extra_bytes as u8) << 6 | sign, magnitude, extra_bytes)?;
+
+    Ok(())
+}
+
+fn sizeof_four_byte_signed(value: i32) -> usize {
+    1 + extra_bytes(u64::from(value.unsigned_abs()), 5)
+}
+
+fn read_eight_byte_unsigned(mut stream: impl io::Read) -> io::Result<u64> {
+    let first_byte = stream.read_u8()?;
+    let extra_bytes = usize::from(first_byte >> 5);
+
+    read_magnitude(stream, first_byte, 5, extra_bytes)
+}
+
+fn write_eight_byte_unsigned(stream: impl io::Write, value: u64) -> Result<(), RdpeiError> {
+    if value > EIGHT_BYTE_UNSIGNED_MAX {
+        return Err(RdpeiError::ValueOutOfRange(i64::try_from(value).unwrap_or(i64::MAX)));
+    }
+
+    let extra_bytes = extra_bytes(value, 5);
+    write_magnitude(stream, (extra_bytes as u8) << 5, value, extra_bytes)?;
+
+    Ok(())
+}
+
+fn sizeof_eight_byte_unsigned(value: u64) -> usize {
+    1 + extra_bytes(value, 5)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn two_byte_unsigned() {
+        for (value, encoded) in [
+            (0x12, &[0x12][..]),
+            (0x7F, &[0x7F]),
+            (0x80, &[0x80, 0x80]),
+            (0x7FFF, &[0xFF, 0xFF]),
+        ] {
+            let mut buf = Vec::new();
+            write_two_byte_unsigned(&mut buf, value).unwrap();
+            assert_eq!(buf, encoded);
+            assert_eq!(sizeof_two_byte_unsigned(value), encoded.len());
+            assert_eq!(read_two_byte_unsigned(encoded).unwrap(), value);
+        }
+
+        assert!(write_two_byte_unsigned(Vec::new(), 0x8000).is_err());
+    }
+
+    #[test]
+    fn two_byte_signed() {
+        for (value, encoded) in [
+            (0x3F, &[0x3F][..]),
+            (-0x3F, &[0x7F]),
+            (0x40, &[0x80, 0x40]),
+            (-0x3FFF, &[0xFF, 0xFF]),
+        ] {
+            let mut buf = Vec::new();
+            write_two_byte_signed(&mut buf, value).unwrap();
+            assert_eq!(buf, encoded);
+            assert_eq!(sizeof_two_byte_signed(value), encoded.len());
+            assert_eq!(read_two_byte_signed(encoded).unwrap(), value);
+        }
+
+        assert!(write_two_byte_signed(Vec::new(), -0x4000).is_err());
+    }
+
+    #[test]
+    fn four_byte_unsigned() {
+        for (value, encoded) in [
+            (0x3F, &[0x3F][..]),
+            (0x40, &[0x40, 0x40]),
+            (0x12_3456, &[0x92, 0x34, 0x56]),
+            (0x3FFF_FFFF, &[0xFF, 0xFF, 0xFF, 0xFF]),
+        ] {
+            let mut buf = Vec::new();
+            write_four_byte_unsigned(&mut buf, value).unwrap();
+            assert_eq!(buf, encoded);
+            assert_eq!(sizeof_four_byte_unsigned(value), encoded.len());
+            assert_eq!(read_four_byte_unsigned(encoded).unwrap(), value);
+        }
+
+        assert!(write_four_byte_unsigned(Vec::new(), 0x4000_0000).is_err());
+    }
+
+    #[test]
+    fn four_byte_signed() {
+        for (value, encoded) in [
+            (0x1F, &[0x1F][..]),
+            (-0x1F, &[0x3F]),
+            (-0x20, &[0x60, 0x20]),
+            (0x1FFF_FFFF, &[0xDF, 0xFF, 0xFF, 0xFF]),
+        ] {
+            let mut buf = Vec::new();
+            write_four_byte_signed(&mut buf, value).unwrap();
+            assert_eq!(buf, encoded);
+            assert_eq!(sizeof_four_byte_signed(value), encoded.len());
+            assert_eq!(read_four_byte_signed(encoded).unwrap(), value);
+        }
+
+        assert!(write_four_byte_signed(Vec::new(), 0x2000_0000).is_err());
+    }
+
+    #[test]
+    fn eight_byte_unsigned() {
+        for (value, encoded) in [
+            (0x1F, &[0x1F][..]),
+            (0x20, &[0x20, 0x20]),
+            (0x1234_5678, &[0x72, 0x34, 0x56, 0x78]),
+            (EIGHT_BYTE_UNSIGNED_MAX, &[0xFF; 8]),
+        ] {
+            let mut buf = Vec::new();
+            write_eight_byte_unsigned(&mut buf, value).unwrap();
+            assert_eq!(buf, encoded);
+            assert_eq!(sizeof_eight_byte_unsigned(value), encoded.len());
+            assert_eq!(read_eight_byte_unsigned(encoded).unwrap(), value);
+        }
+
+        assert!(write_eight_byte_unsigned(Vec::new(), EIGHT_BYTE_UNSIGNED_MAX + 1).is_err());
+    }
+}
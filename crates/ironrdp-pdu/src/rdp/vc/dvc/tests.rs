@@ -69,6 +69,67 @@ fn from_buffer_parsing_for_server_dvc_pdu_with_invalid_id_length_type_fails() {
     };
 }
 
+#[test]
+fn from_buffer_correct_parses_server_dvc_create_request_pdu_with_priority() {
+    let buffer = [0x18, 0x03, 0x74, 0x65, 0x73, 0x74, 0x64, 0x76, 0x63, 0x00];
+
+    match ServerPdu::from_buffer(buffer.as_ref(), buffer.len()).unwrap() {
+        ServerPdu::CreateRequest(create_request) => {
+            assert_eq!(ChannelPriority::Low, create_request.priority);
+            assert_eq!("testdvc", create_request.channel_name);
+        }
+        pdu => panic!("Expected Create Request PDU, got: {pdu:?}"),
+    }
+}
+
+#[test]
+fn server_dvc_data_first_compressed_pdu_round_trip() {
+    // The compressed data may be larger than the uncompressed message
+    let mut buffer = vec![0x60, 0x03, 0x02];
+    buffer.extend_from_slice(&[0x04, 0x61, 0x62]);
+
+    let pdu = ServerPdu::from_buffer(buffer.as_slice(), buffer.len()).unwrap();
+    let expected = ServerPdu::DataFirstCompressed(DataFirstPdu {
+        channel_id_type: FieldType::U8,
+        channel_id: 0x03,
+        total_data_size_type: FieldType::U8,
+        total_data_size: 0x02,
+        data_size: 3,
+    });
+    assert_eq!(expected, pdu);
+
+    let mut encoded = Vec::new();
+    pdu.to_buffer(&mut encoded).unwrap();
+    assert_eq!(buffer[..pdu.buffer_length()], encoded);
+}
+
+#[test]
+fn server_dvc_data_compressed_pdu_round_trip() {
+    let buffer = [0x70, 0x03, 0x04, 0x61, 0x62];
+
+    let pdu = ServerPdu::from_buffer(buffer.as_ref(), buffer.len()).unwrap();
+    let expected = ServerPdu::DataCompressed(DataPdu {
+        channel_id_type: FieldType::U8,
+        channel_id: 0x03,
+        data_size: 3,
+    });
+    assert_eq!(expected, pdu);
+
+    let mut encoded = Vec::new();
+    pdu.to_buffer(&mut encoded).unwrap();
+    assert_eq!(buffer[..pdu.buffer_length()], encoded);
+}
+
+#[test]
+fn from_buffer_parsing_for_client_dvc_data_compressed_pdu_fails() {
+    let buffer = [0x70, 0x03, 0x04, 0x61, 0x62];
+
+    match ClientPdu::from_buffer(buffer.as_ref(), buffer.len()) {
+        Err(ChannelError::InvalidDvcPduType) => (),
+        res => panic!("Expected InvalidDvcPduType error, got: {res:?}"),
+    };
+}
+
 #[test]
 fn from_buffer_according_to_type_u8_test() {
     let channel_id = FieldType::U8
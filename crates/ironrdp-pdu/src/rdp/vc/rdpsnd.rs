@@ -0,0 +1,405 @@
+//! Audio Output Virtual Channel Extension PDUs ([MS-RDPEA])
+//!
+//! The same PDUs are exchanged on the `rdpsnd` static virtual channel and on the `AUDIO_PLAYBACK_DVC`
+//! dynamic virtual channel.
+//!
+//! [MS-RDPEA]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpea/bea2d5cf-e3b9-4419-92e5-0e074ff9bc5b
+
+mod formats;
+mod wave;
+
+use std::io;
+
+use byteorder::{LittleEndian, ReadBytesExt as _, WriteBytesExt as _};
+use num_derive::{FromPrimitive, ToPrimitive};
+use num_traits::FromPrimitive as _;
+use thiserror::Error;
+
+pub use self::formats::{AudioFormat, AudioFormatFlags, AudioFormatPdu, AudioVersion, WaveFormat};
+pub use self::wave::{Wave2Pdu, WaveConfirmPdu, WaveInfoPdu};
+use crate::PduParsing;
+
+pub const RDPSND_CHANNEL_NAME: &str = "rdpsnd";
+pub const AUDIO_PLAYBACK_DVC_CHANNEL_NAME: &str = "AUDIO_PLAYBACK_DVC";
+pub const AUDIO_PLAYBACK_LOSSY_DVC_CHANNEL_NAME: &str = "AUDIO_PLAYBACK_LOSSY_DVC";
+
+const RDPSND_HEADER_SIZE: usize = 4;
+const TRAINING_PDU_SIZE: usize = 4;
+const VOLUME_PDU_SIZE: usize = 4;
+const PITCH_PDU_SIZE: usize = 4;
+const QUALITY_MODE_PDU_SIZE: usize = 4;
+
+#[repr(u8)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+enum RdpsndPduType {
+    Close = 0x01,
+    Wave = 0x02,
+    SetVolume = 0x03,
+    SetPitch = 0x04,
+    WaveConfirm = 0x05,
+    Training = 0x06,
+    Formats = 0x07,
+    CryptKey = 0x08,
+    WaveEncrypt = 0x09,
+    UdpWave = 0x0A,
+    UdpWaveLast = 0x0B,
+    QualityMode = 0x0C,
+    Wave2 = 0x0D,
+}
+
+/// Reads the RDPSND PDU Header (SNDPROLOG), and returns the PDU type along with the body size
+fn read_header(mut stream: impl io::Read) -> Result<(RdpsndPduType, usize), RdpsndError> {
+    let msg_type = stream.read_u8()?;
+    let pdu_type = RdpsndPduType::from_u8(msg_type).ok_or(RdpsndError::InvalidPduType(msg_type))?;
+    let _pad = stream.read_u8()?;
+    let body_size = usize::from(stream.read_u16::<LittleEndian>()?);
+
+    Ok((pdu_type, body_size))
+}
+
+fn write_header(mut stream: impl io::Write, pdu_type: RdpsndPduType, body_size: usize) -> Result<(), RdpsndError> {
+    stream.write_u8(pdu_type as u8)?;
+    stream.write_u8(0)?; // bPad
+    stream.write_u16::<LittleEndian>(u16::try_from(body_size).map_err(|_| RdpsndError::BodyTooLarge(body_size))?)?;
+
+    Ok(())
+}
+
+/// Audio output PDU sent by the server, along with its RDPSND PDU Header (SNDPROLOG)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServerAudioOutputPdu {
+    Formats(AudioFormatPdu),
+    Training(TrainingPdu),
+    /// Followed by a Wave PDU carrying the audio data
+    WaveInfo(WaveInfoPdu),
+    Wave2(Wave2Pdu),
+    Volume(VolumePdu),
+    Pitch(PitchPdu),
+    Close,
+}
+
+impl ServerAudioOutputPdu {
+    fn pdu_type(&self) -> RdpsndPduType {
+        match self {
+            Self::Formats(_) => RdpsndPduType::Formats,
+            Self::Training(_) => RdpsndPduType::Training,
+            Self::WaveInfo(_) => RdpsndPduType::Wave,
+            Self::Wave2(_) => RdpsndPduType::Wave2,
+            Self::Volume(_) => RdpsndPduType::SetVolume,
+            Self::Pitch(_) => RdpsndPduType::SetPitch,
+            Self::Close => RdpsndPduType::Close,
+        }
+    }
+
+    /// Value of the BodySize header field, which also covers the following Wave PDU for the WaveInfo PDU
+    fn body_size(&self) -> usize {
+        match self {
+            Self::WaveInfo(wave_info) => wave_info.body_size(),
+            _ => self.body_length(),
+        }
+    }
+
+    fn body_length(&self) -> usize {
+        match self {
+            Self::Formats(formats) => formats.buffer_length(),
+            Self::Training(training) => training.buffer_length(),
+            Self::WaveInfo(wave_info) => wave_info.buffer_length(),
+            Self::Wave2(wave) => wave.buffer_length(),
+            Self::Volume(volume) => volume.buffer_length(),
+            Self::Pitch(pitch) => pitch.buffer_length(),
+            Self::Close => 0,
+        }
+    }
+}
+
+impl PduParsing for ServerAudioOutputPdu {
+    type Error = RdpsndError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let (pdu_type, body_size) = read_header(&mut stream)?;
+
+        let pdu = match pdu_type {
+            RdpsndPduType::Formats => Self::Formats(AudioFormatPdu::from_buffer(&mut stream)?),
+            RdpsndPduType::Training => Self::Training(TrainingPdu::decode(&mut stream, body_size)?),
+            RdpsndPduType::Wave => Self::WaveInfo(WaveInfoPdu::decode(&mut stream, body_size)?),
+            RdpsndPduType::Wave2 => Self::Wave2(Wave2Pdu::decode(&mut stream, body_size)?),
+            RdpsndPduType::SetVolume => Self::Volume(VolumePdu::from_buffer(&mut stream)?),
+            RdpsndPduType::SetPitch => Self::Pitch(PitchPdu::from_buffer(&mut stream)?),
+            RdpsndPduType::Close => Self::Close,
+            unexpected => return Err(RdpsndError::UnexpectedPduType(unexpected as u8)),
+        };
+
+        Ok(pdu)
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        write_header(&mut stream, self.pdu_type(), self.body_size())?;
+
+        match self {
+            Self::Formats(formats) => formats.to_buffer(&mut stream)?,
+            Self::Training(training) => training.to_buffer(&mut stream)?,
+            Self::WaveInfo(wave_info) => wave_info.to_buffer(&mut stream)?,
+            Self::Wave2(wave) => wave.to_buffer(&mut stream)?,
+            Self::Volume(volume) => volume.to_buffer(&mut stream)?,
+            Self::Pitch(pitch) => pitch.to_buffer(&mut stream)?,
+            Self::Close => (),
+        }
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        RDPSND_HEADER_SIZE + self.body_length()
+    }
+}
+
+/// Audio output PDU sent by the client, along with its RDPSND PDU Header (SNDPROLOG)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClientAudioOutputPdu {
+    Formats(AudioFormatPdu),
+    TrainingConfirm(TrainingConfirmPdu),
+    WaveConfirm(WaveConfirmPdu),
+    QualityMode(QualityModePdu),
+}
+
+impl ClientAudioOutputPdu {
+    fn pdu_type(&self) -> RdpsndPduType {
+        match self {
+            Self::Formats(_) => RdpsndPduType::Formats,
+            Self::TrainingConfirm(_) => RdpsndPduType::Training,
+            Self::WaveConfirm(_) => RdpsndPduType::WaveConfirm,
+            Self::QualityMode(_) => RdpsndPduType::QualityMode,
+        }
+    }
+
+    fn body_length(&self) -> usize {
+        match self {
+            Self::Formats(formats) => formats.buffer_length(),
+            Self::TrainingConfirm(training_confirm) => training_confirm.buffer_length(),
+            Self::WaveConfirm(wave_confirm) => wave_confirm.buffer_length(),
+            Self::QualityMode(quality_mode) => quality_mode.buffer_length(),
+        }
+    }
+}
+
+impl PduParsing for ClientAudioOutputPdu {
+    type Error = RdpsndError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let (pdu_type, _body_size) = read_header(&mut stream)?;
+
+        let pdu = match pdu_type {
+            RdpsndPduType::Formats => Self::Formats(AudioFormatPdu::from_buffer(&mut stream)?),
+            RdpsndPduType::Training => Self::TrainingConfirm(TrainingConfirmPdu::from_buffer(&mut stream)?),
+            RdpsndPduType::WaveConfirm => Self::WaveConfirm(WaveConfirmPdu::from_buffer(&mut stream)?),
+            RdpsndPduType::QualityMode => Self::QualityMode(QualityModePdu::from_buffer(&mut stream)?),
+            unexpected => return Err(RdpsndError::UnexpectedPduType(unexpected as u8)),
+        };
+
+        Ok(pdu)
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        write_header(&mut stream, self.pdu_type(), self.body_length())?;
+
+        match self {
+            Self::Formats(formats) => formats.to_buffer(&mut stream)?,
+            Self::TrainingConfirm(training_confirm) => training_confirm.to_buffer(&mut stream)?,
+            Self::WaveConfirm(wave_confirm) => wave_confirm.to_buffer(&mut stream)?,
+            Self::QualityMode(quality_mode) => quality_mode.to_buffer(&mut stream)?,
+        }
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        RDPSND_HEADER_SIZE + self.body_length()
+    }
+}
+
+/// Training PDU (SNDTRAINING)
+///
+/// Sent by the server to measure the network characteristics, the client replies with a Training Confirm PDU.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrainingPdu {
+    pub timestamp: u16,
+    pub pack_size: u16,
+    pub data: Vec<u8>,
+}
+
+impl TrainingPdu {
+    fn decode(mut stream: impl io::Read, body_size: usize) -> Result<Self, RdpsndError> {
+        let timestamp = stream.read_u16::<LittleEndian>()?;
+        let pack_size = stream.read_u16::<LittleEndian>()?;
+
+        let mut data = vec![0; body_size.saturating_sub(TRAINING_PDU_SIZE)];
+        stream.read_exact(&mut data)?;
+
+        Ok(Self {
+            timestamp,
+            pack_size,
+            data,
+        })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), RdpsndError> {
+        stream.write_u16::<LittleEndian>(self.timestamp)?;
+        stream.write_u16::<LittleEndian>(self.pack_size)?;
+        stream.write_all(&self.data)?;
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        TRAINING_PDU_SIZE + self.data.len()
+    }
+}
+
+/// Training Confirm PDU (SNDTRAININGCONFIRM)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrainingConfirmPdu {
+    pub timestamp: u16,
+    pub pack_size: u16,
+}
+
+impl PduParsing for TrainingConfirmPdu {
+    type Error = RdpsndError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let timestamp = stream.read_u16::<LittleEndian>()?;
+        let pack_size = stream.read_u16::<LittleEndian>()?;
+
+        Ok(Self { timestamp, pack_size })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        stream.write_u16::<LittleEndian>(self.timestamp)?;
+        stream.write_u16::<LittleEndian>(self.pack_size)?;
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        TRAINING_PDU_SIZE
+    }
+}
+
+/// Volume PDU (SNDVOL)
+///
+/// 0xFFFF is the maximum volume, and 0 is silence.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VolumePdu {
+    pub volume_left: u16,
+    pub volume_right: u16,
+}
+
+impl PduParsing for VolumePdu {
+    type Error = RdpsndError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let volume_left = stream.read_u16::<LittleEndian>()?;
+        let volume_right = stream.read_u16::<LittleEndian>()?;
+
+        Ok(Self {
+            volume_left,
+            volume_right,
+        })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        stream.write_u16::<LittleEndian>(self.volume_left)?;
+        stream.write_u16::<LittleEndian>(self.volume_right)?;
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        VOLUME_PDU_SIZE
+    }
+}
+
+/// Pitch PDU (SNDPITCH), the value is not used by the client
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PitchPdu {
+    pub pitch: u32,
+}
+
+impl PduParsing for PitchPdu {
+    type Error = RdpsndError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        Ok(Self {
+            pitch: stream.read_u32::<LittleEndian>()?,
+        })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        stream.write_u32::<LittleEndian>(self.pitch)?;
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        PITCH_PDU_SIZE
+    }
+}
+
+#[repr(u16)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+pub enum QualityMode {
+    /// The server picks the audio quality according to the available bandwidth
+    Dynamic = 0x0000,
+    Medium = 0x0001,
+    High = 0x0002,
+}
+
+/// Quality Mode PDU (AUDIO_FORMAT_QUALITY_MODE), sent by the client when both sides support version 6 or above
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QualityModePdu {
+    pub quality_mode: QualityMode,
+}
+
+impl PduParsing for QualityModePdu {
+    type Error = RdpsndError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let quality_mode =
+            QualityMode::from_u16(stream.read_u16::<LittleEndian>()?).ok_or(RdpsndError::InvalidQualityMode)?;
+        let _reserved = stream.read_u16::<LittleEndian>()?;
+
+        Ok(Self { quality_mode })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        stream.write_u16::<LittleEndian>(self.quality_mode as u16)?;
+        stream.write_u16::<LittleEndian>(0)?; // reserved
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        QUALITY_MODE_PDU_SIZE
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum RdpsndError {
+    #[error("IO error")]
+    IOError(#[from] io::Error),
+    #[error("Invalid RDPSND PDU type: {0:#04x}")]
+    InvalidPduType(u8),
+    #[error("Unexpected RDPSND PDU type: {0:#04x}")]
+    UnexpectedPduType(u8),
+    #[error("RDPSND PDU body is too large: {0} bytes")]
+    BodyTooLarge(usize),
+    #[error("Invalid quality mode")]
+    InvalidQualityMode,
+    #[error("Invalid Wave PDU size: expected {expected} bytes, got {actual}")]
+    InvalidWaveSize { expected: usize, actual: usize },
+}
+
+impl From<RdpsndError> for io::Error {
+    fn from(e: RdpsndError) -> io::Error {
+        io::Error::new(io::ErrorKind::Other, format!("RDPSND error: {e}"))
+    }
+}
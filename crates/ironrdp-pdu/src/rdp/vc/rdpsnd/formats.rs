@@ -0,0 +1,180 @@
+use std::io;
+
+use bitflags::bitflags;
+use byteorder::{BigEndian, LittleEndian, ReadBytesExt as _, WriteBytesExt as _};
+
+use super::RdpsndError;
+use crate::PduParsing;
+
+const AUDIO_FORMAT_PDU_SIZE: usize = 20;
+const AUDIO_FORMAT_SIZE: usize = 18;
+
+bitflags! {
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    pub struct AudioFormatFlags: u32 {
+        /// The client is able to consume audio data
+        const ALIVE = 0x0000_0001;
+        /// The client is able to apply a volume change to the audio data
+        const VOLUME = 0x0000_0002;
+        /// The client is able to apply a pitch change to the audio data
+        const PITCH = 0x0000_0004;
+    }
+}
+
+/// Version of the Audio Output Virtual Channel Extension
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct AudioVersion(pub u16);
+
+impl AudioVersion {
+    pub const V2: Self = Self(0x02);
+    pub const V5: Self = Self(0x05);
+    /// Adds the Quality Mode PDU
+    pub const V6: Self = Self(0x06);
+    /// Adds the Wave2 PDU
+    pub const V8: Self = Self(0x08);
+}
+
+/// Audio format tag ([RFC 2361])
+///
+/// [RFC 2361]: https://www.rfc-editor.org/rfc/rfc2361
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct WaveFormat(pub u16);
+
+impl WaveFormat {
+    pub const PCM: Self = Self(0x0001);
+    pub const ADPCM: Self = Self(0x0002);
+    pub const ALAW: Self = Self(0x0006);
+    pub const MULAW: Self = Self(0x0007);
+    /// IMA ADPCM
+    pub const DVI_ADPCM: Self = Self(0x0011);
+    pub const GSM610: Self = Self(0x0031);
+    pub const MPEGLAYER3: Self = Self(0x0055);
+    pub const WMAUDIO2: Self = Self(0x0161);
+    pub const AAC_MS: Self = Self(0xA106);
+}
+
+/// Audio format (AUDIO_FORMAT), equivalent to the WAVEFORMATEX structure
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AudioFormat {
+    pub format: WaveFormat,
+    pub n_channels: u16,
+    pub n_samples_per_sec: u32,
+    pub n_avg_bytes_per_sec: u32,
+    pub n_block_align: u16,
+    pub bits_per_sample: u16,
+    /// Extra information specific to the format
+    pub data: Vec<u8>,
+}
+
+impl PduParsing for AudioFormat {
+    type Error = RdpsndError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let format = WaveFormat(stream.read_u16::<LittleEndian>()?);
+        let n_channels = stream.read_u16::<LittleEndian>()?;
+        let n_samples_per_sec = stream.read_u32::<LittleEndian>()?;
+        let n_avg_bytes_per_sec = stream.read_u32::<LittleEndian>()?;
+        let n_block_align = stream.read_u16::<LittleEndian>()?;
+        let bits_per_sample = stream.read_u16::<LittleEndian>()?;
+        let data_size = stream.read_u16::<LittleEndian>()?;
+
+        let mut data = vec![0; usize::from(data_size)];
+        stream.read_exact(&mut data)?;
+
+        Ok(Self {
+            format,
+            n_channels,
+            n_samples_per_sec,
+            n_avg_bytes_per_sec,
+            n_block_align,
+            bits_per_sample,
+            data,
+        })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        let data_size = u16::try_from(self.data.len()).map_err(|_| RdpsndError::BodyTooLarge(self.data.len()))?;
+
+        stream.write_u16::<LittleEndian>(self.format.0)?;
+        stream.write_u16::<LittleEndian>(self.n_channels)?;
+        stream.write_u32::<LittleEndian>(self.n_samples_per_sec)?;
+        stream.write_u32::<LittleEndian>(self.n_avg_bytes_per_sec)?;
+        stream.write_u16::<LittleEndian>(self.n_block_align)?;
+        stream.write_u16::<LittleEndian>(self.bits_per_sample)?;
+        stream.write_u16::<LittleEndian>(data_size)?;
+        stream.write_all(&self.data)?;
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        AUDIO_FORMAT_SIZE + self.data.len()
+    }
+}
+
+/// Server Audio Formats and Version PDU (SERVER_AUDIO_VERSION_AND_FORMATS)
+/// and Client Audio Formats and Version PDU (CLIENT_AUDIO_VERSION_AND_FORMATS)
+///
+/// The flags, volume, pitch and datagram port are only meaningful in the client PDU,
+/// while the last confirmed block is only meaningful in the server PDU.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AudioFormatPdu {
+    pub flags: AudioFormatFlags,
+    pub volume: u32,
+    pub pitch: u32,
+    /// UDP port on which the client listens for audio data, or 0
+    pub dgram_port: u16,
+    pub last_block_confirmed: u8,
+    pub version: AudioVersion,
+    pub formats: Vec<AudioFormat>,
+}
+
+impl PduParsing for AudioFormatPdu {
+    type Error = RdpsndError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let flags = AudioFormatFlags::from_bits_truncate(stream.read_u32::<LittleEndian>()?);
+        let volume = stream.read_u32::<LittleEndian>()?;
+        let pitch = stream.read_u32::<LittleEndian>()?;
+        let dgram_port = stream.read_u16::<BigEndian>()?;
+        let formats_count = stream.read_u16::<LittleEndian>()?;
+        let last_block_confirmed = stream.read_u8()?;
+        let version = AudioVersion(stream.read_u16::<LittleEndian>()?);
+        let _pad = stream.read_u8()?;
+
+        let formats = (0..formats_count)
+            .map(|_| AudioFormat::from_buffer(&mut stream))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            flags,
+            volume,
+            pitch,
+            dgram_port,
+            last_block_confirmed,
+            version,
+            formats,
+        })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        stream.write_u32::<LittleEndian>(self.flags.bits())?;
+        stream.write_u32::<LittleEndian>(self.volume)?;
+        stream.write_u32::<LittleEndian>(self.pitch)?;
+        stream.write_u16::<BigEndian>(self.dgram_port)?;
+        stream.write_u16::<LittleEndian>(self.formats.len() as u16)?;
+        stream.write_u8(self.last_block_confirmed)?;
+        stream.write_u16::<LittleEndian>(self.version.0)?;
+        stream.write_u8(0)?; // bPad
+
+        for format in self.formats.iter() {
+            format.to_buffer(&mut stream)?;
+        }
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        AUDIO_FORMAT_PDU_SIZE + self.formats.iter().map(PduParsing::buffer_length).sum::<usize>()
+    }
+}
@@ -0,0 +1,180 @@
+use std::io;
+
+use byteorder::{LittleEndian, ReadBytesExt as _, WriteBytesExt as _};
+
+use super::RdpsndError;
+use crate::PduParsing;
+
+const WAVE_INFO_PDU_SIZE: usize = 12;
+const WAVE_INITIAL_DATA_SIZE: usize = 4;
+const WAVE2_PDU_SIZE: usize = 12;
+const WAVE_CONFIRM_PDU_SIZE: usize = 4;
+
+/// WaveInfo PDU (SNDWAVINFO)
+///
+/// The audio data is split between this PDU, which carries its first four bytes,
+/// and the following Wave PDU (SNDWAV), which is sent without header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WaveInfoPdu {
+    pub timestamp: u16,
+    /// Index of the format in the client formats list
+    pub format_no: u16,
+    pub block_no: u8,
+    pub initial_data: [u8; WAVE_INITIAL_DATA_SIZE],
+    /// Size of the whole audio data, and therefore of the following Wave PDU
+    pub data_size: usize,
+}
+
+impl WaveInfoPdu {
+    pub(super) fn decode(mut stream: impl io::Read, body_size: usize) -> Result<Self, RdpsndError> {
+        let timestamp = stream.read_u16::<LittleEndian>()?;
+        let format_no = stream.read_u16::<LittleEndian>()?;
+        let block_no = stream.read_u8()?;
+        let mut _pad = [0; 3];
+        stream.read_exact(&mut _pad)?;
+        let mut initial_data = [0; WAVE_INITIAL_DATA_SIZE];
+        stream.read_exact(&mut initial_data)?;
+
+        // BodySize covers this PDU body, without the initial data, and the whole Wave PDU
+        let data_size = body_size
+            .checked_sub(WAVE_INFO_PDU_SIZE - WAVE_INITIAL_DATA_SIZE)
+            .filter(|size| *size >= WAVE_INITIAL_DATA_SIZE)
+            .ok_or(RdpsndError::InvalidWaveSize {
+                expected: WAVE_INFO_PDU_SIZE,
+                actual: body_size,
+            })?;
+
+        Ok(Self {
+            timestamp,
+            format_no,
+            block_no,
+            initial_data,
+            data_size,
+        })
+    }
+
+    pub(super) fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), RdpsndError> {
+        stream.write_u16::<LittleEndian>(self.timestamp)?;
+        stream.write_u16::<LittleEndian>(self.format_no)?;
+        stream.write_u8(self.block_no)?;
+        stream.write_all(&[0; 3])?; // bPad
+        stream.write_all(&self.initial_data)?;
+
+        Ok(())
+    }
+
+    pub(super) fn buffer_length(&self) -> usize {
+        WAVE_INFO_PDU_SIZE
+    }
+
+    /// Value of the BodySize header field, which also covers the Wave PDU
+    pub(super) fn body_size(&self) -> usize {
+        WAVE_INFO_PDU_SIZE - WAVE_INITIAL_DATA_SIZE + self.data_size
+    }
+
+    /// Reassembles the audio data from this PDU and the following Wave PDU
+    pub fn audio_data(&self, wave_pdu: &[u8]) -> Result<Vec<u8>, RdpsndError> {
+        if wave_pdu.len() != self.data_size {
+            return Err(RdpsndError::InvalidWaveSize {
+                expected: self.data_size,
+                actual: wave_pdu.len(),
+            });
+        }
+
+        let mut data = Vec::with_capacity(self.data_size);
+        data.extend_from_slice(&self.initial_data);
+        data.extend_from_slice(&wave_pdu[WAVE_INITIAL_DATA_SIZE..]);
+
+        Ok(data)
+    }
+}
+
+/// Wave2 PDU (SNDWAVE2), carrying the audio data along with its capture timestamp
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Wave2Pdu {
+    pub timestamp: u16,
+    /// Index of the format in the client formats list
+    pub format_no: u16,
+    pub block_no: u8,
+    /// Capture time of the audio data, in milliseconds
+    pub audio_timestamp: u32,
+    pub data: Vec<u8>,
+}
+
+impl Wave2Pdu {
+    pub(super) fn decode(mut stream: impl io::Read, body_size: usize) -> Result<Self, RdpsndError> {
+        let timestamp = stream.read_u16::<LittleEndian>()?;
+        let format_no = stream.read_u16::<LittleEndian>()?;
+        let block_no = stream.read_u8()?;
+        let mut _pad = [0; 3];
+        stream.read_exact(&mut _pad)?;
+        let audio_timestamp = stream.read_u32::<LittleEndian>()?;
+
+        let data_size = body_size
+            .checked_sub(WAVE2_PDU_SIZE)
+            .ok_or(RdpsndError::InvalidWaveSize {
+                expected: WAVE2_PDU_SIZE,
+                actual: body_size,
+            })?;
+        let mut data = vec![0; data_size];
+        stream.read_exact(&mut data)?;
+
+        Ok(Self {
+            timestamp,
+            format_no,
+            block_no,
+            audio_timestamp,
+            data,
+        })
+    }
+
+    pub(super) fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), RdpsndError> {
+        stream.write_u16::<LittleEndian>(self.timestamp)?;
+        stream.write_u16::<LittleEndian>(self.format_no)?;
+        stream.write_u8(self.block_no)?;
+        stream.write_all(&[0; 3])?; // bPad
+        stream.write_u32::<LittleEndian>(self.audio_timestamp)?;
+        stream.write_all(&self.data)?;
+
+        Ok(())
+    }
+
+    pub(super) fn buffer_length(&self) -> usize {
+        WAVE2_PDU_SIZE + self.data.len()
+    }
+}
+
+/// Wave Confirm PDU (SNDWAV_CONFIRM), sent by the client once the audio data is played
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WaveConfirmPdu {
+    /// Timestamp of the played WaveInfo or Wave2 PDU, shifted by the time elapsed since its reception
+    pub timestamp: u16,
+    pub confirmed_block_no: u8,
+}
+
+impl PduParsing for WaveConfirmPdu {
+    type Error = RdpsndError;
+
+    fn from_buffer(mut stream: impl io::Read) -> Result<Self, Self::Error> {
+        let timestamp = stream.read_u16::<LittleEndian>()?;
+        let confirmed_block_no = stream.read_u8()?;
+        let _pad = stream.read_u8()?;
+
+        Ok(Self {
+            timestamp,
+            confirmed_block_no,
+        })
+    }
+
+    fn to_buffer(&self, mut stream: impl io::Write) -> Result<(), Self::Error> {
+        stream.write_u16::<LittleEndian>(self.timestamp)?;
+        stream.write_u8(self.confirmed_block_no)?;
+        stream.write_u8(0)?; // bPad
+
+        Ok(())
+    }
+
+    fn buffer_length(&self) -> usize {
+        WAVE_CONFIRM_PDU_SIZE
+    }
+}
@@ -0,0 +1,142 @@
+use ironrdp_pdu::rdp::vc::cliprdr::*;
+use ironrdp_pdu::PduParsing;
+use ironrdp_pdu_samples::cliprdr::*;
+
+fn assert_round_trip(pdu: &ClipboardPdu, buffer: &[u8]) {
+    assert_eq!(*pdu, ClipboardPdu::from_buffer(buffer).unwrap());
+
+    let mut encoded = Vec::with_capacity(buffer.len());
+    pdu.to_buffer(&mut encoded).unwrap();
+    assert_eq!(encoded, buffer);
+
+    assert_eq!(buffer.len(), pdu.buffer_length());
+}
+
+#[test]
+fn monitor_ready_pdu_round_trip() {
+    assert_round_trip(&ClipboardPdu::MonitorReady, MONITOR_READY_BUFFER.as_ref());
+}
+
+#[test]
+fn capabilities_pdu_round_trip() {
+    assert_round_trip(&CAPABILITIES, CAPABILITIES_BUFFER.as_ref());
+}
+
+#[test]
+fn format_list_pdu_round_trip() {
+    assert_round_trip(&FORMAT_LIST, FORMAT_LIST_BUFFER.as_ref());
+}
+
+#[test]
+fn format_list_response_pdu_round_trip() {
+    assert_round_trip(&FORMAT_LIST_RESPONSE, FORMAT_LIST_RESPONSE_BUFFER.as_ref());
+}
+
+#[test]
+fn format_data_request_pdu_round_trip() {
+    assert_round_trip(&FORMAT_DATA_REQUEST, FORMAT_DATA_REQUEST_BUFFER.as_ref());
+}
+
+#[test]
+fn format_data_response_pdu_round_trip() {
+    assert_round_trip(&FORMAT_DATA_RESPONSE, FORMAT_DATA_RESPONSE_BUFFER.as_ref());
+}
+
+#[test]
+fn file_contents_request_pdu_round_trip() {
+    assert_round_trip(&FILE_CONTENTS_REQUEST, FILE_CONTENTS_REQUEST_BUFFER.as_ref());
+}
+
+#[test]
+fn file_contents_response_pdu_round_trip() {
+    assert_round_trip(&FILE_CONTENTS_RESPONSE, FILE_CONTENTS_RESPONSE_BUFFER.as_ref());
+}
+
+#[test]
+fn lock_data_pdu_round_trip() {
+    assert_round_trip(&LOCK_DATA, LOCK_DATA_BUFFER.as_ref());
+}
+
+#[test]
+fn format_list_with_long_format_names_is_decoded() {
+    let ClipboardPdu::FormatList(format_list) = &*FORMAT_LIST else {
+        unreachable!()
+    };
+
+    assert_eq!(*FORMATS, format_list.get_formats(true).unwrap());
+}
+
+#[test]
+fn format_list_with_short_format_names_is_decoded() {
+    let format_list = FormatList::new(&FORMATS, false);
+
+    assert_eq!(format_list.encoded_formats.len(), 72);
+    assert_eq!(*FORMATS, format_list.get_formats(false).unwrap());
+}
+
+#[test]
+fn format_list_with_short_ascii_format_names_is_decoded() {
+    let mut encoded_formats = vec![0xbc, 0xc0, 0x00, 0x00];
+    encoded_formats.extend_from_slice(b"HTML Format");
+    encoded_formats.resize(36, 0);
+
+    let format_list = FormatList {
+        use_ascii: true,
+        encoded_formats,
+    };
+
+    assert_eq!(FORMATS[1..], format_list.get_formats(false).unwrap());
+}
+
+#[test]
+fn format_data_response_text_helpers_round_trip() {
+    let ClipboardPdu::FormatDataResponse(response) = &*FORMAT_DATA_RESPONSE else {
+        unreachable!()
+    };
+    assert_eq!("hi", response.to_unicode_string());
+
+    assert_eq!("hi", FormatDataResponse::new_ansi_string("hi").to_ansi_string());
+}
+
+#[test]
+fn format_data_response_html_helpers_round_trip() {
+    let fragment = "<b>Hello, world!</b>";
+
+    let response = FormatDataResponse::new_html(fragment);
+
+    assert_eq!(fragment, response.to_html().unwrap());
+}
+
+#[test]
+fn format_data_response_file_list_helpers_round_trip() {
+    let file_list = PackedFileList {
+        files: vec![
+            FileDescriptor {
+                attributes: Some(FileAttributes::DIRECTORY),
+                last_write_time: None,
+                file_size: None,
+                name: "folder".to_owned(),
+            },
+            FileDescriptor {
+                attributes: Some(FileAttributes::NORMAL),
+                last_write_time: Some(0x01D9_0000_0000_0000),
+                file_size: Some(0x0000_0001_0000_0002),
+                name: "folder\\file.txt".to_owned(),
+            },
+        ],
+    };
+
+    let response = FormatDataResponse::new_file_list(&file_list).unwrap();
+
+    assert_eq!(4 + 2 * 592, response.data.len());
+    assert_eq!(file_list, response.to_file_list().unwrap());
+}
+
+#[test]
+fn file_contents_response_size_is_decoded() {
+    let ClipboardPdu::FileContentsResponse(response) = &*FILE_CONTENTS_RESPONSE else {
+        unreachable!()
+    };
+
+    assert_eq!(0x0001_0000, response.data_as_size().unwrap());
+}
@@ -1,6 +1,7 @@
 use ironrdp_pdu::input::fast_path::{FastPathInput, FastPathInputEvent};
 use ironrdp_pdu::input::mouse::PointerFlags;
-use ironrdp_pdu::input::MousePdu;
+use ironrdp_pdu::input::mouse_rel::PointerRelFlags;
+use ironrdp_pdu::input::{MousePdu, MouseRelPdu};
 use ironrdp_pdu::PduParsing;
 
 const FASTPATH_INPUT_MESSAGE: [u8; 44] = [
@@ -65,3 +66,39 @@ fn to_buffer_correctly_serializes_fastpath_input_message() {
 
     assert_eq!(buffer, FASTPATH_INPUT_MESSAGE.as_ref());
 }
+
+#[test]
+fn negative_wheel_rotation_is_sign_extended() {
+    let event = FastPathInputEvent::MouseEvent(MousePdu {
+        flags: PointerFlags::VERTICAL_WHEEL,
+        number_of_wheel_rotation_units: -1,
+        x_position: 26,
+        y_position: 1062,
+    });
+    let encoded = [0x20, 0xff, 0x03, 0x1a, 0x00, 0x26, 0x04];
+
+    let mut buffer = Vec::new();
+    event.to_buffer(&mut buffer).unwrap();
+    assert_eq!(buffer, encoded);
+
+    let decoded = FastPathInputEvent::from_buffer(encoded.as_ref()).unwrap();
+    assert_eq!(decoded, event);
+}
+
+#[test]
+fn relative_mouse_event() {
+    let event = FastPathInputEvent::MouseEventRel(MouseRelPdu {
+        flags: PointerRelFlags::MOVE,
+        x_delta: -5,
+        y_delta: 3,
+    });
+    let encoded = [0xa0, 0x00, 0x08, 0xfb, 0xff, 0x03, 0x00];
+
+    let mut buffer = Vec::new();
+    event.to_buffer(&mut buffer).unwrap();
+    assert_eq!(buffer, encoded);
+    assert_eq!(event.buffer_length(), encoded.len());
+
+    let decoded = FastPathInputEvent::from_buffer(encoded.as_ref()).unwrap();
+    assert_eq!(decoded, event);
+}
@@ -56,6 +56,16 @@ fn from_buffer_correctly_parses_rdp_pdu_client_synchronize() {
     );
 }
 
+#[test]
+fn from_buffer_correctly_parses_rdp_pdu_server_compressed_save_session_info() {
+    let buf = SERVER_COMPRESSED_SAVE_SESSION_INFO_BUFFER.as_ref();
+
+    assert_eq!(
+        SERVER_COMPRESSED_SAVE_SESSION_INFO.clone(),
+        ShareControlHeader::from_buffer(buf).unwrap()
+    );
+}
+
 #[test]
 fn from_buffer_correctly_parses_rdp_pdu_client_control_cooperate() {
     let buf = CONTROL_COOPERATE_BUFFER.as_ref();
@@ -107,6 +117,16 @@ fn from_buffer_correctly_parses_rdp_pdu_server_monitor_layout() {
     );
 }
 
+#[test]
+fn from_buffer_correctly_parses_rdp_pdu_server_deactivate_all() {
+    let buf = SERVER_DEACTIVATE_ALL_BUFFER.as_ref();
+
+    assert_eq!(
+        SERVER_DEACTIVATE_ALL.clone(),
+        ShareControlHeader::from_buffer(buf).unwrap()
+    );
+}
+
 #[test]
 fn to_buffer_correctly_serializes_rdp_pdu_client_info() {
     let mut buf = Vec::new();
@@ -147,6 +167,17 @@ fn to_buffer_correctly_serializes_rdp_pdu_client_synchronize() {
     assert_eq!(expected_buf, buf);
 }
 
+#[test]
+fn to_buffer_correctly_serializes_rdp_pdu_server_compressed_save_session_info() {
+    let pdu = SERVER_COMPRESSED_SAVE_SESSION_INFO.clone();
+    let expected_buf = SERVER_COMPRESSED_SAVE_SESSION_INFO_BUFFER.to_vec();
+
+    let mut buf = Vec::new();
+    pdu.to_buffer(&mut buf).unwrap();
+
+    assert_eq!(expected_buf, buf);
+}
+
 #[test]
 fn to_buffer_correctly_serializes_rdp_pdu_client_control_cooperate() {
     let pdu = CONTROL_COOPERATE.clone();
@@ -213,6 +244,17 @@ fn to_buffer_correctly_serializes_rdp_pdu_server_monitor_layout() {
     assert_eq!(expected_buf, buf);
 }
 
+#[test]
+fn to_buffer_correctly_serializes_rdp_pdu_server_deactivate_all() {
+    let pdu = SERVER_DEACTIVATE_ALL.clone();
+    let expected_buf = SERVER_DEACTIVATE_ALL_BUFFER.to_vec();
+
+    let mut buf = Vec::new();
+    pdu.to_buffer(&mut buf).unwrap();
+
+    assert_eq!(expected_buf, buf);
+}
+
 #[test]
 fn buffer_length_is_correct_for_rdp_pdu_client_info() {
     let pdu = CLIENT_INFO_PDU.clone();
@@ -320,6 +362,16 @@ fn buffer_length_is_correct_for_rdp_pdu_server_monitor_layout() {
     assert_eq!(expected_buf_len, len);
 }
 
+#[test]
+fn buffer_length_is_correct_for_rdp_pdu_server_deactivate_all() {
+    let pdu = SERVER_DEACTIVATE_ALL.clone();
+    let expected_buf_len = SERVER_DEACTIVATE_ALL_BUFFER.len();
+
+    let len = pdu.buffer_length();
+
+    assert_eq!(expected_buf_len, len);
+}
+
 #[test]
 fn from_buffer_correct_parses_client_info_pdu_ansi() {
     assert_eq!(
@@ -407,6 +459,35 @@ fn buffer_length_is_correct_for_client_info_pdu_unicode_without_optional_fields(
     assert_eq!(expected_buffer_len, len);
 }
 
+#[test]
+fn from_buffer_correct_parses_client_info_pdu_unicode_with_reconnect_cookie() {
+    assert_eq!(
+        CLIENT_INFO_UNICODE_WITH_RECONNECT_COOKIE.clone(),
+        ClientInfo::from_buffer(CLIENT_INFO_BUFFER_UNICODE_WITH_RECONNECT_COOKIE.as_slice()).unwrap()
+    );
+}
+
+#[test]
+fn to_buffer_correct_serializes_client_info_pdu_unicode_with_reconnect_cookie() {
+    let data = CLIENT_INFO_UNICODE_WITH_RECONNECT_COOKIE.clone();
+    let expected_buffer = CLIENT_INFO_BUFFER_UNICODE_WITH_RECONNECT_COOKIE.to_vec();
+
+    let mut buffer = Vec::new();
+    data.to_buffer(&mut buffer).unwrap();
+
+    assert_eq!(expected_buffer, buffer);
+}
+
+#[test]
+fn buffer_length_is_correct_for_client_info_pdu_unicode_with_reconnect_cookie() {
+    let data = CLIENT_INFO_UNICODE_WITH_RECONNECT_COOKIE.clone();
+    let expected_buffer_len = CLIENT_INFO_BUFFER_UNICODE_WITH_RECONNECT_COOKIE.len();
+
+    let len = data.buffer_length();
+
+    assert_eq!(expected_buffer_len, len);
+}
+
 #[test]
 fn from_buffer_correctly_parses_server_demand_active() {
     let buffer = SERVER_DEMAND_ACTIVE_BUFFER.as_ref();
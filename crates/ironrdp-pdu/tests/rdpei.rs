@@ -0,0 +1,173 @@
+use ironrdp_pdu::rdp::vc::dvc::rdpei::*;
+use ironrdp_pdu::PduParsing;
+
+const SC_READY_V100_BUFFER: [u8; 10] = [0x01, 0x00, 0x0a, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00];
+
+const SC_READY_V300_BUFFER: [u8; 14] = [
+    0x01, 0x00, 0x0e, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03, 0x00, 0x01, 0x00, 0x00, 0x00,
+];
+
+const SUSPEND_INPUT_BUFFER: [u8; 6] = [0x04, 0x00, 0x06, 0x00, 0x00, 0x00];
+
+const CS_READY_BUFFER: [u8; 16] = [
+    0x02, 0x00, 0x10, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x00, 0x0a, 0x00,
+];
+
+const TOUCH_EVENT_BUFFER: [u8; 19] = [
+    0x03, 0x00, 0x13, 0x00, 0x00, 0x00, 0x00, 0x01, 0x01, 0x00, 0x00, 0x04, 0x40, 0x64, 0x60, 0x32, 0x19, 0x42, 0x00,
+];
+
+const PEN_EVENT_BUFFER: [u8; 19] = [
+    0x08, 0x00, 0x13, 0x00, 0x00, 0x00, 0x00, 0x01, 0x01, 0x00, 0x00, 0x0b, 0x0a, 0x14, 0x1a, 0x01, 0x44, 0x00, 0x6d,
+];
+
+const DISMISS_HOVERING_CONTACT_BUFFER: [u8; 7] = [0x06, 0x00, 0x07, 0x00, 0x00, 0x00, 0x05];
+
+lazy_static::lazy_static! {
+    static ref SC_READY_V100: ServerPdu = ServerPdu::ScReady(ScReadyPdu {
+        protocol_version: PROTOCOL_V100,
+        supported_features: None,
+    });
+    static ref SC_READY_V300: ServerPdu = ServerPdu::ScReady(ScReadyPdu {
+        protocol_version: PROTOCOL_V300,
+        supported_features: Some(ScReadyFeatures::MULTIPEN_INJECTION_SUPPORTED),
+    });
+    static ref CS_READY: ClientPdu = ClientPdu::CsReady(CsReadyPdu {
+        flags: CsReadyFlags::SHOW_TOUCH_VISUALS | CsReadyFlags::DISABLE_TIMESTAMP_INJECTION,
+        protocol_version: PROTOCOL_V200,
+        max_touch_contacts: 10,
+    });
+    static ref TOUCH_EVENT: ClientPdu = ClientPdu::Touch(TouchEventPdu {
+        encode_time: 0,
+        frames: vec![TouchFrame {
+            frame_offset: 0,
+            contacts: vec![TouchContact {
+                contact_id: 0,
+                x: 100,
+                y: -50,
+                flags: ContactFlags::DOWN | ContactFlags::IN_RANGE | ContactFlags::IN_CONTACT,
+                contact_rect: None,
+                orientation: None,
+                pressure: Some(512),
+            }],
+        }],
+    });
+    static ref PEN_EVENT: ClientPdu = ClientPdu::Pen(PenEventPdu {
+        encode_time: 0,
+        frames: vec![PenFrame {
+            frame_offset: 0,
+            contacts: vec![PenContact {
+                device_id: 0,
+                x: 10,
+                y: 20,
+                flags: ContactFlags::UPDATE | ContactFlags::IN_RANGE | ContactFlags::IN_CONTACT,
+                pen_flags: Some(PenFlags::BARREL_PRESSED),
+                pressure: Some(1024),
+                rotation: None,
+                tilt_x: Some(-45),
+                tilt_y: None,
+            }],
+        }],
+    });
+}
+
+#[test]
+fn from_buffer_correctly_parses_sc_ready() {
+    let mut buffer = SC_READY_V100_BUFFER.as_ref();
+    assert_eq!(*SC_READY_V100, ServerPdu::from_buffer(&mut buffer).unwrap());
+    assert!(buffer.is_empty());
+
+    let mut buffer = SC_READY_V300_BUFFER.as_ref();
+    assert_eq!(*SC_READY_V300, ServerPdu::from_buffer(&mut buffer).unwrap());
+    assert!(buffer.is_empty());
+}
+
+#[test]
+fn to_buffer_correctly_serializes_sc_ready() {
+    let mut buffer = Vec::new();
+    SC_READY_V300.to_buffer(&mut buffer).unwrap();
+
+    assert_eq!(buffer, SC_READY_V300_BUFFER.as_ref());
+    assert_eq!(SC_READY_V300_BUFFER.len(), SC_READY_V300.buffer_length());
+}
+
+#[test]
+fn suspend_input_roundtrip() {
+    let mut buffer = SUSPEND_INPUT_BUFFER.as_ref();
+    assert_eq!(ServerPdu::SuspendInput, ServerPdu::from_buffer(&mut buffer).unwrap());
+
+    let mut buffer = Vec::new();
+    ServerPdu::SuspendInput.to_buffer(&mut buffer).unwrap();
+    assert_eq!(buffer, SUSPEND_INPUT_BUFFER.as_ref());
+}
+
+#[test]
+fn from_buffer_fails_on_invalid_pdu_length() {
+    let mut buffer = SC_READY_V100_BUFFER;
+    buffer[2] = 0x0e;
+
+    assert!(matches!(
+        ServerPdu::from_buffer(buffer.as_ref()),
+        Err(RdpeiError::InvalidPduLength {
+            expected: 14,
+            actual: 10
+        })
+    ));
+}
+
+#[test]
+fn from_buffer_correctly_parses_client_pdus() {
+    for (buffer, expected) in [
+        (CS_READY_BUFFER.as_ref(), &*CS_READY),
+        (TOUCH_EVENT_BUFFER.as_ref(), &*TOUCH_EVENT),
+        (PEN_EVENT_BUFFER.as_ref(), &*PEN_EVENT),
+        (
+            DISMISS_HOVERING_CONTACT_BUFFER.as_ref(),
+            &ClientPdu::DismissHoveringContact(5),
+        ),
+    ] {
+        let mut buffer = buffer;
+        assert_eq!(*expected, ClientPdu::from_buffer(&mut buffer).unwrap());
+        assert!(buffer.is_empty());
+    }
+}
+
+#[test]
+fn to_buffer_correctly_serializes_client_pdus() {
+    for (expected, pdu) in [
+        (CS_READY_BUFFER.as_ref(), &*CS_READY),
+        (TOUCH_EVENT_BUFFER.as_ref(), &*TOUCH_EVENT),
+        (PEN_EVENT_BUFFER.as_ref(), &*PEN_EVENT),
+        (
+            DISMISS_HOVERING_CONTACT_BUFFER.as_ref(),
+            &ClientPdu::DismissHoveringContact(5),
+        ),
+    ] {
+        let mut buffer = Vec::new();
+        pdu.to_buffer(&mut buffer).unwrap();
+
+        assert_eq!(buffer, expected);
+        assert_eq!(expected.len(), pdu.buffer_length());
+    }
+}
+
+#[test]
+fn to_buffer_fails_on_out_of_range_coordinates() {
+    let pdu = ClientPdu::Touch(TouchEventPdu {
+        encode_time: 0,
+        frames: vec![TouchFrame {
+            frame_offset: 0,
+            contacts: vec![TouchContact {
+                contact_id: 0,
+                x: FOUR_BYTE_SIGNED_MAX + 1,
+                y: 0,
+                flags: ContactFlags::DOWN | ContactFlags::IN_RANGE | ContactFlags::IN_CONTACT,
+                contact_rect: None,
+                orientation: None,
+                pressure: None,
+            }],
+        }],
+    });
+
+    assert!(matches!(pdu.to_buffer(Vec::new()), Err(RdpeiError::ValueOutOfRange(_))));
+}
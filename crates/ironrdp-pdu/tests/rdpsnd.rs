@@ -0,0 +1,94 @@
+use ironrdp_pdu::rdp::vc::rdpsnd::*;
+use ironrdp_pdu::PduParsing;
+use ironrdp_pdu_samples::rdpsnd::*;
+
+fn assert_server_round_trip(pdu: &ServerAudioOutputPdu, buffer: &[u8]) {
+    assert_eq!(*pdu, ServerAudioOutputPdu::from_buffer(buffer).unwrap());
+
+    let mut encoded = Vec::with_capacity(buffer.len());
+    pdu.to_buffer(&mut encoded).unwrap();
+    assert_eq!(encoded, buffer);
+
+    assert_eq!(buffer.len(), pdu.buffer_length());
+}
+
+fn assert_client_round_trip(pdu: &ClientAudioOutputPdu, buffer: &[u8]) {
+    assert_eq!(*pdu, ClientAudioOutputPdu::from_buffer(buffer).unwrap());
+
+    let mut encoded = Vec::with_capacity(buffer.len());
+    pdu.to_buffer(&mut encoded).unwrap();
+    assert_eq!(encoded, buffer);
+
+    assert_eq!(buffer.len(), pdu.buffer_length());
+}
+
+#[test]
+fn server_formats_pdu_round_trip() {
+    assert_server_round_trip(&SERVER_FORMATS, SERVER_FORMATS_BUFFER.as_ref());
+}
+
+#[test]
+fn client_formats_pdu_round_trip() {
+    assert_client_round_trip(&CLIENT_FORMATS, CLIENT_FORMATS_BUFFER.as_ref());
+}
+
+#[test]
+fn training_pdu_round_trip() {
+    assert_server_round_trip(&TRAINING, TRAINING_BUFFER.as_ref());
+}
+
+#[test]
+fn training_confirm_pdu_round_trip() {
+    assert_client_round_trip(&TRAINING_CONFIRM, TRAINING_CONFIRM_BUFFER.as_ref());
+}
+
+#[test]
+fn wave_info_pdu_round_trip() {
+    assert_server_round_trip(&WAVE_INFO, WAVE_INFO_BUFFER.as_ref());
+}
+
+#[test]
+fn wave2_pdu_round_trip() {
+    assert_server_round_trip(&WAVE2, WAVE2_BUFFER.as_ref());
+}
+
+#[test]
+fn wave_confirm_pdu_round_trip() {
+    assert_client_round_trip(&WAVE_CONFIRM, WAVE_CONFIRM_BUFFER.as_ref());
+}
+
+#[test]
+fn volume_pdu_round_trip() {
+    assert_server_round_trip(&VOLUME, VOLUME_BUFFER.as_ref());
+}
+
+#[test]
+fn quality_mode_pdu_round_trip() {
+    assert_client_round_trip(&QUALITY_MODE, QUALITY_MODE_BUFFER.as_ref());
+}
+
+#[test]
+fn close_pdu_round_trip() {
+    assert_server_round_trip(&ServerAudioOutputPdu::Close, CLOSE_BUFFER.as_ref());
+}
+
+#[test]
+fn wave_info_audio_data_is_reassembled_with_wave_pdu() {
+    let ServerAudioOutputPdu::WaveInfo(wave_info) = &*WAVE_INFO else {
+        unreachable!()
+    };
+
+    assert_eq!(
+        vec![0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08],
+        wave_info.audio_data(WAVE_BUFFER.as_ref()).unwrap()
+    );
+    assert!(wave_info.audio_data(&WAVE_BUFFER[..6]).is_err());
+}
+
+#[test]
+fn wave_info_pdu_with_too_small_body_size_is_rejected() {
+    let mut buffer = WAVE_INFO_BUFFER;
+    buffer[2] = 0x0a;
+
+    assert!(ServerAudioOutputPdu::from_buffer(buffer.as_ref()).is_err());
+}
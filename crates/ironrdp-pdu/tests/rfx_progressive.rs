@@ -0,0 +1,302 @@
+use ironrdp_pdu::codecs::rfx::{Quant, RfxRectangle};
+use ironrdp_pdu::codecs::rfx_progressive::*;
+use ironrdp_pdu::PduBufferParsing;
+
+const SYNC_BLOCK_BUFFER: [u8; 12] = [
+    0xc0, 0xcc, // RFX_PROGRESSIVE_SYNC::blockType = PROGRESSIVE_WBT_SYNC
+    0x0c, 0x00, 0x00, 0x00, // RFX_PROGRESSIVE_SYNC::blockLen = 12
+    0xca, 0xac, 0xcc, 0xca, // RFX_PROGRESSIVE_SYNC::magic
+    0x00, 0x01, // RFX_PROGRESSIVE_SYNC::version = 0x0100
+];
+
+const SYNC_BLOCK_BUFFER_WITH_INVALID_MAGIC: [u8; 12] = [
+    0xc0, 0xcc, // RFX_PROGRESSIVE_SYNC::blockType = PROGRESSIVE_WBT_SYNC
+    0x0c, 0x00, 0x00, 0x00, // RFX_PROGRESSIVE_SYNC::blockLen = 12
+    0xca, 0xac, 0xcc, 0xcb, // RFX_PROGRESSIVE_SYNC::magic
+    0x00, 0x01, // RFX_PROGRESSIVE_SYNC::version = 0x0100
+];
+
+const CONTEXT_BLOCK_BUFFER: [u8; 10] = [
+    0xc3, 0xcc, // RFX_PROGRESSIVE_CONTEXT::blockType = PROGRESSIVE_WBT_CONTEXT
+    0x0a, 0x00, 0x00, 0x00, // RFX_PROGRESSIVE_CONTEXT::blockLen = 10
+    0x00, // RFX_PROGRESSIVE_CONTEXT::ctxId = 0
+    0x40, 0x00, // RFX_PROGRESSIVE_CONTEXT::tileSize = 64
+    0x01, // RFX_PROGRESSIVE_CONTEXT::flags = RFX_SUBBAND_DIFFING
+];
+
+const CONTEXT_BLOCK_BUFFER_WITH_INVALID_TILE_SIZE: [u8; 10] = [
+    0xc3, 0xcc, // RFX_PROGRESSIVE_CONTEXT::blockType = PROGRESSIVE_WBT_CONTEXT
+    0x0a, 0x00, 0x00, 0x00, // RFX_PROGRESSIVE_CONTEXT::blockLen = 10
+    0x00, // RFX_PROGRESSIVE_CONTEXT::ctxId = 0
+    0x20, 0x00, // RFX_PROGRESSIVE_CONTEXT::tileSize = 32
+    0x00, // RFX_PROGRESSIVE_CONTEXT::flags
+];
+
+const FRAME_BEGIN_BLOCK_BUFFER: [u8; 12] = [
+    0xc1, 0xcc, // RFX_PROGRESSIVE_FRAME_BEGIN::blockType = PROGRESSIVE_WBT_FRAME_BEGIN
+    0x0c, 0x00, 0x00, 0x00, // RFX_PROGRESSIVE_FRAME_BEGIN::blockLen = 12
+    0x05, 0x00, 0x00, 0x00, // RFX_PROGRESSIVE_FRAME_BEGIN::frameIndex = 5
+    0x01, 0x00, // RFX_PROGRESSIVE_FRAME_BEGIN::regionCount = 1
+];
+
+const FRAME_END_BLOCK_BUFFER: [u8; 6] = [
+    0xc2, 0xcc, // RFX_PROGRESSIVE_FRAME_END::blockType = PROGRESSIVE_WBT_FRAME_END
+    0x06, 0x00, 0x00, 0x00, // RFX_PROGRESSIVE_FRAME_END::blockLen = 6
+];
+
+const REGION_BLOCK_BUFFER: [u8; 102] = [
+    0xc4, 0xcc, // RFX_PROGRESSIVE_REGION::blockType = PROGRESSIVE_WBT_REGION
+    0x66, 0x00, 0x00, 0x00, // RFX_PROGRESSIVE_REGION::blockLen = 102
+    0x40, // RFX_PROGRESSIVE_REGION::tileSize = 64
+    0x01, 0x00, // RFX_PROGRESSIVE_REGION::numRects = 1
+    0x01, // RFX_PROGRESSIVE_REGION::numQuant = 1
+    0x01, // RFX_PROGRESSIVE_REGION::numProgQuant = 1
+    0x01, // RFX_PROGRESSIVE_REGION::flags = RFX_DWT_REDUCE_EXTRAPOLATE
+    0x02, 0x00, // RFX_PROGRESSIVE_REGION::numTiles = 2
+    0x37, 0x00, 0x00, 0x00, // RFX_PROGRESSIVE_REGION::tileDataSize = 55
+    0x40, 0x00, 0x80, 0x00, 0x40, 0x00, 0x20, 0x00, // RFX_PROGRESSIVE_REGION::rects
+    0x66, 0x66, 0x77, 0x88, 0x98, // RFX_PROGRESSIVE_REGION::quantVals
+    0x00, // RFX_PROGRESSIVE_REGION::quantProgVals::quality = 0
+    0x11, 0x11, 0x11, 0x11, 0x11, // RFX_PROGRESSIVE_REGION::quantProgVals::yQuantValues
+    0x22, 0x22, 0x22, 0x22, 0x22, // RFX_PROGRESSIVE_REGION::quantProgVals::cbQuantValues
+    0x21, 0x43, 0x65, 0x87, 0xa9, // RFX_PROGRESSIVE_REGION::quantProgVals::crQuantValues
+    0xc5, 0xcc, // RFX_PROGRESSIVE_TILE_SIMPLE::blockType = PROGRESSIVE_WBT_TILE_SIMPLE
+    0x1a, 0x00, 0x00, 0x00, // RFX_PROGRESSIVE_TILE_SIMPLE::blockLen = 26
+    0x00, 0x00, 0x00, // RFX_PROGRESSIVE_TILE_SIMPLE::quantIdxY/Cb/Cr = 0
+    0x01, 0x00, // RFX_PROGRESSIVE_TILE_SIMPLE::xIdx = 1
+    0x02, 0x00, // RFX_PROGRESSIVE_TILE_SIMPLE::yIdx = 2
+    0x01, // RFX_PROGRESSIVE_TILE_SIMPLE::flags = RFX_TILE_DIFFERENCE
+    0x02, 0x00, // RFX_PROGRESSIVE_TILE_SIMPLE::yLen = 2
+    0x01, 0x00, // RFX_PROGRESSIVE_TILE_SIMPLE::cbLen = 1
+    0x01, 0x00, // RFX_PROGRESSIVE_TILE_SIMPLE::crLen = 1
+    0x00, 0x00, // RFX_PROGRESSIVE_TILE_SIMPLE::tailLen = 0
+    0xaa, 0xbb, 0xcc, 0xdd, // RFX_PROGRESSIVE_TILE_SIMPLE::yData, cbData, crData
+    0xc7, 0xcc, // RFX_PROGRESSIVE_TILE_UPGRADE::blockType = PROGRESSIVE_WBT_TILE_UPGRADE
+    0x1d, 0x00, 0x00, 0x00, // RFX_PROGRESSIVE_TILE_UPGRADE::blockLen = 29
+    0x00, 0x00, 0x00, // RFX_PROGRESSIVE_TILE_UPGRADE::quantIdxY/Cb/Cr = 0
+    0x01, 0x00, // RFX_PROGRESSIVE_TILE_UPGRADE::xIdx = 1
+    0x02, 0x00, // RFX_PROGRESSIVE_TILE_UPGRADE::yIdx = 2
+    0x00, // RFX_PROGRESSIVE_TILE_UPGRADE::quality = 0
+    0x01, 0x00, // RFX_PROGRESSIVE_TILE_UPGRADE::ySrlLen = 1
+    0x01, 0x00, // RFX_PROGRESSIVE_TILE_UPGRADE::yRawLen = 1
+    0x00, 0x00, // RFX_PROGRESSIVE_TILE_UPGRADE::cbSrlLen = 0
+    0x00, 0x00, // RFX_PROGRESSIVE_TILE_UPGRADE::cbRawLen = 0
+    0x00, 0x00, // RFX_PROGRESSIVE_TILE_UPGRADE::crSrlLen = 0
+    0x01, 0x00, // RFX_PROGRESSIVE_TILE_UPGRADE::crRawLen = 1
+    0x11, 0x22, 0x33, // RFX_PROGRESSIVE_TILE_UPGRADE::ySrlData, yRawData, crRawData
+];
+
+const REGION_BLOCK_BUFFER_WITH_INVALID_TILE_DATA_SIZE: [u8; 26] = [
+    0xc4, 0xcc, // RFX_PROGRESSIVE_REGION::blockType = PROGRESSIVE_WBT_REGION
+    0x1a, 0x00, 0x00, 0x00, // RFX_PROGRESSIVE_REGION::blockLen = 26
+    0x40, // RFX_PROGRESSIVE_REGION::tileSize = 64
+    0x01, 0x00, // RFX_PROGRESSIVE_REGION::numRects = 1
+    0x00, // RFX_PROGRESSIVE_REGION::numQuant = 0
+    0x00, // RFX_PROGRESSIVE_REGION::numProgQuant = 0
+    0x00, // RFX_PROGRESSIVE_REGION::flags
+    0x01, 0x00, // RFX_PROGRESSIVE_REGION::numTiles = 1
+    0xff, 0x00, 0x00, 0x00, // RFX_PROGRESSIVE_REGION::tileDataSize = 255
+    0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x40, 0x00, // RFX_PROGRESSIVE_REGION::rects
+];
+
+lazy_static::lazy_static! {
+    static ref CONTEXT_BLOCK: Block<'static> = Block::Context(ContextPdu {
+        context_id: 0,
+        flags: ContextFlags::SUBBAND_DIFFING,
+    });
+    static ref FRAME_BEGIN_BLOCK: Block<'static> = Block::FrameBegin(FrameBeginPdu {
+        frame_index: 5,
+        region_count: 1,
+    });
+    static ref REGION_BLOCK: Block<'static> = Block::Region(RegionPdu {
+        rectangles: vec![RfxRectangle {
+            x: 64,
+            y: 128,
+            width: 64,
+            height: 32,
+        }],
+        quants: vec![Quant {
+            ll3: 6,
+            hl3: 6,
+            lh3: 6,
+            hh3: 6,
+            hl2: 7,
+            lh2: 7,
+            hh2: 8,
+            hl1: 8,
+            lh1: 8,
+            hh1: 9,
+        }],
+        quality_quants: vec![QualityQuant {
+            quality: 0,
+            y: uniform_quant(1),
+            cb: uniform_quant(2),
+            cr: Quant {
+                ll3: 1,
+                hl3: 2,
+                lh3: 3,
+                hh3: 4,
+                hl2: 5,
+                lh2: 6,
+                hh2: 7,
+                hl1: 8,
+                lh1: 9,
+                hh1: 10,
+            },
+        }],
+        flags: RegionFlags::DWT_REDUCE_EXTRAPOLATE,
+        tiles: vec![
+            Tile::Simple(TileSimple {
+                header: TILE_HEADER.clone(),
+                flags: TileFlags::DIFFERENCE,
+                y_data: &REGION_BLOCK_BUFFER[69..71],
+                cb_data: &REGION_BLOCK_BUFFER[71..72],
+                cr_data: &REGION_BLOCK_BUFFER[72..73],
+                tail_data: &[],
+            }),
+            Tile::Upgrade(TileUpgrade {
+                header: TILE_HEADER.clone(),
+                quality: 0,
+                y_srl_data: &REGION_BLOCK_BUFFER[99..100],
+                y_raw_data: &REGION_BLOCK_BUFFER[100..101],
+                cb_srl_data: &[],
+                cb_raw_data: &[],
+                cr_srl_data: &[],
+                cr_raw_data: &REGION_BLOCK_BUFFER[101..102],
+            }),
+        ],
+    });
+}
+
+const TILE_HEADER: TileHeader = TileHeader {
+    y_quant_index: 0,
+    cb_quant_index: 0,
+    cr_quant_index: 0,
+    x: 1,
+    y: 2,
+};
+
+fn uniform_quant(value: u8) -> Quant {
+    Quant {
+        ll3: value,
+        lh3: value,
+        hl3: value,
+        hh3: value,
+        lh2: value,
+        hl2: value,
+        hh2: value,
+        lh1: value,
+        hl1: value,
+        hh1: value,
+    }
+}
+
+fn serialize(block: &Block<'_>) -> Vec<u8> {
+    let mut buffer = vec![0; block.buffer_length()];
+    block.to_buffer_consume(&mut buffer.as_mut_slice()).unwrap();
+    buffer
+}
+
+#[test]
+fn from_buffer_correctly_parses_sync_block() {
+    assert_eq!(Block::Sync, Block::from_buffer(SYNC_BLOCK_BUFFER.as_ref()).unwrap());
+}
+
+#[test]
+fn from_buffer_returns_error_on_invalid_sync_magic() {
+    assert!(Block::from_buffer(SYNC_BLOCK_BUFFER_WITH_INVALID_MAGIC.as_ref()).is_err());
+}
+
+#[test]
+fn to_buffer_correctly_serializes_sync_block() {
+    assert_eq!(SYNC_BLOCK_BUFFER.as_ref(), serialize(&Block::Sync).as_slice());
+}
+
+#[test]
+fn from_buffer_correctly_parses_context_block() {
+    assert_eq!(
+        *CONTEXT_BLOCK,
+        Block::from_buffer(CONTEXT_BLOCK_BUFFER.as_ref()).unwrap()
+    );
+}
+
+#[test]
+fn from_buffer_returns_error_on_invalid_context_tile_size() {
+    assert!(Block::from_buffer(CONTEXT_BLOCK_BUFFER_WITH_INVALID_TILE_SIZE.as_ref()).is_err());
+}
+
+#[test]
+fn to_buffer_correctly_serializes_context_block() {
+    assert_eq!(CONTEXT_BLOCK_BUFFER.as_ref(), serialize(&CONTEXT_BLOCK).as_slice());
+}
+
+#[test]
+fn from_buffer_correctly_parses_frame_begin_block() {
+    assert_eq!(
+        *FRAME_BEGIN_BLOCK,
+        Block::from_buffer(FRAME_BEGIN_BLOCK_BUFFER.as_ref()).unwrap()
+    );
+}
+
+#[test]
+fn to_buffer_correctly_serializes_frame_begin_block() {
+    assert_eq!(
+        FRAME_BEGIN_BLOCK_BUFFER.as_ref(),
+        serialize(&FRAME_BEGIN_BLOCK).as_slice()
+    );
+}
+
+#[test]
+fn from_buffer_correctly_parses_frame_end_block() {
+    assert_eq!(
+        Block::FrameEnd,
+        Block::from_buffer(FRAME_END_BLOCK_BUFFER.as_ref()).unwrap()
+    );
+}
+
+#[test]
+fn from_buffer_correctly_parses_region_block() {
+    assert_eq!(*REGION_BLOCK, Block::from_buffer(REGION_BLOCK_BUFFER.as_ref()).unwrap());
+}
+
+#[test]
+fn from_buffer_returns_error_on_invalid_region_tile_data_size() {
+    assert!(Block::from_buffer(REGION_BLOCK_BUFFER_WITH_INVALID_TILE_DATA_SIZE.as_ref()).is_err());
+}
+
+#[test]
+fn to_buffer_correctly_serializes_region_block() {
+    assert_eq!(REGION_BLOCK_BUFFER.as_ref(), serialize(&REGION_BLOCK).as_slice());
+}
+
+#[test]
+fn buffer_length_is_correct_for_region_block() {
+    assert_eq!(REGION_BLOCK_BUFFER.len(), REGION_BLOCK.buffer_length());
+}
+
+#[test]
+fn from_buffer_consume_parses_a_sequence_of_blocks() {
+    let mut data = Vec::new();
+    data.extend_from_slice(&SYNC_BLOCK_BUFFER);
+    data.extend_from_slice(&CONTEXT_BLOCK_BUFFER);
+    data.extend_from_slice(&FRAME_BEGIN_BLOCK_BUFFER);
+    data.extend_from_slice(&REGION_BLOCK_BUFFER);
+    data.extend_from_slice(&FRAME_END_BLOCK_BUFFER);
+
+    let mut buffer = data.as_slice();
+    let mut blocks = Vec::new();
+    while !buffer.is_empty() {
+        blocks.push(Block::from_buffer_consume(&mut buffer).unwrap());
+    }
+
+    assert_eq!(
+        vec![
+            Block::Sync,
+            CONTEXT_BLOCK.clone(),
+            FRAME_BEGIN_BLOCK.clone(),
+            REGION_BLOCK.clone(),
+            Block::FrameEnd,
+        ],
+        blocks
+    );
+}
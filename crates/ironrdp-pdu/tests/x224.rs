@@ -281,7 +281,7 @@ fn routing_token_decode() {
         .expect("read routing token")
         .expect("routing token");
 
-    assert_eq!(routing_token.0, "3640205228.15629.0000");
+    assert_eq!(routing_token.0, b"3640205228.15629.0000");
 }
 
 #[test]
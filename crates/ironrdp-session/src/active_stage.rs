@@ -1,35 +1,67 @@
-use ironrdp_connector::ConnectionResult;
+use std::sync::Arc;
+
+use ironrdp_connector::{ConnectionResult, DesktopSize, StandardSecurity};
+use ironrdp_graphics::pointer::DecodedPointer;
+use ironrdp_pdu::gcc::Monitor;
 use ironrdp_pdu::geometry::Rectangle;
-use ironrdp_pdu::Action;
+use ironrdp_pdu::input::fast_path::{FastPathInput, FastPathInputEvent};
+use ironrdp_pdu::rdp::server_redirection::ServerRedirectionPdu;
+use ironrdp_pdu::rdp::session_info::ServerAutoReconnect;
+use ironrdp_pdu::rdp::vc::dvc::rdpei::{PenFrame, TouchFrame};
+use ironrdp_pdu::{Action, PduParsing as _};
 
 use crate::image::DecodedImage;
-use crate::x224::GfxHandler;
-use crate::{fast_path, utils, x224, Result};
+use crate::x224::{DynamicVirtualChannel, GfxHandler, StaticVirtualChannel};
+use crate::{fast_path, utils, x224, Error, Result};
 
 pub struct ActiveStage {
     x224_processor: x224::Processor,
     fast_path_processor: fast_path::Processor,
+    standard_security: Option<StandardSecurity>,
 }
 
 impl ActiveStage {
-    pub fn new(connection_result: ConnectionResult, graphics_handler: Option<Box<dyn GfxHandler + Send>>) -> Self {
+    /// Static channel processors are registered by channel name, for the channels requested in the connector
+    /// configuration. Dynamic channel processors are registered by channel name, and replace the built-in ones with
+    /// the same name (graphics pipeline, display control and input).
+    ///
+    /// When no `graphics_handler` is provided, the graphics pipeline is rendered in software into the image.
+    pub fn new(
+        connection_result: ConnectionResult,
+        graphics_handler: Option<Box<dyn GfxHandler + Send>>,
+        static_channels: Vec<Box<dyn StaticVirtualChannel>>,
+        dynamic_channels: Vec<Box<dyn DynamicVirtualChannel>>,
+    ) -> Self {
+        let connection_activation = connection_result.connection_activation;
+        let pointer_cache_size = connection_activation.config.pointer_cache_size;
+
+        // The server compresses the slow-path and fast-path PDUs with the same history, from the connection finalization
+        let bulk_decompressor = Arc::clone(&connection_activation.bulk_decompressor);
+
         let x224_processor = x224::Processor::new(
             utils::swap_hashmap_kv(connection_result.static_channels),
             connection_result.user_channel_id,
             connection_result.io_channel_id,
             connection_result.graphics_config,
             graphics_handler,
+            static_channels,
+            dynamic_channels,
+            connection_activation,
+            Arc::clone(&bulk_decompressor),
         );
 
         let fast_path_processor = fast_path::ProcessorBuilder {
             io_channel_id: connection_result.io_channel_id,
             user_channel_id: connection_result.user_channel_id,
+            pointer_cache_size,
+            bulk_decompressor,
         }
         .build();
 
         Self {
             x224_processor,
             fast_path_processor,
+            standard_security: connection_result.standard_security,
         }
     }
 
@@ -39,43 +71,281 @@ impl ActiveStage {
         action: Action,
         frame: &[u8],
     ) -> Result<Vec<ActiveStageOutput>> {
-        let mut graphics_update_region = None;
+        let decrypted_frame;
+        let frame = match &mut self.standard_security {
+            Some(standard_security) => {
+                decrypted_frame = standard_security.decrypt_frame(frame)?;
+                decrypted_frame.as_slice()
+            }
+            None => frame,
+        };
+
+        let mut stage_outputs = Vec::new();
 
-        let output = match action {
+        match action {
             Action::FastPath => {
                 let mut output = Vec::new();
-                graphics_update_region = self.fast_path_processor.process(image, frame, &mut output)?;
-                output
+                let update_kind = self.fast_path_processor.process(image, frame, &mut output)?;
+
+                if !output.is_empty() {
+                    stage_outputs.push(ActiveStageOutput::ResponseFrame(output));
+                }
+
+                match update_kind {
+                    fast_path::UpdateKind::None => {}
+                    fast_path::UpdateKind::Region(update_region) => {
+                        stage_outputs.push(ActiveStageOutput::GraphicsUpdate(update_region));
+                    }
+                    fast_path::UpdateKind::PointerDefault => {
+                        stage_outputs.push(ActiveStageOutput::PointerDefault);
+                    }
+                    fast_path::UpdateKind::PointerHidden => {
+                        stage_outputs.push(ActiveStageOutput::PointerHidden);
+                    }
+                    fast_path::UpdateKind::PointerPosition { x, y } => {
+                        stage_outputs.push(ActiveStageOutput::PointerPosition { x, y });
+                    }
+                    fast_path::UpdateKind::PointerBitmap(pointer) => {
+                        stage_outputs.push(ActiveStageOutput::PointerBitmap(pointer));
+                    }
+                }
             }
-            Action::X224 => self.x224_processor.process(frame)?,
-        };
+            Action::X224 => {
+                for output in self.x224_processor.process(frame)? {
+                    match output {
+                        x224::ProcessorOutput::ResponseFrame(frame) => {
+                            stage_outputs.push(ActiveStageOutput::ResponseFrame(frame))
+                        }
+                        x224::ProcessorOutput::ResizeDesktop(desktop_size) => {
+                            info!(desktop_size.width, desktop_size.height, "Server resized the desktop");
 
-        let mut stage_outputs = Vec::new();
+                            stage_outputs.push(resize_image(image, &desktop_size));
+                        }
+                        x224::ProcessorOutput::Reactivated(desktop_size) => {
+                            info!(desktop_size.width, desktop_size.height, "Session reactivated");
+
+                            stage_outputs.push(resize_image(image, &desktop_size));
+                            stage_outputs.push(ActiveStageOutput::Reactivated(desktop_size));
+                        }
+                        x224::ProcessorOutput::ServerRedirection(redirection) => {
+                            stage_outputs.push(ActiveStageOutput::ServerRedirection(redirection));
+                        }
+                        x224::ProcessorOutput::MonitorLayout(monitor_layout) => {
+                            info!(
+                                monitor_count = monitor_layout.len(),
+                                "Server updated the monitor layout"
+                            );
 
-        if !output.is_empty() {
-            stage_outputs.push(ActiveStageOutput::ResponseFrame(output));
+                            image.set_monitor_layout(monitor_layout.clone());
+                            stage_outputs.push(ActiveStageOutput::MonitorLayout(monitor_layout));
+                        }
+                    }
+                }
+
+                for update_region in self.x224_processor.flush_gfx_updates(image)? {
+                    stage_outputs.push(ActiveStageOutput::GraphicsUpdate(update_region));
+                }
+            }
         }
 
-        if let Some(update_region) = graphics_update_region {
-            stage_outputs.push(ActiveStageOutput::GraphicsUpdate(update_region));
+        if let Some(standard_security) = &mut self.standard_security {
+            for stage_output in &mut stage_outputs {
+                if let ActiveStageOutput::ResponseFrame(frame) = stage_output {
+                    let mut encrypted_frame = Vec::new();
+                    standard_security.encrypt_frames(frame, false, &mut encrypted_frame)?;
+                    *frame = encrypted_frame;
+                }
+            }
         }
 
         Ok(stage_outputs)
     }
 
+    /// Encodes a request to resize the remote desktop using the Display Control Virtual Channel Extension.
+    ///
+    /// The width is rounded down to an even value and both dimensions are clamped between 200 and 8192 pixels.
+    /// `scale_factor` is the desktop scale factor in percent, and `physical_dims` is the physical size of the
+    /// monitor in millimeters.
+    ///
+    /// Returns `None` when the Display Control dynamic channel is not available, in which case a
+    /// reconnection with the new size is the only option.
+    pub fn encode_resize(
+        &mut self,
+        output: &mut Vec<u8>,
+        width: u32,
+        height: u32,
+        scale_factor: Option<u32>,
+        physical_dims: Option<(u32, u32)>,
+    ) -> Option<Result<usize>> {
+        let written = self
+            .x224_processor
+            .encode_resize(output, width, height, scale_factor, physical_dims)?;
+
+        Some(written.and_then(|written| self.encrypt_output(output, written)))
+    }
+
+    /// Encodes touch frames for the Input Virtual Channel Extension.
+    ///
+    /// Frames are typically produced by an `ironrdp_input::touch::TouchDatabase`, which keeps the contact
+    /// transitions valid. At most [`MAX_TOUCH_CONTACTS`](crate::MAX_TOUCH_CONTACTS) contacts are accepted per frame.
+    ///
+    /// Returns `None` when the Input dynamic channel is not available, in which case touch input may be
+    /// emulated with mouse events.
+    pub fn encode_touch(&mut self, output: &mut Vec<u8>, frames: Vec<TouchFrame>) -> Option<Result<usize>> {
+        let written = self.x224_processor.encode_touch(output, frames)?;
+
+        Some(written.and_then(|written| self.encrypt_output(output, written)))
+    }
+
+    /// Encodes pen frames for the Input Virtual Channel Extension.
+    ///
+    /// Pen input requires version 2.0.0 of the protocol on the server side, see [`ActiveStage::pen_input_ready`].
+    ///
+    /// Returns `None` when the Input dynamic channel is not available.
+    pub fn encode_pen(&mut self, output: &mut Vec<u8>, frames: Vec<PenFrame>) -> Option<Result<usize>> {
+        let written = self.x224_processor.encode_pen(output, frames)?;
+
+        Some(written.and_then(|written| self.encrypt_output(output, written)))
+    }
+
+    /// Whether the server opened the Input dynamic channel and is currently accepting touch input.
+    ///
+    /// The server may suspend the input at any time, e.g.: while the session is locked.
+    pub fn touch_input_ready(&self) -> bool {
+        self.x224_processor.touch_input_ready()
+    }
+
+    /// Whether the server opened the Input dynamic channel and is currently accepting pen input.
+    pub fn pen_input_ready(&self) -> bool {
+        self.x224_processor.pen_input_ready()
+    }
+
+    /// Auto-reconnect packet sent by the server, if any.
+    ///
+    /// It can be passed to the `ClientConnector` to resume the same session after the connection is lost.
+    pub fn auto_reconnect_cookie(&self) -> Option<&ServerAutoReconnect> {
+        self.x224_processor.auto_reconnect_cookie()
+    }
+
+    /// Sends a message on a static virtual channel requested in the connector configuration.
+    pub fn encode_svc(&mut self, output: &mut Vec<u8>, channel_name: &str, payload: &[u8]) -> Result<usize> {
+        let written = self.x224_processor.encode_svc(output, channel_name, payload)?;
+        self.encrypt_output(output, written)
+    }
+
+    /// Processor registered for the given static channel, if the channel was joined.
+    pub fn static_channel<T: StaticVirtualChannel + 'static>(&self, channel_name: &str) -> Option<&T> {
+        self.x224_processor.static_channel(channel_name)
+    }
+
+    pub fn static_channel_mut<T: StaticVirtualChannel + 'static>(&mut self, channel_name: &str) -> Option<&mut T> {
+        self.x224_processor.static_channel_mut(channel_name)
+    }
+
+    /// Processor registered for the given dynamic channel.
+    pub fn dynamic_channel<T: DynamicVirtualChannel + 'static>(&self, channel_name: &str) -> Option<&T> {
+        self.x224_processor.dynamic_channel(channel_name)
+    }
+
+    pub fn dynamic_channel_mut<T: DynamicVirtualChannel + 'static>(&mut self, channel_name: &str) -> Option<&mut T> {
+        self.x224_processor.dynamic_channel_mut(channel_name)
+    }
+
+    /// Queues a message to send on a dynamic channel, once the server opened it.
+    ///
+    /// Queued messages are sent along with the next response on the dynamic virtual channel, or with
+    /// [`ActiveStage::encode_queued_dvc_messages`].
+    pub fn queue_dvc_message(&mut self, channel_name: &str, message: Vec<u8>) -> Result<()> {
+        self.x224_processor.queue_dvc_message(channel_name, message)
+    }
+
+    /// Encodes the messages queued for the opened dynamic channels, by order of channel priority.
+    pub fn encode_queued_dvc_messages(&mut self, output: &mut Vec<u8>) -> Result<usize> {
+        let written = self.x224_processor.encode_queued_dvc_messages(output)?;
+        self.encrypt_output(output, written)
+    }
+
     /// Sends a PDU on the dynamic channel.
-    pub fn encode_dynamic(&self, output: &mut Vec<u8>, channel_name: &str, dvc_data: &[u8]) -> Result<usize> {
-        self.x224_processor.encode_dynamic(output, channel_name, dvc_data)
+    pub fn encode_dynamic(&mut self, output: &mut Vec<u8>, channel_name: &str, dvc_data: &[u8]) -> Result<usize> {
+        let written = self.x224_processor.encode_dynamic(output, channel_name, dvc_data)?;
+        self.encrypt_output(output, written)
     }
 
     /// Send a pdu on the static global channel. Typically used to send input events
-    pub fn encode_static(&self, output: &mut Vec<u8>, pdu: ironrdp_pdu::rdp::headers::ShareDataPdu) -> Result<usize> {
-        self.x224_processor.encode_static(output, pdu)
+    pub fn encode_static(
+        &mut self,
+        output: &mut Vec<u8>,
+        pdu: ironrdp_pdu::rdp::headers::ShareDataPdu,
+    ) -> Result<usize> {
+        let written = self.x224_processor.encode_static(output, pdu)?;
+        self.encrypt_output(output, written)
+    }
+
+    /// Encodes a Fast-Path Input Event PDU.
+    pub fn encode_fastpath_input(&mut self, output: &mut Vec<u8>, events: Vec<FastPathInputEvent>) -> Result<usize> {
+        output.clear();
+
+        FastPathInput(events)
+            .to_buffer(&mut *output)
+            .map_err(|e| Error::new("FastPathInput encode").with_custom(e))?;
+
+        let written = output.len();
+        self.encrypt_output(output, written)
+    }
+
+    /// Encrypts the frames written to the output when Standard RDP Security is in effect.
+    fn encrypt_output(&mut self, output: &mut Vec<u8>, written: usize) -> Result<usize> {
+        let Some(standard_security) = &mut self.standard_security else {
+            return Ok(written);
+        };
+
+        let frames = output[..written].to_vec();
+        output.clear();
+
+        Ok(standard_security.encrypt_frames(&frames, false, output)?)
     }
 }
 
 pub enum ActiveStageOutput {
     ResponseFrame(Vec<u8>),
     GraphicsUpdate(Rectangle),
+    /// The system default pointer should be displayed.
+    PointerDefault,
+    /// The pointer should be hidden.
+    PointerHidden,
+    /// The server moved the pointer to the given position, in desktop coordinates.
+    PointerPosition {
+        x: u16,
+        y: u16,
+    },
+    /// The pointer shape changed. Pointers are shared with the cache, so they are cheap to clone.
+    PointerBitmap(Arc<DecodedPointer>),
+    /// The server completed a Deactivation-Reactivation Sequence.
+    ///
+    /// The image has already been resized to the new desktop size.
+    Reactivated(DesktopSize),
+    /// The server redirected the client, which should reconnect to the target of the redirection.
+    ///
+    /// The redirection is to be provided to the `ClientConnector` of the new connection.
+    ServerRedirection(Box<ServerRedirectionPdu>),
+    /// The server sent the layout of the monitors spanned by the session.
+    ///
+    /// The viewports of the image have already been updated accordingly.
+    MonitorLayout(Vec<Monitor>),
     Terminate,
 }
+
+/// Recreates the image with the new desktop size and returns a full-screen graphics update.
+fn resize_image(image: &mut DecodedImage, desktop_size: &DesktopSize) -> ActiveStageOutput {
+    let monitor_layout = image.monitor_layout().to_vec();
+
+    *image = DecodedImage::new(image.pixel_format(), desktop_size.width, desktop_size.height);
+    image.set_monitor_layout(monitor_layout);
+
+    ActiveStageOutput::GraphicsUpdate(Rectangle {
+        left: 0,
+        top: 0,
+        right: desktop_size.width.saturating_sub(1),
+        bottom: desktop_size.height.saturating_sub(1),
+    })
+}
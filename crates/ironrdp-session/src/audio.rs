@@ -0,0 +1,293 @@
+//! Audio output redirection over the `rdpsnd` static virtual channel or the `AUDIO_PLAYBACK_DVC`
+//! dynamic virtual channel ([MS-RDPEA]).
+//!
+//! The [`Rdpsnd`] channel negotiates the audio formats with the server, decodes the received audio
+//! data, and hands PCM frames to an [`AudioSink`] implementation provided by the client. It is passed to
+//! [`ActiveStage::new`](crate::ActiveStage::new) either as a static channel, requested in the connector
+//! `Config::static_channels` (see [`rdpsnd_channel`]), or as a dynamic channel. In both cases,
+//! `Config::enable_audio_playback` must be set so that the server redirects the audio.
+//!
+//! [MS-RDPEA]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpea/bea2d5cf-e3b9-4419-92e5-0e074ff9bc5b
+
+mod adpcm;
+
+use core::any::Any;
+use core::fmt;
+
+use ironrdp_pdu::gcc::{Channel, ChannelOptions};
+pub use ironrdp_pdu::rdp::vc::rdpsnd::*;
+use ironrdp_pdu::PduParsing as _;
+
+use crate::{DynamicVirtualChannel, Error, Result, StaticVirtualChannel};
+
+/// Version advertised by the client, which supports the Wave2 PDU
+const CLIENT_VERSION: AudioVersion = AudioVersion::V8;
+
+/// Interface to the local audio output
+pub trait AudioSink: Send {
+    /// Plays a frame of audio received from the server.
+    fn play(&mut self, frame: PcmFrame<'_>);
+
+    /// The server changed the volume, 0xFFFF being the maximum and 0 silence.
+    fn set_volume(&mut self, volume_left: u16, volume_right: u16) {
+        let _ = (volume_left, volume_right);
+    }
+
+    /// The server stopped sending audio, pending frames may be discarded.
+    fn close(&mut self) {}
+}
+
+/// Decoded audio data
+#[derive(Debug, Clone, Copy)]
+pub struct PcmFrame<'a> {
+    pub channels: u16,
+    pub sample_rate: u32,
+    /// Server time at which the audio should be played, in milliseconds
+    ///
+    /// Only the lower 16 bits are set by servers which do not support the Wave2 PDU.
+    pub timestamp: u32,
+    /// Signed 16-bit samples, interleaved by channel
+    pub samples: &'a [i16],
+}
+
+/// Client side of the Audio Output Virtual Channel Extension
+pub struct Rdpsnd {
+    sink: Box<dyn AudioSink>,
+    /// Formats supported by both the client and the server, as sent in the Client Audio Formats PDU
+    formats: Vec<AudioFormat>,
+    /// WaveInfo PDU waiting for its Wave PDU
+    pending_wave: Option<WaveInfoPdu>,
+}
+
+impl Rdpsnd {
+    pub fn new(sink: Box<dyn AudioSink>) -> Self {
+        Self {
+            sink,
+            formats: Vec::new(),
+            pending_wave: None,
+        }
+    }
+
+    pub fn sink(&self) -> &dyn AudioSink {
+        self.sink.as_ref()
+    }
+
+    pub fn sink_mut(&mut self) -> &mut dyn AudioSink {
+        self.sink.as_mut()
+    }
+
+    /// Formats negotiated with the server, empty until the server sent its formats
+    pub fn formats(&self) -> &[AudioFormat] {
+        &self.formats
+    }
+
+    /// Processes a message received from the server and returns the PDUs to send in response
+    fn process_message(&mut self, payload: &[u8]) -> Result<Vec<ClientAudioOutputPdu>> {
+        // The Wave PDU has no header, and is only identified by the preceding WaveInfo PDU
+        if let Some(wave_info) = self.pending_wave.take() {
+            let data = wave_info.audio_data(payload)?;
+
+            return self.play(
+                wave_info.format_no,
+                wave_info.block_no,
+                wave_info.timestamp,
+                u32::from(wave_info.timestamp),
+                &data,
+            );
+        }
+
+        let pdu = ServerAudioOutputPdu::from_buffer(payload)?;
+
+        match pdu {
+            ServerAudioOutputPdu::Formats(server_formats) => {
+                debug!(?server_formats, "Received server audio formats");
+
+                self.formats = server_formats.formats.into_iter().filter(is_supported).collect();
+                if self.formats.is_empty() {
+                    warn!("No audio format supported by both the client and the server");
+                }
+
+                let mut responses = vec![ClientAudioOutputPdu::Formats(AudioFormatPdu {
+                    flags: AudioFormatFlags::ALIVE | AudioFormatFlags::VOLUME,
+                    volume: 0xFFFF_FFFF,
+                    pitch: 0,
+                    dgram_port: 0,
+                    last_block_confirmed: 0,
+                    version: CLIENT_VERSION,
+                    formats: self.formats.clone(),
+                })];
+
+                if server_formats.version >= AudioVersion::V6 {
+                    responses.push(ClientAudioOutputPdu::QualityMode(QualityModePdu {
+                        quality_mode: QualityMode::High,
+                    }));
+                }
+
+                Ok(responses)
+            }
+            ServerAudioOutputPdu::Training(training) => {
+                debug!(?training.timestamp, ?training.pack_size, "Received audio training");
+
+                Ok(vec![ClientAudioOutputPdu::TrainingConfirm(TrainingConfirmPdu {
+                    timestamp: training.timestamp,
+                    pack_size: training.pack_size,
+                })])
+            }
+            ServerAudioOutputPdu::WaveInfo(wave_info) => {
+                self.pending_wave = Some(wave_info);
+
+                Ok(Vec::new())
+            }
+            ServerAudioOutputPdu::Wave2(wave) => self.play(
+                wave.format_no,
+                wave.block_no,
+                wave.timestamp,
+                wave.audio_timestamp,
+                &wave.data,
+            ),
+            ServerAudioOutputPdu::Volume(volume) => {
+                self.sink.set_volume(volume.volume_left, volume.volume_right);
+
+                Ok(Vec::new())
+            }
+            ServerAudioOutputPdu::Pitch(_) => Ok(Vec::new()),
+            ServerAudioOutputPdu::Close => {
+                debug!("Audio output closed by the server");
+                self.sink.close();
+
+                Ok(Vec::new())
+            }
+        }
+    }
+
+    fn play(
+        &mut self,
+        format_no: u16,
+        block_no: u8,
+        timestamp: u16,
+        audio_timestamp: u32,
+        data: &[u8],
+    ) -> Result<Vec<ClientAudioOutputPdu>> {
+        let format = self
+            .formats
+            .get(usize::from(format_no))
+            .ok_or_else(|| Error::new("invalid audio format index").with_reason(format!("{format_no}")))?;
+
+        let samples = decode_samples(format, data)?;
+        trace!(block_no, samples = samples.len(), "Play audio");
+
+        self.sink.play(PcmFrame {
+            channels: format.n_channels,
+            sample_rate: format.n_samples_per_sec,
+            timestamp: audio_timestamp,
+            samples: &samples,
+        });
+
+        Ok(vec![ClientAudioOutputPdu::WaveConfirm(WaveConfirmPdu {
+            timestamp,
+            confirmed_block_no: block_no,
+        })])
+    }
+
+    fn reset(&mut self) {
+        self.formats.clear();
+        self.pending_wave = None;
+        self.sink.close();
+    }
+}
+
+fn is_supported(format: &AudioFormat) -> bool {
+    format.n_channels != 0
+        && matches!(
+            (format.format, format.bits_per_sample),
+            (WaveFormat::PCM, 8) | (WaveFormat::PCM, 16) | (WaveFormat::DVI_ADPCM, 4)
+        )
+}
+
+fn decode_samples(format: &AudioFormat, data: &[u8]) -> Result<Vec<i16>> {
+    match (format.format, format.bits_per_sample) {
+        (WaveFormat::PCM, 16) => Ok(data
+            .chunks_exact(2)
+            .map(|sample| i16::from_le_bytes([sample[0], sample[1]]))
+            .collect()),
+        // 8-bit PCM samples are unsigned
+        (WaveFormat::PCM, 8) => Ok(data.iter().map(|sample| (i16::from(*sample) - 128) << 8).collect()),
+        (WaveFormat::DVI_ADPCM, 4) => adpcm::decode(data, format.n_channels, format.n_block_align),
+        _ => Err(Error::new("unsupported audio format").with_reason(format!("{:?}", format.format))),
+    }
+}
+
+fn encode_pdus(pdus: Vec<ClientAudioOutputPdu>) -> Result<Vec<Vec<u8>>> {
+    pdus.into_iter()
+        .map(|pdu| -> Result<Vec<u8>> {
+            trace!(?pdu, "Send audio output PDU");
+
+            let mut buf = Vec::with_capacity(pdu.buffer_length());
+            pdu.to_buffer(&mut buf)?;
+
+            Ok(buf)
+        })
+        .collect()
+}
+
+/// Audio output channel to request in the connector `Config::static_channels`
+pub fn rdpsnd_channel() -> Channel {
+    Channel {
+        name: RDPSND_CHANNEL_NAME.to_owned(),
+        options: ChannelOptions::INITIALIZED | ChannelOptions::ENCRYPT_RDP | ChannelOptions::COMPRESS_RDP,
+    }
+}
+
+impl StaticVirtualChannel for Rdpsnd {
+    fn channel_name(&self) -> &str {
+        RDPSND_CHANNEL_NAME
+    }
+
+    fn process(&mut self, payload: &[u8]) -> Result<Vec<Vec<u8>>> {
+        let responses = self.process_message(payload)?;
+
+        encode_pdus(responses)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+impl DynamicVirtualChannel for Rdpsnd {
+    fn channel_name(&self) -> &str {
+        AUDIO_PLAYBACK_DVC_CHANNEL_NAME
+    }
+
+    fn process(&mut self, payload: &[u8]) -> Result<Vec<Vec<u8>>> {
+        let responses = self.process_message(payload)?;
+
+        encode_pdus(responses)
+    }
+
+    fn close(&mut self) {
+        // The formats are negotiated again when the channel is reopened
+        self.reset();
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+impl fmt::Debug for Rdpsnd {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Rdpsnd")
+            .field("formats", &self.formats)
+            .field("pending_wave", &self.pending_wave)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,169 @@
+//! IMA ADPCM (WAVE_FORMAT_DVI_ADPCM) decoder
+
+use crate::{Error, Result};
+
+const BLOCK_HEADER_SIZE: usize = 4;
+/// Number of bytes per channel in each interleaved group of the block data
+const GROUP_SIZE: usize = 4;
+
+const INDEX_TABLE: [i8; 16] = [-1, -1, -1, -1, 2, 4, 6, 8, -1, -1, -1, -1, 2, 4, 6, 8];
+
+const STEP_TABLE: [i32; 89] = [
+    7, 8, 9, 10, 11, 12, 13, 14, 16, 17, 19, 21, 23, 25, 28, 31, 34, 37, 41, 45, 50, 55, 60, 66, 73, 80, 88, 97, 107,
+    118, 130, 143, 157, 173, 190, 209, 230, 253, 279, 307, 337, 371, 408, 449, 494, 544, 598, 658, 724, 796, 876, 963,
+    1060, 1166, 1282, 1411, 1552, 1707, 1878, 2066, 2272, 2499, 2749, 3024, 3327, 3660, 4026, 4428, 4871, 5358, 5894,
+    6484, 7132, 7845, 8630, 9493, 10442, 11487, 12635, 13899, 15289, 16818, 18500, 20350, 22385, 24623, 27086, 29794,
+    32767,
+];
+
+struct ChannelState {
+    predictor: i32,
+    step_index: usize,
+}
+
+impl ChannelState {
+    fn from_block_header(header: &[u8]) -> Self {
+        Self {
+            predictor: i32::from(i16::from_le_bytes([header[0], header[1]])),
+            step_index: usize::from(header[2]).min(STEP_TABLE.len() - 1),
+        }
+    }
+
+    fn sample(&self) -> i16 {
+        // The predictor is always clamped to the i16 range
+        self.predictor as i16
+    }
+
+    fn decode_nibble(&mut self, nibble: u8) -> i16 {
+        let step = STEP_TABLE[self.step_index];
+
+        let mut diff = step >> 3;
+        if nibble & 0x04 != 0 {
+            diff += step;
+        }
+        if nibble & 0x02 != 0 {
+            diff += step >> 1;
+        }
+        if nibble & 0x01 != 0 {
+            diff += step >> 2;
+        }
+
+        if nibble & 0x08 != 0 {
+            self.predictor -= diff;
+        } else {
+            self.predictor += diff;
+        }
+        self.predictor = self.predictor.clamp(i32::from(i16::MIN), i32::from(i16::MAX));
+
+        let step_index = self.step_index as i32 + i32::from(INDEX_TABLE[usize::from(nibble)]);
+        self.step_index = step_index.clamp(0, STEP_TABLE.len() as i32 - 1) as usize;
+
+        self.sample()
+    }
+}
+
+/// Decodes IMA ADPCM blocks into interleaved 16-bit samples
+///
+/// Each block starts with a header per channel, holding the first sample and the initial step index,
+/// followed by groups of 4 bytes (8 samples) per channel.
+pub(super) fn decode(data: &[u8], channels: u16, block_align: u16) -> Result<Vec<i16>> {
+    let channels = usize::from(channels);
+    let block_align = usize::from(block_align);
+    let headers_size = BLOCK_HEADER_SIZE * channels;
+    let group_size = GROUP_SIZE * channels;
+
+    if channels == 0 || block_align <= headers_size {
+        return Err(Error::new("invalid IMA ADPCM block alignment")
+            .with_reason(format!("{channels} channels, {block_align} bytes per block")));
+    }
+
+    let mut samples = Vec::with_capacity(data.len() * 2);
+
+    // The last block may be shorter than the block alignment
+    for block in data.chunks(block_align) {
+        if block.len() < headers_size {
+            return Err(Error::new("truncated IMA ADPCM block"));
+        }
+
+        let (headers, groups) = block.split_at(headers_size);
+        let mut states = headers
+            .chunks_exact(BLOCK_HEADER_SIZE)
+            .map(ChannelState::from_block_header)
+            .collect::<Vec<_>>();
+
+        samples.extend(states.iter().map(ChannelState::sample));
+
+        for group in groups.chunks_exact(group_size) {
+            let offset = samples.len();
+            samples.resize(offset + GROUP_SIZE * 2 * channels, 0);
+
+            for (channel, (state, channel_data)) in states.iter_mut().zip(group.chunks_exact(GROUP_SIZE)).enumerate() {
+                for (i, byte) in channel_data.iter().enumerate() {
+                    samples[offset + 2 * i * channels + channel] = state.decode_nibble(byte & 0x0F);
+                    samples[offset + (2 * i + 1) * channels + channel] = state.decode_nibble(byte >> 4);
+                }
+            }
+        }
+    }
+
+    Ok(samples)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_mono_block() {
+        let block = [0x00, 0x00, 0x00, 0x00, 0x74, 0x10, 0x88, 0xF7];
+
+        assert_eq!(decode(&block, 1, 8).unwrap(), [0, 7, 23, 25, 31, 29, 28, 51, -1]);
+    }
+
+    #[test]
+    fn decode_stereo_block() {
+        let block = [
+            0x64, 0x00, 10, 0x00, // left header: 100, step index 10
+            0x9C, 0xFF, 20, 0x00, // right header: -100, step index 20
+            0x12, 0x34, 0x56, 0x77, // left samples
+            0x89, 0xAB, 0xCD, 0xEF, // right samples
+        ];
+
+        assert_eq!(
+            decode(&block, 2, 16).unwrap(),
+            [100, -100, 111, -118, 117, -123, 135, -158, 150, -180, 177, -226, 218, -282, 301, -394, 482, -605]
+        );
+    }
+
+    #[test]
+    fn decode_clamps_samples() {
+        let block = [0x00, 0x7D, 60, 0x00, 0x77, 0x77, 0x77, 0x77];
+
+        assert_eq!(
+            decode(&block, 1, 8).unwrap(),
+            [32000, 32767, 32767, 32767, 32767, 32767, 32767, 32767, 32767]
+        );
+    }
+
+    #[test]
+    fn decode_short_last_block() {
+        let blocks = [0x00, 0x00, 0x00, 0x00, 0x74, 0x10, 0x88, 0xF7, 0x2A, 0x00, 0x00, 0x00];
+
+        assert_eq!(decode(&blocks, 1, 8).unwrap(), [0, 7, 23, 25, 31, 29, 28, 51, -1, 42]);
+    }
+
+    #[test]
+    fn decode_truncated_block() {
+        let blocks = [0x00, 0x00, 0x00, 0x00, 0x74, 0x10, 0x88, 0xF7, 0x2A, 0x00];
+
+        assert!(decode(&blocks, 1, 8).is_err());
+        assert!(decode(&[0x00; 6], 2, 16).is_err());
+    }
+
+    #[test]
+    fn decode_invalid_block_align() {
+        assert!(decode(&[0x00; 8], 1, 4).is_err());
+        assert!(decode(&[0x00; 16], 2, 8).is_err());
+        assert!(decode(&[0x00; 8], 0, 8).is_err());
+    }
+}
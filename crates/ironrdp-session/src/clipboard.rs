@@ -0,0 +1,423 @@
+//! Clipboard redirection over the `cliprdr` static virtual channel ([MS-RDPECLIP]).
+//!
+//! The [`Cliprdr`] static virtual channel handles the protocol itself, and delegates the interaction
+//! with the local clipboard to a [`CliprdrBackend`] implementation provided by the client. It is
+//! requested in the connector `Config::static_channels` (see [`cliprdr_channel`]), and passed to
+//! [`ActiveStage::new`](crate::ActiveStage::new).
+//!
+//! Backend callbacks are notifications: the client answers them at its own pace by calling the
+//! `Cliprdr` methods (e.g.: [`Cliprdr::submit_format_data`]) on the processor returned by
+//! [`ActiveStage::static_channel_mut`](crate::ActiveStage::static_channel_mut), and sending the
+//! resulting message with [`ActiveStage::encode_svc`](crate::ActiveStage::encode_svc).
+//!
+//! [MS-RDPECLIP]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpeclip/fb9b7e0b-6db4-41c2-b83c-f889c1ee7688
+
+use core::any::Any;
+use core::fmt;
+
+use ironrdp_pdu::gcc::{Channel, ChannelOptions};
+pub use ironrdp_pdu::rdp::vc::cliprdr::*;
+use ironrdp_pdu::PduParsing as _;
+
+use crate::{Error, Result, StaticVirtualChannel};
+
+/// Interface to the local clipboard
+pub trait CliprdrBackend: Send {
+    /// Directory on the client file system where the server may store temporary files
+    fn temporary_directory(&self) -> &str;
+
+    /// Capabilities supported by the backend, on top of the Long Format Names capability
+    /// which is always advertised.
+    ///
+    /// File copy requires [`ClipboardGeneralCapabilityFlags::STREAM_FILECLIP_ENABLED`].
+    fn client_capabilities(&self) -> ClipboardGeneralCapabilityFlags {
+        ClipboardGeneralCapabilityFlags::empty()
+    }
+
+    /// The channel is ready: the formats currently available on the local clipboard
+    /// should be announced with [`Cliprdr::initiate_copy`].
+    fn on_request_format_list(&mut self);
+
+    /// The remote clipboard content changed and is available in the given formats.
+    fn on_remote_copy(&mut self, available_formats: &[ClipboardFormat]);
+
+    /// The server requests the local clipboard content in the given format.
+    ///
+    /// The data should be sent with [`Cliprdr::submit_format_data`].
+    fn on_format_data_request(&mut self, format: ClipboardFormatId);
+
+    /// The server sent the remote clipboard content requested with [`Cliprdr::initiate_paste`].
+    fn on_format_data_response(&mut self, response: &FormatDataResponse);
+
+    /// The server requests the size or a range of bytes of a file copied on the client.
+    ///
+    /// The data should be sent with [`Cliprdr::submit_file_contents`].
+    fn on_file_contents_request(&mut self, request: &FileContentsRequest) {
+        let _ = request;
+    }
+
+    /// The server sent the file contents requested with [`Cliprdr::request_file_contents`].
+    fn on_file_contents_response(&mut self, response: &FileContentsResponse) {
+        let _ = response;
+    }
+
+    /// The server requests the client to keep the current clipboard content available.
+    fn on_lock(&mut self, data_id: LockDataId) {
+        let _ = data_id;
+    }
+
+    /// The server no longer needs the clipboard content locked with the given ID.
+    fn on_unlock(&mut self, data_id: LockDataId) {
+        let _ = data_id;
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum CliprdrState {
+    /// Waiting for the Monitor Ready PDU
+    Initialization,
+    Ready,
+}
+
+/// Client side of the Clipboard Virtual Channel Extension
+pub struct Cliprdr {
+    backend: Box<dyn CliprdrBackend>,
+    state: CliprdrState,
+    /// Capabilities advertised by the server, if any
+    server_capabilities: Option<Capabilities>,
+}
+
+impl Cliprdr {
+    pub fn new(backend: Box<dyn CliprdrBackend>) -> Self {
+        Self {
+            backend,
+            state: CliprdrState::Initialization,
+            server_capabilities: None,
+        }
+    }
+
+    pub fn backend(&self) -> &dyn CliprdrBackend {
+        self.backend.as_ref()
+    }
+
+    pub fn backend_mut(&mut self) -> &mut dyn CliprdrBackend {
+        self.backend.as_mut()
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.state == CliprdrState::Ready
+    }
+
+    /// Capabilities supported by both the client and the server
+    pub fn capabilities(&self) -> Capabilities {
+        let mut capabilities = self.client_capabilities();
+
+        // When the server does not send its capabilities, no optional capability can be used
+        capabilities.downgrade(self.server_capabilities.as_ref().unwrap_or(&Capabilities {
+            capabilities: Vec::new(),
+        }));
+
+        capabilities
+    }
+
+    fn client_capabilities(&self) -> Capabilities {
+        Capabilities::new(
+            ClipboardProtocolVersion::V2,
+            ClipboardGeneralCapabilityFlags::USE_LONG_FORMAT_NAMES | self.backend.client_capabilities(),
+        )
+    }
+
+    fn use_long_format_names(&self) -> bool {
+        self.capabilities()
+            .flags()
+            .contains(ClipboardGeneralCapabilityFlags::USE_LONG_FORMAT_NAMES)
+    }
+
+    /// Processes a PDU received from the server and returns the PDUs to send in response
+    fn process_pdu(&mut self, pdu: ClipboardPdu) -> Result<Vec<ClipboardPdu>> {
+        match pdu {
+            ClipboardPdu::Capabilities(server_capabilities) => {
+                debug!(?server_capabilities, "Received clipboard capabilities");
+                self.server_capabilities = Some(server_capabilities);
+
+                Ok(Vec::new())
+            }
+            ClipboardPdu::MonitorReady => {
+                let capabilities = ClipboardPdu::Capabilities(self.client_capabilities());
+                let temporary_directory = ClipboardPdu::TemporaryDirectory(ClientTemporaryDirectory {
+                    path: self.backend.temporary_directory().to_owned(),
+                });
+
+                self.state = CliprdrState::Ready;
+                self.backend.on_request_format_list();
+
+                Ok(vec![capabilities, temporary_directory])
+            }
+            ClipboardPdu::FormatList(format_list) => {
+                let response = match format_list.get_formats(self.use_long_format_names()) {
+                    Ok(formats) => {
+                        debug!(?formats, "Remote clipboard content changed");
+                        self.backend.on_remote_copy(&formats);
+
+                        FormatListResponse::Ok
+                    }
+                    Err(e) => {
+                        warn!(error = %e, "Invalid format list");
+
+                        FormatListResponse::Fail
+                    }
+                };
+
+                Ok(vec![ClipboardPdu::FormatListResponse(response)])
+            }
+            ClipboardPdu::FormatListResponse(FormatListResponse::Ok) => Ok(Vec::new()),
+            ClipboardPdu::FormatListResponse(FormatListResponse::Fail) => {
+                warn!("The server rejected the client format list");
+
+                Ok(Vec::new())
+            }
+            ClipboardPdu::FormatDataRequest(request) => {
+                self.backend.on_format_data_request(request.format);
+
+                Ok(Vec::new())
+            }
+            ClipboardPdu::FormatDataResponse(response) => {
+                self.backend.on_format_data_response(&response);
+
+                Ok(Vec::new())
+            }
+            ClipboardPdu::FileContentsRequest(request) => {
+                self.backend.on_file_contents_request(&request);
+
+                Ok(Vec::new())
+            }
+            ClipboardPdu::FileContentsResponse(response) => {
+                self.backend.on_file_contents_response(&response);
+
+                Ok(Vec::new())
+            }
+            ClipboardPdu::LockData(data_id) => {
+                self.backend.on_lock(data_id);
+
+                Ok(Vec::new())
+            }
+            ClipboardPdu::UnlockData(data_id) => {
+                self.backend.on_unlock(data_id);
+
+                Ok(Vec::new())
+            }
+            ClipboardPdu::TemporaryDirectory(_) => Err(Error::new("unexpected clipboard PDU")
+                .with_reason("the Temporary Directory PDU is only sent by the client")),
+        }
+    }
+
+    fn check_ready(&self) -> Result<()> {
+        if self.is_ready() {
+            Ok(())
+        } else {
+            Err(Error::new("clipboard channel is not ready"))
+        }
+    }
+
+    /// Announces that the local clipboard content changed and is available in the given formats
+    pub fn initiate_copy(&self, available_formats: &[ClipboardFormat]) -> Result<Vec<u8>> {
+        self.check_ready()?;
+
+        encode_pdu(&ClipboardPdu::FormatList(FormatList::new(
+            available_formats,
+            self.use_long_format_names(),
+        )))
+    }
+
+    /// Requests the remote clipboard content in the given format
+    pub fn initiate_paste(&self, format: ClipboardFormatId) -> Result<Vec<u8>> {
+        self.check_ready()?;
+
+        encode_pdu(&ClipboardPdu::FormatDataRequest(FormatDataRequest { format }))
+    }
+
+    /// Sends the local clipboard content requested by the server
+    pub fn submit_format_data(&self, response: FormatDataResponse) -> Result<Vec<u8>> {
+        self.check_ready()?;
+
+        encode_pdu(&ClipboardPdu::FormatDataResponse(response))
+    }
+
+    /// Requests the size or a range of bytes of a file listed in the remote clipboard
+    pub fn request_file_contents(&self, request: FileContentsRequest) -> Result<Vec<u8>> {
+        self.check_ready()?;
+        self.check_file_copy_enabled()?;
+
+        encode_pdu(&ClipboardPdu::FileContentsRequest(request))
+    }
+
+    /// Sends the file contents requested by the server
+    pub fn submit_file_contents(&self, response: FileContentsResponse) -> Result<Vec<u8>> {
+        self.check_ready()?;
+        self.check_file_copy_enabled()?;
+
+        encode_pdu(&ClipboardPdu::FileContentsResponse(response))
+    }
+
+    fn check_file_copy_enabled(&self) -> Result<()> {
+        if self
+            .capabilities()
+            .flags()
+            .contains(ClipboardGeneralCapabilityFlags::STREAM_FILECLIP_ENABLED)
+        {
+            Ok(())
+        } else {
+            Err(Error::new(
+                "file copy is not supported by both the client and the server",
+            ))
+        }
+    }
+
+    /// Requests the server to keep the current remote clipboard content available,
+    /// so that files can be retrieved even if the remote clipboard changes in the meantime
+    pub fn lock_clipboard(&self, data_id: LockDataId) -> Result<Vec<u8>> {
+        self.check_ready()?;
+        self.check_locking_enabled()?;
+
+        encode_pdu(&ClipboardPdu::LockData(data_id))
+    }
+
+    pub fn unlock_clipboard(&self, data_id: LockDataId) -> Result<Vec<u8>> {
+        self.check_ready()?;
+        self.check_locking_enabled()?;
+
+        encode_pdu(&ClipboardPdu::UnlockData(data_id))
+    }
+
+    fn check_locking_enabled(&self) -> Result<()> {
+        if self
+            .capabilities()
+            .flags()
+            .contains(ClipboardGeneralCapabilityFlags::CAN_LOCK_CLIPDATA)
+        {
+            Ok(())
+        } else {
+            Err(Error::new(
+                "clipboard locking is not supported by both the client and the server",
+            ))
+        }
+    }
+}
+
+/// Clipboard channel to request in the connector `Config::static_channels`
+pub fn cliprdr_channel() -> Channel {
+    Channel {
+        name: CLIPRDR_CHANNEL_NAME.to_owned(),
+        options: ChannelOptions::INITIALIZED
+            | ChannelOptions::ENCRYPT_RDP
+            | ChannelOptions::COMPRESS_RDP
+            | ChannelOptions::SHOW_PROTOCOL,
+    }
+}
+
+impl StaticVirtualChannel for Cliprdr {
+    fn channel_name(&self) -> &str {
+        CLIPRDR_CHANNEL_NAME
+    }
+
+    fn process(&mut self, payload: &[u8]) -> Result<Vec<Vec<u8>>> {
+        let pdu = ClipboardPdu::from_buffer(payload).map_err(Error::from)?;
+        debug!(?pdu, "Received clipboard PDU");
+
+        let responses = self
+            .process_pdu(pdu)?
+            .iter()
+            .map(encode_pdu)
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(responses)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+impl fmt::Debug for Cliprdr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Cliprdr")
+            .field("state", &self.state)
+            .field("server_capabilities", &self.server_capabilities)
+            .finish_non_exhaustive()
+    }
+}
+
+fn encode_pdu(pdu: &ClipboardPdu) -> Result<Vec<u8>> {
+    debug!(?pdu, "Send clipboard PDU");
+
+    let mut buf = Vec::with_capacity(pdu.buffer_length());
+    pdu.to_buffer(&mut buf)?;
+
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct NullBackend;
+
+    impl CliprdrBackend for NullBackend {
+        fn temporary_directory(&self) -> &str {
+            "C:\\Temp"
+        }
+
+        fn on_request_format_list(&mut self) {}
+
+        fn on_remote_copy(&mut self, _: &[ClipboardFormat]) {}
+
+        fn on_format_data_request(&mut self, _: ClipboardFormatId) {}
+
+        fn on_format_data_response(&mut self, _: &FormatDataResponse) {}
+    }
+
+    fn decode(message: &[u8]) -> ClipboardPdu {
+        ClipboardPdu::from_buffer(message).unwrap()
+    }
+
+    #[test]
+    fn monitor_ready_is_answered_with_the_client_capabilities() {
+        let mut cliprdr = Cliprdr::new(Box::new(NullBackend));
+
+        let responses = cliprdr
+            .process(&encode_pdu(&ClipboardPdu::MonitorReady).unwrap())
+            .unwrap();
+
+        assert_eq!(responses.len(), 2);
+        assert!(matches!(decode(&responses[0]), ClipboardPdu::Capabilities(_)));
+        assert!(matches!(
+            decode(&responses[1]),
+            ClipboardPdu::TemporaryDirectory(ClientTemporaryDirectory { path }) if path == "C:\\Temp"
+        ));
+        assert!(cliprdr.is_ready());
+    }
+
+    #[test]
+    fn messages_are_encoded_once_ready() {
+        let mut cliprdr = Cliprdr::new(Box::new(NullBackend));
+
+        assert!(cliprdr.initiate_paste(ClipboardFormatId::CF_UNICODETEXT).is_err());
+
+        cliprdr
+            .process(&encode_pdu(&ClipboardPdu::MonitorReady).unwrap())
+            .unwrap();
+
+        let message = cliprdr.initiate_paste(ClipboardFormatId::CF_UNICODETEXT).unwrap();
+
+        assert_eq!(
+            decode(&message),
+            ClipboardPdu::FormatDataRequest(FormatDataRequest {
+                format: ClipboardFormatId::CF_UNICODETEXT
+            })
+        );
+    }
+}
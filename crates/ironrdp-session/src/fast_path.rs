@@ -1,9 +1,15 @@
+use std::sync::{Arc, Mutex, PoisonError};
+
+use ironrdp_graphics::bulk;
+use ironrdp_graphics::pointer::DecodedPointer;
 use ironrdp_graphics::{rdp6::BitmapStreamDecoder, rle::RlePixelFormat};
 use ironrdp_pdu::codecs::rfx::FrameAcknowledgePdu;
 use ironrdp_pdu::fast_path::{
     FastPathError, FastPathHeader, FastPathUpdate, FastPathUpdatePdu, Fragmentation, UpdateCode,
 };
 use ironrdp_pdu::geometry::Rectangle;
+use ironrdp_pdu::palette::PaletteEntry;
+use ironrdp_pdu::pointer::PointerUpdateData;
 use ironrdp_pdu::rdp::headers::ShareDataPdu;
 use ironrdp_pdu::surface_commands::{FrameAction, FrameMarkerPdu, SurfaceCommand};
 use ironrdp_pdu::PduBufferParsing;
@@ -12,21 +18,34 @@ use crate::image::DecodedImage;
 use crate::utils::CodecId;
 use crate::{rfx, Error, Result};
 
+/// Result of the processing of a Fast-Path update
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpdateKind {
+    None,
+    /// The image buffer was updated in the given region
+    Region(Rectangle),
+    PointerDefault,
+    PointerHidden,
+    PointerPosition {
+        x: u16,
+        y: u16,
+    },
+    PointerBitmap(Arc<DecodedPointer>),
+}
+
 pub struct Processor {
     complete_data: CompleteData,
     rfx_handler: rfx::DecodingContext,
     marker_processor: FrameMarkerProcessor,
     bitmap_stream_decoder: BitmapStreamDecoder,
+    pointer_cache: PointerCache,
+    /// Color table of 8 bpp bitmaps, as sent by the last Palette Update
+    palette: Vec<PaletteEntry>,
+    bulk_decompressor: Arc<Mutex<bulk::Decompressor>>,
 }
 
 impl Processor {
-    // Returns true if image buffer was updated, false otherwise
-    pub fn process(
-        &mut self,
-        image: &mut DecodedImage,
-        mut input: &[u8],
-        output: &mut Vec<u8>,
-    ) -> Result<Option<Rectangle>> {
+    pub fn process(&mut self, image: &mut DecodedImage, mut input: &[u8], output: &mut Vec<u8>) -> Result<UpdateKind> {
         use ironrdp_pdu::PduParsing as _;
 
         let header = FastPathHeader::from_buffer(&mut input)?;
@@ -35,14 +54,28 @@ impl Processor {
         let update_pdu = FastPathUpdatePdu::from_buffer(input)?;
         trace!(fast_path_update_fragmentation = ?update_pdu.fragmentation);
 
-        let processed_complete_data = self
-            .complete_data
-            .process_data(update_pdu.data, update_pdu.fragmentation);
+        let decompressed_data;
+        let update_data = match (update_pdu.compression_flags, update_pdu.compression_type) {
+            (Some(compression_flags), Some(compression_type)) => {
+                let mut data = Vec::new();
+                self.bulk_decompressor
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .decompress(update_pdu.data, compression_type, compression_flags, &mut data)
+                    .map_err(|e| Error::new("failed to decompress the Fast-Path update").with_custom(e))?;
+
+                decompressed_data = data;
+                decompressed_data.as_slice()
+            }
+            _ => update_pdu.data,
+        };
+
+        let processed_complete_data = self.complete_data.process_data(update_data, update_pdu.fragmentation);
 
         let update_code = update_pdu.update_code;
 
         let Some(data) = processed_complete_data else {
-            return Ok(None);
+            return Ok(UpdateKind::None);
         };
 
         let update = FastPathUpdate::from_buffer_with_code(data.as_slice(), update_code);
@@ -51,7 +84,7 @@ impl Processor {
             Ok(FastPathUpdate::SurfaceCommands(surface_commands)) => {
                 trace!("Received Surface Commands: {} pieces", surface_commands.len());
                 let update_region = self.process_surface_commands(image, output, surface_commands)?;
-                Ok(Some(update_region))
+                Ok(UpdateKind::Region(update_region))
             }
             Ok(FastPathUpdate::Bitmap(bitmap_update)) => {
                 trace!("Received bitmap update");
@@ -83,7 +116,7 @@ impl Processor {
                                 update.height as usize,
                             ) {
                                 Ok(()) => {
-                                    image.apply_rgb24_bitmap(&buf, &update.rectangle);
+                                    image.apply_rgb24_bitmap(&buf, usize::from(update.width) * 3, &update.rectangle);
                                 }
                                 Err(err) => {
                                     warn!("Invalid RDP6_BITMAP_STREAM: {err}");
@@ -102,13 +135,22 @@ impl Processor {
                                 update.height,
                                 update.bits_per_pixel,
                             ) {
+                                // Decompressed rows are not padded
+                                Ok(RlePixelFormat::Rgb8) => {
+                                    let stride = usize::from(update.width);
+                                    image.apply_rgb8_bitmap(&buf, &self.palette, stride, &update.rectangle);
+                                }
+                                Ok(RlePixelFormat::Rgb15) => {
+                                    let stride = usize::from(update.width) * 2;
+                                    image.apply_rgb15_bitmap(&buf, stride, &update.rectangle);
+                                }
                                 Ok(RlePixelFormat::Rgb16) => {
-                                    image.apply_rgb16_bitmap(&buf, &update.rectangle);
+                                    let stride = usize::from(update.width) * 2;
+                                    image.apply_rgb16_bitmap(&buf, stride, &update.rectangle);
                                 }
-
-                                // TODO: support other pixel formats…
-                                Ok(format @ (RlePixelFormat::Rgb8 | RlePixelFormat::Rgb15 | RlePixelFormat::Rgb24)) => {
-                                    warn!("Received RLE-compressed bitmap with unsupported color depth: {format:?}");
+                                Ok(RlePixelFormat::Rgb24) => {
+                                    let stride = usize::from(update.width) * 3;
+                                    image.apply_bgr24_bitmap(&buf, stride, &update.rectangle);
                                 }
 
                                 Err(e) => warn!("Invalid RLE-compressed bitmap: {e}"),
@@ -120,10 +162,16 @@ impl Processor {
                         // four bytes (including up to three bytes of padding, as necessary).
                         trace!("Uncompressed raw bitmap");
 
+                        let data = update.bitmap_data;
+                        let width = usize::from(update.width);
+
                         match update.bits_per_pixel {
-                            16 => image.apply_rgb16_bitmap(update.bitmap_data, &update.rectangle),
-                            // TODO: support other pixel formats…
-                            unsupported => warn!("Invalid raw bitmap with {unsupported} bytes per pixels"),
+                            8 => image.apply_rgb8_bitmap(data, &self.palette, raw_stride(width), &update.rectangle),
+                            15 => image.apply_rgb15_bitmap(data, raw_stride(width * 2), &update.rectangle),
+                            16 => image.apply_rgb16_bitmap(data, raw_stride(width * 2), &update.rectangle),
+                            24 => image.apply_bgr24_bitmap(data, raw_stride(width * 3), &update.rectangle),
+                            32 => image.apply_bgrx32_bitmap(data, raw_stride(width * 4), &update.rectangle),
+                            unsupported => warn!("Invalid raw bitmap with {unsupported} bits per pixel"),
                         }
                     }
 
@@ -133,21 +181,33 @@ impl Processor {
                     }
                 }
 
-                Ok(update_rectangle)
+                Ok(update_rectangle.map_or(UpdateKind::None, UpdateKind::Region))
+            }
+            Ok(FastPathUpdate::Pointer(pointer_update)) => Ok(self.process_pointer_update(pointer_update)),
+            Ok(FastPathUpdate::Palette(palette_update)) => {
+                trace!(colors = palette_update.entries.len(), "Received palette update");
+                self.palette = palette_update.entries;
+                Ok(UpdateKind::None)
             }
-            Err(FastPathError::UnsupportedFastPathUpdate(code))
-                if code == UpdateCode::Orders || code == UpdateCode::Palette =>
-            {
+            Err(FastPathError::UnsupportedFastPathUpdate(code)) if code == UpdateCode::Orders => {
                 warn!(?code, "Received unsupported Fast-Path update");
-                Ok(None)
+                Ok(UpdateKind::None)
             }
             Err(FastPathError::UnsupportedFastPathUpdate(code)) => {
                 debug!(?code, "Received unsupported Fast-Path update");
-                Ok(None)
+                Ok(UpdateKind::None)
             }
             Err(FastPathError::BitmapError(error)) => {
                 warn!(?error, "Received invalid bitmap");
-                Ok(None)
+                Ok(UpdateKind::None)
+            }
+            Err(FastPathError::PointerError(error)) => {
+                warn!(?error, "Received invalid pointer update");
+                Ok(UpdateKind::None)
+            }
+            Err(FastPathError::PaletteError(error)) => {
+                warn!(?error, "Received invalid palette update");
+                Ok(UpdateKind::None)
             }
             Err(e) => Err(Error::new("Fast-Path").with_custom(e)),
         }
@@ -196,11 +256,82 @@ impl Processor {
 
         Ok(update_rectangle)
     }
+
+    fn process_pointer_update(&mut self, update: PointerUpdateData<'_>) -> UpdateKind {
+        let (cache_index, decoded) = match update {
+            PointerUpdateData::SetHidden => {
+                trace!("Pointer hidden");
+                return UpdateKind::PointerHidden;
+            }
+            PointerUpdateData::SetDefault => {
+                trace!("Default pointer");
+                return UpdateKind::PointerDefault;
+            }
+            PointerUpdateData::SetPosition(position) => {
+                trace!(?position, "Pointer position");
+                return UpdateKind::PointerPosition {
+                    x: position.x,
+                    y: position.y,
+                };
+            }
+            PointerUpdateData::Cached(cached) => {
+                trace!(cached.cache_index, "Cached pointer");
+
+                return match self.pointer_cache.get(cached.cache_index) {
+                    Some(pointer) => UpdateKind::PointerBitmap(pointer),
+                    None => {
+                        warn!(
+                            cached.cache_index,
+                            "Received cached pointer update for an empty cache entry"
+                        );
+                        UpdateKind::None
+                    }
+                };
+            }
+            PointerUpdateData::Color(pointer) => {
+                trace!(?pointer, "Color pointer");
+                (pointer.cache_index, DecodedPointer::decode_color_pointer(&pointer))
+            }
+            PointerUpdateData::New(pointer) => {
+                trace!(?pointer, "New pointer");
+                (
+                    pointer.color_pointer.cache_index,
+                    DecodedPointer::decode_pointer(&pointer),
+                )
+            }
+            PointerUpdateData::Large(pointer) => {
+                trace!(?pointer, "Large pointer");
+                (pointer.cache_index, DecodedPointer::decode_large_pointer(&pointer))
+            }
+        };
+
+        match decoded {
+            Ok(pointer) => {
+                let pointer = Arc::new(pointer);
+                self.pointer_cache.insert(cache_index, Arc::clone(&pointer));
+
+                UpdateKind::PointerBitmap(pointer)
+            }
+            Err(error) => {
+                warn!(%error, "Failed to decode pointer");
+                UpdateKind::None
+            }
+        }
+    }
+}
+
+/// Length of an uncompressed bitmap row, which is padded to a multiple of four bytes
+fn raw_stride(row_length: usize) -> usize {
+    (row_length + 3) & !3
 }
 
 pub struct ProcessorBuilder {
     pub io_channel_id: u16,
     pub user_channel_id: u16,
+    /// Number of pointer cache entries, as advertised in the Pointer capability set
+    pub pointer_cache_size: u16,
+    /// Bulk decompressor, whose history is shared with the slow-path PDUs
+    pub bulk_decompressor: Arc<Mutex<bulk::Decompressor>>,
 }
 
 impl ProcessorBuilder {
@@ -210,6 +341,33 @@ impl ProcessorBuilder {
             rfx_handler: rfx::DecodingContext::new(),
             marker_processor: FrameMarkerProcessor::new(self.user_channel_id, self.io_channel_id),
             bitmap_stream_decoder: BitmapStreamDecoder::default(),
+            pointer_cache: PointerCache::new(self.pointer_cache_size),
+            palette: Vec::new(),
+            bulk_decompressor: self.bulk_decompressor,
+        }
+    }
+}
+
+/// Pointers sent by the server, which may be reused by the Cached Pointer Update
+struct PointerCache {
+    entries: Vec<Option<Arc<DecodedPointer>>>,
+}
+
+impl PointerCache {
+    fn new(size: u16) -> Self {
+        Self {
+            entries: vec![None; usize::from(size)],
+        }
+    }
+
+    fn get(&self, index: u16) -> Option<Arc<DecodedPointer>> {
+        self.entries.get(usize::from(index)).cloned().flatten()
+    }
+
+    fn insert(&mut self, index: u16, pointer: Arc<DecodedPointer>) {
+        match self.entries.get_mut(usize::from(index)) {
+            Some(entry) => *entry = Some(pointer),
+            None => warn!(index, "Pointer cache index is out of range"),
         }
     }
 }
@@ -0,0 +1,1219 @@
+//! Software rendering of the RDP 8 graphics pipeline
+//!
+//! The surfaces created by the server are kept in memory, and the areas of the surfaces mapped to the output
+//! are copied to the [`DecodedImage`] once the current frame is complete.
+
+use std::collections::HashMap;
+
+use ironrdp_graphics::avc::{Avc444Version, AvcDecoder};
+use ironrdp_graphics::clearcodec::ClearCodecDecoder;
+use ironrdp_graphics::h264::H264Decoder;
+use ironrdp_graphics::image_processing::{ImageRegionMut, PixelFormat as ImagePixelFormat};
+use ironrdp_graphics::rdp6::BitmapStreamDecoder;
+use ironrdp_pdu::dvc::gfx::{
+    Avc420BitmapStream, Avc444BitmapStream, CacheToSurfacePdu, Codec1Type, Codec2Type, Color, CreateSurfacePdu,
+    DeleteEncodingContextPdu, MapSurfaceToOutputPdu, PixelFormat, Point, ServerPdu, SolidFillPdu, SurfaceToCachePdu,
+    SurfaceToSurfacePdu, WireToSurface1Pdu, WireToSurface2Pdu,
+};
+use ironrdp_pdu::geometry::Rectangle;
+use ironrdp_pdu::PduBufferParsing;
+
+use crate::image::DecodedImage;
+use crate::{rfx_progressive, Error, Result};
+
+/// The surfaces use the XRGB and ARGB formats of the graphics pipeline, which are stored in little-endian order
+const SURFACE_BYTES_PER_PIXEL: usize = 4;
+
+/// Largest width and height of a surface, which is also the largest size of the output
+const MAX_SURFACE_SIZE: usize = 32_766;
+
+/// Number of cache slots of a client advertising the small cache (16 MB)
+pub(crate) const SMALL_CACHE_SLOTS: u16 = 4096;
+/// Number of cache slots of a client advertising the regular cache (100 MB)
+pub(crate) const CACHE_SLOTS: u16 = 25600;
+
+/// Area of a surface, with exclusive right and bottom bounds as in the graphics pipeline PDUs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Area {
+    left: usize,
+    top: usize,
+    width: usize,
+    height: usize,
+}
+
+impl Area {
+    fn from_rectangle(rectangle: &Rectangle) -> Result<Self> {
+        if rectangle.right < rectangle.left || rectangle.bottom < rectangle.top {
+            return Err(Error::new("invalid rectangle").with_reason(format!("{rectangle:?}")));
+        }
+
+        Ok(Self {
+            left: usize::from(rectangle.left),
+            top: usize::from(rectangle.top),
+            width: usize::from(rectangle.right - rectangle.left),
+            height: usize::from(rectangle.bottom - rectangle.top),
+        })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    /// Part of the area which is inside of a `width` x `height` surface.
+    fn clip(self, width: usize, height: usize) -> Self {
+        let right = (self.left + self.width).min(width);
+        let bottom = (self.top + self.height).min(height);
+
+        Self {
+            left: self.left,
+            top: self.top,
+            width: right.saturating_sub(self.left),
+            height: bottom.saturating_sub(self.top),
+        }
+    }
+
+    fn to_rectangle(self) -> Rectangle {
+        debug_assert!(!self.is_empty());
+
+        // The surfaces and the output are at most 32766 pixels wide and high
+        Rectangle {
+            left: u16::try_from(self.left).unwrap(),
+            top: u16::try_from(self.top).unwrap(),
+            right: u16::try_from(self.left + self.width - 1).unwrap(),
+            bottom: u16::try_from(self.top + self.height - 1).unwrap(),
+        }
+    }
+}
+
+/// Pixels of an area copied from a surface, one row after the other
+#[derive(Debug, Clone)]
+struct Bitmap {
+    width: usize,
+    height: usize,
+    data: Vec<u8>,
+}
+
+#[derive(Debug)]
+struct Surface {
+    width: usize,
+    height: usize,
+    /// Whether the alpha channel is ignored (XRGB surfaces)
+    is_opaque: bool,
+    data: Vec<u8>,
+    /// Position of the surface on the output, when mapped
+    output_origin: Option<(usize, usize)>,
+    /// Updated area since the last flush, in surface coordinates
+    dirty_area: Option<Rectangle>,
+    /// ClearCodec caches are kept per surface, and created on the first ClearCodec bitmap
+    clear_codec: Option<ClearCodecDecoder>,
+    /// RemoteFX Progressive tiles, by codec context ID
+    progressive_contexts: HashMap<u32, rfx_progressive::DecodingContext>,
+    /// H.264 decoder and YUV 4:4:4 planes, created on the first AVC bitmap
+    avc: Option<AvcDecoder>,
+}
+
+impl Surface {
+    fn new(pdu: &CreateSurfacePdu) -> Result<Self> {
+        let width = usize::from(pdu.width);
+        let height = usize::from(pdu.height);
+
+        if width > MAX_SURFACE_SIZE || height > MAX_SURFACE_SIZE {
+            return Err(Error::new("surface too large").with_reason(format!(
+                "{width}x{height} surface {} exceeds the {MAX_SURFACE_SIZE}x{MAX_SURFACE_SIZE} limit",
+                pdu.surface_id
+            )));
+        }
+
+        let is_opaque = pdu.pixel_format == PixelFormat::XRgb;
+
+        // New surfaces are black, and fully transparent when they have an alpha channel
+        let initial_pixel = [0, 0, 0, if is_opaque { 0xFF } else { 0x00 }];
+
+        Ok(Self {
+            width,
+            height,
+            is_opaque,
+            data: initial_pixel.repeat(width * height),
+            output_origin: None,
+            dirty_area: None,
+            clear_codec: None,
+            progressive_contexts: HashMap::new(),
+            avc: None,
+        })
+    }
+
+    fn stride(&self) -> usize {
+        self.width * SURFACE_BYTES_PER_PIXEL
+    }
+
+    fn mark_dirty(&mut self, area: Area) {
+        if area.is_empty() {
+            return;
+        }
+
+        let rectangle = area.to_rectangle();
+
+        self.dirty_area = Some(match self.dirty_area.take() {
+            Some(dirty_area) => dirty_area.union(&rectangle),
+            None => rectangle,
+        });
+    }
+
+    fn fill(&mut self, area: Area, color: &Color) {
+        let area = area.clip(self.width, self.height);
+
+        if area.is_empty() {
+            return;
+        }
+
+        let alpha = if self.is_opaque { 0xFF } else { color.xa };
+        let pixel = [color.b, color.g, color.r, alpha];
+        let stride = self.stride();
+
+        for row in self.data.chunks_exact_mut(stride).skip(area.top).take(area.height) {
+            let start = area.left * SURFACE_BYTES_PER_PIXEL;
+            let end = start + area.width * SURFACE_BYTES_PER_PIXEL;
+
+            row[start..end]
+                .chunks_exact_mut(SURFACE_BYTES_PER_PIXEL)
+                .for_each(|dst_pixel| dst_pixel.copy_from_slice(&pixel));
+        }
+
+        self.mark_dirty(area);
+    }
+
+    /// Copies an area of the surface, which must be fully inside of the surface.
+    fn read(&self, area: Area) -> Result<Bitmap> {
+        if area.clip(self.width, self.height) != area {
+            return Err(Error::new("source rectangle out of the surface bounds")
+                .with_reason(format!("{area:?} does not fit in {}x{}", self.width, self.height)));
+        }
+
+        let row_length = area.width * SURFACE_BYTES_PER_PIXEL;
+        let mut data = Vec::with_capacity(row_length * area.height);
+
+        if area.is_empty() {
+            return Ok(Bitmap {
+                width: area.width,
+                height: area.height,
+                data,
+            });
+        }
+
+        for row in self.data.chunks_exact(self.stride()).skip(area.top).take(area.height) {
+            let start = area.left * SURFACE_BYTES_PER_PIXEL;
+            data.extend_from_slice(&row[start..start + row_length]);
+        }
+
+        Ok(Bitmap {
+            width: area.width,
+            height: area.height,
+            data,
+        })
+    }
+
+    /// Copies a bitmap at the given position, ignoring the pixels falling outside of the surface.
+    fn write(&mut self, destination: &Point, bitmap: &Bitmap) {
+        let area = Area {
+            left: usize::from(destination.x),
+            top: usize::from(destination.y),
+            width: bitmap.width,
+            height: bitmap.height,
+        }
+        .clip(self.width, self.height);
+
+        if area.is_empty() {
+            return;
+        }
+
+        let src_stride = bitmap.width * SURFACE_BYTES_PER_PIXEL;
+        let row_length = area.width * SURFACE_BYTES_PER_PIXEL;
+        let dst_stride = self.stride();
+        let is_opaque = self.is_opaque;
+
+        for (src_row, dst_row) in bitmap
+            .data
+            .chunks_exact(src_stride)
+            .zip(self.data.chunks_exact_mut(dst_stride).skip(area.top))
+            .take(area.height)
+        {
+            let start = area.left * SURFACE_BYTES_PER_PIXEL;
+            let dst_row = &mut dst_row[start..start + row_length];
+            dst_row.copy_from_slice(&src_row[..row_length]);
+
+            if is_opaque {
+                dst_row
+                    .chunks_exact_mut(SURFACE_BYTES_PER_PIXEL)
+                    .for_each(|dst_pixel| dst_pixel[3] = 0xFF);
+            }
+        }
+
+        self.mark_dirty(area);
+    }
+
+    /// Decodes a ClearCodec bitmap over the given area, ignoring the pixels falling outside of the surface.
+    fn decode_clear_codec(&mut self, area: Area, bitmap_data: &[u8]) -> Result<()> {
+        let visible_area = area.clip(self.width, self.height);
+
+        if visible_area.is_empty() {
+            warn!(?area, "ClearCodec bitmap outside of the surface");
+            return Ok(());
+        }
+
+        let step = u16::try_from(self.stride()).map_err(|_| {
+            Error::new("surface too large for ClearCodec").with_reason(format!("{} pixels wide", self.width))
+        })?;
+
+        let mut region = ImageRegionMut {
+            region: visible_area.to_rectangle(),
+            step,
+            pixel_format: ImagePixelFormat::BgrA32,
+            data: &mut self.data,
+        };
+
+        self.clear_codec
+            .get_or_insert_with(ClearCodecDecoder::new)
+            .decode(bitmap_data, area.width, area.height, &mut region)
+            .map_err(|e| Error::new("failed to decode ClearCodec bitmap").with_custom(e))?;
+
+        self.mark_dirty(visible_area);
+
+        Ok(())
+    }
+
+    /// Decodes an AVC420 bitmap, or an AVC444 bitmap when `avc444_version` is set, in place.
+    ///
+    /// The metablock rectangles of the bitmap are in surface coordinates, so the destination rectangle of the PDU
+    /// is not used.
+    fn decode_avc(&mut self, avc444_version: Option<Avc444Version>, bitmap_data: &[u8]) -> Result<()> {
+        if self.avc.is_none() {
+            self.avc = new_h264_decoder()?.map(|decoder| AvcDecoder::new(decoder, self.width, self.height));
+        }
+
+        let Some(decoder) = &mut self.avc else {
+            warn!("No H.264 decoder available, AVC bitmap ignored");
+            return Ok(());
+        };
+
+        let updated_rectangles = match avc444_version {
+            None => {
+                let stream = Avc420BitmapStream::from_buffer(bitmap_data)
+                    .map_err(|e| Error::new("invalid AVC420 bitmap").with_custom(e))?;
+
+                decoder.decode_avc420(&stream, &mut self.data)
+            }
+            Some(version) => {
+                let stream = Avc444BitmapStream::from_buffer(bitmap_data)
+                    .map_err(|e| Error::new("invalid AVC444 bitmap").with_custom(e))?;
+
+                decoder.decode_avc444(&stream, version, &mut self.data)
+            }
+        }
+        .map_err(|e| Error::new("failed to decode AVC bitmap").with_custom(e))?;
+
+        for rectangle in &updated_rectangles {
+            let area = Area::from_rectangle(rectangle)?;
+            self.mark_dirty(area);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "openh264")]
+fn new_h264_decoder() -> Result<Option<Box<dyn H264Decoder>>> {
+    let decoder = ironrdp_graphics::h264::OpenH264Decoder::new()
+        .map_err(|e| Error::new("failed to create the H.264 decoder").with_custom(e))?;
+
+    Ok(Some(Box::new(decoder)))
+}
+
+#[cfg(not(feature = "openh264"))]
+fn new_h264_decoder() -> Result<Option<Box<dyn H264Decoder>>> {
+    Ok(None)
+}
+
+/// Applies the surface and cache commands of the graphics pipeline
+#[derive(Debug)]
+pub(crate) struct GfxCompositor {
+    surfaces: HashMap<u16, Surface>,
+    cache_slots: HashMap<u16, Bitmap>,
+    max_cache_slots: u16,
+    planar_decoder: BitmapStreamDecoder,
+    /// Whether a Start Frame PDU was received without the matching End Frame PDU
+    frame_in_progress: bool,
+}
+
+impl GfxCompositor {
+    pub(crate) fn new(max_cache_slots: u16) -> Self {
+        Self {
+            surfaces: HashMap::new(),
+            cache_slots: HashMap::new(),
+            max_cache_slots,
+            planar_decoder: BitmapStreamDecoder::default(),
+            frame_in_progress: false,
+        }
+    }
+
+    /// Deletes all the surfaces and cache entries.
+    pub(crate) fn reset(&mut self) {
+        self.surfaces.clear();
+        self.cache_slots.clear();
+        self.frame_in_progress = false;
+    }
+
+    pub(crate) fn process(&mut self, pdu: &ServerPdu) -> Result<()> {
+        match pdu {
+            ServerPdu::CreateSurface(pdu) => {
+                self.surfaces.insert(pdu.surface_id, Surface::new(pdu)?);
+            }
+            ServerPdu::DeleteSurface(pdu) => self.delete_surface(pdu.surface_id),
+            ServerPdu::SolidFill(pdu) => self.solid_fill(pdu)?,
+            ServerPdu::SurfaceToSurface(pdu) => self.surface_to_surface(pdu)?,
+            ServerPdu::SurfaceToCache(pdu) => self.surface_to_cache(pdu)?,
+            ServerPdu::CacheToSurface(pdu) => self.cache_to_surface(pdu)?,
+            ServerPdu::EvictCacheEntry(pdu) => {
+                self.cache_slots.remove(&pdu.cache_slot);
+            }
+            ServerPdu::MapSurfaceToOutput(pdu) => self.map_surface_to_output(pdu)?,
+            ServerPdu::WireToSurface1(pdu) => self.wire_to_surface_1(pdu)?,
+            ServerPdu::WireToSurface2(pdu) => self.wire_to_surface_2(pdu)?,
+            ServerPdu::DeleteEncodingContext(pdu) => self.delete_encoding_context(pdu)?,
+            ServerPdu::ResetGraphics(_) => self.reset(),
+            ServerPdu::StartFrame(_) => self.frame_in_progress = true,
+            ServerPdu::EndFrame(_) => self.frame_in_progress = false,
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Copies the updated areas of the mapped surfaces to the image, and returns the updated regions of the image.
+    ///
+    /// Nothing is copied while a frame is in progress, so that partially drawn frames are never displayed.
+    pub(crate) fn flush(&mut self, image: &mut DecodedImage) -> Result<Vec<Rectangle>> {
+        if self.frame_in_progress {
+            return Ok(Vec::new());
+        }
+
+        let image_width = usize::from(image.width());
+        let image_height = usize::from(image.height());
+
+        let mut updated_regions = Vec::new();
+
+        for surface in self.surfaces.values_mut() {
+            let Some(dirty_area) = surface.dirty_area.take() else {
+                continue;
+            };
+
+            let Some((origin_x, origin_y)) = surface.output_origin else {
+                continue;
+            };
+
+            let destination = Area {
+                left: origin_x + usize::from(dirty_area.left),
+                top: origin_y + usize::from(dirty_area.top),
+                width: usize::from(dirty_area.width()),
+                height: usize::from(dirty_area.height()),
+            }
+            .clip(image_width, image_height);
+
+            if destination.is_empty() {
+                continue;
+            }
+
+            let source = Area {
+                left: usize::from(dirty_area.left),
+                top: usize::from(dirty_area.top),
+                width: destination.width,
+                height: destination.height,
+            };
+
+            let destination = destination.to_rectangle();
+
+            image.apply_bgra32_region(&surface.data, surface.stride(), &source.to_rectangle(), &destination)?;
+
+            updated_regions.push(destination);
+        }
+
+        Ok(updated_regions)
+    }
+
+    fn surface_mut(&mut self, surface_id: u16) -> Result<&mut Surface> {
+        self.surfaces
+            .get_mut(&surface_id)
+            .ok_or_else(|| Error::new("unknown surface").with_reason(surface_id.to_string()))
+    }
+
+    fn delete_surface(&mut self, surface_id: u16) {
+        if self.surfaces.remove(&surface_id).is_none() {
+            warn!(surface_id, "Attempted to delete an unknown surface");
+        }
+    }
+
+    fn solid_fill(&mut self, pdu: &SolidFillPdu) -> Result<()> {
+        let surface = self.surface_mut(pdu.surface_id)?;
+
+        for rectangle in &pdu.rectangles {
+            surface.fill(Area::from_rectangle(rectangle)?, &pdu.fill_pixel);
+        }
+
+        Ok(())
+    }
+
+    fn surface_to_surface(&mut self, pdu: &SurfaceToSurfacePdu) -> Result<()> {
+        let source_area = Area::from_rectangle(&pdu.source_rectangle)?;
+
+        // The source and destination surfaces may be the same, and the areas may overlap
+        let bitmap = self.surface_mut(pdu.source_surface_id)?.read(source_area)?;
+
+        let destination = self.surface_mut(pdu.destination_surface_id)?;
+
+        for point in &pdu.destination_points {
+            destination.write(point, &bitmap);
+        }
+
+        Ok(())
+    }
+
+    fn surface_to_cache(&mut self, pdu: &SurfaceToCachePdu) -> Result<()> {
+        self.check_cache_slot(pdu.cache_slot)?;
+
+        let source_area = Area::from_rectangle(&pdu.source_rectangle)?;
+        let bitmap = self.surface_mut(pdu.surface_id)?.read(source_area)?;
+
+        self.cache_slots.insert(pdu.cache_slot, bitmap);
+
+        Ok(())
+    }
+
+    fn cache_to_surface(&mut self, pdu: &CacheToSurfacePdu) -> Result<()> {
+        self.check_cache_slot(pdu.cache_slot)?;
+
+        let Some(bitmap) = self.cache_slots.get(&pdu.cache_slot) else {
+            return Err(Error::new("empty cache slot").with_reason(pdu.cache_slot.to_string()));
+        };
+
+        let surface = self
+            .surfaces
+            .get_mut(&pdu.surface_id)
+            .ok_or_else(|| Error::new("unknown surface").with_reason(pdu.surface_id.to_string()))?;
+
+        for point in &pdu.destination_points {
+            surface.write(point, bitmap);
+        }
+
+        Ok(())
+    }
+
+    fn map_surface_to_output(&mut self, pdu: &MapSurfaceToOutputPdu) -> Result<()> {
+        let origin_x = usize::try_from(pdu.output_origin_x).map_err(|e| Error::new("invalid origin").with_custom(e))?;
+        let origin_y = usize::try_from(pdu.output_origin_y).map_err(|e| Error::new("invalid origin").with_custom(e))?;
+
+        let surface = self.surface_mut(pdu.surface_id)?;
+        surface.output_origin = Some((origin_x, origin_y));
+
+        // The whole surface is displayed at its new location
+        let area = Area {
+            left: 0,
+            top: 0,
+            width: surface.width,
+            height: surface.height,
+        };
+        surface.mark_dirty(area);
+
+        Ok(())
+    }
+
+    fn wire_to_surface_1(&mut self, pdu: &WireToSurface1Pdu) -> Result<()> {
+        let area = Area::from_rectangle(&pdu.destination_rectangle)?;
+
+        let bitmap = match pdu.codec_id {
+            Codec1Type::Uncompressed => {
+                let expected_length = area.width * area.height * SURFACE_BYTES_PER_PIXEL;
+
+                if pdu.bitmap_data.len() != expected_length {
+                    return Err(Error::new("invalid uncompressed bitmap size").with_reason(format!(
+                        "expected {expected_length} bytes, got {}",
+                        pdu.bitmap_data.len()
+                    )));
+                }
+
+                Bitmap {
+                    width: area.width,
+                    height: area.height,
+                    data: pdu.bitmap_data.clone(),
+                }
+            }
+            Codec1Type::Planar => self.decode_planar(area, &pdu.bitmap_data)?,
+            Codec1Type::ClearCodec => {
+                // ClearCodec draws over the current content of the surface, so it is decoded in place
+                return self
+                    .surface_mut(pdu.surface_id)?
+                    .decode_clear_codec(area, &pdu.bitmap_data);
+            }
+            Codec1Type::Avc420 => return self.surface_mut(pdu.surface_id)?.decode_avc(None, &pdu.bitmap_data),
+            Codec1Type::Avc444 => {
+                return self
+                    .surface_mut(pdu.surface_id)?
+                    .decode_avc(Some(Avc444Version::V1), &pdu.bitmap_data);
+            }
+            Codec1Type::Avc444v2 => {
+                return self
+                    .surface_mut(pdu.surface_id)?
+                    .decode_avc(Some(Avc444Version::V2), &pdu.bitmap_data);
+            }
+            codec_id => {
+                warn!(?codec_id, "Unsupported codec");
+                return Ok(());
+            }
+        };
+
+        let destination = Point {
+            x: pdu.destination_rectangle.left,
+            y: pdu.destination_rectangle.top,
+        };
+
+        self.surface_mut(pdu.surface_id)?.write(&destination, &bitmap);
+
+        Ok(())
+    }
+
+    fn wire_to_surface_2(&mut self, pdu: &WireToSurface2Pdu) -> Result<()> {
+        let surface = self.surface_mut(pdu.surface_id)?;
+
+        match pdu.codec_id {
+            Codec2Type::RemoteFxProgressive => {
+                let updates = surface
+                    .progressive_contexts
+                    .entry(pdu.codec_context_id)
+                    .or_insert_with(rfx_progressive::DecodingContext::new)
+                    .decode(&pdu.bitmap_data)?;
+
+                for update in updates {
+                    let destination = Point {
+                        x: update.left,
+                        y: update.top,
+                    };
+
+                    let bitmap = Bitmap {
+                        width: update.width,
+                        height: update.height,
+                        data: update.data,
+                    };
+
+                    surface.write(&destination, &bitmap);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn delete_encoding_context(&mut self, pdu: &DeleteEncodingContextPdu) -> Result<()> {
+        let surface = self.surface_mut(pdu.surface_id)?;
+
+        if surface.progressive_contexts.remove(&pdu.codec_context_id).is_none() {
+            warn!(codec_context_id = pdu.codec_context_id, "Unknown encoding context");
+        }
+
+        Ok(())
+    }
+
+    fn decode_planar(&mut self, area: Area, bitmap_data: &[u8]) -> Result<Bitmap> {
+        let mut bitmap = Bitmap {
+            width: area.width,
+            height: area.height,
+            data: vec![0; area.width * area.height * SURFACE_BYTES_PER_PIXEL],
+        };
+
+        if area.is_empty() {
+            return Ok(bitmap);
+        }
+
+        let step = u16::try_from(area.width * SURFACE_BYTES_PER_PIXEL)
+            .map_err(|_| Error::new("planar bitmap too large").with_reason(format!("{area:?}")))?;
+
+        let mut region = ImageRegionMut {
+            region: Rectangle {
+                left: 0,
+                top: 0,
+                right: u16::try_from(area.width - 1).unwrap(),
+                bottom: u16::try_from(area.height - 1).unwrap(),
+            },
+            step,
+            pixel_format: ImagePixelFormat::BgrA32,
+            data: &mut bitmap.data,
+        };
+
+        self.planar_decoder
+            .decode_bitmap_stream_to_region(bitmap_data, &mut region, area.width, area.height)
+            .map_err(|e| Error::new("failed to decode planar bitmap").with_custom(e))?;
+
+        Ok(bitmap)
+    }
+
+    /// Cache slots are numbered from 1 to the maximum number of cache slots.
+    fn check_cache_slot(&self, cache_slot: u16) -> Result<()> {
+        if cache_slot == 0 || cache_slot > self.max_cache_slots {
+            return Err(Error::new("invalid cache slot").with_reason(cache_slot.to_string()));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ironrdp_graphics::h264::{H264Error, YuvFrame};
+    use ironrdp_pdu::codecs::rfx::{Quant, RfxRectangle};
+    use ironrdp_pdu::codecs::rfx_progressive::{
+        Block, RegionFlags, RegionPdu, Tile, TileFlags, TileHeader, TileSimple, TileUpgrade, FULL_QUALITY,
+    };
+    use ironrdp_pdu::dvc::gfx::{DeleteSurfacePdu, EndFramePdu, EvictCacheEntryPdu, StartFramePdu, Timestamp};
+
+    use super::*;
+
+    const RED: Color = Color {
+        b: 0x00,
+        g: 0x00,
+        r: 0xFF,
+        xa: 0x00,
+    };
+
+    const BLUE: Color = Color {
+        b: 0xFF,
+        g: 0x00,
+        r: 0x00,
+        xa: 0x00,
+    };
+
+    fn compositor_with_surface(width: u16, height: u16) -> GfxCompositor {
+        let mut compositor = GfxCompositor::new(CACHE_SLOTS);
+
+        compositor
+            .process(&ServerPdu::CreateSurface(CreateSurfacePdu {
+                surface_id: 1,
+                width,
+                height,
+                pixel_format: PixelFormat::XRgb,
+            }))
+            .unwrap();
+
+        compositor
+    }
+
+    fn solid_fill(color: Color, rectangle: Rectangle) -> ServerPdu {
+        ServerPdu::SolidFill(SolidFillPdu {
+            surface_id: 1,
+            fill_pixel: color,
+            rectangles: vec![rectangle],
+        })
+    }
+
+    fn map_to_output(x: u32, y: u32) -> ServerPdu {
+        ServerPdu::MapSurfaceToOutput(MapSurfaceToOutputPdu {
+            surface_id: 1,
+            output_origin_x: x,
+            output_origin_y: y,
+        })
+    }
+
+    fn pixel(image: &DecodedImage, x: usize, y: usize) -> &[u8] {
+        let start = (y * usize::from(image.width()) + x) * 4;
+        &image.data()[start..start + 4]
+    }
+
+    #[test]
+    fn solid_fill_is_copied_to_the_output() {
+        let mut compositor = compositor_with_surface(4, 4);
+        let mut image = DecodedImage::new(ImagePixelFormat::RgbA32, 8, 8);
+
+        compositor.process(&map_to_output(2, 2)).unwrap();
+        compositor.flush(&mut image).unwrap();
+
+        compositor
+            .process(&solid_fill(
+                RED,
+                Rectangle {
+                    left: 1,
+                    top: 1,
+                    right: 3,
+                    bottom: 2,
+                },
+            ))
+            .unwrap();
+
+        let regions = compositor.flush(&mut image).unwrap();
+
+        assert_eq!(
+            regions,
+            [Rectangle {
+                left: 3,
+                top: 3,
+                right: 4,
+                bottom: 3,
+            }]
+        );
+        assert_eq!(pixel(&image, 3, 3), [0xFF, 0x00, 0x00, 0xFF]);
+        assert_eq!(pixel(&image, 4, 3), [0xFF, 0x00, 0x00, 0xFF]);
+        assert_eq!(pixel(&image, 5, 3), [0x00, 0x00, 0x00, 0xFF]);
+        assert_eq!(pixel(&image, 3, 4), [0x00, 0x00, 0x00, 0xFF]);
+    }
+
+    #[test]
+    fn unmapped_surfaces_are_not_displayed() {
+        let mut compositor = compositor_with_surface(4, 4);
+        let mut image = DecodedImage::new(ImagePixelFormat::RgbA32, 8, 8);
+
+        compositor
+            .process(&solid_fill(
+                RED,
+                Rectangle {
+                    left: 0,
+                    top: 0,
+                    right: 4,
+                    bottom: 4,
+                },
+            ))
+            .unwrap();
+
+        assert!(compositor.flush(&mut image).unwrap().is_empty());
+        assert!(image.data().iter().all(|value| *value == 0));
+    }
+
+    #[test]
+    fn oversized_surfaces_are_rejected() {
+        let mut compositor = GfxCompositor::new(CACHE_SLOTS);
+
+        for (width, height) in [(32767, 1), (1, u16::MAX)] {
+            assert!(compositor
+                .process(&ServerPdu::CreateSurface(CreateSurfacePdu {
+                    surface_id: 1,
+                    width,
+                    height,
+                    pixel_format: PixelFormat::XRgb,
+                }))
+                .is_err());
+        }
+
+        assert!(compositor.surfaces.is_empty());
+    }
+
+    #[test]
+    fn output_is_updated_at_the_end_of_the_frame() {
+        let mut compositor = compositor_with_surface(4, 4);
+        let mut image = DecodedImage::new(ImagePixelFormat::RgbA32, 4, 4);
+
+        compositor
+            .process(&ServerPdu::StartFrame(StartFramePdu {
+                timestamp: Timestamp {
+                    milliseconds: 0,
+                    seconds: 0,
+                    minutes: 0,
+                    hours: 0,
+                },
+                frame_id: 1,
+            }))
+            .unwrap();
+        compositor.process(&map_to_output(0, 0)).unwrap();
+
+        assert!(compositor.flush(&mut image).unwrap().is_empty());
+
+        compositor
+            .process(&ServerPdu::EndFrame(EndFramePdu { frame_id: 1 }))
+            .unwrap();
+
+        assert_eq!(compositor.flush(&mut image).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn surface_to_surface_copies_overlapping_areas() {
+        let mut compositor = compositor_with_surface(4, 1);
+        let mut image = DecodedImage::new(ImagePixelFormat::RgbA32, 4, 1);
+
+        compositor.process(&map_to_output(0, 0)).unwrap();
+        compositor
+            .process(&solid_fill(
+                RED,
+                Rectangle {
+                    left: 0,
+                    top: 0,
+                    right: 1,
+                    bottom: 1,
+                },
+            ))
+            .unwrap();
+        compositor
+            .process(&solid_fill(
+                BLUE,
+                Rectangle {
+                    left: 1,
+                    top: 0,
+                    right: 2,
+                    bottom: 1,
+                },
+            ))
+            .unwrap();
+        compositor
+            .process(&ServerPdu::SurfaceToSurface(SurfaceToSurfacePdu {
+                source_surface_id: 1,
+                destination_surface_id: 1,
+                source_rectangle: Rectangle {
+                    left: 0,
+                    top: 0,
+                    right: 2,
+                    bottom: 1,
+                },
+                destination_points: vec![Point { x: 1, y: 0 }, Point { x: 3, y: 0 }],
+            }))
+            .unwrap();
+        compositor.flush(&mut image).unwrap();
+
+        assert_eq!(pixel(&image, 0, 0), [0xFF, 0x00, 0x00, 0xFF]);
+        assert_eq!(pixel(&image, 1, 0), [0xFF, 0x00, 0x00, 0xFF]);
+        assert_eq!(pixel(&image, 2, 0), [0x00, 0x00, 0xFF, 0xFF]);
+        // Clipped to the surface bounds
+        assert_eq!(pixel(&image, 3, 0), [0xFF, 0x00, 0x00, 0xFF]);
+    }
+
+    #[test]
+    fn cache_entries_are_copied_to_surfaces() {
+        let mut compositor = compositor_with_surface(2, 1);
+        let mut image = DecodedImage::new(ImagePixelFormat::RgbA32, 2, 1);
+
+        compositor.process(&map_to_output(0, 0)).unwrap();
+        compositor
+            .process(&solid_fill(
+                BLUE,
+                Rectangle {
+                    left: 0,
+                    top: 0,
+                    right: 1,
+                    bottom: 1,
+                },
+            ))
+            .unwrap();
+        compositor
+            .process(&ServerPdu::SurfaceToCache(SurfaceToCachePdu {
+                surface_id: 1,
+                cache_key: 0,
+                cache_slot: 1,
+                source_rectangle: Rectangle {
+                    left: 0,
+                    top: 0,
+                    right: 1,
+                    bottom: 1,
+                },
+            }))
+            .unwrap();
+        compositor
+            .process(&ServerPdu::CacheToSurface(CacheToSurfacePdu {
+                cache_slot: 1,
+                surface_id: 1,
+                destination_points: vec![Point { x: 1, y: 0 }],
+            }))
+            .unwrap();
+        compositor.flush(&mut image).unwrap();
+
+        assert_eq!(pixel(&image, 1, 0), [0x00, 0x00, 0xFF, 0xFF]);
+
+        compositor
+            .process(&ServerPdu::EvictCacheEntry(EvictCacheEntryPdu { cache_slot: 1 }))
+            .unwrap();
+
+        assert!(compositor
+            .process(&ServerPdu::CacheToSurface(CacheToSurfacePdu {
+                cache_slot: 1,
+                surface_id: 1,
+                destination_points: vec![Point { x: 0, y: 0 }],
+            }))
+            .is_err());
+    }
+
+    #[test]
+    fn planar_bitmaps_keep_their_alpha_channel() {
+        let mut compositor = GfxCompositor::new(CACHE_SLOTS);
+        let mut image = DecodedImage::new(ImagePixelFormat::RgbA32, 2, 1);
+
+        compositor
+            .process(&ServerPdu::CreateSurface(CreateSurfacePdu {
+                surface_id: 1,
+                width: 2,
+                height: 1,
+                pixel_format: PixelFormat::ARgb,
+            }))
+            .unwrap();
+        compositor.process(&map_to_output(0, 0)).unwrap();
+        compositor
+            .process(&ServerPdu::WireToSurface1(WireToSurface1Pdu {
+                surface_id: 1,
+                codec_id: Codec1Type::Planar,
+                pixel_format: PixelFormat::ARgb,
+                destination_rectangle: Rectangle {
+                    left: 0,
+                    top: 0,
+                    right: 2,
+                    bottom: 1,
+                },
+                // Raw ARGB planes (alpha, red, green, blue) followed by the padding byte
+                bitmap_data: vec![0x00, 0x80, 0xFF, 0x10, 0x20, 0x30, 0x40, 0x50, 0x60, 0x00],
+            }))
+            .unwrap();
+        compositor.flush(&mut image).unwrap();
+
+        assert_eq!(pixel(&image, 0, 0), [0x10, 0x30, 0x50, 0x80]);
+        assert_eq!(pixel(&image, 1, 0), [0x20, 0x40, 0x60, 0xFF]);
+    }
+
+    #[test]
+    fn clear_codec_glyphs_are_cached_per_surface() {
+        let mut compositor = compositor_with_surface(2, 1);
+        let mut image = DecodedImage::new(ImagePixelFormat::RgbA32, 2, 1);
+
+        let clear_codec = |left: u16, bitmap_data: Vec<u8>| {
+            ServerPdu::WireToSurface1(WireToSurface1Pdu {
+                surface_id: 1,
+                codec_id: Codec1Type::ClearCodec,
+                pixel_format: PixelFormat::XRgb,
+                destination_rectangle: Rectangle {
+                    left,
+                    top: 0,
+                    right: left + 1,
+                    bottom: 1,
+                },
+                bitmap_data,
+            })
+        };
+
+        compositor.process(&map_to_output(0, 0)).unwrap();
+        // Glyph 0 made of a single pixel, stored in the cache
+        compositor
+            .process(&clear_codec(
+                0,
+                vec![
+                    0x01, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                    0x10, 0x20, 0x30, 0x01,
+                ],
+            ))
+            .unwrap();
+        // Glyph 0 drawn again from the cache
+        compositor
+            .process(&clear_codec(1, vec![0x03, 0x01, 0x00, 0x00]))
+            .unwrap();
+        compositor.flush(&mut image).unwrap();
+
+        assert_eq!(pixel(&image, 0, 0), [0x30, 0x20, 0x10, 0xFF]);
+        assert_eq!(pixel(&image, 1, 0), [0x30, 0x20, 0x10, 0xFF]);
+    }
+
+    fn progressive_tile(codec_context_id: u32, tile: Tile<'_>) -> ServerPdu {
+        let region = Block::Region(RegionPdu {
+            rectangles: vec![RfxRectangle {
+                x: 0,
+                y: 0,
+                width: 2,
+                height: 2,
+            }],
+            quants: vec![Quant {
+                ll3: 6,
+                lh3: 6,
+                hl3: 6,
+                hh3: 6,
+                lh2: 7,
+                hl2: 7,
+                hh2: 8,
+                lh1: 8,
+                hl1: 8,
+                hh1: 9,
+            }],
+            quality_quants: vec![],
+            flags: RegionFlags::DWT_REDUCE_EXTRAPOLATE,
+            tiles: vec![tile],
+        });
+
+        let mut bitmap_data = Vec::new();
+        for block in [Block::Sync, region] {
+            let start = bitmap_data.len();
+            bitmap_data.resize(start + block.buffer_length(), 0);
+            block.to_buffer_consume(&mut &mut bitmap_data[start..]).unwrap();
+        }
+
+        ServerPdu::WireToSurface2(WireToSurface2Pdu {
+            surface_id: 1,
+            codec_id: Codec2Type::RemoteFxProgressive,
+            codec_context_id,
+            pixel_format: PixelFormat::XRgb,
+            bitmap_data,
+        })
+    }
+
+    const TILE_HEADER: TileHeader = TileHeader {
+        y_quant_index: 0,
+        cb_quant_index: 0,
+        cr_quant_index: 0,
+        x: 0,
+        y: 0,
+    };
+
+    const SIMPLE_TILE: Tile<'static> = Tile::Simple(TileSimple {
+        header: TILE_HEADER,
+        flags: TileFlags::empty(),
+        y_data: &[],
+        cb_data: &[],
+        cr_data: &[],
+        tail_data: &[],
+    });
+
+    const UPGRADE_TILE: Tile<'static> = Tile::Upgrade(TileUpgrade {
+        header: TILE_HEADER,
+        quality: FULL_QUALITY,
+        y_srl_data: &[],
+        y_raw_data: &[],
+        cb_srl_data: &[],
+        cb_raw_data: &[],
+        cr_srl_data: &[],
+        cr_raw_data: &[],
+    });
+
+    #[test]
+    fn progressive_tiles_are_clipped_to_their_region() {
+        let mut compositor = compositor_with_surface(4, 4);
+        let mut image = DecodedImage::new(ImagePixelFormat::RgbA32, 4, 4);
+
+        compositor.process(&map_to_output(0, 0)).unwrap();
+        compositor.process(&progressive_tile(1, SIMPLE_TILE)).unwrap();
+        compositor.flush(&mut image).unwrap();
+
+        // Zero coefficients are decoded to the middle of the color range
+        assert_eq!(pixel(&image, 1, 1), [0x80, 0x80, 0x80, 0xFF]);
+        assert_eq!(pixel(&image, 2, 2), [0x00, 0x00, 0x00, 0xFF]);
+    }
+
+    #[test]
+    fn progressive_tiles_are_forgotten_with_their_encoding_context() {
+        let mut compositor = compositor_with_surface(4, 4);
+
+        compositor.process(&progressive_tile(1, SIMPLE_TILE)).unwrap();
+        compositor.process(&progressive_tile(1, UPGRADE_TILE)).unwrap();
+
+        // The first pass of the tile was decoded in another context
+        assert!(compositor.process(&progressive_tile(2, UPGRADE_TILE)).is_err());
+
+        compositor
+            .process(&ServerPdu::DeleteEncodingContext(DeleteEncodingContextPdu {
+                surface_id: 1,
+                codec_context_id: 1,
+            }))
+            .unwrap();
+
+        assert!(compositor.process(&progressive_tile(1, UPGRADE_TILE)).is_err());
+    }
+
+    /// Decodes every bitstream as the same 16x16 picture.
+    struct UniformPicture {
+        y: u8,
+        u: u8,
+        v: u8,
+    }
+
+    impl H264Decoder for UniformPicture {
+        fn decode(&mut self, _bitstream: &[u8]) -> core::result::Result<Option<YuvFrame>, H264Error> {
+            Ok(Some(YuvFrame {
+                width: 16,
+                height: 16,
+                y: vec![self.y; 16 * 16],
+                u: vec![self.u; 8 * 8],
+                v: vec![self.v; 8 * 8],
+                y_stride: 16,
+                uv_stride: 8,
+            }))
+        }
+    }
+
+    #[test]
+    fn avc420_bitmaps_update_their_metablock_rectangles() {
+        let mut compositor = compositor_with_surface(4, 4);
+
+        let surface = compositor.surfaces.get_mut(&1).unwrap();
+        let decoder = UniformPicture { y: 81, u: 90, v: 240 };
+        surface.avc = Some(AvcDecoder::new(Box::new(decoder), 4, 4));
+        surface.dirty_area = None;
+
+        compositor
+            .process(&ServerPdu::WireToSurface1(WireToSurface1Pdu {
+                surface_id: 1,
+                codec_id: Codec1Type::Avc420,
+                pixel_format: PixelFormat::XRgb,
+                destination_rectangle: Rectangle {
+                    left: 0,
+                    top: 0,
+                    right: 4,
+                    bottom: 4,
+                },
+                bitmap_data: vec![
+                    0x01, 0x00, 0x00, 0x00, // one metablock rectangle
+                    0x01, 0x00, 0x02, 0x00, 0x03, 0x00, 0x03, 0x00, // left, top, right, bottom
+                    0x16, 0x64, // quantization parameter and quality
+                ],
+            }))
+            .unwrap();
+
+        let surface = &compositor.surfaces[&1];
+
+        assert_eq!(
+            surface.dirty_area,
+            Some(Rectangle {
+                left: 1,
+                top: 2,
+                right: 2,
+                bottom: 2,
+            })
+        );
+        assert_eq!(
+            surface.data[(2 * 4 + 1) * 4..(2 * 4 + 3) * 4],
+            [10, 35, 255, 255, 10, 35, 255, 255]
+        );
+        assert_eq!(surface.data[(3 * 4 + 1) * 4..(3 * 4 + 2) * 4], [0, 0, 0, 0xFF]);
+    }
+
+    #[test]
+    fn commands_on_deleted_surfaces_are_rejected() {
+        let mut compositor = compositor_with_surface(2, 2);
+
+        compositor
+            .process(&ServerPdu::DeleteSurface(DeleteSurfacePdu { surface_id: 1 }))
+            .unwrap();
+
+        assert!(compositor
+            .process(&solid_fill(
+                RED,
+                Rectangle {
+                    left: 0,
+                    top: 0,
+                    right: 1,
+                    bottom: 1,
+                },
+            ))
+            .is_err());
+    }
+
+    #[test]
+    fn source_rectangle_must_be_inside_of_the_surface() {
+        let mut compositor = compositor_with_surface(2, 2);
+
+        let result = compositor.process(&ServerPdu::SurfaceToCache(SurfaceToCachePdu {
+            surface_id: 1,
+            cache_key: 0,
+            cache_slot: 1,
+            source_rectangle: Rectangle {
+                left: 0,
+                top: 0,
+                right: 3,
+                bottom: 1,
+            },
+        }));
+
+        assert!(result.is_err());
+    }
+}
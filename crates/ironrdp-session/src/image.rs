@@ -1,6 +1,8 @@
 use ironrdp_graphics::image_processing::{ImageRegion, ImageRegionMut, PixelFormat};
 use ironrdp_graphics::rectangle_processing::Region;
+use ironrdp_pdu::gcc::{Monitor, MonitorFlags};
 use ironrdp_pdu::geometry::Rectangle;
+use ironrdp_pdu::palette::PaletteEntry;
 
 use crate::{Error, Result};
 
@@ -8,11 +10,22 @@ const TILE_SIZE: u16 = 64;
 const SOURCE_PIXEL_FORMAT: PixelFormat = PixelFormat::BgrX32;
 const SOURCE_STRIDE: u16 = TILE_SIZE * SOURCE_PIXEL_FORMAT.bytes_per_pixel() as u16;
 
+/// Area of the image displayed by one of the monitors of the session
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MonitorViewport {
+    /// Inclusive bounds in the image, whose origin is the top-left corner of the virtual desktop
+    pub rectangle: Rectangle,
+    pub is_primary: bool,
+}
+
+/// Image of the virtual desktop, that is the bounding box of all the monitors of the session
 pub struct DecodedImage {
     pixel_format: PixelFormat,
     data: Vec<u8>,
     width: u16,
     height: u16,
+    monitor_layout: Vec<Monitor>,
+    viewports: Vec<MonitorViewport>,
 }
 
 impl DecodedImage {
@@ -24,6 +37,35 @@ impl DecodedImage {
             data: vec![0; len],
             width,
             height,
+            monitor_layout: Vec::new(),
+            viewports: compute_viewports(&[], width, height),
+        }
+    }
+
+    /// Splits the image into one viewport per monitor, as laid out in the virtual desktop.
+    ///
+    /// Monitors are positioned relatively to the primary one, so the viewports are offset by the top-left corner of
+    /// their bounding box. An empty layout stands for a single monitor covering the whole image.
+    pub fn set_monitor_layout(&mut self, monitor_layout: Vec<Monitor>) {
+        self.viewports = compute_viewports(&monitor_layout, self.width, self.height);
+        self.monitor_layout = monitor_layout;
+    }
+
+    pub fn monitor_layout(&self) -> &[Monitor] {
+        &self.monitor_layout
+    }
+
+    pub fn viewports(&self) -> &[MonitorViewport] {
+        &self.viewports
+    }
+
+    /// Region of the image displayed by the given viewport, e.g.: to be copied into the buffer of a monitor window
+    pub fn viewport_region(&self, viewport: &MonitorViewport) -> ImageRegion<'_> {
+        ImageRegion {
+            region: viewport.rectangle.clone(),
+            step: self.width * u16::from(self.pixel_format.bytes_per_pixel()),
+            pixel_format: self.pixel_format,
+            data: &self.data,
         }
     }
 
@@ -86,60 +128,325 @@ impl DecodedImage {
         Ok(())
     }
 
-    // FIXME: this assumes PixelFormat::RgbA32
-    pub(crate) fn apply_rgb16_bitmap(&mut self, rgb16: &[u8], update_rectangle: &Rectangle) {
-        const SRC_COLOR_DEPTH: usize = 2;
-        const DST_COLOR_DEPTH: usize = 4;
+    /// Copies an area of a top-down 32 bpp bitmap whose pixels are stored in blue, green, red and alpha order.
+    ///
+    /// Both rectangles must have the same size and be inside of their respective images.
+    pub(crate) fn apply_bgra32_region(
+        &mut self,
+        bgra32: &[u8],
+        stride: usize,
+        source_rectangle: &Rectangle,
+        update_rectangle: &Rectangle,
+    ) -> Result<()> {
+        const SRC_COLOR_DEPTH: usize = 4;
 
-        let image_width = self.width as usize;
-        let rectangle_width = usize::from(update_rectangle.width());
-        let top = usize::from(update_rectangle.top);
-        let left = usize::from(update_rectangle.left);
+        debug_assert_eq!(source_rectangle.width(), update_rectangle.width());
+        debug_assert_eq!(source_rectangle.height(), update_rectangle.height());
 
-        rgb16
-            .chunks_exact(rectangle_width * SRC_COLOR_DEPTH)
-            .rev()
-            .enumerate()
-            .for_each(|(row_idx, row)| {
-                row.chunks_exact(SRC_COLOR_DEPTH)
-                    .enumerate()
-                    .for_each(|(col_idx, src_pixel)| {
-                        let rgb16_value = u16::from_le_bytes(src_pixel.try_into().unwrap());
-                        let dst_idx = ((top + row_idx) * image_width + left + col_idx) * DST_COLOR_DEPTH;
+        let dst_color_depth = usize::from(self.pixel_format.bytes_per_pixel());
+        let dst_stride = usize::from(self.width) * dst_color_depth;
+        let width = usize::from(update_rectangle.width());
 
-                        self.data[dst_idx] = (((((rgb16_value >> 11) & 0x1f) * 527) + 23) >> 6) as u8;
-                        self.data[dst_idx + 1] = (((((rgb16_value >> 5) & 0x3f) * 259) + 33) >> 6) as u8;
-                        self.data[dst_idx + 2] = ((((rgb16_value & 0x1f) * 527) + 23) >> 6) as u8;
-                        self.data[dst_idx + 3] = 0xff;
-                    })
-            });
+        for row_idx in 0..usize::from(update_rectangle.height()) {
+            let src_start = (usize::from(source_rectangle.top) + row_idx) * stride
+                + usize::from(source_rectangle.left) * SRC_COLOR_DEPTH;
+            let dst_start = (usize::from(update_rectangle.top) + row_idx) * dst_stride
+                + usize::from(update_rectangle.left) * dst_color_depth;
+
+            let src_row = &bgra32[src_start..src_start + width * SRC_COLOR_DEPTH];
+            let dst_row = &mut self.data[dst_start..dst_start + width * dst_color_depth];
+
+            for (src_pixel, dst_pixel) in src_row
+                .chunks_exact(SRC_COLOR_DEPTH)
+                .zip(dst_row.chunks_exact_mut(dst_color_depth))
+            {
+                let color = PixelFormat::BgrA32
+                    .read_color(src_pixel)
+                    .map_err(|e| Error::new("read_color").with_custom(e))?;
+                self.pixel_format
+                    .write_color(color, dst_pixel)
+                    .map_err(|e| Error::new("write_color").with_custom(e))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Applies a bottom-up 8 bpp bitmap, mapping each color index through `palette`.
+    ///
+    /// Indexes which are not part of the palette are rendered black.
+    pub(crate) fn apply_rgb8_bitmap(
+        &mut self,
+        rgb8: &[u8],
+        palette: &[PaletteEntry],
+        stride: usize,
+        update_rectangle: &Rectangle,
+    ) {
+        self.apply_bottom_up_bitmap(rgb8, 1, stride, update_rectangle, |src_pixel| {
+            palette
+                .get(usize::from(src_pixel[0]))
+                .map_or([0, 0, 0], |entry| [entry.red, entry.green, entry.blue])
+        });
+    }
+
+    pub(crate) fn apply_rgb15_bitmap(&mut self, rgb15: &[u8], stride: usize, update_rectangle: &Rectangle) {
+        self.apply_bottom_up_bitmap(rgb15, 2, stride, update_rectangle, |src_pixel| {
+            let rgb15_value = u16::from_le_bytes([src_pixel[0], src_pixel[1]]);
+
+            let expand = |value: u16| -> u8 {
+                let value = (value & 0x1f) as u8;
+                (value << 3) | (value >> 2)
+            };
+
+            [expand(rgb15_value >> 10), expand(rgb15_value >> 5), expand(rgb15_value)]
+        });
+    }
+
+    // FIXME: this assumes PixelFormat::RgbA32
+    pub(crate) fn apply_rgb16_bitmap(&mut self, rgb16: &[u8], stride: usize, update_rectangle: &Rectangle) {
+        self.apply_bottom_up_bitmap(rgb16, 2, stride, update_rectangle, |src_pixel| {
+            let rgb16_value = u16::from_le_bytes([src_pixel[0], src_pixel[1]]);
+
+            [
+                (((((rgb16_value >> 11) & 0x1f) * 527) + 23) >> 6) as u8,
+                (((((rgb16_value >> 5) & 0x3f) * 259) + 33) >> 6) as u8,
+                ((((rgb16_value & 0x1f) * 527) + 23) >> 6) as u8,
+            ]
+        });
     }
 
     // FIXME: this assumes PixelFormat::RgbA32
-    pub(crate) fn apply_rgb24_bitmap(&mut self, rgb24: &[u8], update_rectangle: &Rectangle) {
-        const SRC_COLOR_DEPTH: usize = 3;
+    pub(crate) fn apply_rgb24_bitmap(&mut self, rgb24: &[u8], stride: usize, update_rectangle: &Rectangle) {
+        // Copy RGB channels as is
+        self.apply_bottom_up_bitmap(rgb24, 3, stride, update_rectangle, |src_pixel| {
+            [src_pixel[0], src_pixel[1], src_pixel[2]]
+        });
+    }
+
+    /// Applies a bottom-up 24 bpp bitmap, as found on the wire (blue, green, red).
+    pub(crate) fn apply_bgr24_bitmap(&mut self, bgr24: &[u8], stride: usize, update_rectangle: &Rectangle) {
+        self.apply_bottom_up_bitmap(bgr24, 3, stride, update_rectangle, |src_pixel| {
+            [src_pixel[2], src_pixel[1], src_pixel[0]]
+        });
+    }
+
+    /// Applies a bottom-up 32 bpp bitmap, as found on the wire (blue, green, red, unused).
+    pub(crate) fn apply_bgrx32_bitmap(&mut self, bgrx32: &[u8], stride: usize, update_rectangle: &Rectangle) {
+        self.apply_bottom_up_bitmap(bgrx32, 4, stride, update_rectangle, |src_pixel| {
+            [src_pixel[2], src_pixel[1], src_pixel[0]]
+        });
+    }
+
+    /// Copies the pixels of a bottom-up bitmap into the update rectangle.
+    ///
+    /// `stride` is the length of a source row in bytes, which may include padding or pixels outside of the
+    /// update rectangle. Pixels falling outside of the image are ignored.
+    fn apply_bottom_up_bitmap(
+        &mut self,
+        src: &[u8],
+        src_color_depth: usize,
+        stride: usize,
+        update_rectangle: &Rectangle,
+        to_rgb: impl Fn(&[u8]) -> [u8; 3],
+    ) {
         const DST_COLOR_DEPTH: usize = 4;
 
-        let image_width = self.width as usize;
-        let rectangle_width = usize::from(update_rectangle.width());
+        // The pixels are written as RgbA32
+        debug_assert_eq!(self.pixel_format, PixelFormat::RgbA32);
+
+        if stride == 0 {
+            return;
+        }
+
+        let image_width = usize::from(self.width);
+        let image_height = usize::from(self.height);
         let top = usize::from(update_rectangle.top);
         let left = usize::from(update_rectangle.left);
+        let rectangle_width = usize::from(update_rectangle.width()).min(image_width.saturating_sub(left));
+        let rectangle_height = usize::from(update_rectangle.height()).min(image_height.saturating_sub(top));
 
-        rgb24
-            .chunks_exact(rectangle_width * SRC_COLOR_DEPTH)
+        src.chunks_exact(stride)
             .rev()
+            .take(rectangle_height)
             .enumerate()
             .for_each(|(row_idx, row)| {
-                row.chunks_exact(SRC_COLOR_DEPTH)
+                row.chunks_exact(src_color_depth)
+                    .take(rectangle_width)
                     .enumerate()
                     .for_each(|(col_idx, src_pixel)| {
                         let dst_idx = ((top + row_idx) * image_width + left + col_idx) * DST_COLOR_DEPTH;
 
-                        // Copy RGB channels as is
-                        self.data[dst_idx..dst_idx + SRC_COLOR_DEPTH].copy_from_slice(src_pixel);
+                        self.data[dst_idx..dst_idx + 3].copy_from_slice(&to_rgb(src_pixel));
                         // Set alpha channel to opaque(0xFF)
                         self.data[dst_idx + 3] = 0xFF;
                     })
             });
     }
 }
+
+fn compute_viewports(monitor_layout: &[Monitor], width: u16, height: u16) -> Vec<MonitorViewport> {
+    if width == 0 || height == 0 {
+        return Vec::new();
+    }
+
+    if monitor_layout.is_empty() {
+        return vec![MonitorViewport {
+            rectangle: Rectangle {
+                left: 0,
+                top: 0,
+                right: width - 1,
+                bottom: height - 1,
+            },
+            is_primary: true,
+        }];
+    }
+
+    let origin_left = monitor_layout.iter().map(|monitor| monitor.left).min().unwrap_or(0);
+    let origin_top = monitor_layout.iter().map(|monitor| monitor.top).min().unwrap_or(0);
+
+    // Monitors extending past the image are clipped, those entirely outside of it are ignored
+    let clip = |value: i32, origin: i32, max: u16| {
+        let value = i64::from(value) - i64::from(origin);
+        u16::try_from(value.clamp(0, i64::from(max))).unwrap_or(max)
+    };
+
+    monitor_layout
+        .iter()
+        .filter(|monitor| {
+            i64::from(monitor.left) - i64::from(origin_left) < i64::from(width)
+                && i64::from(monitor.top) - i64::from(origin_top) < i64::from(height)
+                && monitor.left <= monitor.right
+                && monitor.top <= monitor.bottom
+        })
+        .map(|monitor| MonitorViewport {
+            rectangle: Rectangle {
+                left: clip(monitor.left, origin_left, width - 1),
+                top: clip(monitor.top, origin_top, height - 1),
+                right: clip(monitor.right, origin_left, width - 1),
+                bottom: clip(monitor.bottom, origin_top, height - 1),
+            },
+            is_primary: monitor.flags.contains(MonitorFlags::PRIMARY),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor(left: i32, top: i32, right: i32, bottom: i32, flags: MonitorFlags) -> Monitor {
+        Monitor {
+            left,
+            top,
+            right,
+            bottom,
+            flags,
+        }
+    }
+
+    #[test]
+    fn single_viewport_by_default() {
+        let image = DecodedImage::new(PixelFormat::RgbA32, 1920, 1080);
+
+        assert_eq!(
+            image.viewports(),
+            [MonitorViewport {
+                rectangle: Rectangle {
+                    left: 0,
+                    top: 0,
+                    right: 1919,
+                    bottom: 1079,
+                },
+                is_primary: true,
+            }]
+        );
+    }
+
+    #[test]
+    fn viewports_are_relative_to_the_bounding_box() {
+        // A secondary monitor on the left of the primary one, slightly lower
+        let mut image = DecodedImage::new(PixelFormat::RgbA32, 3200, 1200);
+
+        image.set_monitor_layout(vec![
+            monitor(0, 0, 1919, 1079, MonitorFlags::PRIMARY),
+            monitor(-1280, 200, -1, 1199, MonitorFlags::empty()),
+        ]);
+
+        assert_eq!(
+            image.viewports(),
+            [
+                MonitorViewport {
+                    rectangle: Rectangle {
+                        left: 1280,
+                        top: 0,
+                        right: 3199,
+                        bottom: 1079,
+                    },
+                    is_primary: true,
+                },
+                MonitorViewport {
+                    rectangle: Rectangle {
+                        left: 0,
+                        top: 200,
+                        right: 1279,
+                        bottom: 1199,
+                    },
+                    is_primary: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn viewports_are_clipped_to_the_image() {
+        let mut image = DecodedImage::new(PixelFormat::RgbA32, 1000, 500);
+
+        image.set_monitor_layout(vec![
+            monitor(0, 0, 1919, 1079, MonitorFlags::PRIMARY),
+            monitor(1920, 0, 3839, 1079, MonitorFlags::empty()),
+        ]);
+
+        assert_eq!(
+            image.viewports(),
+            [MonitorViewport {
+                rectangle: Rectangle {
+                    left: 0,
+                    top: 0,
+                    right: 999,
+                    bottom: 499,
+                },
+                is_primary: true,
+            }]
+        );
+    }
+
+    #[test]
+    fn viewport_region_copy() {
+        let mut image = DecodedImage::new(PixelFormat::RgbA32, 4, 1);
+        image
+            .data
+            .copy_from_slice(&[0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3]);
+        image.set_monitor_layout(vec![
+            monitor(0, 0, 1, 0, MonitorFlags::PRIMARY),
+            monitor(2, 0, 3, 0, MonitorFlags::empty()),
+        ]);
+
+        let viewport = &image.viewports()[1];
+        let mut output = vec![0; 8];
+        let mut destination = ImageRegionMut {
+            region: Rectangle {
+                left: 0,
+                top: 0,
+                right: 1,
+                bottom: 0,
+            },
+            step: 8,
+            pixel_format: PixelFormat::RgbA32,
+            data: &mut output,
+        };
+
+        image.viewport_region(viewport).copy_to(&mut destination).unwrap();
+
+        assert_eq!(output, [2, 2, 2, 2, 3, 3, 3, 3]);
+    }
+}
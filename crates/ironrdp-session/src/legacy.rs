@@ -1,13 +1,18 @@
-use ironrdp_connector::legacy::{encode_send_data_request, SendDataIndicationCtx};
+use std::borrow::Cow;
+
+use ironrdp_connector::legacy::SendDataIndicationCtx;
+use ironrdp_graphics::bulk;
+use ironrdp_pdu::rdp::client_info::CompressionType;
+use ironrdp_pdu::rdp::headers::CompressionFlags;
 use ironrdp_pdu::rdp::vc;
-use ironrdp_pdu::PduParsing as _;
+use ironrdp_pdu::{mcs, PduParsing as _};
 
 pub fn encode_dvc_message(
     initiator_id: u16,
     drdynvc_id: u16,
     dvc_pdu: vc::dvc::ClientPdu,
     dvc_data: &[u8],
-    mut buf: &mut Vec<u8>,
+    buf: &mut Vec<u8>,
 ) -> crate::Result<usize> {
     let dvc_length = dvc_pdu.buffer_length() + dvc_data.len();
 
@@ -16,19 +21,90 @@ pub fn encode_dvc_message(
         flags: vc::ChannelControlFlags::FLAG_FIRST | vc::ChannelControlFlags::FLAG_LAST,
     };
 
-    // [ TPKT | TPDU | SendDataRequest | vc::ChannelPduHeader | …
-    let written = encode_send_data_request(initiator_id, drdynvc_id, &channel_header, buf)?;
+    // [ vc::ChannelPduHeader | dvc::ClientPdu | DvcData ]
+    let mut user_data = Vec::with_capacity(channel_header.buffer_length() + dvc_length);
+    channel_header.to_buffer(&mut user_data)?;
+    dvc_pdu.to_buffer(&mut user_data)?;
+    user_data.extend_from_slice(dvc_data);
+
+    // [ TPKT | TPDU | SendDataRequest | UserData ]
+    let written = encode_send_data_request(initiator_id, drdynvc_id, user_data, buf)?;
     buf.truncate(written);
 
-    // … | dvc::ClientPdu | …
-    dvc_pdu.to_buffer(&mut buf)?;
+    Ok(written)
+}
 
-    // … | DvcData ]
-    buf.extend_from_slice(dvc_data);
+/// Encodes a message for a static virtual channel.
+///
+/// The message is split into chunks of at most `chunk_length` bytes, each one sent in its own
+/// Virtual Channel PDU. The frames are written one after the other at the beginning of `buf`.
+/// `flags` are added to the chunk flags: `FLAG_SHOW_PROTOCOL` must be set for channels declared with
+/// the `SHOW_PROTOCOL` option.
+pub fn encode_svc_message(
+    initiator_id: u16,
+    channel_id: u16,
+    data: &[u8],
+    flags: vc::ChannelControlFlags,
+    chunk_length: usize,
+    buf: &mut Vec<u8>,
+) -> crate::Result<usize> {
+    let total_length = u32::try_from(data.len()).map_err(|_| {
+        crate::Error::new("static channel message too large").with_reason(format!("{} bytes", data.len()))
+    })?;
+
+    if chunk_length == 0 {
+        return Err(crate::Error::new("invalid static channel chunk length"));
+    }
+
+    let mut chunks = data.chunks(chunk_length).peekable();
+    let mut is_first = true;
+    let mut frame = Vec::new();
+
+    buf.clear();
+
+    // An empty message is still sent as a single chunk
+    while is_first || chunks.peek().is_some() {
+        let chunk = chunks.next().unwrap_or_default();
+
+        let mut chunk_flags = flags;
+        chunk_flags.set(vc::ChannelControlFlags::FLAG_FIRST, is_first);
+        chunk_flags.set(vc::ChannelControlFlags::FLAG_LAST, chunks.peek().is_none());
+
+        let channel_header = vc::ChannelPduHeader {
+            length: total_length,
+            flags: chunk_flags,
+        };
+
+        // [ vc::ChannelPduHeader | Chunk ]
+        let mut user_data = Vec::with_capacity(channel_header.buffer_length() + chunk.len());
+        channel_header.to_buffer(&mut user_data)?;
+        user_data.extend_from_slice(chunk);
+
+        // [ TPKT | TPDU | SendDataRequest | UserData ]
+        let written = encode_send_data_request(initiator_id, channel_id, user_data, &mut frame)?;
+        buf.extend_from_slice(&frame[..written]);
+
+        is_first = false;
+    }
+
+    Ok(buf.len())
+}
+
+fn encode_send_data_request(
+    initiator_id: u16,
+    channel_id: u16,
+    user_data: Vec<u8>,
+    buf: &mut Vec<u8>,
+) -> crate::Result<usize> {
+    let pdu = mcs::SendDataRequest {
+        initiator_id,
+        channel_id,
+        user_data: Cow::Owned(user_data),
+    };
 
-    debug_assert_eq!(buf.len(), written + dvc_length);
+    let written = ironrdp_pdu::encode_buf(&pdu, buf)?;
 
-    Ok(written + dvc_length)
+    Ok(written)
 }
 
 pub struct DynamicChannelCtx<'a> {
@@ -36,23 +112,68 @@ pub struct DynamicChannelCtx<'a> {
     pub dvc_data: &'a [u8],
 }
 
-pub fn decode_dvc_message(ctx: SendDataIndicationCtx<'_>) -> crate::Result<DynamicChannelCtx<'_>> {
+/// Decodes a DVC PDU, decompressing it with the virtual channel history when needed.
+///
+/// `decompressed` holds the decompressed data the returned context borrows from.
+pub fn decode_dvc_message<'a>(
+    ctx: SendDataIndicationCtx<'a>,
+    vc_decompressor: &mut bulk::Decompressor,
+    decompressed: &'a mut Vec<u8>,
+) -> crate::Result<DynamicChannelCtx<'a>> {
     let mut user_data = ctx.user_data;
-    let user_data_len = user_data.len();
 
     // [ vc::ChannelPduHeader | …
     let channel_header = vc::ChannelPduHeader::from_buffer(&mut user_data)?;
-    debug_assert_eq!(user_data_len, channel_header.length as usize);
+
+    let mut dvc_message = decompress_vc_chunk(channel_header.flags, user_data, vc_decompressor, decompressed)?;
+    let dvc_message_len = dvc_message.len();
+    debug_assert_eq!(dvc_message_len, channel_header.length as usize);
 
     // … | dvc::ServerPdu | …
-    let dvc_pdu = vc::dvc::ServerPdu::from_buffer(&mut user_data, user_data_len)?;
+    let dvc_pdu = vc::dvc::ServerPdu::from_buffer(&mut dvc_message, dvc_message_len)?;
 
     // … | DvcData ]
-    let dvc_data = user_data;
+    let dvc_data = dvc_message;
 
     Ok(DynamicChannelCtx { dvc_pdu, dvc_data })
 }
 
+/// Returns the data of a Virtual Channel PDU chunk, decompressed if needed.
+///
+/// The virtual channels share a history which is separate from the one of the slow-path and fast-path PDUs.
+/// The compression flags are processed even for uncompressed chunks, as they may flush the history.
+pub fn decompress_vc_chunk<'a>(
+    flags: vc::ChannelControlFlags,
+    chunk: &'a [u8],
+    vc_decompressor: &mut bulk::Decompressor,
+    decompressed: &'a mut Vec<u8>,
+) -> crate::Result<&'a [u8]> {
+    // The compression flags and type are the high bits of the channel flags
+    let compression_bits = flags.bits() >> 16;
+    let compression_flags = CompressionFlags::from_bits_truncate(compression_bits as u8);
+
+    if compression_flags.is_empty() {
+        return Ok(chunk);
+    }
+
+    let compression_type = match compression_bits & 0x0F {
+        0 => CompressionType::K8,
+        1 => CompressionType::K64,
+        2 => CompressionType::Rdp6,
+        3 => CompressionType::Rdp61,
+        unknown => {
+            return Err(crate::Error::new("invalid virtual channel compression type").with_reason(unknown.to_string()))
+        }
+    };
+
+    decompressed.clear();
+    vc_decompressor
+        .decompress(chunk, compression_type, compression_flags, decompressed)
+        .map_err(|e| crate::Error::new("failed to decompress the virtual channel data").with_custom(e))?;
+
+    Ok(decompressed.as_slice())
+}
+
 impl From<ironrdp_pdu::rdp::vc::ChannelError> for crate::Error {
     fn from(e: ironrdp_pdu::rdp::vc::ChannelError) -> Self {
         Self::new("virtual channel error").with_custom(e)
@@ -78,6 +199,20 @@ impl From<ironrdp_connector::Error> for crate::Error {
     }
 }
 
+impl From<crate::Error> for ironrdp_connector::Error {
+    fn from(value: crate::Error) -> Self {
+        Self {
+            context: value.context,
+            kind: match value.kind {
+                crate::ErrorKind::Pdu(e) => ironrdp_connector::ErrorKind::Pdu(e),
+                crate::ErrorKind::Custom(e) => ironrdp_connector::ErrorKind::Custom(e),
+                crate::ErrorKind::General => ironrdp_connector::ErrorKind::General,
+            },
+            reason: value.reason,
+        }
+    }
+}
+
 impl From<ironrdp_pdu::fast_path::FastPathError> for crate::Error {
     fn from(e: ironrdp_pdu::fast_path::FastPathError) -> Self {
         Self::new("Fast-Path").with_custom(e)
@@ -90,12 +225,30 @@ impl From<ironrdp_pdu::codecs::rfx::RfxError> for crate::Error {
     }
 }
 
+impl From<ironrdp_pdu::rdp::vc::cliprdr::ClipboardError> for crate::Error {
+    fn from(e: ironrdp_pdu::rdp::vc::cliprdr::ClipboardError) -> Self {
+        Self::new("clipboard").with_custom(e)
+    }
+}
+
+impl From<ironrdp_pdu::rdp::vc::rdpsnd::RdpsndError> for crate::Error {
+    fn from(e: ironrdp_pdu::rdp::vc::rdpsnd::RdpsndError) -> Self {
+        Self::new("audio output").with_custom(e)
+    }
+}
+
 impl From<ironrdp_pdu::dvc::display::DisplayPipelineError> for crate::Error {
     fn from(e: ironrdp_pdu::dvc::display::DisplayPipelineError) -> Self {
         Self::new("display pipeline").with_custom(e)
     }
 }
 
+impl From<ironrdp_pdu::dvc::rdpei::RdpeiError> for crate::Error {
+    fn from(e: ironrdp_pdu::dvc::rdpei::RdpeiError) -> Self {
+        Self::new("input extension").with_custom(e)
+    }
+}
+
 impl From<ironrdp_graphics::zgfx::ZgfxError> for crate::Error {
     fn from(e: ironrdp_graphics::zgfx::ZgfxError) -> Self {
         Self::new("zgfx").with_reason(e.to_string())
@@ -107,3 +260,128 @@ impl From<ironrdp_pdu::dvc::gfx::GraphicsPipelineError> for crate::Error {
         Self::new("graphics pipeline").with_reason(e.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the Channel PDU Header and the chunk of each frame
+    fn decode_svc_frames(mut frames: &[u8]) -> Vec<(vc::ChannelPduHeader, Vec<u8>)> {
+        let mut chunks = Vec::new();
+
+        while !frames.is_empty() {
+            let length = ironrdp_pdu::find_size(frames).unwrap().unwrap().length;
+            let (frame, rest) = frames.split_at(length);
+
+            let request = ironrdp_pdu::decode::<mcs::SendDataRequest<'_>>(frame).unwrap();
+            assert_eq!((request.initiator_id, request.channel_id), (1007, 1004));
+
+            let mut user_data = request.user_data.as_ref();
+            let channel_header = vc::ChannelPduHeader::from_buffer(&mut user_data).unwrap();
+            chunks.push((channel_header, user_data.to_vec()));
+
+            frames = rest;
+        }
+
+        chunks
+    }
+
+    #[test]
+    fn svc_message_is_split_at_the_chunk_length() {
+        let mut buf = Vec::new();
+
+        let written = encode_svc_message(
+            1007,
+            1004,
+            b"0123456789",
+            vc::ChannelControlFlags::FLAG_SHOW_PROTOCOL,
+            4,
+            &mut buf,
+        )
+        .unwrap();
+
+        let chunks = decode_svc_frames(&buf[..written]);
+
+        let flags = |flags| vc::ChannelControlFlags::FLAG_SHOW_PROTOCOL | flags;
+        assert_eq!(
+            chunks,
+            [
+                (
+                    vc::ChannelPduHeader {
+                        length: 10,
+                        flags: flags(vc::ChannelControlFlags::FLAG_FIRST),
+                    },
+                    b"0123".to_vec()
+                ),
+                (
+                    vc::ChannelPduHeader {
+                        length: 10,
+                        flags: flags(vc::ChannelControlFlags::empty()),
+                    },
+                    b"4567".to_vec()
+                ),
+                (
+                    vc::ChannelPduHeader {
+                        length: 10,
+                        flags: flags(vc::ChannelControlFlags::FLAG_LAST),
+                    },
+                    b"89".to_vec()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn svc_message_of_the_chunk_length_is_sent_in_one_chunk() {
+        let mut buf = Vec::new();
+
+        let written = encode_svc_message(1007, 1004, b"0123", vc::ChannelControlFlags::empty(), 4, &mut buf).unwrap();
+
+        let chunks = decode_svc_frames(&buf[..written]);
+
+        assert_eq!(
+            chunks,
+            [(
+                vc::ChannelPduHeader {
+                    length: 4,
+                    flags: vc::ChannelControlFlags::FLAG_FIRST | vc::ChannelControlFlags::FLAG_LAST,
+                },
+                b"0123".to_vec()
+            )]
+        );
+    }
+
+    #[test]
+    fn empty_svc_message_is_sent_in_one_chunk() {
+        let mut buf = Vec::new();
+
+        let written = encode_svc_message(1007, 1004, &[], vc::ChannelControlFlags::empty(), 4, &mut buf).unwrap();
+
+        let chunks = decode_svc_frames(&buf[..written]);
+
+        assert_eq!(
+            chunks,
+            [(
+                vc::ChannelPduHeader {
+                    length: 0,
+                    flags: vc::ChannelControlFlags::FLAG_FIRST | vc::ChannelControlFlags::FLAG_LAST,
+                },
+                Vec::new()
+            )]
+        );
+    }
+
+    #[test]
+    fn zero_chunk_length_is_rejected() {
+        let result = encode_svc_message(
+            1007,
+            1004,
+            b"data",
+            vc::ChannelControlFlags::empty(),
+            0,
+            &mut Vec::new(),
+        );
+
+        assert!(result.is_err());
+    }
+}
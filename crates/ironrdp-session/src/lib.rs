@@ -1,18 +1,23 @@
 #[macro_use]
 extern crate tracing;
 
+pub mod audio;
+pub mod clipboard;
 pub mod image;
 pub mod legacy;
 
 mod active_stage;
 mod fast_path;
+mod gfx;
 mod rfx;
+mod rfx_progressive;
 mod utils;
 mod x224;
 
 use core::fmt;
 
 pub use active_stage::{ActiveStage, ActiveStageOutput};
+pub use x224::{DynamicVirtualChannel, GfxHandler, StaticVirtualChannel, MAX_TOUCH_CONTACTS};
 
 pub type Result<T> = std::result::Result<T, Error>;
 
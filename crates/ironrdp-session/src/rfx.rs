@@ -223,8 +223,8 @@ fn tiles_to_rectangles<'a>(tiles: &'a [Tile<'_>], destination: &'a Rectangle) ->
     tiles.iter().map(|t| Rectangle {
         left: destination.left + t.x * TILE_SIZE,
         top: destination.top + t.y * TILE_SIZE,
-        right: destination.left + t.x * TILE_SIZE + TILE_SIZE,
-        bottom: destination.top + t.y * TILE_SIZE + TILE_SIZE,
+        right: destination.left + t.x * TILE_SIZE + TILE_SIZE - 1,
+        bottom: destination.top + t.y * TILE_SIZE + TILE_SIZE - 1,
     })
 }
 
@@ -255,6 +255,7 @@ enum SequenceState {
 #[cfg(test)]
 mod tests {
     use ironrdp_graphics::image_processing::PixelFormat;
+    use ironrdp_graphics::rfx_encoder::{BgraImage, RfxEncoder};
 
     use super::*;
     use crate::image::DecodedImage;
@@ -287,6 +288,80 @@ mod tests {
         assert_eq!(expected, image.data());
     }
 
+    #[test]
+    fn decode_restores_frames_from_the_rfx_encoder() {
+        const WIDTH: u16 = 128;
+        const HEIGHT: u16 = 64;
+
+        let pixels = (0..HEIGHT)
+            .flat_map(|y| (0..WIDTH).map(move |x| (x, y)))
+            .flat_map(|(x, y)| [(x * 2) as u8, (y * 4) as u8, (x + y) as u8, 0xFF])
+            .collect::<Vec<_>>();
+        let quant = Quant {
+            ll3: 6,
+            lh3: 6,
+            hl3: 6,
+            hh3: 6,
+            lh2: 6,
+            hl2: 6,
+            hh2: 6,
+            lh1: 6,
+            hl1: 6,
+            hh1: 6,
+        };
+
+        for entropy_algorithm in [EntropyAlgorithm::Rlgr1, EntropyAlgorithm::Rlgr3] {
+            let mut encoder = RfxEncoder::new(entropy_algorithm, quant.clone());
+            let mut encoded = Vec::new();
+            encoder
+                .encode_headers(WIDTH, HEIGHT, rfx::OperatingMode::IMAGE_MODE, &mut encoded)
+                .unwrap();
+            encoder
+                .encode_frame(
+                    7,
+                    BgraImage {
+                        data: &pixels,
+                        width: WIDTH,
+                        height: HEIGHT,
+                        stride: usize::from(WIDTH) * 4,
+                    },
+                    &[RfxRectangle {
+                        x: 0,
+                        y: 0,
+                        width: WIDTH,
+                        height: HEIGHT,
+                    }],
+                    &mut encoded,
+                )
+                .unwrap();
+
+            let destination = Rectangle {
+                left: 0,
+                top: 0,
+                right: WIDTH - 1,
+                bottom: HEIGHT - 1,
+            };
+            let mut image = DecodedImage::new(PixelFormat::BgrX32, WIDTH, HEIGHT);
+
+            let (frame_id, _) = DecodingContext::new()
+                .decode(&mut image, &destination, &mut encoded.as_slice())
+                .unwrap();
+
+            assert_eq!(frame_id, 7);
+            let max_difference = image
+                .data()
+                .chunks_exact(4)
+                .zip(pixels.chunks_exact(4))
+                .flat_map(|(actual, expected)| actual[..3].iter().zip(&expected[..3]))
+                .map(|(actual, expected)| actual.abs_diff(*expected))
+                .max();
+            assert!(
+                matches!(max_difference, Some(difference) if difference <= 4),
+                "{max_difference:?}"
+            );
+        }
+    }
+
     const ENCODED_MESSAGES: [u8; 2970] = [
         /* HEADERS as in 4.2.2 */
         0xc0, 0xcc, 0x0c, 0x00, 0x00, 0x00, 0xca, 0xac, 0xcc, 0xca, 0x00, 0x01, 0xc3, 0xcc, 0x0d, 0x00, 0x00, 0x00,
@@ -0,0 +1,263 @@
+use std::collections::HashMap;
+
+use ironrdp_graphics::color_conversion::{self, YCbCrBuffer};
+use ironrdp_graphics::rfx_progressive::{ComponentState, COEFFICIENTS_COUNT};
+use ironrdp_pdu::codecs::rfx::{Quant, RfxRectangle};
+use ironrdp_pdu::codecs::rfx_progressive::{
+    Block, QualityQuant, RegionFlags, RegionPdu, Tile, TileFirst, TileFlags, TileHeader, TileSimple, FULL_QUALITY,
+};
+use ironrdp_pdu::PduBufferParsing;
+
+use crate::{Error, Result};
+
+const TILE_SIZE: usize = 64;
+const BYTES_PER_PIXEL: usize = 4;
+
+/// Quality quantization values of the simple tiles and of the last pass of progressive tiles
+const NO_QUALITY_QUANT: Quant = Quant {
+    ll3: 0,
+    lh3: 0,
+    hl3: 0,
+    hh3: 0,
+    lh2: 0,
+    hl2: 0,
+    hh2: 0,
+    lh1: 0,
+    hl1: 0,
+    hh1: 0,
+};
+
+/// Part of a decoded tile visible through the rectangles of its region, in surface coordinates
+#[derive(Debug, Clone)]
+pub(crate) struct TileUpdate {
+    pub(crate) left: u16,
+    pub(crate) top: u16,
+    pub(crate) width: usize,
+    pub(crate) height: usize,
+    /// BGRA pixels, one row after the other
+    pub(crate) data: Vec<u8>,
+}
+
+/// Decoding state of a RemoteFX Progressive codec context
+///
+/// The coefficients of the tiles are kept between the bitmaps, so that the next passes can upgrade them. A context
+/// lives until the server deletes it with the Delete Encoding Context PDU.
+#[derive(Debug, Clone)]
+pub(crate) struct DecodingContext {
+    tiles: HashMap<(u16, u16), TileState>,
+    coefficients: Vec<Vec<i16>>,
+    temp: Vec<i16>,
+    tile_output: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct TileState {
+    components: [ComponentState; 3],
+}
+
+impl Default for DecodingContext {
+    fn default() -> Self {
+        Self {
+            tiles: HashMap::new(),
+            coefficients: vec![vec![0; COEFFICIENTS_COUNT]; 3],
+            temp: vec![0; COEFFICIENTS_COUNT],
+            tile_output: vec![0; TILE_SIZE * TILE_SIZE * BYTES_PER_PIXEL],
+        }
+    }
+}
+
+impl DecodingContext {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decodes the blocks of a bitmap, and returns the updated parts of the tiles.
+    pub(crate) fn decode(&mut self, mut input: &[u8]) -> Result<Vec<TileUpdate>> {
+        let mut updates = Vec::new();
+
+        while !input.is_empty() {
+            let block = Block::from_buffer_consume(&mut input)
+                .map_err(|e| Error::new("invalid RemoteFX Progressive block").with_custom(e))?;
+
+            match block {
+                Block::Sync | Block::FrameEnd => (),
+                Block::FrameBegin(frame_begin) => {
+                    debug!(frame_index = frame_begin.frame_index);
+                }
+                Block::Context(context) => {
+                    // subband diffing only changes how the server encodes the coefficients
+                    trace!(?context);
+                }
+                Block::Region(region) => self.decode_region(&region, &mut updates)?,
+            }
+        }
+
+        Ok(updates)
+    }
+
+    fn decode_region(&mut self, region: &RegionPdu<'_>, updates: &mut Vec<TileUpdate>) -> Result<()> {
+        let extrapolate = region.flags.contains(RegionFlags::DWT_REDUCE_EXTRAPOLATE);
+
+        for tile in &region.tiles {
+            let (header, quality) = match tile {
+                Tile::Simple(tile) => (&tile.header, FULL_QUALITY),
+                Tile::First(tile) => (&tile.header, tile.quality),
+                Tile::Upgrade(tile) => (&tile.header, tile.quality),
+            };
+
+            let quants = [
+                quant(region, header.y_quant_index)?,
+                quant(region, header.cb_quant_index)?,
+                quant(region, header.cr_quant_index)?,
+            ];
+            let quality_quants = quality_quants(region, quality)?;
+
+            let state = self.tiles.entry((header.x, header.y)).or_default();
+
+            match tile {
+                Tile::Simple(TileSimple {
+                    flags,
+                    y_data,
+                    cb_data,
+                    cr_data,
+                    ..
+                })
+                | Tile::First(TileFirst {
+                    flags,
+                    y_data,
+                    cb_data,
+                    cr_data,
+                    ..
+                }) => {
+                    let difference = flags.contains(TileFlags::DIFFERENCE);
+                    let data = [*y_data, *cb_data, *cr_data];
+
+                    for ((((component, data), quant), quality_quant), coefficients) in state
+                        .components
+                        .iter_mut()
+                        .zip(data)
+                        .zip(quants)
+                        .zip(quality_quants)
+                        .zip(self.coefficients.iter_mut())
+                    {
+                        component
+                            .decode_first_pass(
+                                data,
+                                quant,
+                                quality_quant,
+                                difference,
+                                extrapolate,
+                                coefficients,
+                                &mut self.temp,
+                            )
+                            .map_err(|e| Error::new("failed to decode tile first pass").with_custom(e))?;
+                    }
+                }
+                Tile::Upgrade(tile) => {
+                    let data = [
+                        (tile.y_srl_data, tile.y_raw_data),
+                        (tile.cb_srl_data, tile.cb_raw_data),
+                        (tile.cr_srl_data, tile.cr_raw_data),
+                    ];
+
+                    for ((((component, (srl_data, raw_data)), quant), quality_quant), coefficients) in state
+                        .components
+                        .iter_mut()
+                        .zip(data)
+                        .zip(quants)
+                        .zip(quality_quants)
+                        .zip(self.coefficients.iter_mut())
+                    {
+                        component
+                            .decode_upgrade_pass(
+                                srl_data,
+                                raw_data,
+                                quant,
+                                quality_quant,
+                                extrapolate,
+                                coefficients,
+                                &mut self.temp,
+                            )
+                            .map_err(|e| Error::new("failed to decode tile upgrade pass").with_custom(e))?;
+                    }
+                }
+            }
+
+            let ycbcr_buffer = YCbCrBuffer {
+                y: &self.coefficients[0],
+                cb: &self.coefficients[1],
+                cr: &self.coefficients[2],
+            };
+
+            color_conversion::ycbcr_to_bgra(ycbcr_buffer, &mut self.tile_output)
+                .map_err(|e| Error::new("failed to convert tile colors").with_custom(e))?;
+
+            clip_tile(&self.tile_output, header, &region.rectangles, updates);
+        }
+
+        Ok(())
+    }
+}
+
+fn quant<'a>(region: &'a RegionPdu<'_>, index: u8) -> Result<&'a Quant> {
+    region
+        .quants
+        .get(usize::from(index))
+        .ok_or_else(|| Error::new("invalid tile quantization index").with_reason(index.to_string()))
+}
+
+/// Returns the quality quantization values of the Y, Cb and Cr components for the given quality.
+fn quality_quants<'a>(region: &'a RegionPdu<'_>, quality: u8) -> Result<[&'a Quant; 3]> {
+    if quality == FULL_QUALITY {
+        return Ok([&NO_QUALITY_QUANT; 3]);
+    }
+
+    region
+        .quality_quants
+        .get(usize::from(quality))
+        .map(|QualityQuant { y, cb, cr, .. }| [y, cb, cr])
+        .ok_or_else(|| Error::new("invalid tile quality").with_reason(quality.to_string()))
+}
+
+/// Extracts the parts of the tile covered by the region rectangles.
+fn clip_tile(tile_output: &[u8], header: &TileHeader, rectangles: &[RfxRectangle], updates: &mut Vec<TileUpdate>) {
+    let tile_left = usize::from(header.x) * TILE_SIZE;
+    let tile_top = usize::from(header.y) * TILE_SIZE;
+
+    for rectangle in rectangles {
+        let left = tile_left.max(usize::from(rectangle.x));
+        let top = tile_top.max(usize::from(rectangle.y));
+        let right = (tile_left + TILE_SIZE).min(usize::from(rectangle.x) + usize::from(rectangle.width));
+        let bottom = (tile_top + TILE_SIZE).min(usize::from(rectangle.y) + usize::from(rectangle.height));
+
+        if left >= right || top >= bottom {
+            continue;
+        }
+
+        // the tile is out of any surface
+        let (Ok(update_left), Ok(update_top)) = (u16::try_from(left), u16::try_from(top)) else {
+            continue;
+        };
+
+        let width = right - left;
+        let height = bottom - top;
+        let mut data = Vec::with_capacity(width * height * BYTES_PER_PIXEL);
+
+        for row in tile_output
+            .chunks_exact(TILE_SIZE * BYTES_PER_PIXEL)
+            .skip(top - tile_top)
+            .take(height)
+        {
+            let start = (left - tile_left) * BYTES_PER_PIXEL;
+            data.extend_from_slice(&row[start..start + width * BYTES_PER_PIXEL]);
+        }
+
+        updates.push(TileUpdate {
+            left: update_left,
+            top: update_top,
+            width,
+            height,
+            data,
+        });
+    }
+}
@@ -1,15 +1,136 @@
-use ironrdp_pdu::dvc::display::ServerPdu;
+use core::any::Any;
+
+use ironrdp_pdu::dvc::display::{
+    ClientPdu, DisplayControlCapsPdu, Monitor, MonitorFlags, MonitorLayoutPdu, Orientation, ServerPdu,
+};
 use ironrdp_pdu::PduParsing;
 
-use super::DynamicChannelDataHandler;
-use crate::Result;
+use super::{DynamicVirtualChannel, RDP8_DISPLAY_PIPELINE_NAME};
+use crate::{Error, Result};
+
+// https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpedisp/ea2de591-9203-42cd-9908-be7a55237d1c
+const MIN_MONITOR_SIZE: u32 = 200;
+const MAX_MONITOR_SIZE: u32 = 8192;
+
+const MIN_DESKTOP_SCALE_FACTOR: u32 = 100;
+const MAX_DESKTOP_SCALE_FACTOR: u32 = 500;
+
+#[derive(Default)]
+pub struct Handler {
+    capabilities: Option<DisplayControlCapsPdu>,
+}
+
+impl Handler {
+    /// Capabilities advertised by the server, if already received.
+    pub fn capabilities(&self) -> Option<&DisplayControlCapsPdu> {
+        self.capabilities.as_ref()
+    }
+
+    /// Builds a DISPLAYCONTROL_MONITOR_LAYOUT_PDU requesting a single primary monitor of the given size.
+    ///
+    /// The width is rounded down to an even value and both dimensions are clamped to the range allowed by
+    /// [MS-RDPEDISP]. The request is validated against the capabilities advertised by the server.
+    pub fn encode_monitor_layout(
+        &self,
+        width: u32,
+        height: u32,
+        scale_factor: Option<u32>,
+        physical_dims: Option<(u32, u32)>,
+    ) -> Result<Vec<u8>> {
+        let capabilities = self
+            .capabilities
+            .as_ref()
+            .ok_or(Error::new("display control capabilities not received yet"))?;
+
+        let (width, height) = adjust_display_size(width, height);
+
+        if capabilities.max_num_monitors == 0 {
+            return Err(Error::new("server does not accept any monitor"));
+        }
+
+        let max_area = u64::from(capabilities.max_monitor_area_factora)
+            * u64::from(capabilities.max_monitor_area_factorb)
+            * u64::from(capabilities.max_num_monitors);
+
+        if u64::from(width) * u64::from(height) > max_area {
+            return Err(Error::new("requested monitor area exceeds the server limit")
+                .with_reason(format!("{width}x{height} > {max_area} pixels")));
+        }
+
+        let desktop_scale_factor = scale_factor
+            .unwrap_or(MIN_DESKTOP_SCALE_FACTOR)
+            .clamp(MIN_DESKTOP_SCALE_FACTOR, MAX_DESKTOP_SCALE_FACTOR);
+
+        let (physical_width, physical_height) = physical_dims.unwrap_or((0, 0));
+
+        let pdu = ClientPdu::DisplayControlMonitorLayout(MonitorLayoutPdu {
+            monitors: vec![Monitor {
+                flags: MonitorFlags::PRIMARY,
+                left: 0,
+                top: 0,
+                width,
+                height,
+                physical_width,
+                physical_height,
+                orientation: Orientation::Landscape,
+                desktop_scale_factor,
+                device_scale_factor: device_scale_factor(desktop_scale_factor),
+            }],
+        });
+
+        debug!(?pdu, "Send Display PDU");
+
+        let mut buf = Vec::with_capacity(pdu.buffer_length());
+        pdu.to_buffer(&mut buf)?;
 
-pub struct Handler;
+        Ok(buf)
+    }
+}
+
+impl DynamicVirtualChannel for Handler {
+    fn channel_name(&self) -> &str {
+        RDP8_DISPLAY_PIPELINE_NAME
+    }
+
+    fn process(&mut self, mut complete_data: &[u8]) -> Result<Vec<Vec<u8>>> {
+        let display_pdu = ServerPdu::from_buffer(&mut complete_data)?;
+        debug!("Got Display PDU: {:?}", display_pdu);
+
+        match display_pdu {
+            ServerPdu::DisplayControlCaps(capabilities) => self.capabilities = Some(capabilities),
+        }
+
+        Ok(Vec::new())
+    }
+
+    fn close(&mut self) {
+        // The server advertises its capabilities again when the channel is reopened
+        self.capabilities = None;
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Adjusts the requested size so that it satisfies the [MS-RDPEDISP] constraints.
+///
+/// The width must be even and both dimensions must be between 200 and 8192 pixels.
+pub fn adjust_display_size(width: u32, height: u32) -> (u32, u32) {
+    let width = width.clamp(MIN_MONITOR_SIZE, MAX_MONITOR_SIZE) & !1;
+    let height = height.clamp(MIN_MONITOR_SIZE, MAX_MONITOR_SIZE);
+    (width, height)
+}
 
-impl DynamicChannelDataHandler for Handler {
-    fn process_complete_data(&mut self, complete_data: Vec<u8>) -> Result<Option<Vec<u8>>> {
-        let gfx_pdu = ServerPdu::from_buffer(&mut complete_data.as_slice())?;
-        debug!("Got Display PDU: {:?}", gfx_pdu);
-        Ok(None)
+/// Picks the closest device scale factor allowed by the specification (100, 140 or 180).
+fn device_scale_factor(desktop_scale_factor: u32) -> u32 {
+    match desktop_scale_factor {
+        0..=119 => 100,
+        120..=159 => 140,
+        _ => 180,
     }
 }
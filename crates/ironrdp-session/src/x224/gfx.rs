@@ -1,14 +1,19 @@
+use core::any::Any;
+
 use bitflags::bitflags;
-use ironrdp_connector::GraphicsConfig;
+use ironrdp_connector::{DesktopSize, GraphicsConfig};
 use ironrdp_graphics::zgfx;
 use ironrdp_pdu::dvc::gfx::{
     CapabilitiesAdvertisePdu, CapabilitiesV103Flags, CapabilitiesV104Flags, CapabilitiesV107Flags,
     CapabilitiesV10Flags, CapabilitiesV81Flags, CapabilitiesV8Flags, CapabilitySet, ClientPdu, FrameAcknowledgePdu,
     QueueDepth, ServerPdu,
 };
+use ironrdp_pdu::geometry::Rectangle;
 use ironrdp_pdu::PduParsing;
 
-use crate::x224::DynamicChannelDataHandler;
+use crate::gfx::{GfxCompositor, CACHE_SLOTS, SMALL_CACHE_SLOTS};
+use crate::image::DecodedImage;
+use crate::x224::{DynamicVirtualChannel, RDP8_GRAPHICS_PIPELINE_NAME};
 use crate::{Error, Result};
 
 pub trait GfxHandler {
@@ -20,31 +25,75 @@ pub struct Handler {
     decompressed_buffer: Vec<u8>,
     frames_decoded: u32,
     gfx_handler: Option<Box<dyn GfxHandler + Send>>,
+    /// Renders the graphics pipeline in software when no handler is provided by the application
+    compositor: Option<GfxCompositor>,
+    graphics_config: Option<GraphicsConfig>,
+    reset_graphics: Option<DesktopSize>,
 }
 
 impl Handler {
-    pub fn new(gfx_handler: Option<Box<dyn GfxHandler + Send>>) -> Self {
+    pub fn new(gfx_handler: Option<Box<dyn GfxHandler + Send>>, graphics_config: Option<GraphicsConfig>) -> Self {
+        let compositor = gfx_handler.is_none().then(|| {
+            let small_cache = matches!(graphics_config, Some(GraphicsConfig { small_cache: true, .. }));
+            GfxCompositor::new(if small_cache { SMALL_CACHE_SLOTS } else { CACHE_SLOTS })
+        });
+
         Self {
             decompressor: zgfx::Decompressor::new(),
             decompressed_buffer: Vec::with_capacity(1024 * 16),
             frames_decoded: 0,
             gfx_handler,
+            compositor,
+            graphics_config,
+            reset_graphics: None,
+        }
+    }
+
+    /// Returns the desktop size requested by the last Reset Graphics PDU, if any was received since the last call.
+    pub fn take_reset_graphics(&mut self) -> Option<DesktopSize> {
+        self.reset_graphics.take()
+    }
+
+    /// Copies the graphics updates completed since the last call to the image, when rendering in software.
+    ///
+    /// Returns the updated regions of the image.
+    pub fn flush(&mut self, image: &mut DecodedImage) -> Result<Vec<Rectangle>> {
+        match self.compositor.as_mut() {
+            Some(compositor) => compositor.flush(image),
+            None => Ok(Vec::new()),
         }
     }
 }
 
-impl DynamicChannelDataHandler for Handler {
-    fn process_complete_data(&mut self, complete_data: Vec<u8>) -> Result<Option<Vec<u8>>> {
+impl DynamicVirtualChannel for Handler {
+    fn channel_name(&self) -> &str {
+        RDP8_GRAPHICS_PIPELINE_NAME
+    }
+
+    fn start(&mut self) -> Result<Vec<Vec<u8>>> {
+        debug!("Send GFX Capabilities Advertise PDU");
+        let capabilities_advertise = create_capabilities_advertise(&self.graphics_config)?;
+
+        Ok(vec![capabilities_advertise])
+    }
+
+    fn process(&mut self, complete_data: &[u8]) -> Result<Vec<Vec<u8>>> {
         let mut client_pdu_buffer: Vec<u8> = vec![];
         self.decompressed_buffer.clear();
         self.decompressor
-            .decompress(complete_data.as_slice(), &mut self.decompressed_buffer)?;
+            .decompress(complete_data, &mut self.decompressed_buffer)?;
         let mut slice = &mut self.decompressed_buffer.as_slice();
         while !slice.is_empty() {
             let gfx_pdu = ServerPdu::from_buffer(&mut slice)?;
             debug!("Got GFX PDU: {:?}", gfx_pdu);
 
-            if let ServerPdu::EndFrame(end_frame_pdu) = &gfx_pdu {
+            if let ServerPdu::ResetGraphics(reset_graphics_pdu) = &gfx_pdu {
+                // The width and height are bounded by MAX_RESET_GRAPHICS_WIDTH_HEIGHT when decoding
+                self.reset_graphics = Some(DesktopSize {
+                    width: u16::try_from(reset_graphics_pdu.width).expect("valid Reset Graphics width"),
+                    height: u16::try_from(reset_graphics_pdu.height).expect("valid Reset Graphics height"),
+                });
+            } else if let ServerPdu::EndFrame(end_frame_pdu) = &gfx_pdu {
                 self.frames_decoded += 1;
                 // Enqueue an acknowledge for every end frame
                 let client_pdu = ClientPdu::FrameAcknowledge(FrameAcknowledgePdu {
@@ -59,6 +108,10 @@ impl DynamicChannelDataHandler for Handler {
                 // Handle the normal PDU
             }
 
+            if let Some(compositor) = self.compositor.as_mut() {
+                compositor.process(&gfx_pdu)?;
+            }
+
             // If there is a listener send all the data to the listener
             if let Some(handler) = self.gfx_handler.as_mut() {
                 // Handle the normal PDU
@@ -72,10 +125,28 @@ impl DynamicChannelDataHandler for Handler {
         }
 
         if !client_pdu_buffer.is_empty() {
-            return Ok(Some(client_pdu_buffer));
+            return Ok(vec![client_pdu_buffer]);
         }
 
-        Ok(None)
+        Ok(Vec::new())
+    }
+
+    fn close(&mut self) {
+        // The compression history is not kept when the channel is opened again
+        self.decompressor = zgfx::Decompressor::new();
+        self.frames_decoded = 0;
+
+        if let Some(compositor) = self.compositor.as_mut() {
+            compositor.reset();
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
     }
 }
 
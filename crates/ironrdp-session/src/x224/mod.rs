@@ -1,113 +1,308 @@
 mod display;
 mod gfx;
+mod rdpei;
 
-use std::collections::HashMap;
-use std::{cmp, io};
+use core::any::Any;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::{cmp, mem};
 
 use ironrdp_connector::legacy::SendDataIndicationCtx;
-use ironrdp_connector::GraphicsConfig;
+use ironrdp_connector::{
+    ConnectionActivationSequence, ConnectionActivationState, DesktopSize, GraphicsConfig, Sequence as _, State as _,
+};
+use ironrdp_graphics::{bulk, zgfx};
 use ironrdp_pdu::dvc::FieldType;
-use ironrdp_pdu::rdp::headers::ShareDataPdu;
+use ironrdp_pdu::gcc::{ChannelOptions, Monitor};
+use ironrdp_pdu::geometry::Rectangle;
+use ironrdp_pdu::rdp::headers::{ShareControlPdu, ShareDataPdu};
 use ironrdp_pdu::rdp::server_error_info::{ErrorInfo, ProtocolIndependentCode, ServerSetErrorInfoPdu};
+use ironrdp_pdu::rdp::server_redirection::{ServerRedirectionFlags, ServerRedirectionPdu};
+use ironrdp_pdu::rdp::session_info::{InfoData, ServerAutoReconnect};
+use ironrdp_pdu::rdp::vc::dvc::rdpei as rdpei_pdu;
 use ironrdp_pdu::rdp::vc::{self, dvc};
+use ironrdp_pdu::PduParsing as _;
 
 pub use self::gfx::GfxHandler;
+pub use self::rdpei::MAX_TOUCH_CONTACTS;
+use crate::image::DecodedImage;
 use crate::{Error, Result};
 
 pub const RDP8_GRAPHICS_PIPELINE_NAME: &str = "Microsoft::Windows::RDS::Graphics";
 pub const RDP8_DISPLAY_PIPELINE_NAME: &str = "Microsoft::Windows::RDS::DisplayControl";
+pub const RDP_INPUT_CHANNEL_NAME: &str = "Microsoft::Windows::RDS::Input";
+
+/// The result of an x224 frame processing
+pub enum ProcessorOutput {
+    /// A buffer with encoded data to send to the server.
+    ResponseFrame(Vec<u8>),
+    /// The server changed the desktop size (e.g.: upon reception of a Reset Graphics PDU).
+    ResizeDesktop(DesktopSize),
+    /// The Deactivation-Reactivation Sequence completed, possibly with a new desktop size.
+    Reactivated(DesktopSize),
+    /// The server redirected the client, which should reconnect to the target of the redirection.
+    ServerRedirection(Box<ServerRedirectionPdu>),
+    /// The server sent the layout of the monitors spanned by the session.
+    MonitorLayout(Vec<Monitor>),
+}
 
 pub struct Processor {
-    channel_map: HashMap<String, u32>,
-    dynamic_channels: HashMap<u32, DynamicChannel>,
+    /// Name of the opened dynamic channels, by channel ID
+    channel_map: HashMap<u32, String>,
+    /// Dynamic channels which can be opened by the server, by channel name
+    dynamic_channels: HashMap<String, DynamicChannel>,
+    /// DRDYNVC version requested by the server
+    dvc_caps_version: dvc::CapsVersion,
+    /// RDP 8.0 Lite decompressor for the compressed DVC data PDUs
+    dvc_decompressor: zgfx::Decompressor,
+    /// Bulk decompressor, whose history is shared with the fast-path PDUs
+    bulk_decompressor: Arc<Mutex<bulk::Decompressor>>,
+    /// Bulk decompressor of the virtual channel data, with its own history
+    vc_decompressor: bulk::Decompressor,
     user_channel_id: u16,
     io_channel_id: u16,
     drdynvc_channel_id: Option<u16>,
-    graphics_config: Option<GraphicsConfig>,
-    graphics_handler: Option<Box<dyn GfxHandler + Send>>,
+    connection_activation: ConnectionActivationSequence,
+    auto_reconnect_cookie: Option<ServerAutoReconnect>,
+    static_channels: HashMap<u16, StaticChannel>,
+    /// Maximum size of the static virtual channel chunks, as negotiated during the capabilities exchange
+    vc_chunk_size: usize,
 }
 
 impl Processor {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
-        static_channels: HashMap<u16, String>,
+        static_channel_ids: HashMap<u16, String>,
         user_channel_id: u16,
         io_channel_id: u16,
         graphics_config: Option<GraphicsConfig>,
         graphics_handler: Option<Box<dyn GfxHandler + Send>>,
+        static_channels: Vec<Box<dyn StaticVirtualChannel>>,
+        dynamic_channels: Vec<Box<dyn DynamicVirtualChannel>>,
+        connection_activation: ConnectionActivationSequence,
+        bulk_decompressor: Arc<Mutex<bulk::Decompressor>>,
     ) -> Self {
-        let drdynvc_channel_id = static_channels.iter().find_map(|(id, name)| {
-            if name == vc::DRDYNVC_CHANNEL_NAME {
-                Some(*id)
-            } else {
-                None
-            }
-        });
+        let find_channel_id = |channel_name: &str| {
+            static_channel_ids
+                .iter()
+                .find_map(|(id, name)| if name == channel_name { Some(*id) } else { None })
+        };
+
+        let drdynvc_channel_id = find_channel_id(vc::DRDYNVC_CHANNEL_NAME);
+
+        // The options of the channels are the ones requested in the Client Network Data
+        let static_channels = static_channels
+            .into_iter()
+            .filter_map(|processor| {
+                let channel_name = processor.channel_name().to_owned();
+
+                let channel_id = find_channel_id(&channel_name);
+                let options = connection_activation
+                    .config
+                    .static_channels
+                    .iter()
+                    .find(|channel| channel.name == channel_name)
+                    .map(|channel| channel.options);
+
+                if let (Some(channel_id), Some(options)) = (channel_id, options) {
+                    Some((channel_id, StaticChannel::new(channel_name, options, processor)))
+                } else {
+                    warn!(channel_name, "Static virtual channel was not joined");
+                    None
+                }
+            })
+            .collect();
+
+        let vc_chunk_size = match &connection_activation.state {
+            ConnectionActivationState::Finalized { vc_chunk_size, .. } => *vc_chunk_size,
+            _ => vc::CHANNEL_CHUNK_LENGTH,
+        };
+
+        let builtin_dynamic_channels: [Box<dyn DynamicVirtualChannel>; 3] = [
+            Box::new(gfx::Handler::new(graphics_handler, graphics_config)),
+            Box::new(display::Handler::default()),
+            Box::new(rdpei::Handler::default()),
+        ];
+
+        // The processors provided by the application replace the built-in ones registered with the same name
+        let dynamic_channels = builtin_dynamic_channels
+            .into_iter()
+            .chain(dynamic_channels)
+            .map(|processor| (processor.channel_name().to_owned(), DynamicChannel::new(processor)))
+            .collect();
 
         Self {
-            dynamic_channels: HashMap::new(),
+            dynamic_channels,
             channel_map: HashMap::new(),
+            dvc_caps_version: dvc::CapsVersion::V1,
+            dvc_decompressor: zgfx::Decompressor::new(),
+            bulk_decompressor,
+            vc_decompressor: bulk::Decompressor::new(),
             user_channel_id,
             io_channel_id,
             drdynvc_channel_id,
-            graphics_config,
-            graphics_handler,
+            connection_activation,
+            auto_reconnect_cookie: None,
+            static_channels,
+            vc_chunk_size,
         }
     }
 
-    pub fn process(&mut self, frame: &[u8]) -> Result<Vec<u8>> {
+    /// Auto-reconnect packet sent by the server in the Save Session Info PDU, if any
+    pub fn auto_reconnect_cookie(&self) -> Option<&ServerAutoReconnect> {
+        self.auto_reconnect_cookie.as_ref()
+    }
+
+    pub fn process(&mut self, frame: &[u8]) -> Result<Vec<ProcessorOutput>> {
         let data_ctx = ironrdp_connector::legacy::decode_send_data_indication(frame)?;
         let channel_id = data_ctx.channel_id;
 
         if channel_id == self.io_channel_id {
-            self.process_io_channel(data_ctx)?;
-            Ok(Vec::new())
+            if self.connection_activation.state.is_terminal() {
+                self.process_io_channel(data_ctx)
+            } else {
+                self.process_connection_activation(frame)
+            }
         } else {
             match self.drdynvc_channel_id {
                 Some(dyvc_id) if channel_id == dyvc_id => self.process_dyvc(data_ctx),
+                _ if self.static_channels.contains_key(&channel_id) => self.process_svc(data_ctx),
                 _ => Err(Error::new("unexpected channel").with_reason(format!("received ID {channel_id}"))),
             }
         }
     }
 
-    fn process_io_channel(&self, data_ctx: SendDataIndicationCtx<'_>) -> Result<()> {
+    fn process_io_channel(&mut self, data_ctx: SendDataIndicationCtx<'_>) -> Result<Vec<ProcessorOutput>> {
         debug_assert_eq!(data_ctx.channel_id, self.io_channel_id);
 
-        let ctx = ironrdp_connector::legacy::decode_share_data(data_ctx)?;
+        let ctx = ironrdp_connector::legacy::decode_share_control(data_ctx)?;
+
+        let share_data_header = match ctx.pdu {
+            ShareControlPdu::Data(share_data_header) => share_data_header,
+            ShareControlPdu::ServerDeactivateAll(_) => {
+                // https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpbcgr/dfc234ce-481a-4674-9a5d-2a7bafb14432
+                //
+                // The server is about to send a new Demand Active PDU, and the
+                // capabilities exchange and connection finalization are run again.
+                info!("Received Deactivate All PDU, waiting for reactivation");
+                self.connection_activation.reset();
+                return Ok(Vec::new());
+            }
+            ShareControlPdu::ServerRedirect(redirection) => {
+                if redirection.flags.contains(ServerRedirectionFlags::NO_REDIRECT) {
+                    debug!("Ignoring informative Server Redirection PDU");
+                    return Ok(Vec::new());
+                }
+
+                info!(redirection.session_id, "Received Server Redirection PDU");
+                return Ok(vec![ProcessorOutput::ServerRedirection(Box::new(redirection))]);
+            }
+            unexpected => {
+                return Err(Error::new("unexpected Share Control PDU")
+                    .with_reason(format!("got: {:?}", unexpected.as_short_name())))
+            }
+        };
 
-        match ctx.pdu {
+        match ironrdp_connector::legacy::decompress_share_data(share_data_header, &self.bulk_decompressor)? {
             ShareDataPdu::SaveSessionInfo(session_info) => {
                 debug!("Got Session Save Info PDU: {session_info:?}");
-                Ok(())
+
+                if let InfoData::LogonExtended(logon_info) = session_info.info_data {
+                    if let Some(auto_reconnect) = logon_info.auto_reconnect {
+                        debug!(auto_reconnect.logon_id, "Received auto-reconnect cookie");
+                        self.auto_reconnect_cookie = Some(auto_reconnect);
+                    }
+                }
+
+                Ok(Vec::new())
             }
             ShareDataPdu::ServerSetErrorInfo(ServerSetErrorInfoPdu(ErrorInfo::ProtocolIndependentCode(
                 ProtocolIndependentCode::None,
             ))) => {
                 debug!("Received None server error");
-                Ok(())
+                Ok(Vec::new())
             }
             ShareDataPdu::ServerSetErrorInfo(ServerSetErrorInfoPdu(e)) => {
                 Err(Error::new("ServerSetErrorInfo").with_reason(e.description()))
             }
-            _ => Err(Error::new("unexpected PDU").with_reason(format!(
+            ShareDataPdu::MonitorLayout(monitor_layout) => {
+                debug!(?monitor_layout, "Received Monitor Layout PDU");
+                Ok(vec![ProcessorOutput::MonitorLayout(monitor_layout.monitors)])
+            }
+            unexpected => Err(Error::new("unexpected PDU").with_reason(format!(
                 "Expected Session Save Info PDU, got: {:?}",
-                ctx.pdu.as_short_name()
+                unexpected.as_short_name()
             ))),
         }
     }
 
-    fn process_dyvc(&mut self, data_ctx: SendDataIndicationCtx<'_>) -> Result<Vec<u8>> {
+    /// Drives the connection activation sequence during a Deactivation-Reactivation Sequence.
+    fn process_connection_activation(&mut self, frame: &[u8]) -> Result<Vec<ProcessorOutput>> {
+        let mut buf = Vec::new();
+        let mut output = Vec::new();
+
+        let written = self.connection_activation.step(frame, &mut output)?;
+
+        // A connection broker may redirect the client instead of reactivating the session
+        if let ConnectionActivationState::Redirected { redirection } = &self.connection_activation.state {
+            return Ok(vec![ProcessorOutput::ServerRedirection(redirection.clone())]);
+        }
+
+        if let Some(size) = written.size() {
+            buf.extend_from_slice(&output[..size]);
+        }
+
+        // The client sends the finalization PDUs one after the other without waiting for the server
+        while self.connection_activation.next_pdu_hint().is_none() && !self.connection_activation.state.is_terminal() {
+            let written = self.connection_activation.step_no_input(&mut output)?;
+            if let Some(size) = written.size() {
+                buf.extend_from_slice(&output[..size]);
+            }
+        }
+
+        let mut outputs = Vec::new();
+
+        if !buf.is_empty() {
+            outputs.push(ProcessorOutput::ResponseFrame(buf));
+        }
+
+        if let ConnectionActivationState::Finalized {
+            desktop_size,
+            vc_chunk_size,
+        } = &self.connection_activation.state
+        {
+            self.vc_chunk_size = *vc_chunk_size;
+            outputs.push(ProcessorOutput::Reactivated(desktop_size.clone()));
+
+            if let Some(monitor_layout) = &self.connection_activation.monitor_layout {
+                outputs.push(ProcessorOutput::MonitorLayout(monitor_layout.clone()));
+            }
+        }
+
+        Ok(outputs)
+    }
+
+    fn process_dyvc(&mut self, data_ctx: SendDataIndicationCtx<'_>) -> Result<Vec<ProcessorOutput>> {
         debug_assert_eq!(Some(data_ctx.channel_id), self.drdynvc_channel_id);
 
-        let dvc_ctx = crate::legacy::decode_dvc_message(data_ctx)?;
+        let mut decompressed = Vec::new();
+        let dvc_ctx = crate::legacy::decode_dvc_message(data_ctx, &mut self.vc_decompressor, &mut decompressed)?;
 
         let mut buf = Vec::new();
 
         match dvc_ctx.dvc_pdu {
             dvc::ServerPdu::CapabilitiesRequest(caps_request) => {
                 debug!("Got DVC Capabilities Request PDU: {caps_request:?}");
-                let caps_response = dvc::ClientPdu::CapabilitiesResponse(dvc::CapabilitiesResponsePdu {
-                    version: dvc::CapsVersion::V1,
-                });
+
+                // All the versions are supported, so the client replies with the version requested by the server
+                let version = match caps_request {
+                    dvc::CapabilitiesRequestPdu::V1 => dvc::CapsVersion::V1,
+                    dvc::CapabilitiesRequestPdu::V2 { .. } => dvc::CapsVersion::V2,
+                    dvc::CapabilitiesRequestPdu::V3 { .. } => dvc::CapsVersion::V3,
+                };
+                self.dvc_caps_version = version;
+
+                let caps_response = dvc::ClientPdu::CapabilitiesResponse(dvc::CapabilitiesResponsePdu { version });
 
                 debug!("Send DVC Capabilities Response PDU: {caps_response:?}");
                 crate::legacy::encode_dvc_message(
@@ -121,19 +316,26 @@ impl Processor {
             dvc::ServerPdu::CreateRequest(create_request) => {
                 debug!("Got DVC Create Request PDU: {create_request:?}");
 
-                let creation_status = if let Some(dynamic_channel) = create_dvc(
-                    create_request.channel_name.as_str(),
-                    create_request.channel_id,
-                    create_request.channel_id_type,
-                    &mut self.graphics_handler,
-                ) {
-                    self.dynamic_channels.insert(create_request.channel_id, dynamic_channel);
-                    self.channel_map
-                        .insert(create_request.channel_name.clone(), create_request.channel_id);
-
-                    dvc::DVC_CREATION_STATUS_OK
-                } else {
-                    dvc::DVC_CREATION_STATUS_NO_LISTENER
+                let creation_status = match self.dynamic_channels.get_mut(&create_request.channel_name) {
+                    Some(dynamic_channel) => {
+                        dynamic_channel.open(OpenedChannel {
+                            channel_id: create_request.channel_id,
+                            channel_id_type: create_request.channel_id_type,
+                            priority: create_request.priority,
+                        })?;
+                        self.channel_map
+                            .insert(create_request.channel_id, create_request.channel_name.clone());
+
+                        dvc::DVC_CREATION_STATUS_OK
+                    }
+                    None => {
+                        warn!(
+                            channel_name = create_request.channel_name,
+                            "No processor registered for the dynamic channel"
+                        );
+
+                        dvc::DVC_CREATION_STATUS_NO_LISTENER
+                    }
                 };
 
                 let create_response = dvc::ClientPdu::CreateResponse(dvc::CreateResponsePdu {
@@ -150,14 +352,6 @@ impl Processor {
                     &[],
                     &mut buf,
                 )?;
-
-                negotiate_dvc(
-                    &create_request,
-                    data_ctx.initiator_id,
-                    data_ctx.channel_id,
-                    &mut buf,
-                    &self.graphics_config,
-                )?;
             }
             dvc::ServerPdu::CloseRequest(close_request) => {
                 debug!("Got DVC Close Request PDU: {close_request:?}");
@@ -176,104 +370,319 @@ impl Processor {
                     &mut buf,
                 )?;
 
-                self.dynamic_channels.remove(&close_request.channel_id);
+                if let Some(channel_name) = self.channel_map.remove(&close_request.channel_id) {
+                    if let Some(dynamic_channel) = self.dynamic_channels.get_mut(&channel_name) {
+                        dynamic_channel.close();
+                    }
+                }
             }
             dvc::ServerPdu::DataFirst(data) => {
-                let channel_id_type = data.channel_id_type;
-                let channel_id = data.channel_id;
-
-                let dvc_data = dvc_ctx.dvc_data;
-
                 // FIXME(perf): copy with data_buf.to_vec()
-                if let Some(dvc_data) = self
-                    .dynamic_channels
-                    .get_mut(&data.channel_id)
-                    .ok_or_else(|| {
-                        Error::new("access to non existing channel").with_reason(data.channel_id.to_string())
-                    })?
-                    .process_data_first_pdu(data.total_data_size as usize, dvc_data.to_vec())?
-                {
-                    let client_data = dvc::ClientPdu::Data(dvc::DataPdu {
-                        channel_id_type,
-                        channel_id,
-                        data_size: dvc_data.len(),
-                    });
-
-                    crate::legacy::encode_dvc_message(
-                        data_ctx.initiator_id,
-                        data_ctx.channel_id,
-                        client_data,
-                        &dvc_data,
-                        &mut buf,
-                    )?;
-                }
+                self.opened_dynamic_channel_mut(data.channel_id)?
+                    .process_data_first_pdu(data.total_data_size as usize, dvc_ctx.dvc_data.to_vec())?;
             }
             dvc::ServerPdu::Data(data) => {
-                let channel_id_type = data.channel_id_type;
-                let channel_id = data.channel_id;
+                // FIXME(perf): copy with data_buf.to_vec()
+                self.opened_dynamic_channel_mut(data.channel_id)?
+                    .process_data_pdu(dvc_ctx.dvc_data.to_vec())?;
+            }
+            dvc::ServerPdu::DataFirstCompressed(data) => {
+                let dvc_data = self.decompress_dvc_data(dvc_ctx.dvc_data)?;
 
-                let dvc_data = dvc_ctx.dvc_data;
+                self.opened_dynamic_channel_mut(data.channel_id)?
+                    .process_data_first_pdu(data.total_data_size as usize, dvc_data)?;
+            }
+            dvc::ServerPdu::DataCompressed(data) => {
+                let dvc_data = self.decompress_dvc_data(dvc_ctx.dvc_data)?;
 
-                // FIXME(perf): copy with data_buf.to_vec()
-                if let Some(dvc_data) = self
-                    .dynamic_channels
-                    .get_mut(&data.channel_id)
-                    .ok_or_else(|| {
-                        Error::new("access to non existing channel").with_reason(data.channel_id.to_string())
-                    })?
-                    .process_data_pdu(dvc_data.to_vec())?
-                {
-                    let client_data = dvc::ClientPdu::Data(dvc::DataPdu {
-                        channel_id_type,
-                        channel_id,
-                        data_size: dvc_data.len(),
-                    });
-
-                    crate::legacy::encode_dvc_message(
-                        data_ctx.initiator_id,
-                        data_ctx.channel_id,
-                        client_data,
-                        &dvc_data,
-                        &mut buf,
-                    )?;
-                }
+                self.opened_dynamic_channel_mut(data.channel_id)?
+                    .process_data_pdu(dvc_data)?;
             }
         }
 
-        Ok(buf)
+        // Sends the responses of the dynamic channel processors, along with the messages queued by the application
+        self.flush_dvc_queues(&mut buf)?;
+
+        let mut outputs = Vec::new();
+
+        if !buf.is_empty() {
+            outputs.push(ProcessorOutput::ResponseFrame(buf));
+        }
+
+        if let Some(desktop_size) = self
+            .dynamic_channel_mut::<gfx::Handler>(RDP8_GRAPHICS_PIPELINE_NAME)
+            .and_then(gfx::Handler::take_reset_graphics)
+        {
+            outputs.push(ProcessorOutput::ResizeDesktop(desktop_size));
+        }
+
+        Ok(outputs)
+    }
+
+    fn opened_dynamic_channel_mut(&mut self, channel_id: u32) -> Result<&mut DynamicChannel> {
+        self.channel_map
+            .get(&channel_id)
+            .and_then(|channel_name| self.dynamic_channels.get_mut(channel_name))
+            .ok_or_else(|| Error::new("access to non existing channel").with_reason(channel_id.to_string()))
+    }
+
+    fn decompress_dvc_data(&mut self, compressed_data: &[u8]) -> Result<Vec<u8>> {
+        if self.dvc_caps_version != dvc::CapsVersion::V3 {
+            return Err(
+                Error::new("unexpected compressed DVC data").with_reason("compression requires DRDYNVC version 3")
+            );
+        }
+
+        let mut dvc_data = Vec::new();
+        self.dvc_decompressor
+            .decompress_bulk_encoded_data(compressed_data, &mut dvc_data)?;
+
+        Ok(dvc_data)
+    }
+
+    /// Encodes the messages queued for the opened dynamic channels, appending the frames to `output`.
+    ///
+    /// The messages of the channels with the highest priority are sent first.
+    fn flush_dvc_queues(&mut self, output: &mut Vec<u8>) -> Result<()> {
+        let Some(drdynvc_channel_id) = self.drdynvc_channel_id else {
+            return Ok(());
+        };
+
+        let user_channel_id = self.user_channel_id;
+
+        let mut pending_channels = self
+            .dynamic_channels
+            .values_mut()
+            .filter_map(|dynamic_channel| {
+                let opened = dynamic_channel.opened?;
+                (!dynamic_channel.outbound_queue.is_empty()).then_some((opened, dynamic_channel))
+            })
+            .collect::<Vec<_>>();
+
+        pending_channels.sort_by_key(|(opened, _)| (opened.priority, opened.channel_id));
+
+        for (opened, dynamic_channel) in pending_channels {
+            for message in dynamic_channel.outbound_queue.drain(..) {
+                encode_dvc_data(user_channel_id, drdynvc_channel_id, opened, &message, output)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn process_svc(&mut self, data_ctx: SendDataIndicationCtx<'_>) -> Result<Vec<ProcessorOutput>> {
+        let channel_id = data_ctx.channel_id;
+
+        let static_channel = self
+            .static_channels
+            .get_mut(&channel_id)
+            .ok_or_else(|| Error::new("access to non existing channel").with_reason(channel_id.to_string()))?;
+
+        let Some(message) = static_channel.process_chunk(data_ctx.user_data, &mut self.vc_decompressor)? else {
+            return Ok(Vec::new());
+        };
+
+        let responses = static_channel.processor.process(&message)?;
+
+        let channel_name = static_channel.name.clone();
+
+        let mut buf = Vec::new();
+        let mut frame = Vec::new();
+
+        for response in responses {
+            let written = self.encode_svc(&mut frame, &channel_name, &response)?;
+            buf.extend_from_slice(&frame[..written]);
+        }
+
+        if buf.is_empty() {
+            Ok(Vec::new())
+        } else {
+            Ok(vec![ProcessorOutput::ResponseFrame(buf)])
+        }
+    }
+
+    /// Sends a message on a static virtual channel.
+    ///
+    /// The message is split into chunks according to the negotiated chunk size.
+    pub fn encode_svc(&self, output: &mut Vec<u8>, channel_name: &str, payload: &[u8]) -> Result<usize> {
+        let (channel_id, static_channel) = self
+            .static_channels
+            .iter()
+            .find(|(_, static_channel)| static_channel.name == channel_name)
+            .ok_or_else(|| Error::new("access to non existing channel name").with_reason(channel_name))?;
+
+        let flags = if static_channel.options.contains(ChannelOptions::SHOW_PROTOCOL) {
+            vc::ChannelControlFlags::FLAG_SHOW_PROTOCOL
+        } else {
+            vc::ChannelControlFlags::empty()
+        };
+
+        crate::legacy::encode_svc_message(
+            self.user_channel_id,
+            *channel_id,
+            payload,
+            flags,
+            self.vc_chunk_size,
+            output,
+        )
+    }
+
+    /// Processor registered for the given static channel, if the channel was joined.
+    pub fn static_channel<T: Any>(&self, channel_name: &str) -> Option<&T> {
+        self.static_channels
+            .values()
+            .find(|static_channel| static_channel.name == channel_name)?
+            .processor
+            .as_any()
+            .downcast_ref()
+    }
+
+    pub fn static_channel_mut<T: Any>(&mut self, channel_name: &str) -> Option<&mut T> {
+        self.static_channels
+            .values_mut()
+            .find(|static_channel| static_channel.name == channel_name)?
+            .processor
+            .as_any_mut()
+            .downcast_mut()
+    }
+
+    /// Processor registered for the given dynamic channel, whether the channel is opened or not.
+    pub fn dynamic_channel<T: Any>(&self, channel_name: &str) -> Option<&T> {
+        self.dynamic_channels
+            .get(channel_name)?
+            .processor
+            .as_any()
+            .downcast_ref()
+    }
+
+    pub fn dynamic_channel_mut<T: Any>(&mut self, channel_name: &str) -> Option<&mut T> {
+        self.dynamic_channels
+            .get_mut(channel_name)?
+            .processor
+            .as_any_mut()
+            .downcast_mut()
+    }
+
+    /// Queues a message to send on a dynamic channel.
+    ///
+    /// The message is kept until the server opens the channel, and is sent along with the next response
+    /// on the dynamic virtual channel or by [`Processor::encode_queued_dvc_messages`].
+    pub fn queue_dvc_message(&mut self, channel_name: &str, message: Vec<u8>) -> Result<()> {
+        self.dynamic_channels
+            .get_mut(channel_name)
+            .ok_or_else(|| Error::new("access to non existing channel name").with_reason(channel_name))?
+            .outbound_queue
+            .push_back(message);
+
+        Ok(())
+    }
+
+    /// Encodes the messages queued for the opened dynamic channels.
+    pub fn encode_queued_dvc_messages(&mut self, output: &mut Vec<u8>) -> Result<usize> {
+        output.clear();
+        self.flush_dvc_queues(output)?;
+
+        Ok(output.len())
+    }
+
+    /// Copies the graphics pipeline updates completed since the last call to the image.
+    ///
+    /// Nothing is copied when the graphics pipeline is rendered by the application's [`GfxHandler`].
+    pub fn flush_gfx_updates(&mut self, image: &mut DecodedImage) -> Result<Vec<Rectangle>> {
+        match self.dynamic_channel_mut::<gfx::Handler>(RDP8_GRAPHICS_PIPELINE_NAME) {
+            Some(gfx_handler) => gfx_handler.flush(image),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Encodes a resize request for the Display Control dynamic channel.
+    ///
+    /// Returns `None` when the server did not open the Display Control channel.
+    pub fn encode_resize(
+        &self,
+        output: &mut Vec<u8>,
+        width: u32,
+        height: u32,
+        scale_factor: Option<u32>,
+        physical_dims: Option<(u32, u32)>,
+    ) -> Option<Result<usize>> {
+        let display_handler = self.dynamic_channel::<display::Handler>(RDP8_DISPLAY_PIPELINE_NAME)?;
+
+        // The server did not advertise its capabilities yet, the channel is not ready to use
+        display_handler.capabilities()?;
+
+        let written = display_handler
+            .encode_monitor_layout(width, height, scale_factor, physical_dims)
+            .and_then(|dvc_data| self.encode_dynamic(output, RDP8_DISPLAY_PIPELINE_NAME, &dvc_data));
+
+        Some(written)
+    }
+
+    /// Encodes touch frames for the Input dynamic channel.
+    ///
+    /// Returns `None` when the server did not open the Input channel.
+    pub fn encode_touch(&self, output: &mut Vec<u8>, frames: Vec<rdpei_pdu::TouchFrame>) -> Option<Result<usize>> {
+        let input_handler = self.dynamic_channel::<rdpei::Handler>(RDP_INPUT_CHANNEL_NAME)?;
+
+        // The server did not send its SC_READY PDU yet, the channel is not ready to use
+        input_handler.protocol_version()?;
+
+        let written = input_handler
+            .encode_touch_event(frames)
+            .and_then(|dvc_data| self.encode_dynamic(output, RDP_INPUT_CHANNEL_NAME, &dvc_data));
+
+        Some(written)
+    }
+
+    /// Encodes pen frames for the Input dynamic channel.
+    ///
+    /// Returns `None` when the server did not open the Input channel.
+    pub fn encode_pen(&self, output: &mut Vec<u8>, frames: Vec<rdpei_pdu::PenFrame>) -> Option<Result<usize>> {
+        let input_handler = self.dynamic_channel::<rdpei::Handler>(RDP_INPUT_CHANNEL_NAME)?;
+
+        // The server did not send its SC_READY PDU yet, the channel is not ready to use
+        input_handler.protocol_version()?;
+
+        let written = input_handler
+            .encode_pen_event(frames)
+            .and_then(|dvc_data| self.encode_dynamic(output, RDP_INPUT_CHANNEL_NAME, &dvc_data));
+
+        Some(written)
+    }
+
+    /// Whether the server is currently accepting touch input.
+    pub fn touch_input_ready(&self) -> bool {
+        self.dynamic_channel::<rdpei::Handler>(RDP_INPUT_CHANNEL_NAME)
+            .map(rdpei::Handler::is_ready)
+            .unwrap_or(false)
+    }
+
+    /// Whether the server is currently accepting pen input.
+    pub fn pen_input_ready(&self) -> bool {
+        self.dynamic_channel::<rdpei::Handler>(RDP_INPUT_CHANNEL_NAME)
+            .map(rdpei::Handler::supports_pen)
+            .unwrap_or(false)
     }
 
     /// Sends a PDU on the dynamic channel.
+    ///
+    /// Messages which do not fit in a single DVC Data PDU are fragmented.
     pub fn encode_dynamic(&self, output: &mut Vec<u8>, channel_name: &str, dvc_data: &[u8]) -> Result<usize> {
         let drdynvc_channel_id = self
             .drdynvc_channel_id
             .ok_or(Error::new("dynamic virtual channel not connected"))?;
 
-        let dvc_channel_id = self
-            .channel_map
-            .get(channel_name)
-            .ok_or_else(|| Error::new("access to non existing channel name").with_reason(channel_name))?;
-
-        let dvc_channel = self
+        let opened = self
             .dynamic_channels
-            .get(dvc_channel_id)
-            .ok_or_else(|| Error::new("access to non existing channel").with_reason(dvc_channel_id.to_string()))?;
-
-        let dvc_client_data = dvc::ClientPdu::Data(dvc::DataPdu {
-            channel_id_type: dvc_channel.channel_id_type,
-            channel_id: dvc_channel.channel_id,
-            data_size: dvc_data.len(),
-        });
+            .get(channel_name)
+            .ok_or_else(|| Error::new("access to non existing channel name").with_reason(channel_name))?
+            .opened
+            .ok_or_else(|| Error::new("dynamic channel not opened").with_reason(channel_name))?;
 
-        let written = crate::legacy::encode_dvc_message(
-            self.user_channel_id,
-            drdynvc_channel_id,
-            dvc_client_data,
-            dvc_data,
-            output,
-        )?;
+        output.clear();
+        encode_dvc_data(self.user_channel_id, drdynvc_channel_id, opened, dvc_data, output)?;
 
-        Ok(written)
+        Ok(output.len())
     }
 
     /// Send a pdu on the static global channel. Typically used to send input events
@@ -284,97 +693,274 @@ impl Processor {
     }
 }
 
-fn create_dvc(
-    channel_name: &str,
-    channel_id: u32,
-    channel_id_type: FieldType,
-    graphics_handler: &mut Option<Box<dyn GfxHandler + Send>>,
-) -> Option<DynamicChannel> {
-    match channel_name {
-        RDP8_GRAPHICS_PIPELINE_NAME => {
-            let handler = graphics_handler.take();
-            Some(DynamicChannel::new(
-                Box::new(gfx::Handler::new(handler)),
-                channel_id,
-                channel_id_type,
-            ))
+/// Static virtual channel, along with the chunks of the message being received
+struct StaticChannel {
+    name: String,
+    options: ChannelOptions,
+    processor: Box<dyn StaticVirtualChannel>,
+    pending_data: Vec<u8>,
+}
+
+impl StaticChannel {
+    fn new(name: String, options: ChannelOptions, processor: Box<dyn StaticVirtualChannel>) -> Self {
+        Self {
+            name,
+            options,
+            processor,
+            pending_data: Vec::new(),
         }
-        RDP8_DISPLAY_PIPELINE_NAME => Some(DynamicChannel::new(
-            Box::new(display::Handler),
-            channel_id,
-            channel_id_type,
-        )),
-        _ => {
-            error!("Unknown channel name: {}", channel_name);
-            None
+    }
+
+    /// Reassembles the message sent over multiple Virtual Channel PDUs.
+    ///
+    /// Returns the complete message once its last chunk is received. Compressed chunks are decompressed first,
+    /// the length of the message being the one of the uncompressed data.
+    fn process_chunk(
+        &mut self,
+        mut user_data: &[u8],
+        vc_decompressor: &mut bulk::Decompressor,
+    ) -> Result<Option<Vec<u8>>> {
+        let channel_header = vc::ChannelPduHeader::from_buffer(&mut user_data)?;
+
+        let mut decompressed = Vec::new();
+        let chunk =
+            crate::legacy::decompress_vc_chunk(channel_header.flags, user_data, vc_decompressor, &mut decompressed)?;
+
+        if channel_header.flags.contains(vc::ChannelControlFlags::FLAG_FIRST) {
+            self.pending_data.clear();
         }
+
+        self.pending_data.extend_from_slice(chunk);
+
+        let length = usize::try_from(channel_header.length).map_err(|_| {
+            Error::new("invalid static channel message length").with_reason(channel_header.length.to_string())
+        })?;
+
+        // The chunks never exceed the total length of the message
+        if self.pending_data.len() > length {
+            let received = mem::take(&mut self.pending_data).len();
+            return Err(Error::new("invalid static channel message")
+                .with_reason(format!("expected {length} bytes, got {received}")));
+        }
+
+        if !channel_header.flags.contains(vc::ChannelControlFlags::FLAG_LAST) {
+            return Ok(None);
+        }
+
+        let message = mem::take(&mut self.pending_data);
+
+        if message.len() != length {
+            return Err(Error::new("invalid static channel message")
+                .with_reason(format!("expected {length} bytes, got {}", message.len())));
+        }
+
+        Ok(Some(message))
     }
 }
 
-fn negotiate_dvc(
-    create_request: &dvc::CreateRequestPdu,
-    initiator_id: u16,
-    channel_id: u16,
-    mut stream: impl io::Write,
-    graphics_config: &Option<GraphicsConfig>,
-) -> Result<()> {
-    if create_request.channel_name == RDP8_GRAPHICS_PIPELINE_NAME {
-        let dvc_data = gfx::create_capabilities_advertise(graphics_config)?;
-        let dvc_pdu = dvc::ClientPdu::Data(dvc::DataPdu {
-            channel_id_type: create_request.channel_id_type,
-            channel_id: create_request.channel_id,
-            data_size: dvc_data.len(),
-        });
+/// A static virtual channel processor (section [3.1.5.2] of MS-RDPBCGR).
+///
+/// The channel must be requested in the connector `Config::static_channels`, and its processor is registered
+/// by channel name when creating the `ActiveStage`. Messages are dispatched to the processor once reassembled
+/// from their chunks.
+///
+/// [3.1.5.2]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpbcgr/343e4888-4c48-4054-b0e3-4e0762d1993c
+pub trait StaticVirtualChannel: Send {
+    /// Name of the channel (at most 7 characters), as requested in the Client Network Data
+    fn channel_name(&self) -> &str;
 
-        debug!("Send GFX Capabilities Advertise PDU");
-        let mut buf = Vec::new();
-        crate::legacy::encode_dvc_message(initiator_id, channel_id, dvc_pdu, &dvc_data, &mut buf)?;
-        stream
-            .write_all(&buf)
-            .map_err(|e| Error::new("write negotiation dvc").with_custom(e))?;
+    /// Processes a complete message received from the server.
+    ///
+    /// Returns the messages to send back on the same channel.
+    fn process(&mut self, payload: &[u8]) -> Result<Vec<Vec<u8>>>;
+
+    fn as_any(&self) -> &dyn Any;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+/// A dynamic virtual channel processor ([MS-RDPEDYC]).
+///
+/// Processors are registered by channel name when creating the `ActiveStage`, and the channel is accepted
+/// when the server asks to open it. The same processor is used again if the server reopens the channel.
+///
+/// [MS-RDPEDYC]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpedyc/3bd53020-9b64-4c9a-97fc-90a79e7e1e06
+pub trait DynamicVirtualChannel: Send {
+    /// Name of the channel, as sent by the server in the DVC Create Request PDU
+    fn channel_name(&self) -> &str;
+
+    /// Called when the server opens the channel.
+    ///
+    /// Returns the messages to send right away (e.g.: a capabilities advertisement).
+    fn start(&mut self) -> Result<Vec<Vec<u8>>> {
+        Ok(Vec::new())
     }
 
-    Ok(())
+    /// Processes a complete message received from the server.
+    ///
+    /// Returns the messages to send back on the same channel.
+    fn process(&mut self, payload: &[u8]) -> Result<Vec<Vec<u8>>>;
+
+    /// Called when the server closes the channel.
+    fn close(&mut self) {}
+
+    fn as_any(&self) -> &dyn Any;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
 }
 
-trait DynamicChannelDataHandler {
-    fn process_complete_data(&mut self, complete_data: Vec<u8>) -> Result<Option<Vec<u8>>>;
+/// Identifiers of a dynamic channel opened by the server
+#[derive(Debug, Copy, Clone)]
+struct OpenedChannel {
+    channel_id: u32,
+    channel_id_type: FieldType,
+    priority: dvc::ChannelPriority,
 }
 
-pub struct DynamicChannel {
+struct DynamicChannel {
+    processor: Box<dyn DynamicVirtualChannel>,
+    /// Set while the channel is opened
+    opened: Option<OpenedChannel>,
     data: CompleteData,
-    channel_id_type: FieldType,
-    channel_id: u32,
-    handler: Box<dyn DynamicChannelDataHandler + Send>,
+    /// Messages waiting to be sent on the channel
+    outbound_queue: VecDeque<Vec<u8>>,
 }
 
 impl DynamicChannel {
-    fn new(handler: Box<dyn DynamicChannelDataHandler + Send>, channel_id: u32, channel_id_type: FieldType) -> Self {
+    fn new(processor: Box<dyn DynamicVirtualChannel>) -> Self {
         Self {
+            processor,
+            opened: None,
             data: CompleteData::new(),
-            handler,
-            channel_id_type,
-            channel_id,
+            outbound_queue: VecDeque::new(),
+        }
+    }
+
+    fn open(&mut self, opened: OpenedChannel) -> Result<()> {
+        if self.opened.is_some() {
+            warn!(
+                channel_name = self.processor.channel_name(),
+                "Dynamic channel opened again without being closed"
+            );
+            self.close();
         }
+
+        self.opened = Some(opened);
+
+        // The start messages are sent before the ones queued while the channel was closed
+        for message in self.processor.start()?.into_iter().rev() {
+            self.outbound_queue.push_front(message);
+        }
+
+        Ok(())
     }
 
-    fn process_data_first_pdu(&mut self, total_data_size: usize, data: Vec<u8>) -> Result<Option<Vec<u8>>> {
+    fn close(&mut self) {
+        self.opened = None;
+        self.data = CompleteData::new();
+        self.processor.close();
+    }
+
+    fn process_data_first_pdu(&mut self, total_data_size: usize, data: Vec<u8>) -> Result<()> {
         if let Some(complete_data) = self.data.process_data_first_pdu(total_data_size, data) {
-            self.handler.process_complete_data(complete_data)
-        } else {
-            Ok(None)
+            self.process_complete_data(complete_data)?;
         }
+
+        Ok(())
     }
 
-    fn process_data_pdu(&mut self, data: Vec<u8>) -> Result<Option<Vec<u8>>> {
+    fn process_data_pdu(&mut self, data: Vec<u8>) -> Result<()> {
         if let Some(complete_data) = self.data.process_data_pdu(data) {
-            self.handler.process_complete_data(complete_data)
-        } else {
-            Ok(None)
+            self.process_complete_data(complete_data)?;
         }
+
+        Ok(())
+    }
+
+    fn process_complete_data(&mut self, complete_data: Vec<u8>) -> Result<()> {
+        let responses = self.processor.process(&complete_data)?;
+        self.outbound_queue.extend(responses);
+
+        Ok(())
     }
 }
 
+/// Encodes a message for an opened dynamic channel, appending the frames to `output`.
+///
+/// A message which does not fit in a single DVC Data PDU is sent in a DVC Data First PDU followed by
+/// as many DVC Data PDUs as needed.
+fn encode_dvc_data(
+    initiator_id: u16,
+    drdynvc_id: u16,
+    opened: OpenedChannel,
+    data: &[u8],
+    output: &mut Vec<u8>,
+) -> Result<()> {
+    let mut frame = Vec::new();
+    let mut remaining = data;
+
+    let max_data_size = dvc::PDU_WITH_DATA_MAX_SIZE
+        - dvc::DataPdu {
+            channel_id_type: opened.channel_id_type,
+            channel_id: opened.channel_id,
+            data_size: 0,
+        }
+        .buffer_length();
+
+    if data.len() > max_data_size {
+        let total_data_size = u32::try_from(data.len()).map_err(|_| Error::new("DVC message is too big"))?;
+
+        let total_data_size_type = if u16::try_from(total_data_size).is_ok() {
+            FieldType::U16
+        } else {
+            FieldType::U32
+        };
+
+        let mut data_first = dvc::DataFirstPdu {
+            channel_id_type: opened.channel_id_type,
+            channel_id: opened.channel_id,
+            total_data_size_type,
+            total_data_size,
+            data_size: 0,
+        };
+
+        let (first_chunk, rest) = data.split_at(dvc::PDU_WITH_DATA_MAX_SIZE - data_first.buffer_length());
+        data_first.data_size = first_chunk.len();
+
+        let written = crate::legacy::encode_dvc_message(
+            initiator_id,
+            drdynvc_id,
+            dvc::ClientPdu::DataFirst(data_first),
+            first_chunk,
+            &mut frame,
+        )?;
+        output.extend_from_slice(&frame[..written]);
+
+        remaining = rest;
+    }
+
+    let mut chunks = remaining.chunks(max_data_size).peekable();
+    let mut is_first = true;
+
+    // An empty message is still sent in a single DVC Data PDU
+    while is_first || chunks.peek().is_some() {
+        let chunk = chunks.next().unwrap_or_default();
+
+        let data_pdu = dvc::ClientPdu::Data(dvc::DataPdu {
+            channel_id_type: opened.channel_id_type,
+            channel_id: opened.channel_id,
+            data_size: chunk.len(),
+        });
+
+        let written = crate::legacy::encode_dvc_message(initiator_id, drdynvc_id, data_pdu, chunk, &mut frame)?;
+        output.extend_from_slice(&frame[..written]);
+
+        is_first = false;
+    }
+
+    Ok(())
+}
+
 #[derive(Debug, PartialEq)]
 struct CompleteData {
     total_size: usize,
@@ -437,3 +1023,796 @@ impl CompleteData {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use ironrdp_pdu::dvc::display;
+    use ironrdp_pdu::gcc::{self, KeyboardType};
+    use ironrdp_pdu::mcs;
+    use ironrdp_pdu::nego::SecurityProtocol;
+    use ironrdp_pdu::rdp::capability_sets::MajorPlatformType;
+    use ironrdp_pdu::rdp::client_info::{CompressionType, PerformanceFlags};
+    use ironrdp_pdu::rdp::headers::CompressionFlags;
+
+    use super::*;
+
+    const USER_CHANNEL_ID: u16 = 1007;
+    const IO_CHANNEL_ID: u16 = 1003;
+    const DRDYNVC_CHANNEL_ID: u16 = 1004;
+
+    struct NullChannel;
+
+    impl StaticVirtualChannel for NullChannel {
+        fn channel_name(&self) -> &str {
+            "null"
+        }
+
+        fn process(&mut self, _: &[u8]) -> Result<Vec<Vec<u8>>> {
+            Ok(Vec::new())
+        }
+
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        fn as_any_mut(&mut self) -> &mut dyn Any {
+            self
+        }
+    }
+
+    fn static_channel() -> StaticChannel {
+        StaticChannel::new("null".to_owned(), ChannelOptions::INITIALIZED, Box::new(NullChannel))
+    }
+
+    /// Virtual Channel PDU carrying a chunk of a message of `length` bytes
+    fn svc_chunk(length: u32, flags: vc::ChannelControlFlags, chunk: &[u8]) -> Vec<u8> {
+        let mut user_data = Vec::new();
+        vc::ChannelPduHeader { length, flags }
+            .to_buffer(&mut user_data)
+            .unwrap();
+        user_data.extend_from_slice(chunk);
+        user_data
+    }
+
+    #[test]
+    fn svc_chunks_are_reassembled_from_first_to_last() {
+        let mut static_channel = static_channel();
+        let mut vc_decompressor = bulk::Decompressor::new();
+
+        let first = svc_chunk(10, vc::ChannelControlFlags::FLAG_FIRST, b"0123");
+        assert_eq!(
+            static_channel.process_chunk(&first, &mut vc_decompressor).unwrap(),
+            None
+        );
+
+        let next = svc_chunk(10, vc::ChannelControlFlags::empty(), b"4567");
+        assert_eq!(static_channel.process_chunk(&next, &mut vc_decompressor).unwrap(), None);
+
+        let last = svc_chunk(10, vc::ChannelControlFlags::FLAG_LAST, b"89");
+        assert_eq!(
+            static_channel.process_chunk(&last, &mut vc_decompressor).unwrap(),
+            Some(b"0123456789".to_vec())
+        );
+    }
+
+    #[test]
+    fn svc_single_chunk_is_a_complete_message() {
+        let mut static_channel = static_channel();
+        let mut vc_decompressor = bulk::Decompressor::new();
+
+        let single = svc_chunk(
+            4,
+            vc::ChannelControlFlags::FLAG_FIRST | vc::ChannelControlFlags::FLAG_LAST,
+            b"data",
+        );
+
+        assert_eq!(
+            static_channel.process_chunk(&single, &mut vc_decompressor).unwrap(),
+            Some(b"data".to_vec())
+        );
+    }
+
+    #[test]
+    fn svc_first_chunk_discards_the_incomplete_message() {
+        let mut static_channel = static_channel();
+        let mut vc_decompressor = bulk::Decompressor::new();
+
+        let interrupted = svc_chunk(10, vc::ChannelControlFlags::FLAG_FIRST, b"abcd");
+        assert_eq!(
+            static_channel
+                .process_chunk(&interrupted, &mut vc_decompressor)
+                .unwrap(),
+            None
+        );
+
+        let first = svc_chunk(6, vc::ChannelControlFlags::FLAG_FIRST, b"012");
+        assert_eq!(
+            static_channel.process_chunk(&first, &mut vc_decompressor).unwrap(),
+            None
+        );
+
+        let last = svc_chunk(6, vc::ChannelControlFlags::FLAG_LAST, b"345");
+        assert_eq!(
+            static_channel.process_chunk(&last, &mut vc_decompressor).unwrap(),
+            Some(b"012345".to_vec())
+        );
+    }
+
+    #[test]
+    fn svc_chunks_longer_than_the_message_are_rejected() {
+        let mut static_channel = static_channel();
+        let mut vc_decompressor = bulk::Decompressor::new();
+
+        let first = svc_chunk(4, vc::ChannelControlFlags::FLAG_FIRST, b"0123");
+        assert_eq!(
+            static_channel.process_chunk(&first, &mut vc_decompressor).unwrap(),
+            None
+        );
+
+        let next = svc_chunk(4, vc::ChannelControlFlags::empty(), b"4");
+        assert!(static_channel.process_chunk(&next, &mut vc_decompressor).is_err());
+    }
+
+    #[test]
+    fn svc_message_shorter_than_its_length_is_rejected() {
+        let mut static_channel = static_channel();
+        let mut vc_decompressor = bulk::Decompressor::new();
+
+        let first = svc_chunk(10, vc::ChannelControlFlags::FLAG_FIRST, b"0123");
+        assert_eq!(
+            static_channel.process_chunk(&first, &mut vc_decompressor).unwrap(),
+            None
+        );
+
+        let last = svc_chunk(10, vc::ChannelControlFlags::FLAG_LAST, b"45");
+        assert!(static_channel.process_chunk(&last, &mut vc_decompressor).is_err());
+    }
+
+    /// Dynamic channel processor sending back the messages it receives
+    struct EchoChannel(&'static str);
+
+    impl DynamicVirtualChannel for EchoChannel {
+        fn channel_name(&self) -> &str {
+            self.0
+        }
+
+        fn process(&mut self, payload: &[u8]) -> Result<Vec<Vec<u8>>> {
+            Ok(vec![payload.to_vec()])
+        }
+
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        fn as_any_mut(&mut self) -> &mut dyn Any {
+            self
+        }
+    }
+
+    fn config() -> ironrdp_connector::Config {
+        ironrdp_connector::Config {
+            desktop_size: DesktopSize {
+                width: 1024,
+                height: 768,
+            },
+            security_protocol: SecurityProtocol::SSL,
+            username: "user".to_owned(),
+            password: "password".to_owned(),
+            domain: None,
+            client_build: 0,
+            client_name: "client".to_owned(),
+            keyboard_layout: 0,
+            keyboard_type: KeyboardType::IbmEnhanced,
+            keyboard_subtype: 0,
+            keyboard_functional_keys_count: 12,
+            ime_file_name: String::new(),
+            graphics: None,
+            bitmap: None,
+            dig_product_id: String::new(),
+            client_dir: String::new(),
+            platform: MajorPlatformType::Unspecified,
+            enable_audio_playback: false,
+            pointer_cache_size: 32,
+            compression_type: None,
+            timezone: None,
+            performance_flags: PerformanceFlags::empty(),
+            standard_rdp_security: false,
+            static_channels: Vec::new(),
+            monitors: Vec::new(),
+        }
+    }
+
+    fn processor(dynamic_channels: Vec<Box<dyn DynamicVirtualChannel>>) -> Processor {
+        Processor::new(
+            HashMap::from([(DRDYNVC_CHANNEL_ID, vc::DRDYNVC_CHANNEL_NAME.to_owned())]),
+            USER_CHANNEL_ID,
+            IO_CHANNEL_ID,
+            None,
+            None,
+            Vec::new(),
+            dynamic_channels,
+            ConnectionActivationSequence::new(config(), IO_CHANNEL_ID, USER_CHANNEL_ID),
+            Arc::new(Mutex::new(bulk::Decompressor::new())),
+        )
+    }
+
+    /// Processor with the "record" static channel joined with the ID 1005
+    fn processor_with_static_channel() -> Processor {
+        let mut config = config();
+        config.static_channels.push(gcc::Channel {
+            name: "record".to_owned(),
+            options: ChannelOptions::INITIALIZED | ChannelOptions::COMPRESS_RDP,
+        });
+
+        Processor::new(
+            HashMap::from([
+                (DRDYNVC_CHANNEL_ID, vc::DRDYNVC_CHANNEL_NAME.to_owned()),
+                (1005, "record".to_owned()),
+            ]),
+            USER_CHANNEL_ID,
+            IO_CHANNEL_ID,
+            None,
+            None,
+            vec![Box::new(RecordChannel::default())],
+            Vec::new(),
+            ConnectionActivationSequence::new(config, IO_CHANNEL_ID, USER_CHANNEL_ID),
+            Arc::new(Mutex::new(bulk::Decompressor::new())),
+        )
+    }
+
+    /// Static channel processor keeping the messages it receives
+    #[derive(Default)]
+    struct RecordChannel {
+        messages: Vec<Vec<u8>>,
+    }
+
+    impl StaticVirtualChannel for RecordChannel {
+        fn channel_name(&self) -> &str {
+            "record"
+        }
+
+        fn process(&mut self, payload: &[u8]) -> Result<Vec<Vec<u8>>> {
+            self.messages.push(payload.to_vec());
+            Ok(Vec::new())
+        }
+
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        fn as_any_mut(&mut self) -> &mut dyn Any {
+            self
+        }
+    }
+
+    /// Send Data Indication PDU carrying a single chunk of a message of `length` bytes
+    fn send_data_indication(channel_id: u16, length: u32, flags: vc::ChannelControlFlags, chunk: &[u8]) -> Vec<u8> {
+        let indication = mcs::SendDataIndication {
+            initiator_id: USER_CHANNEL_ID,
+            channel_id,
+            user_data: Cow::Owned(svc_chunk(
+                length,
+                vc::ChannelControlFlags::FLAG_FIRST | vc::ChannelControlFlags::FLAG_LAST | flags,
+                chunk,
+            )),
+        };
+
+        let mut frame = Vec::new();
+        ironrdp_pdu::encode_buf(&indication, &mut frame).unwrap();
+        frame
+    }
+
+    /// Opens a dynamic channel as if the server sent a DVC Create Request PDU
+    fn open_dynamic_channel(
+        processor: &mut Processor,
+        channel_name: &str,
+        channel_id: u32,
+        priority: dvc::ChannelPriority,
+    ) {
+        processor
+            .dynamic_channels
+            .get_mut(channel_name)
+            .unwrap()
+            .open(OpenedChannel {
+                channel_id,
+                channel_id_type: FieldType::U8,
+                priority,
+            })
+            .unwrap();
+        processor.channel_map.insert(channel_id, channel_name.to_owned());
+    }
+
+    /// Returns the DVC PDUs of the frames written one after the other, along with their data
+    fn decode_dvc_frames(mut frames: &[u8]) -> Vec<(dvc::ClientPdu, Vec<u8>)> {
+        let mut pdus = Vec::new();
+
+        while !frames.is_empty() {
+            let length = ironrdp_pdu::find_size(frames).unwrap().unwrap().length;
+            let (frame, rest) = frames.split_at(length);
+
+            let request = ironrdp_pdu::decode::<mcs::SendDataRequest<'_>>(frame).unwrap();
+            assert_eq!(
+                (request.initiator_id, request.channel_id),
+                (USER_CHANNEL_ID, DRDYNVC_CHANNEL_ID)
+            );
+
+            let mut user_data = request.user_data.as_ref();
+            let channel_header = vc::ChannelPduHeader::from_buffer(&mut user_data).unwrap();
+            assert_eq!(channel_header.length as usize, user_data.len());
+
+            let dvc_length = user_data.len();
+            let dvc_pdu = dvc::ClientPdu::from_buffer(&mut user_data, dvc_length).unwrap();
+            pdus.push((dvc_pdu, user_data.to_vec()));
+
+            frames = rest;
+        }
+
+        pdus
+    }
+
+    fn opened_channel(channel_id: u32) -> OpenedChannel {
+        OpenedChannel {
+            channel_id,
+            channel_id_type: FieldType::U8,
+            priority: dvc::ChannelPriority::Low,
+        }
+    }
+
+    #[test]
+    fn dvc_message_fitting_in_a_data_pdu_is_not_fragmented() {
+        // 1600 bytes minus the header and the 1-byte channel ID
+        let message = vec![0xAB; 1598];
+
+        let mut output = Vec::new();
+        encode_dvc_data(
+            USER_CHANNEL_ID,
+            DRDYNVC_CHANNEL_ID,
+            opened_channel(3),
+            &message,
+            &mut output,
+        )
+        .unwrap();
+
+        let pdus = decode_dvc_frames(&output);
+
+        assert_eq!(pdus.len(), 1);
+        assert!(matches!(&pdus[0].0, dvc::ClientPdu::Data(data) if data.channel_id == 3 && data.data_size == 1598));
+        assert_eq!(pdus[0].1, message);
+    }
+
+    #[test]
+    fn dvc_message_is_fragmented_at_the_pdu_size_limit() {
+        let message = (0..4000).map(|i| i as u8).collect::<Vec<_>>();
+
+        let mut output = Vec::new();
+        encode_dvc_data(
+            USER_CHANNEL_ID,
+            DRDYNVC_CHANNEL_ID,
+            opened_channel(3),
+            &message,
+            &mut output,
+        )
+        .unwrap();
+
+        let pdus = decode_dvc_frames(&output);
+
+        // Data First PDU: 1600 bytes minus the header, the 1-byte channel ID and the 2-byte total size
+        assert_eq!(pdus.len(), 3);
+        assert!(matches!(
+            &pdus[0].0,
+            dvc::ClientPdu::DataFirst(data_first)
+                if data_first.channel_id == 3 && data_first.total_data_size == 4000 && data_first.data_size == 1596
+        ));
+        assert!(matches!(&pdus[1].0, dvc::ClientPdu::Data(data) if data.data_size == 1598));
+        assert!(matches!(&pdus[2].0, dvc::ClientPdu::Data(data) if data.data_size == 806));
+
+        assert_eq!(
+            pdus.iter()
+                .flat_map(|(_, data)| data.iter().copied())
+                .collect::<Vec<_>>(),
+            message
+        );
+    }
+
+    #[test]
+    fn dvc_message_one_byte_over_the_limit_is_fragmented() {
+        let message = vec![0xCD; 1599];
+
+        let mut output = Vec::new();
+        encode_dvc_data(
+            USER_CHANNEL_ID,
+            DRDYNVC_CHANNEL_ID,
+            opened_channel(3),
+            &message,
+            &mut output,
+        )
+        .unwrap();
+
+        let pdus = decode_dvc_frames(&output);
+
+        assert_eq!(pdus.len(), 2);
+        assert!(matches!(&pdus[0].0, dvc::ClientPdu::DataFirst(data_first) if data_first.data_size == 1596));
+        assert!(matches!(&pdus[1].0, dvc::ClientPdu::Data(data) if data.data_size == 3));
+        assert_eq!(
+            pdus.iter()
+                .flat_map(|(_, data)| data.iter().copied())
+                .collect::<Vec<_>>(),
+            message
+        );
+    }
+
+    #[test]
+    fn empty_dvc_message_is_sent_in_a_data_pdu() {
+        let mut output = Vec::new();
+        encode_dvc_data(USER_CHANNEL_ID, DRDYNVC_CHANNEL_ID, opened_channel(3), &[], &mut output).unwrap();
+
+        let pdus = decode_dvc_frames(&output);
+
+        assert_eq!(pdus.len(), 1);
+        assert!(matches!(&pdus[0].0, dvc::ClientPdu::Data(data) if data.data_size == 0));
+    }
+
+    #[test]
+    fn dvc_data_is_reassembled() {
+        let mut complete_data = CompleteData::new();
+
+        assert_eq!(complete_data.process_data_first_pdu(10, b"0123".to_vec()), None);
+        assert_eq!(complete_data.process_data_pdu(b"4567".to_vec()), None);
+        assert_eq!(
+            complete_data.process_data_pdu(b"89".to_vec()),
+            Some(b"0123456789".to_vec())
+        );
+
+        // The next message is not fragmented
+        assert_eq!(complete_data.process_data_pdu(b"abc".to_vec()), Some(b"abc".to_vec()));
+    }
+
+    #[test]
+    fn dvc_data_first_pdu_with_the_whole_message_is_complete() {
+        let mut complete_data = CompleteData::new();
+
+        assert_eq!(
+            complete_data.process_data_first_pdu(4, b"data".to_vec()),
+            Some(b"data".to_vec())
+        );
+        assert_eq!(complete_data, CompleteData::new());
+    }
+
+    #[test]
+    fn dvc_data_longer_than_the_total_size_is_dropped() {
+        let mut complete_data = CompleteData::new();
+
+        assert_eq!(complete_data.process_data_first_pdu(6, b"0123".to_vec()), None);
+        assert_eq!(complete_data.process_data_pdu(b"456".to_vec()), None);
+        assert_eq!(complete_data, CompleteData::new());
+    }
+
+    #[test]
+    fn dvc_data_first_pdu_discards_the_incomplete_message() {
+        let mut complete_data = CompleteData::new();
+
+        assert_eq!(complete_data.process_data_first_pdu(10, b"abcd".to_vec()), None);
+        assert_eq!(complete_data.process_data_first_pdu(6, b"012".to_vec()), None);
+        assert_eq!(
+            complete_data.process_data_pdu(b"345".to_vec()),
+            Some(b"012345".to_vec())
+        );
+    }
+
+    #[test]
+    fn fragmented_dvc_message_is_processed_once_complete() {
+        let message = (0..4000).map(|i| i as u8).collect::<Vec<_>>();
+
+        let mut output = Vec::new();
+        encode_dvc_data(
+            USER_CHANNEL_ID,
+            DRDYNVC_CHANNEL_ID,
+            opened_channel(3),
+            &message,
+            &mut output,
+        )
+        .unwrap();
+
+        let mut processor = processor(vec![Box::new(EchoChannel("echo"))]);
+        open_dynamic_channel(&mut processor, "echo", 3, dvc::ChannelPriority::Low);
+
+        let dynamic_channel = processor.opened_dynamic_channel_mut(3).unwrap();
+
+        for (pdu, data) in decode_dvc_frames(&output) {
+            match pdu {
+                dvc::ClientPdu::DataFirst(data_first) => dynamic_channel
+                    .process_data_first_pdu(data_first.total_data_size as usize, data)
+                    .unwrap(),
+                dvc::ClientPdu::Data(_) => dynamic_channel.process_data_pdu(data).unwrap(),
+                unexpected => panic!("unexpected DVC PDU: {unexpected:?}"),
+            }
+        }
+
+        assert_eq!(dynamic_channel.outbound_queue, [message]);
+    }
+
+    #[test]
+    fn dvc_queues_are_flushed_by_priority() {
+        let mut processor = processor(vec![
+            Box::new(EchoChannel("low")),
+            Box::new(EchoChannel("high")),
+            Box::new(EchoChannel("medium")),
+            Box::new(EchoChannel("high-2")),
+            Box::new(EchoChannel("closed")),
+        ]);
+
+        open_dynamic_channel(&mut processor, "low", 3, dvc::ChannelPriority::Low);
+        open_dynamic_channel(&mut processor, "high", 5, dvc::ChannelPriority::High);
+        open_dynamic_channel(&mut processor, "medium", 4, dvc::ChannelPriority::Medium);
+        open_dynamic_channel(&mut processor, "high-2", 2, dvc::ChannelPriority::High);
+
+        for channel_name in ["low", "high", "medium", "high-2", "closed"] {
+            processor.queue_dvc_message(channel_name, b"first".to_vec()).unwrap();
+            processor.queue_dvc_message(channel_name, b"second".to_vec()).unwrap();
+        }
+
+        let mut output = Vec::new();
+        let written = processor.encode_queued_dvc_messages(&mut output).unwrap();
+
+        let sent = decode_dvc_frames(&output[..written])
+            .into_iter()
+            .map(|(pdu, data)| match pdu {
+                dvc::ClientPdu::Data(data_pdu) => (data_pdu.channel_id, data),
+                unexpected => panic!("unexpected DVC PDU: {unexpected:?}"),
+            })
+            .collect::<Vec<_>>();
+
+        let expected = [2, 5, 4, 3]
+            .into_iter()
+            .flat_map(|channel_id| [(channel_id, b"first".to_vec()), (channel_id, b"second".to_vec())])
+            .collect::<Vec<_>>();
+
+        assert_eq!(sent, expected);
+
+        // The messages of the closed channel are kept until it is opened
+        assert_eq!(processor.encode_queued_dvc_messages(&mut output).unwrap(), 0);
+        assert_eq!(processor.dynamic_channels["closed"].outbound_queue.len(), 2);
+    }
+
+    /// Opens the Display Control channel and processes the capabilities advertised by the server
+    fn processor_with_display_control(max_num_monitors: u32, max_monitor_area: (u32, u32)) -> Processor {
+        let mut processor = processor(Vec::new());
+        open_dynamic_channel(&mut processor, RDP8_DISPLAY_PIPELINE_NAME, 6, dvc::ChannelPriority::Low);
+
+        let caps = display::ServerPdu::DisplayControlCaps(display::DisplayControlCapsPdu {
+            max_num_monitors,
+            max_monitor_area_factora: max_monitor_area.0,
+            max_monitor_area_factorb: max_monitor_area.1,
+        });
+
+        let mut caps_data = Vec::new();
+        caps.to_buffer(&mut caps_data).unwrap();
+
+        let responses = processor
+            .dynamic_channel_mut::<super::display::Handler>(RDP8_DISPLAY_PIPELINE_NAME)
+            .unwrap()
+            .process(&caps_data)
+            .unwrap();
+        assert!(responses.is_empty());
+
+        processor
+    }
+
+    /// Decodes the monitors of the DISPLAYCONTROL_MONITOR_LAYOUT_PDU sent in a resize request
+    fn decode_monitor_layout(frames: &[u8]) -> Vec<display::Monitor> {
+        let mut pdus = decode_dvc_frames(frames);
+        assert_eq!(pdus.len(), 1);
+
+        let (dvc_pdu, data) = pdus.pop().unwrap();
+        assert!(matches!(dvc_pdu, dvc::ClientPdu::Data(data) if data.channel_id == 6));
+
+        match display::ClientPdu::from_buffer(data.as_slice()).unwrap() {
+            display::ClientPdu::DisplayControlMonitorLayout(layout) => layout.monitors,
+        }
+    }
+
+    fn primary_monitor(width: u32, height: u32) -> display::Monitor {
+        display::Monitor {
+            flags: display::MonitorFlags::PRIMARY,
+            left: 0,
+            top: 0,
+            width,
+            height,
+            physical_width: 0,
+            physical_height: 0,
+            orientation: display::Orientation::Landscape,
+            desktop_scale_factor: 100,
+            device_scale_factor: 100,
+        }
+    }
+
+    #[test]
+    fn resize_requests_a_single_primary_monitor() {
+        let processor = processor_with_display_control(1, (8192, 8192));
+
+        let mut output = Vec::new();
+        let written = processor
+            .encode_resize(&mut output, 1920, 1080, None, None)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(decode_monitor_layout(&output[..written]), [primary_monitor(1920, 1080)]);
+    }
+
+    #[test]
+    fn resize_rounds_the_width_down_to_an_even_value() {
+        let processor = processor_with_display_control(1, (8192, 8192));
+
+        let mut output = Vec::new();
+        let written = processor
+            .encode_resize(&mut output, 1025, 767, None, None)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(decode_monitor_layout(&output[..written]), [primary_monitor(1024, 767)]);
+    }
+
+    #[test]
+    fn resize_clamps_the_monitor_size() {
+        let processor = processor_with_display_control(1, (8192, 8192));
+
+        let mut output = Vec::new();
+
+        let written = processor
+            .encode_resize(&mut output, 100, 10000, None, None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(decode_monitor_layout(&output[..written]), [primary_monitor(200, 8192)]);
+
+        let written = processor
+            .encode_resize(&mut output, 9001, 0, None, None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(decode_monitor_layout(&output[..written]), [primary_monitor(8192, 200)]);
+    }
+
+    #[test]
+    fn resize_sends_the_scale_factor_and_physical_size() {
+        let processor = processor_with_display_control(1, (8192, 8192));
+
+        let mut output = Vec::new();
+        let written = processor
+            .encode_resize(&mut output, 2560, 1440, Some(150), Some((600, 340)))
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(
+            decode_monitor_layout(&output[..written]),
+            [display::Monitor {
+                physical_width: 600,
+                physical_height: 340,
+                desktop_scale_factor: 150,
+                device_scale_factor: 140,
+                ..primary_monitor(2560, 1440)
+            }]
+        );
+    }
+
+    #[test]
+    fn resize_exceeding_the_server_area_is_rejected() {
+        let processor = processor_with_display_control(1, (1920, 1080));
+
+        let mut output = Vec::new();
+
+        assert!(processor
+            .encode_resize(&mut output, 1920, 1080, None, None)
+            .unwrap()
+            .is_ok());
+        assert!(processor
+            .encode_resize(&mut output, 1920, 1082, None, None)
+            .unwrap()
+            .is_err());
+    }
+
+    #[test]
+    fn resize_is_unavailable_without_the_display_control_channel() {
+        let mut output = Vec::new();
+
+        // The server did not open the channel
+        let mut processor = processor(Vec::new());
+        assert!(processor.encode_resize(&mut output, 1920, 1080, None, None).is_none());
+
+        // The server opened the channel, but did not advertise its capabilities yet
+        open_dynamic_channel(&mut processor, RDP8_DISPLAY_PIPELINE_NAME, 6, dvc::ChannelPriority::Low);
+        assert!(processor.encode_resize(&mut output, 1920, 1080, None, None).is_none());
+    }
+
+    #[test]
+    fn compressed_svc_chunks_are_decompressed_with_the_virtual_channel_history() {
+        // MPPC with the 8K history
+        let compressed = vc::ChannelControlFlags::PACKET_COMPRESSED;
+        let mut processor = processor_with_static_channel();
+
+        // 'a' 'b' 'c', then a copy of 6 bytes at offset 3
+        let frame = send_data_indication(1005, 9, compressed, &[0x61, 0x62, 0x63, 0xF0, 0xE8]);
+        assert!(processor.process(&frame).unwrap().is_empty());
+
+        // A copy of 3 bytes at offset 9, from the history of the previous chunk, then '}'
+        let next = [0xF2, 0x4F, 0xA0];
+        let frame = send_data_indication(1005, 4, compressed, &next);
+        assert!(processor.process(&frame).unwrap().is_empty());
+
+        // Same copy once the history is flushed
+        let frame = send_data_indication(1005, 4, compressed | vc::ChannelControlFlags::PACKET_FLUSHED, &next);
+        assert!(processor.process(&frame).unwrap().is_empty());
+
+        // The copy source wraps around the end of the history once at the front
+        let frame = send_data_indication(1005, 4, compressed | vc::ChannelControlFlags::PACKET_AT_FRONT, &next);
+        assert!(processor.process(&frame).unwrap().is_empty());
+
+        // Uncompressed data is passed through
+        let frame = send_data_indication(1005, 3, vc::ChannelControlFlags::empty(), b"raw");
+        assert!(processor.process(&frame).unwrap().is_empty());
+
+        let record = processor.static_channel::<RecordChannel>("record").unwrap();
+        assert_eq!(
+            record.messages,
+            [
+                b"abcabcabc".to_vec(),
+                b"abc}".to_vec(),
+                b"\0\0\0}".to_vec(),
+                b"\0\0\0}".to_vec(),
+                b"raw".to_vec(),
+            ]
+        );
+
+        // The slow-path and fast-path history is left untouched
+        let mut output = Vec::new();
+        let bulk_output = processor
+            .bulk_decompressor
+            .lock()
+            .unwrap()
+            .decompress(
+                &[0xF0, 0xC0],
+                CompressionType::K8,
+                CompressionFlags::COMPRESSED,
+                &mut output,
+            )
+            .unwrap();
+        assert_eq!(output[..bulk_output], [0, 0, 0]);
+    }
+
+    #[test]
+    fn compressed_dvc_pdu_is_decompressed() {
+        let mut processor = processor(Vec::new());
+
+        // DVC Capabilities Request PDU (version 2, no priority charges): four literals, then a copy of 8 bytes
+        // at offset 1
+        let frame = send_data_indication(
+            DRDYNVC_CHANNEL_ID,
+            12,
+            vc::ChannelControlFlags::PACKET_COMPRESSED,
+            &[0x50, 0x00, 0x02, 0x00, 0xF0, 0x70],
+        );
+
+        let outputs = processor.process(&frame).unwrap();
+
+        let [ProcessorOutput::ResponseFrame(response)] = outputs.as_slice() else {
+            panic!("expected a single response frame");
+        };
+
+        let pdus = decode_dvc_frames(response);
+        assert_eq!(pdus.len(), 1);
+        assert!(matches!(
+            &pdus[0].0,
+            dvc::ClientPdu::CapabilitiesResponse(response) if response.version == dvc::CapsVersion::V2
+        ));
+    }
+
+    #[test]
+    fn svc_chunk_with_an_invalid_compression_type_is_rejected() {
+        let mut processor = processor_with_static_channel();
+
+        let flags = vc::ChannelControlFlags::PACKET_COMPRESSED | vc::ChannelControlFlags::from_bits_retain(0x0005_0000);
+        let frame = send_data_indication(1005, 3, flags, b"abc");
+
+        assert!(processor.process(&frame).is_err());
+    }
+}
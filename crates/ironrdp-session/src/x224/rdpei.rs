@@ -0,0 +1,136 @@
+use core::any::Any;
+use core::cmp;
+
+use ironrdp_pdu::dvc::rdpei::{
+    ClientPdu, CsReadyFlags, CsReadyPdu, PenEventPdu, PenFrame, ScReadyPdu, ServerPdu, TouchEventPdu, TouchFrame,
+    PROTOCOL_V200,
+};
+use ironrdp_pdu::PduParsing;
+
+use super::{DynamicVirtualChannel, RDP_INPUT_CHANNEL_NAME};
+use crate::{Error, Result};
+
+/// Number of simultaneous touch contacts advertised to the server
+pub const MAX_TOUCH_CONTACTS: u16 = 10;
+
+#[derive(Default)]
+pub struct Handler {
+    /// Version negotiated with the server, set once the SC_READY PDU is received
+    protocol_version: Option<u32>,
+    /// Set while the server asks the client to stop sending input
+    suspended: bool,
+}
+
+impl Handler {
+    /// Protocol version negotiated with the server, if the channel is ready.
+    pub fn protocol_version(&self) -> Option<u32> {
+        self.protocol_version
+    }
+
+    /// Whether touch events can currently be sent to the server.
+    pub fn is_ready(&self) -> bool {
+        self.protocol_version.is_some() && !self.suspended
+    }
+
+    /// Whether pen events can currently be sent to the server, which requires version 2.0.0 of the protocol.
+    pub fn supports_pen(&self) -> bool {
+        self.is_ready() && self.protocol_version >= Some(PROTOCOL_V200)
+    }
+
+    /// Builds a RDPINPUT_TOUCH_EVENT_PDU carrying the given frames.
+    pub fn encode_touch_event(&self, frames: Vec<TouchFrame>) -> Result<Vec<u8>> {
+        self.check_ready()?;
+
+        if frames
+            .iter()
+            .any(|frame| frame.contacts.len() > usize::from(MAX_TOUCH_CONTACTS))
+        {
+            return Err(
+                Error::new("too many touch contacts in frame").with_reason(format!("maximum is {MAX_TOUCH_CONTACTS}"))
+            );
+        }
+
+        encode_pdu(ClientPdu::Touch(TouchEventPdu { encode_time: 0, frames }))
+    }
+
+    /// Builds a RDPINPUT_PEN_EVENT_PDU carrying the given frames.
+    pub fn encode_pen_event(&self, frames: Vec<PenFrame>) -> Result<Vec<u8>> {
+        self.check_ready()?;
+
+        if self.protocol_version < Some(PROTOCOL_V200) {
+            return Err(Error::new("pen input not supported by the server"));
+        }
+
+        encode_pdu(ClientPdu::Pen(PenEventPdu { encode_time: 0, frames }))
+    }
+
+    fn check_ready(&self) -> Result<()> {
+        if self.protocol_version.is_none() {
+            Err(Error::new("input extension not ready yet"))
+        } else if self.suspended {
+            Err(Error::new("input suspended by the server"))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl DynamicVirtualChannel for Handler {
+    fn channel_name(&self) -> &str {
+        RDP_INPUT_CHANNEL_NAME
+    }
+
+    fn process(&mut self, mut complete_data: &[u8]) -> Result<Vec<Vec<u8>>> {
+        let input_pdu = ServerPdu::from_buffer(&mut complete_data)?;
+        debug!(?input_pdu, "Received Input PDU");
+
+        match input_pdu {
+            ServerPdu::ScReady(ScReadyPdu { protocol_version, .. }) => {
+                // Pen input is the most recent feature supported
+                let protocol_version = cmp::min(protocol_version, PROTOCOL_V200);
+                self.protocol_version = Some(protocol_version);
+                self.suspended = false;
+
+                let cs_ready = ClientPdu::CsReady(CsReadyPdu {
+                    // Timestamps are not provided for the frames
+                    flags: CsReadyFlags::SHOW_TOUCH_VISUALS | CsReadyFlags::DISABLE_TIMESTAMP_INJECTION,
+                    protocol_version,
+                    max_touch_contacts: MAX_TOUCH_CONTACTS,
+                });
+
+                debug!(?cs_ready, "Send Input PDU");
+
+                Ok(vec![encode_pdu(cs_ready)?])
+            }
+            ServerPdu::SuspendInput => {
+                self.suspended = true;
+                Ok(Vec::new())
+            }
+            ServerPdu::ResumeInput => {
+                self.suspended = false;
+                Ok(Vec::new())
+            }
+        }
+    }
+
+    fn close(&mut self) {
+        // The server sends the SC_READY PDU again when the channel is reopened
+        self.protocol_version = None;
+        self.suspended = false;
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+fn encode_pdu(pdu: ClientPdu) -> Result<Vec<u8>> {
+    let mut buf = Vec::with_capacity(pdu.buffer_length());
+    pdu.to_buffer(&mut buf)?;
+
+    Ok(buf)
+}
@@ -0,0 +1,238 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use ironrdp_connector::{
+    Config, ConnectionActivationSequence, ConnectionActivationState, ConnectionResult, DesktopSize, MonitorConfig,
+};
+use ironrdp_graphics::image_processing::PixelFormat;
+use ironrdp_pdu::nego::SecurityProtocol;
+use ironrdp_pdu::rdp::capability_sets::{
+    Bitmap, BitmapDrawingFlags, CapabilitySet, DemandActive, MajorPlatformType, ServerDeactivateAll,
+    ServerDemandActive, SERVER_CHANNEL_ID,
+};
+use ironrdp_pdu::rdp::client_info::{CompressionType, PerformanceFlags};
+use ironrdp_pdu::rdp::finalization_messages::{FontPdu, MonitorLayoutPdu, SequenceFlags};
+use ironrdp_pdu::rdp::headers::{
+    CompressionFlags, ShareControlHeader, ShareControlPdu, ShareDataHeader, ShareDataPdu, StreamPriority,
+};
+use ironrdp_pdu::{gcc, mcs, Action, PduParsing as _};
+use ironrdp_session::image::DecodedImage;
+use ironrdp_session::{ActiveStage, ActiveStageOutput};
+
+const IO_CHANNEL_ID: u16 = 1003;
+const USER_CHANNEL_ID: u16 = 1007;
+const SHARE_ID: u32 = 0x0001_03ea;
+
+fn monitor(left: i32, width: u32, height: u32) -> MonitorConfig {
+    MonitorConfig {
+        left,
+        top: 0,
+        width,
+        height,
+        is_primary: left == 0,
+        physical_width: 0,
+        physical_height: 0,
+        orientation: gcc::MonitorOrientation::Landscape,
+        desktop_scale_factor: 100,
+        device_scale_factor: 100,
+    }
+}
+
+fn config() -> Config {
+    Config {
+        desktop_size: DesktopSize {
+            width: 1024,
+            height: 768,
+        },
+        security_protocol: SecurityProtocol::SSL,
+        username: "user".to_owned(),
+        password: "password".to_owned(),
+        domain: None,
+        client_build: 0,
+        client_name: "client".to_owned(),
+        keyboard_layout: 0,
+        keyboard_type: gcc::KeyboardType::IbmEnhanced,
+        keyboard_subtype: 0,
+        keyboard_functional_keys_count: 12,
+        ime_file_name: String::new(),
+        graphics: None,
+        bitmap: None,
+        dig_product_id: String::new(),
+        client_dir: String::new(),
+        platform: MajorPlatformType::Unspecified,
+        enable_audio_playback: false,
+        pointer_cache_size: 32,
+        compression_type: None,
+        timezone: None,
+        performance_flags: PerformanceFlags::empty(),
+        standard_rdp_security: false,
+        static_channels: Vec::new(),
+        monitors: Vec::new(),
+    }
+}
+
+/// Active stage of a session activated with a 1024x768 desktop
+fn active_stage() -> ActiveStage {
+    let desktop_size = DesktopSize {
+        width: 1024,
+        height: 768,
+    };
+
+    let mut connection_activation = ConnectionActivationSequence::new(config(), IO_CHANNEL_ID, USER_CHANNEL_ID);
+    connection_activation.state = ConnectionActivationState::Finalized {
+        desktop_size: desktop_size.clone(),
+        vc_chunk_size: 1600,
+    };
+
+    let connection_result = ConnectionResult {
+        io_channel_id: IO_CHANNEL_ID,
+        user_channel_id: USER_CHANNEL_ID,
+        static_channels: HashMap::new(),
+        desktop_size,
+        graphics_config: None,
+        connection_activation,
+        standard_security: None,
+    };
+
+    ActiveStage::new(connection_result, None, Vec::new(), Vec::new())
+}
+
+fn server_share_control(pdu: ShareControlPdu) -> Vec<u8> {
+    let header = ShareControlHeader {
+        share_control_pdu: pdu,
+        pdu_source: SERVER_CHANNEL_ID,
+        share_id: SHARE_ID,
+    };
+
+    let mut user_data = Vec::new();
+    header.to_buffer(&mut user_data).unwrap();
+
+    let indication = mcs::SendDataIndication {
+        initiator_id: SERVER_CHANNEL_ID,
+        channel_id: IO_CHANNEL_ID,
+        user_data: Cow::Owned(user_data),
+    };
+
+    let mut frame = Vec::new();
+    ironrdp_pdu::encode_buf(&indication, &mut frame).unwrap();
+    frame
+}
+
+fn server_share_data(pdu: ShareDataPdu) -> Vec<u8> {
+    server_share_control(ShareControlPdu::Data(ShareDataHeader {
+        share_data_pdu: pdu,
+        stream_priority: StreamPriority::Medium,
+        compression_flags: CompressionFlags::empty(),
+        compression_type: CompressionType::K8,
+    }))
+}
+
+fn server_demand_active(width: u16, height: u16) -> Vec<u8> {
+    server_share_control(ShareControlPdu::ServerDemandActive(ServerDemandActive {
+        pdu: DemandActive {
+            source_descriptor: "RDP".to_owned(),
+            capability_sets: vec![CapabilitySet::Bitmap(Bitmap {
+                pref_bits_per_pix: 32,
+                desktop_width: width,
+                desktop_height: height,
+                desktop_resize_flag: true,
+                drawing_flags: BitmapDrawingFlags::empty(),
+            })],
+        },
+    }))
+}
+
+/// Returns the Share Control PDUs of the client frames written one after the other
+fn decode_client_frames(mut frames: &[u8]) -> Vec<ShareControlPdu> {
+    let mut pdus = Vec::new();
+
+    while !frames.is_empty() {
+        let length = ironrdp_pdu::find_size(frames).unwrap().unwrap().length;
+        let (frame, rest) = frames.split_at(length);
+
+        let request = ironrdp_pdu::decode::<mcs::SendDataRequest<'_>>(frame).unwrap();
+        assert_eq!(
+            (request.initiator_id, request.channel_id),
+            (USER_CHANNEL_ID, IO_CHANNEL_ID)
+        );
+
+        let header = ShareControlHeader::from_buffer(request.user_data.as_ref()).unwrap();
+        pdus.push(header.share_control_pdu);
+
+        frames = rest;
+    }
+
+    pdus
+}
+
+#[test]
+fn deactivate_all_reactivates_the_session_with_the_new_desktop_size() {
+    let mut active_stage = active_stage();
+    let mut image = DecodedImage::new(PixelFormat::RgbA32, 1024, 768);
+
+    let deactivate_all = server_share_control(ShareControlPdu::ServerDeactivateAll(ServerDeactivateAll));
+    let outputs = active_stage.process(&mut image, Action::X224, &deactivate_all).unwrap();
+    assert!(outputs.is_empty());
+
+    // The client confirms the new desktop size and sends the finalization PDUs right away
+    let outputs = active_stage
+        .process(&mut image, Action::X224, &server_demand_active(2560, 1440))
+        .unwrap();
+
+    let [ActiveStageOutput::ResponseFrame(response)] = outputs.as_slice() else {
+        panic!("expected a single response frame");
+    };
+
+    let pdus = decode_client_frames(response);
+    assert_eq!(pdus.len(), 5);
+
+    let ShareControlPdu::ClientConfirmActive(confirm_active) = &pdus[0] else {
+        panic!("expected a Confirm Active PDU");
+    };
+
+    let bitmap_desktop_size =
+        confirm_active
+            .pdu
+            .capability_sets
+            .iter()
+            .find_map(|capability_set| match capability_set {
+                CapabilitySet::Bitmap(bitmap) => Some((bitmap.desktop_width, bitmap.desktop_height)),
+                _ => None,
+            });
+    assert_eq!(bitmap_desktop_size, Some((2560, 1440)));
+
+    // The server sends the Monitor Layout PDU during the finalization
+    let monitor_layout = vec![
+        monitor(0, 1280, 1440).to_monitor(),
+        monitor(1280, 1280, 1440).to_monitor(),
+    ];
+    let frame = server_share_data(ShareDataPdu::MonitorLayout(MonitorLayoutPdu {
+        monitors: monitor_layout.clone(),
+    }));
+    assert!(active_stage
+        .process(&mut image, Action::X224, &frame)
+        .unwrap()
+        .is_empty());
+
+    let font_map = server_share_data(ShareDataPdu::FontMap(FontPdu {
+        number: 0,
+        total_number: 0,
+        flags: SequenceFlags::FIRST | SequenceFlags::LAST,
+        entry_size: 4,
+    }));
+    let outputs = active_stage.process(&mut image, Action::X224, &font_map).unwrap();
+
+    assert!(matches!(
+        outputs.as_slice(),
+        [
+            ActiveStageOutput::GraphicsUpdate(update),
+            ActiveStageOutput::Reactivated(desktop_size),
+            ActiveStageOutput::MonitorLayout(layout),
+        ] if (update.right, update.bottom) == (2559, 1439)
+            && (desktop_size.width, desktop_size.height) == (2560, 1440)
+            && *layout == monitor_layout
+    ));
+
+    assert_eq!((image.width(), image.height()), (2560, 1440));
+    assert_eq!(image.monitor_layout(), monitor_layout.as_slice());
+}
@@ -0,0 +1,268 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::{CertificateFingerprint, CertificateVerifier};
+
+/// Outcome of looking up a server in a [`KnownHosts`] store.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HostStatus {
+    /// The presented certificate matches the recorded one.
+    Trusted,
+    /// The server has never been recorded.
+    Unknown,
+    /// The server is recorded with a different certificate.
+    Mismatch { known: CertificateFingerprint },
+}
+
+/// Store of trusted certificate fingerprints, indexed by server name.
+///
+/// The on-disk format is line-based: each line holds a server name and the SHA-256 fingerprint
+/// of its certificate, separated by whitespace. Empty lines and lines starting with `#` are ignored.
+#[derive(Debug, Clone, Default)]
+pub struct KnownHosts {
+    path: Option<PathBuf>,
+    entries: Vec<(String, CertificateFingerprint)>,
+}
+
+impl KnownHosts {
+    /// Creates an empty, in-memory store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads the store from the given file.
+    ///
+    /// A missing file is treated as an empty store, which will be created on [`KnownHosts::save`].
+    pub fn load(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+
+        let entries = match std::fs::read_to_string(&path) {
+            Ok(content) => parse(&content)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+
+        Ok(Self {
+            path: Some(path),
+            entries,
+        })
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Writes the store back to the file it was loaded from.
+    ///
+    /// This is a no-op for in-memory stores.
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let mut content = String::new();
+
+        for (host, fingerprint) in &self.entries {
+            content.push_str(host);
+            content.push(' ');
+            content.push_str(&fingerprint.to_string());
+            content.push('\n');
+        }
+
+        std::fs::write(path, content)
+    }
+
+    pub fn get(&self, host: &str) -> Option<&CertificateFingerprint> {
+        self.entries
+            .iter()
+            .find(|(known_host, _)| known_host.eq_ignore_ascii_case(host))
+            .map(|(_, fingerprint)| fingerprint)
+    }
+
+    pub fn check(&self, host: &str, fingerprint: &CertificateFingerprint) -> HostStatus {
+        match self.get(host) {
+            Some(known) if known == fingerprint => HostStatus::Trusted,
+            Some(known) => HostStatus::Mismatch { known: *known },
+            None => HostStatus::Unknown,
+        }
+    }
+
+    /// Records the fingerprint for the given server, replacing any previous one.
+    pub fn insert(&mut self, host: &str, fingerprint: CertificateFingerprint) {
+        match self
+            .entries
+            .iter_mut()
+            .find(|(known_host, _)| known_host.eq_ignore_ascii_case(host))
+        {
+            Some((_, known)) => *known = fingerprint,
+            None => self.entries.push((host.to_owned(), fingerprint)),
+        }
+    }
+
+    pub fn remove(&mut self, host: &str) -> Option<CertificateFingerprint> {
+        let idx = self
+            .entries
+            .iter()
+            .position(|(known_host, _)| known_host.eq_ignore_ascii_case(host))?;
+        Some(self.entries.remove(idx).1)
+    }
+}
+
+fn parse(content: &str) -> io::Result<Vec<(String, CertificateFingerprint)>> {
+    let mut entries = Vec::new();
+
+    for (line_idx, line) in content.lines().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let invalid_line = || {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid entry at line {}", line_idx + 1),
+            )
+        };
+
+        let mut fields = line.split_whitespace();
+        let (Some(host), Some(fingerprint), None) = (fields.next(), fields.next(), fields.next()) else {
+            return Err(invalid_line());
+        };
+
+        let fingerprint = fingerprint.parse().map_err(|_| invalid_line())?;
+
+        entries.push((host.to_owned(), fingerprint));
+    }
+
+    Ok(entries)
+}
+
+/// Trust-on-first-use verifier backed by a [`KnownHosts`] store.
+///
+/// Recorded servers are accepted silently as long as they present the same certificate.
+/// Otherwise, `prompt` is called with the server name, the presented fingerprint and the lookup status
+/// (never [`HostStatus::Trusted`]); when it returns `true`, the fingerprint is recorded and the store saved.
+///
+/// `prompt` is called synchronously from within the TLS handshake, that is, from an async task. A prompt blocking
+/// on user input should tell the runtime about it (e.g.: with `tokio::task::block_in_place`).
+pub struct TrustOnFirstUse<F> {
+    known_hosts: Mutex<KnownHosts>,
+    prompt: F,
+}
+
+impl<F> TrustOnFirstUse<F>
+where
+    F: Fn(&str, &CertificateFingerprint, HostStatus) -> bool + Send + Sync,
+{
+    pub fn new(known_hosts: KnownHosts, prompt: F) -> Self {
+        Self {
+            known_hosts: Mutex::new(known_hosts),
+            prompt,
+        }
+    }
+
+    pub fn into_known_hosts(self) -> KnownHosts {
+        self.known_hosts.into_inner().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<F> CertificateVerifier for TrustOnFirstUse<F>
+where
+    F: Fn(&str, &CertificateFingerprint, HostStatus) -> bool + Send + Sync,
+{
+    fn verify(&self, server_name: &str, chain: &[&[u8]]) -> bool {
+        let Some(end_entity) = chain.first() else {
+            return false;
+        };
+
+        let fingerprint = CertificateFingerprint::of(end_entity);
+
+        let mut known_hosts = self.known_hosts.lock().unwrap_or_else(|e| e.into_inner());
+
+        let status = known_hosts.check(server_name, &fingerprint);
+
+        if status == HostStatus::Trusted {
+            return true;
+        }
+
+        if !(self.prompt)(server_name, &fingerprint, status) {
+            return false;
+        }
+
+        known_hosts.insert(server_name, fingerprint);
+
+        // The user explicitly accepted the certificate: failing to persist it only means being asked again next time.
+        if let Err(error) = known_hosts.save() {
+            warn!(%error, server_name, "Failed to save the known hosts");
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KNOWN_HOSTS: &str = "\
+# comment
+server.example.com BA:78:16:BF:8F:01:CF:EA:41:41:40:DE:5D:AE:22:23:B0:03:61:A3:96:17:7A:9C:B4:10:FF:61:F2:00:15:AD
+
+192.168.1.10 ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad
+";
+
+    #[test]
+    fn parse_known_hosts() {
+        let entries = parse(KNOWN_HOSTS).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].0, "server.example.com");
+        assert_eq!(entries[1].0, "192.168.1.10");
+        assert_eq!(entries[0].1, CertificateFingerprint::of(b"abc"));
+        assert_eq!(entries[1].1, CertificateFingerprint::of(b"abc"));
+    }
+
+    #[test]
+    fn parse_known_hosts_rejects_malformed_line() {
+        assert!(parse("server.example.com\n").is_err());
+        assert!(parse("server.example.com AB:CD\n").is_err());
+        assert!(parse("server.example.com AB:CD extra\n").is_err());
+    }
+
+    #[test]
+    fn check_and_insert() {
+        let mut known_hosts = KnownHosts::new();
+        let first = CertificateFingerprint::of(b"first");
+        let second = CertificateFingerprint::of(b"second");
+
+        assert_eq!(known_hosts.check("server", &first), HostStatus::Unknown);
+
+        known_hosts.insert("server", first);
+        assert_eq!(known_hosts.check("SERVER", &first), HostStatus::Trusted);
+        assert_eq!(
+            known_hosts.check("server", &second),
+            HostStatus::Mismatch { known: first }
+        );
+
+        known_hosts.insert("server", second);
+        assert_eq!(known_hosts.check("server", &second), HostStatus::Trusted);
+        assert_eq!(known_hosts.remove("server"), Some(second));
+        assert_eq!(known_hosts.check("server", &second), HostStatus::Unknown);
+    }
+
+    #[test]
+    fn trust_on_first_use() {
+        let verifier = TrustOnFirstUse::new(KnownHosts::new(), |_: &str, _: &CertificateFingerprint, status| {
+            status == HostStatus::Unknown
+        });
+
+        assert!(verifier.verify("server", &[b"first"]));
+        assert!(verifier.verify("server", &[b"first"]));
+        assert!(!verifier.verify("server", &[b"second"]));
+        assert!(!verifier.verify("server", &[]));
+
+        let known_hosts = verifier.into_known_hosts();
+        assert_eq!(known_hosts.get("server"), Some(&CertificateFingerprint::of(b"first")));
+    }
+}
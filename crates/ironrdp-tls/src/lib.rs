@@ -1,14 +1,32 @@
+#[macro_use]
+extern crate tracing;
+
+mod known_hosts;
+mod verification;
+
 use std::io;
 
 use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt as _};
 
+pub use self::known_hosts::{HostStatus, KnownHosts, TrustOnFirstUse};
+pub use self::verification::{
+    CertificateFingerprint, CertificateVerification, CertificateVerifier, InvalidFingerprint,
+};
+
 #[cfg(feature = "rustls")]
 pub type TlsStream<S> = tokio_rustls::client::TlsStream<S>;
 
 #[cfg(all(feature = "native-tls", not(feature = "rustls")))]
 pub type TlsStream<S> = tokio_native_tls::TlsStream<S>;
 
-pub async fn upgrade<S>(stream: S, server_name: &str) -> io::Result<(TlsStream<S>, Vec<u8>)>
+/// Performs the TLS handshake, verifying the server certificate according to `verification`.
+///
+/// On success, the public key of the server certificate is returned alongside the stream, as required by CredSSP.
+pub async fn upgrade<S>(
+    stream: S,
+    server_name: &str,
+    verification: &CertificateVerification,
+) -> io::Result<(TlsStream<S>, Vec<u8>)>
 where
     S: Unpin + AsyncRead + AsyncWrite,
 {
@@ -18,9 +36,11 @@ where
         // https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-cssp/385a7489-d46b-464c-b224-f7340e308a5c
         // Option is available starting rustls 0.21
 
+        let verifier = rustls_verifier::PolicyVerifier::new(verification)?;
+
         let mut config = tokio_rustls::rustls::client::ClientConfig::builder()
             .with_safe_defaults()
-            .with_custom_certificate_verifier(std::sync::Arc::new(danger::NoCertificateVerification))
+            .with_custom_certificate_verifier(std::sync::Arc::new(verifier))
             .with_no_client_auth();
 
         // This adds support for the SSLKEYLOGFILE env variable (https://wiki.wireshark.org/TLS#using-the-pre-master-secret)
@@ -28,7 +48,9 @@ where
 
         let config = std::sync::Arc::new(config);
 
-        let server_name = server_name.try_into().unwrap();
+        let server_name = server_name
+            .try_into()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
 
         tokio_rustls::TlsConnector::from(config)
             .connect(server_name, stream)
@@ -37,9 +59,32 @@ where
 
     #[cfg(all(feature = "native-tls", not(feature = "rustls")))]
     let mut tls_stream = {
-        let connector = tokio_native_tls::native_tls::TlsConnector::builder()
-            .danger_accept_invalid_certs(true)
-            .use_sni(false)
+        use tokio_native_tls::native_tls;
+
+        let mut builder = native_tls::TlsConnector::builder();
+
+        builder.use_sni(false);
+
+        match verification {
+            CertificateVerification::SystemRoots => {}
+            CertificateVerification::CustomCa(certificates) => {
+                builder.disable_built_in_roots(true);
+
+                for certificate in certificates {
+                    let certificate = native_tls::Certificate::from_der(certificate)
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+                    builder.add_root_certificate(certificate);
+                }
+            }
+            // Checked manually once the handshake is complete, before anything is sent to the server
+            CertificateVerification::Fingerprints(_)
+            | CertificateVerification::Custom(_)
+            | CertificateVerification::DangerousAcceptAny => {
+                builder.danger_accept_invalid_certs(true);
+            }
+        }
+
+        let connector = builder
             .build()
             .map(tokio_native_tls::TlsConnector::from)
             .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
@@ -50,8 +95,6 @@ where
             .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?
     };
 
-    tls_stream.flush().await?;
-
     #[cfg(feature = "rustls")]
     let server_public_key = {
         let cert = tls_stream
@@ -71,13 +114,27 @@ where
             .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?
             .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "peer certificate is missing"))?;
         let cert = cert.to_der().map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+
+        // native-tls does not expose the intermediate certificates
+        if verification.verify_presented_chain(server_name, &[&cert]) == Some(false) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "server certificate rejected by the verification policy",
+            ));
+        }
+
         extract_tls_server_public_key(&cert)?
     };
 
+    tls_stream.flush().await?;
+
     Ok((tls_stream, server_public_key))
 }
 
-fn extract_tls_server_public_key(cert: &[u8]) -> io::Result<Vec<u8>> {
+/// Extracts the public key from a DER-encoded X.509 certificate, as expected by CredSSP.
+///
+/// Servers use it on their own certificate to build the acceptor.
+pub fn extract_tls_server_public_key(cert: &[u8]) -> io::Result<Vec<u8>> {
     use x509_cert::der::Decode as _;
 
     let cert = x509_cert::Certificate::from_der(cert).map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
@@ -94,25 +151,91 @@ fn extract_tls_server_public_key(cert: &[u8]) -> io::Result<Vec<u8>> {
 }
 
 #[cfg(feature = "rustls")]
-mod danger {
+mod rustls_verifier {
+    use std::io;
     use std::time::SystemTime;
 
-    use tokio_rustls::rustls::client::ServerCertVerified;
-    use tokio_rustls::rustls::{Certificate, Error, ServerName};
+    use tokio_rustls::rustls::client::{ServerCertVerified, ServerCertVerifier, WebPkiVerifier};
+    use tokio_rustls::rustls::{Certificate, CertificateError, Error, RootCertStore, ServerName};
+
+    use crate::CertificateVerification;
 
-    pub(super) struct NoCertificateVerification;
+    pub(super) struct PolicyVerifier {
+        policy: CertificateVerification,
+        webpki: Option<WebPkiVerifier>,
+    }
+
+    impl PolicyVerifier {
+        pub(super) fn new(policy: &CertificateVerification) -> io::Result<Self> {
+            let roots = match policy {
+                CertificateVerification::SystemRoots => {
+                    let mut roots = RootCertStore::empty();
+                    let certificates = rustls_native_certs::load_native_certs()?;
+                    let (added, _ignored) = roots.add_parsable_certificates(&certificates);
+
+                    if added == 0 {
+                        return Err(io::Error::new(
+                            io::ErrorKind::NotFound,
+                            "no usable root certificate found in the system trust store",
+                        ));
+                    }
+
+                    Some(roots)
+                }
+                CertificateVerification::CustomCa(certificates) => {
+                    let mut roots = RootCertStore::empty();
+
+                    for certificate in certificates {
+                        roots
+                            .add(&Certificate(certificate.clone()))
+                            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+                    }
+
+                    Some(roots)
+                }
+                CertificateVerification::Fingerprints(_)
+                | CertificateVerification::Custom(_)
+                | CertificateVerification::DangerousAcceptAny => None,
+            };
+
+            Ok(Self {
+                policy: policy.clone(),
+                webpki: roots.map(|roots| WebPkiVerifier::new(roots, None)),
+            })
+        }
+    }
 
-    impl tokio_rustls::rustls::client::ServerCertVerifier for NoCertificateVerification {
+    impl ServerCertVerifier for PolicyVerifier {
         fn verify_server_cert(
             &self,
-            _end_entity: &Certificate,
-            _intermediates: &[Certificate],
-            _server_name: &ServerName,
-            _scts: &mut dyn Iterator<Item = &[u8]>,
-            _ocsp_response: &[u8],
-            _now: SystemTime,
+            end_entity: &Certificate,
+            intermediates: &[Certificate],
+            server_name: &ServerName,
+            scts: &mut dyn Iterator<Item = &[u8]>,
+            ocsp_response: &[u8],
+            now: SystemTime,
         ) -> Result<ServerCertVerified, Error> {
-            Ok(tokio_rustls::rustls::client::ServerCertVerified::assertion())
+            if let Some(webpki) = &self.webpki {
+                return webpki.verify_server_cert(end_entity, intermediates, server_name, scts, ocsp_response, now);
+            }
+
+            let server_name = match server_name {
+                ServerName::DnsName(name) => name.as_ref().to_owned(),
+                ServerName::IpAddress(address) => address.to_string(),
+                _ => String::new(),
+            };
+
+            let chain = core::iter::once(end_entity)
+                .chain(intermediates)
+                .map(|certificate| certificate.0.as_slice())
+                .collect::<Vec<_>>();
+
+            match self.policy.verify_presented_chain(&server_name, &chain) {
+                Some(false) => Err(Error::InvalidCertificate(
+                    CertificateError::ApplicationVerificationFailure,
+                )),
+                Some(true) | None => Ok(ServerCertVerified::assertion()),
+            }
         }
     }
 }
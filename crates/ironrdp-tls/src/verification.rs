@@ -0,0 +1,237 @@
+use core::fmt;
+use core::str::FromStr;
+use std::io;
+use std::sync::Arc;
+
+use sha2::{Digest as _, Sha256};
+
+/// Policy used to decide whether the certificate presented by the server is trusted.
+#[derive(Clone)]
+pub enum CertificateVerification {
+    /// Verifies the certificate chain and the server name against the platform trust store.
+    SystemRoots,
+    /// Verifies the certificate chain and the server name against the provided DER-encoded CA certificates only.
+    CustomCa(Vec<Vec<u8>>),
+    /// Accepts the server certificate only if its SHA-256 fingerprint is one of the pinned ones.
+    ///
+    /// The chain itself is not validated, which makes this suitable for self-signed certificates.
+    Fingerprints(Vec<CertificateFingerprint>),
+    /// Delegates the decision to the provided verifier (e.g.: a trust-on-first-use prompt).
+    Custom(Arc<dyn CertificateVerifier>),
+    /// Accepts any certificate.
+    ///
+    /// The connection is then trivially vulnerable to man-in-the-middle attacks.
+    DangerousAcceptAny,
+}
+
+impl CertificateVerification {
+    /// Builds a [`CertificateVerification::CustomCa`] policy from a PEM bundle containing one or more certificates.
+    pub fn custom_ca_from_pem(pem: &[u8]) -> io::Result<Self> {
+        use x509_cert::der::Encode as _;
+
+        let certificates =
+            x509_cert::Certificate::load_pem_chain(pem).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        if certificates.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "no certificate found in PEM bundle",
+            ));
+        }
+
+        let certificates = certificates
+            .iter()
+            .map(|certificate| certificate.to_der())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        Ok(Self::CustomCa(certificates))
+    }
+
+    /// Wraps a closure into a [`CertificateVerification::Custom`] policy.
+    pub fn custom<F>(verifier: F) -> Self
+    where
+        F: Fn(&str, &[&[u8]]) -> bool + Send + Sync + 'static,
+    {
+        Self::Custom(Arc::new(verifier))
+    }
+
+    /// Checks a presented chain against the policies which are not handled by the TLS backend itself.
+    ///
+    /// Returns `None` when the backend is in charge of the verification.
+    pub(crate) fn verify_presented_chain(&self, server_name: &str, chain: &[&[u8]]) -> Option<bool> {
+        match self {
+            CertificateVerification::Fingerprints(pins) => {
+                let Some(end_entity) = chain.first() else {
+                    return Some(false);
+                };
+
+                let fingerprint = CertificateFingerprint::of(end_entity);
+
+                Some(pins.contains(&fingerprint))
+            }
+            CertificateVerification::Custom(verifier) => Some(verifier.verify(server_name, chain)),
+            CertificateVerification::SystemRoots
+            | CertificateVerification::CustomCa(_)
+            | CertificateVerification::DangerousAcceptAny => None,
+        }
+    }
+}
+
+impl fmt::Debug for CertificateVerification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SystemRoots => write!(f, "SystemRoots"),
+            Self::CustomCa(certificates) => write!(f, "CustomCa({} certificate(s))", certificates.len()),
+            Self::Fingerprints(pins) => f.debug_tuple("Fingerprints").field(pins).finish(),
+            Self::Custom(_) => write!(f, "Custom"),
+            Self::DangerousAcceptAny => write!(f, "DangerousAcceptAny"),
+        }
+    }
+}
+
+/// Custom certificate verification logic.
+///
+/// The verifier receives the server name and the DER-encoded certificate chain as presented by the server,
+/// starting with the end-entity certificate. Depending on the TLS backend, the chain may only hold the
+/// end-entity certificate.
+///
+/// This is called during the TLS handshake, so blocking for a user decision is acceptable but delays the
+/// handshake accordingly.
+pub trait CertificateVerifier: Send + Sync {
+    /// Returns `true` if the presented certificate is trusted.
+    fn verify(&self, server_name: &str, chain: &[&[u8]]) -> bool;
+}
+
+impl<F> CertificateVerifier for F
+where
+    F: Fn(&str, &[&[u8]]) -> bool + Send + Sync,
+{
+    fn verify(&self, server_name: &str, chain: &[&[u8]]) -> bool {
+        (self)(server_name, chain)
+    }
+}
+
+/// SHA-256 fingerprint of a DER-encoded certificate.
+///
+/// Displayed as colon-separated uppercase hexadecimal bytes (e.g.: `AB:CD:…`), which is also the format
+/// accepted by [`FromStr`], although separators are optional and the case is ignored.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CertificateFingerprint(pub [u8; 32]);
+
+impl CertificateFingerprint {
+    /// Computes the fingerprint of a DER-encoded certificate.
+    pub fn of(certificate: &[u8]) -> Self {
+        Self(Sha256::digest(certificate).into())
+    }
+}
+
+impl fmt::Display for CertificateFingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (idx, byte) in self.0.iter().enumerate() {
+            if idx != 0 {
+                write!(f, ":")?;
+            }
+            write!(f, "{byte:02X}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for CertificateFingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CertificateFingerprint({self})")
+    }
+}
+
+impl FromStr for CertificateFingerprint {
+    type Err = InvalidFingerprint;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let digits = s.trim().bytes().filter(|b| *b != b':').collect::<Vec<u8>>();
+
+        if digits.len() != 64 || !digits.iter().all(u8::is_ascii_hexdigit) {
+            return Err(InvalidFingerprint);
+        }
+
+        let mut fingerprint = [0; 32];
+
+        for (byte, pair) in fingerprint.iter_mut().zip(digits.chunks_exact(2)) {
+            let pair = core::str::from_utf8(pair).map_err(|_| InvalidFingerprint)?;
+            *byte = u8::from_str_radix(pair, 16).map_err(|_| InvalidFingerprint)?;
+        }
+
+        Ok(Self(fingerprint))
+    }
+}
+
+/// Error returned when parsing a malformed [`CertificateFingerprint`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidFingerprint;
+
+impl fmt::Display for InvalidFingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid SHA-256 fingerprint (expected 32 hexadecimal bytes)")
+    }
+}
+
+impl std::error::Error for InvalidFingerprint {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FINGERPRINT: &str =
+        "BA:78:16:BF:8F:01:CF:EA:41:41:40:DE:5D:AE:22:23:B0:03:61:A3:96:17:7A:9C:B4:10:FF:61:F2:00:15:AD";
+
+    #[test]
+    fn fingerprint_of_certificate() {
+        let fingerprint = CertificateFingerprint::of(b"abc");
+        assert_eq!(fingerprint.to_string(), FINGERPRINT);
+    }
+
+    #[test]
+    fn fingerprint_from_str_ignores_separators_and_case() {
+        let expected = CertificateFingerprint::of(b"abc");
+        assert_eq!(FINGERPRINT.parse::<CertificateFingerprint>().unwrap(), expected);
+
+        let compact = FINGERPRINT.replace(':', "").to_lowercase();
+        assert_eq!(compact.parse::<CertificateFingerprint>().unwrap(), expected);
+    }
+
+    #[test]
+    fn fingerprint_from_str_rejects_malformed_input() {
+        assert!("AB:CD".parse::<CertificateFingerprint>().is_err());
+        assert!(FINGERPRINT
+            .replace("BA", "ZZ")
+            .parse::<CertificateFingerprint>()
+            .is_err());
+    }
+
+    #[test]
+    fn fingerprints_policy_checks_end_entity_only() {
+        let policy = CertificateVerification::Fingerprints(vec![CertificateFingerprint::of(b"end entity")]);
+
+        assert_eq!(
+            policy.verify_presented_chain("server", &[b"end entity", b"issuer"]),
+            Some(true)
+        );
+        assert_eq!(
+            policy.verify_presented_chain("server", &[b"issuer", b"end entity"]),
+            Some(false)
+        );
+        assert_eq!(policy.verify_presented_chain("server", &[]), Some(false));
+    }
+
+    #[test]
+    fn custom_policy_receives_chain() {
+        let policy = CertificateVerification::custom(|server_name, chain| server_name == "server" && chain.len() == 2);
+
+        assert_eq!(policy.verify_presented_chain("server", &[b"a", b"b"]), Some(true));
+        assert_eq!(policy.verify_presented_chain("other", &[b"a", b"b"]), Some(false));
+        assert_eq!(
+            CertificateVerification::SystemRoots.verify_presented_chain("server", &[]),
+            None
+        );
+    }
+}
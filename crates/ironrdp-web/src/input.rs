@@ -1,4 +1,4 @@
-use ironrdp::input::{MouseButton, MousePosition, Operation, Scancode, WheelRotations};
+use ironrdp::input::{MouseButton, MouseDelta, MousePosition, Operation, Scancode, WheelRotations};
 use smallvec::SmallVec;
 use wasm_bindgen::prelude::*;
 
@@ -32,6 +32,11 @@ impl DeviceEvent {
         Self(Operation::MouseMove(MousePosition { x, y }))
     }
 
+    /// Relative movement, e.g.: `movementX` and `movementY` of a `MouseEvent` while the pointer is locked
+    pub fn new_mouse_move_relative(x: i16, y: i16) -> Self {
+        Self(Operation::MouseMoveRelative(MouseDelta { x, y }))
+    }
+
     pub fn new_wheel_rotations(vertical: bool, rotation_units: i16) -> Self {
         Self(Operation::WheelRotations(WheelRotations {
             is_vertical: vertical,
@@ -46,6 +51,24 @@ impl DeviceEvent {
     pub fn new_key_released(scancode: u16) -> Self {
         Self(Operation::KeyReleased(Scancode::from_u16(scancode)))
     }
+
+    /// Builds a key press from a `KeyboardEvent.code` value, or returns `undefined` for unmapped keys
+    pub fn new_key_code_pressed(code: &str) -> Option<DeviceEvent> {
+        Scancode::from_web_code(code).map(|scancode| Self(Operation::KeyPressed(scancode)))
+    }
+
+    /// Builds a key release from a `KeyboardEvent.code` value, or returns `undefined` for unmapped keys
+    pub fn new_key_code_released(code: &str) -> Option<DeviceEvent> {
+        Scancode::from_web_code(code).map(|scancode| Self(Operation::KeyReleased(scancode)))
+    }
+
+    pub fn new_unicode_pressed(unicode: char) -> Self {
+        Self(Operation::UnicodeKeyPressed(unicode))
+    }
+
+    pub fn new_unicode_released(unicode: char) -> Self {
+        Self(Operation::UnicodeKeyReleased(unicode))
+    }
 }
 
 #[wasm_bindgen]
@@ -60,6 +83,11 @@ impl InputTransaction {
     pub fn add_event(&mut self, event: DeviceEvent) {
         self.0.push(event.0);
     }
+
+    /// Adds the events typing the given text, e.g.: for the content of a paste or of an IME composition.
+    pub fn add_text(&mut self, text: &str) {
+        self.0.extend(ironrdp::input::type_text(text));
+    }
 }
 
 impl IntoIterator for InputTransaction {
@@ -7,6 +7,7 @@ mod error;
 mod image;
 mod input;
 mod network_client;
+mod pointer;
 mod session;
 mod websocket;
 
@@ -0,0 +1,59 @@
+use ironrdp::graphics::pointer::DecodedPointer;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PointerKind {
+    /// The system default pointer should be displayed
+    Default,
+    Hidden,
+    /// The server moved the pointer to `x` and `y`
+    Position,
+    /// The pointer shape changed: the RGBA pixels are passed along
+    Bitmap,
+}
+
+/// Pointer update passed to the pointer callback, along with the RGBA pixels of `Bitmap` updates
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug)]
+pub struct PointerInfo {
+    pub kind: PointerKind,
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+    pub hotspot_x: u16,
+    pub hotspot_y: u16,
+}
+
+impl PointerInfo {
+    pub fn new(kind: PointerKind) -> Self {
+        Self {
+            kind,
+            x: 0,
+            y: 0,
+            width: 0,
+            height: 0,
+            hotspot_x: 0,
+            hotspot_y: 0,
+        }
+    }
+
+    pub fn position(x: u16, y: u16) -> Self {
+        Self {
+            x,
+            y,
+            ..Self::new(PointerKind::Position)
+        }
+    }
+
+    pub fn bitmap(pointer: &DecodedPointer) -> Self {
+        Self {
+            width: pointer.width,
+            height: pointer.height,
+            hotspot_x: pointer.hotspot_x,
+            hotspot_y: pointer.hotspot_y,
+            ..Self::new(PointerKind::Bitmap)
+        }
+    }
+}
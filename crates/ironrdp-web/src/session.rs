@@ -19,6 +19,7 @@ use crate::error::{IronRdpError, IronRdpErrorKind};
 use crate::image::{extract_partial_image, RectInfo};
 use crate::input::InputTransaction;
 use crate::network_client::PlaceholderNetworkClientFactory;
+use crate::pointer::{PointerInfo, PointerKind};
 use crate::websocket::WebSocketCompat;
 use crate::DesktopSize;
 
@@ -38,8 +39,10 @@ struct SessionBuilderInner {
     auth_token: Option<String>,
     pcb: Option<String>,
     desktop_size: DesktopSize,
+    keyboard_layout: u32,
     update_callback: Option<js_sys::Function>,
     update_callback_context: Option<JsValue>,
+    pointer_callback: Option<js_sys::Function>,
 }
 
 impl Default for SessionBuilderInner {
@@ -56,8 +59,10 @@ impl Default for SessionBuilderInner {
                 width: DEFAULT_WIDTH,
                 height: DEFAULT_HEIGHT,
             },
+            keyboard_layout: 0,
             update_callback: None,
             update_callback_context: None,
+            pointer_callback: None,
         }
     }
 }
@@ -112,6 +117,15 @@ impl SessionBuilder {
         self.clone()
     }
 
+    /// Advertises the default keyboard layout of the given locale, e.g.: `navigator.language`
+    pub fn keyboard_layout(&self, locale: String) -> SessionBuilder {
+        match ironrdp::input::keyboard_layout::klid_from_locale(&locale) {
+            Some(klid) => self.0.borrow_mut().keyboard_layout = klid,
+            None => warn!("Unknown keyboard layout for locale {locale}"),
+        }
+        self.clone()
+    }
+
     pub fn update_callback(&self, callback: js_sys::Function) -> SessionBuilder {
         self.0.borrow_mut().update_callback = Some(callback);
         self.clone()
@@ -122,6 +136,12 @@ impl SessionBuilder {
         self.clone()
     }
 
+    /// Receives the pointer updates (`PointerInfo` and RGBA pixels), called with the update callback context
+    pub fn pointer_callback(&self, callback: js_sys::Function) -> SessionBuilder {
+        self.0.borrow_mut().pointer_callback = Some(callback);
+        self.clone()
+    }
+
     pub async fn connect(&self) -> Result<Session, IronRdpError> {
         let (
             username,
@@ -132,8 +152,10 @@ impl SessionBuilder {
             auth_token,
             pcb,
             desktop_size,
+            keyboard_layout,
             update_callback,
             update_callback_context,
+            pointer_callback,
         );
 
         {
@@ -146,13 +168,15 @@ impl SessionBuilder {
             auth_token = inner.auth_token.clone().expect("auth_token");
             pcb = inner.pcb.clone();
             desktop_size = inner.desktop_size.clone();
+            keyboard_layout = inner.keyboard_layout;
             update_callback = inner.update_callback.clone().expect("update_callback");
             update_callback_context = inner.update_callback_context.clone().expect("update_callback_context");
+            pointer_callback = inner.pointer_callback.clone();
         }
 
         info!("Connect to RDP host");
 
-        let config = build_config(username, password, server_domain, desktop_size);
+        let config = build_config(username, password, server_domain, desktop_size, keyboard_layout);
 
         let ws = WebSocketCompat::new(WebSocket::open(&proxy_address).context("Couldn’t open WebSocket")?);
 
@@ -166,11 +190,18 @@ impl SessionBuilder {
 
         spawn_local(writer_task(writer_rx, rdp_writer));
 
+        let mut input_database = ironrdp::input::Database::new();
+
+        if let Some(server_input_flags) = connection_result.connection_activation.server_input_flags {
+            input_database.set_server_input_flags(server_input_flags);
+        }
+
         Ok(Session {
             connection_result,
             update_callback,
             update_callback_context,
-            input_database: RefCell::new(ironrdp::input::Database::new()),
+            pointer_callback,
+            input_database: RefCell::new(input_database),
             rdp_reader: RefCell::new(Some(rdp_reader)),
             writer_tx,
         })
@@ -182,6 +213,7 @@ pub struct Session {
     connection_result: connector::ConnectionResult,
     update_callback: js_sys::Function,
     update_callback_context: JsValue,
+    pointer_callback: Option<js_sys::Function>,
     input_database: RefCell<ironrdp::input::Database>,
     rdp_reader: RefCell<Option<ReadHalf<WebSocketCompat>>>,
     writer_tx: mpsc::UnboundedSender<Vec<u8>>,
@@ -206,7 +238,11 @@ impl Session {
             self.connection_result.desktop_size.height,
         );
 
-        let mut active_stage = ActiveStage::new(self.connection_result.clone(), None);
+        if let Some(monitor_layout) = &self.connection_result.connection_activation.monitor_layout {
+            image.set_monitor_layout(monitor_layout.clone());
+        }
+
+        let mut active_stage = ActiveStage::new(self.connection_result.clone(), None, Vec::new(), Vec::new());
         let mut frame_id = 0;
 
         'outer: loop {
@@ -240,6 +276,28 @@ impl Session {
 
                         frame_id += 1;
                     }
+                    ActiveStageOutput::PointerDefault => {
+                        self.send_pointer_update(PointerInfo::new(PointerKind::Default), &[])?;
+                    }
+                    ActiveStageOutput::PointerHidden => {
+                        self.send_pointer_update(PointerInfo::new(PointerKind::Hidden), &[])?;
+                    }
+                    ActiveStageOutput::PointerPosition { x, y } => {
+                        self.send_pointer_update(PointerInfo::position(x, y), &[])?;
+                    }
+                    ActiveStageOutput::PointerBitmap(pointer) => {
+                        self.send_pointer_update(PointerInfo::bitmap(&pointer), &pointer.bitmap_data)?;
+                    }
+                    ActiveStageOutput::Reactivated(desktop_size) => {
+                        info!(desktop_size.width, desktop_size.height, "Session reactivated");
+                    }
+                    ActiveStageOutput::MonitorLayout(_) => {
+                        // Only a single monitor is advertised by the web client
+                    }
+                    ActiveStageOutput::ServerRedirection(_) => {
+                        // TODO: reconnect to the target of the redirection through the RDCleanPath proxy
+                        return Err(anyhow::Error::msg("server redirection is not supported").into());
+                    }
                     ActiveStageOutput::Terminate => break 'outer,
                 }
             }
@@ -260,6 +318,14 @@ impl Session {
         }
     }
 
+    /// Whether relative mouse events are supported by the server, e.g.: to decide to lock the pointer
+    pub fn supports_relative_mouse(&self) -> bool {
+        self.input_database
+            .borrow()
+            .server_input_flags()
+            .contains(ironrdp::pdu::rdp::capability_sets::InputFlags::MOUSE_RELATIVE)
+    }
+
     pub fn apply_inputs(&self, transaction: InputTransaction) -> Result<(), IronRdpError> {
         let inputs = self.input_database.borrow_mut().apply(transaction);
         self.h_send_inputs(inputs)
@@ -323,17 +389,42 @@ impl Session {
     }
 }
 
+impl Session {
+    fn send_pointer_update(&self, info: PointerInfo, rgba: &[u8]) -> anyhow::Result<()> {
+        use js_sys::Uint8ClampedArray;
+
+        let Some(pointer_callback) = &self.pointer_callback else {
+            return Ok(());
+        };
+
+        let js_array = Uint8ClampedArray::new_with_length(rgba.len() as u32);
+        js_array.copy_from(rgba);
+
+        let _ret = pointer_callback
+            .call2(
+                &self.update_callback_context,
+                &JsValue::from(info),
+                &JsValue::from(js_array),
+            )
+            .map_err(|e| anyhow::Error::msg(format!("pointer callback failed: {e:?}")))?;
+
+        Ok(())
+    }
+}
+
 fn build_config(
     username: String,
     password: String,
     domain: Option<String>,
     desktop_size: DesktopSize,
+    keyboard_layout: u32,
 ) -> connector::Config {
     connector::Config {
         username,
         password,
         domain,
         security_protocol: ironrdp::pdu::nego::SecurityProtocol::HYBRID_EX,
+        keyboard_layout,
         keyboard_type: ironrdp::pdu::gcc::KeyboardType::IbmEnhanced,
         keyboard_subtype: 0,
         keyboard_functional_keys_count: 12,
@@ -356,6 +447,14 @@ fn build_config(
             .to_string_lossy()
             .into_owned(),
         platform: ironrdp::pdu::rdp::capability_sets::MajorPlatformType::Unspecified,
+        enable_audio_playback: false,
+        pointer_cache_size: 32,
+        compression_type: None,
+        timezone: None,
+        performance_flags: ironrdp::pdu::rdp::client_info::PerformanceFlags::empty(),
+        standard_rdp_security: false,
+        static_channels: Vec::new(),
+        monitors: Vec::new(),
     }
 }
 
@@ -433,7 +532,13 @@ async fn connect(
 
     let upgraded = connect_rdcleanpath(&mut framed, &mut connector, destination, proxy_auth_token, pcb).await?;
 
-    let connection_result = ironrdp_futures::connect_finalize(upgraded, &mut framed, connector).await?;
+    let connection_result = match ironrdp_futures::connect_finalize(upgraded, &mut framed, connector).await? {
+        connector::ConnectionOutcome::Connected(result) => result,
+        connector::ConnectionOutcome::Redirected(_) => {
+            // TODO: reconnect to the target of the redirection through the RDCleanPath proxy
+            return Err(anyhow::Error::msg("server redirection is not supported").into());
+        }
+    };
 
     let ws = framed.into_inner_no_leftover();
 
@@ -1,5 +1,5 @@
 import {BehaviorSubject, from, Observable, of, Subject} from 'rxjs';
-import init, {DeviceEvent, InputTransaction, ironrdp_init, IronRdpError, Session, SessionBuilder} from '../../../../crates/web/pkg/ironrdp_web';
+import init, {DeviceEvent, InputTransaction, ironrdp_init, IronRdpError, PointerInfo, PointerKind, Session, SessionBuilder} from '../../../../crates/web/pkg/ironrdp_web';
 import {loggingService} from './logging.service';
 import {catchError, filter, map} from 'rxjs/operators';
 import {scanCode} from '../lib/scancodes';
@@ -92,6 +92,7 @@ export class WasmBridgeService {
         sessionBuilder.username(username);
         sessionBuilder.update_callback_context(this);
         sessionBuilder.update_callback(this.updateImageCallback);
+        sessionBuilder.pointer_callback(this.updatePointerCallback);
 
         return from(sessionBuilder.connect()).pipe(
             catchError((err: IronRdpError) => {
@@ -207,6 +208,33 @@ export class WasmBridgeService {
         });
     }
 
+    private updatePointerCallback(info: PointerInfo, rgba: Uint8ClampedArray) {
+        if (!this.canvas) {
+            return;
+        }
+
+        switch (info.kind) {
+            case PointerKind.Default:
+                this.canvas.style.cursor = 'default';
+                break;
+            case PointerKind.Hidden:
+                this.canvas.style.cursor = 'none';
+                break;
+            case PointerKind.Bitmap: {
+                const pointerCanvas = document.createElement('canvas');
+                pointerCanvas.width = info.width;
+                pointerCanvas.height = info.height;
+                pointerCanvas.getContext('2d').putImageData(new ImageData(rgba, info.width, info.height), 0, 0);
+
+                this.canvas.style.cursor = `url(${pointerCanvas.toDataURL()}) ${info.hotspot_x} ${info.hotspot_y}, default`;
+                break;
+            }
+            case PointerKind.Position:
+                // Browsers do not allow moving the pointer
+                break;
+        }
+    }
+
     private syncModifier(evt: any): void {
         const mouseEvent = evt as MouseEvent;
 